@@ -108,7 +108,7 @@ mod tests {
     use quickwit_cli::split::{DescribeSplitArgs, SplitCliCommand};
     use quickwit_cli::tool::{
         ExtractSplitArgs, GarbageCollectIndexArgs, LocalIngestDocsArgs, LocalSearchArgs, MergeArgs,
-        ToolCliCommand,
+        ToolCliCommand, WalArgs, WalCommand,
     };
     use quickwit_cli::ClientArgs;
     use quickwit_common::uri::Uri;
@@ -740,6 +740,82 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_wal_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches =
+            app.try_get_matches_from(["tool", "wal", "list", "--config", "/config.yaml"])?;
+        let command = CliCommand::parse_cli_args(matches)?;
+        let expected_config_uri = Uri::from_str("file:///config.yaml").unwrap();
+        assert_eq!(
+            command,
+            CliCommand::Tool(ToolCliCommand::Wal(WalArgs {
+                config_uri: expected_config_uri.clone(),
+                wal_dir_opt: None,
+                command: WalCommand::List,
+                assume_yes: false,
+            }))
+        );
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "tool",
+            "wal",
+            "dump",
+            "--queue",
+            "test-queue",
+            "--from",
+            "10",
+            "--wal-dir",
+            "/qwdata/wal",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(matches)?;
+        assert_eq!(
+            command,
+            CliCommand::Tool(ToolCliCommand::Wal(WalArgs {
+                config_uri: expected_config_uri.clone(),
+                wal_dir_opt: Some(PathBuf::from("/qwdata/wal")),
+                command: WalCommand::Dump {
+                    queue_id: "test-queue".to_string(),
+                    from_position: 10,
+                    to_position_opt: None,
+                    max_records: 100,
+                },
+                assume_yes: false,
+            }))
+        );
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "tool",
+            "wal",
+            "truncate",
+            "--queue",
+            "test-queue",
+            "--up-to",
+            "42",
+            "--config",
+            "/config.yaml",
+            "--yes",
+        ])?;
+        let command = CliCommand::parse_cli_args(matches)?;
+        assert_eq!(
+            command,
+            CliCommand::Tool(ToolCliCommand::Wal(WalArgs {
+                config_uri: expected_config_uri,
+                wal_dir_opt: None,
+                command: WalCommand::Truncate {
+                    queue_id: "test-queue".to_string(),
+                    truncate_up_to_position: 42,
+                },
+                assume_yes: true,
+            }))
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_parse_no_color() {
         let previous_no_color_res = std::env::var("NO_COLOR");
@@ -47,24 +47,25 @@ use quickwit_indexing::models::{
     DetachIndexingPipeline, DetachMergePipeline, IndexingStatistics, SpawnPipeline,
 };
 use quickwit_indexing::IndexingPipeline;
-use quickwit_ingest::IngesterPool;
+use quickwit_ingest::{IngesterPool, MRecord, WalInspector};
 use quickwit_metastore::IndexMetadataResponseExt;
 use quickwit_proto::indexing::CpuCapacity;
 use quickwit_proto::metastore::{IndexMetadataRequest, MetastoreService, MetastoreServiceClient};
-use quickwit_proto::search::{CountHits, SearchResponse};
+use quickwit_proto::search::{CountHits, SearchPriority, SearchResponse};
 use quickwit_proto::types::{NodeId, PipelineUid};
 use quickwit_search::{single_node_search, SearchResponseRest};
 use quickwit_serve::{
     search_request_from_api_request, BodyFormat, SearchRequestQueryString, SortBy,
 };
 use quickwit_storage::{BundleStorage, Storage};
+use tabled::Tabled;
 use thousands::Separable;
 use tracing::{debug, info};
 
 use crate::checklist::{GREEN_COLOR, RED_COLOR};
 use crate::{
-    config_cli_arg, get_resolvers, load_node_config, run_index_checklist, start_actor_runtimes,
-    THROUGHPUT_WINDOW_SIZE,
+    config_cli_arg, get_resolvers, load_node_config, make_table, prompt_confirmation,
+    run_index_checklist, start_actor_runtimes, THROUGHPUT_WINDOW_SIZE,
 };
 
 pub fn build_tool_command() -> Command {
@@ -168,6 +169,55 @@ pub fn build_tool_command() -> Command {
                         .required(true),
                 ])
             )
+        .subcommand(
+            Command::new("wal")
+                .display_order(10)
+                .about("Inspects and repairs the WAL of an ingester.")
+                .long_about("Inspects and repairs the write-ahead log (WAL) of an ingester. The ingester must be stopped beforehand.")
+                .arg(
+                    arg!(--"wal-dir" <WAL_DIR> "Location of the WAL directory. Defaults to the `wal` directory located in the data directory of the node.")
+                        .global(true)
+                        .required(false),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("Lists the shards stored in the WAL along with their positions.")
+                )
+                .subcommand(
+                    Command::new("dump")
+                        .about("Displays the records of a shard.")
+                        .args(&[
+                            arg!(--queue <QUEUE_ID> "ID of the shard queue, as displayed by `quickwit tool wal list`.")
+                                .display_order(1)
+                                .required(true),
+                            arg!(--from <POSITION> "Position of the first record to display.")
+                                .default_value("0")
+                                .required(false),
+                            arg!(--to <POSITION> "Position of the last record to display.")
+                                .required(false),
+                            arg!(--"max-records" <MAX_RECORDS> "Maximum number of records to display.")
+                                .default_value("100")
+                                .required(false),
+                        ])
+                )
+                .subcommand(
+                    Command::new("truncate")
+                        .about("Deletes the records of a shard up to a given position, inclusive.")
+                        .args(&[
+                            arg!(--queue <QUEUE_ID> "ID of the shard queue, as displayed by `quickwit tool wal list`.")
+                                .display_order(1)
+                                .required(true),
+                            arg!(--"up-to" <POSITION> "Position of the last record to delete.")
+                                .display_order(2)
+                                .required(true),
+                        ])
+                )
+                .subcommand(
+                    Command::new("verify")
+                        .about("Verifies the checksums of the WAL, decodes all its records, and reports the records lost in corrupted blocks.")
+                )
+                .arg_required_else_help(true)
+            )
         .arg_required_else_help(true)
 }
 
@@ -220,6 +270,30 @@ pub struct ExtractSplitArgs {
     pub target_dir: PathBuf,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub enum WalCommand {
+    List,
+    Dump {
+        queue_id: String,
+        from_position: u64,
+        to_position_opt: Option<u64>,
+        max_records: usize,
+    },
+    Truncate {
+        queue_id: String,
+        truncate_up_to_position: u64,
+    },
+    Verify,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct WalArgs {
+    pub config_uri: Uri,
+    pub wal_dir_opt: Option<PathBuf>,
+    pub command: WalCommand,
+    pub assume_yes: bool,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum ToolCliCommand {
     GarbageCollect(GarbageCollectIndexArgs),
@@ -227,6 +301,7 @@ pub enum ToolCliCommand {
     LocalSearch(LocalSearchArgs),
     Merge(MergeArgs),
     ExtractSplit(ExtractSplitArgs),
+    Wal(WalArgs),
 }
 
 impl ToolCliCommand {
@@ -240,6 +315,7 @@ impl ToolCliCommand {
             "local-search" => Self::parse_local_search_args(submatches),
             "merge" => Self::parse_merge_args(submatches),
             "extract-split" => Self::parse_extract_split_args(submatches),
+            "wal" => Self::parse_wal_args(submatches),
             _ => bail!("unknown tool subcommand `{subcommand}`"),
         }
     }
@@ -392,6 +468,65 @@ impl ToolCliCommand {
         }))
     }
 
+    fn parse_wal_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .remove_one::<String>("config")
+            .map(|uri_str| Uri::from_str(&uri_str))
+            .expect("`config` should be a required arg.")?;
+        let wal_dir_opt = matches.remove_one::<String>("wal-dir").map(PathBuf::from);
+        let assume_yes = matches.get_flag("yes");
+        let (subcommand, mut submatches) = matches
+            .remove_subcommand()
+            .context("failed to parse wal subcommand")?;
+        let command = match subcommand.as_str() {
+            "list" => WalCommand::List,
+            "dump" => {
+                let queue_id = submatches
+                    .remove_one::<String>("queue")
+                    .expect("`queue` should be a required arg.");
+                let from_position = submatches
+                    .remove_one::<String>("from")
+                    .expect("`from` should have a default value.")
+                    .parse()?;
+                let to_position_opt = submatches
+                    .remove_one::<String>("to")
+                    .map(|position| position.parse())
+                    .transpose()?;
+                let max_records = submatches
+                    .remove_one::<String>("max-records")
+                    .expect("`max-records` should have a default value.")
+                    .parse()?;
+                WalCommand::Dump {
+                    queue_id,
+                    from_position,
+                    to_position_opt,
+                    max_records,
+                }
+            }
+            "truncate" => {
+                let queue_id = submatches
+                    .remove_one::<String>("queue")
+                    .expect("`queue` should be a required arg.");
+                let truncate_up_to_position = submatches
+                    .remove_one::<String>("up-to")
+                    .expect("`up-to` should be a required arg.")
+                    .parse()?;
+                WalCommand::Truncate {
+                    queue_id,
+                    truncate_up_to_position,
+                }
+            }
+            "verify" => WalCommand::Verify,
+            _ => bail!("unknown wal subcommand `{subcommand}`"),
+        };
+        Ok(Self::Wal(WalArgs {
+            config_uri,
+            wal_dir_opt,
+            command,
+            assume_yes,
+        }))
+    }
+
     pub async fn execute(self) -> anyhow::Result<()> {
         match self {
             Self::GarbageCollect(args) => garbage_collect_index_cli(args).await,
@@ -399,6 +534,7 @@ impl ToolCliCommand {
             Self::LocalSearch(args) => local_search_cli(args).await,
             Self::Merge(args) => merge_cli(args).await,
             Self::ExtractSplit(args) => extract_split_cli(args).await,
+            Self::Wal(args) => wal_cli(args).await,
         }
     }
 }
@@ -554,6 +690,10 @@ pub async fn local_search_cli(args: LocalSearchArgs) -> anyhow::Result<()> {
         format: BodyFormat::Json,
         sort_by,
         count_all: CountHits::CountAll,
+        priority: SearchPriority::Interactive,
+        profile: false,
+        dry_run: false,
+        runtime_mappings: Default::default(),
     };
     let search_request =
         search_request_from_api_request(vec![args.index_id], search_request_query_string)?;
@@ -749,6 +889,130 @@ async fn extract_split_cli(args: ExtractSplitArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn wal_cli(args: WalArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "wal");
+
+    let wal_dir_path = if let Some(wal_dir_path) = args.wal_dir_opt {
+        wal_dir_path
+    } else {
+        let config = load_node_config(&args.config_uri).await?;
+        config.data_dir_path.join("wal")
+    };
+    let mut wal_inspector = WalInspector::open(&wal_dir_path).await?;
+
+    match args.command {
+        WalCommand::List => {
+            let rows = wal_inspector
+                .list_shards()
+                .into_iter()
+                .map(|shard_summary| {
+                    let (first_position, last_position) = match shard_summary.position_range_opt {
+                        Some(position_range) => (
+                            position_range.start().to_string(),
+                            position_range.end().to_string(),
+                        ),
+                        None => ("-".to_string(), "-".to_string()),
+                    };
+                    WalShardRow {
+                        queue_id: shard_summary.queue_id,
+                        first_position,
+                        last_position,
+                        num_records: shard_summary.num_records,
+                    }
+                });
+            println!("{}", make_table("Shards", rows, false));
+        }
+        WalCommand::Dump {
+            queue_id,
+            from_position,
+            to_position_opt,
+            max_records,
+        } => {
+            let records = wal_inspector.dump_records(
+                &queue_id,
+                from_position,
+                to_position_opt,
+                max_records,
+            )?;
+
+            for record in records {
+                let record_str = match record.mrecord_opt {
+                    Some(MRecord::Doc(doc)) => format!("doc {}", String::from_utf8_lossy(&doc)),
+                    Some(MRecord::Commit) => "commit".to_string(),
+                    Some(MRecord::DedupId {
+                        dedup_id,
+                        timestamp,
+                    }) => format!("dedup-id {dedup_id} (timestamp: {timestamp})"),
+                    Some(MRecord::CompressedDocs(payload)) => {
+                        format!("compressed-docs ({} bytes)", payload.len())
+                    }
+                    None => "corrupted".color(RED_COLOR).to_string(),
+                };
+                println!("{} {record_str}", record.position);
+            }
+        }
+        WalCommand::Truncate {
+            queue_id,
+            truncate_up_to_position,
+        } => {
+            if !args.assume_yes {
+                let prompt = format!(
+                    "This operation will delete the records of the shard `{queue_id}` up to                      position {truncate_up_to_position}. Do you want to proceed?"
+                );
+                if !prompt_confirmation(&prompt, false) {
+                    return Ok(());
+                }
+            }
+            wal_inspector
+                .truncate_shard(&queue_id, truncate_up_to_position)
+                .await?;
+            println!("{} Shard successfully truncated.", "✔".color(GREEN_COLOR));
+        }
+        WalCommand::Verify => {
+            let report = wal_inspector.verify();
+
+            for (queue_id, position, reason) in &report.corrupted_records {
+                println!("{} {queue_id} {position}: {reason}", "✖".color(RED_COLOR));
+            }
+            for (queue_id, positions) in &report.missing_positions {
+                println!(
+                    "{} {queue_id} {}..={}: missing records",
+                    "✖".color(RED_COLOR),
+                    positions.start(),
+                    positions.end()
+                );
+            }
+            if !report.corrupted_records.is_empty() || !report.missing_positions.is_empty() {
+                bail!(
+                    "found {} corrupted record(s) out of {} and {} range(s) of missing records",
+                    report.corrupted_records.len(),
+                    report.num_records,
+                    report.missing_positions.len()
+                );
+            }
+            println!(
+                "{} Verified {} record(s) in {} shard(s).",
+                "✔".color(GREEN_COLOR),
+                report.num_records,
+                report.num_shards
+            );
+        }
+    }
+    Ok(())
+}
+
+#[derive(Tabled)]
+struct WalShardRow {
+    #[tabled(rename = "Queue ID")]
+    queue_id: String,
+    #[tabled(rename = "First Position")]
+    first_position: String,
+    #[tabled(rename = "Last Position")]
+    last_position: String,
+    #[tabled(rename = "Num Records")]
+    num_records: usize,
+}
+
 /// Starts a tokio task that displays the indexing statistics
 /// every once in awhile.
 pub async fn start_statistics_reporting_loop(
@@ -941,6 +1205,7 @@ async fn create_empty_cluster(config: &NodeConfig) -> anyhow::Result<Cluster> {
         grpc_advertise_addr: config.grpc_advertise_addr,
         indexing_cpu_capacity: CpuCapacity::zero(),
         indexing_tasks: Vec::new(),
+        availability_zone: None,
     };
     let cluster = Cluster::join(
         config.cluster_id.clone(),
@@ -146,6 +146,7 @@ pub async fn start_cluster_service(node_config: &NodeConfig) -> anyhow::Result<C
         grpc_advertise_addr: node_config.grpc_advertise_addr,
         indexing_tasks,
         indexing_cpu_capacity,
+        availability_zone: node_config.availability_zone.clone(),
     };
     let cluster = Cluster::join(
         cluster_id,
@@ -34,6 +34,7 @@ use crate::{GenerationId, QuickwitService};
 // Keys used to store member's data in chitchat state.
 pub(crate) const GRPC_ADVERTISE_ADDR_KEY: &str = "grpc_advertise_addr";
 pub(crate) const ENABLED_SERVICES_KEY: &str = "enabled_services";
+pub(crate) const AVAILABILITY_ZONE_KEY: &str = "availability_zone";
 pub(crate) const PIPELINE_METRICS_PREFIX: &str = "pipeline_metrics:";
 
 // Readiness key and values used to store node's readiness in Chitchat state.
@@ -104,6 +105,8 @@ pub struct ClusterMember {
     pub indexing_tasks: Vec<IndexingTask>,
     /// Indexing cpu capacity of the node expressed in milli cpu.
     pub indexing_cpu_capacity: CpuCapacity,
+    /// Availability zone (or rack) of the node, if configured.
+    pub availability_zone: Option<String>,
     pub is_ready: bool,
 }
 
@@ -156,6 +159,7 @@ pub(crate) fn build_cluster_member(
     let grpc_advertise_addr = node_state.grpc_advertise_addr()?;
     let indexing_tasks = parse_indexing_tasks(node_state);
     let indexing_cpu_capacity = parse_indexing_cpu_capacity(node_state);
+    let availability_zone = node_state.get(AVAILABILITY_ZONE_KEY).map(str::to_string);
     let member = ClusterMember {
         node_id: chitchat_id.node_id.into(),
         generation_id: chitchat_id.generation_id.into(),
@@ -165,6 +169,7 @@ pub(crate) fn build_cluster_member(
         grpc_advertise_addr,
         indexing_tasks,
         indexing_cpu_capacity,
+        availability_zone,
     };
     Ok(member)
 }
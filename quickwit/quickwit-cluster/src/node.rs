@@ -50,6 +50,7 @@ impl ClusterNode {
             grpc_advertise_addr: member.grpc_advertise_addr,
             indexing_tasks: member.indexing_tasks,
             indexing_capacity: member.indexing_cpu_capacity,
+            availability_zone: member.availability_zone,
             is_ready: member.is_ready,
             is_self_node,
         };
@@ -129,6 +130,10 @@ impl ClusterNode {
         self.inner.indexing_capacity
     }
 
+    pub fn availability_zone(&self) -> Option<&str> {
+        self.inner.availability_zone.as_deref()
+    }
+
     pub fn is_ready(&self) -> bool {
         self.inner.is_ready
     }
@@ -155,6 +160,7 @@ impl PartialEq for ClusterNode {
             && self.inner.enabled_services == other.inner.enabled_services
             && self.inner.grpc_advertise_addr == other.inner.grpc_advertise_addr
             && self.inner.indexing_tasks == other.inner.indexing_tasks
+            && self.inner.availability_zone == other.inner.availability_zone
             && self.inner.is_ready == other.inner.is_ready
             && self.inner.is_self_node == other.inner.is_self_node
     }
@@ -167,6 +173,7 @@ struct InnerNode {
     grpc_advertise_addr: SocketAddr,
     indexing_tasks: Vec<IndexingTask>,
     indexing_capacity: CpuCapacity,
+    availability_zone: Option<String>,
     is_ready: bool,
     is_self_node: bool,
 }
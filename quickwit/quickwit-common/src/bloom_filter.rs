@@ -0,0 +1,257 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+use std::io;
+
+use siphasher::sip128::{Hasher128, SipHasher};
+
+const MIN_NUM_BITS: usize = 64;
+
+const MAX_NUM_HASHES: u32 = 16;
+
+/// Version of the serialization format of the bloom filters of a split.
+const BLOOM_FILTERS_FORMAT_VERSION: u8 = 1;
+
+/// A bloom filter over byte strings.
+///
+/// The filter is sized from the number of items it is expected to hold and the target false
+/// positive rate. Items are hashed with a 128-bit SipHash with fixed keys, so a filter can be
+/// persisted and queried by another process.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BloomFilter {
+    num_hashes: u32,
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Creates an empty bloom filter sized to hold `num_items` items with a false positive rate
+    /// of `false_positive_rate`.
+    pub fn with_capacity(num_items: usize, false_positive_rate: f64) -> BloomFilter {
+        let num_items = num_items.max(1) as f64;
+        let false_positive_rate = false_positive_rate.clamp(f64::EPSILON, 0.5);
+        let ln_2 = std::f64::consts::LN_2;
+        let optimal_num_bits = (-num_items * false_positive_rate.ln() / (ln_2 * ln_2)).ceil();
+        let num_bits = (optimal_num_bits as usize).max(MIN_NUM_BITS);
+        let num_words = num_bits.div_ceil(64);
+        let num_hashes = ((num_words * 64) as f64 / num_items * ln_2).round() as u32;
+        BloomFilter {
+            num_hashes: num_hashes.clamp(1, MAX_NUM_HASHES),
+            bits: vec![0; num_words],
+        }
+    }
+
+    /// Returns the size of the filter in bytes.
+    pub fn num_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// Adds an item to the filter.
+    pub fn insert(&mut self, item: &[u8]) {
+        for bit in self.bit_positions(item) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Returns `false` if the item was never inserted in the filter. A `true` answer may be a
+    /// false positive.
+    pub fn contains(&self, item: &[u8]) -> bool {
+        self.bit_positions(item)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Enhanced double hashing: the positions are derived from the two halves of a single
+    /// 128-bit hash.
+    fn bit_positions(&self, item: &[u8]) -> impl Iterator<Item = usize> {
+        let mut hasher = SipHasher::new();
+        hasher.write(item);
+        let hash = hasher.finish128();
+        let num_bits = (self.bits.len() * 64) as u64;
+        let mut position = hash.h1;
+        let mut delta = hash.h2;
+
+        (0..self.num_hashes).map(move |i| {
+            let bit = (position % num_bits) as usize;
+            position = position.wrapping_add(delta);
+            delta = delta.wrapping_add(i as u64);
+            bit
+        })
+    }
+
+    fn serialize(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.num_hashes.to_le_bytes());
+        output.extend_from_slice(&(self.bits.len() as u64).to_le_bytes());
+
+        for word in &self.bits {
+            output.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    fn deserialize(bytes: &mut &[u8]) -> io::Result<BloomFilter> {
+        let num_hashes = u32::from_le_bytes(read_array(bytes)?);
+        if num_hashes == 0 || num_hashes > MAX_NUM_HASHES {
+            return Err(invalid_data(format!(
+                "invalid number of bloom filter hashes: {num_hashes}"
+            )));
+        }
+        let num_words = u64::from_le_bytes(read_array(bytes)?) as usize;
+        if num_words == 0 || num_words > bytes.len() / 8 {
+            return Err(invalid_data(format!(
+                "invalid bloom filter size: {num_words} words"
+            )));
+        }
+        let bits = (0..num_words)
+            .map(|_| read_array(bytes).map(u64::from_le_bytes))
+            .collect::<io::Result<Vec<u64>>>()?;
+        Ok(BloomFilter { num_hashes, bits })
+    }
+}
+
+/// Serializes the bloom filters of the fields of a split, keyed by field name.
+pub fn serialize_bloom_filters(bloom_filters: &BTreeMap<String, BloomFilter>) -> Vec<u8> {
+    let num_bytes = bloom_filters
+        .iter()
+        .map(|(field_name, bloom_filter)| field_name.len() + bloom_filter.num_bytes() + 16)
+        .sum::<usize>();
+    let mut output = Vec::with_capacity(num_bytes + 5);
+    output.push(BLOOM_FILTERS_FORMAT_VERSION);
+    output.extend_from_slice(&(bloom_filters.len() as u32).to_le_bytes());
+
+    for (field_name, bloom_filter) in bloom_filters {
+        output.extend_from_slice(&(field_name.len() as u32).to_le_bytes());
+        output.extend_from_slice(field_name.as_bytes());
+        bloom_filter.serialize(&mut output);
+    }
+    output
+}
+
+/// Deserializes the bloom filters serialized with [`serialize_bloom_filters`].
+pub fn deserialize_bloom_filters(mut bytes: &[u8]) -> io::Result<BTreeMap<String, BloomFilter>> {
+    let [version] = read_array(&mut bytes)?;
+    if version != BLOOM_FILTERS_FORMAT_VERSION {
+        return Err(invalid_data(format!(
+            "unsupported bloom filters format version: {version}"
+        )));
+    }
+    let num_bloom_filters = u32::from_le_bytes(read_array(&mut bytes)?);
+    let mut bloom_filters = BTreeMap::new();
+
+    for _ in 0..num_bloom_filters {
+        let field_name_len = u32::from_le_bytes(read_array(&mut bytes)?) as usize;
+        if field_name_len > bytes.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let (field_name_bytes, remaining_bytes) = bytes.split_at(field_name_len);
+        let field_name = String::from_utf8(field_name_bytes.to_vec())
+            .map_err(|error| invalid_data(error.to_string()))?;
+        bytes = remaining_bytes;
+        let bloom_filter = BloomFilter::deserialize(&mut bytes)?;
+        bloom_filters.insert(field_name, bloom_filter);
+    }
+    if !bytes.is_empty() {
+        return Err(invalid_data(format!(
+            "{} trailing bytes after the bloom filters",
+            bytes.len()
+        )));
+    }
+    Ok(bloom_filters)
+}
+
+fn read_array<const N: usize>(bytes: &mut &[u8]) -> io::Result<[u8; N]> {
+    if bytes.len() < N {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (head, tail) = bytes.split_at(N);
+    *bytes = tail;
+    Ok(head.try_into().unwrap())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter_no_false_negatives() {
+        let mut bloom_filter = BloomFilter::with_capacity(1_000, 0.01);
+
+        for i in 0..1_000 {
+            bloom_filter.insert(format!("item-{i}").as_bytes());
+        }
+        for i in 0..1_000 {
+            assert!(bloom_filter.contains(format!("item-{i}").as_bytes()));
+        }
+        let num_false_positives = (0..10_000)
+            .filter(|i| bloom_filter.contains(format!("other-item-{i}").as_bytes()))
+            .count();
+        assert!(num_false_positives < 300, "{num_false_positives}");
+    }
+
+    #[test]
+    fn test_bloom_filter_is_sized_to_the_number_of_items() {
+        let num_items = 100_000;
+        let mut bloom_filter = BloomFilter::with_capacity(num_items, 0.01);
+        // About 9.6 bits per item for a 1% false positive rate.
+        assert_eq!(bloom_filter.num_bytes(), 119_816);
+
+        for i in 0..num_items {
+            bloom_filter.insert(format!("item-{i}").as_bytes());
+        }
+        let num_false_positives = (0..10_000)
+            .filter(|i| bloom_filter.contains(format!("other-item-{i}").as_bytes()))
+            .count();
+        assert!(num_false_positives < 300, "{num_false_positives}");
+
+        let bloom_filter = BloomFilter::with_capacity(0, 0.01);
+        assert_eq!(bloom_filter.num_bytes(), 8);
+        assert!(!bloom_filter.contains(b"item"));
+    }
+
+    #[test]
+    fn test_bloom_filters_serialization() {
+        let mut trace_id_bloom_filter = BloomFilter::with_capacity(100, 0.01);
+        trace_id_bloom_filter.insert(b"foo");
+        trace_id_bloom_filter.insert(b"bar");
+        let mut span_id_bloom_filter = BloomFilter::with_capacity(1, 0.01);
+        span_id_bloom_filter.insert(&[0xab, 0xcd]);
+
+        let bloom_filters = BTreeMap::from([
+            ("trace_id".to_string(), trace_id_bloom_filter),
+            ("span_id".to_string(), span_id_bloom_filter),
+        ]);
+        let serialized = serialize_bloom_filters(&bloom_filters);
+        let deserialized = deserialize_bloom_filters(&serialized).unwrap();
+        assert_eq!(deserialized, bloom_filters);
+        assert!(deserialized["trace_id"].contains(b"foo"));
+        assert!(deserialized["trace_id"].contains(b"bar"));
+        assert!(deserialized["span_id"].contains(&[0xab, 0xcd]));
+
+        let serialized = serialize_bloom_filters(&BTreeMap::new());
+        assert!(deserialize_bloom_filters(&serialized).unwrap().is_empty());
+
+        let serialized = serialize_bloom_filters(&bloom_filters);
+        deserialize_bloom_filters(&serialized[..serialized.len() - 1]).unwrap_err();
+        deserialize_bloom_filters(&[]).unwrap_err();
+        deserialize_bloom_filters(&[2, 0, 0, 0, 0]).unwrap_err();
+    }
+}
@@ -39,5 +39,12 @@ pub const SCROLL_BATCH_LEN: usize = 1_000;
 /// Prefix used in chitchat to broadcast the list of primary shards hosted by a leader.
 pub const INGESTER_PRIMARY_SHARDS_PREFIX: &str = "ingester.primary_shards:";
 
+/// Prefix used in chitchat to broadcast the volume of data ingested daily by a leader for each
+/// index.
+pub const INGESTER_DAILY_INGEST_VOLUME_PREFIX: &str = "ingester.daily_ingest_volume:";
+
 /// File name for the encoded list of fields in the split
 pub const SPLIT_FIELDS_FILE_NAME: &str = "split_fields";
+
+/// File name for the bloom filters of the `bloom_filter_fields` in the split
+pub const BLOOM_FILTERS_FILE_NAME: &str = "bloom_filters";
@@ -19,21 +19,21 @@
 
 pub(crate) mod serialize;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
 use std::num::NonZeroU32;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::{ensure, Context};
+use anyhow::{bail, ensure, Context};
 use bytesize::ByteSize;
 use chrono::Utc;
 use cron::Schedule;
 use humantime::parse_duration;
 use quickwit_common::uri::Uri;
 use quickwit_doc_mapper::{
-    DefaultDocMapper, DefaultDocMapperBuilder, DocMapper, FieldMappingEntry, Mode, ModeType,
-    QuickwitJsonOptions, TokenizerEntry,
+    Cardinality, DefaultDocMapper, DefaultDocMapperBuilder, DocMapper, FieldMappingEntry,
+    FieldMappingType, Mode, ModeType, QuickwitJsonOptions, TokenizerEntry,
 };
 use quickwit_proto::types::IndexId;
 use serde::{Deserialize, Serialize};
@@ -42,7 +42,7 @@ use tracing::warn;
 
 use crate::index_config::serialize::VersionedIndexConfig;
 use crate::merge_policy_config::{MergePolicyConfig, StableLogMergePolicyConfig};
-use crate::TestableForRegression;
+use crate::{validate_identifier, TestableForRegression};
 
 // Note(fmassot): `DocMapping` is a struct only used for
 // serialization/deserialization of `DocMapper` parameters.
@@ -65,6 +65,18 @@ pub struct DocMapping {
     #[schema(value_type = Vec<String>)]
     #[serde(default)]
     pub tag_fields: BTreeSet<String>,
+    /// Fast fields whose min and max values are recorded in the split metadata in order to
+    /// prune splits at search time.
+    #[schema(value_type = Vec<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub min_max_fields: BTreeSet<String>,
+    /// Fields whose terms are recorded in a bloom filter in the split metadata in order to
+    /// prune splits at search time.
+    #[schema(value_type = Vec<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub bloom_filter_fields: BTreeSet<String>,
     #[serde(default)]
     pub store_source: bool,
     #[serde(default)]
@@ -167,6 +179,11 @@ pub struct IndexingSettings {
     pub merge_policy: MergePolicyConfig,
     #[serde(default)]
     pub resources: IndexingResources,
+    /// Sorts the documents of the splits by a fast field when they are merged, so that searches
+    /// sorted by that field can terminate early.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<IndexSortBy>,
 }
 
 impl IndexingSettings {
@@ -208,10 +225,63 @@ impl Default for IndexingSettings {
             split_num_docs_target: Self::default_split_num_docs_target(),
             merge_policy: MergePolicyConfig::default(),
             resources: IndexingResources::default(),
+            sort_by: None,
         }
     }
 }
 
+/// Order in which the documents of a split are sorted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexSortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Fast field the documents of a split are sorted by.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IndexSortBy {
+    /// Name of the field. It must be a single-valued numeric or datetime fast field.
+    pub field: String,
+    #[serde(default)]
+    pub order: IndexSortOrder,
+}
+
+impl IndexSortBy {
+    fn validate(&self, doc_mapping: &DocMapping) -> anyhow::Result<()> {
+        let Some(field_mapping) = doc_mapping
+            .field_mappings
+            .iter()
+            .find(|field_mapping| field_mapping.name == self.field)
+        else {
+            bail!(
+                "sort by field `{}` does not exist in the doc mapping",
+                self.field
+            );
+        };
+        let is_single_valued_fast_field = match &field_mapping.mapping_type {
+            FieldMappingType::I64(options, cardinality)
+            | FieldMappingType::U64(options, cardinality)
+            | FieldMappingType::F64(options, cardinality) => {
+                options.fast && *cardinality == Cardinality::SingleValue
+            }
+            FieldMappingType::DateTime(options, cardinality) => {
+                options.fast && *cardinality == Cardinality::SingleValue
+            }
+            // Tantivy only sorts the documents of a segment by numerical or datetime columns.
+            _ => false,
+        };
+        ensure!(
+            is_single_valued_fast_field,
+            "sort by field `{}` must be a single-valued numeric or datetime fast field",
+            self.field
+        );
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct SearchSettings {
@@ -233,6 +303,12 @@ pub struct RetentionPolicy {
     #[serde(default = "RetentionPolicy::default_schedule")]
     #[serde(rename = "schedule")]
     pub evaluation_schedule: String,
+
+    /// Retention periods applying to the documents holding a given tag, for instance a tenant
+    /// with a shorter contract. The index retention period still applies to all the documents.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RetentionRule>,
 }
 
 impl RetentionPolicy {
@@ -262,23 +338,289 @@ impl RetentionPolicy {
 
     pub fn duration_until_next_evaluation(&self) -> anyhow::Result<Duration> {
         let schedule = self.evaluation_schedule()?;
-        let future_date = schedule
-            .upcoming(Utc)
-            .next()
-            .expect("Failed to obtain next evaluation date.");
-        let duration = (future_date - Utc::now())
-            .to_std()
-            .map_err(|err| anyhow::anyhow!(err.to_string()))?;
-        Ok(duration)
+        duration_until_next_occurrence(&schedule)
     }
 
     pub(super) fn validate(&self) -> anyhow::Result<()> {
         self.retention_period()?;
         self.evaluation_schedule()?;
+
+        let mut tags = HashSet::with_capacity(self.rules.len());
+
+        for rule in &self.rules {
+            rule.retention_period()?;
+            rule.tag_field_and_value()?;
+
+            if !tags.insert(&rule.tag) {
+                bail!(
+                    "retention rule tag `{}` is defined more than once",
+                    rule.tag
+                );
+            }
+        }
         Ok(())
     }
 }
 
+/// Retention period of the documents holding a given tag.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionRule {
+    /// Tag of the form `{field_name}:{field_value}`, e.g. `tenant:free`. The field must be one of
+    /// the tag fields of the doc mapping.
+    pub tag: String,
+
+    /// Duration of time for which the documents holding the tag should be retained, expressed in a
+    /// human-friendly way (`1 hour`, `3 days`, `a week`, ...).
+    #[serde(rename = "period")]
+    pub retention_period: String,
+}
+
+impl RetentionRule {
+    pub fn retention_period(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.retention_period).with_context(|| {
+            format!(
+                "failed to parse retention period `{}` of rule `{}`",
+                self.retention_period, self.tag
+            )
+        })
+    }
+
+    /// Splits the tag of the rule into its field name and value.
+    pub fn tag_field_and_value(&self) -> anyhow::Result<(&str, &str)> {
+        match self.tag.split_once(':') {
+            Some((field_name, field_value)) if !field_name.is_empty() => {
+                Ok((field_name, field_value))
+            }
+            _ => bail!(
+                "retention rule tag `{}` must be of the form `{{field_name}}:{{field_value}}`",
+                self.tag
+            ),
+        }
+    }
+}
+
+/// Moves the splits older than a given age to a cheaper storage tier, for instance another bucket
+/// or a bucket with a colder storage class.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LifecyclePolicy {
+    /// Age after which the splits are moved to the cold storage tier, expressed in a
+    /// human-friendly way (`1 hour`, `30 days`, ...). The age of a split is computed from its
+    /// most recent timestamp.
+    pub cold_after: String,
+
+    /// URI of the cold storage tier, e.g. `s3://my-archive-bucket/indexes/my-index`.
+    #[schema(value_type = String)]
+    pub cold_storage_uri: Uri,
+
+    /// Defines the frequency at which the lifecycle policy is evaluated and applied, expressed in
+    /// a human-friendly way (`hourly`, `daily`, ...) or as a cron expression.
+    #[serde(default = "LifecyclePolicy::default_schedule")]
+    #[serde(rename = "schedule")]
+    pub evaluation_schedule: String,
+}
+
+impl LifecyclePolicy {
+    fn default_schedule() -> String {
+        "daily".to_string()
+    }
+
+    pub fn cold_after(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.cold_after).with_context(|| {
+            format!(
+                "failed to parse lifecycle `cold_after` period `{}`",
+                self.cold_after
+            )
+        })
+    }
+
+    pub fn evaluation_schedule(&self) -> anyhow::Result<Schedule> {
+        let evaluation_schedule = prepend_at_char(&self.evaluation_schedule);
+
+        Schedule::from_str(&evaluation_schedule).with_context(|| {
+            format!(
+                "failed to parse lifecycle evaluation schedule `{}`",
+                self.evaluation_schedule
+            )
+        })
+    }
+
+    pub fn duration_until_next_evaluation(&self) -> anyhow::Result<Duration> {
+        let schedule = self.evaluation_schedule()?;
+        duration_until_next_occurrence(&schedule)
+    }
+
+    pub(super) fn validate(
+        &self,
+        index_uri: &Uri,
+        retention_policy_opt: Option<&RetentionPolicy>,
+    ) -> anyhow::Result<()> {
+        let cold_after = self.cold_after()?;
+        self.evaluation_schedule()?;
+
+        ensure!(
+            &self.cold_storage_uri != index_uri,
+            "lifecycle `cold_storage_uri` must differ from the index URI"
+        );
+        if let Some(retention_policy) = retention_policy_opt {
+            ensure!(
+                cold_after < retention_policy.retention_period()?,
+                "lifecycle `cold_after` period must be shorter than the retention period"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Periodically rolls up the documents of an index into a summary index: an aggregation is
+/// computed over each time window once it is complete, and the resulting buckets are ingested as
+/// documents into the target index.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RollupPolicy {
+    /// ID of the index receiving the rollup documents.
+    pub target_index_id: IndexId,
+
+    /// Duration of the time windows rolled up, expressed in a human-friendly way (`1 hour`,
+    /// `1 day`, ...). Windows are aligned on the Unix epoch.
+    pub window: String,
+
+    /// Delay after the end of a window before it is rolled up, leaving time for late documents
+    /// to be indexed.
+    #[serde(default = "RollupPolicy::default_delay")]
+    pub delay: String,
+
+    /// Query selecting the documents rolled up.
+    #[serde(default = "RollupPolicy::default_query")]
+    pub query: String,
+
+    /// Aggregation computed over each window, in the Elasticsearch aggregation format. Each
+    /// level of the aggregation may hold at most one bucket aggregation (`terms`,
+    /// `date_histogram`, ...) and any number of metric aggregations.
+    #[schema(value_type = Object)]
+    pub aggregation: serde_json::Value,
+
+    /// Defines the frequency at which the rollup policy is evaluated and applied, expressed in a
+    /// human-friendly way (`hourly`, `daily`, ...) or as a cron expression.
+    #[serde(default = "RollupPolicy::default_schedule")]
+    #[serde(rename = "schedule")]
+    pub evaluation_schedule: String,
+}
+
+impl RollupPolicy {
+    fn default_delay() -> String {
+        "10 minutes".to_string()
+    }
+
+    fn default_query() -> String {
+        "*".to_string()
+    }
+
+    fn default_schedule() -> String {
+        "hourly".to_string()
+    }
+
+    pub fn window(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.window)
+            .with_context(|| format!("failed to parse rollup window `{}`", self.window))
+    }
+
+    pub fn delay(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.delay)
+            .with_context(|| format!("failed to parse rollup delay `{}`", self.delay))
+    }
+
+    pub fn evaluation_schedule(&self) -> anyhow::Result<Schedule> {
+        let evaluation_schedule = prepend_at_char(&self.evaluation_schedule);
+
+        Schedule::from_str(&evaluation_schedule).with_context(|| {
+            format!(
+                "failed to parse rollup evaluation schedule `{}`",
+                self.evaluation_schedule
+            )
+        })
+    }
+
+    pub fn duration_until_next_evaluation(&self) -> anyhow::Result<Duration> {
+        let schedule = self.evaluation_schedule()?;
+        duration_until_next_occurrence(&schedule)
+    }
+
+    pub(super) fn validate(&self, index_id: &str) -> anyhow::Result<()> {
+        validate_identifier("rollup target index", &self.target_index_id)?;
+        ensure!(
+            self.target_index_id != index_id,
+            "rollup `target_index_id` must differ from the index ID"
+        );
+        let window = self.window()?;
+        ensure!(
+            window.as_secs() > 0 && window.subsec_nanos() == 0,
+            "rollup window `{}` must be a whole number of seconds",
+            self.window
+        );
+        self.delay()?;
+        self.evaluation_schedule()?;
+
+        match self.aggregation.as_object() {
+            Some(aggregations) if !aggregations.is_empty() => Ok(()),
+            _ => bail!("rollup `aggregation` must be a non-empty JSON object"),
+        }
+    }
+}
+
+/// Limits the volume of data ingested into an index, cluster-wide.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IngestQuota {
+    /// Maximum ingestion throughput of the index per second. The control plane does not open more
+    /// shards than necessary to sustain this throughput.
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_ingest_throughput: Option<ByteSize>,
+
+    /// Maximum volume of data ingested into the index per day (UTC). Once it is reached,
+    /// ingest requests targeting the index are rejected until the end of the day.
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_daily_ingest_volume: Option<ByteSize>,
+}
+
+impl IngestQuota {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.max_ingest_throughput.is_some() || self.max_daily_ingest_volume.is_some(),
+            "ingest quota must define `max_ingest_throughput` or `max_daily_ingest_volume`"
+        );
+        if let Some(max_ingest_throughput) = self.max_ingest_throughput {
+            ensure!(
+                max_ingest_throughput.as_u64() > 0,
+                "ingest quota `max_ingest_throughput` must be strictly positive"
+            );
+        }
+        if let Some(max_daily_ingest_volume) = self.max_daily_ingest_volume {
+            ensure!(
+                max_daily_ingest_volume.as_u64() > 0,
+                "ingest quota `max_daily_ingest_volume` must be strictly positive"
+            );
+        }
+        Ok(())
+    }
+}
+
+fn duration_until_next_occurrence(schedule: &Schedule) -> anyhow::Result<Duration> {
+    let future_date = schedule
+        .upcoming(Utc)
+        .next()
+        .expect("Failed to obtain next evaluation date.");
+    let duration = (future_date - Utc::now())
+        .to_std()
+        .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+    Ok(duration)
+}
+
 /// Prepends an `@` char at the start of the cron expression if necessary:
 /// `hourly` -> `@hourly`
 fn prepend_at_char(schedule: &str) -> String {
@@ -304,6 +646,9 @@ pub struct IndexConfig {
     pub indexing_settings: IndexingSettings,
     pub search_settings: SearchSettings,
     pub retention_policy_opt: Option<RetentionPolicy>,
+    pub lifecycle_policy_opt: Option<LifecyclePolicy>,
+    pub rollup_policy_opt: Option<RollupPolicy>,
+    pub ingest_quota_opt: Option<IngestQuota>,
 }
 
 impl IndexConfig {
@@ -389,6 +734,9 @@ impl IndexConfig {
             indexing_settings,
             search_settings,
             retention_policy_opt: Default::default(),
+            lifecycle_policy_opt: Default::default(),
+            rollup_policy_opt: Default::default(),
+            ingest_quota_opt: Default::default(),
         }
     }
 }
@@ -448,6 +796,8 @@ impl TestableForRegression for IndexConfig {
                 .into_iter()
                 .map(|tag_field| tag_field.to_string())
                 .collect::<BTreeSet<String>>(),
+            min_max_fields: BTreeSet::new(),
+            bloom_filter_fields: BTreeSet::new(),
             store_source: true,
             mode: Mode::default(),
             partition_key: Some("tenant_id".to_string()),
@@ -458,6 +808,7 @@ impl TestableForRegression for IndexConfig {
         let retention_policy = Some(RetentionPolicy {
             retention_period: "90 days".to_string(),
             evaluation_schedule: "daily".to_string(),
+            rules: Vec::new(),
         });
         let stable_log_config = StableLogMergePolicyConfig {
             merge_factor: 9,
@@ -485,6 +836,9 @@ impl TestableForRegression for IndexConfig {
             doc_mapping,
             indexing_settings,
             retention_policy_opt: retention_policy,
+            lifecycle_policy_opt: None,
+            rollup_policy_opt: None,
+            ingest_quota_opt: None,
             search_settings,
         }
     }
@@ -527,6 +881,8 @@ pub fn build_doc_mapper(
         timestamp_field: doc_mapping.timestamp_field.clone(),
         field_mappings: doc_mapping.field_mappings.clone(),
         tag_fields: doc_mapping.tag_fields.iter().cloned().collect(),
+        min_max_fields: doc_mapping.min_max_fields.iter().cloned().collect(),
+        bloom_filter_fields: doc_mapping.bloom_filter_fields.iter().cloned().collect(),
         mode: doc_mapping.mode.clone(),
         partition_key: doc_mapping.partition_key.clone(),
         max_num_partitions: doc_mapping.max_num_partitions,
@@ -551,6 +907,9 @@ pub(super) fn validate_index_config(
     indexing_settings.merge_policy.validate()?;
     indexing_settings.resources.validate()?;
 
+    if let Some(sort_by) = &indexing_settings.sort_by {
+        sort_by.validate(doc_mapping)?;
+    }
     if let Some(retention_policy) = retention_policy_opt {
         retention_policy.validate()?;
 
@@ -558,6 +917,14 @@ pub(super) fn validate_index_config(
             doc_mapping.timestamp_field.is_some(),
             "retention policy requires a timestamp field, but indexing settings do not declare one"
         );
+        for rule in &retention_policy.rules {
+            let (field_name, _) = rule.tag_field_and_value()?;
+            ensure!(
+                doc_mapping.tag_fields.contains(field_name),
+                "retention rule tag `{}` refers to field `{field_name}`, which is not a tag field",
+                rule.tag
+            );
+        }
     }
     Ok(())
 }
@@ -613,6 +980,7 @@ mod tests {
         let expected_retention_policy = RetentionPolicy {
             retention_period: "90 days".to_string(),
             evaluation_schedule: "daily".to_string(),
+            rules: Vec::new(),
         };
         assert_eq!(
             index_config.retention_policy_opt.unwrap(),
@@ -783,11 +1151,170 @@ mod tests {
             .contains("failed to parse human-readable duration `x`"));
     }
 
+    #[test]
+    fn test_index_config_with_sort_by() {
+        let config_yaml = r#"
+            version: 0.7
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping:
+              field_mappings:
+                - name: status_code
+                  type: u64
+                  fast: true
+                - name: body
+                  type: text
+                - name: is_error
+                  type: bool
+                  fast: true
+            indexing_settings:
+              sort_by:
+                field: status_code
+                order: desc
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap();
+        assert_eq!(
+            index_config.indexing_settings.sort_by,
+            Some(IndexSortBy {
+                field: "status_code".to_string(),
+                order: IndexSortOrder::Desc,
+            })
+        );
+        for sort_by_field in ["body", "is_error", "unknown"] {
+            let config_yaml = format!(
+                r#"
+                version: 0.7
+                index_id: hdfs-logs
+                index_uri: "s3://my-index"
+                doc_mapping:
+                  field_mappings:
+                    - name: status_code
+                      type: u64
+                    - name: body
+                      type: text
+                    - name: is_error
+                      type: bool
+                      fast: true
+                indexing_settings:
+                  sort_by:
+                    field: {sort_by_field}
+            "#
+            );
+            load_index_config_from_user_config(
+                ConfigFormat::Yaml,
+                config_yaml.as_bytes(),
+                &Uri::for_test("s3://my-index"),
+            )
+            .unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_index_config_with_field_stats_fields() {
+        let config_yaml = r#"
+            version: 0.7
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping:
+              field_mappings:
+                - name: status_code
+                  type: u64
+                  fast: true
+                - name: trace_id
+                  type: text
+                  tokenizer: raw
+              min_max_fields: [status_code]
+              bloom_filter_fields: [trace_id]
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap();
+        assert_eq!(
+            index_config.doc_mapping.min_max_fields,
+            BTreeSet::from(["status_code".to_string()])
+        );
+        assert_eq!(
+            index_config.doc_mapping.bloom_filter_fields,
+            BTreeSet::from(["trace_id".to_string()])
+        );
+        let config_yaml = r#"
+            version: 0.7
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping:
+              field_mappings:
+                - name: status_code
+                  type: u64
+              min_max_fields: [status_code]
+        "#;
+        load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_index_config_with_time_partitioned_merge_policy() {
+        let config_yaml = r#"
+            version: 0.7
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping: {}
+            indexing_settings:
+              merge_policy:
+                type: time_partitioned
+                window: 1h
+                merge_factor: 5
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap();
+        let MergePolicyConfig::TimePartitioned(merge_policy_config) =
+            index_config.indexing_settings.merge_policy
+        else {
+            panic!("expected a time-partitioned merge policy");
+        };
+        assert_eq!(merge_policy_config.window, Duration::from_secs(3_600));
+        assert_eq!(merge_policy_config.merge_factor, 5);
+        assert_eq!(merge_policy_config.max_merge_factor, 12);
+
+        let config_yaml = r#"
+            version: 0.7
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping: {}
+            indexing_settings:
+              merge_policy:
+                type: time_partitioned
+                window: 0s
+        "#;
+        load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap_err();
+    }
+
     #[test]
     fn test_retention_policy_serialization() {
         let retention_policy = RetentionPolicy {
             retention_period: "90 days".to_string(),
             evaluation_schedule: "hourly".to_string(),
+            rules: Vec::new(),
         };
         let retention_policy_yaml = serde_yaml::to_string(&retention_policy).unwrap();
         assert_eq!(
@@ -808,6 +1335,7 @@ mod tests {
             let expected_retention_policy = RetentionPolicy {
                 retention_period: "90 days".to_string(),
                 evaluation_schedule: "hourly".to_string(),
+                rules: Vec::new(),
             };
             assert_eq!(retention_policy, expected_retention_policy);
         }
@@ -822,17 +1350,100 @@ mod tests {
             let expected_retention_policy = RetentionPolicy {
                 retention_period: "90 days".to_string(),
                 evaluation_schedule: "daily".to_string(),
+                rules: Vec::new(),
+            };
+            assert_eq!(retention_policy, expected_retention_policy);
+        }
+        {
+            let retention_policy_yaml = r#"
+            period: 90 days
+            rules:
+              - tag: tenant:free
+                period: 7 days
+        "#;
+            let retention_policy =
+                serde_yaml::from_str::<RetentionPolicy>(retention_policy_yaml).unwrap();
+
+            let expected_retention_policy = RetentionPolicy {
+                retention_period: "90 days".to_string(),
+                evaluation_schedule: "hourly".to_string(),
+                rules: vec![RetentionRule {
+                    tag: "tenant:free".to_string(),
+                    retention_period: "7 days".to_string(),
+                }],
             };
             assert_eq!(retention_policy, expected_retention_policy);
+
+            let rule = &retention_policy.rules[0];
+            assert_eq!(rule.tag_field_and_value().unwrap(), ("tenant", "free"));
+            assert_eq!(
+                rule.retention_period().unwrap(),
+                Duration::from_secs(7 * 24 * 3600)
+            );
         }
     }
 
+    #[test]
+    fn test_retention_rules_validation() {
+        let mut retention_policy = RetentionPolicy {
+            retention_period: "90 days".to_string(),
+            evaluation_schedule: "daily".to_string(),
+            rules: vec![RetentionRule {
+                tag: "tenant".to_string(),
+                retention_period: "7 days".to_string(),
+            }],
+        };
+        let error = retention_policy.validate().unwrap_err();
+        assert!(error.to_string().contains("must be of the form"));
+
+        retention_policy.rules = vec![
+            RetentionRule {
+                tag: "tenant:free".to_string(),
+                retention_period: "7 days".to_string(),
+            },
+            RetentionRule {
+                tag: "tenant:free".to_string(),
+                retention_period: "30 days".to_string(),
+            },
+        ];
+        let error = retention_policy.validate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "retention rule tag `tenant:free` is defined more than once"
+        );
+
+        retention_policy.rules.truncate(1);
+        retention_policy.validate().unwrap();
+
+        let index_config = IndexConfig::for_test("test-index", "s3://test-index");
+        let error = validate_index_config(
+            &index_config.doc_mapping,
+            &index_config.indexing_settings,
+            &index_config.search_settings,
+            &Some(retention_policy.clone()),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "retention rule tag `tenant:free` refers to field `tenant`, which is not a tag field"
+        );
+        retention_policy.rules[0].tag = "owner:free".to_string();
+        validate_index_config(
+            &index_config.doc_mapping,
+            &index_config.indexing_settings,
+            &index_config.search_settings,
+            &Some(retention_policy),
+        )
+        .unwrap();
+    }
+
     #[test]
     fn test_parse_retention_policy_period() {
         {
             let retention_policy = RetentionPolicy {
                 retention_period: "1 hour".to_string(),
                 evaluation_schedule: "hourly".to_string(),
+                rules: Vec::new(),
             };
             assert_eq!(
                 retention_policy.retention_period().unwrap(),
@@ -842,6 +1453,7 @@ mod tests {
                 let retention_policy = RetentionPolicy {
                     retention_period: "foo".to_string(),
                     evaluation_schedule: "hourly".to_string(),
+                    rules: Vec::new(),
                 };
                 assert_eq!(
                     retention_policy.retention_period().unwrap_err().to_string(),
@@ -851,6 +1463,178 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_lifecycle_policy_deserialization() {
+        let lifecycle_policy_yaml = r#"
+            cold_after: 30 days
+            cold_storage_uri: s3://archive-bucket/indexes/my-index
+        "#;
+        let lifecycle_policy =
+            serde_yaml::from_str::<LifecyclePolicy>(lifecycle_policy_yaml).unwrap();
+
+        let expected_lifecycle_policy = LifecyclePolicy {
+            cold_after: "30 days".to_string(),
+            cold_storage_uri: Uri::for_test("s3://archive-bucket/indexes/my-index"),
+            evaluation_schedule: "daily".to_string(),
+        };
+        assert_eq!(lifecycle_policy, expected_lifecycle_policy);
+        assert_eq!(
+            lifecycle_policy.cold_after().unwrap(),
+            Duration::from_secs(30 * 24 * 3600)
+        );
+    }
+
+    #[test]
+    fn test_lifecycle_policy_validate() {
+        let index_uri = Uri::for_test("s3://hot-bucket/indexes/my-index");
+        let lifecycle_policy = LifecyclePolicy {
+            cold_after: "30 days".to_string(),
+            cold_storage_uri: Uri::for_test("s3://archive-bucket/indexes/my-index"),
+            evaluation_schedule: "daily".to_string(),
+        };
+        lifecycle_policy.validate(&index_uri, None).unwrap();
+
+        let retention_policy = RetentionPolicy {
+            retention_period: "1 year".to_string(),
+            evaluation_schedule: "daily".to_string(),
+            rules: Vec::new(),
+        };
+        lifecycle_policy
+            .validate(&index_uri, Some(&retention_policy))
+            .unwrap();
+
+        let retention_policy = RetentionPolicy {
+            retention_period: "7 days".to_string(),
+            evaluation_schedule: "daily".to_string(),
+            rules: Vec::new(),
+        };
+        let error = lifecycle_policy
+            .validate(&index_uri, Some(&retention_policy))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "lifecycle `cold_after` period must be shorter than the retention period"
+        );
+        let error = lifecycle_policy
+            .validate(&lifecycle_policy.cold_storage_uri, None)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "lifecycle `cold_storage_uri` must differ from the index URI"
+        );
+        let invalid_lifecycle_policy = LifecyclePolicy {
+            cold_after: "foo".to_string(),
+            ..lifecycle_policy.clone()
+        };
+        invalid_lifecycle_policy
+            .validate(&index_uri, None)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_rollup_policy_deserialization() {
+        let rollup_policy_yaml = r#"
+            target_index_id: hdfs-rollup
+            window: 1 hour
+            aggregation:
+              per_host:
+                terms:
+                  field: host
+                aggs:
+                  avg_latency:
+                    avg:
+                      field: latency
+        "#;
+        let rollup_policy = serde_yaml::from_str::<RollupPolicy>(rollup_policy_yaml).unwrap();
+        assert_eq!(rollup_policy.target_index_id, "hdfs-rollup");
+        assert_eq!(rollup_policy.window().unwrap(), Duration::from_secs(3_600));
+        assert_eq!(rollup_policy.delay().unwrap(), Duration::from_secs(600));
+        assert_eq!(rollup_policy.query, "*");
+        assert_eq!(rollup_policy.evaluation_schedule, "hourly");
+        assert_eq!(
+            rollup_policy.aggregation["per_host"]["terms"]["field"],
+            "host"
+        );
+        rollup_policy.validate("hdfs").unwrap();
+    }
+
+    #[test]
+    fn test_rollup_policy_validate() {
+        let rollup_policy = RollupPolicy {
+            target_index_id: "hdfs-rollup".to_string(),
+            window: "1 day".to_string(),
+            delay: "1 hour".to_string(),
+            query: "*".to_string(),
+            aggregation: serde_json::json!({"count": {"value_count": {"field": "host"}}}),
+            evaluation_schedule: "daily".to_string(),
+        };
+        rollup_policy.validate("hdfs").unwrap();
+
+        let error = rollup_policy.validate("hdfs-rollup").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "rollup `target_index_id` must differ from the index ID"
+        );
+        let invalid_rollup_policy = RollupPolicy {
+            window: "1500ms".to_string(),
+            ..rollup_policy.clone()
+        };
+        let error = invalid_rollup_policy.validate("hdfs").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "rollup window `1500ms` must be a whole number of seconds"
+        );
+        let invalid_rollup_policy = RollupPolicy {
+            aggregation: serde_json::json!({}),
+            ..rollup_policy.clone()
+        };
+        let error = invalid_rollup_policy.validate("hdfs").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "rollup `aggregation` must be a non-empty JSON object"
+        );
+        let invalid_rollup_policy = RollupPolicy {
+            delay: "foo".to_string(),
+            ..rollup_policy
+        };
+        invalid_rollup_policy.validate("hdfs").unwrap_err();
+    }
+
+    #[test]
+    fn test_ingest_quota_deserialization() {
+        let ingest_quota_yaml = r#"
+            max_ingest_throughput: 20MB
+            max_daily_ingest_volume: 1TB
+        "#;
+        let ingest_quota = serde_yaml::from_str::<IngestQuota>(ingest_quota_yaml).unwrap();
+        let expected_ingest_quota = IngestQuota {
+            max_ingest_throughput: Some(ByteSize::mb(20)),
+            max_daily_ingest_volume: Some(ByteSize::tb(1)),
+        };
+        assert_eq!(ingest_quota, expected_ingest_quota);
+        ingest_quota.validate().unwrap();
+    }
+
+    #[test]
+    fn test_ingest_quota_validate() {
+        let error = IngestQuota::default().validate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "ingest quota must define `max_ingest_throughput` or `max_daily_ingest_volume`"
+        );
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: Some(ByteSize(0)),
+            max_daily_ingest_volume: None,
+        };
+        ingest_quota.validate().unwrap_err();
+
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: None,
+            max_daily_ingest_volume: Some(ByteSize::gb(10)),
+        };
+        ingest_quota.validate().unwrap();
+    }
+
     #[test]
     fn test_prepend_at_char() {
         assert_eq!(prepend_at_char(""), "");
@@ -866,6 +1650,7 @@ mod tests {
             let retention_policy = RetentionPolicy {
                 retention_period: "1 hour".to_string(),
                 evaluation_schedule: "@hourly".to_string(),
+                rules: Vec::new(),
             };
             assert_eq!(
                 retention_policy.evaluation_schedule().unwrap(),
@@ -876,6 +1661,7 @@ mod tests {
             let retention_policy = RetentionPolicy {
                 retention_period: "1 hour".to_string(),
                 evaluation_schedule: "hourly".to_string(),
+                rules: Vec::new(),
             };
             assert_eq!(
                 retention_policy.evaluation_schedule().unwrap(),
@@ -886,6 +1672,7 @@ mod tests {
             let retention_policy = RetentionPolicy {
                 retention_period: "1 hour".to_string(),
                 evaluation_schedule: "0 * * * * *".to_string(),
+                rules: Vec::new(),
             };
             let evaluation_schedule = retention_policy.evaluation_schedule().unwrap();
             assert_eq!(evaluation_schedule.seconds().count(), 1);
@@ -899,6 +1686,7 @@ mod tests {
             let retention_policy = RetentionPolicy {
                 retention_period: "1 hour".to_string(),
                 evaluation_schedule: "hourly".to_string(),
+                rules: Vec::new(),
             };
             retention_policy.validate().unwrap();
         }
@@ -906,6 +1694,7 @@ mod tests {
             let retention_policy = RetentionPolicy {
                 retention_period: "foo".to_string(),
                 evaluation_schedule: "hourly".to_string(),
+                rules: Vec::new(),
             };
             retention_policy.validate().unwrap_err();
         }
@@ -913,6 +1702,7 @@ mod tests {
             let retention_policy = RetentionPolicy {
                 retention_period: "1 hour".to_string(),
                 evaluation_schedule: "foo".to_string(),
+                rules: Vec::new(),
             };
             retention_policy.validate().unwrap_err();
         }
@@ -925,6 +1715,7 @@ mod tests {
             let retention_policy = RetentionPolicy {
                 retention_period: "1 hour".to_string(),
                 evaluation_schedule: schedule_str.to_string(),
+                rules: Vec::new(),
             };
 
             let next_evaluation_duration = chrono::Duration::nanoseconds(
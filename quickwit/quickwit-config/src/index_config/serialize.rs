@@ -17,15 +17,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use anyhow::Context;
+use anyhow::{ensure, Context};
 use quickwit_common::uri::Uri;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
 use super::validate_index_config;
 use crate::{
-    validate_identifier, ConfigFormat, DocMapping, IndexConfig, IndexingSettings, RetentionPolicy,
-    SearchSettings,
+    validate_identifier, ConfigFormat, DocMapping, IndexConfig, IndexingSettings, IngestQuota,
+    LifecyclePolicy, RetentionPolicy, RollupPolicy, SearchSettings,
 };
 
 /// Alias for the latest serialization format.
@@ -97,6 +97,9 @@ impl IndexConfigForSerialization {
             indexing_settings: self.indexing_settings,
             search_settings: self.search_settings,
             retention_policy_opt: self.retention_policy_opt,
+            lifecycle_policy_opt: self.lifecycle_policy_opt,
+            rollup_policy_opt: self.rollup_policy_opt,
+            ingest_quota_opt: self.ingest_quota_opt,
         };
         validate_index_config(
             &index_config.doc_mapping,
@@ -104,6 +107,28 @@ impl IndexConfigForSerialization {
             &index_config.search_settings,
             &index_config.retention_policy_opt,
         )?;
+        if let Some(lifecycle_policy) = &index_config.lifecycle_policy_opt {
+            lifecycle_policy.validate(
+                &index_config.index_uri,
+                index_config.retention_policy_opt.as_ref(),
+            )?;
+            ensure!(
+                index_config.doc_mapping.timestamp_field.is_some(),
+                "lifecycle policy requires a timestamp field, but the doc mapping does not \
+                 declare one"
+            );
+        }
+        if let Some(rollup_policy) = &index_config.rollup_policy_opt {
+            rollup_policy.validate(&index_config.index_id)?;
+            ensure!(
+                index_config.doc_mapping.timestamp_field.is_some(),
+                "rollup policy requires a timestamp field, but the doc mapping does not declare \
+                 one"
+            );
+        }
+        if let Some(ingest_quota) = &index_config.ingest_quota_opt {
+            ingest_quota.validate()?;
+        }
         Ok(index_config)
     }
 }
@@ -139,6 +164,18 @@ pub struct IndexConfigV0_8 {
     #[serde(rename = "retention")]
     #[serde(default)]
     pub retention_policy_opt: Option<RetentionPolicy>,
+    #[serde(rename = "lifecycle")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lifecycle_policy_opt: Option<LifecyclePolicy>,
+    #[serde(rename = "rollup")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollup_policy_opt: Option<RollupPolicy>,
+    #[serde(rename = "ingest_quota")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingest_quota_opt: Option<IngestQuota>,
 }
 
 impl From<IndexConfig> for IndexConfigV0_8 {
@@ -150,6 +187,9 @@ impl From<IndexConfig> for IndexConfigV0_8 {
             indexing_settings: index_config.indexing_settings,
             search_settings: index_config.search_settings,
             retention_policy_opt: index_config.retention_policy_opt,
+            lifecycle_policy_opt: index_config.lifecycle_policy_opt,
+            rollup_policy_opt: index_config.rollup_policy_opt,
+            ingest_quota_opt: index_config.ingest_quota_opt,
         }
     }
 }
@@ -209,6 +249,7 @@ mod test {
         invalid_index_config.retention_policy_opt = Some(RetentionPolicy {
             retention_period: "90 days".to_string(),
             evaluation_schedule: "hourly".to_string(),
+            rules: Vec::new(),
         });
         let validation_err = invalid_index_config
             .build_and_validate(None)
@@ -75,6 +75,9 @@ impl IndexTemplate {
             indexing_settings: self.indexing_settings.clone(),
             search_settings: self.search_settings.clone(),
             retention_policy_opt: self.retention_policy_opt.clone(),
+            lifecycle_policy_opt: None,
+            rollup_policy_opt: None,
+            ingest_quota_opt: None,
         };
         Ok(index_config)
     }
@@ -171,6 +174,7 @@ impl TestableForRegression for IndexTemplate {
             retention_policy_opt: Some(RetentionPolicy {
                 retention_period: "42 days".to_string(),
                 evaluation_schedule: "daily".to_string(),
+                rules: Vec::new(),
             }),
         }
     }
@@ -230,6 +234,7 @@ mod tests {
         index_template.retention_policy_opt = Some(RetentionPolicy {
             retention_period: "42 days".to_string(),
             evaluation_schedule: "hourly".to_string(),
+            rules: Vec::new(),
         });
         let default_index_root_uri = Uri::for_test("s3://test-bucket/indexes");
 
@@ -281,6 +286,7 @@ mod tests {
         index_template.retention_policy_opt = Some(RetentionPolicy {
             retention_period: "".to_string(),
             evaluation_schedule: "".to_string(),
+            rules: Vec::new(),
         });
         let error = index_template.validate().unwrap_err();
         assert!(error
@@ -46,8 +46,9 @@ pub use cluster_config::ClusterConfig;
 // See #2048
 use index_config::serialize::{IndexConfigV0_8, VersionedIndexConfig};
 pub use index_config::{
-    build_doc_mapper, load_index_config_from_user_config, DocMapping, IndexConfig,
-    IndexingResources, IndexingSettings, RetentionPolicy, SearchSettings,
+    build_doc_mapper, load_index_config_from_user_config, DocMapping, IndexConfig, IndexSortBy,
+    IndexSortOrder, IndexingResources, IndexingSettings, IngestQuota, LifecyclePolicy,
+    RetentionPolicy, RetentionRule, RollupPolicy, SearchSettings,
 };
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -56,7 +57,7 @@ pub use source_config::{
     load_source_config_from_user_config, FileSourceParams, KafkaSourceParams, KinesisSourceParams,
     PubSubSourceParams, PulsarSourceAuth, PulsarSourceParams, RegionOrEndpoint, SourceConfig,
     SourceInputFormat, SourceParams, TransformConfig, VecSourceParams, VoidSourceParams,
-    CLI_SOURCE_ID, INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID,
+    CLI_SOURCE_ID, INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID, ROLLUP_SOURCE_ID,
 };
 use tracing::warn;
 
@@ -64,13 +65,14 @@ use crate::index_template::IndexTemplateV0_8;
 pub use crate::index_template::{IndexTemplate, IndexTemplateId, VersionedIndexTemplate};
 use crate::merge_policy_config::{
     ConstWriteAmplificationMergePolicyConfig, MergePolicyConfig, StableLogMergePolicyConfig,
+    TimePartitionedMergePolicyConfig,
 };
 pub use crate::metastore_config::{
     MetastoreBackend, MetastoreConfig, MetastoreConfigs, PostgresMetastoreConfig,
 };
 pub use crate::node_config::{
-    enable_ingest_v2, IndexerConfig, IngestApiConfig, JaegerConfig, NodeConfig, SearcherConfig,
-    SplitCacheLimits, DEFAULT_QW_CONFIG_PATH,
+    enable_ingest_v2, IndexerConfig, IngestApiConfig, JaegerConfig, NodeConfig, SearchQuotasConfig,
+    SearcherConfig, SplitCacheLimits, DEFAULT_QW_CONFIG_PATH,
 };
 use crate::source_config::serialize::{SourceConfigV0_7, SourceConfigV0_8, VersionedSourceConfig};
 pub use crate::storage_config::{
@@ -82,8 +84,14 @@ pub use crate::storage_config::{
 #[openapi(components(schemas(
     IndexingResources,
     IndexingSettings,
+    IndexSortBy,
+    IndexSortOrder,
     SearchSettings,
     RetentionPolicy,
+    RetentionRule,
+    LifecyclePolicy,
+    RollupPolicy,
+    IngestQuota,
     MergePolicyConfig,
     DocMapping,
     VersionedSourceConfig,
@@ -104,6 +112,7 @@ pub use crate::storage_config::{
     RegionOrEndpoint,
     ConstWriteAmplificationMergePolicyConfig,
     StableLogMergePolicyConfig,
+    TimePartitionedMergePolicyConfig,
     TransformConfig,
     VecSourceParams,
     VoidSourceParams,
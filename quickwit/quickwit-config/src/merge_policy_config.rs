@@ -78,6 +78,67 @@ pub struct StableLogMergePolicyConfig {
     pub maturation_period: Duration,
 }
 
+/// Merge policy bucketing splits by time window before merging them, so that merged splits never
+/// span several windows.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TimePartitionedMergePolicyConfig {
+    /// Duration of the time windows, e.g. `1h` or `1d`. Windows are aligned on the Unix epoch.
+    /// Splits spanning several windows are only merged with splits spanning the same windows.
+    #[schema(value_type = String)]
+    #[serde(default = "default_time_partition_window")]
+    #[serde(deserialize_with = "parse_human_duration")]
+    #[serde(serialize_with = "serialize_duration")]
+    pub window: Duration,
+    /// Number of docs below which all splits of a window are considered as belonging to the same
+    /// level.
+    #[serde(default = "default_min_level_num_docs")]
+    pub min_level_num_docs: usize,
+    /// Number of splits to merge together in a single merge operation.
+    #[serde(default = "default_merge_factor")]
+    pub merge_factor: usize,
+    /// Maximum number of splits that can be merged together in a single merge operation.
+    #[serde(default = "default_max_merge_factor")]
+    pub max_merge_factor: usize,
+    /// Duration relative to `split.created_timestamp` after which a split
+    /// becomes mature.
+    /// If `now() >= split.created_timestamp + maturation_period` then
+    /// the split is mature.
+    #[schema(value_type = String)]
+    #[serde(default = "default_maturation_period")]
+    #[serde(deserialize_with = "parse_human_duration")]
+    #[serde(serialize_with = "serialize_duration")]
+    pub maturation_period: Duration,
+}
+
+impl TimePartitionedMergePolicyConfig {
+    /// Returns the configuration of the stable log merge policy applied within each window.
+    pub fn stable_log_config(&self) -> StableLogMergePolicyConfig {
+        StableLogMergePolicyConfig {
+            min_level_num_docs: self.min_level_num_docs,
+            merge_factor: self.merge_factor,
+            max_merge_factor: self.max_merge_factor,
+            maturation_period: self.maturation_period,
+        }
+    }
+}
+
+impl Default for TimePartitionedMergePolicyConfig {
+    fn default() -> Self {
+        TimePartitionedMergePolicyConfig {
+            window: default_time_partition_window(),
+            min_level_num_docs: default_min_level_num_docs(),
+            merge_factor: default_merge_factor(),
+            max_merge_factor: default_max_merge_factor(),
+            maturation_period: default_maturation_period(),
+        }
+    }
+}
+
+fn default_time_partition_window() -> Duration {
+    Duration::from_secs(24 * 3600)
+}
+
 fn default_merge_factor() -> usize {
     10
 }
@@ -137,6 +198,8 @@ pub enum MergePolicyConfig {
     #[serde(rename = "stable_log")]
     #[serde(alias = "default")]
     StableLog(StableLogMergePolicyConfig),
+    #[serde(rename = "time_partitioned")]
+    TimePartitioned(TimePartitionedMergePolicyConfig),
 }
 
 impl Default for MergePolicyConfig {
@@ -155,6 +218,14 @@ impl MergePolicyConfig {
                 (config.merge_factor, config.max_merge_factor)
             }
             MergePolicyConfig::StableLog(config) => (config.merge_factor, config.max_merge_factor),
+            MergePolicyConfig::TimePartitioned(config) => {
+                if config.window.as_secs() == 0 || config.window.subsec_nanos() != 0 {
+                    anyhow::bail!(
+                        "index config merge policy `window` must be a whole number of seconds"
+                    );
+                }
+                (config.merge_factor, config.max_merge_factor)
+            }
         };
         if max_merge_factor < merge_factor {
             anyhow::bail!(
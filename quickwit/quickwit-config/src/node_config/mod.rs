@@ -33,6 +33,7 @@ use once_cell::sync::Lazy;
 use quickwit_common::net::HostAddr;
 use quickwit_common::uri::Uri;
 use quickwit_proto::indexing::CpuCapacity;
+use quickwit_proto::ingest::ingester::DecommissionMode;
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
@@ -204,6 +205,45 @@ impl SplitCacheLimits {
     }
 }
 
+/// Limits the resources that the searches targeting a single index can use on a searcher, so that
+/// heavy searches on one index do not starve the searches on the other indexes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchQuotasConfig {
+    /// Maximum number of root searches running concurrently on an index.
+    pub max_num_concurrent_searches_per_index: NonZeroU32,
+    /// Maximum number of root searches of the `batch` priority class running concurrently on an
+    /// index. Capping it below `max_num_concurrent_searches_per_index` keeps some slots available
+    /// for interactive searches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_num_concurrent_batch_searches_per_index: Option<NonZeroU32>,
+    /// Maximum amount of aggregation memory that the root searches running concurrently on an
+    /// index can reserve. Each aggregation search reserves `aggregation_memory_limit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_aggregation_memory_per_index: Option<ByteSize>,
+    /// Maximum number of root searches waiting for their turn on an index. Beyond that, searches
+    /// are rejected right away.
+    #[serde(default = "SearchQuotasConfig::default_max_num_queued_searches_per_index")]
+    pub max_num_queued_searches_per_index: u32,
+    /// How long a root search can wait for its turn before being rejected.
+    #[serde(default = "SearchQuotasConfig::default_queue_timeout_secs")]
+    pub queue_timeout_secs: NonZeroU64,
+}
+
+impl SearchQuotasConfig {
+    fn default_max_num_queued_searches_per_index() -> u32 {
+        100
+    }
+
+    fn default_queue_timeout_secs() -> NonZeroU64 {
+        NonZeroU64::new(30).unwrap()
+    }
+
+    pub fn queue_timeout(&self) -> Duration {
+        Duration::from_secs(self.queue_timeout_secs.get())
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, default)]
 pub struct SearcherConfig {
@@ -212,6 +252,7 @@ pub struct SearcherConfig {
     pub fast_field_cache_capacity: ByteSize,
     pub split_footer_cache_capacity: ByteSize,
     pub partial_request_cache_capacity: ByteSize,
+    pub root_search_cache_capacity: ByteSize,
     pub max_num_concurrent_split_searches: usize,
     pub max_num_concurrent_split_streams: usize,
     // Strangely, if None, this will also have the effect of not forwarding
@@ -219,6 +260,8 @@ pub struct SearcherConfig {
     // TODO document and fix if necessary.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub split_cache: Option<SplitCacheLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_quotas: Option<SearchQuotasConfig>,
 }
 
 impl Default for SearcherConfig {
@@ -227,17 +270,44 @@ impl Default for SearcherConfig {
             fast_field_cache_capacity: ByteSize::gb(1),
             split_footer_cache_capacity: ByteSize::mb(500),
             partial_request_cache_capacity: ByteSize::mb(64),
+            root_search_cache_capacity: ByteSize::b(0),
             max_num_concurrent_split_streams: 100,
             max_num_concurrent_split_searches: 100,
             aggregation_memory_limit: ByteSize::mb(500),
             aggregation_bucket_limit: 65000,
             split_cache: None,
+            search_quotas: None,
         }
     }
 }
 
 impl SearcherConfig {
     fn validate(&self) -> anyhow::Result<()> {
+        if let Some(search_quotas) = self.search_quotas {
+            if let Some(max_num_concurrent_batch_searches_per_index) =
+                search_quotas.max_num_concurrent_batch_searches_per_index
+            {
+                ensure!(
+                    max_num_concurrent_batch_searches_per_index
+                        <= search_quotas.max_num_concurrent_searches_per_index,
+                    "search_quotas.max_num_concurrent_batch_searches_per_index ({}) must be lower \
+                     or equal to search_quotas.max_num_concurrent_searches_per_index ({})",
+                    max_num_concurrent_batch_searches_per_index,
+                    search_quotas.max_num_concurrent_searches_per_index
+                );
+            }
+            if let Some(max_aggregation_memory_per_index) =
+                search_quotas.max_aggregation_memory_per_index
+            {
+                ensure!(
+                    max_aggregation_memory_per_index >= self.aggregation_memory_limit,
+                    "search_quotas.max_aggregation_memory_per_index ({}) must be greater or equal \
+                     to aggregation_memory_limit ({})",
+                    max_aggregation_memory_per_index,
+                    self.aggregation_memory_limit
+                );
+            }
+        }
         if let Some(split_cache_limits) = self.split_cache {
             if self.max_num_concurrent_split_searches
                 > split_cache_limits.max_file_descriptors.get() as usize
@@ -271,6 +341,20 @@ pub struct IngestApiConfig {
     pub max_queue_disk_usage: ByteSize,
     pub replication_factor: usize,
     pub content_length_limit: ByteSize,
+    /// Period of time during which the ingesters remember the dedup IDs of the persisted batches.
+    /// Setting it to zero disables deduplication.
+    pub dedup_window_secs: u64,
+    /// Whether ingest routers parse documents against the doc mapping of their index and reject
+    /// invalid ones before persisting them.
+    pub validate_docs: bool,
+    /// Zstd compression level of the documents written to the WAL and replicated to the
+    /// followers. Compression is disabled when unset.
+    pub wal_compression_level: Option<i32>,
+    /// How an ingester decommissions: either by waiting for its shards to be fully indexed
+    /// (`drain`) or by handing them off to other ingesters (`handoff`).
+    pub decommission_mode: DecommissionMode,
+    /// Port on which ingesters accept the Kafka Produce API. The endpoint is disabled when unset.
+    pub kafka_listen_port: Option<u16>,
 }
 
 impl Default for IngestApiConfig {
@@ -280,6 +364,11 @@ impl Default for IngestApiConfig {
             max_queue_disk_usage: ByteSize::gib(4),   // TODO maybe we want more?
             replication_factor: 1,
             content_length_limit: ByteSize::mib(10),
+            dedup_window_secs: 600,
+            validate_docs: true,
+            wal_compression_level: None,
+            decommission_mode: DecommissionMode::Drain,
+            kafka_listen_port: None,
         }
     }
 }
@@ -296,20 +385,25 @@ impl IngestApiConfig {
             let replication_factor = match replication_factor_str.trim() {
                 "1" => 1,
                 "2" => 2,
+                "3" => 3,
                 _ => bail!(
-                    "replication factor must be either 1 or 2, got `{replication_factor_str}`"
+                    "replication factor must be either 1, 2, or 3, got `{replication_factor_str}`"
                 ),
             };
             return Ok(NonZeroUsize::new(replication_factor)
-                .expect("replication factor should be either 1 or 2"));
+                .expect("replication factor should be either 1, 2, or 3"));
         }
         ensure!(
-            self.replication_factor >= 1 && self.replication_factor <= 2,
-            "replication factor must be either 1 or 2, got `{}`",
+            self.replication_factor >= 1 && self.replication_factor <= 3,
+            "replication factor must be either 1, 2, or 3, got `{}`",
             self.replication_factor
         );
         Ok(NonZeroUsize::new(self.replication_factor)
-            .expect("replication factor should be either 1 or 2"))
+            .expect("replication factor should be either 1, 2, or 3"))
+    }
+
+    pub fn dedup_window(&self) -> Duration {
+        Duration::from_secs(self.dedup_window_secs)
     }
 
     fn validate(&self) -> anyhow::Result<()> {
@@ -325,6 +419,12 @@ impl IngestApiConfig {
             self.max_queue_disk_usage,
             self.max_queue_memory_usage
         );
+        if let Some(wal_compression_level) = self.wal_compression_level {
+            ensure!(
+                (1..=22).contains(&wal_compression_level),
+                "wal_compression_level must be between 1 and 22, got `{wal_compression_level}`"
+            );
+        }
         Ok(())
     }
 }
@@ -407,6 +507,7 @@ pub struct NodeConfig {
     pub grpc_listen_addr: SocketAddr,
     pub gossip_advertise_addr: SocketAddr,
     pub grpc_advertise_addr: SocketAddr,
+    pub availability_zone: Option<String>,
     pub gossip_interval: Duration,
     pub peer_seeds: Vec<String>,
     pub data_dir_path: PathBuf,
@@ -571,6 +672,76 @@ mod tests {
                  MB)"
             );
         }
+        {
+            let indexer_config: IngestApiConfig = serde_yaml::from_str(
+                r#"
+                    wal_compression_level: 23
+                "#,
+            )
+            .unwrap();
+            assert_eq!(
+                indexer_config.validate().unwrap_err().to_string(),
+                "wal_compression_level must be between 1 and 22, got `23`"
+            );
+        }
+    }
+
+    #[test]
+    fn test_searcher_config_search_quotas() {
+        let searcher_config: SearcherConfig = serde_yaml::from_str(
+            r#"
+                search_quotas:
+                  max_num_concurrent_searches_per_index: 10
+                  max_num_concurrent_batch_searches_per_index: 2
+                  max_aggregation_memory_per_index: 2G
+            "#,
+        )
+        .unwrap();
+        let search_quotas = searcher_config.search_quotas.unwrap();
+        assert_eq!(
+            search_quotas.max_num_concurrent_searches_per_index,
+            NonZeroU32::new(10).unwrap()
+        );
+        assert_eq!(
+            search_quotas.max_num_concurrent_batch_searches_per_index,
+            Some(NonZeroU32::new(2).unwrap())
+        );
+        assert_eq!(
+            search_quotas.max_aggregation_memory_per_index,
+            Some(ByteSize::gb(2))
+        );
+        assert_eq!(search_quotas.max_num_queued_searches_per_index, 100);
+        assert_eq!(search_quotas.queue_timeout(), Duration::from_secs(30));
+        searcher_config.validate().unwrap();
+
+        let searcher_config: SearcherConfig = serde_yaml::from_str(
+            r#"
+                search_quotas:
+                  max_num_concurrent_searches_per_index: 2
+                  max_num_concurrent_batch_searches_per_index: 4
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            searcher_config.validate().unwrap_err().to_string(),
+            "search_quotas.max_num_concurrent_batch_searches_per_index (4) must be lower or equal \
+             to search_quotas.max_num_concurrent_searches_per_index (2)"
+        );
+
+        let searcher_config: SearcherConfig = serde_yaml::from_str(
+            r#"
+                aggregation_memory_limit: 500M
+                search_quotas:
+                  max_num_concurrent_searches_per_index: 2
+                  max_aggregation_memory_per_index: 100M
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            searcher_config.validate().unwrap_err().to_string(),
+            "search_quotas.max_aggregation_memory_per_index (100.0 MB) must be greater or equal \
+             to aggregation_memory_limit (500.0 MB)"
+        );
     }
 
     #[test]
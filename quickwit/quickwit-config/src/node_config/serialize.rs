@@ -179,6 +179,7 @@ struct NodeConfigBuilder {
     #[serde(default = "default_listen_address")]
     listen_address: ConfigValue<String, QW_LISTEN_ADDRESS>,
     advertise_address: ConfigValue<String, QW_ADVERTISE_ADDRESS>,
+    availability_zone: ConfigValue<String, QW_AVAILABILITY_ZONE>,
     // Deprecated, use `rest.listen_port` instead.
     rest_listen_port: Option<u16>,
     gossip_listen_port: ConfigValue<u16, QW_GOSSIP_LISTEN_PORT>,
@@ -313,6 +314,7 @@ impl NodeConfigBuilder {
             grpc_listen_addr,
             gossip_advertise_addr,
             grpc_advertise_addr,
+            availability_zone: self.availability_zone.resolve_optional(env_vars)?,
             gossip_interval,
             peer_seeds: self.peer_seeds.resolve(env_vars)?.0,
             data_dir_path,
@@ -359,6 +361,7 @@ impl Default for NodeConfigBuilder {
             grpc_listen_port: ConfigValue::none(),
             gossip_interval_ms: ConfigValue::none(),
             advertise_address: ConfigValue::none(),
+            availability_zone: ConfigValue::none(),
             peer_seeds: ConfigValue::with_default(List::default()),
             data_dir_uri: default_data_dir_uri(),
             metastore_uri: ConfigValue::none(),
@@ -451,6 +454,7 @@ pub fn node_config_for_test() -> NodeConfig {
         grpc_advertise_addr: grpc_listen_addr,
         gossip_listen_addr,
         grpc_listen_addr,
+        availability_zone: None,
         gossip_interval: Duration::from_millis(25u64),
         peer_seeds: Vec::new(),
         data_dir_path,
@@ -476,6 +480,7 @@ mod tests {
 
     use bytesize::ByteSize;
     use itertools::Itertools;
+    use quickwit_proto::ingest::ingester::DecommissionMode;
 
     use super::*;
     use crate::storage_config::StorageBackendFlavor;
@@ -530,6 +535,7 @@ mod tests {
             config.grpc_advertise_addr,
             SocketAddr::new(IpAddr::V4(Ipv4Addr::new(172, 0, 0, 12)), 3333)
         );
+        assert_eq!(config.availability_zone.as_deref(), Some("us-east-1a"));
         assert_eq!(
             config.peer_seeds,
             vec![
@@ -597,6 +603,11 @@ mod tests {
             config.ingest_api_config,
             IngestApiConfig {
                 replication_factor: 2,
+                dedup_window_secs: 300,
+                validate_docs: false,
+                wal_compression_level: Some(3),
+                decommission_mode: DecommissionMode::Handoff,
+                kafka_listen_port: Some(9092),
                 ..Default::default()
             }
         );
@@ -608,9 +619,11 @@ mod tests {
                 fast_field_cache_capacity: ByteSize::gb(10),
                 split_footer_cache_capacity: ByteSize::gb(1),
                 partial_request_cache_capacity: ByteSize::mb(64),
+                root_search_cache_capacity: ByteSize::mb(32),
                 max_num_concurrent_split_searches: 150,
                 max_num_concurrent_split_streams: 120,
                 split_cache: None,
+                search_quotas: None,
             }
         );
         assert_eq!(
@@ -722,6 +735,7 @@ mod tests {
         );
         env_vars.insert("QW_LISTEN_ADDRESS".to_string(), "172.0.0.12".to_string());
         env_vars.insert("QW_ADVERTISE_ADDRESS".to_string(), "172.0.0.13".to_string());
+        env_vars.insert("QW_AVAILABILITY_ZONE".to_string(), "us-east-1a".to_string());
         env_vars.insert("QW_REST_LISTEN_PORT".to_string(), "1234".to_string());
         env_vars.insert("QW_GOSSIP_LISTEN_PORT".to_string(), "5678".to_string());
         env_vars.insert("QW_GRPC_LISTEN_PORT".to_string(), "9012".to_string());
@@ -773,6 +787,7 @@ mod tests {
             config.grpc_advertise_addr,
             SocketAddr::new(IpAddr::V4(Ipv4Addr::new(172, 0, 0, 13)), 9012)
         );
+        assert_eq!(config.availability_zone.as_deref(), Some("us-east-1a"));
         assert_eq!(
             config.peer_seeds,
             vec![
@@ -1232,14 +1247,20 @@ mod tests {
             ..Default::default()
         };
         let error_message = ingest_config.validate().unwrap_err().to_string();
-        assert!(error_message.contains("either 1 or 2, got `0`"));
+        assert!(error_message.contains("either 1, 2, or 3, got `0`"));
 
         let ingest_config = IngestApiConfig {
             replication_factor: 3,
             ..Default::default()
         };
+        ingest_config.validate().unwrap();
+
+        let ingest_config = IngestApiConfig {
+            replication_factor: 4,
+            ..Default::default()
+        };
         let error_message = ingest_config.validate().unwrap_err().to_string();
-        assert!(error_message.contains("either 1 or 2, got `3`"));
+        assert!(error_message.contains("either 1, 2, or 3, got `4`"));
 
         let node_config_yaml = r#"
             version: 0.7
@@ -53,6 +53,7 @@ qw_env_vars!(
     QW_ENABLED_SERVICES,
     QW_LISTEN_ADDRESS,
     QW_ADVERTISE_ADDRESS,
+    QW_AVAILABILITY_ZONE,
     QW_REST_LISTEN_PORT,
     QW_GOSSIP_LISTEN_PORT,
     QW_GRPC_LISTEN_PORT,
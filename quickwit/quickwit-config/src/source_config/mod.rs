@@ -46,8 +46,16 @@ pub const INGEST_API_SOURCE_ID: &str = "_ingest-api-source";
 /// (this is for ingest v2)
 pub const INGEST_V2_SOURCE_ID: &str = "_ingest-source";
 
-pub const RESERVED_SOURCE_IDS: &[&str] =
-    &[CLI_SOURCE_ID, INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID];
+/// Source ID under which the rollup policy of an index records the time windows already rolled
+/// up in the index checkpoint. No source is registered under this ID.
+pub const ROLLUP_SOURCE_ID: &str = "_rollup-source";
+
+pub const RESERVED_SOURCE_IDS: &[&str] = &[
+    CLI_SOURCE_ID,
+    INGEST_API_SOURCE_ID,
+    INGEST_V2_SOURCE_ID,
+    ROLLUP_SOURCE_ID,
+];
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(into = "VersionedSourceConfig")]
@@ -38,13 +38,13 @@ use quickwit_common::uri::Uri;
 use quickwit_common::Progress;
 use quickwit_config::service::QuickwitService;
 use quickwit_config::{ClusterConfig, IndexConfig, IndexTemplate, SourceConfig};
-use quickwit_ingest::{IngesterPool, LocalShardsUpdate};
+use quickwit_ingest::{IngestVolumeUpdate, IngesterPool, LocalShardsUpdate};
 use quickwit_metastore::IndexMetadata;
 use quickwit_proto::control_plane::{
     AdviseResetShardsRequest, AdviseResetShardsResponse, ControlPlaneError, ControlPlaneResult,
     GetDebugStateRequest, GetDebugStateResponse, GetOrCreateOpenShardsRequest,
-    GetOrCreateOpenShardsResponse, GetOrCreateOpenShardsSubrequest, PhysicalIndexingPlanEntry,
-    ShardTableEntry,
+    GetOrCreateOpenShardsResponse, GetOrCreateOpenShardsSubrequest, HandoffShardsRequest,
+    HandoffShardsResponse, PhysicalIndexingPlanEntry, ShardTableEntry,
 };
 use quickwit_proto::indexing::ShardPositionsUpdate;
 use quickwit_proto::metastore::{
@@ -305,9 +305,12 @@ impl ControlPlane {
                     .collect()
             })
             .unwrap_or_default();
+        let ingest_quotas = self.ingest_controller.ingest_quota_entries(&self.model);
+
         GetDebugStateResponse {
             shard_table,
             physical_index_plan,
+            ingest_quotas,
         }
     }
 
@@ -533,6 +536,7 @@ impl Handler<DeleteIndexRequest> for ControlPlane {
             .collect();
 
         self.model.delete_index(&index_uid);
+        self.ingest_controller.handle_index_deleted(&index_uid);
 
         self.ingest_controller
             .sync_with_ingesters(&ingester_needing_resync, &self.model);
@@ -722,6 +726,34 @@ impl Handler<AdviseResetShardsRequest> for ControlPlane {
     }
 }
 
+// This is neither a proxied call nor a metastore callback.
+#[async_trait]
+impl Handler<HandoffShardsRequest> for ControlPlane {
+    type Reply = ControlPlaneResult<HandoffShardsResponse>;
+
+    async fn handle(
+        &mut self,
+        request: HandoffShardsRequest,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        let response = match self
+            .ingest_controller
+            .handoff_shards(request, &mut self.model, ctx.progress())
+            .await
+        {
+            Ok(response) => response,
+            Err(ControlPlaneError::Metastore(metastore_error)) => {
+                return convert_metastore_error(metastore_error);
+            }
+            Err(control_plane_error) => {
+                return Ok(Err(control_plane_error));
+            }
+        };
+        let _rebuild_plan_waiter = self.rebuild_plan_debounced(ctx);
+        Ok(Ok(response))
+    }
+}
+
 #[async_trait]
 impl Handler<LocalShardsUpdate> for ControlPlane {
     type Reply = ControlPlaneResult<()>;
@@ -739,6 +771,22 @@ impl Handler<LocalShardsUpdate> for ControlPlane {
     }
 }
 
+#[async_trait]
+impl Handler<IngestVolumeUpdate> for ControlPlane {
+    type Reply = ControlPlaneResult<()>;
+
+    async fn handle(
+        &mut self,
+        ingest_volume_update: IngestVolumeUpdate,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        self.ingest_controller
+            .handle_ingest_volume_update(ingest_volume_update, &mut self.model, ctx.progress())
+            .await;
+        Ok(Ok(()))
+    }
+}
+
 #[async_trait]
 impl Handler<GetDebugStateRequest> for ControlPlane {
     type Reply = ControlPlaneResult<GetDebugStateResponse>;
@@ -775,6 +823,20 @@ impl EventSubscriber<LocalShardsUpdate> for ControlPlaneEventSubscriber {
     }
 }
 
+#[async_trait]
+impl EventSubscriber<IngestVolumeUpdate> for ControlPlaneEventSubscriber {
+    async fn handle_event(&mut self, ingest_volume_update: IngestVolumeUpdate) {
+        if let Some(control_plane_mailbox) = self.0.upgrade() {
+            if let Err(error) = control_plane_mailbox
+                .send_message(ingest_volume_update)
+                .await
+            {
+                error!(error=%error, "failed to forward ingest volume update to control plane");
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl EventSubscriber<ShardPositionsUpdate> for ControlPlaneEventSubscriber {
     async fn handle_event(&mut self, shard_positions_update: ShardPositionsUpdate) {
@@ -821,6 +883,10 @@ impl Handler<IndexerJoined> for ControlPlane {
             "indexer `{}` joined the cluster: rebuilding indexing plan",
             message.0.node_id()
         );
+        self.ingest_controller.handle_ingester_joined(
+            message.0.node_id().into(),
+            message.0.availability_zone().map(str::to_string),
+        );
         // TODO:
         // 1. Update shard table.
         // 2. Rebalance shards if necessary.
@@ -841,12 +907,17 @@ impl Handler<IndexerLeft> for ControlPlane {
     async fn handle(
         &mut self,
         message: IndexerLeft,
-        _ctx: &ActorContext<Self>,
+        ctx: &ActorContext<Self>,
     ) -> Result<Self::Reply, ActorExitStatus> {
         info!(
             "indexer `{}` left the cluster: rebuilding indexing plan",
             message.0.node_id()
         );
+        let node_id: NodeId = message.0.node_id().into();
+        self.ingest_controller.handle_ingester_left(&node_id);
+        self.ingest_controller
+            .promote_shards(&node_id, &mut self.model, ctx.progress())
+            .await;
         // 1. Update shard table.
         // 2. Rebalance shards if necessary.
         self.ingest_controller.rebalance_shards();
@@ -1410,6 +1481,7 @@ mod tests {
             client,
             indexing_tasks: Vec::new(),
             indexing_capacity: CpuCapacity::from_cpu_millis(4_000),
+            availability_zone: None,
         };
         indexer_pool.insert("indexer-node-1".to_string(), indexer_node_info);
         let ingester_pool = IngesterPool::default();
@@ -1485,6 +1557,7 @@ mod tests {
             .ask(ShardPositionsUpdate {
                 source_uid: source_uid.clone(),
                 updated_shard_positions: vec![(ShardId::from(17), Position::offset(1_000u64))],
+                published_split_ids: Vec::new(),
             })
             .await
             .unwrap();
@@ -1520,6 +1593,7 @@ mod tests {
             .ask(ShardPositionsUpdate {
                 source_uid,
                 updated_shard_positions: vec![(ShardId::from(17), Position::eof(1_000u64))],
+                published_split_ids: Vec::new(),
             })
             .await
             .unwrap();
@@ -1558,6 +1632,7 @@ mod tests {
             client,
             indexing_tasks: Vec::new(),
             indexing_capacity: CpuCapacity::from_cpu_millis(4_000),
+            availability_zone: None,
         };
         indexer_pool.insert("indexer-node-1".to_string(), indexer_node_info);
         let ingester_pool = IngesterPool::default();
@@ -1642,6 +1717,7 @@ mod tests {
             client,
             indexing_tasks: Vec::new(),
             indexing_capacity: CpuCapacity::from_cpu_millis(4_000),
+            availability_zone: None,
         };
         indexer_pool.insert("indexer-node-1".to_string(), indexer_node_info);
         let ingester_pool = IngesterPool::default();
@@ -1707,6 +1783,7 @@ mod tests {
             .ask(ShardPositionsUpdate {
                 source_uid: source_uid.clone(),
                 updated_shard_positions: vec![(ShardId::from(17), Position::eof(1_000u64))],
+                published_split_ids: Vec::new(),
             })
             .await
             .unwrap();
@@ -1759,6 +1836,7 @@ mod tests {
                             shard_id: Some(ShardId::from(15)),
                             leader_id: "node1".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             shard_state: ShardState::Open as i32,
                             publish_position_inclusive: None,
                             publish_token: None,
@@ -1886,6 +1964,7 @@ mod tests {
                             shard_id: Some(ShardId::from(15)),
                             leader_id: "node1".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             shard_state: ShardState::Open as i32,
                             publish_position_inclusive: None,
                             publish_token: None,
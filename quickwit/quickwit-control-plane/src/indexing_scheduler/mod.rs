@@ -224,6 +224,14 @@ impl IndexingScheduler {
             })
             .collect();
 
+        let indexer_id_to_availability_zones: FnvHashMap<String, String> = indexers
+            .iter()
+            .filter_map(|indexer| {
+                let availability_zone = indexer.availability_zone.clone()?;
+                Some((indexer.node_id.to_string(), availability_zone))
+            })
+            .collect();
+
         if indexer_id_to_cpu_capacities.is_empty() {
             if !sources.is_empty() {
                 warn!("no indexing capacity available, cannot schedule an indexing plan");
@@ -234,6 +242,7 @@ impl IndexingScheduler {
         let new_physical_plan = build_physical_indexing_plan(
             &sources,
             &indexer_id_to_cpu_capacities,
+            &indexer_id_to_availability_zones,
             self.state.last_applied_physical_plan.as_ref(),
         );
         if let Some(last_applied_plan) = &self.state.last_applied_physical_plan {
@@ -800,7 +809,12 @@ mod tests {
         let mut indexer_max_loads = FnvHashMap::default();
         indexer_max_loads.insert("indexer1".to_string(), mcpu(3_000));
         indexer_max_loads.insert("indexer2".to_string(), mcpu(3_000));
-        let physical_plan = build_physical_indexing_plan(&sources[..], &indexer_max_loads, None);
+        let physical_plan = build_physical_indexing_plan(
+            &sources[..],
+            &indexer_max_loads,
+            &FnvHashMap::default(),
+            None,
+        );
         assert_eq!(physical_plan.indexing_tasks_per_indexer().len(), 2);
         let indexing_tasks_1 = physical_plan.indexer("indexer1").unwrap();
         assert_eq!(indexing_tasks_1.len(), 2);
@@ -830,7 +844,7 @@ mod tests {
                 let indexer_id = format!("indexer-{i}");
                 indexer_max_loads.insert(indexer_id, mcpu(4_000));
             }
-            let _physical_indexing_plan = build_physical_indexing_plan(&sources, &indexer_max_loads, None);
+            let _physical_indexing_plan = build_physical_indexing_plan(&sources, &indexer_max_loads, &FnvHashMap::default(), None);
         }
     }
 
@@ -25,7 +25,7 @@ use std::num::NonZeroU32;
 use fnv::{FnvHashMap, FnvHashSet};
 use quickwit_proto::indexing::{CpuCapacity, IndexingTask};
 use quickwit_proto::types::{PipelineUid, ShardId, SourceUid};
-use scheduling_logic_model::{IndexerOrd, SourceOrd};
+use scheduling_logic_model::{IndexerOrd, SourceOrd, ZoneOrd};
 use tracing::{error, warn};
 
 use crate::indexing_plan::PhysicalIndexingPlan;
@@ -524,11 +524,15 @@ fn inflate_node_capacities_if_necessary(problem: &mut SchedulingProblem) {
 /// - 3) compute the new scheduling solution.
 /// - 4) convert the new scheduling solution back to the real world by reallocating the shard ids.
 ///
+/// Indexers missing from `indexer_id_to_availability_zones` are considered to belong to the same
+/// (unknown) availability zone.
+///
 /// TODO cut into pipelines.
 /// Panics if any sources has no shards.
 pub fn build_physical_indexing_plan(
     sources: &[SourceToSchedule],
     indexer_id_to_cpu_capacities: &FnvHashMap<String, CpuCapacity>,
+    indexer_id_to_availability_zones: &FnvHashMap<String, String>,
     previous_plan_opt: Option<&PhysicalIndexingPlan>,
 ) -> PhysicalIndexingPlan {
     for source in sources {
@@ -551,6 +555,21 @@ pub fn build_physical_indexing_plan(
 
     let mut problem = SchedulingProblem::with_indexer_cpu_capacities(indexer_cpu_capacities);
 
+    // We use a Vec as a `IndexOrd` -> `ZoneOrd` map.
+    let mut zone_ords: FnvHashMap<Option<&str>, ZoneOrd> = FnvHashMap::default();
+    let indexer_zones: Vec<ZoneOrd> = id_to_ord_map
+        .indexer_ids
+        .iter()
+        .map(|indexer_id| {
+            let zone_opt = indexer_id_to_availability_zones
+                .get(indexer_id)
+                .map(String::as_str);
+            let num_zones = zone_ords.len();
+            *zone_ords.entry(zone_opt).or_insert(num_zones)
+        })
+        .collect();
+    problem.set_indexer_zones(indexer_zones);
+
     for source in sources {
         if let Some(source_ord) = populate_problem(source, &mut problem) {
             let registered_source_ord = id_to_ord_map.add_source(source);
@@ -652,6 +671,7 @@ mod tests {
         let indexing_plan = build_physical_indexing_plan(
             &[source_0, source_1, source_2],
             &indexer_id_to_cpu_capacities,
+            &FnvHashMap::default(),
             None,
         );
         assert_eq!(indexing_plan.indexing_tasks_per_indexer().len(), 2);
@@ -687,6 +707,44 @@ mod tests {
         assert_eq!(&node2_plan[3].source_id, &source_uid2.source_id);
     }
 
+    #[test]
+    fn test_build_physical_plan_across_availability_zones() {
+        let indexer1 = "indexer1".to_string();
+        let indexer2 = "indexer2".to_string();
+        let source_uid = source_id();
+        let source = SourceToSchedule {
+            source_uid: source_uid.clone(),
+            source_type: SourceToScheduleType::NonSharded {
+                num_pipelines: 2,
+                load_per_pipeline: NonZeroU32::new(3_200).unwrap(),
+            },
+        };
+        let mut indexer_id_to_cpu_capacities = FnvHashMap::default();
+        indexer_id_to_cpu_capacities.insert(indexer1.clone(), mcpu(16_000));
+        indexer_id_to_cpu_capacities.insert(indexer2.clone(), mcpu(16_000));
+
+        let mut indexer_id_to_availability_zones = FnvHashMap::default();
+        indexer_id_to_availability_zones.insert(indexer1.clone(), "zone-a".to_string());
+        indexer_id_to_availability_zones.insert(indexer2.clone(), "zone-b".to_string());
+
+        let indexing_plan = build_physical_indexing_plan(
+            &[source],
+            &indexer_id_to_cpu_capacities,
+            &indexer_id_to_availability_zones,
+            None,
+        );
+        assert_eq!(indexing_plan.indexing_tasks_per_indexer().len(), 2);
+
+        // The pipelines are spread across zones instead of being scheduled on the same node.
+        let node1_plan = indexing_plan.indexer(&indexer1).unwrap();
+        assert_eq!(node1_plan.len(), 1);
+        assert_eq!(&node1_plan[0].source_id, &source_uid.source_id);
+
+        let node2_plan = indexing_plan.indexer(&indexer2).unwrap();
+        assert_eq!(node2_plan.len(), 1);
+        assert_eq!(&node2_plan[0].source_id, &source_uid.source_id);
+    }
+
     #[tokio::test]
     async fn test_build_physical_indexing_plan_with_not_enough_indexers() {
         let source_uid1 = source_id();
@@ -704,7 +762,12 @@ mod tests {
         {
             indexer_max_loads.insert(indexer1.clone(), mcpu(1_999));
             // This test what happens when there isn't enough capacity on the cluster.
-            let physical_plan = build_physical_indexing_plan(&sources, &indexer_max_loads, None);
+            let physical_plan = build_physical_indexing_plan(
+                &sources,
+                &indexer_max_loads,
+                &FnvHashMap::default(),
+                None,
+            );
             assert_eq!(physical_plan.indexing_tasks_per_indexer().len(), 1);
             let expected_tasks = physical_plan.indexer(&indexer1).unwrap();
             assert_eq!(expected_tasks.len(), 2);
@@ -713,7 +776,12 @@ mod tests {
         {
             indexer_max_loads.insert(indexer1.clone(), mcpu(2_000));
             // This test what happens when there isn't enough capacity on the cluster.
-            let physical_plan = build_physical_indexing_plan(&sources, &indexer_max_loads, None);
+            let physical_plan = build_physical_indexing_plan(
+                &sources,
+                &indexer_max_loads,
+                &FnvHashMap::default(),
+                None,
+            );
             assert_eq!(physical_plan.indexing_tasks_per_indexer().len(), 1);
             let expected_tasks = physical_plan.indexer(&indexer1).unwrap();
             assert_eq!(expected_tasks.len(), 2);
@@ -778,6 +846,7 @@ mod tests {
         let new_plan = build_physical_indexing_plan(
             &sources,
             &indexer_id_to_cpu_capacities,
+            &FnvHashMap::default(),
             Some(&indexing_plan),
         );
         let indexing_tasks = new_plan.indexer("node1").unwrap();
@@ -816,6 +885,7 @@ mod tests {
         let new_plan = build_physical_indexing_plan(
             &sources,
             &indexer_id_to_cpu_capacities,
+            &FnvHashMap::default(),
             Some(&indexing_plan),
         );
         let mut indexing_tasks = new_plan.indexer(NODE).unwrap().to_vec();
@@ -1004,7 +1074,12 @@ mod tests {
         ];
         let mut capacities = FnvHashMap::default();
         capacities.insert("indexer-1".to_string(), CpuCapacity::from_cpu_millis(8000));
-        build_physical_indexing_plan(&sources_to_schedule, &capacities, None);
+        build_physical_indexing_plan(
+            &sources_to_schedule,
+            &capacities,
+            &FnvHashMap::default(),
+            None,
+        );
     }
 
     #[test]
@@ -19,7 +19,7 @@
 
 use std::cmp::Reverse;
 use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::collections::{BTreeMap, HashMap};
 
 use quickwit_proto::indexing::CpuCapacity;
 use tracing::warn;
@@ -200,12 +200,13 @@ fn attempt_place_unassigned_shards(
     partial_solution: &SchedulingSolution,
 ) -> Result<SchedulingSolution, NotEnoughCapacity> {
     let mut solution = partial_solution.clone();
-    let mut indexers_with_most_available_capacity: BinaryHeap<(CpuCapacity, IndexerOrd)> =
+    let mut indexer_available_capacities: Vec<CpuCapacity> =
         compute_indexer_available_capacity(problem, &solution);
     for source in unassigned_shards {
         place_unassigned_shards_single_source(
             source,
-            &mut indexers_with_most_available_capacity,
+            problem,
+            &mut indexer_available_capacities,
             &mut solution,
         )?;
     }
@@ -223,6 +224,11 @@ fn attempt_place_unassigned_shards(
 // We then try to place as many shards as possible in the node with the
 // highest available capacity.
 //
+// When indexers span several availability zones, we first pick the zone hosting the fewest shards
+// of the source, and we do not place more than `num_shards / num_zones` (rounded up) shards of a
+// source in a zone as long as other zones have some room left. This way, a zone outage does not
+// stop the indexing of a source altogether.
+//
 // If this algorithm fails to place all remaining shards, we inflate the node capacities by 20%
 // in the scheduling problem and start from the beginning.
 #[must_use]
@@ -289,24 +295,57 @@ struct NotEnoughCapacity;
 /// amongst the node with their given node capacity.
 fn place_unassigned_shards_single_source(
     source: &Source,
-    indexer_available_capacities: &mut BinaryHeap<(CpuCapacity, IndexerOrd)>,
+    problem: &SchedulingProblem,
+    indexer_available_capacities: &mut [CpuCapacity],
     solution: &mut SchedulingSolution,
 ) -> Result<(), NotEnoughCapacity> {
+    let num_zones = problem.num_zones();
+    let mut num_shards_per_zone: Vec<u32> = vec![0; num_zones];
+
+    for indexer_assignment in &solution.indexer_assignments {
+        let zone_ord = problem.indexer_zone(indexer_assignment.indexer_ord);
+        num_shards_per_zone[zone_ord] += indexer_assignment.num_shards(source.source_ord);
+    }
+    let total_num_shards = num_shards_per_zone.iter().sum::<u32>() + source.num_shards;
+    let max_num_shards_per_zone = total_num_shards.div_ceil(num_zones as u32);
+
     let mut num_shards = source.num_shards;
     while num_shards > 0 {
-        let mut node_with_most_capacity = indexer_available_capacities.peek_mut().unwrap();
-        let node_id = node_with_most_capacity.1;
-        let available_capacity = &mut node_with_most_capacity.0;
-        let num_placable_shards = available_capacity.cpu_millis() / source.load_per_shard;
-        let num_shards_to_place = num_placable_shards.min(num_shards);
-        if num_shards_to_place == 0 {
+        // We pick the node with the most available capacity in the zone hosting the fewest shards
+        // of the source.
+        let Some((indexer_ord, num_placable_shards)) = indexer_available_capacities
+            .iter()
+            .enumerate()
+            .map(|(indexer_ord, available_capacity)| {
+                let num_placable_shards = available_capacity.cpu_millis() / source.load_per_shard;
+                (indexer_ord, num_placable_shards)
+            })
+            .filter(|(_, num_placable_shards)| *num_placable_shards > 0)
+            .min_by_key(|&(indexer_ord, _)| {
+                let zone_ord = problem.indexer_zone(indexer_ord);
+                (
+                    num_shards_per_zone[zone_ord],
+                    Reverse(indexer_available_capacities[indexer_ord]),
+                    Reverse(indexer_ord),
+                )
+            })
+        else {
             return Err(NotEnoughCapacity);
-        }
+        };
+        let zone_ord = problem.indexer_zone(indexer_ord);
+        let num_shards_left_in_zone = max_num_shards_per_zone
+            .saturating_sub(num_shards_per_zone[zone_ord])
+            .max(1);
+        let num_shards_to_place = num_placable_shards
+            .min(num_shards)
+            .min(num_shards_left_in_zone);
         // TODO take in account colocation.
         // Update the solution, the shard load, and the number of shards to place.
-        solution.indexer_assignments[node_id].add_shards(source.source_ord, num_shards_to_place);
-        *available_capacity = *available_capacity
+        solution.indexer_assignments[indexer_ord]
+            .add_shards(source.source_ord, num_shards_to_place);
+        indexer_available_capacities[indexer_ord] = indexer_available_capacities[indexer_ord]
             - CpuCapacity::from_cpu_millis(num_shards_to_place * source.load_per_shard.get());
+        num_shards_per_zone[zone_ord] += num_shards_to_place;
         num_shards -= num_shards_to_place;
     }
     Ok(())
@@ -337,24 +376,21 @@ fn compute_unassigned_sources(
     unassigned_sources.into_values().collect()
 }
 
-/// Builds a BinaryHeap with the different indexer capacities.
+/// Builds a vector with the available capacity of each indexer.
 ///
 /// Panics if one of the indexer is over-assigned.
 fn compute_indexer_available_capacity(
     problem: &SchedulingProblem,
     solution: &SchedulingSolution,
-) -> BinaryHeap<(CpuCapacity, IndexerOrd)> {
-    let mut indexer_available_capacity: BinaryHeap<(CpuCapacity, IndexerOrd)> =
-        BinaryHeap::with_capacity(problem.num_indexers());
+) -> Vec<CpuCapacity> {
+    let mut indexer_available_capacities: Vec<CpuCapacity> =
+        Vec::with_capacity(problem.num_indexers());
     for indexer_assignment in &solution.indexer_assignments {
         let available_capacity: i32 = indexer_assignment.indexer_available_capacity(problem);
         assert!(available_capacity >= 0i32);
-        indexer_available_capacity.push((
-            CpuCapacity::from_cpu_millis(available_capacity as u32),
-            indexer_assignment.indexer_ord,
-        ));
+        indexer_available_capacities.push(CpuCapacity::from_cpu_millis(available_capacity as u32));
     }
-    indexer_available_capacity
+    indexer_available_capacities
 }
 #[cfg(test)]
 mod tests {
@@ -527,6 +563,24 @@ mod tests {
         assert_eq!(solution.indexer_assignments[0].num_shards(0), 4);
     }
 
+    #[test]
+    fn test_place_unassigned_shards_across_zones() {
+        let mut problem = SchedulingProblem::with_indexer_cpu_capacities(vec![
+            mcpu(8_000),
+            mcpu(8_000),
+            mcpu(8_000),
+            mcpu(8_000),
+        ]);
+        problem.set_indexer_zones(vec![0, 0, 1, 1]);
+        problem.add_source(4, NonZeroU32::new(1_000).unwrap());
+        let partial_solution = problem.new_solution();
+        let solution = place_unassigned_shards(problem, partial_solution);
+        assert_eq!(solution.indexer_assignments[0].num_shards(0), 0);
+        assert_eq!(solution.indexer_assignments[1].num_shards(0), 2);
+        assert_eq!(solution.indexer_assignments[2].num_shards(0), 0);
+        assert_eq!(solution.indexer_assignments[3].num_shards(0), 2);
+    }
+
     #[test]
     fn test_place_unassigned_shards_reach_capacity() {
         let mut problem =
@@ -25,6 +25,7 @@ use quickwit_proto::indexing::CpuCapacity;
 
 pub type SourceOrd = u32;
 pub type IndexerOrd = usize;
+pub type ZoneOrd = usize;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Source {
@@ -37,6 +38,7 @@ pub struct Source {
 pub struct SchedulingProblem {
     sources: Vec<Source>,
     indexer_cpu_capacities: Vec<CpuCapacity>,
+    indexer_zones: Vec<ZoneOrd>,
 }
 
 impl SchedulingProblem {
@@ -51,12 +53,36 @@ impl SchedulingProblem {
         assert!(indexer_cpu_capacities
             .iter()
             .all(|cpu_capacity| cpu_capacity.cpu_millis() > 0));
+        let indexer_zones = vec![0; indexer_cpu_capacities.len()];
         SchedulingProblem {
             sources: Vec::new(),
             indexer_cpu_capacities,
+            indexer_zones,
         }
     }
 
+    /// Sets the availability zone of each indexer. By default, all the indexers belong to the
+    /// same zone.
+    ///
+    /// Panics if the number of zones does not match the number of indexers.
+    pub fn set_indexer_zones(&mut self, indexer_zones: Vec<ZoneOrd>) {
+        assert_eq!(indexer_zones.len(), self.indexer_cpu_capacities.len());
+        self.indexer_zones = indexer_zones;
+    }
+
+    pub fn indexer_zone(&self, indexer_ord: IndexerOrd) -> ZoneOrd {
+        self.indexer_zones[indexer_ord]
+    }
+
+    pub fn num_zones(&self) -> usize {
+        self.indexer_zones
+            .iter()
+            .copied()
+            .max()
+            .map(|max_zone_ord| max_zone_ord + 1)
+            .unwrap_or(1)
+    }
+
     pub fn new_solution(&self) -> SchedulingSolution {
         SchedulingSolution::with_num_indexers(self.indexer_cpu_capacities.len())
     }
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::future::Future;
 use std::time::Duration;
@@ -26,29 +26,36 @@ use fnv::{FnvHashMap, FnvHashSet};
 use itertools::Itertools;
 use quickwit_common::pretty::PrettySample;
 use quickwit_common::Progress;
-use quickwit_ingest::{IngesterPool, LocalShardsUpdate};
+use quickwit_ingest::{today, IngestVolumeUpdate, IngesterPool, LocalShardsUpdate};
 use quickwit_proto::control_plane::{
     AdviseResetShardsRequest, AdviseResetShardsResponse, ControlPlaneResult,
     GetOrCreateOpenShardsFailure, GetOrCreateOpenShardsFailureReason, GetOrCreateOpenShardsRequest,
-    GetOrCreateOpenShardsResponse, GetOrCreateOpenShardsSuccess,
+    GetOrCreateOpenShardsResponse, GetOrCreateOpenShardsSuccess, HandoffShardsRequest,
+    HandoffShardsResponse, IngestQuotaEntry,
 };
 use quickwit_proto::ingest::ingester::{
-    CloseShardsRequest, IngesterService, InitShardsRequest, RetainShardsForSource,
-    RetainShardsRequest,
+    CloseShardsRequest, GetReplicaPositionsRequest, IngesterService, InitShardsRequest,
+    RetainShardsForSource, RetainShardsRequest,
 };
 use quickwit_proto::ingest::{IngestV2Error, Shard, ShardIdPosition, ShardIdPositions, ShardIds};
 use quickwit_proto::metastore;
 use quickwit_proto::metastore::{MetastoreService, MetastoreServiceClient};
-use quickwit_proto::types::{IndexUid, NodeId, ShardId, SourceUid};
+use quickwit_proto::types::{IndexUid, NodeId, Position, QueueId, ShardId, SourceUid};
 use serde::{Deserialize, Serialize};
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 use ulid::Ulid;
 
+use crate::ingest::ingest_quotas::{
+    is_daily_quota_exceeded, max_num_shards, shard_throughput_limit, IngestVolumes,
+};
 use crate::ingest::wait_handle::WaitHandle;
 use crate::model::{ControlPlaneModel, ScalingMode, ShardEntry, ShardStats};
 
 const MAX_SHARD_INGESTION_THROUGHPUT_MIB_PER_SEC: f32 = 5.;
 
+const MAX_SHARD_INGESTION_THROUGHPUT_BYTES_PER_SEC: u64 =
+    MAX_SHARD_INGESTION_THROUGHPUT_MIB_PER_SEC as u64 * 1024 * 1024;
+
 /// Threshold in MiB/s above which we increase the number of shards.
 const SCALE_UP_SHARDS_THRESHOLD_MIB_PER_SEC: f32 =
     MAX_SHARD_INGESTION_THROUGHPUT_MIB_PER_SEC * 8. / 10.;
@@ -83,6 +90,9 @@ pub struct IngestController {
     ingester_pool: IngesterPool,
     metastore: MetastoreServiceClient,
     replication_factor: usize,
+    ingest_volumes: IngestVolumes,
+    // Availability zones of the ingesters that advertise one.
+    availability_zones: HashMap<NodeId, String>,
     pub stats: IngestControllerStats,
 }
 
@@ -106,10 +116,241 @@ impl IngestController {
             metastore,
             ingester_pool,
             replication_factor,
+            ingest_volumes: IngestVolumes::default(),
+            availability_zones: HashMap::new(),
             stats: IngestControllerStats::default(),
         }
     }
 
+    /// Records the availability zone of an ingester that joined the cluster.
+    pub(crate) fn handle_ingester_joined(
+        &mut self,
+        ingester: NodeId,
+        availability_zone_opt: Option<String>,
+    ) {
+        if let Some(availability_zone) = availability_zone_opt {
+            self.availability_zones.insert(ingester, availability_zone);
+        } else {
+            self.availability_zones.remove(&ingester);
+        }
+    }
+
+    /// Forgets the availability zone of an ingester that left the cluster.
+    pub(crate) fn handle_ingester_left(&mut self, ingester: &NodeId) {
+        self.availability_zones.remove(ingester);
+    }
+
+    /// Promotes one of the followers of the shards led by an ingester that left the cluster so
+    /// that these shards remain available for ingestion.
+    ///
+    /// Only the most advanced followers of a shard, according to the replication positions of
+    /// their replicas, are candidates: promoting a follower that lags behind would lose the
+    /// records it did not receive. Since a record is acknowledged once a write quorum of replicas
+    /// persisted it, the most advanced follower is only known for sure when at least
+    /// `replication_factor - write_quorum + 1` followers report their position. Otherwise, the
+    /// shard is not promoted and remains unavailable. The candidates are tried in order until one
+    /// of them accepts to become the new leader of the shard. The new leaders and followers are
+    /// then recorded in the metastore and the model.
+    pub(crate) async fn promote_shards(
+        &mut self,
+        former_leader_id: &NodeId,
+        model: &mut ControlPlaneModel,
+        progress: &Progress,
+    ) {
+        let shards_to_promote: Vec<(SourceUid, Shard)> = model
+            .all_shards_with_source()
+            .flat_map(|(source_uid, shard_entries)| {
+                shard_entries
+                    .filter(|shard_entry| {
+                        !shard_entry.is_closed()
+                            && *former_leader_id == shard_entry.leader_id
+                            && shard_entry.follower_id.is_some()
+                    })
+                    .map(move |shard_entry| (source_uid.clone(), shard_entry.shard.clone()))
+            })
+            .collect();
+
+        if shards_to_promote.is_empty() {
+            return;
+        }
+        let replica_positions = self
+            .get_replica_positions(&shards_to_promote, progress)
+            .await;
+        let mut promoted_shards: Vec<(SourceUid, Shard)> = Vec::new();
+
+        for (source_uid, shard) in shards_to_promote {
+            let queue_id = shard.queue_id();
+            let follower_ids: Vec<NodeId> = shard.follower_ids().collect();
+
+            let follower_positions: Vec<(&NodeId, &Position)> = follower_ids
+                .iter()
+                .filter_map(|follower_id| {
+                    let replica_position =
+                        replica_positions.get(&(follower_id.clone(), queue_id.clone()))?;
+                    Some((follower_id, replica_position))
+                })
+                .collect();
+            let min_num_follower_positions = min_num_follower_positions(follower_ids.len());
+
+            if follower_positions.len() < min_num_follower_positions {
+                warn!(
+                    "failed to promote shard `{queue_id}`: {} out of {} follower(s) reported the \
+                     position of its replica, at least {min_num_follower_positions} required",
+                    follower_positions.len(),
+                    follower_ids.len(),
+                );
+                continue;
+            }
+            let max_replica_position = follower_positions
+                .iter()
+                .map(|(_, replica_position)| *replica_position)
+                .max()
+                .expect("at least one follower should have reported its position");
+            let candidate_ids: Vec<&NodeId> = follower_positions
+                .iter()
+                .filter(|(_, replica_position)| *replica_position == max_replica_position)
+                .map(|(follower_id, _)| *follower_id)
+                .collect();
+
+            for candidate_id in candidate_ids {
+                let Some(mut candidate) = self.ingester_pool.get(candidate_id) else {
+                    continue;
+                };
+                let new_follower_ids: Vec<NodeId> = follower_ids
+                    .iter()
+                    .filter(|follower_id| *follower_id != candidate_id)
+                    .cloned()
+                    .collect();
+                let mut promoted_shard = shard.clone();
+                let mut new_follower_ids_iter = new_follower_ids.iter().map(ToString::to_string);
+                promoted_shard.leader_id = candidate_id.to_string();
+                promoted_shard.follower_id = new_follower_ids_iter.next();
+                promoted_shard.additional_follower_ids = new_follower_ids_iter.collect();
+
+                let init_shards_request = InitShardsRequest {
+                    shards: Vec::new(),
+                    promoted_shards: vec![promoted_shard.clone()],
+                    max_shard_throughputs: HashMap::new(),
+                };
+                if let Err(error) = progress
+                    .protect_future(candidate.init_shards(init_shards_request))
+                    .await
+                {
+                    warn!(
+                        "failed to promote shard `{queue_id}` on ingester `{candidate_id}`: \
+                         {error}"
+                    );
+                    continue;
+                }
+                info!(
+                    "promoted shard `{queue_id}` on ingester `{candidate_id}` after its leader \
+                     `{former_leader_id}` left the cluster"
+                );
+                model.promote_shard(
+                    &source_uid,
+                    shard.shard_id(),
+                    candidate_id.clone(),
+                    new_follower_ids,
+                );
+                promoted_shards.push((source_uid, promoted_shard));
+                break;
+            }
+        }
+        if promoted_shards.is_empty() {
+            return;
+        }
+        let open_shards_subrequests = promoted_shards
+            .iter()
+            .enumerate()
+            .map(
+                |(subrequest_id, (source_uid, shard))| metastore::OpenShardsSubrequest {
+                    subrequest_id: subrequest_id as u32,
+                    index_uid: Some(source_uid.index_uid.clone()),
+                    source_id: source_uid.source_id.clone(),
+                    shard_id: shard.shard_id.clone(),
+                    leader_id: shard.leader_id.clone(),
+                    follower_id: shard.follower_id.clone(),
+                    additional_follower_ids: shard.additional_follower_ids.clone(),
+                    reassign: true,
+                },
+            )
+            .collect();
+        let open_shards_request = metastore::OpenShardsRequest {
+            subrequests: open_shards_subrequests,
+        };
+        // The promoted shards are already serving requests. If the metastore cannot be updated,
+        // indexers keep reading from them by failing over to the followers of the former leader.
+        if let Err(error) = progress
+            .protect_future(self.metastore.open_shards(open_shards_request))
+            .await
+        {
+            error!(
+                "failed to record the new leaders of {} promoted shard(s) in the metastore: \
+                 {error}",
+                promoted_shards.len()
+            );
+        }
+    }
+
+    /// Asks the followers of the shards to promote for the replication positions of their
+    /// replicas. Followers that cannot be reached are ignored, which may prevent the promotion of
+    /// their shards.
+    async fn get_replica_positions(
+        &self,
+        shards_to_promote: &[(SourceUid, Shard)],
+        progress: &Progress,
+    ) -> HashMap<(NodeId, QueueId), Position> {
+        let mut per_follower_shard_ids: HashMap<NodeId, HashMap<&SourceUid, Vec<ShardId>>> =
+            HashMap::new();
+
+        for (source_uid, shard) in shards_to_promote {
+            for follower_id in shard.follower_ids() {
+                per_follower_shard_ids
+                    .entry(follower_id)
+                    .or_default()
+                    .entry(source_uid)
+                    .or_default()
+                    .push(shard.shard_id().clone());
+            }
+        }
+        let mut replica_positions = HashMap::new();
+
+        for (follower_id, per_source_shard_ids) in per_follower_shard_ids {
+            let Some(mut follower) = self.ingester_pool.get(&follower_id) else {
+                continue;
+            };
+            let shards = per_source_shard_ids
+                .into_iter()
+                .map(|(source_uid, shard_ids)| ShardIds {
+                    index_uid: Some(source_uid.index_uid.clone()),
+                    source_id: source_uid.source_id.clone(),
+                    shard_ids,
+                })
+                .collect();
+            let get_replica_positions_request = GetReplicaPositionsRequest { shards };
+
+            match progress
+                .protect_future(follower.get_replica_positions(get_replica_positions_request))
+                .await
+            {
+                Ok(get_replica_positions_response) => {
+                    for replica_position in get_replica_positions_response.replica_positions {
+                        replica_positions.insert(
+                            (follower_id.clone(), replica_position.queue_id()),
+                            replica_position.replication_position_inclusive().clone(),
+                        );
+                    }
+                }
+                Err(error) => {
+                    warn!(
+                        "failed to get the replica positions of ingester `{follower_id}`: {error}"
+                    );
+                }
+            }
+        }
+        replica_positions
+    }
+
     /// Sends a retain shard request to the given list of ingesters.
     ///
     /// If the request fails, we just log an error.
@@ -219,6 +460,161 @@ impl IngestController {
         }
     }
 
+    /// Records the volume of data ingested today into an index by a leader. If the daily ingest
+    /// quota of the index is exceeded, all its open shards are closed.
+    pub(crate) async fn handle_ingest_volume_update(
+        &mut self,
+        ingest_volume_update: IngestVolumeUpdate,
+        model: &mut ControlPlaneModel,
+        progress: &Progress,
+    ) {
+        let index_uid = ingest_volume_update.index_uid;
+
+        // Only the volumes of the indexes with a quota are tracked. This also prevents the updates
+        // broadcast by the leaders after an index is deleted from recording its volume again.
+        let Some(ingest_quota) = model.ingest_quota(&index_uid) else {
+            return;
+        };
+        self.ingest_volumes.record(
+            ingest_volume_update.leader_id,
+            index_uid.clone(),
+            ingest_volume_update.daily_ingest_volume,
+        );
+        let daily_ingest_volume = self.ingest_volumes.daily_ingest_volume(&index_uid, today());
+        let quota_exceeded = is_daily_quota_exceeded(ingest_quota, daily_ingest_volume);
+
+        let index_id = index_uid.index_id.as_str();
+        crate::metrics::CONTROL_PLANE_METRICS
+            .daily_ingest_volume_bytes
+            .with_label_values([index_id])
+            .set(daily_ingest_volume as i64);
+        crate::metrics::CONTROL_PLANE_METRICS
+            .ingest_quota_exceeded
+            .with_label_values([index_id])
+            .set(quota_exceeded as i64);
+
+        if quota_exceeded {
+            self.close_open_shards_for_index(&index_uid, model, progress)
+                .await;
+        }
+    }
+
+    /// Forgets the ingest volumes of a deleted index.
+    pub(crate) fn handle_index_deleted(&mut self, index_uid: &IndexUid) {
+        self.ingest_volumes.remove_index(index_uid);
+    }
+
+    /// Returns the maximum ingest throughput of the index in bytes per second, if it has a
+    /// throughput quota.
+    fn max_ingest_throughput(
+        &self,
+        index_uid: &IndexUid,
+        model: &ControlPlaneModel,
+    ) -> Option<u64> {
+        let ingest_quota = model.ingest_quota(index_uid)?;
+        ingest_quota
+            .max_ingest_throughput
+            .map(|max_ingest_throughput| max_ingest_throughput.as_u64())
+    }
+
+    /// Returns whether the volume of data ingested today into the index exceeds its daily quota.
+    fn is_daily_quota_exceeded(&self, index_uid: &IndexUid, model: &ControlPlaneModel) -> bool {
+        let Some(ingest_quota) = model.ingest_quota(index_uid) else {
+            return false;
+        };
+        let daily_ingest_volume = self.ingest_volumes.daily_ingest_volume(index_uid, today());
+        is_daily_quota_exceeded(ingest_quota, daily_ingest_volume)
+    }
+
+    /// Closes all the open shards of an index, for instance, when its daily ingest quota is
+    /// exceeded.
+    async fn close_open_shards_for_index(
+        &self,
+        index_uid: &IndexUid,
+        model: &mut ControlPlaneModel,
+        progress: &Progress,
+    ) {
+        let mut per_leader_shards: HashMap<NodeId, HashMap<SourceUid, Vec<ShardId>>> =
+            HashMap::new();
+
+        for shard_entry in model.list_shards_for_index(index_uid) {
+            if !shard_entry.is_open() {
+                continue;
+            }
+            let source_uid = SourceUid {
+                index_uid: index_uid.clone(),
+                source_id: shard_entry.source_id.clone(),
+            };
+            per_leader_shards
+                .entry(shard_entry.leader_id.clone().into())
+                .or_default()
+                .entry(source_uid)
+                .or_default()
+                .push(shard_entry.shard_id().clone());
+        }
+        if per_leader_shards.is_empty() {
+            return;
+        }
+        info!(
+            index_id=%index_uid.index_id,
+            "daily ingest quota exceeded, closing open shards"
+        );
+        for (leader_id, per_source_shard_ids) in per_leader_shards {
+            let Some(mut ingester) = self.ingester_pool.get(&leader_id) else {
+                warn!("failed to close shards: ingester `{leader_id}` is unavailable");
+                continue;
+            };
+            let shards = per_source_shard_ids
+                .iter()
+                .map(|(source_uid, shard_ids)| ShardIds {
+                    index_uid: source_uid.index_uid.clone().into(),
+                    source_id: source_uid.source_id.clone(),
+                    shard_ids: shard_ids.clone(),
+                })
+                .collect();
+            let close_shards_request = CloseShardsRequest { shards };
+
+            if let Err(error) = progress
+                .protect_future(ingester.close_shards(close_shards_request))
+                .await
+            {
+                warn!("failed to close shards on ingester `{leader_id}`: {error}");
+                continue;
+            }
+            for (source_uid, shard_ids) in per_source_shard_ids {
+                model.close_shards(&source_uid, &shard_ids);
+            }
+        }
+    }
+
+    /// Returns the state of the ingest quotas, for debugging purposes.
+    pub(crate) fn ingest_quota_entries(&self, model: &ControlPlaneModel) -> Vec<IngestQuotaEntry> {
+        let today = today();
+
+        model
+            .ingest_quotas()
+            .map(|(index_uid, ingest_quota)| {
+                let daily_ingest_volume = self.ingest_volumes.daily_ingest_volume(index_uid, today);
+                let num_open_shards = model
+                    .list_shards_for_index(index_uid)
+                    .filter(|shard_entry| shard_entry.is_open())
+                    .count();
+                IngestQuotaEntry {
+                    index_uid: index_uid.to_string(),
+                    daily_ingest_volume_bytes: daily_ingest_volume,
+                    max_daily_ingest_volume_bytes: ingest_quota
+                        .max_daily_ingest_volume
+                        .map(|max_daily_ingest_volume| max_daily_ingest_volume.as_u64()),
+                    max_ingest_throughput_bytes: ingest_quota
+                        .max_ingest_throughput
+                        .map(|max_ingest_throughput| max_ingest_throughput.as_u64()),
+                    num_open_shards: num_open_shards as u32,
+                    quota_exceeded: is_daily_quota_exceeded(ingest_quota, daily_ingest_volume),
+                }
+            })
+            .collect()
+    }
+
     fn handle_unavailable_leaders(
         &self,
         unavailable_leaders: &FnvHashSet<NodeId>,
@@ -275,6 +671,16 @@ impl IngestController {
                 get_or_create_open_shards_failures.push(get_or_create_open_shards_failure);
                 continue;
             };
+            if self.is_daily_quota_exceeded(&index_uid, model) {
+                let get_or_create_open_shards_failure = GetOrCreateOpenShardsFailure {
+                    subrequest_id: get_open_shards_subrequest.subrequest_id,
+                    index_id: get_open_shards_subrequest.index_id,
+                    source_id: get_open_shards_subrequest.source_id,
+                    reason: GetOrCreateOpenShardsFailureReason::QuotaExceeded as i32,
+                };
+                get_or_create_open_shards_failures.push(get_or_create_open_shards_failure);
+                continue;
+            }
             let Some(open_shard_entries) = model.find_open_shards(
                 &index_uid,
                 &get_open_shards_subrequest.source_id,
@@ -294,11 +700,16 @@ impl IngestController {
                     .into_iter()
                     .map(|shard_entry| shard_entry.shard)
                     .collect();
+                let doc_mapping_json =
+                    model.doc_mapping_json(&index_uid, &get_open_shards_subrequest.source_id);
+                let max_ingest_throughput = self.max_ingest_throughput(&index_uid, model);
                 let get_or_create_open_shards_success = GetOrCreateOpenShardsSuccess {
                     subrequest_id: get_open_shards_subrequest.subrequest_id,
                     index_uid: index_uid.into(),
                     source_id: get_open_shards_subrequest.source_id,
                     open_shards,
+                    doc_mapping_json,
+                    max_ingest_throughput,
                 };
                 get_or_create_open_shards_successes.push(get_or_create_open_shards_success);
             } else {
@@ -311,6 +722,8 @@ impl IngestController {
                     // These attributes will be overwritten in the next stage.
                     leader_id: "".to_string(),
                     follower_id: None,
+                    additional_follower_ids: Vec::new(),
+                    reassign: false,
                 };
                 open_shards_subrequests.push(open_shards_subrequest);
             }
@@ -319,12 +732,14 @@ impl IngestController {
             if let Some(leader_follower_pairs) =
                 self.allocate_shards(open_shards_subrequests.len(), &unavailable_leaders, model)
             {
-                for (open_shards_subrequest, (leader_id, follower_opt)) in open_shards_subrequests
+                for (open_shards_subrequest, (leader_id, follower_ids)) in open_shards_subrequests
                     .iter_mut()
                     .zip(leader_follower_pairs)
                 {
+                    let mut follower_ids = follower_ids.into_iter().map(String::from);
                     open_shards_subrequest.leader_id = leader_id.into();
-                    open_shards_subrequest.follower_id = follower_opt.map(Into::into);
+                    open_shards_subrequest.follower_id = follower_ids.next();
+                    open_shards_subrequest.additional_follower_ids = follower_ids.collect();
                 }
                 let open_shards_request = metastore::OpenShardsRequest {
                     subrequests: open_shards_subrequests,
@@ -334,7 +749,9 @@ impl IngestController {
                     .await?;
 
                 // TODO: Handle failures.
-                let _ = self.init_shards(&open_shards_response, progress).await;
+                let _ = self
+                    .init_shards(&open_shards_response, model, progress)
+                    .await;
 
                 for open_shards_subresponse in open_shards_response.subresponses {
                     let index_uid: IndexUid = open_shards_subresponse.index_uid().clone();
@@ -351,11 +768,15 @@ impl IngestController {
                             .into_iter()
                             .map(|shard_entry| shard_entry.shard)
                             .collect();
+                        let doc_mapping_json = model.doc_mapping_json(&index_uid, &source_id);
+                        let max_ingest_throughput = self.max_ingest_throughput(&index_uid, model);
                         let get_or_create_open_shards_success = GetOrCreateOpenShardsSuccess {
                             subrequest_id: open_shards_subresponse.subrequest_id,
                             index_uid: index_uid.into(),
                             source_id: open_shards_subresponse.source_id,
                             open_shards,
+                            doc_mapping_json,
+                            max_ingest_throughput,
                         };
                         get_or_create_open_shards_successes.push(get_or_create_open_shards_success);
                     }
@@ -385,7 +806,7 @@ impl IngestController {
         num_shards_to_allocate: usize,
         unavailable_leaders: &FnvHashSet<NodeId>,
         model: &ControlPlaneModel,
-    ) -> Option<Vec<(NodeId, Option<NodeId>)>> {
+    ) -> Option<Vec<(NodeId, Vec<NodeId>)>> {
         let ingesters: Vec<NodeId> = self
             .ingester_pool
             .keys()
@@ -393,6 +814,7 @@ impl IngestController {
             .filter(|ingester| !unavailable_leaders.contains(ingester))
             .sorted_by(|left, right| left.cmp(right))
             .collect();
+        let ingesters = self.interleave_ingesters_by_availability_zone(ingesters);
 
         let num_ingesters = ingesters.len();
 
@@ -426,7 +848,7 @@ impl IngestController {
         let max_num_shards_to_allocate_per_node = num_open_shards_target / num_ingesters;
 
         // Allocate at most `max_num_shards_to_allocate_per_node` shards to each ingester.
-        for (leader_id, follower_id) in ingesters.iter().zip(ingesters.iter().cycle().skip(1)) {
+        for (leader_idx, leader_id) in ingesters.iter().enumerate() {
             if num_remaining_shards_to_allocate == 0 {
                 break;
             }
@@ -443,42 +865,120 @@ impl IngestController {
                 num_remaining_shards_to_allocate -= 1;
 
                 let leader = leader_id.clone();
-                let mut follower_opt = None;
-
-                if self.replication_factor > 1 {
-                    follower_opt = Some(follower_id.clone());
-                }
-                leader_follower_pairs.push((leader, follower_opt));
+                let followers = self.select_followers(&ingesters, leader_idx);
+                leader_follower_pairs.push((leader, followers));
             }
         }
         // Allocate remaining shards one by one.
-        for (leader_id, follower_id) in ingesters.iter().zip(ingesters.iter().cycle().skip(1)) {
+        for (leader_idx, leader_id) in ingesters.iter().enumerate() {
             if num_remaining_shards_to_allocate == 0 {
                 break;
             }
             num_remaining_shards_to_allocate -= 1;
 
             let leader = leader_id.clone();
-            let mut follower_opt = None;
+            let followers = self.select_followers(&ingesters, leader_idx);
+            leader_follower_pairs.push((leader, followers));
+        }
+        Some(leader_follower_pairs)
+    }
+
+    /// Reorders the ingesters so that consecutive ingesters belong to different availability
+    /// zones whenever possible. Ingesters of the same zone keep their relative order, and ingesters
+    /// without an availability zone are treated as belonging to the same zone.
+    fn interleave_ingesters_by_availability_zone(&self, ingesters: Vec<NodeId>) -> Vec<NodeId> {
+        let num_ingesters = ingesters.len();
+        let mut ingesters_per_zone: BTreeMap<Option<&String>, VecDeque<NodeId>> = BTreeMap::new();
+
+        for ingester in ingesters {
+            ingesters_per_zone
+                .entry(self.availability_zones.get(&ingester))
+                .or_default()
+                .push_back(ingester);
+        }
+        let mut interleaved_ingesters = Vec::with_capacity(num_ingesters);
 
-            if self.replication_factor > 1 {
-                follower_opt = Some(follower_id.clone());
+        while interleaved_ingesters.len() < num_ingesters {
+            for zone_ingesters in ingesters_per_zone.values_mut() {
+                if let Some(ingester) = zone_ingesters.pop_front() {
+                    interleaved_ingesters.push(ingester);
+                }
             }
-            leader_follower_pairs.push((leader, follower_opt));
         }
-        Some(leader_follower_pairs)
+        interleaved_ingesters
+    }
+
+    /// Selects the `replication_factor - 1` followers of a shard led by the ingester at
+    /// `leader_idx`. The followers are the next ingesters located in an availability zone that
+    /// neither the leader nor the other followers belong to. When there are not enough such
+    /// ingesters, for instance if the zones of the ingesters are unknown, the followers are simply
+    /// the next ingesters.
+    fn select_followers(&self, ingesters: &[NodeId], leader_idx: usize) -> Vec<NodeId> {
+        let num_followers = self.replication_factor.saturating_sub(1);
+        let mut followers: Vec<NodeId> = Vec::with_capacity(num_followers);
+
+        if num_followers == 0 {
+            return followers;
+        }
+        let num_ingesters = ingesters.len();
+        let candidates = || {
+            ingesters
+                .iter()
+                .cycle()
+                .skip(leader_idx + 1)
+                .take(num_ingesters - 1)
+        };
+        if let Some(leader_zone) = self.availability_zones.get(&ingesters[leader_idx]) {
+            let mut used_zones: HashSet<&String> = HashSet::from([leader_zone]);
+
+            for candidate in candidates() {
+                if followers.len() == num_followers {
+                    return followers;
+                }
+                if let Some(zone) = self.availability_zones.get(candidate) {
+                    if used_zones.insert(zone) {
+                        followers.push(candidate.clone());
+                    }
+                }
+            }
+        }
+        for candidate in candidates() {
+            if followers.len() == num_followers {
+                break;
+            }
+            if !followers.contains(candidate) {
+                followers.push(candidate.clone());
+            }
+        }
+        followers
     }
 
-    /// Calls init shards on the leaders hosting newly opened shards.
+    /// Calls init shards on the leaders hosting newly opened shards. The throughput of the shards
+    /// of the indexes whose throughput quota is lower than the throughput of a single shard is
+    /// limited to that quota.
     // TODO: Return partial failures instead of failing the whole request.
     async fn init_shards(
         &self,
         open_shards_response: &metastore::OpenShardsResponse,
+        model: &ControlPlaneModel,
         progress: &Progress,
     ) -> Result<(), IngestV2Error> {
         let mut per_leader_opened_shards: FnvHashMap<&String, Vec<Shard>> = FnvHashMap::default();
+        let mut max_shard_throughputs: HashMap<String, u64> = HashMap::new();
 
         for subresponse in &open_shards_response.subresponses {
+            let index_uid = subresponse.index_uid();
+
+            if let Some(max_shard_throughput) =
+                model.ingest_quota(index_uid).and_then(|ingest_quota| {
+                    shard_throughput_limit(
+                        ingest_quota,
+                        MAX_SHARD_INGESTION_THROUGHPUT_BYTES_PER_SEC,
+                    )
+                })
+            {
+                max_shard_throughputs.insert(index_uid.to_string(), max_shard_throughput);
+            }
             for shard in &subresponse.opened_shards {
                 per_leader_opened_shards
                     .entry(&shard.leader_id)
@@ -488,7 +988,19 @@ impl IngestController {
         }
         // TODO: Init shards in parallel.
         for (leader_id, shards) in per_leader_opened_shards {
-            let init_shards_request = InitShardsRequest { shards };
+            let max_shard_throughputs = shards
+                .iter()
+                .filter_map(|shard| {
+                    let index_uid = shard.index_uid().to_string();
+                    let max_shard_throughput = *max_shard_throughputs.get(&index_uid)?;
+                    Some((index_uid, max_shard_throughput))
+                })
+                .collect();
+            let init_shards_request = InitShardsRequest {
+                shards,
+                promoted_shards: Vec::new(),
+                max_shard_throughputs,
+            };
 
             let Some(mut leader) = self.ingester_pool.get(leader_id) else {
                 warn!("failed to init shards: ingester `{leader_id}` is unavailable");
@@ -513,6 +1025,26 @@ impl IngestController {
     ) {
         const NUM_PERMITS: u64 = 1;
 
+        if let Some(max_num_shards) =
+            model
+                .ingest_quota(&source_uid.index_uid)
+                .and_then(|ingest_quota| {
+                    max_num_shards(ingest_quota, MAX_SHARD_INGESTION_THROUGHPUT_BYTES_PER_SEC)
+                })
+        {
+            let num_open_shards = model
+                .list_shards_for_index(&source_uid.index_uid)
+                .filter(|shard_entry| shard_entry.is_open())
+                .count();
+
+            if num_open_shards >= max_num_shards {
+                debug!(
+                    index_id=%source_uid.index_uid.index_id,
+                    "not scaling up number of shards: max ingest throughput reached"
+                );
+                return;
+            }
+        }
         if !model
             .acquire_scaling_permits(&source_uid, ScalingMode::Up, NUM_PERMITS)
             .unwrap_or(false)
@@ -528,7 +1060,7 @@ impl IngestController {
         );
         let unavailable_leaders: FnvHashSet<NodeId> = FnvHashSet::default();
 
-        let Some((leader_id, follower_id)) = self
+        let Some((leader_id, follower_ids)) = self
             .allocate_shards(1, &unavailable_leaders, model)
             .and_then(|pairs| pairs.into_iter().next())
         else {
@@ -537,13 +1069,16 @@ impl IngestController {
             return;
         };
         let shard_id = ShardId::from(Ulid::new());
+        let mut follower_ids = follower_ids.into_iter().map(String::from);
         let open_shards_subrequest = metastore::OpenShardsSubrequest {
             subrequest_id: 0,
             index_uid: source_uid.index_uid.clone().into(),
             source_id: source_uid.source_id.clone(),
             shard_id: Some(shard_id),
             leader_id: leader_id.to_string(),
-            follower_id: follower_id.map(Into::into),
+            follower_id: follower_ids.next(),
+            additional_follower_ids: follower_ids.collect(),
+            reassign: false,
         };
         let open_shards_request = metastore::OpenShardsRequest {
             subrequests: vec![open_shards_subrequest],
@@ -559,7 +1094,10 @@ impl IngestController {
                 return;
             }
         };
-        if let Err(error) = self.init_shards(&open_shards_response, progress).await {
+        if let Err(error) = self
+            .init_shards(&open_shards_response, model, progress)
+            .await
+        {
             warn!("failed to scale up number of shards: {error}");
             model.release_scaling_permits(&source_uid, ScalingMode::Up, NUM_PERMITS);
             return;
@@ -684,6 +1222,59 @@ impl IngestController {
         }
     }
 
+    /// Records the new leader and followers of the shards handed off by a decommissioning ingester
+    /// in the metastore and the model.
+    pub(crate) async fn handoff_shards(
+        &mut self,
+        request: HandoffShardsRequest,
+        model: &mut ControlPlaneModel,
+        progress: &Progress,
+    ) -> ControlPlaneResult<HandoffShardsResponse> {
+        if request.shards.is_empty() {
+            return Ok(HandoffShardsResponse {});
+        }
+        let open_shards_subrequests = request
+            .shards
+            .iter()
+            .enumerate()
+            .map(|(subrequest_id, shard)| metastore::OpenShardsSubrequest {
+                subrequest_id: subrequest_id as u32,
+                index_uid: shard.index_uid.clone(),
+                source_id: shard.source_id.clone(),
+                shard_id: shard.shard_id.clone(),
+                leader_id: shard.leader_id.clone(),
+                follower_id: shard.follower_id.clone(),
+                additional_follower_ids: shard.additional_follower_ids.clone(),
+                reassign: true,
+            })
+            .collect();
+        let open_shards_request = metastore::OpenShardsRequest {
+            subrequests: open_shards_subrequests,
+        };
+        progress
+            .protect_future(self.metastore.open_shards(open_shards_request))
+            .await?;
+
+        for shard in request.shards {
+            let source_uid = SourceUid {
+                index_uid: shard.index_uid().clone(),
+                source_id: shard.source_id.clone(),
+            };
+            let leader_id = NodeId::from(shard.leader_id.clone());
+            let follower_ids: Vec<NodeId> = shard.follower_ids().collect();
+
+            if model.promote_shard(&source_uid, shard.shard_id(), leader_id, follower_ids) {
+                info!(
+                    "shard `{}` was handed off from ingester `{}` to ingester `{}`",
+                    shard.queue_id(),
+                    request.former_leader_id,
+                    shard.leader_id
+                );
+            }
+        }
+        Ok(HandoffShardsResponse {})
+    }
+
     pub fn rebalance_shards(&mut self) {
         // TODO: As of now, it is only used for unit testing.
         self.stats.num_rebalance_shards_ops += 1;
@@ -726,6 +1317,16 @@ fn find_scale_down_candidate(
         })
 }
 
+/// Returns the minimum number of followers of a shard that must report the position of their
+/// replica after its leader left for the most advanced one to be known. Any record acknowledged by
+/// the former leader was persisted by a write quorum of replicas, so it is held by at least one of
+/// any `replication_factor - write_quorum + 1` replicas.
+fn min_num_follower_positions(num_followers: usize) -> usize {
+    let replication_factor = num_followers + 1;
+    let write_quorum = replication_factor / 2 + 1;
+    replication_factor - write_quorum + 1
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -734,13 +1335,14 @@ mod tests {
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
 
-    use quickwit_config::{SourceConfig, INGEST_V2_SOURCE_ID};
-    use quickwit_ingest::{RateMibPerSec, ShardInfo};
+    use bytesize::ByteSize;
+    use quickwit_config::{DocMapping, IngestQuota, SourceConfig, INGEST_V2_SOURCE_ID};
+    use quickwit_ingest::{DailyIngestVolume, RateMibPerSec, ShardInfo};
     use quickwit_metastore::IndexMetadata;
     use quickwit_proto::control_plane::GetOrCreateOpenShardsSubrequest;
     use quickwit_proto::ingest::ingester::{
-        CloseShardsResponse, IngesterServiceClient, InitShardsResponse, MockIngesterService,
-        RetainShardsResponse,
+        CloseShardsResponse, GetReplicaPositionsResponse, IngesterServiceClient,
+        InitShardsResponse, MockIngesterService, ReplicaPosition, RetainShardsResponse,
     };
     use quickwit_proto::ingest::{Shard, ShardState};
     use quickwit_proto::metastore::MetastoreError;
@@ -910,6 +1512,9 @@ mod tests {
         assert_eq!(success.open_shards[0].shard_id(), ShardId::from(2));
         assert_eq!(success.open_shards[0].leader_id, "test-ingester-1");
 
+        let doc_mapping: DocMapping = serde_json::from_str(&success.doc_mapping_json).unwrap();
+        assert_eq!(doc_mapping, index_metadata_0.index_config.doc_mapping);
+
         let success = &response.successes[1];
         assert_eq!(success.subrequest_id, 1);
         assert_eq!(success.index_uid(), &index_uid_1);
@@ -1102,7 +1707,7 @@ mod tests {
         assert_eq!(leader_follower_pairs[0].0, "test-ingester-1");
         assert_eq!(
             leader_follower_pairs[0].1,
-            Some(NodeId::from("test-ingester-2"))
+            vec![NodeId::from("test-ingester-2")]
         );
 
         let leader_follower_pairs = ingest_controller
@@ -1112,13 +1717,13 @@ mod tests {
         assert_eq!(leader_follower_pairs[0].0, "test-ingester-1");
         assert_eq!(
             leader_follower_pairs[0].1,
-            Some(NodeId::from("test-ingester-2"))
+            vec![NodeId::from("test-ingester-2")]
         );
 
         assert_eq!(leader_follower_pairs[1].0, "test-ingester-2");
         assert_eq!(
             leader_follower_pairs[1].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         let leader_follower_pairs = ingest_controller
@@ -1128,19 +1733,19 @@ mod tests {
         assert_eq!(leader_follower_pairs[0].0, "test-ingester-1");
         assert_eq!(
             leader_follower_pairs[0].1,
-            Some(NodeId::from("test-ingester-2"))
+            vec![NodeId::from("test-ingester-2")]
         );
 
         assert_eq!(leader_follower_pairs[1].0, "test-ingester-2");
         assert_eq!(
             leader_follower_pairs[1].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[2].0, "test-ingester-1");
         assert_eq!(
             leader_follower_pairs[2].1,
-            Some(NodeId::from("test-ingester-2"))
+            vec![NodeId::from("test-ingester-2")]
         );
 
         let index_uid = IndexUid::for_test("test-index", 0);
@@ -1162,19 +1767,19 @@ mod tests {
         assert_eq!(leader_follower_pairs[0].0, "test-ingester-1");
         assert_eq!(
             leader_follower_pairs[0].1,
-            Some(NodeId::from("test-ingester-2"))
+            vec![NodeId::from("test-ingester-2")]
         );
 
         assert_eq!(leader_follower_pairs[1].0, "test-ingester-2");
         assert_eq!(
             leader_follower_pairs[1].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[2].0, "test-ingester-2");
         assert_eq!(
             leader_follower_pairs[2].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         let open_shards = vec![
@@ -1204,7 +1809,7 @@ mod tests {
         assert_eq!(leader_follower_pairs[0].0, "test-ingester-2");
         assert_eq!(
             leader_follower_pairs[0].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         ingester_pool.insert(
@@ -1219,80 +1824,621 @@ mod tests {
         assert_eq!(leader_follower_pairs[0].0, "test-ingester-3");
         assert_eq!(
             leader_follower_pairs[0].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[1].0, "test-ingester-3");
         assert_eq!(
             leader_follower_pairs[1].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[2].0, "test-ingester-3");
         assert_eq!(
             leader_follower_pairs[2].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[3].0, "test-ingester-1");
         assert_eq!(
             leader_follower_pairs[3].1,
-            Some(NodeId::from("test-ingester-3"))
+            vec![NodeId::from("test-ingester-3")]
         );
     }
 
-    #[tokio::test]
-    async fn test_ingest_controller_handle_local_shards_update() {
-        let mut mock_metastore = MetastoreServiceClient::mock();
-        mock_metastore
-            .expect_open_shards()
-            .once()
-            .returning(|request| {
-                assert_eq!(request.subrequests.len(), 1);
-                let subrequest = &request.subrequests[0];
-
-                assert_eq!(subrequest.index_uid(), &IndexUid::for_test("test-index", 0));
-                assert_eq!(subrequest.source_id, "test-source");
-                assert_eq!(subrequest.leader_id, "test-ingester");
-
-                Err(MetastoreError::InvalidArgument {
-                    message: "failed to open shards".to_string(),
-                })
-            });
-        let metastore = MetastoreServiceClient::from(mock_metastore);
+    #[test]
+    fn test_ingest_controller_allocate_shards_across_availability_zones() {
+        let metastore = MetastoreServiceClient::mock().into();
         let ingester_pool = IngesterPool::default();
-        let replication_factor = 1;
+        let replication_factor = 2;
 
         let mut ingest_controller =
             IngestController::new(metastore, ingester_pool.clone(), replication_factor);
 
-        let index_uid = IndexUid::for_test("test-index", 0);
-        let source_id: SourceId = "test-source".into();
+        for (ingester, availability_zone) in [
+            ("test-ingester-1", "test-zone-a"),
+            ("test-ingester-2", "test-zone-a"),
+            ("test-ingester-3", "test-zone-b"),
+            ("test-ingester-4", "test-zone-b"),
+        ] {
+            ingester_pool.insert(ingester.into(), IngesterServiceClient::mock().into());
+            ingest_controller
+                .handle_ingester_joined(ingester.into(), Some(availability_zone.to_string()));
+        }
+        let model = ControlPlaneModel::default();
 
-        let source_uid = SourceUid {
-            index_uid: index_uid.clone(),
-            source_id: source_id.clone(),
-        };
-        let mut model = ControlPlaneModel::default();
-        let progress = Progress::default();
+        let leader_follower_pairs = ingest_controller
+            .allocate_shards(4, &FnvHashSet::default(), &model)
+            .unwrap();
+        assert_eq!(leader_follower_pairs.len(), 4);
 
-        let shards = vec![Shard {
-            index_uid: Some(index_uid.clone()),
-            source_id: source_id.clone(),
-            shard_id: Some(ShardId::from(1)),
-            leader_id: "test-ingester".to_string(),
-            shard_state: ShardState::Open as i32,
-            ..Default::default()
-        }];
-        model.insert_shards(&index_uid, &source_id, shards);
-        let shard_entries: Vec<ShardEntry> = model.all_shards().cloned().collect();
+        assert_eq!(leader_follower_pairs[0].0, "test-ingester-1");
+        assert_eq!(
+            leader_follower_pairs[0].1,
+            vec![NodeId::from("test-ingester-3")]
+        );
 
-        assert_eq!(shard_entries.len(), 1);
-        assert_eq!(shard_entries[0].ingestion_rate, 0);
+        assert_eq!(leader_follower_pairs[1].0, "test-ingester-3");
+        assert_eq!(
+            leader_follower_pairs[1].1,
+            vec![NodeId::from("test-ingester-2")]
+        );
 
-        // Test update shard ingestion rate but no scale down because num open shards is 1.
-        let shard_infos = BTreeSet::from_iter([ShardInfo {
-            shard_id: ShardId::from(1),
+        assert_eq!(leader_follower_pairs[2].0, "test-ingester-2");
+        assert_eq!(
+            leader_follower_pairs[2].1,
+            vec![NodeId::from("test-ingester-4")]
+        );
+
+        assert_eq!(leader_follower_pairs[3].0, "test-ingester-4");
+        assert_eq!(
+            leader_follower_pairs[3].1,
+            vec![NodeId::from("test-ingester-1")]
+        );
+
+        // The ingesters of zone B are unavailable: the shards are replicated within zone A.
+        let unavailable_leaders = FnvHashSet::from_iter([
+            NodeId::from("test-ingester-3"),
+            NodeId::from("test-ingester-4"),
+        ]);
+        let leader_follower_pairs = ingest_controller
+            .allocate_shards(2, &unavailable_leaders, &model)
+            .unwrap();
+        assert_eq!(leader_follower_pairs.len(), 2);
+
+        assert_eq!(leader_follower_pairs[0].0, "test-ingester-1");
+        assert_eq!(
+            leader_follower_pairs[0].1,
+            vec![NodeId::from("test-ingester-2")]
+        );
+
+        assert_eq!(leader_follower_pairs[1].0, "test-ingester-2");
+        assert_eq!(
+            leader_follower_pairs[1].1,
+            vec![NodeId::from("test-ingester-1")]
+        );
+
+        ingest_controller.handle_ingester_left(&NodeId::from("test-ingester-3"));
+        assert_eq!(ingest_controller.availability_zones.len(), 3);
+    }
+
+    #[test]
+    fn test_ingest_controller_allocate_shards_with_replication_factor_3() {
+        let metastore = MetastoreServiceClient::mock().into();
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 3;
+
+        let mut ingest_controller =
+            IngestController::new(metastore, ingester_pool.clone(), replication_factor);
+
+        let model = ControlPlaneModel::default();
+
+        for ingester in ["test-ingester-1", "test-ingester-2"] {
+            ingester_pool.insert(ingester.into(), IngesterServiceClient::mock().into());
+        }
+        let leader_follower_pairs_opt =
+            ingest_controller.allocate_shards(1, &FnvHashSet::default(), &model);
+        assert!(leader_follower_pairs_opt.is_none());
+
+        ingester_pool.insert(
+            "test-ingester-3".into(),
+            IngesterServiceClient::mock().into(),
+        );
+        let leader_follower_pairs = ingest_controller
+            .allocate_shards(1, &FnvHashSet::default(), &model)
+            .unwrap();
+        assert_eq!(leader_follower_pairs.len(), 1);
+        assert_eq!(leader_follower_pairs[0].0, "test-ingester-1");
+        assert_eq!(
+            leader_follower_pairs[0].1,
+            [
+                NodeId::from("test-ingester-2"),
+                NodeId::from("test-ingester-3")
+            ]
+        );
+
+        ingester_pool.insert(
+            "test-ingester-4".into(),
+            IngesterServiceClient::mock().into(),
+        );
+        for (ingester, availability_zone) in [
+            ("test-ingester-1", "test-zone-a"),
+            ("test-ingester-2", "test-zone-a"),
+            ("test-ingester-3", "test-zone-b"),
+            ("test-ingester-4", "test-zone-c"),
+        ] {
+            ingest_controller
+                .handle_ingester_joined(ingester.into(), Some(availability_zone.to_string()));
+        }
+        let leader_follower_pairs = ingest_controller
+            .allocate_shards(4, &FnvHashSet::default(), &model)
+            .unwrap();
+        assert_eq!(leader_follower_pairs.len(), 4);
+
+        assert_eq!(leader_follower_pairs[0].0, "test-ingester-1");
+        assert_eq!(
+            leader_follower_pairs[0].1,
+            [
+                NodeId::from("test-ingester-3"),
+                NodeId::from("test-ingester-4")
+            ]
+        );
+        assert_eq!(leader_follower_pairs[1].0, "test-ingester-3");
+        assert_eq!(
+            leader_follower_pairs[1].1,
+            [
+                NodeId::from("test-ingester-4"),
+                NodeId::from("test-ingester-2")
+            ]
+        );
+        assert_eq!(leader_follower_pairs[2].0, "test-ingester-4");
+        assert_eq!(
+            leader_follower_pairs[2].1,
+            [
+                NodeId::from("test-ingester-2"),
+                NodeId::from("test-ingester-3")
+            ]
+        );
+        assert_eq!(leader_follower_pairs[3].0, "test-ingester-2");
+        assert_eq!(
+            leader_follower_pairs[3].1,
+            [
+                NodeId::from("test-ingester-3"),
+                NodeId::from("test-ingester-4")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingest_controller_promote_shards() {
+        let mut mock_metastore = MetastoreServiceClient::mock();
+        mock_metastore
+            .expect_open_shards()
+            .once()
+            .returning(|request| {
+                assert_eq!(request.subrequests.len(), 2);
+
+                for subrequest in &request.subrequests {
+                    assert!(subrequest.reassign);
+
+                    if subrequest.shard_id() == ShardId::from(1) {
+                        assert_eq!(subrequest.leader_id, "test-ingester-2");
+                        assert_eq!(subrequest.follower_id(), "test-ingester-1");
+                    } else {
+                        assert_eq!(subrequest.shard_id(), ShardId::from(4));
+                        assert_eq!(subrequest.leader_id, "test-ingester-1");
+                        assert_eq!(subrequest.follower_id(), "test-ingester-2");
+                    }
+                    assert!(subrequest.additional_follower_ids.is_empty());
+                }
+                Ok(metastore::OpenShardsResponse {
+                    subresponses: Vec::new(),
+                })
+            });
+        let metastore = MetastoreServiceClient::from(mock_metastore);
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 3;
+
+        let mut ingest_controller =
+            IngestController::new(metastore, ingester_pool.clone(), replication_factor);
+
+        let index_uid = IndexUid::for_test("test-index", 0);
+        let source_id: SourceId = INGEST_V2_SOURCE_ID.to_string();
+
+        let mut model = ControlPlaneModel::default();
+        let index_metadata =
+            IndexMetadata::for_test(&index_uid.index_id, "ram://indexes/test-index:0");
+        model.add_index(index_metadata);
+
+        let souce_config = SourceConfig::ingest_v2();
+        model.add_source(&index_uid, souce_config).unwrap();
+
+        let shard = |shard_id: u64,
+                     leader_id: &str,
+                     follower_ids: [&str; 2],
+                     shard_state: ShardState| Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(ShardId::from(shard_id)),
+            leader_id: leader_id.to_string(),
+            follower_id: Some(follower_ids[0].to_string()),
+            additional_follower_ids: vec![follower_ids[1].to_string()],
+            shard_state: shard_state as i32,
+            ..Default::default()
+        };
+        let shards = vec![
+            shard(
+                1,
+                "test-ingester-0",
+                ["test-ingester-1", "test-ingester-2"],
+                ShardState::Unavailable,
+            ),
+            shard(
+                2,
+                "test-ingester-0",
+                ["test-ingester-1", "test-ingester-2"],
+                ShardState::Closed,
+            ),
+            shard(
+                3,
+                "test-ingester-1",
+                ["test-ingester-0", "test-ingester-2"],
+                ShardState::Open,
+            ),
+            shard(
+                4,
+                "test-ingester-0",
+                ["test-ingester-2", "test-ingester-1"],
+                ShardState::Open,
+            ),
+            shard(
+                5,
+                "test-ingester-0",
+                ["test-ingester-1", "test-ingester-2"],
+                ShardState::Open,
+            ),
+        ];
+        model.insert_shards(&index_uid, &source_id, shards);
+
+        // Shard 1 is equally replicated on both followers, shard 4 is more advanced on
+        // `test-ingester-1`, and the followers of shard 5 do not host a replica.
+        let replica_positions = |request: GetReplicaPositionsRequest, positions: [u64; 2]| {
+            assert_eq!(request.shards.len(), 1);
+            assert_eq!(request.shards[0].shard_ids.len(), 3);
+
+            let replica_positions = request.shards[0]
+                .shard_ids
+                .iter()
+                .filter_map(|shard_id| {
+                    let position = if *shard_id == ShardId::from(1) {
+                        positions[0]
+                    } else if *shard_id == ShardId::from(4) {
+                        positions[1]
+                    } else {
+                        assert_eq!(shard_id, ShardId::from(5));
+                        return None;
+                    };
+                    Some(ReplicaPosition {
+                        index_uid: request.shards[0].index_uid.clone(),
+                        source_id: request.shards[0].source_id.clone(),
+                        shard_id: Some(shard_id.clone()),
+                        replication_position_inclusive: Some(Position::offset(position)),
+                    })
+                })
+                .collect();
+            Ok(GetReplicaPositionsResponse { replica_positions })
+        };
+        let mut mock_ingester_1 = IngesterServiceClient::mock();
+        mock_ingester_1
+            .expect_get_replica_positions()
+            .once()
+            .returning(move |request| replica_positions(request, [7, 9]));
+        mock_ingester_1
+            .expect_init_shards()
+            .times(2)
+            .returning(|request| {
+                assert!(request.shards.is_empty());
+                assert_eq!(request.promoted_shards.len(), 1);
+
+                let promoted_shard = &request.promoted_shards[0];
+                assert_eq!(promoted_shard.leader_id, "test-ingester-1");
+
+                if promoted_shard.shard_id() == ShardId::from(1) {
+                    return Err(IngestV2Error::Internal(
+                        "failed to promote shard".to_string(),
+                    ));
+                }
+                assert_eq!(promoted_shard.shard_id(), ShardId::from(4));
+                assert_eq!(promoted_shard.follower_id(), "test-ingester-2");
+                Ok(InitShardsResponse {})
+            });
+        ingester_pool.insert("test-ingester-1".into(), mock_ingester_1.into());
+
+        let mut mock_ingester_2 = IngesterServiceClient::mock();
+        mock_ingester_2
+            .expect_get_replica_positions()
+            .once()
+            .returning(move |request| replica_positions(request, [7, 3]));
+        mock_ingester_2
+            .expect_init_shards()
+            .once()
+            .returning(|request| {
+                assert!(request.shards.is_empty());
+                assert_eq!(request.promoted_shards.len(), 1);
+
+                let promoted_shard = &request.promoted_shards[0];
+                assert_eq!(promoted_shard.shard_id(), ShardId::from(1));
+                assert_eq!(promoted_shard.leader_id, "test-ingester-2");
+                assert_eq!(promoted_shard.follower_id(), "test-ingester-1");
+                assert!(promoted_shard.additional_follower_ids.is_empty());
+
+                Ok(InitShardsResponse {})
+            });
+        ingester_pool.insert("test-ingester-2".into(), mock_ingester_2.into());
+
+        let progress = Progress::default();
+
+        ingest_controller
+            .promote_shards(&NodeId::from("test-ingester-0"), &mut model, &progress)
+            .await;
+
+        let source_uid = SourceUid {
+            index_uid: index_uid.clone(),
+            source_id: source_id.clone(),
+        };
+        let shard_entries = model.get_shards_for_source(&source_uid).unwrap();
+
+        let shard_1 = &shard_entries[&ShardId::from(1)];
+        assert_eq!(shard_1.leader_id, "test-ingester-2");
+        assert_eq!(shard_1.follower_id(), "test-ingester-1");
+        assert!(shard_1.additional_follower_ids.is_empty());
+        assert!(shard_1.is_open());
+
+        let shard_2 = &shard_entries[&ShardId::from(2)];
+        assert_eq!(shard_2.leader_id, "test-ingester-0");
+        assert!(shard_2.is_closed());
+
+        let shard_3 = &shard_entries[&ShardId::from(3)];
+        assert_eq!(shard_3.leader_id, "test-ingester-1");
+
+        let shard_4 = &shard_entries[&ShardId::from(4)];
+        assert_eq!(shard_4.leader_id, "test-ingester-1");
+        assert_eq!(shard_4.follower_id(), "test-ingester-2");
+
+        let shard_5 = &shard_entries[&ShardId::from(5)];
+        assert_eq!(shard_5.leader_id, "test-ingester-0");
+    }
+
+    #[test]
+    fn test_min_num_follower_positions() {
+        assert_eq!(min_num_follower_positions(1), 1);
+        assert_eq!(min_num_follower_positions(2), 2);
+        assert_eq!(min_num_follower_positions(3), 2);
+        assert_eq!(min_num_follower_positions(4), 3);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_controller_promote_shards_requires_enough_follower_positions() {
+        let mock_metastore = MetastoreServiceClient::mock();
+        let metastore = MetastoreServiceClient::from(mock_metastore);
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 3;
+
+        let mut ingest_controller =
+            IngestController::new(metastore, ingester_pool.clone(), replication_factor);
+
+        let index_uid = IndexUid::for_test("test-index", 0);
+        let source_id: SourceId = INGEST_V2_SOURCE_ID.to_string();
+
+        let mut model = ControlPlaneModel::default();
+        let index_metadata =
+            IndexMetadata::for_test(&index_uid.index_id, "ram://indexes/test-index:0");
+        model.add_index(index_metadata);
+
+        let souce_config = SourceConfig::ingest_v2();
+        model.add_source(&index_uid, souce_config).unwrap();
+
+        let shards = vec![Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(ShardId::from(1)),
+            leader_id: "test-ingester-0".to_string(),
+            follower_id: Some("test-ingester-1".to_string()),
+            additional_follower_ids: vec!["test-ingester-2".to_string()],
+            shard_state: ShardState::Open as i32,
+            ..Default::default()
+        }];
+        model.insert_shards(&index_uid, &source_id, shards);
+
+        // The most advanced follower fails to report its position, so promoting the other one
+        // could lose acknowledged records.
+        let mut mock_ingester_1 = IngesterServiceClient::mock();
+        mock_ingester_1
+            .expect_get_replica_positions()
+            .once()
+            .returning(|_request| {
+                Err(IngestV2Error::Internal(
+                    "failed to get replica positions".to_string(),
+                ))
+            });
+        mock_ingester_1.expect_init_shards().never();
+        ingester_pool.insert("test-ingester-1".into(), mock_ingester_1.into());
+
+        let mut mock_ingester_2 = IngesterServiceClient::mock();
+        mock_ingester_2
+            .expect_get_replica_positions()
+            .once()
+            .returning(|request| {
+                assert_eq!(request.shards.len(), 1);
+                assert_eq!(request.shards[0].shard_ids, [ShardId::from(1)]);
+
+                let replica_position = ReplicaPosition {
+                    index_uid: request.shards[0].index_uid.clone(),
+                    source_id: request.shards[0].source_id.clone(),
+                    shard_id: Some(ShardId::from(1)),
+                    replication_position_inclusive: Some(Position::offset(3u64)),
+                };
+                Ok(GetReplicaPositionsResponse {
+                    replica_positions: vec![replica_position],
+                })
+            });
+        mock_ingester_2.expect_init_shards().never();
+        ingester_pool.insert("test-ingester-2".into(), mock_ingester_2.into());
+
+        let progress = Progress::default();
+
+        ingest_controller
+            .promote_shards(&NodeId::from("test-ingester-0"), &mut model, &progress)
+            .await;
+
+        let source_uid = SourceUid {
+            index_uid: index_uid.clone(),
+            source_id: source_id.clone(),
+        };
+        let shard_entries = model.get_shards_for_source(&source_uid).unwrap();
+
+        let shard_1 = &shard_entries[&ShardId::from(1)];
+        assert_eq!(shard_1.leader_id, "test-ingester-0");
+        assert_eq!(shard_1.follower_id(), "test-ingester-1");
+        assert_eq!(shard_1.additional_follower_ids, ["test-ingester-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_controller_handoff_shards() {
+        let mut mock_metastore = MetastoreServiceClient::mock();
+        mock_metastore
+            .expect_open_shards()
+            .once()
+            .returning(|request| {
+                assert_eq!(request.subrequests.len(), 1);
+
+                let subrequest = &request.subrequests[0];
+                assert_eq!(subrequest.shard_id(), ShardId::from(1));
+                assert_eq!(subrequest.leader_id, "test-ingester-1");
+                assert!(subrequest.follower_id.is_none());
+                assert!(subrequest.reassign);
+
+                Ok(metastore::OpenShardsResponse::default())
+            });
+        let metastore = MetastoreServiceClient::from(mock_metastore);
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 1;
+
+        let mut ingest_controller =
+            IngestController::new(metastore, ingester_pool, replication_factor);
+
+        let index_uid = IndexUid::for_test("test-index", 0);
+        let source_id: SourceId = INGEST_V2_SOURCE_ID.to_string();
+
+        let mut model = ControlPlaneModel::default();
+        let index_metadata =
+            IndexMetadata::for_test(&index_uid.index_id, "ram://indexes/test-index:0");
+        model.add_index(index_metadata);
+
+        let souce_config = SourceConfig::ingest_v2();
+        model.add_source(&index_uid, souce_config).unwrap();
+
+        let shards = vec![Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(ShardId::from(1)),
+            leader_id: "test-ingester-0".to_string(),
+            shard_state: ShardState::Closed as i32,
+            ..Default::default()
+        }];
+        model.insert_shards(&index_uid, &source_id, shards);
+
+        let progress = Progress::default();
+
+        let handoff_shards_request = HandoffShardsRequest {
+            former_leader_id: "test-ingester-0".to_string(),
+            shards: Vec::new(),
+        };
+        ingest_controller
+            .handoff_shards(handoff_shards_request, &mut model, &progress)
+            .await
+            .unwrap();
+
+        let handoff_shards_request = HandoffShardsRequest {
+            former_leader_id: "test-ingester-0".to_string(),
+            shards: vec![Shard {
+                index_uid: Some(index_uid.clone()),
+                source_id: source_id.clone(),
+                shard_id: Some(ShardId::from(1)),
+                leader_id: "test-ingester-1".to_string(),
+                shard_state: ShardState::Closed as i32,
+                ..Default::default()
+            }],
+        };
+        ingest_controller
+            .handoff_shards(handoff_shards_request, &mut model, &progress)
+            .await
+            .unwrap();
+
+        let source_uid = SourceUid {
+            index_uid: index_uid.clone(),
+            source_id: source_id.clone(),
+        };
+        let shard_entries = model.get_shards_for_source(&source_uid).unwrap();
+
+        let shard_1 = &shard_entries[&ShardId::from(1)];
+        assert_eq!(shard_1.leader_id, "test-ingester-1");
+        assert!(shard_1.follower_id.is_none());
+        assert!(shard_1.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_controller_handle_local_shards_update() {
+        let mut mock_metastore = MetastoreServiceClient::mock();
+        mock_metastore
+            .expect_open_shards()
+            .once()
+            .returning(|request| {
+                assert_eq!(request.subrequests.len(), 1);
+                let subrequest = &request.subrequests[0];
+
+                assert_eq!(subrequest.index_uid(), &IndexUid::for_test("test-index", 0));
+                assert_eq!(subrequest.source_id, "test-source");
+                assert_eq!(subrequest.leader_id, "test-ingester");
+
+                Err(MetastoreError::InvalidArgument {
+                    message: "failed to open shards".to_string(),
+                })
+            });
+        let metastore = MetastoreServiceClient::from(mock_metastore);
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 1;
+
+        let mut ingest_controller =
+            IngestController::new(metastore, ingester_pool.clone(), replication_factor);
+
+        let index_uid = IndexUid::for_test("test-index", 0);
+        let source_id: SourceId = "test-source".into();
+
+        let source_uid = SourceUid {
+            index_uid: index_uid.clone(),
+            source_id: source_id.clone(),
+        };
+        let mut model = ControlPlaneModel::default();
+        let progress = Progress::default();
+
+        let shards = vec![Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(ShardId::from(1)),
+            leader_id: "test-ingester".to_string(),
+            shard_state: ShardState::Open as i32,
+            ..Default::default()
+        }];
+        model.insert_shards(&index_uid, &source_id, shards);
+        let shard_entries: Vec<ShardEntry> = model.all_shards().cloned().collect();
+
+        assert_eq!(shard_entries.len(), 1);
+        assert_eq!(shard_entries[0].ingestion_rate, 0);
+
+        // Test update shard ingestion rate but no scale down because num open shards is 1.
+        let shard_infos = BTreeSet::from_iter([ShardInfo {
+            shard_id: ShardId::from(1),
             shard_state: ShardState::Open,
             ingestion_rate: RateMibPerSec(1),
         }]);
@@ -1617,6 +2763,231 @@ mod tests {
         assert!(model.all_shards().any(|shard| shard.is_open()));
     }
 
+    #[tokio::test]
+    async fn test_ingest_controller_ingest_quotas() {
+        let metastore = MetastoreServiceClient::mock().into();
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 1;
+
+        let mut ingest_controller =
+            IngestController::new(metastore, ingester_pool.clone(), replication_factor);
+
+        let mut index_metadata = IndexMetadata::for_test("test-index", "ram://indexes/test-index");
+        index_metadata.index_config.ingest_quota_opt = Some(IngestQuota {
+            max_ingest_throughput: Some(ByteSize::mib(5)),
+            max_daily_ingest_volume: Some(ByteSize::kb(1)),
+        });
+        let index_uid = index_metadata.index_uid.clone();
+        let source_id: SourceId = INGEST_V2_SOURCE_ID.to_string();
+
+        let source_uid = SourceUid {
+            index_uid: index_uid.clone(),
+            source_id: source_id.clone(),
+        };
+        let mut model = ControlPlaneModel::default();
+        model.add_index(index_metadata);
+        model
+            .add_source(&index_uid, SourceConfig::ingest_v2())
+            .unwrap();
+
+        let shards = vec![Shard {
+            shard_id: Some(ShardId::from(1)),
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            leader_id: "test-ingester".to_string(),
+            shard_state: ShardState::Open as i32,
+            ..Default::default()
+        }];
+        model.insert_shards(&index_uid, &source_id, shards);
+
+        let progress = Progress::default();
+
+        // Test the max ingest throughput caps the number of shards: the metastore mock panics if
+        // a shard is opened.
+        let shard_stats = ShardStats {
+            num_open_shards: 1,
+            ..Default::default()
+        };
+        ingest_controller
+            .try_scale_up_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .await;
+        assert_eq!(model.all_shards().count(), 1);
+
+        let mut ingester_mock = IngesterServiceClient::mock();
+
+        let index_uid_clone = index_uid.clone();
+        ingester_mock
+            .expect_close_shards()
+            .once()
+            .returning(move |request| {
+                assert_eq!(request.shards.len(), 1);
+                assert_eq!(request.shards[0].index_uid(), &index_uid_clone);
+                assert_eq!(request.shards[0].source_id, INGEST_V2_SOURCE_ID);
+                assert_eq!(request.shards[0].shard_ids, [ShardId::from(1)]);
+
+                Ok(CloseShardsResponse {})
+            });
+        ingester_pool.insert("test-ingester".into(), ingester_mock.into());
+
+        // Test the daily ingest quota is not exceeded yet.
+        let ingest_volume_update = IngestVolumeUpdate {
+            leader_id: "test-ingester".into(),
+            index_uid: index_uid.clone(),
+            daily_ingest_volume: DailyIngestVolume {
+                day: today(),
+                num_bytes: 600,
+            },
+        };
+        ingest_controller
+            .handle_ingest_volume_update(ingest_volume_update, &mut model, &progress)
+            .await;
+        assert!(model.all_shards().all(|shard| shard.is_open()));
+
+        // Test the daily ingest quota is exceeded.
+        let ingest_volume_update = IngestVolumeUpdate {
+            leader_id: "test-ingester-2".into(),
+            index_uid: index_uid.clone(),
+            daily_ingest_volume: DailyIngestVolume {
+                day: today(),
+                num_bytes: 400,
+            },
+        };
+        ingest_controller
+            .handle_ingest_volume_update(ingest_volume_update, &mut model, &progress)
+            .await;
+        assert!(model.all_shards().all(|shard| shard.is_closed()));
+
+        let ingest_quota_entries = ingest_controller.ingest_quota_entries(&model);
+        assert_eq!(ingest_quota_entries.len(), 1);
+
+        let ingest_quota_entry = &ingest_quota_entries[0];
+        assert_eq!(ingest_quota_entry.index_uid, index_uid.to_string());
+        assert_eq!(ingest_quota_entry.daily_ingest_volume_bytes, 1_000);
+        assert_eq!(
+            ingest_quota_entry.max_daily_ingest_volume_bytes,
+            Some(1_000)
+        );
+        assert_eq!(
+            ingest_quota_entry.max_ingest_throughput_bytes,
+            Some(5 * 1024 * 1024)
+        );
+        assert_eq!(ingest_quota_entry.num_open_shards, 0);
+        assert!(ingest_quota_entry.quota_exceeded);
+
+        let get_open_shards_request = GetOrCreateOpenShardsRequest {
+            subrequests: vec![GetOrCreateOpenShardsSubrequest {
+                subrequest_id: 0,
+                index_id: "test-index".to_string(),
+                source_id: source_id.clone(),
+            }],
+            closed_shards: Vec::new(),
+            unavailable_leaders: Vec::new(),
+        };
+        let response = ingest_controller
+            .get_or_create_open_shards(get_open_shards_request, &mut model, &progress)
+            .await
+            .unwrap();
+        assert!(response.successes.is_empty());
+        assert_eq!(response.failures.len(), 1);
+        assert_eq!(
+            response.failures[0].reason(),
+            GetOrCreateOpenShardsFailureReason::QuotaExceeded
+        );
+
+        ingest_controller.handle_index_deleted(&index_uid);
+        assert_eq!(
+            ingest_controller
+                .ingest_volumes
+                .daily_ingest_volume(&index_uid, today()),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingest_controller_limits_shard_throughput_to_quota() {
+        let mut index_metadata = IndexMetadata::for_test("test-index", "ram://indexes/test-index");
+        index_metadata.index_config.ingest_quota_opt = Some(IngestQuota {
+            max_ingest_throughput: Some(ByteSize::kib(512)),
+            max_daily_ingest_volume: None,
+        });
+        let index_uid = index_metadata.index_uid.clone();
+        let source_id: SourceId = INGEST_V2_SOURCE_ID.to_string();
+
+        let mut mock_metastore = MetastoreServiceClient::mock();
+        mock_metastore.expect_open_shards().once().returning({
+            let index_uid = index_uid.clone();
+
+            move |request| {
+                assert_eq!(request.subrequests.len(), 1);
+
+                let subresponses = vec![metastore::OpenShardsSubresponse {
+                    subrequest_id: 0,
+                    index_uid: index_uid.clone().into(),
+                    source_id: INGEST_V2_SOURCE_ID.to_string(),
+                    opened_shards: vec![Shard {
+                        index_uid: index_uid.clone().into(),
+                        source_id: INGEST_V2_SOURCE_ID.to_string(),
+                        shard_id: Some(ShardId::from(1)),
+                        shard_state: ShardState::Open as i32,
+                        leader_id: "test-ingester".to_string(),
+                        ..Default::default()
+                    }],
+                }];
+                let response = metastore::OpenShardsResponse { subresponses };
+                Ok(response)
+            }
+        });
+        let ingester_pool = IngesterPool::default();
+
+        let mut mock_ingester = MockIngesterService::default();
+        let index_uid_clone = index_uid.clone();
+        mock_ingester
+            .expect_init_shards()
+            .once()
+            .returning(move |request| {
+                assert_eq!(request.shards.len(), 1);
+                assert_eq!(request.max_shard_throughputs.len(), 1);
+                assert_eq!(
+                    request.max_shard_throughputs[&index_uid_clone.to_string()],
+                    ByteSize::kib(512).as_u64()
+                );
+                Ok(InitShardsResponse {})
+            });
+        ingester_pool.insert("test-ingester".into(), mock_ingester.into());
+
+        let replication_factor = 1;
+        let mut ingest_controller = IngestController::new(
+            MetastoreServiceClient::from(mock_metastore),
+            ingester_pool,
+            replication_factor,
+        );
+        let mut model = ControlPlaneModel::default();
+        model.add_index(index_metadata);
+        model
+            .add_source(&index_uid, SourceConfig::ingest_v2())
+            .unwrap();
+
+        let request = GetOrCreateOpenShardsRequest {
+            subrequests: vec![GetOrCreateOpenShardsSubrequest {
+                subrequest_id: 0,
+                index_id: "test-index".to_string(),
+                source_id,
+            }],
+            closed_shards: Vec::new(),
+            unavailable_leaders: Vec::new(),
+        };
+        let progress = Progress::default();
+        let response = ingest_controller
+            .get_or_create_open_shards(request, &mut model, &progress)
+            .await
+            .unwrap();
+        assert_eq!(response.successes.len(), 1);
+        assert_eq!(
+            response.successes[0].max_ingest_throughput,
+            Some(ByteSize::kib(512).as_u64())
+        );
+    }
+
     #[test]
     fn test_find_scale_down_candidate() {
         let index_uid = IndexUid::for_test("test-index", 0);
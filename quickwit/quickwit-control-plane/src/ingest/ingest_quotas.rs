@@ -0,0 +1,335 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use fnv::FnvHashMap;
+use quickwit_config::IngestQuota;
+use quickwit_ingest::DailyIngestVolume;
+use quickwit_proto::types::{IndexUid, NodeId};
+
+/// Keeps track of the volume of data ingested today for each index, as reported by each leader.
+#[derive(Debug, Default)]
+pub(super) struct IngestVolumes {
+    per_index_volumes: FnvHashMap<IndexUid, FnvHashMap<NodeId, LeaderIngestVolume>>,
+    // Most recent day reported by the leaders. The volumes of the days before are evicted.
+    latest_day: u64,
+}
+
+/// Volume of data ingested into an index by a leader on a given day.
+///
+/// Leaders keep their counters in memory, so a leader that restarts reports its volume from zero
+/// again. The volume reported before the restart is not lost: the control plane accumulates the
+/// increments of the reported volume rather than the reported volume itself.
+#[derive(Debug)]
+struct LeaderIngestVolume {
+    day: u64,
+    last_reported_num_bytes: u64,
+    num_bytes: u64,
+}
+
+impl IngestVolumes {
+    pub fn record(
+        &mut self,
+        leader_id: NodeId,
+        index_uid: IndexUid,
+        daily_ingest_volume: DailyIngestVolume,
+    ) {
+        if daily_ingest_volume.day < self.latest_day {
+            return;
+        }
+        if daily_ingest_volume.day > self.latest_day {
+            self.latest_day = daily_ingest_volume.day;
+            self.evict_past_days();
+        }
+        let per_leader_volumes = self.per_index_volumes.entry(index_uid).or_default();
+
+        match per_leader_volumes.get_mut(&leader_id) {
+            // Out-of-order updates from a previous day must not overwrite today's volume.
+            Some(leader_volume) if leader_volume.day > daily_ingest_volume.day => {}
+            Some(leader_volume) if leader_volume.day == daily_ingest_volume.day => {
+                if daily_ingest_volume.num_bytes >= leader_volume.last_reported_num_bytes {
+                    leader_volume.num_bytes +=
+                        daily_ingest_volume.num_bytes - leader_volume.last_reported_num_bytes;
+                } else {
+                    // The leader restarted and reset its counter.
+                    leader_volume.num_bytes += daily_ingest_volume.num_bytes;
+                }
+                leader_volume.last_reported_num_bytes = daily_ingest_volume.num_bytes;
+            }
+            _ => {
+                let leader_volume = LeaderIngestVolume {
+                    day: daily_ingest_volume.day,
+                    last_reported_num_bytes: daily_ingest_volume.num_bytes,
+                    num_bytes: daily_ingest_volume.num_bytes,
+                };
+                per_leader_volumes.insert(leader_id, leader_volume);
+            }
+        }
+    }
+
+    /// Returns the volume of data ingested into the index on `day` across all the leaders.
+    pub fn daily_ingest_volume(&self, index_uid: &IndexUid, day: u64) -> u64 {
+        let Some(per_leader_volumes) = self.per_index_volumes.get(index_uid) else {
+            return 0;
+        };
+        per_leader_volumes
+            .values()
+            .filter(|leader_volume| leader_volume.day == day)
+            .map(|leader_volume| leader_volume.num_bytes)
+            .sum()
+    }
+
+    /// Forgets the volumes of a deleted index.
+    pub fn remove_index(&mut self, index_uid: &IndexUid) {
+        self.per_index_volumes.remove(index_uid);
+    }
+
+    fn evict_past_days(&mut self) {
+        let latest_day = self.latest_day;
+
+        self.per_index_volumes.retain(|_, per_leader_volumes| {
+            per_leader_volumes.retain(|_, leader_volume| leader_volume.day >= latest_day);
+            !per_leader_volumes.is_empty()
+        });
+    }
+}
+
+/// Returns whether the volume of data ingested today into an index exceeds its daily quota.
+pub(super) fn is_daily_quota_exceeded(
+    ingest_quota: &IngestQuota,
+    daily_ingest_volume: u64,
+) -> bool {
+    ingest_quota
+        .max_daily_ingest_volume
+        .map(|max_daily_ingest_volume| daily_ingest_volume >= max_daily_ingest_volume.as_u64())
+        .unwrap_or(false)
+}
+
+/// Returns the maximum number of open shards allowed for an index given the maximum throughput of a
+/// shard, or `None` if the number of shards is unbounded. An index is always allowed one shard: if
+/// its throughput quota is lower than the throughput of a shard, the throughput of the shard is
+/// limited instead (see [`shard_throughput_limit`]).
+pub(super) fn max_num_shards(
+    ingest_quota: &IngestQuota,
+    max_shard_throughput: u64,
+) -> Option<usize> {
+    let max_ingest_throughput = ingest_quota.max_ingest_throughput?;
+    let max_num_shards = (max_ingest_throughput.as_u64() / max_shard_throughput).max(1);
+    Some(max_num_shards as usize)
+}
+
+/// Returns the throughput in bytes per second to which the shards of an index must be limited when
+/// its throughput quota is lower than the maximum throughput of a shard.
+pub(super) fn shard_throughput_limit(
+    ingest_quota: &IngestQuota,
+    max_shard_throughput: u64,
+) -> Option<u64> {
+    let max_ingest_throughput = ingest_quota.max_ingest_throughput?.as_u64();
+    (max_ingest_throughput < max_shard_throughput).then_some(max_ingest_throughput)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytesize::ByteSize;
+
+    use super::*;
+
+    #[test]
+    fn test_ingest_volumes() {
+        let mut ingest_volumes = IngestVolumes::default();
+        let index_uid = IndexUid::for_test("test-index", 0);
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 2), 0);
+
+        ingest_volumes.record(
+            NodeId::from("test-ingester-1"),
+            index_uid.clone(),
+            DailyIngestVolume {
+                day: 2,
+                num_bytes: 100,
+            },
+        );
+        ingest_volumes.record(
+            NodeId::from("test-ingester-2"),
+            index_uid.clone(),
+            DailyIngestVolume {
+                day: 2,
+                num_bytes: 50,
+            },
+        );
+        ingest_volumes.record(
+            NodeId::from("test-ingester-3"),
+            index_uid.clone(),
+            DailyIngestVolume {
+                day: 1,
+                num_bytes: 1_000,
+            },
+        );
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 2), 150);
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 1), 0);
+
+        ingest_volumes.record(
+            NodeId::from("test-ingester-1"),
+            index_uid.clone(),
+            DailyIngestVolume {
+                day: 1,
+                num_bytes: 1_000,
+            },
+        );
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 2), 150);
+
+        ingest_volumes.record(
+            NodeId::from("test-ingester-1"),
+            index_uid.clone(),
+            DailyIngestVolume {
+                day: 3,
+                num_bytes: 10,
+            },
+        );
+        // The volumes of the previous days are evicted.
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 2), 0);
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 3), 10);
+        assert_eq!(ingest_volumes.per_index_volumes[&index_uid].len(), 1);
+
+        ingest_volumes.record(
+            NodeId::from("test-ingester-1"),
+            index_uid.clone(),
+            DailyIngestVolume {
+                day: 3,
+                num_bytes: 30,
+            },
+        );
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 3), 30);
+
+        // The leader restarts and reports its volume from zero again.
+        ingest_volumes.record(
+            NodeId::from("test-ingester-1"),
+            index_uid.clone(),
+            DailyIngestVolume {
+                day: 3,
+                num_bytes: 5,
+            },
+        );
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 3), 35);
+
+        ingest_volumes.record(
+            NodeId::from("test-ingester-1"),
+            index_uid.clone(),
+            DailyIngestVolume {
+                day: 3,
+                num_bytes: 15,
+            },
+        );
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 3), 45);
+
+        let other_index_uid = IndexUid::for_test("other-index", 0);
+        ingest_volumes.record(
+            NodeId::from("test-ingester-1"),
+            other_index_uid.clone(),
+            DailyIngestVolume {
+                day: 3,
+                num_bytes: 10,
+            },
+        );
+        ingest_volumes.remove_index(&index_uid);
+        assert_eq!(ingest_volumes.daily_ingest_volume(&index_uid, 3), 0);
+        assert_eq!(ingest_volumes.daily_ingest_volume(&other_index_uid, 3), 10);
+
+        ingest_volumes.record(
+            NodeId::from("test-ingester-1"),
+            index_uid.clone(),
+            DailyIngestVolume {
+                day: 4,
+                num_bytes: 20,
+            },
+        );
+        assert!(!ingest_volumes
+            .per_index_volumes
+            .contains_key(&other_index_uid));
+    }
+
+    #[test]
+    fn test_is_daily_quota_exceeded() {
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: Some(ByteSize::mib(10)),
+            max_daily_ingest_volume: None,
+        };
+        assert!(!is_daily_quota_exceeded(&ingest_quota, u64::MAX));
+
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: None,
+            max_daily_ingest_volume: Some(ByteSize::kb(1)),
+        };
+        assert!(!is_daily_quota_exceeded(&ingest_quota, 999));
+        assert!(is_daily_quota_exceeded(&ingest_quota, 1_000));
+    }
+
+    #[test]
+    fn test_max_num_shards() {
+        let max_shard_throughput = ByteSize::mib(5).as_u64();
+
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: None,
+            max_daily_ingest_volume: Some(ByteSize::gb(1)),
+        };
+        assert_eq!(max_num_shards(&ingest_quota, max_shard_throughput), None);
+
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: Some(ByteSize::mib(1)),
+            max_daily_ingest_volume: None,
+        };
+        assert_eq!(max_num_shards(&ingest_quota, max_shard_throughput), Some(1));
+
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: Some(ByteSize::mib(12)),
+            max_daily_ingest_volume: None,
+        };
+        assert_eq!(max_num_shards(&ingest_quota, max_shard_throughput), Some(2));
+    }
+
+    #[test]
+    fn test_shard_throughput_limit() {
+        let max_shard_throughput = ByteSize::mib(5).as_u64();
+
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: None,
+            max_daily_ingest_volume: Some(ByteSize::gb(1)),
+        };
+        assert_eq!(
+            shard_throughput_limit(&ingest_quota, max_shard_throughput),
+            None
+        );
+
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: Some(ByteSize::kib(512)),
+            max_daily_ingest_volume: None,
+        };
+        assert_eq!(
+            shard_throughput_limit(&ingest_quota, max_shard_throughput),
+            Some(ByteSize::kib(512).as_u64())
+        );
+
+        let ingest_quota = IngestQuota {
+            max_ingest_throughput: Some(ByteSize::mib(5)),
+            max_daily_ingest_volume: None,
+        };
+        assert_eq!(
+            shard_throughput_limit(&ingest_quota, max_shard_throughput),
+            None
+        );
+    }
+}
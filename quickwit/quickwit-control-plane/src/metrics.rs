@@ -29,6 +29,8 @@ pub struct ControlPlaneMetrics {
     pub metastore_error_aborted: IntCounter,
     pub metastore_error_maybe_executed: IntCounter,
     pub open_shards_total: IntGaugeVec<1>,
+    pub daily_ingest_volume_bytes: IntGaugeVec<1>,
+    pub ingest_quota_exceeded: IntGaugeVec<1>,
 }
 
 impl Default for ControlPlaneMetrics {
@@ -64,6 +66,20 @@ impl Default for ControlPlaneMetrics {
                 &[],
                 ["index_id"],
             ),
+            daily_ingest_volume_bytes: new_gauge_vec(
+                "daily_ingest_volume_bytes",
+                "Volume of data ingested today (UTC) per index.",
+                "control_plane",
+                &[],
+                ["index_id"],
+            ),
+            ingest_quota_exceeded: new_gauge_vec(
+                "ingest_quota_exceeded",
+                "Whether the daily ingest quota of the index is exceeded (1) or not (0).",
+                "control_plane",
+                &[],
+                ["index_id"],
+            ),
         }
     }
 }
@@ -29,7 +29,7 @@ use anyhow::bail;
 use fnv::{FnvHashMap, FnvHashSet};
 use quickwit_common::pretty::PrettyDisplay;
 use quickwit_common::Progress;
-use quickwit_config::SourceConfig;
+use quickwit_config::{IngestQuota, SourceConfig};
 use quickwit_ingest::ShardInfos;
 use quickwit_metastore::{IndexMetadata, ListIndexesMetadataResponseExt};
 use quickwit_proto::control_plane::ControlPlaneResult;
@@ -40,7 +40,7 @@ use quickwit_proto::metastore::{
 };
 use quickwit_proto::types::{IndexId, IndexUid, NodeId, ShardId, SourceId, SourceUid};
 pub(super) use shard_table::{ScalingMode, ShardEntry, ShardStats, ShardTable};
-use tracing::{info, instrument, warn};
+use tracing::{error, info, instrument, warn};
 
 /// The control plane maintains a model in sync with the metastore.
 ///
@@ -152,6 +152,44 @@ impl ControlPlaneModel {
         self.index_uid_table.get(index_id).cloned()
     }
 
+    /// Returns the JSON-serialized doc mapping of an index, or an empty string if the index or the
+    /// source does not exist or if the source transforms documents before indexing, in which case
+    /// raw documents cannot be validated against the doc mapping.
+    pub(crate) fn doc_mapping_json(&self, index_uid: &IndexUid, source_id: &str) -> String {
+        let Some(index_metadata) = self.index_table.get(index_uid) else {
+            return String::new();
+        };
+        let Some(source_config) = index_metadata.sources.get(source_id) else {
+            return String::new();
+        };
+        if source_config.transform_config.is_some() {
+            return String::new();
+        }
+        serde_json::to_string(&index_metadata.index_config.doc_mapping).unwrap_or_else(|error| {
+            error!(index_uid=%index_uid, error=%error, "failed to serialize doc mapping");
+            String::new()
+        })
+    }
+
+    /// Returns the ingest quota of the index, if any.
+    pub(crate) fn ingest_quota(&self, index_uid: &IndexUid) -> Option<&IngestQuota> {
+        self.index_table
+            .get(index_uid)?
+            .index_config
+            .ingest_quota_opt
+            .as_ref()
+    }
+
+    /// Lists the indexes subject to an ingest quota.
+    pub(crate) fn ingest_quotas(&self) -> impl Iterator<Item = (&IndexUid, &IngestQuota)> + '_ {
+        self.index_table
+            .iter()
+            .filter_map(|(index_uid, index_metadata)| {
+                let ingest_quota = index_metadata.index_config.ingest_quota_opt.as_ref()?;
+                Some((index_uid, ingest_quota))
+            })
+    }
+
     fn update_metrics(&self) {
         crate::metrics::CONTROL_PLANE_METRICS
             .indexes_total
@@ -342,6 +380,19 @@ impl ControlPlaneModel {
         self.shard_table.close_shards(source_uid, shard_ids)
     }
 
+    /// Replaces the leader and the followers of a promoted shard. Returns `false` if the shard does
+    /// not exist.
+    pub fn promote_shard(
+        &mut self,
+        source_uid: &SourceUid,
+        shard_id: &ShardId,
+        leader_id: NodeId,
+        follower_ids: Vec<NodeId>,
+    ) -> bool {
+        self.shard_table
+            .promote_shard(source_uid, shard_id, leader_id, follower_ids)
+    }
+
     /// Removes the shards identified by their index UID, source ID, and shard IDs.
     pub fn delete_shards(&mut self, source_uid: &SourceUid, shard_ids: &[ShardId]) {
         self.shard_table.delete_shards(source_uid, shard_ids);
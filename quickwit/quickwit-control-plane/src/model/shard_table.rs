@@ -477,6 +477,49 @@ impl ShardTable {
         closed_shard_ids
     }
 
+    /// Replaces the leader and the followers of a shard after one of its followers was promoted
+    /// because the leader left the cluster. The promoted shard is open again. Returns `false` if
+    /// the shard does not exist.
+    pub fn promote_shard(
+        &mut self,
+        source_uid: &SourceUid,
+        shard_id: &ShardId,
+        leader_id: NodeId,
+        follower_ids: Vec<NodeId>,
+    ) -> bool {
+        let Some(shard_entry) = self
+            .table_entries
+            .get_mut(source_uid)
+            .and_then(|table_entry| table_entry.shard_entries.get_mut(shard_id))
+        else {
+            return false;
+        };
+        remove_shard_from_ingesters_internal(
+            source_uid,
+            &shard_entry.shard,
+            &mut self.ingester_shards,
+        );
+        let mut follower_ids = follower_ids.into_iter().map(String::from);
+        shard_entry.leader_id = leader_id.into();
+        shard_entry.follower_id = follower_ids.next();
+        shard_entry.additional_follower_ids = follower_ids.collect();
+
+        if shard_entry.is_unavailable() {
+            shard_entry.set_shard_state(ShardState::Open);
+        }
+        for node in shard_entry.shard.ingesters() {
+            self.ingester_shards
+                .entry(node)
+                .or_default()
+                .entry(source_uid.clone())
+                .or_default()
+                .insert(shard_id.clone());
+        }
+        self.update_shard_metrics_for_source_uid(source_uid);
+        self.check_invariant();
+        true
+    }
+
     /// Removes the shards identified by their index UID, source ID, and shard IDs.
     pub fn delete_shards(&mut self, source_uid: &SourceUid, shard_ids: &[ShardId]) {
         let mut shard_entries_to_remove: Vec<ShardEntry> = Vec::new();
@@ -930,6 +973,59 @@ mod tests {
         assert_eq!(shards[0].shard_state(), ShardState::Closed);
     }
 
+    #[test]
+    fn test_shard_table_promote_shard() {
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+        let source_id = "test-source".to_string();
+
+        let mut shard_table = ShardTable::default();
+
+        let shard = Shard {
+            index_uid: index_uid.clone().into(),
+            source_id: source_id.clone(),
+            shard_id: Some(ShardId::from(1)),
+            leader_id: "test-ingester-0".to_string(),
+            follower_id: Some("test-ingester-1".to_string()),
+            additional_follower_ids: vec!["test-ingester-2".to_string()],
+            shard_state: ShardState::Unavailable as i32,
+            ..Default::default()
+        };
+        shard_table.insert_shards(&index_uid, &source_id, vec![shard]);
+
+        let source_uid = SourceUid {
+            index_uid,
+            source_id,
+        };
+        assert!(!shard_table.promote_shard(
+            &source_uid,
+            &ShardId::from(2),
+            "test-ingester-1".into(),
+            Vec::new(),
+        ));
+        assert!(shard_table.promote_shard(
+            &source_uid,
+            &ShardId::from(1),
+            "test-ingester-1".into(),
+            vec!["test-ingester-2".into()],
+        ));
+        let table_entry = shard_table.table_entries.get(&source_uid).unwrap();
+        let shards = table_entry.shards();
+        assert_eq!(shards[0].leader_id, "test-ingester-1");
+        assert_eq!(shards[0].follower_id(), "test-ingester-2");
+        assert!(shards[0].additional_follower_ids.is_empty());
+        assert_eq!(shards[0].shard_state(), ShardState::Open);
+
+        let shard_ids = shard_table
+            .list_shards_for_node(&NodeId::from("test-ingester-0"))
+            .unwrap();
+        assert!(shard_ids[&source_uid].is_empty());
+
+        let shard_ids = shard_table
+            .list_shards_for_node(&NodeId::from("test-ingester-2"))
+            .unwrap();
+        assert!(shard_ids[&source_uid].contains(&ShardId::from(1)));
+    }
+
     #[test]
     fn test_shard_table_delete_shards() {
         let mut shard_table = ShardTable::default();
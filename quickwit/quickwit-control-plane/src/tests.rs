@@ -90,6 +90,7 @@ pub fn test_indexer_change_stream(
                             client,
                             indexing_tasks,
                             indexing_capacity: CpuCapacity::from_cpu_millis(4_000),
+                            availability_zone: None,
                         },
                     ))
                 }
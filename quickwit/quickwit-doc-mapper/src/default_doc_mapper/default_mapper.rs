@@ -77,6 +77,10 @@ pub struct DefaultDocMapper {
     schema: Schema,
     /// List of field names used for tagging.
     tag_field_names: BTreeSet<String>,
+    /// List of fast field names whose min and max values are recorded in the split metadata.
+    min_max_field_names: BTreeSet<String>,
+    /// List of field names whose terms are recorded in a bloom filter in the split metadata.
+    bloom_filter_field_names: BTreeSet<String>,
     /// The partition key is a DSL used to route documents
     /// into specific splits.
     partition_key: RoutingExpr,
@@ -233,6 +237,18 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             validate_tag(tag_field_name, &schema)?;
         }
 
+        // Resolve field stats fields
+        let min_max_field_names: BTreeSet<String> =
+            builder.min_max_fields.iter().cloned().collect();
+        for min_max_field_name in &min_max_field_names {
+            validate_min_max_field(min_max_field_name, &schema)?;
+        }
+        let bloom_filter_field_names: BTreeSet<String> =
+            builder.bloom_filter_fields.iter().cloned().collect();
+        for bloom_filter_field_name in &bloom_filter_field_names {
+            validate_bloom_filter_field(bloom_filter_field_name, &schema)?;
+        }
+
         let partition_key_expr: &str = builder.partition_key.as_deref().unwrap_or("");
         let partition_key = RoutingExpr::new(partition_key_expr).with_context(|| {
             format!("failed to interpret the partition key: `{partition_key_expr}`")
@@ -255,6 +271,8 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             timestamp_field_name: builder.timestamp_field,
             field_mappings,
             tag_field_names,
+            min_max_field_names,
+            bloom_filter_field_names,
             required_fields,
             partition_key,
             max_num_partitions: builder.max_num_partitions,
@@ -317,6 +335,77 @@ fn validate_tag(tag_field_name: &str, schema: &Schema) -> Result<(), anyhow::Err
     Ok(())
 }
 
+/// Checks that a given field name is a valid candidate for min/max statistics.
+///
+/// The conditions are:
+/// - the field must be i64, u64, f64, or datetime.
+/// - the field must be fast.
+fn validate_min_max_field(field_name: &str, schema: &Schema) -> Result<(), anyhow::Error> {
+    let field = schema
+        .get_field(field_name)
+        .with_context(|| format!("unknown min/max field: `{field_name}`"))?;
+    let field_entry = schema.get_field_entry(field);
+    let field_type = field_entry.field_type();
+    match field_type {
+        FieldType::I64(_) | FieldType::U64(_) | FieldType::F64(_) | FieldType::Date(_) => {}
+        _ => {
+            bail!(
+                "min/max statistics are not allowed on `{}` fields",
+                field_type.value_type().name().to_lowercase()
+            )
+        }
+    }
+    if !field_entry.is_fast() {
+        bail!(
+            "min/max fields are required to be fast. (`{}` is not configured as fast)",
+            field_name
+        )
+    }
+    Ok(())
+}
+
+/// Checks that a given field name is a valid candidate for a bloom filter.
+///
+/// The conditions are:
+/// - the field must be str or bytes.
+/// - if str, the field must use the `raw` tokenizer for indexing.
+/// - the field must be indexed.
+fn validate_bloom_filter_field(field_name: &str, schema: &Schema) -> Result<(), anyhow::Error> {
+    let field = schema
+        .get_field(field_name)
+        .with_context(|| format!("unknown bloom filter field: `{field_name}`"))?;
+    let field_type = schema.get_field_entry(field).field_type();
+    match field_type {
+        FieldType::Str(options) => {
+            let tokenizer_opt = options
+                .get_indexing_options()
+                .map(|text_options: &tantivy::schema::TextFieldIndexing| text_options.tokenizer());
+            if tokenizer_opt != Some(RAW_TOKENIZER_NAME) {
+                bail!(
+                    "bloom filters are only allowed on indexed text fields with the `raw` \
+                     tokenizer"
+                );
+            }
+        }
+        FieldType::Bytes(options) => {
+            if !options.is_indexed() {
+                bail!(
+                    "bloom filter fields are required to be indexed. (`{}` is not configured as \
+                     indexed)",
+                    field_name
+                );
+            }
+        }
+        _ => {
+            bail!(
+                "bloom filters are not allowed on `{}` fields",
+                field_type.value_type().name().to_lowercase()
+            )
+        }
+    }
+    Ok(())
+}
+
 /// Checks that a given text/json field name has a registered tokenizer.
 fn validate_fields_tokenizers(
     schema: &Schema,
@@ -361,6 +450,11 @@ impl From<DefaultDocMapper> for DefaultDocMapperBuilder {
                 .map(ToString::to_string),
             field_mappings: default_doc_mapper.field_mappings.into(),
             tag_fields: default_doc_mapper.tag_field_names.into_iter().collect(),
+            min_max_fields: default_doc_mapper.min_max_field_names.into_iter().collect(),
+            bloom_filter_fields: default_doc_mapper
+                .bloom_filter_field_names
+                .into_iter()
+                .collect(),
             default_search_fields: default_doc_mapper.default_search_field_names,
             mode: default_doc_mapper.mode,
             partition_key: partition_key_opt,
@@ -637,6 +731,14 @@ impl DocMapper for DefaultDocMapper {
         self.tag_field_names.clone()
     }
 
+    fn min_max_field_names(&self) -> BTreeSet<String> {
+        self.min_max_field_names.clone()
+    }
+
+    fn bloom_filter_field_names(&self) -> BTreeSet<String> {
+        self.bloom_filter_field_names.clone()
+    }
+
     fn max_num_partitions(&self) -> NonZeroU32 {
         self.max_num_partitions
     }
@@ -1392,6 +1494,95 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_build_doc_mapper_with_field_stats_fields() {
+        let doc_mapper = r#"{
+            "min_max_fields": ["status", "timestamp"],
+            "bloom_filter_fields": ["trace_id", "span_id"],
+            "field_mappings": [
+                {"name": "status", "type": "u64", "fast": true},
+                {"name": "timestamp", "type": "datetime", "fast": true},
+                {"name": "trace_id", "type": "text", "tokenizer": "raw"},
+                {"name": "span_id", "type": "bytes"},
+                {"name": "body", "type": "text"}
+            ]
+        }"#;
+        let doc_mapper = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)
+            .unwrap()
+            .try_build()
+            .unwrap();
+        let min_max_field_names: Vec<String> =
+            doc_mapper.min_max_field_names().into_iter().collect();
+        assert_eq!(min_max_field_names, ["status", "timestamp"]);
+
+        let bloom_filter_named_fields = doc_mapper.bloom_filter_named_fields().unwrap();
+        assert_eq!(bloom_filter_named_fields.len(), 2);
+        assert_eq!(bloom_filter_named_fields[0].name, "span_id");
+        assert_eq!(bloom_filter_named_fields[1].name, "trace_id");
+
+        let doc_mapper_json = serde_json::to_value(&doc_mapper).unwrap();
+        assert_eq!(
+            doc_mapper_json["min_max_fields"],
+            json!(["status", "timestamp"])
+        );
+        assert_eq!(
+            doc_mapper_json["bloom_filter_fields"],
+            json!(["span_id", "trace_id"])
+        );
+    }
+
+    #[test]
+    fn test_fail_to_build_doc_mapper_with_wrong_field_stats_fields() {
+        let build_error = |doc_mapper: &str| {
+            serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)
+                .unwrap()
+                .try_build()
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            build_error(
+                r#"{
+                    "min_max_fields": ["status"],
+                    "field_mappings": [{"name": "status", "type": "u64", "fast": false}]
+                }"#
+            ),
+            "min/max fields are required to be fast. (`status` is not configured as fast)"
+        );
+        assert_eq!(
+            build_error(
+                r#"{
+                    "min_max_fields": ["is_error"],
+                    "field_mappings": [{"name": "is_error", "type": "bool", "fast": true}]
+                }"#
+            ),
+            "min/max statistics are not allowed on `bool` fields"
+        );
+        assert_eq!(
+            build_error(
+                r#"{
+                    "bloom_filter_fields": ["body"],
+                    "field_mappings": [{"name": "body", "type": "text"}]
+                }"#
+            ),
+            "bloom filters are only allowed on indexed text fields with the `raw` tokenizer"
+        );
+        assert_eq!(
+            build_error(
+                r#"{
+                    "bloom_filter_fields": ["payload"],
+                    "field_mappings": [{"name": "payload", "type": "bytes", "indexed": false}]
+                }"#
+            ),
+            "bloom filter fields are required to be indexed. (`payload` is not configured as \
+             indexed)"
+        );
+        assert_eq!(
+            build_error(r#"{"bloom_filter_fields": ["missing"]}"#),
+            "unknown bloom filter field: `missing`"
+        );
+    }
+
     // See #1132
     #[test]
     fn test_by_default_store_source_is_false_and_fields_are_stored_individually() {
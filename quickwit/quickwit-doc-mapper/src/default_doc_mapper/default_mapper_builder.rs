@@ -55,6 +55,14 @@ pub struct DefaultDocMapperBuilder {
     /// Name of the fields that are tagged.
     #[serde(default)]
     pub tag_fields: Vec<String>,
+    /// Name of the fast fields whose min and max values are recorded in the split metadata.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub min_max_fields: Vec<String>,
+    /// Name of the fields whose terms are recorded in a bloom filter in the split metadata.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bloom_filter_fields: Vec<String>,
     /// The partition key is a DSL used to route documents
     /// into specific splits.
     #[serde(default)]
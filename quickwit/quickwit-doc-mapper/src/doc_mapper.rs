@@ -126,20 +126,31 @@ pub trait DocMapper: Send + Sync + Debug + DynClone + 'static {
     /// Returns the tag `NameField`s on the current schema.
     /// Returns an error if a tag field is not found in this schema.
     fn tag_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
-        let index_schema = self.schema();
-        self.tag_field_names()
-            .iter()
-            .map(|field_name| {
-                index_schema
-                    .get_field(field_name)
-                    .context(format!("field `{field_name}` must exist in the schema"))
-                    .map(|field| NamedField {
-                        name: field_name.clone(),
-                        field,
-                        field_type: index_schema.get_field_entry(field).field_type().clone(),
-                    })
-            })
-            .collect::<Result<Vec<_>, _>>()
+        named_fields(&self.schema(), &self.tag_field_names())
+    }
+
+    /// Returns the names of the fast fields whose min and max values are recorded in the
+    /// split metadata.
+    fn min_max_field_names(&self) -> BTreeSet<String> {
+        Default::default()
+    }
+
+    /// Returns the min/max `NamedField`s on the current schema.
+    /// Returns an error if a min/max field is not found in this schema.
+    fn min_max_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
+        named_fields(&self.schema(), &self.min_max_field_names())
+    }
+
+    /// Returns the names of the fields whose terms are recorded in a bloom filter in the
+    /// split metadata.
+    fn bloom_filter_field_names(&self) -> BTreeSet<String> {
+        Default::default()
+    }
+
+    /// Returns the bloom filter `NamedField`s on the current schema.
+    /// Returns an error if a bloom filter field is not found in this schema.
+    fn bloom_filter_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
+        named_fields(&self.schema(), &self.bloom_filter_field_names())
     }
 
     /// Returns the maximum number of partitions.
@@ -160,6 +171,25 @@ pub struct NamedField {
     pub field_type: FieldType,
 }
 
+fn named_fields(
+    schema: &Schema,
+    field_names: &BTreeSet<String>,
+) -> anyhow::Result<Vec<NamedField>> {
+    field_names
+        .iter()
+        .map(|field_name| {
+            schema
+                .get_field(field_name)
+                .context(format!("field `{field_name}` must exist in the schema"))
+                .map(|field| NamedField {
+                    name: field_name.clone(),
+                    field,
+                    field_type: schema.get_field_entry(field).field_type().clone(),
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()
+}
+
 clone_trait_object!(DocMapper);
 
 /// Bounds for a range of terms, with an optional max count of terms being matched.
@@ -24,6 +24,7 @@ use std::time::Duration;
 
 use futures::Future;
 use quickwit_common::pretty::PrettySample;
+use quickwit_common::uri::Uri;
 use quickwit_common::{Progress, ServiceStream};
 use quickwit_metastore::{
     ListSplitsQuery, ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, SplitInfo,
@@ -34,7 +35,7 @@ use quickwit_proto::metastore::{
     MetastoreError, MetastoreResult, MetastoreService, MetastoreServiceClient,
 };
 use quickwit_proto::types::{IndexUid, SplitId};
-use quickwit_storage::{BulkDeleteError, Storage};
+use quickwit_storage::{BulkDeleteError, Storage, StorageResolver};
 use thiserror::Error;
 use time::OffsetDateTime;
 use tracing::{error, instrument};
@@ -77,6 +78,8 @@ pub struct SplitRemovalInfo {
 ///
 /// * `index_id` - The target index id.
 /// * `storage - The storage managing the target index.
+/// * `storage_resolver` - Resolves the storages of the splits moved out of the index storage by the
+///   index lifecycle policy.
 /// * `metastore` - The metastore managing the target index.
 /// * `staged_grace_period` -  Threshold period after which a staged split can be safely garbage
 ///   collected.
@@ -87,6 +90,7 @@ pub struct SplitRemovalInfo {
 pub async fn run_garbage_collect(
     index_uid: IndexUid,
     storage: Arc<dyn Storage>,
+    storage_resolver: &StorageResolver,
     mut metastore: MetastoreServiceClient,
     staged_grace_period: Duration,
     deletion_grace_period: Duration,
@@ -158,6 +162,7 @@ pub async fn run_garbage_collect(
         index_uid,
         updated_before_timestamp,
         storage,
+        storage_resolver,
         metastore,
         progress_opt,
     )
@@ -165,7 +170,7 @@ pub async fn run_garbage_collect(
 
     Ok(deleted_splits)
 }
-#[instrument(skip(storage, metastore, progress_opt))]
+#[instrument(skip(storage, storage_resolver, metastore, progress_opt))]
 /// Removes any splits marked for deletion which haven't been
 /// updated after `updated_before_timestamp` in batches of 1000 splits.
 ///
@@ -175,6 +180,7 @@ async fn delete_splits_marked_for_deletion(
     index_uid: IndexUid,
     updated_before_timestamp: i64,
     storage: Arc<dyn Storage>,
+    storage_resolver: &StorageResolver,
     mut metastore: MetastoreServiceClient,
     progress_opt: Option<&Progress>,
 ) -> SplitRemovalInfo {
@@ -222,6 +228,7 @@ async fn delete_splits_marked_for_deletion(
         let delete_splits_result = delete_splits_from_storage_and_metastore(
             index_uid.clone(),
             storage.clone(),
+            storage_resolver,
             metastore.clone(),
             splits_metadata_to_delete,
             progress_opt,
@@ -251,60 +258,97 @@ async fn delete_splits_marked_for_deletion(
 ///
 /// * `index_id` - The target index id.
 /// * `storage - The storage managing the target index.
+/// * `storage_resolver` - Resolves the storages of the splits moved out of the index storage by the
+///   index lifecycle policy.
 /// * `metastore` - The metastore managing the target index.
 /// * `splits`  - The list of splits to delete.
 /// * `progress` - For reporting progress (useful when called from within a quickwit actor).
 pub async fn delete_splits_from_storage_and_metastore(
     index_uid: IndexUid,
     storage: Arc<dyn Storage>,
+    storage_resolver: &StorageResolver,
     mut metastore: MetastoreServiceClient,
     splits: Vec<SplitMetadata>,
     progress_opt: Option<&Progress>,
 ) -> anyhow::Result<Vec<SplitInfo>, DeleteSplitsError> {
     let mut split_infos: HashMap<PathBuf, SplitInfo> = HashMap::with_capacity(splits.len());
+    let mut split_paths_per_storage_uri: HashMap<Option<Uri>, Vec<PathBuf>> = HashMap::new();
 
     for split in splits {
         let split_info = split.as_split_info();
+        split_paths_per_storage_uri
+            .entry(split.storage_uri)
+            .or_default()
+            .push(split_info.file_name.clone());
         split_infos.insert(split_info.file_name.clone(), split_info);
     }
-    let split_paths = split_infos
-        .keys()
-        .map(|split_path_buf| split_path_buf.as_path())
-        .collect::<Vec<&Path>>();
-    let delete_result = protect_future(progress_opt, storage.bulk_delete(&split_paths)).await;
-
-    if let Some(progress) = progress_opt {
-        progress.record_progress();
-    }
     let mut successes = Vec::with_capacity(split_infos.len());
     let mut storage_error: Option<BulkDeleteError> = None;
     let mut storage_failures = Vec::new();
 
-    match delete_result {
-        Ok(_) => successes.extend(split_infos.into_values()),
-        Err(bulk_delete_error) => {
-            let success_split_paths: HashSet<&PathBuf> =
-                bulk_delete_error.successes.iter().collect();
-            for (split_path, split_info) in split_infos {
-                if success_split_paths.contains(&split_path) {
-                    successes.push(split_info);
-                } else {
-                    storage_failures.push(split_info);
+    for (storage_uri_opt, split_path_bufs) in split_paths_per_storage_uri {
+        let split_storage = if let Some(storage_uri) = storage_uri_opt {
+            match storage_resolver.resolve(&storage_uri).await {
+                Ok(split_storage) => split_storage,
+                Err(error) => {
+                    error!(
+                        error=?error,
+                        index_id=index_uid.index_id,
+                        "Failed to resolve storage `{storage_uri}`."
+                    );
+                    storage_failures.extend(
+                        split_path_bufs
+                            .iter()
+                            .filter_map(|split_path| split_infos.remove(split_path)),
+                    );
+                    continue;
                 }
             }
-            let failed_split_paths = storage_failures
-                .iter()
-                .map(|split_info| split_info.file_name.as_path())
-                .collect::<Vec<_>>();
-            error!(
-                error=?bulk_delete_error.error,
-                index_id=index_uid.index_id,
-                "Failed to delete split file(s) {:?} from storage.",
-                PrettySample::new(&failed_split_paths, 5),
-            );
-            storage_error = Some(bulk_delete_error);
+        } else {
+            storage.clone()
+        };
+        let split_paths = split_path_bufs
+            .iter()
+            .map(|split_path_buf| split_path_buf.as_path())
+            .collect::<Vec<&Path>>();
+        let delete_result =
+            protect_future(progress_opt, split_storage.bulk_delete(&split_paths)).await;
+
+        if let Some(progress) = progress_opt {
+            progress.record_progress();
         }
-    };
+        match delete_result {
+            Ok(_) => successes.extend(
+                split_path_bufs
+                    .iter()
+                    .filter_map(|split_path| split_infos.remove(split_path)),
+            ),
+            Err(bulk_delete_error) => {
+                let success_split_paths: HashSet<&PathBuf> =
+                    bulk_delete_error.successes.iter().collect();
+                let mut failed_split_paths = Vec::new();
+
+                for split_path in &split_path_bufs {
+                    let Some(split_info) = split_infos.remove(split_path) else {
+                        continue;
+                    };
+                    if success_split_paths.contains(split_path) {
+                        successes.push(split_info);
+                    } else {
+                        failed_split_paths.push(split_path.as_path());
+                        storage_failures.push(split_info);
+                    }
+                }
+                error!(
+                    error=?bulk_delete_error.error,
+                    index_id=index_uid.index_id,
+                    "Failed to delete split file(s) {:?} from storage.",
+                    PrettySample::new(&failed_split_paths, 5),
+                );
+                storage_error = Some(bulk_delete_error);
+            }
+        };
+    }
     if !successes.is_empty() {
         let split_ids: Vec<SplitId> = successes
             .iter()
@@ -414,6 +458,7 @@ mod tests {
         run_garbage_collect(
             index_uid.clone(),
             storage.clone(),
+            &StorageResolver::for_test(),
             metastore.clone(),
             Duration::from_secs(30),
             Duration::from_secs(30),
@@ -442,6 +487,7 @@ mod tests {
         run_garbage_collect(
             index_uid.clone(),
             storage.clone(),
+            &StorageResolver::for_test(),
             metastore.clone(),
             Duration::from_secs(0),
             Duration::from_secs(30),
@@ -520,6 +566,7 @@ mod tests {
         run_garbage_collect(
             index_uid.clone(),
             storage.clone(),
+            &StorageResolver::for_test(),
             metastore.clone(),
             Duration::from_secs(30),
             Duration::from_secs(30),
@@ -548,6 +595,7 @@ mod tests {
         run_garbage_collect(
             index_uid.clone(),
             storage.clone(),
+            &StorageResolver::for_test(),
             metastore.clone(),
             Duration::from_secs(30),
             Duration::from_secs(0),
@@ -584,6 +632,7 @@ mod tests {
         run_garbage_collect(
             IndexUid::new_with_random_ulid("index-test-gc-deletes"),
             storage.clone(),
+            &StorageResolver::for_test(),
             MetastoreServiceClient::from(metastore),
             Duration::from_secs(30),
             Duration::from_secs(30),
@@ -646,6 +695,7 @@ mod tests {
         let deleted_split_infos = delete_splits_from_storage_and_metastore(
             index_uid.clone(),
             storage.clone(),
+            &StorageResolver::for_test(),
             metastore.clone(),
             vec![split_metadata],
             None,
@@ -744,6 +794,7 @@ mod tests {
         let error = delete_splits_from_storage_and_metastore(
             index_uid.clone(),
             storage.clone(),
+            &StorageResolver::for_test(),
             metastore.clone(),
             vec![split_metadata_0, split_metadata_1],
             None,
@@ -817,6 +868,7 @@ mod tests {
         let error = delete_splits_from_storage_and_metastore(
             index_uid.clone(),
             storage.clone(),
+            &StorageResolver::for_test(),
             MetastoreServiceClient::from(mock_metastore),
             vec![split_metadata_0, split_metadata_1],
             None,
@@ -208,6 +208,7 @@ impl IndexService {
         let deleted_splits = delete_splits_from_storage_and_metastore(
             index_uid.clone(),
             storage,
+            &self.storage_resolver,
             self.metastore.clone(),
             splits_metadata_to_delete,
             None,
@@ -351,6 +352,7 @@ impl IndexService {
         let deleted_entries = run_garbage_collect(
             index_uid,
             storage,
+            &self.storage_resolver,
             self.metastore.clone(),
             grace_period,
             // deletion_grace_period of zero, so that a cli call directly deletes splits after
@@ -405,6 +407,7 @@ impl IndexService {
         if let Err(err) = delete_splits_from_storage_and_metastore(
             index_uid.clone(),
             storage,
+            &self.storage_resolver,
             self.metastore.clone(),
             splits_metadata,
             None,
@@ -58,6 +58,7 @@ use tracing::{info, info_span, warn, Span};
 use ulid::Ulid;
 
 use crate::actors::IndexSerializer;
+use crate::index_sort_by_field;
 use crate::models::{
     CommitTrigger, EmptySplit, IndexedSplitBatchBuilder, IndexedSplitBuilder, NewPublishLock,
     NewPublishToken, ProcessedDoc, ProcessedDocBatch, PublishLock,
@@ -547,6 +548,7 @@ impl Indexer {
             compression_level: Some(indexing_settings.docstore_compression_level),
         });
         let index_settings = IndexSettings {
+            sort_by_field: indexing_settings.sort_by.as_ref().map(index_sort_by_field),
             docstore_blocksize: indexing_settings.docstore_blocksize,
             docstore_compression,
             docstore_compress_dedicated_thread: true,
@@ -713,6 +715,7 @@ mod tests {
     use std::time::Duration;
 
     use quickwit_actors::Universe;
+    use quickwit_config::{IndexSortBy, IndexSortOrder};
     use quickwit_doc_mapper::{default_doc_mapper_for_test, DefaultDocMapper};
     use quickwit_metastore::checkpoint::SourceCheckpointDelta;
     use quickwit_proto::metastore::{EmptyResponse, LastDeleteOpstampResponse};
@@ -1589,6 +1592,93 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_indexer_sorts_splits_by_sort_by_field() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let pipeline_id = IndexingPipelineId {
+            index_uid: IndexUid::new_with_random_ulid("test-index"),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_uid: PipelineUid::default(),
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let schema = doc_mapper.schema();
+        let body_field = schema.get_field("body").unwrap();
+        let timestamp_field = schema.get_field("timestamp").unwrap();
+        let indexing_directory = TempDirectory::for_test();
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.sort_by = Some(IndexSortBy {
+            field: "timestamp".to_string(),
+            order: IndexSortOrder::Desc,
+        });
+        let mut metastore = MetastoreServiceClient::mock();
+        metastore
+            .expect_last_delete_opstamp()
+            .times(1)
+            .returning(move |_last_delete_opstamp_request| Ok(LastDeleteOpstampResponse::new(10)));
+        metastore.expect_publish_splits().never();
+        let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            MetastoreServiceClient::from(metastore),
+            indexing_directory,
+            indexing_settings,
+            None,
+            index_serializer_mailbox,
+        );
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+        let processed_docs = [1_662_529_435, 1_662_529_437, 1_662_529_436]
+            .into_iter()
+            .map(|timestamp| ProcessedDoc {
+                doc: doc!(
+                    body_field=>"this is a test document",
+                    timestamp_field=>DateTime::from_timestamp_secs(timestamp)
+                ),
+                timestamp_opt: Some(DateTime::from_timestamp_secs(timestamp)),
+                partition: 0,
+                num_bytes: 30,
+            })
+            .collect();
+        indexer_mailbox
+            .send_message(ProcessedDocBatch::new(
+                processed_docs,
+                SourceCheckpointDelta::from_range(0..3),
+                true,
+            ))
+            .await
+            .unwrap();
+        universe
+            .send_exit_with_success(&indexer_mailbox)
+            .await
+            .unwrap();
+        let (exit_status, _indexer_counters) = indexer_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+
+        let mut output_messages: Vec<IndexedSplitBatchBuilder> =
+            index_serializer_inbox.drain_for_test_typed();
+        assert_eq!(output_messages.len(), 1);
+        let split = output_messages.remove(0).splits.remove(0).finalize()?;
+        let sort_by_field = split.index.settings().sort_by_field.clone().unwrap();
+        assert_eq!(sort_by_field.field, "timestamp");
+        assert_eq!(sort_by_field.order, tantivy::Order::Desc);
+
+        let searcher = split.index.reader()?.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let timestamp_column = segment_reader.fast_fields().date("timestamp")?;
+        let timestamps: Vec<i64> = (0..segment_reader.max_doc())
+            .map(|doc_id| {
+                timestamp_column
+                    .first(doc_id)
+                    .unwrap()
+                    .into_timestamp_secs()
+            })
+            .collect();
+        assert_eq!(timestamps, [1_662_529_437, 1_662_529_436, 1_662_529_435]);
+        universe.assert_quit().await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_indexer_checkpoint_on_all_failed_docs() -> anyhow::Result<()> {
         let pipeline_id = IndexingPipelineId {
@@ -381,7 +381,15 @@ impl IndexingPipeline {
 
         // Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let packager = Packager::new("Packager", tag_fields, uploader_mailbox);
+        let min_max_fields = self.params.doc_mapper.min_max_named_fields()?;
+        let bloom_filter_fields = self.params.doc_mapper.bloom_filter_named_fields()?;
+        let packager = Packager::new(
+            "Packager",
+            tag_fields,
+            min_max_fields,
+            bloom_filter_fields,
+            uploader_mailbox,
+        );
         let (packager_mailbox, packager_handle) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -900,6 +908,7 @@ mod tests {
             indexing_directory: TempDirectory::for_test(),
             metastore: metastore.clone(),
             split_store: split_store.clone(),
+            storage_resolver: StorageResolver::for_test(),
             merge_policy: default_merge_policy(),
             max_concurrent_split_uploads: 2,
             merge_io_throughput_limiter_opt: None,
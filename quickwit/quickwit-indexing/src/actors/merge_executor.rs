@@ -151,6 +151,13 @@ impl Handler<MergeScratch> for MergeExecutor {
 fn combine_index_meta(mut index_metas: Vec<IndexMeta>) -> anyhow::Result<IndexMeta> {
     let mut union_index_meta = index_metas.pop().with_context(|| "only one IndexMeta")?;
     for index_meta in index_metas {
+        // Tantivy merges sorted segments by interleaving them, so the merged split can only be
+        // sorted if all the splits are sorted the same way. This is not the case for splits
+        // produced before `sort_by` was configured or changed.
+        if index_meta.index_settings.sort_by_field != union_index_meta.index_settings.sort_by_field
+        {
+            union_index_meta.index_settings.sort_by_field = None;
+        }
         union_index_meta.segments.extend(index_meta.segments);
     }
     Ok(union_index_meta)
@@ -37,6 +37,7 @@ use quickwit_proto::indexing::IndexingPipelineId;
 use quickwit_proto::metastore::{
     ListSplitsRequest, MetastoreError, MetastoreService, MetastoreServiceClient,
 };
+use quickwit_storage::StorageResolver;
 use time::OffsetDateTime;
 use tokio::sync::Semaphore;
 use tracing::{debug, error, info, instrument};
@@ -279,7 +280,15 @@ impl MergePipeline {
 
         // Merge Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let merge_packager = Packager::new("MergePackager", tag_fields, merge_uploader_mailbox);
+        let min_max_fields = self.params.doc_mapper.min_max_named_fields()?;
+        let bloom_filter_fields = self.params.doc_mapper.bloom_filter_named_fields()?;
+        let merge_packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            min_max_fields,
+            bloom_filter_fields,
+            merge_uploader_mailbox,
+        );
         let (merge_packager_mailbox, merge_packager_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -314,6 +323,7 @@ impl MergePipeline {
         let merge_split_downloader = MergeSplitDownloader {
             scratch_directory: self.params.indexing_directory.clone(),
             split_store: self.params.split_store.clone(),
+            storage_resolver: self.params.storage_resolver.clone(),
             executor_mailbox: merge_executor_mailbox,
             io_controls: split_downloader_io_controls,
         };
@@ -476,6 +486,7 @@ pub struct MergePipelineParams {
     pub metastore: MetastoreServiceClient,
     pub merge_scheduler_service: Mailbox<MergeSchedulerService>,
     pub split_store: IndexingSplitStore,
+    pub storage_resolver: StorageResolver,
     pub merge_policy: Arc<dyn MergePolicy>,
     pub max_concurrent_split_uploads: usize, //< TODO share with the indexing pipeline.
     pub merge_io_throughput_limiter_opt: Option<Limiter>,
@@ -495,7 +506,7 @@ mod tests {
     use quickwit_proto::indexing::IndexingPipelineId;
     use quickwit_proto::metastore::MetastoreServiceClient;
     use quickwit_proto::types::{IndexUid, PipelineUid};
-    use quickwit_storage::RamStorage;
+    use quickwit_storage::{RamStorage, StorageResolver};
 
     use crate::actors::merge_pipeline::{MergePipeline, MergePipelineParams};
     use crate::merge_policy::default_merge_policy;
@@ -537,6 +548,7 @@ mod tests {
             metastore: MetastoreServiceClient::from(metastore),
             merge_scheduler_service: universe.get_or_spawn_one(),
             split_store,
+            storage_resolver: StorageResolver::for_test(),
             merge_policy: default_merge_policy(),
             max_concurrent_split_uploads: 2,
             merge_io_throughput_limiter_opt: None,
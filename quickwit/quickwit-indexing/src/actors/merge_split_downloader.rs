@@ -24,6 +24,7 @@ use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, Qu
 use quickwit_common::io::IoControls;
 use quickwit_common::temp_dir::{self, TempDirectory};
 use quickwit_metastore::SplitMetadata;
+use quickwit_storage::StorageResolver;
 use tantivy::Directory;
 use tracing::{debug, info, instrument};
 
@@ -36,6 +37,9 @@ use crate::split_store::IndexingSplitStore;
 pub struct MergeSplitDownloader {
     pub scratch_directory: TempDirectory,
     pub split_store: IndexingSplitStore,
+    /// Resolves the storages of the splits moved out of the index storage by the index lifecycle
+    /// policy.
+    pub storage_resolver: StorageResolver,
     pub executor_mailbox: Mailbox<MergeExecutor>,
     pub io_controls: IoControls,
 }
@@ -118,17 +122,42 @@ impl MergeSplitDownloader {
                 .set_kill_switch(ctx.kill_switch().clone());
             let _protect_guard = ctx.protect_zone();
             let tantivy_dir = self
-                .split_store
-                .fetch_and_open_split(split.split_id(), download_directory, &io_controls)
+                .fetch_and_open_split(split, download_directory, &io_controls)
                 .await
                 .map_err(|error| {
                     let split_id = split.split_id();
-                    anyhow::anyhow!(error).context(format!("failed to download split `{split_id}`"))
+                    error.context(format!("failed to download split `{split_id}`"))
                 })?;
             tantivy_dirs.push(tantivy_dir);
         }
         Ok(tantivy_dirs)
     }
+
+    async fn fetch_and_open_split(
+        &self,
+        split: &SplitMetadata,
+        download_directory: &Path,
+        io_controls: &IoControls,
+    ) -> anyhow::Result<Box<dyn Directory>> {
+        let Some(storage_uri) = &split.storage_uri else {
+            let tantivy_dir = self
+                .split_store
+                .fetch_and_open_split(split.split_id(), download_directory, io_controls)
+                .await?;
+            return Ok(tantivy_dir);
+        };
+        // The split was moved out of the index storage, typically to cold storage by the index
+        // lifecycle policy, so it is not in the split store.
+        let storage = self.storage_resolver.resolve(storage_uri).await?;
+        let tantivy_dir = IndexingSplitStore::fetch_and_open_split_from_storage(
+            &*storage,
+            split.split_id(),
+            download_directory,
+            io_controls,
+        )
+        .await?;
+        Ok(tantivy_dir)
+    }
 }
 
 #[cfg(test)]
@@ -174,6 +203,7 @@ mod tests {
         let merge_split_downloader = MergeSplitDownloader {
             scratch_directory,
             split_store,
+            storage_resolver: StorageResolver::for_test(),
             executor_mailbox: merge_executor_mailbox,
             io_controls: IoControls::default(),
         };
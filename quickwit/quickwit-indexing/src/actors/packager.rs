@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -27,17 +27,22 @@ use async_trait::async_trait;
 use fail::fail_point;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_common::bloom_filter::{serialize_bloom_filters, BloomFilter};
 use quickwit_common::runtimes::RuntimeType;
+use quickwit_common::shared_consts::BLOOM_FILTERS_FILE_NAME;
 use quickwit_common::temp_dir::TempDirectory;
 use quickwit_directories::write_hotcache;
 use quickwit_doc_mapper::tag_pruning::append_to_tag_set;
 use quickwit_doc_mapper::NamedField;
+use quickwit_metastore::FieldStats;
 use quickwit_proto::search::{
     serialize_split_fields, ListFieldType, ListFields, ListFieldsEntryResponse,
 };
+use tantivy::columnar::{DynamicColumn, HasAssociatedColumnType};
+use tantivy::fastfield::Column;
 use tantivy::index::FieldMetadata;
 use tantivy::schema::{FieldType, Type};
-use tantivy::{InvertedIndexReader, ReloadPolicy, SegmentMeta};
+use tantivy::{DateTime, InvertedIndexReader, ReloadPolicy, Searcher, SegmentMeta};
 use tokio::runtime::Handle;
 use tracing::{debug, info, instrument, warn};
 
@@ -48,6 +53,9 @@ const MAX_VALUES_PER_TAG_FIELD: usize = if cfg!(any(test, feature = "testsuite")
     1000
 };
 
+/// Target false positive rate of the bloom filters stored in the split file.
+const BLOOM_FILTER_FALSE_POSITIVE_RATE: f64 = 0.01;
+
 use crate::actors::Uploader;
 use crate::models::{
     EmptySplit, IndexedSplit, IndexedSplitBatch, PackagedSplit, PackagedSplitBatch,
@@ -59,6 +67,7 @@ use crate::models::{
 /// This includes the following steps:
 /// - commit: this step is CPU heavy
 /// - identifying the list of tags for the splits, and labelling it accordingly
+/// - computing the field statistics and bloom filters used to prune the split at search time
 /// - creating a bundle file
 /// - computing the hotcache
 /// - appending it to the split file.
@@ -70,18 +79,26 @@ pub struct Packager {
     uploader_mailbox: Mailbox<Uploader>,
     /// List of tag fields ([`Vec<NamedField>`]) defined in the index config.
     tag_fields: Vec<NamedField>,
+    /// List of fields whose min and max values are recorded in the split metadata.
+    min_max_fields: Vec<NamedField>,
+    /// List of fields whose terms are recorded in a bloom filter stored in the split file.
+    bloom_filter_fields: Vec<NamedField>,
 }
 
 impl Packager {
     pub fn new(
         actor_name: &'static str,
         tag_fields: Vec<NamedField>,
+        min_max_fields: Vec<NamedField>,
+        bloom_filter_fields: Vec<NamedField>,
         uploader_mailbox: Mailbox<Uploader>,
     ) -> Packager {
         Packager {
             actor_name,
             uploader_mailbox,
             tag_fields,
+            min_max_fields,
+            bloom_filter_fields,
         }
     }
 
@@ -92,8 +109,14 @@ impl Packager {
     ) -> anyhow::Result<PackagedSplit> {
         let segment_metas = split.index.searchable_segment_metas()?;
         assert_eq!(segment_metas.len(), 1);
-        let packaged_split =
-            create_packaged_split(&segment_metas[..], split, &self.tag_fields, ctx)?;
+        let packaged_split = create_packaged_split(
+            &segment_metas[..],
+            split,
+            &self.tag_fields,
+            &self.min_max_fields,
+            &self.bloom_filter_fields,
+            ctx,
+        )?;
         Ok(packaged_split)
     }
 }
@@ -273,14 +296,129 @@ fn try_extract_terms(
     Ok(terms)
 }
 
+/// Computes the min and max values of the `min_max_fields`.
+///
+/// Fields without any value in the split are omitted. Datetime ranges are rounded outwards to
+/// the second, so that they remain conservative whatever the precision of the field.
+fn compute_field_stats(
+    searcher: &Searcher,
+    min_max_fields: &[NamedField],
+) -> anyhow::Result<BTreeMap<String, FieldStats>> {
+    let mut field_stats = BTreeMap::new();
+
+    for named_field in min_max_fields {
+        let field_stats_opt = match named_field.field_type {
+            FieldType::I64(_) => column_min_max::<i64>(searcher, &named_field.name)?
+                .map(|(min, max)| FieldStats::I64Range { min, max }),
+            FieldType::U64(_) => column_min_max::<u64>(searcher, &named_field.name)?
+                .map(|(min, max)| FieldStats::U64Range { min, max }),
+            FieldType::F64(_) => column_min_max::<f64>(searcher, &named_field.name)?
+                .map(|(min, max)| FieldStats::F64Range { min, max }),
+            FieldType::Date(_) => {
+                column_min_max::<DateTime>(searcher, &named_field.name)?.map(|(min, max)| {
+                    FieldStats::DateRange {
+                        min: min
+                            .into_timestamp_nanos()
+                            .div_euclid(1_000_000_000)
+                            .saturating_mul(1_000_000_000),
+                        max: max
+                            .into_timestamp_nanos()
+                            .saturating_add(999_999_999)
+                            .div_euclid(1_000_000_000)
+                            .saturating_mul(1_000_000_000),
+                    }
+                })
+            }
+            _ => {
+                warn!(
+                    field = %named_field.name,
+                    "min/max statistics are not supported on this field type"
+                );
+                None
+            }
+        };
+        if let Some(stats) = field_stats_opt {
+            field_stats.insert(named_field.name.clone(), stats);
+        }
+    }
+    Ok(field_stats)
+}
+
+/// Computes the bloom filters of the `bloom_filter_fields`, sized to the number of terms of each
+/// field in the split.
+///
+/// Fields without any term in the split are omitted.
+fn compute_bloom_filters(
+    searcher: &Searcher,
+    bloom_filter_fields: &[NamedField],
+) -> anyhow::Result<BTreeMap<String, BloomFilter>> {
+    let mut bloom_filters = BTreeMap::new();
+
+    for named_field in bloom_filter_fields {
+        let inverted_indexes = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment| segment.inverted_index(named_field.field))
+            .collect::<Result<Vec<_>, _>>()?;
+        let num_terms = inverted_indexes
+            .iter()
+            .map(|inv_index| inv_index.terms().num_terms())
+            .sum::<usize>();
+        if num_terms == 0 {
+            continue;
+        }
+        let mut bloom_filter =
+            BloomFilter::with_capacity(num_terms, BLOOM_FILTER_FALSE_POSITIVE_RATE);
+
+        for inv_index in &inverted_indexes {
+            let mut terms_streamer = inv_index.terms().stream()?;
+            while let Some((term_data, _)) = terms_streamer.next() {
+                bloom_filter.insert(term_data);
+            }
+        }
+        bloom_filters.insert(named_field.name.clone(), bloom_filter);
+    }
+    Ok(bloom_filters)
+}
+
+/// Returns the min and max values of a fast field across all the segments, or `None` if the
+/// field has no value.
+fn column_min_max<T>(searcher: &Searcher, field_name: &str) -> anyhow::Result<Option<(T, T)>>
+where
+    T: HasAssociatedColumnType,
+    DynamicColumn: Into<Option<Column<T>>>,
+{
+    let mut min_max_opt: Option<(T, T)> = None;
+
+    for segment_reader in searcher.segment_readers() {
+        let Some(column) = segment_reader.fast_fields().column_opt::<T>(field_name)? else {
+            continue;
+        };
+        if column.values.num_vals() == 0 {
+            continue;
+        }
+        let (column_min, column_max) = (column.min_value(), column.max_value());
+        min_max_opt = Some(match min_max_opt {
+            Some((min, max)) => (
+                if column_min < min { column_min } else { min },
+                if column_max > max { column_max } else { max },
+            ),
+            None => (column_min, column_max),
+        });
+    }
+    Ok(min_max_opt)
+}
+
 fn create_packaged_split(
     segment_metas: &[SegmentMeta],
     split: IndexedSplit,
     tag_fields: &[NamedField],
+    min_max_fields: &[NamedField],
+    bloom_filter_fields: &[NamedField],
     ctx: &ActorContext<Packager>,
 ) -> anyhow::Result<PackagedSplit> {
     debug!(split_id = split.split_id(), "create-packaged-split");
-    let split_files = list_split_files(segment_metas, &split.split_scratch_directory)?;
+    let mut split_files = list_split_files(segment_metas, &split.split_scratch_directory)?;
 
     // Extracts tag values from inverted indexes only when a field cardinality is less
     // than `MAX_VALUES_PER_TAG_FIELD`.
@@ -314,6 +452,24 @@ fn create_packaged_split(
 
     ctx.record_progress();
 
+    debug!(split_id = split.split_id(), "compute-field-stats");
+    let field_stats = compute_field_stats(&index_reader.searcher(), min_max_fields)?;
+    ctx.record_progress();
+
+    debug!(split_id = split.split_id(), "compute-bloom-filters");
+    let bloom_filters = compute_bloom_filters(&index_reader.searcher(), bloom_filter_fields)?;
+    if !bloom_filters.is_empty() {
+        // The bloom filters are stored in the split file rather than in the split metadata, and
+        // are only loaded by the searchers when a query targets one of the fields.
+        let bloom_filters_path = split
+            .split_scratch_directory
+            .path()
+            .join(BLOOM_FILTERS_FILE_NAME);
+        std::fs::write(&bloom_filters_path, serialize_bloom_filters(&bloom_filters))?;
+        split_files.push(bloom_filters_path);
+    }
+    ctx.record_progress();
+
     debug!(split_id = split.split_id(), "build-hotcache");
     let mut hotcache_bytes = Vec::new();
     build_hotcache(split.split_scratch_directory.path(), &mut hotcache_bytes)?;
@@ -326,6 +482,7 @@ fn create_packaged_split(
         split_attrs: split.split_attrs,
         split_scratch_directory: split.split_scratch_directory,
         tags,
+        field_stats,
         split_files,
         hotcache_bytes,
     };
@@ -386,6 +543,7 @@ mod tests {
     use std::ops::RangeInclusive;
 
     use quickwit_actors::{ObservationType, Universe};
+    use quickwit_common::bloom_filter::deserialize_bloom_filters;
     use quickwit_metastore::checkpoint::IndexCheckpointDelta;
     use quickwit_proto::indexing::IndexingPipelineId;
     use quickwit_proto::search::{deserialize_split_fields, ListFieldsEntryResponse};
@@ -463,6 +621,7 @@ mod tests {
             schema_builder.add_f64_field("tag_f64", NumericOptions::default().set_indexed());
         let tag_bool =
             schema_builder.add_bool_field("tag_bool", NumericOptions::default().set_indexed());
+        let fast_i64 = schema_builder.add_i64_field("fast_i64", FAST);
         let schema = schema_builder.build();
         let index_builder = IndexBuilder::new()
             .settings(IndexSettings::default())
@@ -493,6 +652,7 @@ mod tests {
                     tag_i64 => -42i64,
                     tag_f64 => -42.02f64,
                     tag_bool => true,
+                    fast_i64 => -(num as i64),
                 );
                 index_writer.add_document(doc)?;
                 num_docs += 1;
@@ -536,7 +696,7 @@ mod tests {
         Ok(indexed_split)
     }
 
-    fn get_tag_fields(schema: Schema, field_names: &[&str]) -> Vec<NamedField> {
+    fn get_named_fields(schema: Schema, field_names: &[&str]) -> Vec<NamedField> {
         field_names
             .iter()
             .map(|field_name| {
@@ -560,13 +720,21 @@ mod tests {
             DateTime::from_timestamp_secs(1628203589),
             DateTime::from_timestamp_secs(1628203640),
         ])?;
-        let tag_fields = get_tag_fields(
+        let tag_fields = get_named_fields(
             indexed_split.index.schema(),
             &[
                 "tag_str", "tag_many", "tag_u64", "tag_i64", "tag_f64", "tag_bool",
             ],
         );
-        let packager = Packager::new("TestPackager", tag_fields, mailbox);
+        let min_max_fields = get_named_fields(indexed_split.index.schema(), &["fast_i64"]);
+        let bloom_filter_fields = get_named_fields(indexed_split.index.schema(), &["tag_many"]);
+        let packager = Packager::new(
+            "TestPackager",
+            tag_fields,
+            min_max_fields,
+            bloom_filter_fields,
+            mailbox,
+        );
         let (packager_mailbox, packager_handle) = universe.spawn_builder().spawn(packager);
         packager_mailbox
             .send_message(IndexedSplitBatch {
@@ -603,6 +771,22 @@ mod tests {
                 "tag_u64:42"
             ]
         );
+        assert_eq!(
+            split.field_stats.get("fast_i64"),
+            Some(&FieldStats::I64Range { min: -9, max: -1 })
+        );
+        assert!(!split.field_stats.contains_key("tag_many"));
+
+        let bloom_filters_path = split
+            .split_scratch_directory
+            .path()
+            .join(BLOOM_FILTERS_FILE_NAME);
+        assert!(split.split_files.contains(&bloom_filters_path));
+        let bloom_filters =
+            deserialize_bloom_filters(&std::fs::read(&bloom_filters_path)?).unwrap();
+        assert_eq!(bloom_filters.len(), 1);
+        assert!(bloom_filters["tag_many"].contains(b"many-1"));
+        assert!(bloom_filters["tag_many"].contains(b"many-9"));
         assert_eq!(
             split.split_attrs.time_range,
             Some(
@@ -159,7 +159,7 @@ impl Handler<SplitsUpdate> for Publisher {
                 let suggest_truncate_res = ctx
                     .send_message(
                         source_mailbox,
-                        SuggestTruncate(checkpoint.source_delta.get_source_checkpoint()),
+                        SuggestTruncate(checkpoint.source_delta.get_source_checkpoint(), split_ids),
                     )
                     .await;
                 if let Err(send_truncate_err) = suggest_truncate_res {
@@ -262,19 +262,18 @@ mod tests {
         let publisher_observation = publisher_handle.process_pending_and_observe().await.state;
         assert_eq!(publisher_observation.num_published_splits, 1);
 
-        let suggest_truncate_checkpoints: Vec<SourceCheckpoint> = source_inbox
-            .drain_for_test_typed::<SuggestTruncate>()
-            .into_iter()
-            .map(|msg| msg.0)
-            .collect();
+        let suggest_truncates: Vec<SuggestTruncate> =
+            source_inbox.drain_for_test_typed::<SuggestTruncate>();
 
-        assert_eq!(suggest_truncate_checkpoints.len(), 1);
+        assert_eq!(suggest_truncates.len(), 1);
         assert_eq!(
-            suggest_truncate_checkpoints[0]
+            suggest_truncates[0]
+                .0
                 .position_for_partition(&PartitionId::default())
                 .unwrap(),
             &Position::offset(2u64)
         );
+        assert_eq!(suggest_truncates[0].1, ["split".to_string()]);
 
         let merger_msgs: Vec<NewSplits> = merge_planner_inbox.drain_for_test_typed::<NewSplits>();
         assert_eq!(merger_msgs.len(), 1);
@@ -325,6 +325,7 @@ impl Handler<PackagedSplitBatch> for Uploader {
                         &merge_policy,
                         &packaged_split.split_attrs,
                         packaged_split.tags.clone(),
+                        packaged_split.field_stats.clone(),
                         split_streamer.footer_range.start..split_streamer.footer_range.end,
                     );
 
@@ -567,6 +568,7 @@ mod tests {
                     serialized_split_fields: Vec::new(),
                     split_scratch_directory,
                     tags: Default::default(),
+                    field_stats: Default::default(),
                     hotcache_bytes: Vec::new(),
                     split_files: Vec::new(),
                 }],
@@ -679,6 +681,7 @@ mod tests {
             serialized_split_fields: Vec::new(),
             split_scratch_directory: split_scratch_directory_1,
             tags: Default::default(),
+            field_stats: Default::default(),
             split_files: Vec::new(),
             hotcache_bytes: Vec::new(),
         };
@@ -703,6 +706,7 @@ mod tests {
             serialized_split_fields: Vec::new(),
             split_scratch_directory: split_scratch_directory_2,
             tags: Default::default(),
+            field_stats: Default::default(),
             split_files: Vec::new(),
             hotcache_bytes: Vec::new(),
         };
@@ -823,6 +827,7 @@ mod tests {
                     serialized_split_fields: Vec::new(),
                     split_scratch_directory,
                     tags: Default::default(),
+                    field_stats: Default::default(),
                     hotcache_bytes: Vec::new(),
                     split_files: Vec::new(),
                 }],
@@ -1002,6 +1007,7 @@ mod tests {
                     serialized_split_fields: Vec::new(),
                     split_scratch_directory,
                     tags: Default::default(),
+                    field_stats: Default::default(),
                     hotcache_bytes: Vec::new(),
                     split_files: Vec::new(),
                 }],
@@ -22,11 +22,12 @@
 use quickwit_actors::{Mailbox, Universe};
 use quickwit_cluster::Cluster;
 use quickwit_common::pubsub::EventBroker;
-use quickwit_config::NodeConfig;
+use quickwit_config::{IndexSortBy, IndexSortOrder, NodeConfig};
 use quickwit_ingest::{IngestApiService, IngesterPool};
 use quickwit_proto::indexing::PipelineMetrics;
 use quickwit_proto::metastore::MetastoreServiceClient;
 use quickwit_storage::StorageResolver;
+use tantivy::{IndexSortByField, Order};
 use tracing::info;
 
 use crate::actors::MergeSchedulerService;
@@ -64,6 +65,18 @@ pub fn new_split_id() -> String {
     ulid::Ulid::new().to_string()
 }
 
+/// Converts the `sort_by` indexing setting into the tantivy index sort setting.
+pub fn index_sort_by_field(sort_by: &IndexSortBy) -> IndexSortByField {
+    let order = match sort_by.order {
+        IndexSortOrder::Asc => Order::Asc,
+        IndexSortOrder::Desc => Order::Desc,
+    };
+    IndexSortByField {
+        field: sort_by.field.clone(),
+        order,
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn start_indexing_service(
     universe: &Universe,
@@ -20,6 +20,7 @@
 mod const_write_amplification;
 mod nop_merge_policy;
 mod stable_log_merge_policy;
+mod time_partitioned_merge_policy;
 
 use std::fmt;
 use std::ops::Deref;
@@ -34,6 +35,7 @@ use quickwit_metastore::{SplitMaturity, SplitMetadata};
 use serde::Serialize;
 pub(crate) use stable_log_merge_policy::StableLogMergePolicy;
 use tantivy::TrackedObject;
+pub(crate) use time_partitioned_merge_policy::TimePartitionedMergePolicy;
 use tracing::{info_span, Span};
 
 use crate::actors::MergePermit;
@@ -178,6 +180,11 @@ pub fn merge_policy_from_settings(settings: &IndexingSettings) -> Arc<dyn MergeP
             let merge_policy = StableLogMergePolicy::new(config, settings.split_num_docs_target);
             Arc::new(merge_policy)
         }
+        MergePolicyConfig::TimePartitioned(config) => {
+            let merge_policy =
+                TimePartitionedMergePolicy::new(config, settings.split_num_docs_target);
+            Arc::new(merge_policy)
+        }
     }
 }
 
@@ -380,7 +387,7 @@ pub mod tests {
             pipeline_uid: PipelineUid::for_test(0u128),
         };
         let split_attrs = merge_split_attrs(merged_split_id, &pipeline_id, splits);
-        create_split_metadata(merge_policy, &split_attrs, tags, 0..0)
+        create_split_metadata(merge_policy, &split_attrs, tags, Default::default(), 0..0)
     }
 
     fn apply_merge(
@@ -0,0 +1,186 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use quickwit_config::merge_policy_config::TimePartitionedMergePolicyConfig;
+use quickwit_metastore::{SplitMaturity, SplitMetadata};
+
+use crate::merge_policy::{MergeOperation, MergePolicy, StableLogMergePolicy};
+
+/// `TimePartitionedMergePolicy` partitions the splits into time windows of a fixed duration,
+/// aligned on the Unix epoch, and applies the [`StableLogMergePolicy`] to each window
+/// independently.
+///
+/// Splits are never merged across windows, which keeps time pruning and time-based retention
+/// efficient. Splits whose time range spans several windows, for instance because they were
+/// indexed around the end of a window, are only merged with splits spanning the exact same
+/// windows. Splits without a time range are merged together.
+#[derive(Debug, Clone)]
+pub struct TimePartitionedMergePolicy {
+    window_secs: i64,
+    stable_log_merge_policy: StableLogMergePolicy,
+}
+
+impl TimePartitionedMergePolicy {
+    pub fn new(
+        config: TimePartitionedMergePolicyConfig,
+        split_num_docs_target: usize,
+    ) -> TimePartitionedMergePolicy {
+        let window_secs = config.window.as_secs().max(1) as i64;
+        let stable_log_merge_policy =
+            StableLogMergePolicy::new(config.stable_log_config(), split_num_docs_target);
+        TimePartitionedMergePolicy {
+            window_secs,
+            stable_log_merge_policy,
+        }
+    }
+
+    /// Returns the first and last windows spanned by the split, or `None` for splits without a
+    /// time range. Splits are only merged with splits spanning the same windows.
+    fn split_windows(&self, split: &SplitMetadata) -> Option<(i64, i64)> {
+        let time_range = split.time_range.as_ref()?;
+        let start_window = time_range.start().div_euclid(self.window_secs);
+        let end_window = time_range.end().div_euclid(self.window_secs);
+        Some((start_window, end_window))
+    }
+}
+
+impl MergePolicy for TimePartitionedMergePolicy {
+    fn operations(&self, splits: &mut Vec<SplitMetadata>) -> Vec<MergeOperation> {
+        let mut splits_per_window: BTreeMap<Option<(i64, i64)>, Vec<SplitMetadata>> =
+            BTreeMap::new();
+
+        for split in splits.drain(..) {
+            let windows = self.split_windows(&split);
+            splits_per_window.entry(windows).or_default().push(split);
+        }
+        let mut remaining_splits = Vec::new();
+        let mut operations = Vec::new();
+
+        for mut window_splits in splits_per_window.into_values() {
+            operations.extend(self.stable_log_merge_policy.operations(&mut window_splits));
+            remaining_splits.append(&mut window_splits);
+        }
+        *splits = remaining_splits;
+        operations
+    }
+
+    fn split_maturity(&self, split_num_docs: usize, split_num_merge_ops: usize) -> SplitMaturity {
+        self.stable_log_merge_policy
+            .split_maturity(split_num_docs, split_num_merge_ops)
+    }
+
+    #[cfg(test)]
+    fn check_is_valid(&self, merge_op: &MergeOperation, remaining_splits: &[SplitMetadata]) {
+        let windows: Vec<Option<(i64, i64)>> = merge_op
+            .splits_as_slice()
+            .iter()
+            .map(|split| self.split_windows(split))
+            .collect();
+        assert!(windows.iter().all(|window| *window == windows[0]));
+        self.stable_log_merge_policy
+            .check_is_valid(merge_op, remaining_splits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    const DAY_SECS: i64 = 24 * 3_600;
+
+    fn split_for_test(split_id: &str, start: i64, end: i64) -> SplitMetadata {
+        SplitMetadata {
+            split_id: split_id.to_string(),
+            num_docs: 1_000,
+            time_range: Some(start..=end),
+            create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            maturity: SplitMaturity::Immature {
+                maturation_period: Duration::from_secs(3_600),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_proptest() {
+        let config = TimePartitionedMergePolicyConfig {
+            window: Duration::from_secs(DAY_SECS as u64),
+            min_level_num_docs: 100_000,
+            merge_factor: 4,
+            max_merge_factor: 6,
+            maturation_period: Duration::from_secs(3_600),
+        };
+        let merge_policy = TimePartitionedMergePolicy::new(config, 10_000_000);
+        crate::merge_policy::tests::proptest_merge_policy(&merge_policy);
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_merges_per_window() {
+        let config = TimePartitionedMergePolicyConfig {
+            merge_factor: 3,
+            max_merge_factor: 3,
+            ..Default::default()
+        };
+        let merge_policy = TimePartitionedMergePolicy::new(config, 10_000_000);
+        let day = 19_000 * DAY_SECS;
+        let mut splits = vec![
+            split_for_test("day_0_a", day, day + 10),
+            split_for_test("day_0_b", day + 100, day + 200),
+            split_for_test("day_0_c", day + DAY_SECS - 10, day + DAY_SECS - 1),
+            split_for_test("day_1_a", day + DAY_SECS, day + DAY_SECS + 10),
+            split_for_test("day_1_b", day + DAY_SECS + 100, day + DAY_SECS + 200),
+            // Splits spanning both days are only merged together.
+            split_for_test("across_days_a", day + DAY_SECS - 10, day + DAY_SECS + 100),
+            split_for_test("day_1_c", day + DAY_SECS + 300, day + DAY_SECS + 400),
+            split_for_test("across_days_b", day + DAY_SECS - 20, day + DAY_SECS + 10),
+            split_for_test("across_days_c", day + 100, day + DAY_SECS + 200),
+        ];
+        let operations = merge_policy.operations(&mut splits);
+        assert_eq!(operations.len(), 3);
+
+        let mut merged_split_ids: Vec<Vec<&str>> = operations
+            .iter()
+            .map(|operation| {
+                let mut split_ids: Vec<&str> = operation
+                    .splits_as_slice()
+                    .iter()
+                    .map(|split| split.split_id())
+                    .collect();
+                split_ids.sort();
+                split_ids
+            })
+            .collect();
+        merged_split_ids.sort();
+        assert_eq!(
+            merged_split_ids,
+            [
+                vec!["across_days_a", "across_days_b", "across_days_c"],
+                vec!["day_0_a", "day_0_b", "day_0_c"],
+                vec!["day_1_a", "day_1_b", "day_1_c"],
+            ]
+        );
+        assert!(splits.is_empty());
+    }
+}
@@ -29,12 +29,19 @@ use quickwit_cluster::{Cluster, ListenerHandle};
 use quickwit_common::pretty::PrettyDisplay;
 use quickwit_common::pubsub::{Event, EventBroker};
 use quickwit_proto::indexing::ShardPositionsUpdate;
-use quickwit_proto::types::{Position, ShardId, SourceUid};
+use quickwit_proto::types::{Position, ShardId, SourceUid, SplitId};
 use tracing::{debug, error, info, warn};
 
 /// Prefix used in chitchat to publish the shard positions.
 const SHARD_POSITIONS_PREFIX: &str = "indexer.shard_positions:";
 
+/// Prefix used in chitchat to publish the shard positions along with the IDs of the splits whose
+/// publication advanced them. The values are formatted as `<position>|<split_id>,<split_id>,...`.
+///
+/// These keys are set right before the corresponding shard position keys, so nodes receive the
+/// split IDs before the plain positions, which they then ignore.
+const PUBLISHED_SPLITS_PREFIX: &str = "indexer.published_splits:";
+
 /// This event means that a pipeline running in the current node (hence "local")
 /// performed a publish on an ingest pipeline, and hence the position of a shard has been updated.
 ///
@@ -53,6 +60,7 @@ pub(crate) struct LocalShardPositionsUpdate {
     source_uid: SourceUid,
     // This list can be partial: not all shards for the source need to be listed here.
     shard_positions: Vec<(ShardId, Position)>,
+    published_split_ids: Vec<SplitId>,
 }
 
 impl LocalShardPositionsUpdate {
@@ -60,8 +68,15 @@ impl LocalShardPositionsUpdate {
         LocalShardPositionsUpdate {
             source_uid,
             shard_positions,
+            published_split_ids: Vec::new(),
         }
     }
+
+    /// Sets the IDs of the splits whose publication advanced the shard positions.
+    pub fn with_published_split_ids(mut self, published_split_ids: Vec<SplitId>) -> Self {
+        self.published_split_ids = published_split_ids;
+        self
+    }
 }
 
 /// This event is an internal detail of the `ShardPositionsService`.
@@ -73,6 +88,7 @@ struct ClusterShardPositionsUpdate {
     pub source_uid: SourceUid,
     pub shard_id: ShardId,
     pub position: Position,
+    pub published_split_ids: Vec<SplitId>,
 }
 
 impl Event for LocalShardPositionsUpdate {}
@@ -87,6 +103,7 @@ pub struct ShardPositionsService {
     cluster: Cluster,
     event_broker: EventBroker,
     cluster_listener_handle_opt: Option<ListenerHandle>,
+    published_splits_listener_handle_opt: Option<ListenerHandle>,
 }
 
 fn parse_shard_positions_from_kv(
@@ -106,15 +123,31 @@ fn parse_shard_positions_from_kv(
         source_uid,
         shard_id,
         position,
+        published_split_ids: Vec::new(),
     })
 }
 
+fn parse_published_splits_from_kv(
+    key: &str,
+    value: &str,
+) -> anyhow::Result<ClusterShardPositionsUpdate> {
+    let (position_str, split_ids_str) = value.split_once('|').context("invalid value")?;
+    let mut update = parse_shard_positions_from_kv(key, position_str)?;
+    update.published_split_ids = split_ids_str
+        .split(',')
+        .filter(|split_id| !split_id.is_empty())
+        .map(|split_id| split_id.to_string())
+        .collect();
+    Ok(update)
+}
+
 fn push_position_update(
     shard_positions_service_mailbox: &Mailbox<ShardPositionsService>,
     key: &str,
     value: &str,
+    parse_fn: fn(&str, &str) -> anyhow::Result<ClusterShardPositionsUpdate>,
 ) {
-    let shard_positions = match parse_shard_positions_from_kv(key, value) {
+    let shard_positions = match parse_fn(key, value) {
         Ok(shard_positions) => shard_positions,
         Err(error) => {
             error!(key=key, value=value, error=%error, "failed to parse shard positions from cluster kv");
@@ -137,10 +170,29 @@ impl Actor for ShardPositionsService {
     async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
         let mailbox = ctx.mailbox().clone();
 
+        self.published_splits_listener_handle_opt = Some(
+            self.cluster
+                .subscribe(PUBLISHED_SPLITS_PREFIX, move |event| {
+                    push_position_update(
+                        &mailbox,
+                        event.key,
+                        event.value,
+                        parse_published_splits_from_kv,
+                    );
+                })
+                .await,
+        );
+        let mailbox = ctx.mailbox().clone();
+
         self.cluster_listener_handle_opt = Some(
             self.cluster
                 .subscribe(SHARD_POSITIONS_PREFIX, move |event| {
-                    push_position_update(&mailbox, event.key, event.value);
+                    push_position_update(
+                        &mailbox,
+                        event.key,
+                        event.value,
+                        parse_shard_positions_from_kv,
+                    );
                 })
                 .await,
         );
@@ -157,9 +209,23 @@ impl Actor for ShardPositionsService {
         let chitchat_lock = chitchat.lock().await;
         let mut num_keys = 0;
         for node_state in chitchat_lock.node_states().values() {
+            for (key, versioned_value) in node_state.iter_prefix(PUBLISHED_SPLITS_PREFIX) {
+                let key_stripped = key.strip_prefix(PUBLISHED_SPLITS_PREFIX).unwrap();
+                push_position_update(
+                    ctx.mailbox(),
+                    key_stripped,
+                    &versioned_value.value,
+                    parse_published_splits_from_kv,
+                );
+            }
             for (key, versioned_value) in node_state.iter_prefix(SHARD_POSITIONS_PREFIX) {
                 let key_stripped = key.strip_prefix(SHARD_POSITIONS_PREFIX).unwrap();
-                push_position_update(ctx.mailbox(), key_stripped, &versioned_value.value);
+                push_position_update(
+                    ctx.mailbox(),
+                    key_stripped,
+                    &versioned_value.value,
+                    parse_shard_positions_from_kv,
+                );
             }
             num_keys += 1;
             // It is tempting to yield here, but we are holding the chitchat lock.
@@ -205,6 +271,7 @@ impl ShardPositionsService {
             cluster,
             event_broker,
             cluster_listener_handle_opt: None,
+            published_splits_listener_handle_opt: None,
         }
     }
 }
@@ -222,11 +289,16 @@ impl Handler<ClusterShardPositionsUpdate> for ShardPositionsService {
             source_uid,
             shard_id,
             position,
+            published_split_ids,
         } = update;
         let updated_shard_positions = self.apply_update(&source_uid, vec![(shard_id, position)]);
         debug!(updated_shard_positions=?updated_shard_positions, "cluster position update");
         if !updated_shard_positions.is_empty() {
-            self.publish_shard_updates_to_event_broker(source_uid, updated_shard_positions);
+            self.publish_shard_updates_to_event_broker(
+                source_uid,
+                updated_shard_positions,
+                published_split_ids,
+            );
         }
         Ok(())
     }
@@ -244,15 +316,24 @@ impl Handler<LocalShardPositionsUpdate> for ShardPositionsService {
         let LocalShardPositionsUpdate {
             source_uid,
             shard_positions,
+            published_split_ids,
         } = update;
         let updated_shard_positions: Vec<(ShardId, Position)> =
             self.apply_update(&source_uid, shard_positions);
         if updated_shard_positions.is_empty() {
             return Ok(());
         }
-        self.publish_positions_into_chitchat(&source_uid, &updated_shard_positions)
-            .await;
-        self.publish_shard_updates_to_event_broker(source_uid, updated_shard_positions);
+        self.publish_positions_into_chitchat(
+            &source_uid,
+            &updated_shard_positions,
+            &published_split_ids,
+        )
+        .await;
+        self.publish_shard_updates_to_event_broker(
+            source_uid,
+            updated_shard_positions,
+            published_split_ids,
+        );
         Ok(())
     }
 }
@@ -262,12 +343,20 @@ impl ShardPositionsService {
         &self,
         source_uid: &SourceUid,
         shard_positions: &[(ShardId, Position)],
+        published_split_ids: &[SplitId],
     ) {
         let SourceUid {
             index_uid,
             source_id,
         } = &source_uid;
         for (shard_id, position) in shard_positions {
+            if !published_split_ids.is_empty() {
+                let key = format!("{PUBLISHED_SPLITS_PREFIX}{index_uid}:{source_id}:{shard_id}");
+                let value = format!("{position}|{}", published_split_ids.join(","));
+                self.cluster
+                    .set_self_key_value_delete_after_ttl(key, value)
+                    .await;
+            }
             let key = format!("{SHARD_POSITIONS_PREFIX}{index_uid}:{source_id}:{shard_id}");
             self.cluster
                 .set_self_key_value_delete_after_ttl(key, position)
@@ -279,11 +368,13 @@ impl ShardPositionsService {
         &self,
         source_uid: SourceUid,
         shard_positions: Vec<(ShardId, Position)>,
+        published_split_ids: Vec<SplitId>,
     ) {
-        debug!(shard_positions=?shard_positions, "shard positions updates");
+        debug!(shard_positions=?shard_positions, published_split_ids=?published_split_ids, "shard positions updates");
         self.event_broker.publish(ShardPositionsUpdate {
             source_uid,
             updated_shard_positions: shard_positions,
+            published_split_ids,
         });
     }
 
@@ -555,6 +646,60 @@ mod tests {
                 .unwrap();
             assert_eq!(&value3, "00000000000000003000");
         }
+        {
+            event_broker.publish(
+                LocalShardPositionsUpdate::new(
+                    source_uid.clone(),
+                    vec![(shard_id3.clone(), Position::offset(4_000u64))],
+                )
+                .with_published_split_ids(vec![
+                    "test-split-1".to_string(),
+                    "test-split-2".to_string(),
+                ]),
+            );
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let value3 = cluster
+                .get_self_key_value(&format!("{key_prefix}:{shard_id3}"))
+                .await
+                .unwrap();
+            assert_eq!(&value3, "00000000000000004000");
+
+            let published_splits_key = format!(
+                "{PUBLISHED_SPLITS_PREFIX}{}:{}:{shard_id3}",
+                source_uid.index_uid, source_uid.source_id
+            );
+            let published_splits_value = cluster
+                .get_self_key_value(&published_splits_key)
+                .await
+                .unwrap();
+            assert_eq!(
+                &published_splits_value,
+                "00000000000000004000|test-split-1,test-split-2"
+            );
+        }
         universe.assert_quit().await;
     }
+
+    #[test]
+    fn test_parse_published_splits_from_kv() {
+        let index_uid = IndexUid::for_test("test-index", 0);
+        let key = format!("{index_uid}:test-source:1");
+
+        let update =
+            parse_published_splits_from_kv(&key, "00000000000000000042|test-split-1,test-split-2")
+                .unwrap();
+        assert_eq!(update.source_uid.index_uid, index_uid);
+        assert_eq!(update.source_uid.source_id, "test-source");
+        assert_eq!(update.shard_id, ShardId::from(1));
+        assert_eq!(update.position, Position::offset(42u64));
+        assert_eq!(
+            update.published_split_ids,
+            ["test-split-1".to_string(), "test-split-2".to_string()]
+        );
+
+        let update = parse_published_splits_from_kv(&key, "00000000000000000042|").unwrap();
+        assert!(update.published_split_ids.is_empty());
+
+        parse_published_splits_from_kv(&key, "00000000000000000042").unwrap_err();
+    }
 }
@@ -33,7 +33,7 @@ use quickwit_common::rand::append_random_suffix;
 use quickwit_config::PubSubSourceParams;
 use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpoint};
 use quickwit_proto::metastore::SourceType;
-use quickwit_proto::types::Position;
+use quickwit_proto::types::{Position, SplitId};
 use serde_json::{json, Value as JsonValue};
 use tokio::time;
 use tracing::{debug, info, warn};
@@ -216,6 +216,7 @@ impl Source for GcpPubSubSource {
     async fn suggest_truncate(
         &mut self,
         _checkpoint: SourceCheckpoint,
+        _published_split_ids: Vec<SplitId>,
         _ctx: &ActorContext<SourceActor>,
     ) -> anyhow::Result<()> {
         // TODO: add ack of ids
@@ -42,7 +42,7 @@ use quickwit_proto::metastore::{
     AcquireShardsRequest, MetastoreService, MetastoreServiceClient, SourceType,
 };
 use quickwit_proto::types::{
-    NodeId, PipelineUid, Position, PublishToken, ShardId, SourceId, SourceUid,
+    NodeId, PipelineUid, Position, PublishToken, ShardId, SourceId, SourceUid, SplitId,
 };
 use serde::Serialize;
 use serde_json::json;
@@ -134,7 +134,7 @@ enum IndexingStatus {
 #[derive(Debug, Eq, PartialEq)]
 struct AssignedShard {
     leader_id: NodeId,
-    follower_id_opt: Option<NodeId>,
+    follower_ids: Vec<NodeId>,
     // This is just the shard id converted to a partition id object.
     partition_id: PartitionId,
     current_position_inclusive: Position,
@@ -230,6 +230,8 @@ impl IngestSource {
                 MRecord::Commit => {
                     batch_builder.force_commit();
                 }
+                // Fetch tasks decompress compressed records into `Doc` records.
+                MRecord::DedupId { .. } | MRecord::CompressedDocs(_) => {}
             }
         }
         batch_builder
@@ -298,11 +300,16 @@ impl IngestSource {
         Ok(())
     }
 
-    async fn truncate(&mut self, truncate_up_to_positions: Vec<(ShardId, Position)>) {
+    async fn truncate(
+        &mut self,
+        truncate_up_to_positions: Vec<(ShardId, Position)>,
+        published_split_ids: Vec<SplitId>,
+    ) {
         let shard_positions_update = LocalShardPositionsUpdate::new(
             self.client_id.source_uid.clone(),
             truncate_up_to_positions.clone(),
-        );
+        )
+        .with_published_split_ids(published_split_ids);
         // Let's record all shards that have reached Eof as complete.
         for (shard, truncate_up_to_position_inclusive) in &truncate_up_to_positions {
             if truncate_up_to_position_inclusive.is_eof() {
@@ -335,7 +342,7 @@ impl IngestSource {
                 shard_id: Some(shard_id),
                 truncate_up_to_position_inclusive: Some(truncate_up_to_position_inclusive),
             };
-            if let Some(follower_id) = &shard.follower_id_opt {
+            for follower_id in &shard.follower_ids {
                 per_ingester_truncate_subrequests
                     .entry(follower_id)
                     .or_default()
@@ -558,8 +565,8 @@ impl Source for IngestSource {
             let shard_id = acquired_shard.shard_id().clone();
             let mut current_position_inclusive =
                 acquired_shard.publish_position_inclusive().clone();
+            let follower_ids: Vec<NodeId> = acquired_shard.follower_ids().collect();
             let leader_id: NodeId = acquired_shard.leader_id.into();
-            let follower_id_opt: Option<NodeId> = acquired_shard.follower_id.map(Into::into);
             let source_id: SourceId = acquired_shard.source_id;
             let partition_id = PartitionId::from(shard_id.as_str());
             let from_position_exclusive = current_position_inclusive.clone();
@@ -569,7 +576,7 @@ impl Source for IngestSource {
             } else if let Err(error) = ctx
                 .protect_future(self.fetch_stream.subscribe(
                     leader_id.clone(),
-                    follower_id_opt.clone(),
+                    follower_ids.clone(),
                     index_uid,
                     source_id,
                     shard_id.clone(),
@@ -592,14 +599,14 @@ impl Source for IngestSource {
 
             let assigned_shard = AssignedShard {
                 leader_id,
-                follower_id_opt,
+                follower_ids,
                 partition_id,
                 current_position_inclusive,
                 status,
             };
             self.assigned_shards.insert(shard_id, assigned_shard);
         }
-        self.truncate(truncate_up_to_positions).await;
+        self.truncate(truncate_up_to_positions, Vec::new()).await;
 
         Ok(())
     }
@@ -607,6 +614,7 @@ impl Source for IngestSource {
     async fn suggest_truncate(
         &mut self,
         checkpoint: SourceCheckpoint,
+        published_split_ids: Vec<SplitId>,
         _ctx: &SourceContext,
     ) -> anyhow::Result<()> {
         let truncate_up_to_positions: Vec<(ShardId, Position)> = checkpoint
@@ -617,7 +625,8 @@ impl Source for IngestSource {
             })
             .collect();
         if !truncate_up_to_positions.is_empty() {
-            self.truncate(truncate_up_to_positions).await;
+            self.truncate(truncate_up_to_positions, published_split_ids)
+                .await;
         }
         Ok(())
     }
@@ -705,6 +714,7 @@ mod tests {
                         source_id: "test-source".to_string(),
                         leader_id: "test-ingester-0".to_string(),
                         follower_id: None,
+                        additional_follower_ids: Vec::new(),
                         shard_id: Some(ShardId::from(0)),
                         shard_state: ShardState::Open as i32,
                         publish_position_inclusive: Some(Position::offset(10u64)),
@@ -725,6 +735,7 @@ mod tests {
                     acquired_shards: vec![Shard {
                         leader_id: "test-ingester-0".to_string(),
                         follower_id: None,
+                        additional_follower_ids: Vec::new(),
                         index_uid: Some(IndexUid::for_test("test-index", 0)),
                         source_id: "test-source".to_string(),
                         shard_id: Some(ShardId::from(1)),
@@ -748,6 +759,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(1)),
@@ -758,6 +770,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(2)),
@@ -999,7 +1012,7 @@ mod tests {
         let assigned_shard = source.assigned_shards.get(&ShardId::from(1)).unwrap();
         let expected_assigned_shard = AssignedShard {
             leader_id: "test-ingester-0".into(),
-            follower_id_opt: None,
+            follower_ids: Vec::new(),
             partition_id: 1u64.into(),
             current_position_inclusive: Position::offset(11u64),
             status: IndexingStatus::Active,
@@ -1009,7 +1022,7 @@ mod tests {
         let assigned_shard = source.assigned_shards.get(&ShardId::from(2)).unwrap();
         let expected_assigned_shard = AssignedShard {
             leader_id: "test-ingester-0".into(),
-            follower_id_opt: None,
+            follower_ids: Vec::new(),
             partition_id: 2u64.into(),
             current_position_inclusive: Position::offset(12u64),
             status: IndexingStatus::Active,
@@ -1050,6 +1063,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(1)),
@@ -1060,6 +1074,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(2)),
@@ -1190,6 +1205,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(1)),
@@ -1200,6 +1216,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(2)),
@@ -1367,7 +1384,7 @@ mod tests {
             ShardId::from(1),
             AssignedShard {
                 leader_id: "test-ingester-0".into(),
-                follower_id_opt: None,
+                follower_ids: Vec::new(),
                 partition_id: 1u64.into(),
                 current_position_inclusive: Position::offset(11u64),
                 status: IndexingStatus::Active,
@@ -1377,7 +1394,7 @@ mod tests {
             ShardId::from(2),
             AssignedShard {
                 leader_id: "test-ingester-1".into(),
-                follower_id_opt: None,
+                follower_ids: Vec::new(),
                 partition_id: 2u64.into(),
                 current_position_inclusive: Position::offset(22u64),
                 status: IndexingStatus::Active,
@@ -1540,6 +1557,7 @@ mod tests {
                     acquired_shards: vec![Shard {
                         leader_id: "test-ingester-0".to_string(),
                         follower_id: None,
+                        additional_follower_ids: Vec::new(),
                         index_uid: Some(IndexUid::for_test("test-index", 0)),
                         source_id: "test-source".to_string(),
                         shard_id: Some(ShardId::from(1)),
@@ -1750,7 +1768,7 @@ mod tests {
             ShardId::from(1),
             AssignedShard {
                 leader_id: "test-ingester-0".into(),
-                follower_id_opt: None,
+                follower_ids: Vec::new(),
                 partition_id: 1u64.into(),
                 current_position_inclusive: Position::offset(11u64),
                 status: IndexingStatus::Active,
@@ -1760,7 +1778,7 @@ mod tests {
             ShardId::from(2),
             AssignedShard {
                 leader_id: "test-ingester-0".into(),
-                follower_id_opt: Some("test-ingester-1".into()),
+                follower_ids: vec!["test-ingester-1".into()],
                 partition_id: 2u64.into(),
                 current_position_inclusive: Position::offset(22u64),
                 status: IndexingStatus::Active,
@@ -1770,7 +1788,7 @@ mod tests {
             ShardId::from(3),
             AssignedShard {
                 leader_id: "test-ingester-1".into(),
-                follower_id_opt: Some("test-ingester-0".into()),
+                follower_ids: vec!["test-ingester-0".into()],
                 partition_id: 3u64.into(),
                 current_position_inclusive: Position::offset(33u64),
                 status: IndexingStatus::Active,
@@ -1780,7 +1798,7 @@ mod tests {
             ShardId::from(4),
             AssignedShard {
                 leader_id: "test-ingester-2".into(),
-                follower_id_opt: Some("test-ingester-3".into()),
+                follower_ids: vec!["test-ingester-3".into()],
                 partition_id: 4u64.into(),
                 current_position_inclusive: Position::offset(44u64),
                 status: IndexingStatus::Active,
@@ -1790,7 +1808,7 @@ mod tests {
             ShardId::from(5),
             AssignedShard {
                 leader_id: "test-ingester-2".into(),
-                follower_id_opt: Some("test-ingester-3".into()),
+                follower_ids: vec!["test-ingester-3".into()],
                 partition_id: 5u64.into(),
                 current_position_inclusive: Position::Beginning,
                 status: IndexingStatus::Active,
@@ -1805,7 +1823,11 @@ mod tests {
             (5u64.into(), Position::Beginning),
             (6u64.into(), Position::offset(66u64)),
         ]);
-        source.suggest_truncate(checkpoint, &ctx).await.unwrap();
+        let published_split_ids = vec!["test-split".to_string()];
+        source
+            .suggest_truncate(checkpoint, published_split_ids, &ctx)
+            .await
+            .unwrap();
 
         let local_shards_update = shard_positions_update_rx.recv().await.unwrap();
         let expected_local_shards_update = LocalShardPositionsUpdate::new(
@@ -1821,7 +1843,8 @@ mod tests {
                 (ShardId::from(5u64), Position::Beginning),
                 (ShardId::from(6u64), Position::offset(66u64)),
             ],
-        );
+        )
+        .with_published_split_ids(vec!["test-split".to_string()]);
         assert_eq!(local_shards_update, expected_local_shards_update);
     }
 }
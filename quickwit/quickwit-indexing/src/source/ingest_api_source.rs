@@ -29,7 +29,7 @@ use quickwit_ingest::{
 };
 use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpoint};
 use quickwit_proto::metastore::SourceType;
-use quickwit_proto::types::Position;
+use quickwit_proto::types::{Position, SplitId};
 use serde::Serialize;
 use serde_json::Value as JsonValue;
 
@@ -198,6 +198,7 @@ impl Source for IngestApiSource {
     async fn suggest_truncate(
         &mut self,
         checkpoint: SourceCheckpoint,
+        _published_split_ids: Vec<SplitId>,
         ctx: &ActorContext<SourceActor>,
     ) -> anyhow::Result<()> {
         if let Some(Position::Offset(offset)) =
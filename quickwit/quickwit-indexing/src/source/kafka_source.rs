@@ -32,7 +32,7 @@ use quickwit_config::KafkaSourceParams;
 use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpoint};
 use quickwit_metastore::IndexMetadataResponseExt;
 use quickwit_proto::metastore::{IndexMetadataRequest, MetastoreService, SourceType};
-use quickwit_proto::types::{IndexUid, Position};
+use quickwit_proto::types::{IndexUid, Position, SplitId};
 use rdkafka::config::{ClientConfig, RDKafkaLogLevel};
 use rdkafka::consumer::{
     BaseConsumer, CommitMode, Consumer, ConsumerContext, DefaultConsumerContext, Rebalance,
@@ -521,6 +521,7 @@ impl Source for KafkaSource {
     async fn suggest_truncate(
         &mut self,
         checkpoint: SourceCheckpoint,
+        _published_split_ids: Vec<SplitId>,
         _ctx: &SourceContext,
     ) -> anyhow::Result<()> {
         self.truncate(checkpoint)?;
@@ -98,7 +98,7 @@ use quickwit_ingest::IngesterPool;
 use quickwit_metastore::checkpoint::{SourceCheckpoint, SourceCheckpointDelta};
 use quickwit_proto::indexing::IndexingPipelineId;
 use quickwit_proto::metastore::{MetastoreServiceClient, SourceType};
-use quickwit_proto::types::{IndexUid, PipelineUid, ShardId};
+use quickwit_proto::types::{IndexUid, PipelineUid, ShardId, SplitId};
 use quickwit_storage::StorageResolver;
 use serde_json::Value as JsonValue;
 pub use source_factory::{SourceFactory, SourceLoader, TypedSourceFactory};
@@ -247,7 +247,8 @@ pub trait Source: Send + 'static {
         Ok(())
     }
 
-    /// After publication of a split, `suggest_truncate` is called.
+    /// After publication of a split, `suggest_truncate` is called with the IDs of the splits
+    /// that were just published.
     /// This makes it possible for the implementation of a source to
     /// release some resources associated to the data that was just published.
     ///
@@ -264,6 +265,7 @@ pub trait Source: Send + 'static {
     async fn suggest_truncate(
         &mut self,
         _checkpoint: SourceCheckpoint,
+        _published_split_ids: Vec<SplitId>,
         _ctx: &SourceContext,
     ) -> anyhow::Result<()> {
         Ok(())
@@ -452,8 +454,10 @@ pub async fn check_source_connectivity(
     }
 }
 
+/// Sent by the publisher to the source after a publish with the checkpoint of the source and the
+/// IDs of the published splits.
 #[derive(Debug)]
-pub struct SuggestTruncate(pub SourceCheckpoint);
+pub struct SuggestTruncate(pub SourceCheckpoint, pub Vec<SplitId>);
 
 #[async_trait]
 impl Handler<SuggestTruncate> for SourceActor {
@@ -464,9 +468,13 @@ impl Handler<SuggestTruncate> for SourceActor {
         suggest_truncate: SuggestTruncate,
         ctx: &SourceContext,
     ) -> Result<(), ActorExitStatus> {
-        let SuggestTruncate(checkpoint) = suggest_truncate;
+        let SuggestTruncate(checkpoint, published_split_ids) = suggest_truncate;
 
-        if let Err(error) = self.source.suggest_truncate(checkpoint, ctx).await {
+        if let Err(error) = self
+            .source
+            .suggest_truncate(checkpoint, published_split_ids, ctx)
+            .await
+        {
             // Failing to process suggest truncate does not
             // kill the source nor the indexing pipeline, but we log the error.
             error!(%error, "failed to process suggest truncate");
@@ -35,7 +35,7 @@ use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
 use quickwit_config::{PulsarSourceAuth, PulsarSourceParams};
 use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpoint};
 use quickwit_proto::metastore::SourceType;
-use quickwit_proto::types::{IndexUid, Position};
+use quickwit_proto::types::{IndexUid, Position, SplitId};
 use serde_json::{json, Value as JsonValue};
 use tokio::time;
 use tracing::{debug, info, warn};
@@ -256,6 +256,7 @@ impl Source for PulsarSource {
     async fn suggest_truncate(
         &mut self,
         checkpoint: SourceCheckpoint,
+        _published_split_ids: Vec<SplitId>,
         _ctx: &ActorContext<SourceActor>,
     ) -> anyhow::Result<()> {
         self.try_ack_messages(checkpoint).await
@@ -664,7 +665,7 @@ mod pulsar_broker_tests {
         checkpoint
             .try_apply_delta(checkpoints!(partition => truncate_to))
             .expect("Create checkpoint");
-        let truncate = SuggestTruncate(checkpoint);
+        let truncate = SuggestTruncate(checkpoint, Vec::new());
         source_handle
             .mailbox()
             .send_message(truncate)
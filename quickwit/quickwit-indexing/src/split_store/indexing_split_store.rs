@@ -187,7 +187,6 @@ impl IndexingSplitStore {
         output_dir_path: &Path,
         io_controls: &IoControls,
     ) -> StorageResult<Box<dyn Directory>> {
-        let path = PathBuf::from(quickwit_common::split_file(split_id));
         if let Some(split_path) = self
             .inner
             .local_split_store
@@ -203,11 +202,29 @@ impl IndexingSplitStore {
         } else {
             tracing::Span::current().record("cache_hit", false);
         }
+        Self::fetch_and_open_split_from_storage(
+            &*self.inner.remote_storage,
+            split_id,
+            output_dir_path,
+            io_controls,
+        )
+        .await
+    }
+
+    /// Downloads a split from the given storage, bypassing the local cache, and makes it available
+    /// to the given `output_path`. This is used for the splits moved out of the index storage, for
+    /// instance to cold storage by the index lifecycle policy.
+    pub async fn fetch_and_open_split_from_storage(
+        storage: &dyn Storage,
+        split_id: &str,
+        output_dir_path: &Path,
+        io_controls: &IoControls,
+    ) -> StorageResult<Box<dyn Directory>> {
+        let path = PathBuf::from(quickwit_common::split_file(split_id));
         let dest_filepath = output_dir_path.join(&path);
         let dest_file = tokio::fs::File::create(&dest_filepath).await?;
         let mut dest_file_with_write_limit = io_controls.clone().wrap_write(dest_file);
-        self.inner
-            .remote_storage
+        storage
             .copy_to(&path, &mut dest_file_with_write_limit)
             .instrument(info_span!("fetch_split_from_remote_storage", path=?path))
             .await?;
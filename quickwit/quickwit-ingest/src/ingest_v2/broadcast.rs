@@ -32,6 +32,7 @@ use serde::{Deserialize, Serialize, Serializer};
 use tokio::task::JoinHandle;
 use tracing::{debug, warn};
 
+use super::ingest_volume::{self, today, DailyIngestVolumes};
 use super::metrics::INGEST_V2_METRICS;
 use super::state::WeakIngesterState;
 use crate::RateMibPerSec;
@@ -99,10 +100,12 @@ impl<'de> Deserialize<'de> for ShardInfo {
 /// A set of primary shards belonging to the same source.
 pub type ShardInfos = BTreeSet<ShardInfo>;
 
-/// Lists ALL the primary shards hosted by a SINGLE ingester, grouped by source.
+/// Lists ALL the primary shards hosted by a SINGLE ingester, grouped by source, along with the
+/// volume of data ingested today for each index.
 #[derive(Debug, Default, Eq, PartialEq)]
 struct LocalShardsSnapshot {
     per_source_shard_infos: BTreeMap<SourceUid, ShardInfos>,
+    daily_ingest_volumes: DailyIngestVolumes,
 }
 
 #[derive(Debug)]
@@ -225,8 +228,15 @@ impl BroadcastLocalShardsTask {
             .closed_shards
             .set(num_closed_shards as i64);
 
+        let today = today();
+        state_guard
+            .daily_ingest_volumes
+            .retain(|_, daily_ingest_volume| daily_ingest_volume.day == today);
+        let daily_ingest_volumes = state_guard.daily_ingest_volumes.clone();
+
         let snapshot = LocalShardsSnapshot {
             per_source_shard_infos,
+            daily_ingest_volumes,
         };
         Some(snapshot)
     }
@@ -253,6 +263,27 @@ impl BroadcastLocalShardsTask {
                 }
             }
         }
+        for key_diff in previous_snapshot
+            .daily_ingest_volumes
+            .iter()
+            .diff_by_key(new_snapshot.daily_ingest_volumes.iter())
+        {
+            match key_diff {
+                KeyDiff::Unchanged(_, previous_daily_ingest_volume, new_daily_ingest_volume)
+                    if previous_daily_ingest_volume == new_daily_ingest_volume => {}
+                KeyDiff::Added(index_uid, daily_ingest_volume)
+                | KeyDiff::Unchanged(index_uid, _, daily_ingest_volume) => {
+                    let key = ingest_volume::make_key(index_uid);
+                    let value = serde_json::to_string(daily_ingest_volume)
+                        .expect("`DailyIngestVolume` should be JSON serializable");
+                    self.cluster.set_self_key_value(key, value).await;
+                }
+                KeyDiff::Removed(index_uid, _) => {
+                    let key = ingest_volume::make_key(index_uid);
+                    self.cluster.remove_self_key(&key).await;
+                }
+            }
+        }
     }
 
     async fn run(&mut self) {
@@ -382,6 +413,7 @@ mod tests {
             )]
             .into_iter()
             .collect(),
+            daily_ingest_volumes: DailyIngestVolumes::default(),
         };
         let changes = previous_snapshot
             .diff(&current_snapshot)
@@ -422,6 +454,7 @@ mod tests {
             )]
             .into_iter()
             .collect(),
+            daily_ingest_volumes: DailyIngestVolumes::default(),
         };
         let changes = previous_snapshot
             .diff(&current_snapshot)
@@ -494,10 +527,28 @@ mod tests {
             .rate_trackers
             .insert(queue_id_01.clone(), (rate_limiter, rate_meter));
 
+        state_guard
+            .daily_ingest_volumes
+            .entry(index_uid.clone())
+            .or_default()
+            .record(today(), 1_024);
+
+        let other_index_uid: IndexUid = IndexUid::for_test("other-index", 0);
+        state_guard
+            .daily_ingest_volumes
+            .entry(other_index_uid)
+            .or_default()
+            .record(today() - 1, 1_024);
+
         drop(state_guard);
 
         let new_snapshot = task.snapshot_local_shards().await.unwrap();
         assert_eq!(new_snapshot.per_source_shard_infos.len(), 1);
+        assert_eq!(new_snapshot.daily_ingest_volumes.len(), 1);
+        assert_eq!(
+            new_snapshot.daily_ingest_volumes[&index_uid].num_bytes,
+            1_024
+        );
 
         task.broadcast_local_shards(&previous_snapshot, &new_snapshot)
             .await;
@@ -510,6 +561,9 @@ mod tests {
         );
         task.cluster.get_self_key_value(&key).await.unwrap();
 
+        let volume_key = ingest_volume::make_key(&index_uid);
+        task.cluster.get_self_key_value(&volume_key).await.unwrap();
+
         task.broadcast_local_shards(&new_snapshot, &previous_snapshot)
             .await;
 
@@ -517,6 +571,9 @@ mod tests {
 
         let value_opt = task.cluster.get_self_key_value(&key).await;
         assert!(value_opt.is_none());
+
+        let value_opt = task.cluster.get_self_key_value(&volume_key).await;
+        assert!(value_opt.is_none());
     }
 
     #[test]
@@ -0,0 +1,128 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use quickwit_proto::types::Position;
+
+/// Returns the current time in seconds since the Unix epoch.
+pub(super) fn unix_timestamp_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time should be after the Unix epoch")
+        .as_secs()
+}
+
+/// Tracks the dedup IDs of recent batches along with a value attached to each of them, and
+/// evicts them once they expire.
+///
+/// Ingesters record the position of the last record of each batch persisted in a shard, so that
+/// retried batches can be acknowledged without being written twice. Routers record the leader
+/// the batch was first routed to, so that retries keep landing on it.
+#[derive(Debug)]
+pub(super) struct DedupIds<T = Position> {
+    entries: HashMap<String, (T, u64)>,
+    // Dedup IDs ordered by insertion timestamp, used to evict expired entries.
+    timestamps: VecDeque<(u64, String)>,
+}
+
+impl<T> Default for DedupIds<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::default(),
+            timestamps: VecDeque::default(),
+        }
+    }
+}
+
+impl<T> DedupIds<T> {
+    /// Returns the value recorded for `dedup_id`, if any.
+    pub fn get(&self, dedup_id: &str) -> Option<&T> {
+        self.entries.get(dedup_id).map(|(value, _)| value)
+    }
+
+    /// Records `value` for `dedup_id` at `timestamp`, expressed in seconds since the Unix epoch.
+    pub fn insert(&mut self, dedup_id: String, value: T, timestamp: u64) {
+        self.timestamps.push_back((timestamp, dedup_id.clone()));
+        self.entries.insert(dedup_id, (value, timestamp));
+    }
+
+    /// Evicts the dedup IDs recorded more than `dedup_window` ago.
+    pub fn evict_expired(&mut self, now: u64, dedup_window: Duration) {
+        let dedup_window_secs = dedup_window.as_secs();
+
+        while let Some((timestamp, _)) = self.timestamps.front() {
+            if timestamp + dedup_window_secs > now {
+                break;
+            }
+            let (timestamp, dedup_id) = self.timestamps.pop_front().expect("entry should exist");
+
+            // The dedup ID may have been recorded again since, in which case we keep it.
+            if let Some((_, inserted_at)) = self.entries.get(&dedup_id) {
+                if *inserted_at == timestamp {
+                    self.entries.remove(&dedup_id);
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_ids() {
+        let mut dedup_ids = DedupIds::default();
+        assert!(dedup_ids.get("test-dedup-id-foo").is_none());
+
+        dedup_ids.insert("test-dedup-id-foo".to_string(), Position::offset(1u64), 100);
+        dedup_ids.insert("test-dedup-id-bar".to_string(), Position::offset(3u64), 110);
+        assert_eq!(dedup_ids.len(), 2);
+        assert_eq!(
+            dedup_ids.get("test-dedup-id-foo"),
+            Some(&Position::offset(1u64))
+        );
+
+        let dedup_window = Duration::from_secs(30);
+
+        dedup_ids.evict_expired(129, dedup_window);
+        assert_eq!(dedup_ids.len(), 2);
+
+        dedup_ids.evict_expired(130, dedup_window);
+        assert_eq!(dedup_ids.len(), 1);
+        assert!(dedup_ids.get("test-dedup-id-foo").is_none());
+
+        dedup_ids.insert("test-dedup-id-bar".to_string(), Position::offset(5u64), 135);
+        dedup_ids.evict_expired(140, dedup_window);
+        assert_eq!(
+            dedup_ids.get("test-dedup-id-bar"),
+            Some(&Position::offset(5u64))
+        );
+
+        dedup_ids.evict_expired(165, dedup_window);
+        assert_eq!(dedup_ids.len(), 0);
+    }
+}
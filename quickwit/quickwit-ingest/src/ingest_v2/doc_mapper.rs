@@ -0,0 +1,170 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use quickwit_config::{build_doc_mapper, DocMapping, SearchSettings};
+use quickwit_doc_mapper::{DocMapper, DocParsingError};
+use quickwit_proto::ingest::router::{ParseFailure, ParseFailureReason};
+use quickwit_proto::ingest::DocBatchV2;
+use quickwit_proto::types::IndexUid;
+use tracing::error;
+
+use super::DocBatchV2Builder;
+
+/// Builds a doc mapper from the JSON-serialized doc mapping sent by the control plane. Returns
+/// `None` if the doc mapping is empty, i.e. documents of the source cannot be validated, or
+/// invalid.
+pub(super) fn try_build_doc_mapper(
+    index_uid: &IndexUid,
+    doc_mapping_json: &str,
+) -> Option<Arc<dyn DocMapper>> {
+    if doc_mapping_json.is_empty() {
+        return None;
+    }
+    let doc_mapping: DocMapping = match serde_json::from_str(doc_mapping_json) {
+        Ok(doc_mapping) => doc_mapping,
+        Err(error) => {
+            error!(%index_uid, %error, "failed to deserialize doc mapping");
+            return None;
+        }
+    };
+    match build_doc_mapper(&doc_mapping, &SearchSettings::default()) {
+        Ok(doc_mapper) => Some(doc_mapper),
+        Err(error) => {
+            error!(%index_uid, %error, "failed to build doc mapper");
+            None
+        }
+    }
+}
+
+/// Parses the documents of a batch against a doc mapper. Returns the batch of valid documents, if
+/// any, along with the parse failures of the invalid ones.
+pub(super) fn validate_doc_batch(
+    doc_batch: DocBatchV2,
+    doc_mapper: &dyn DocMapper,
+) -> (Option<DocBatchV2>, Vec<ParseFailure>) {
+    let mut parse_failures = Vec::new();
+
+    for (doc_ordinal, doc) in doc_batch.iter_docs().enumerate() {
+        if let Err(error) = doc_mapper.doc_from_json_bytes(&doc) {
+            let reason = match error {
+                DocParsingError::NotJsonObject(_) => ParseFailureReason::InvalidJson,
+                _ => ParseFailureReason::InvalidSchema,
+            };
+            let parse_failure = ParseFailure {
+                doc_ordinal: doc_ordinal as u32,
+                reason: reason as i32,
+                message: error.to_string(),
+            };
+            parse_failures.push(parse_failure);
+        }
+    }
+    if parse_failures.is_empty() {
+        return (Some(doc_batch), parse_failures);
+    }
+    let mut doc_batch_builder = DocBatchV2Builder::default();
+    let mut parse_failures_iter = parse_failures.iter().peekable();
+
+    for (doc_ordinal, doc) in doc_batch.docs().enumerate() {
+        if parse_failures_iter
+            .next_if(|parse_failure| parse_failure.doc_ordinal as usize == doc_ordinal)
+            .is_none()
+        {
+            doc_batch_builder.add_doc(&doc);
+        }
+    }
+    (doc_batch_builder.build(), parse_failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc_mapping_json() -> String {
+        let doc_mapping_json = r#"{
+            "mode": "strict",
+            "field_mappings": [
+                {
+                    "name": "body",
+                    "type": "text"
+                },
+                {
+                    "name": "status",
+                    "type": "u64"
+                }
+            ]
+        }"#;
+        let doc_mapping: DocMapping = serde_json::from_str(doc_mapping_json).unwrap();
+        serde_json::to_string(&doc_mapping).unwrap()
+    }
+
+    #[test]
+    fn test_try_build_doc_mapper() {
+        let index_uid = IndexUid::for_test("test-index", 0);
+
+        assert!(try_build_doc_mapper(&index_uid, "").is_none());
+        assert!(try_build_doc_mapper(&index_uid, "{").is_none());
+        assert!(try_build_doc_mapper(&index_uid, &doc_mapping_json()).is_some());
+    }
+
+    #[test]
+    fn test_validate_doc_batch() {
+        let index_uid = IndexUid::for_test("test-index", 0);
+        let doc_mapper = try_build_doc_mapper(&index_uid, &doc_mapping_json()).unwrap();
+
+        let doc_batch = DocBatchV2::for_test([r#"{"body": "foo"}"#, r#"{"status": 200}"#]);
+        let (doc_batch_opt, parse_failures) = validate_doc_batch(doc_batch, &*doc_mapper);
+        assert_eq!(doc_batch_opt.unwrap().num_docs(), 2);
+        assert!(parse_failures.is_empty());
+
+        let doc_batch = DocBatchV2::for_test([
+            r#"{"body": "foo"}"#,
+            r#"{"body": "#,
+            r#"{"status": "not-a-number"}"#,
+            r#"{"unknown_field": "bar"}"#,
+            r#"{"status": 200}"#,
+        ]);
+        let (doc_batch_opt, parse_failures) = validate_doc_batch(doc_batch, &*doc_mapper);
+        let doc_batch = doc_batch_opt.unwrap();
+        assert_eq!(doc_batch.num_docs(), 2);
+        assert_eq!(
+            doc_batch.docs().collect::<Vec<_>>(),
+            [&br#"{"body": "foo"}"#[..], &br#"{"status": 200}"#[..]]
+        );
+        assert_eq!(parse_failures.len(), 3);
+        assert_eq!(parse_failures[0].doc_ordinal, 1);
+        assert_eq!(parse_failures[0].reason(), ParseFailureReason::InvalidJson);
+        assert_eq!(parse_failures[1].doc_ordinal, 2);
+        assert_eq!(
+            parse_failures[1].reason(),
+            ParseFailureReason::InvalidSchema
+        );
+        assert_eq!(parse_failures[2].doc_ordinal, 3);
+        assert_eq!(
+            parse_failures[2].reason(),
+            ParseFailureReason::InvalidSchema
+        );
+
+        let doc_batch = DocBatchV2::for_test([r#"["foo"]"#]);
+        let (doc_batch_opt, parse_failures) = validate_doc_batch(doc_batch, &*doc_mapper);
+        assert!(doc_batch_opt.is_none());
+        assert_eq!(parse_failures.len(), 1);
+    }
+}
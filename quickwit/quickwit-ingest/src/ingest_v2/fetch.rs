@@ -23,7 +23,7 @@ use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use bytesize::ByteSize;
 use futures::StreamExt;
 use mrecordlog::Record;
@@ -34,15 +34,18 @@ use quickwit_common::{spawn_named_task, ServiceStream};
 use quickwit_proto::ingest::ingester::{
     fetch_message, FetchEof, FetchMessage, FetchPayload, IngesterService, OpenFetchStreamRequest,
 };
-use quickwit_proto::ingest::{IngestV2Error, IngestV2Result, MRecordBatch};
+use quickwit_proto::ingest::{CompressedDocBatch, IngestV2Error, IngestV2Result, MRecordBatch};
 use quickwit_proto::types::{queue_id, IndexUid, NodeId, Position, QueueId, ShardId, SourceId};
 use tokio::sync::{mpsc, watch, RwLock};
 use tokio::task::JoinHandle;
 use tracing::{debug, error, warn};
 
 use super::models::ShardStatus;
+use super::mrecord::{
+    COMPRESSED_DOCS_HEADER_V0, MAX_DECOMPRESSED_DOCS_NUM_BYTES, MRECORD_HEADER_LEN,
+};
 use crate::mrecordlog_async::MultiRecordLogAsync;
-use crate::{with_lock_metrics, ClientId, IngesterPool};
+use crate::{with_lock_metrics, ClientId, IngesterPool, MRecord};
 
 /// A fetch stream task is responsible for waiting and pushing new records written to a shard's
 /// record log into a channel named `fetch_message_tx`.
@@ -146,24 +149,75 @@ impl FetchStreamTask {
                 // The queue was dropped.
                 break;
             };
+            // Compressed records are copied as is and decompressed once the lock is released.
+            let mut wal_records: Vec<Bytes> = Vec::new();
+            let mut wal_records_num_bytes = 0;
+
             for Record { payload, .. } in mrecords {
-                if mrecord_buffer.len() + payload.len() > mrecord_buffer.capacity() {
+                if !wal_records.is_empty()
+                    && wal_records_num_bytes + payload.len() > self.batch_num_bytes
+                {
                     has_drained_queue = false;
                     break;
                 }
-                mrecord_buffer.put(payload.borrow());
-                mrecord_lengths.push(payload.len() as u32);
+                wal_records_num_bytes += payload.len();
+                wal_records.push(Bytes::copy_from_slice(payload.borrow()));
             }
-            // Drop the lock while we send the message.
             drop(mrecordlog_guard);
 
-            if !mrecord_lengths.is_empty() {
+            // Compressed records expand into several `Doc` records, so the number of WAL records
+            // read may differ from the number of records in the batch.
+            let mut num_records_read = 0;
+            let mut decompression_error_opt = None;
+
+            for wal_record in wal_records {
+                if !wal_record.starts_with(COMPRESSED_DOCS_HEADER_V0) {
+                    if num_records_read > 0
+                        && mrecord_buffer.len() + wal_record.len() > self.batch_num_bytes
+                    {
+                        has_drained_queue = false;
+                        break;
+                    }
+                    mrecord_lengths.push(wal_record.len() as u32);
+                    mrecord_buffer.put(wal_record);
+                    num_records_read += 1;
+                    continue;
+                }
+                let compressed_payload = &wal_record[MRECORD_HEADER_LEN..];
+                let doc_batch = match CompressedDocBatch::decompress_payload(
+                    compressed_payload,
+                    MAX_DECOMPRESSED_DOCS_NUM_BYTES,
+                ) {
+                    Ok(doc_batch) => doc_batch,
+                    Err(error) => {
+                        // Skipping the record would silently lose its documents, so the stream
+                        // stops right before it instead.
+                        decompression_error_opt = Some(error);
+                        break;
+                    }
+                };
+                let num_bytes = doc_batch.num_bytes() + doc_batch.num_docs() * MRECORD_HEADER_LEN;
+
+                // A compressed record may expand beyond the size of a batch on its own, in which
+                // case it is fetched alone.
+                if num_records_read > 0 && mrecord_buffer.len() + num_bytes > self.batch_num_bytes {
+                    has_drained_queue = false;
+                    break;
+                }
+                for doc in doc_batch.docs() {
+                    let mrecord_length = MRECORD_HEADER_LEN + doc.len();
+                    mrecord_buffer.put(MRecord::Doc(doc).encode());
+                    mrecord_lengths.push(mrecord_length as u32);
+                }
+                num_records_read += 1;
+            }
+            if num_records_read > 0 {
                 let from_position_exclusive = if self.from_position_inclusive == 0 {
                     Position::Beginning
                 } else {
                     Position::offset(self.from_position_inclusive - 1)
                 };
-                self.from_position_inclusive += mrecord_lengths.len() as u64;
+                self.from_position_inclusive += num_records_read;
 
                 to_position_inclusive = Position::offset(self.from_position_inclusive - 1);
 
@@ -192,6 +246,25 @@ impl FetchStreamTask {
                     return;
                 }
             }
+            if let Some(decompression_error) = decompression_error_opt {
+                error!(
+                    client_id=%self.client_id,
+                    index_uid=%self.index_uid,
+                    source_id=%self.source_id,
+                    shard_id=%self.shard_id,
+                    position=%self.from_position_inclusive,
+                    "failed to decompress WAL record: {decompression_error}"
+                );
+                let message = format!(
+                    "failed to decompress WAL record at position {}: {decompression_error}",
+                    self.from_position_inclusive
+                );
+                let _ = self
+                    .fetch_message_tx
+                    .send(Err(IngestV2Error::Internal(message)), ByteSize(0))
+                    .await;
+                return;
+            }
             if has_drained_queue {
                 let has_reached_eof = {
                     let shard_status = self.shard_status_rx.borrow();
@@ -293,12 +366,12 @@ impl MultiFetchStream {
         self.fetch_message_tx.clone()
     }
 
-    /// Subscribes to a shard and fails over to the replica if an error occurs.
+    /// Subscribes to a shard and fails over to the replicas if an error occurs.
     #[allow(clippy::too_many_arguments)]
     pub async fn subscribe(
         &mut self,
         leader_id: NodeId,
-        follower_id_opt: Option<NodeId>,
+        follower_ids: Vec<NodeId>,
         index_uid: IndexUid,
         source_id: SourceId,
         shard_id: ShardId,
@@ -312,15 +385,8 @@ impl MultiFetchStream {
                 "stream has already subscribed to shard `{queue_id}`"
             )));
         }
-        let (preferred_ingester_id, failover_ingester_id_opt) =
-            select_preferred_and_failover_ingesters(&self.self_node_id, leader_id, follower_id_opt);
-
-        let mut ingester_ids = Vec::with_capacity(1 + failover_ingester_id_opt.is_some() as usize);
-        ingester_ids.push(preferred_ingester_id);
-
-        if let Some(failover_ingester_id) = failover_ingester_id_opt {
-            ingester_ids.push(failover_ingester_id);
-        }
+        let ingester_ids =
+            select_preferred_and_failover_ingesters(&self.self_node_id, leader_id, follower_ids);
         let fetch_stream_future = retrying_fetch_stream(
             self.client_id.clone(),
             index_uid,
@@ -386,25 +452,28 @@ impl Drop for MultiFetchStream {
     }
 }
 
-/// Chooses the ingester to stream records from, preferring "local" ingesters.
+/// Orders the ingesters to stream records from, preferring "local" ingesters. The first ingester
+/// is the preferred one, the others are tried in order if an error occurs.
 fn select_preferred_and_failover_ingesters(
     self_node_id: &NodeId,
     leader_id: NodeId,
-    follower_id_opt: Option<NodeId>,
-) -> (NodeId, Option<NodeId>) {
-    // The replication factor is 1 and there is no follower.
-    let Some(follower_id) = follower_id_opt else {
-        return (leader_id, None);
-    };
-    if &leader_id == self_node_id {
-        (leader_id, Some(follower_id))
-    } else if &follower_id == self_node_id {
-        (follower_id, Some(leader_id))
-    } else if rand::random::<bool>() {
-        (leader_id, Some(follower_id))
+    follower_ids: Vec<NodeId>,
+) -> Vec<NodeId> {
+    let mut ingester_ids = Vec::with_capacity(1 + follower_ids.len());
+    ingester_ids.push(leader_id);
+    ingester_ids.extend(follower_ids);
+
+    if let Some(self_idx) = ingester_ids
+        .iter()
+        .position(|ingester_id| ingester_id == self_node_id)
+    {
+        ingester_ids.swap(0, self_idx);
     } else {
-        (follower_id, Some(leader_id))
+        // Spread the load of the fetch streams across the replicas.
+        let offset = rand::random::<usize>() % ingester_ids.len();
+        ingester_ids.rotate_left(offset);
     }
+    ingester_ids
 }
 
 /// Performs multiple fault-tolerant fetch stream attempts until the stream reaches
@@ -442,7 +511,7 @@ async fn retrying_fetch_stream(
     }
 }
 
-/// Streams records from the preferred ingester and fails over to the other ingester if an error
+/// Streams records from the preferred ingester and fails over to the other ingesters if an error
 /// occurs.
 #[allow(clippy::too_many_arguments)]
 async fn fault_tolerant_fetch_stream(
@@ -627,12 +696,12 @@ pub(super) mod tests {
 
     use bytes::Bytes;
     use quickwit_proto::ingest::ingester::IngesterServiceClient;
-    use quickwit_proto::ingest::ShardState;
+    use quickwit_proto::ingest::{DocBatchV2, ShardState};
     use quickwit_proto::types::queue_id;
     use tokio::time::timeout;
 
     use super::*;
-    use crate::MRecord;
+    use crate::decoded_mrecords;
 
     pub fn into_fetch_payload(fetch_message: FetchMessage) -> FetchPayload {
         match fetch_message.message.unwrap() {
@@ -1201,30 +1270,169 @@ pub(super) mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_fetch_task_compressed_records() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mrecordlog = Arc::new(RwLock::new(Some(
+            MultiRecordLogAsync::open(tempdir.path()).await.unwrap(),
+        )));
+        let client_id = "test-client".to_string();
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+        let source_id = "test-source".to_string();
+        let shard_id = ShardId::from(1);
+        let queue_id = queue_id(&index_uid, &source_id, &shard_id);
+
+        let open_fetch_stream_request = OpenFetchStreamRequest {
+            client_id: client_id.clone(),
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(shard_id.clone()),
+            from_position_exclusive: Some(Position::Beginning),
+        };
+        let (shard_status_tx, shard_status_rx) = watch::channel(ShardStatus::default());
+        let (mut fetch_stream, _fetch_task_handle) = FetchStreamTask::spawn(
+            open_fetch_stream_request,
+            mrecordlog.clone(),
+            shard_status_rx,
+            30,
+        );
+        let mut mrecordlog_guard = mrecordlog.write().await;
+
+        mrecordlog_guard
+            .as_mut()
+            .unwrap()
+            .create_queue(&queue_id)
+            .await
+            .unwrap();
+
+        let compressed_doc_batch_foo = DocBatchV2::for_test(["test-doc-foo", "test-doc-bar"])
+            .compress(3)
+            .unwrap();
+        let compressed_doc_batch_baz = DocBatchV2::for_test(["test-doc-baz"]).compress(3).unwrap();
+        let records = [
+            MRecord::CompressedDocs(compressed_doc_batch_foo.payload).encode(),
+            MRecord::Commit.encode(),
+            MRecord::CompressedDocs(Bytes::from_static(b"not-zstd")).encode(),
+            MRecord::CompressedDocs(compressed_doc_batch_baz.payload).encode(),
+        ]
+        .into_iter();
+
+        mrecordlog_guard
+            .as_mut()
+            .unwrap()
+            .append_records(&queue_id, None, records)
+            .await
+            .unwrap();
+        drop(mrecordlog_guard);
+
+        let shard_status = (ShardState::Open, Position::offset(3u64));
+        shard_status_tx.send(shard_status).unwrap();
+
+        // The first compressed record expands beyond the size of a batch (30 bytes) on its own.
+        let fetch_message = timeout(Duration::from_millis(100), fetch_stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let fetch_payload = into_fetch_payload(fetch_message);
+
+        assert_eq!(
+            *fetch_payload.from_position_exclusive(),
+            Position::Beginning
+        );
+        assert_eq!(
+            *fetch_payload.to_position_inclusive(),
+            Position::offset(0u64)
+        );
+        let mrecords: Vec<MRecord> =
+            decoded_mrecords(fetch_payload.mrecord_batch.as_ref().unwrap()).collect();
+        assert_eq!(
+            mrecords,
+            [
+                MRecord::new_doc("test-doc-foo"),
+                MRecord::new_doc("test-doc-bar")
+            ]
+        );
+
+        // The records preceding the corrupted record are fetched.
+        let fetch_message = timeout(Duration::from_millis(100), fetch_stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let fetch_payload = into_fetch_payload(fetch_message);
+
+        assert_eq!(
+            *fetch_payload.from_position_exclusive(),
+            Position::offset(0u64)
+        );
+        assert_eq!(
+            *fetch_payload.to_position_inclusive(),
+            Position::offset(1u64)
+        );
+        let mrecords: Vec<MRecord> =
+            decoded_mrecords(fetch_payload.mrecord_batch.as_ref().unwrap()).collect();
+        assert_eq!(mrecords, [MRecord::Commit]);
+
+        // The stream then stops at the corrupted record instead of skipping it.
+        let fetch_error = timeout(Duration::from_millis(100), fetch_stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            fetch_error,
+            IngestV2Error::Internal(message) if message.contains("position 2")
+        ));
+        assert!(timeout(Duration::from_millis(100), fetch_stream.next())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[test]
     fn test_select_preferred_and_failover_ingesters() {
         let self_node_id: NodeId = "test-ingester-0".into();
 
-        let (preferred, failover) =
-            select_preferred_and_failover_ingesters(&self_node_id, "test-ingester-0".into(), None);
-        assert_eq!(preferred, "test-ingester-0");
-        assert!(failover.is_none());
+        let ingester_ids = select_preferred_and_failover_ingesters(
+            &self_node_id,
+            "test-ingester-0".into(),
+            Vec::new(),
+        );
+        assert_eq!(ingester_ids, ["test-ingester-0"]);
 
-        let (preferred, failover) = select_preferred_and_failover_ingesters(
+        let ingester_ids = select_preferred_and_failover_ingesters(
             &self_node_id,
             "test-ingester-0".into(),
-            Some("test-ingester-1".into()),
+            vec!["test-ingester-1".into()],
+        );
+        assert_eq!(ingester_ids, ["test-ingester-0", "test-ingester-1"]);
+
+        let ingester_ids = select_preferred_and_failover_ingesters(
+            &self_node_id,
+            "test-ingester-1".into(),
+            vec!["test-ingester-0".into()],
+        );
+        assert_eq!(ingester_ids, ["test-ingester-0", "test-ingester-1"]);
+
+        let ingester_ids = select_preferred_and_failover_ingesters(
+            &self_node_id,
+            "test-ingester-1".into(),
+            vec!["test-ingester-2".into(), "test-ingester-0".into()],
         );
-        assert_eq!(preferred, "test-ingester-0");
-        assert_eq!(failover.unwrap(), "test-ingester-1");
+        assert_eq!(ingester_ids.len(), 3);
+        assert_eq!(ingester_ids[0], "test-ingester-0");
 
-        let (preferred, failover) = select_preferred_and_failover_ingesters(
+        let mut ingester_ids = select_preferred_and_failover_ingesters(
             &self_node_id,
             "test-ingester-1".into(),
-            Some("test-ingester-0".into()),
+            vec!["test-ingester-2".into(), "test-ingester-3".into()],
+        );
+        ingester_ids.sort();
+        assert_eq!(
+            ingester_ids,
+            ["test-ingester-1", "test-ingester-2", "test-ingester-3"]
         );
-        assert_eq!(preferred, "test-ingester-0");
-        assert_eq!(failover.unwrap(), "test-ingester-1");
     }
 
     #[tokio::test]
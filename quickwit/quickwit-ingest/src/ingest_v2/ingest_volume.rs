@@ -0,0 +1,201 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use quickwit_cluster::{Cluster, ListenerHandle};
+use quickwit_common::pubsub::{Event, EventBroker};
+use quickwit_common::shared_consts::INGESTER_DAILY_INGEST_VOLUME_PREFIX;
+use quickwit_proto::types::{IndexUid, NodeId};
+use serde::{Deserialize, Serialize, Serializer};
+use tracing::warn;
+
+use super::dedup::unix_timestamp_secs;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Returns the number of days elapsed since the Unix epoch, i.e. the current UTC day.
+pub fn today() -> u64 {
+    unix_timestamp_secs() / SECONDS_PER_DAY
+}
+
+/// Volume of data ingested by a leader for an index during a UTC day.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct DailyIngestVolume {
+    /// Number of days elapsed since the Unix epoch.
+    pub day: u64,
+    pub num_bytes: u64,
+}
+
+impl DailyIngestVolume {
+    /// Records `num_bytes` ingested on `day`. The volume is reset when the day changes.
+    pub fn record(&mut self, day: u64, num_bytes: u64) {
+        if self.day != day {
+            self.day = day;
+            self.num_bytes = 0;
+        }
+        self.num_bytes += num_bytes;
+    }
+}
+
+impl Serialize for DailyIngestVolume {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{}:{}", self.day, self.num_bytes))
+    }
+}
+
+impl<'de> Deserialize<'de> for DailyIngestVolume {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        let value = String::deserialize(deserializer)?;
+        let (day_str, num_bytes_str) = value
+            .split_once(':')
+            .ok_or_else(|| serde::de::Error::custom("invalid daily ingest volume"))?;
+        let day = day_str
+            .parse::<u64>()
+            .map_err(|_| serde::de::Error::custom("invalid daily ingest volume day"))?;
+        let num_bytes = num_bytes_str
+            .parse::<u64>()
+            .map_err(|_| serde::de::Error::custom("invalid daily ingest volume num bytes"))?;
+        Ok(Self { day, num_bytes })
+    }
+}
+
+/// Daily ingest volumes of ALL the indexes written to by a SINGLE leader.
+pub(super) type DailyIngestVolumes = BTreeMap<IndexUid, DailyIngestVolume>;
+
+pub(super) fn make_key(index_uid: &IndexUid) -> String {
+    format!("{INGESTER_DAILY_INGEST_VOLUME_PREFIX}{index_uid}")
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestVolumeUpdate {
+    pub leader_id: NodeId,
+    pub index_uid: IndexUid,
+    pub daily_ingest_volume: DailyIngestVolume,
+}
+
+impl Event for IngestVolumeUpdate {}
+
+pub async fn setup_ingest_volume_update_listener(
+    cluster: Cluster,
+    event_broker: EventBroker,
+) -> ListenerHandle {
+    cluster
+        .subscribe(INGESTER_DAILY_INGEST_VOLUME_PREFIX, move |event| {
+            let Ok(index_uid) = event.key.parse::<IndexUid>() else {
+                warn!("failed to parse index UID `{}`", event.key);
+                return;
+            };
+            let Ok(daily_ingest_volume) = serde_json::from_str(event.value) else {
+                warn!("failed to parse daily ingest volume `{}`", event.value);
+                return;
+            };
+            let leader_id: NodeId = event.node.node_id.clone().into();
+
+            let ingest_volume_update = IngestVolumeUpdate {
+                leader_id,
+                index_uid,
+                daily_ingest_volume,
+            };
+            event_broker.publish(ingest_volume_update);
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use quickwit_cluster::{create_cluster_for_test, ChannelTransport};
+
+    use super::*;
+
+    #[test]
+    fn test_daily_ingest_volume_record() {
+        let mut daily_ingest_volume = DailyIngestVolume::default();
+
+        daily_ingest_volume.record(1, 10);
+        daily_ingest_volume.record(1, 5);
+        assert_eq!(daily_ingest_volume.day, 1);
+        assert_eq!(daily_ingest_volume.num_bytes, 15);
+
+        daily_ingest_volume.record(2, 7);
+        assert_eq!(daily_ingest_volume.day, 2);
+        assert_eq!(daily_ingest_volume.num_bytes, 7);
+    }
+
+    #[test]
+    fn test_daily_ingest_volume_serde() {
+        let daily_ingest_volume = DailyIngestVolume {
+            day: 19_000,
+            num_bytes: 1_024,
+        };
+        let serialized = serde_json::to_string(&daily_ingest_volume).unwrap();
+        assert_eq!(serialized, r#""19000:1024""#);
+
+        let deserialized = serde_json::from_str::<DailyIngestVolume>(&serialized).unwrap();
+        assert_eq!(deserialized, daily_ingest_volume);
+
+        serde_json::from_str::<DailyIngestVolume>(r#""19000""#).unwrap_err();
+        serde_json::from_str::<DailyIngestVolume>(r#""foo:1024""#).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_ingest_volume_update_listener() {
+        let transport = ChannelTransport::default();
+        let cluster = create_cluster_for_test(Vec::new(), &["indexer"], &transport, true)
+            .await
+            .unwrap();
+        let event_broker = EventBroker::default();
+
+        let ingest_volume_update_counter = Arc::new(AtomicUsize::new(0));
+        let ingest_volume_update_counter_clone = ingest_volume_update_counter.clone();
+        let index_uid = IndexUid::for_test("test-index", 0);
+
+        let index_uid_clone = index_uid.clone();
+        event_broker
+            .subscribe(move |event: IngestVolumeUpdate| {
+                ingest_volume_update_counter_clone.fetch_add(1, Ordering::Release);
+
+                assert_eq!(event.index_uid, index_uid_clone);
+                assert_eq!(event.daily_ingest_volume.day, 19_000);
+                assert_eq!(event.daily_ingest_volume.num_bytes, 1_024);
+            })
+            .forever();
+
+        setup_ingest_volume_update_listener(cluster.clone(), event_broker.clone())
+            .await
+            .forever();
+
+        let key = make_key(&index_uid);
+        let value = serde_json::to_string(&DailyIngestVolume {
+            day: 19_000,
+            num_bytes: 1_024,
+        })
+        .unwrap();
+
+        cluster.set_self_key_value(key, value).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(ingest_volume_update_counter.load(Ordering::Acquire), 1);
+    }
+}
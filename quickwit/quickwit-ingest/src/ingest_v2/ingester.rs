@@ -26,47 +26,53 @@ use std::time::{Duration, Instant};
 
 use anyhow::Context;
 use async_trait::async_trait;
+use bytes::BytesMut;
 use bytesize::ByteSize;
 use futures::stream::FuturesUnordered;
-use futures::StreamExt;
+use futures::{Future, StreamExt};
 use mrecordlog::error::CreateQueueError;
 use quickwit_cluster::Cluster;
 use quickwit_common::pretty::PrettyDisplay;
 use quickwit_common::pubsub::{EventBroker, EventSubscriber};
 use quickwit_common::rate_limiter::{RateLimiter, RateLimiterSettings};
-use quickwit_common::tower::Pool;
+use quickwit_common::tower::{ConstantRate, Pool, Rate};
 use quickwit_common::{rate_limited_warn, ServiceStream};
 use quickwit_proto::control_plane::{
-    AdviseResetShardsRequest, ControlPlaneService, ControlPlaneServiceClient,
+    AdviseResetShardsRequest, ControlPlaneService, ControlPlaneServiceClient, HandoffShardsRequest,
 };
 use quickwit_proto::indexing::ShardPositionsUpdate;
 use quickwit_proto::ingest::ingester::{
-    AckReplicationMessage, CloseShardsRequest, CloseShardsResponse, DecommissionRequest,
-    DecommissionResponse, FetchMessage, IngesterService, IngesterServiceClient,
-    IngesterServiceStream, IngesterStatus, InitShardsRequest, InitShardsResponse,
-    ObservationMessage, OpenFetchStreamRequest, OpenObservationStreamRequest,
-    OpenReplicationStreamRequest, OpenReplicationStreamResponse, PersistFailure,
-    PersistFailureReason, PersistRequest, PersistResponse, PersistSuccess, ReplicateFailureReason,
+    AckReplicationMessage, CloseShardsRequest, CloseShardsResponse, DecommissionMode,
+    DecommissionRequest, DecommissionResponse, FetchMessage, GetReplicaPositionsRequest,
+    GetReplicaPositionsResponse, IngesterService, IngesterServiceClient, IngesterServiceStream,
+    IngesterStatus, InitShardsRequest, InitShardsResponse, ObservationMessage,
+    OpenFetchStreamRequest, OpenObservationStreamRequest, OpenReplicationStreamRequest,
+    OpenReplicationStreamResponse, PersistFailure, PersistFailureReason, PersistRequest,
+    PersistResponse, PersistSubrequest, PersistSuccess, ReplicaPosition, ReplicateFailureReason,
     ReplicateSubrequest, RetainShardsForSource, RetainShardsRequest, RetainShardsResponse,
-    SynReplicationMessage, TruncateShardsRequest, TruncateShardsResponse,
+    SynReplicationMessage, TruncateShardsRequest, TruncateShardsResponse, TruncateShardsSubrequest,
 };
 use quickwit_proto::ingest::{
-    CommitTypeV2, IngestV2Error, IngestV2Result, Shard, ShardIds, ShardState,
+    CommitTypeV2, DocBatchV2, IngestV2Error, IngestV2Result, MRecordBatch, Shard, ShardIds,
+    ShardState,
 };
 use quickwit_proto::types::{
     queue_id, split_queue_id, IndexUid, NodeId, Position, QueueId, ShardId, SourceId,
 };
+use rand::seq::SliceRandom;
 use tokio::sync::Semaphore;
 use tokio::time::{sleep, timeout};
 use tracing::{debug, error, info, warn};
 
 use super::broadcast::BroadcastLocalShardsTask;
+use super::dedup::unix_timestamp_secs;
 use super::fetch::FetchStreamTask;
 use super::idle::CloseIdleShardsTask;
+use super::ingest_volume::today;
 use super::metrics::INGEST_V2_METRICS;
 use super::models::IngesterShard;
 use super::mrecordlog_utils::{
-    append_non_empty_doc_batch, check_enough_capacity, AppendDocBatchError,
+    append_non_empty_doc_batch, check_enough_capacity, AppendDocBatchError, WalDocBatch,
 };
 use super::rate_meter::RateMeter;
 use super::replication::{
@@ -87,6 +93,23 @@ const MIN_RESET_SHARDS_INTERVAL: Duration = if cfg!(any(test, feature = "testsui
     Duration::from_secs(60)
 };
 
+/// Maximum size of the batches of records sent to the new leader of a shard when a decommissioning
+/// ingester hands off the tail of its WAL.
+const HANDOFF_BATCH_NUM_BYTES: usize = 1024 * 1024; // 1 MiB
+
+/// Maximum duration of the handoff of the shards of a decommissioning ingester, including the copy
+/// of their records to new leaders and the report to the control plane. The shards that are not
+/// handed off before the deadline are drained instead.
+const HANDOFF_TIMEOUT: Duration = if cfg!(any(test, feature = "testsuite")) {
+    Duration::from_secs(1)
+} else {
+    Duration::from_secs(60)
+};
+
+/// Persist requests whose documents weigh more than this are compressed on the blocking thread
+/// pool.
+const SPAWN_BLOCKING_COMPRESSION_NUM_BYTES: usize = 256 * 1024;
+
 /// Duration after which persist requests time out with
 /// [`quickwit_proto::ingest::IngestV2Error::Timeout`].
 pub(super) const PERSIST_REQUEST_TIMEOUT: Duration = if cfg!(any(test, feature = "testsuite")) {
@@ -105,6 +128,11 @@ pub struct Ingester {
     memory_capacity: ByteSize,
     rate_limiter_settings: RateLimiterSettings,
     replication_factor: usize,
+    // Period of time during which the dedup IDs of persisted batches are remembered. Zero disables
+    // deduplication.
+    dedup_window: Duration,
+    // Zstd compression level of the documents written to the WAL. `None` disables compression.
+    wal_compression_level_opt: Option<i32>,
     // This semaphore ensures that the ingester that not run two reset shards operations
     // concurrently.
     reset_shards_permits: Arc<Semaphore>,
@@ -130,6 +158,8 @@ impl Ingester {
         rate_limiter_settings: RateLimiterSettings,
         replication_factor: usize,
         idle_shard_timeout: Duration,
+        dedup_window: Duration,
+        wal_compression_level_opt: Option<i32>,
     ) -> IngestV2Result<Self> {
         let self_node_id: NodeId = cluster.self_node_id().into();
         let state = IngesterState::load(wal_dir_path, rate_limiter_settings);
@@ -147,6 +177,8 @@ impl Ingester {
             memory_capacity,
             rate_limiter_settings,
             replication_factor,
+            dedup_window,
+            wal_compression_level_opt,
             reset_shards_permits: Arc::new(Semaphore::new(1)),
         };
         ingester.background_reset_shards();
@@ -154,12 +186,59 @@ impl Ingester {
         Ok(ingester)
     }
 
+    /// Prepares the document batches of the subrequests of a persist request for the WAL. This
+    /// step compresses the batches if compression is enabled, which is CPU-bound, so it must run
+    /// before the lock on the state is taken. Large requests are compressed on the blocking thread
+    /// pool.
+    async fn prepare_wal_doc_batches(
+        &self,
+        subrequests: Vec<PersistSubrequest>,
+    ) -> Vec<(PersistSubrequest, Option<PreparedDocBatch>)> {
+        let wal_compression_level_opt = self.wal_compression_level_opt;
+
+        let prepare = move |subrequests: Vec<PersistSubrequest>| {
+            subrequests
+                .into_iter()
+                .map(|mut subrequest| {
+                    let prepared_doc_batch_opt = subrequest
+                        .doc_batch
+                        .take()
+                        .filter(|doc_batch| !doc_batch.is_empty())
+                        .map(|doc_batch| {
+                            PreparedDocBatch::new(doc_batch, wal_compression_level_opt)
+                        });
+                    (subrequest, prepared_doc_batch_opt)
+                })
+                .collect()
+        };
+        let num_bytes: usize = subrequests
+            .iter()
+            .filter_map(|subrequest| subrequest.doc_batch.as_ref())
+            .map(|doc_batch| doc_batch.num_bytes())
+            .sum();
+
+        if wal_compression_level_opt.is_none() || num_bytes < SPAWN_BLOCKING_COMPRESSION_NUM_BYTES {
+            return prepare(subrequests);
+        }
+        tokio::task::spawn_blocking(move || prepare(subrequests))
+            .await
+            .expect("doc batch compression task should not panic")
+    }
+
     /// Checks whether the ingester is fully decommissioned and updates its status accordingly.
     fn check_decommissioning_status(&self, state: &mut InnerIngesterState) {
         if state.status() != IngesterStatus::Decommissioning {
             return;
         }
-        if state.shards.values().all(|shard| shard.is_indexed()) {
+        // In handoff mode, the replica shards, including the ones handed off, no longer need to be
+        // drained because their leader holds a copy of the data.
+        let handoff = state.decommission_mode == DecommissionMode::Handoff;
+
+        if state
+            .shards
+            .values()
+            .all(|shard| shard.is_indexed() || (handoff && shard.is_replica()))
+        {
             state.set_status(IngesterStatus::Decommissioned);
         }
     }
@@ -167,15 +246,19 @@ impl Ingester {
     /// Initializes a primary shard by creating a queue in the write-ahead log and inserting a new
     /// [`IngesterShard`] into the ingester state. If replication is enabled, this method will
     /// also:
-    /// - open a replication stream between the leader and the follower if one does not already
+    /// - open a replication stream between the leader and each follower if one does not already
     ///   exist.
-    /// - initialize the replica shard.
+    /// - initialize the replica shards.
+    ///
+    /// The throughput of the shard is limited to `max_shard_throughput_opt` bytes per second when
+    /// the throughput quota of its index is lower than the throughput of a single shard.
     async fn init_primary_shard(
         &self,
         state: &mut InnerIngesterState,
         mrecordlog: &mut MultiRecordLogAsync,
         shard: Shard,
         now: Instant,
+        max_shard_throughput_opt: Option<u64>,
     ) -> IngestV2Result<()> {
         let queue_id = shard.queue_id();
         info!(
@@ -184,9 +267,11 @@ impl Ingester {
             shard_id=%shard.shard_id(),
             "init primary shard"
         );
-        let Entry::Vacant(entry) = state.shards.entry(queue_id.clone()) else {
+        let follower_ids: Vec<NodeId> = shard.follower_ids().collect();
+
+        if state.shards.contains_key(&queue_id) {
             return Ok(());
-        };
+        }
         match mrecordlog.create_queue(&queue_id).await {
             Ok(_) => {}
             Err(CreateQueueError::AlreadyExists) => {
@@ -200,50 +285,494 @@ impl Ingester {
                 return Err(IngestV2Error::Internal(message));
             }
         };
-        let rate_limiter = RateLimiter::from_settings(self.rate_limiter_settings);
+        let rate_limiter_settings =
+            scale_rate_limiter_settings(self.rate_limiter_settings, max_shard_throughput_opt);
+        let rate_limiter = RateLimiter::from_settings(rate_limiter_settings);
         let rate_meter = RateMeter::default();
         state
             .rate_trackers
-            .insert(queue_id, (rate_limiter, rate_meter));
-
-        let primary_shard = if let Some(follower_id) = &shard.follower_id {
-            let leader_id: NodeId = shard.leader_id.clone().into();
-            let follower_id: NodeId = follower_id.clone().into();
-
-            let replication_client = self
-                .init_replication_stream(
-                    &mut state.replication_streams,
-                    leader_id,
-                    follower_id.clone(),
-                )
-                .await?;
+            .insert(queue_id.clone(), (rate_limiter, rate_meter));
 
-            if let Err(error) = replication_client.init_replica(shard).await {
+        for follower_id in &follower_ids {
+            if let Err(error) = self
+                .init_replica_shard(state, &shard, follower_id, Position::Beginning)
+                .await
+            {
                 // TODO: Remove dangling queue from the WAL.
-                error!("failed to initialize replica shard: {error}",);
+                error!("failed to initialize replica shard on `{follower_id}`: {error}",);
                 return Err(IngestV2Error::Internal(format!(
-                    "failed to initialize replica shard: {error}"
+                    "failed to initialize replica shard on `{follower_id}`: {error}"
                 )));
             }
-            IngesterShard::new_primary(
-                follower_id,
+        }
+        let primary_shard = if follower_ids.is_empty() {
+            IngesterShard::new_solo(
                 ShardState::Open,
                 Position::Beginning,
                 Position::Beginning,
                 now,
             )
         } else {
-            IngesterShard::new_solo(
+            IngesterShard::new_primary(
+                follower_ids,
                 ShardState::Open,
                 Position::Beginning,
                 Position::Beginning,
                 now,
             )
         };
-        entry.insert(primary_shard);
+        state.shards.insert(queue_id, primary_shard);
+        Ok(())
+    }
+
+    /// Promotes the local replica of a shard whose leader left the cluster to a primary shard and
+    /// asks the other followers of the shard to follow this ingester. Followers whose replica is
+    /// out of sync with the local one are dropped and asked to delete their replica.
+    async fn promote_replica_shard(
+        &self,
+        state: &mut InnerIngesterState,
+        shard: Shard,
+    ) -> IngestV2Result<()> {
+        let queue_id = shard.queue_id();
+        info!(
+            index_uid=%shard.index_uid(),
+            source_id=shard.source_id,
+            shard_id=%shard.shard_id(),
+            "promote replica shard"
+        );
+        let Some(replica_shard) = state.shards.get(&queue_id) else {
+            warn!("failed to promote replica shard `{queue_id}`: shard not found");
+            return Err(IngestV2Error::ShardNotFound {
+                shard_id: shard.shard_id().clone(),
+            });
+        };
+        if !replica_shard.is_replica() {
+            // The shard was already promoted.
+            return Ok(());
+        }
+        if replica_shard.is_closed() {
+            return Err(IngestV2Error::Internal(format!(
+                "replica shard `{queue_id}` is closed"
+            )));
+        }
+        let replication_position_inclusive = replica_shard.replication_position_inclusive.clone();
+        let mut follower_ids = Vec::new();
+
+        for follower_id in shard.follower_ids() {
+            match self
+                .init_replica_shard(
+                    state,
+                    &shard,
+                    &follower_id,
+                    replication_position_inclusive.clone(),
+                )
+                .await
+            {
+                Ok(()) => follower_ids.push(follower_id),
+                Err(error) => {
+                    warn!(
+                        "failed to initialize replica shard `{queue_id}` on `{follower_id}`: \
+                         {error}"
+                    );
+                    self.background_delete_replica_shard(follower_id, &queue_id);
+                }
+            }
+        }
+        state
+            .rate_trackers
+            .entry(queue_id.clone())
+            .or_insert_with(|| {
+                let rate_limiter = RateLimiter::from_settings(self.rate_limiter_settings);
+                let rate_meter = RateMeter::default();
+                (rate_limiter, rate_meter)
+            });
+        let replica_shard = state
+            .shards
+            .get_mut(&queue_id)
+            .expect("replica shard should exist");
+        replica_shard.promote(follower_ids);
+        replica_shard.last_write_instant = Instant::now();
+
+        info!("promoted replica shard `{queue_id}` to primary shard");
+        Ok(())
+    }
+
+    /// Snapshots the tails of the primary and solo shards that are not fully indexed yet so that
+    /// they can be handed off once the lock on the ingester state is released. The shards are
+    /// closed beforehand, so their tails no longer grow.
+    fn snapshot_shard_tails(&self, state: &InnerIngesterState) -> Vec<ShardTail> {
+        state
+            .shards
+            .iter()
+            .filter(|(_, shard)| !shard.is_replica() && !shard.is_indexed())
+            .map(|(queue_id, shard)| ShardTail {
+                queue_id: queue_id.clone(),
+                follower_ids: shard.follower_ids().to_vec(),
+                truncation_position_inclusive: shard.truncation_position_inclusive.clone(),
+                replication_position_inclusive: shard.replication_position_inclusive.clone(),
+            })
+            .collect()
+    }
+
+    /// Hands off the primary and solo shards that are not fully indexed yet to other ingesters so
+    /// that a decommissioning ingester does not have to wait for the indexers to catch up. The
+    /// handoff runs without holding the lock on the ingester state and must complete before
+    /// `deadline`. Once the control plane has acknowledged the new leaders, the shards are
+    /// returned along with their new leader so that the caller can demote the local shards into
+    /// replicas of the new leaders: they keep serving the fetch streams already opened until the
+    /// ingester exits. Shards that cannot be handed off are drained as usual.
+    async fn handoff_shards(
+        &self,
+        shard_tails: Vec<ShardTail>,
+        deadline: Instant,
+    ) -> Vec<(QueueId, NodeId)> {
+        let mut handed_off_shards = Vec::with_capacity(shard_tails.len());
+
+        for shard_tail in shard_tails {
+            if Instant::now() >= deadline {
+                warn!(
+                    "failed to hand off shard `{}`: handoff timed out after {HANDOFF_TIMEOUT:?}",
+                    shard_tail.queue_id
+                );
+                continue;
+            }
+            match self.handoff_shard(&shard_tail, deadline).await {
+                Ok(handed_off_shard) => handed_off_shards.push(handed_off_shard),
+                Err(error) => {
+                    warn!(
+                        "failed to hand off shard `{}`: {error}",
+                        shard_tail.queue_id
+                    );
+                }
+            }
+        }
+        if handed_off_shards.is_empty() {
+            return Vec::new();
+        }
+        let num_handed_off_shards = handed_off_shards.len();
+        let new_leader_ids: Vec<(QueueId, NodeId)> = handed_off_shards
+            .iter()
+            .map(|shard| (shard.queue_id(), shard.leader_id.clone().into()))
+            .collect();
+        let handoff_shards_request = HandoffShardsRequest {
+            former_leader_id: self.self_node_id.to_string(),
+            shards: handed_off_shards,
+        };
+        let mut control_plane = self.control_plane.clone();
+        let handoff_shards_future = async {
+            control_plane
+                .handoff_shards(handoff_shards_request)
+                .await
+                .map_err(|error| IngestV2Error::Internal(error.to_string()))
+        };
+        if let Err(error) = handoff_rpc(deadline, handoff_shards_future).await {
+            warn!("failed to report shard handoff to control plane: {error}");
+            return Vec::new();
+        }
+        info!("handed off {num_handed_off_shards} shard(s) to other ingesters");
+        new_leader_ids
+    }
+
+    /// Hands off a shard to another ingester and returns the shard with its new leader and
+    /// followers. Followers are in sync with the leader, so the first available one is promoted.
+    /// Otherwise, the records of the shard that have not been truncated yet are copied, positions
+    /// included, to another ingester, which is then promoted. In both cases, the shard is closed on
+    /// its new leader.
+    async fn handoff_shard(
+        &self,
+        shard_tail: &ShardTail,
+        deadline: Instant,
+    ) -> IngestV2Result<Shard> {
+        let queue_id = &shard_tail.queue_id;
+
+        let Some((index_uid, source_id, shard_id)) = split_queue_id(queue_id) else {
+            let message = format!("failed to parse queue ID `{queue_id}`");
+            return Err(IngestV2Error::Internal(message));
+        };
+        let mut follower_ids = shard_tail.follower_ids.clone();
+
+        let mut shard = Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(shard_id.clone()),
+            leader_id: self.self_node_id.to_string(),
+            shard_state: ShardState::Open as i32,
+            ..Default::default()
+        };
+        let new_leader_id = if let Some(follower_idx) = follower_ids
+            .iter()
+            .position(|follower_id| self.ingester_pool.contains_key(follower_id))
+        {
+            follower_ids.remove(follower_idx)
+        } else {
+            let mut candidate_ids: Vec<NodeId> = self
+                .ingester_pool
+                .keys()
+                .into_iter()
+                .filter(|candidate_id| {
+                    *candidate_id != self.self_node_id && !follower_ids.contains(candidate_id)
+                })
+                .collect();
+            candidate_ids.shuffle(&mut rand::thread_rng());
+
+            let mut new_leader_id_opt = None;
+
+            for candidate_id in candidate_ids {
+                match self
+                    .copy_shard_tail(shard_tail, &shard, &candidate_id, deadline)
+                    .await
+                {
+                    Ok(()) => {
+                        new_leader_id_opt = Some(candidate_id);
+                        break;
+                    }
+                    Err(IngestV2Error::Timeout(message)) => {
+                        warn!("failed to copy shard `{queue_id}` to `{candidate_id}`: {message}");
+                        self.background_delete_replica_shard(candidate_id, queue_id);
+                        break;
+                    }
+                    Err(error) => {
+                        warn!("failed to copy shard `{queue_id}` to `{candidate_id}`: {error}");
+                        self.background_delete_replica_shard(candidate_id, queue_id);
+                    }
+                }
+            }
+            new_leader_id_opt.ok_or_else(|| {
+                IngestV2Error::Unavailable("no ingester available to take over".to_string())
+            })?
+        };
+        let mut new_leader = self.ingester_pool.get(&new_leader_id).ok_or_else(|| {
+            let message = format!("ingester `{new_leader_id}` is unavailable");
+            IngestV2Error::Unavailable(message)
+        })?;
+        let mut follower_ids_iter = follower_ids.into_iter().map(String::from);
+        shard.leader_id = new_leader_id.to_string();
+        shard.follower_id = follower_ids_iter.next();
+        shard.additional_follower_ids = follower_ids_iter.collect();
+
+        let init_shards_request = InitShardsRequest {
+            shards: Vec::new(),
+            promoted_shards: vec![shard.clone()],
+            max_shard_throughputs: HashMap::new(),
+        };
+        handoff_rpc(deadline, new_leader.init_shards(init_shards_request)).await?;
+
+        let close_shards_request = CloseShardsRequest {
+            shards: vec![ShardIds {
+                index_uid: Some(index_uid),
+                source_id,
+                shard_ids: vec![shard_id],
+            }],
+        };
+        handoff_rpc(deadline, new_leader.close_shards(close_shards_request)).await?;
+
+        shard.shard_state = ShardState::Closed as i32;
+        info!("handed off shard `{queue_id}` to ingester `{new_leader_id}`");
+        Ok(shard)
+    }
+
+    /// Copies the records of a shard that have not been truncated yet to a new replica shard
+    /// hosted by `target_id`. The records are sent as is in batches of
+    /// [`HANDOFF_BATCH_NUM_BYTES`] and keep their positions. The WAL is only locked, for reads,
+    /// while a batch is read.
+    async fn copy_shard_tail(
+        &self,
+        shard_tail: &ShardTail,
+        shard: &Shard,
+        target_id: &NodeId,
+        deadline: Instant,
+    ) -> IngestV2Result<()> {
+        let queue_id = &shard_tail.queue_id;
+
+        let replication_client = self.handoff_replication_client(target_id, deadline).await?;
+        let init_replica_future = async {
+            replication_client
+                .init_replica(
+                    shard.clone(),
+                    shard_tail.truncation_position_inclusive.clone(),
+                )
+                .await
+                .map_err(|error| IngestV2Error::Internal(error.to_string()))
+        };
+        handoff_rpc(deadline, init_replica_future).await?;
+
+        let Some(last_position) = shard_tail.replication_position_inclusive.as_u64() else {
+            return Ok(());
+        };
+        let mrecordlog = self.state.mrecordlog();
+        let mut from_position_exclusive = shard_tail.truncation_position_inclusive.clone();
+
+        loop {
+            let start_position = from_position_exclusive
+                .as_u64()
+                .map(|position| position + 1)
+                .unwrap_or_default();
+
+            if start_position > last_position {
+                return Ok(());
+            }
+            let mut mrecord_buffer = BytesMut::with_capacity(HANDOFF_BATCH_NUM_BYTES);
+            let mut mrecord_lengths = Vec::new();
+            let mut last_position_opt = None;
+
+            let mrecordlog_guard = mrecordlog.read().await;
+            let records = mrecordlog_guard
+                .as_ref()
+                .expect("mrecordlog should be initialized")
+                .range(queue_id, start_position..=last_position)
+                .map_err(|_| IngestV2Error::ShardNotFound {
+                    shard_id: shard.shard_id().clone(),
+                })?;
+
+            for record in records {
+                let expected_position = last_position_opt
+                    .map(|position| position + 1)
+                    .unwrap_or(start_position);
+
+                if record.position != expected_position {
+                    let message = format!("shard `{queue_id}` was truncated during the handoff");
+                    return Err(IngestV2Error::Internal(message));
+                }
+                if !mrecord_lengths.is_empty()
+                    && mrecord_buffer.len() + record.payload.len() > HANDOFF_BATCH_NUM_BYTES
+                {
+                    break;
+                }
+                mrecord_buffer.extend_from_slice(&record.payload);
+                mrecord_lengths.push(record.payload.len() as u32);
+                last_position_opt = Some(record.position);
+            }
+            drop(mrecordlog_guard);
+
+            let Some(batch_last_position) = last_position_opt else {
+                let message = format!("shard `{queue_id}` was truncated during the handoff");
+                return Err(IngestV2Error::Internal(message));
+            };
+            let replicate_subrequest = ReplicateSubrequest {
+                subrequest_id: 0,
+                index_uid: shard.index_uid.clone(),
+                source_id: shard.source_id.clone(),
+                shard_id: shard.shard_id.clone(),
+                from_position_exclusive: Some(from_position_exclusive),
+                doc_batch: None,
+                dedup_id: None,
+                compressed_doc_batch: None,
+                mrecord_batch: Some(MRecordBatch {
+                    mrecord_buffer: mrecord_buffer.freeze(),
+                    mrecord_lengths,
+                }),
+            };
+            let replication_client = self.handoff_replication_client(target_id, deadline).await?;
+            let replicate_future = async {
+                replication_client
+                    .replicate(
+                        self.self_node_id.clone(),
+                        target_id.clone(),
+                        vec![replicate_subrequest],
+                        CommitTypeV2::Auto,
+                    )
+                    .await
+                    .map_err(|error| IngestV2Error::Internal(error.to_string()))
+            };
+            let replicate_response = handoff_rpc(deadline, replicate_future).await?;
+
+            let expected_position_inclusive = Position::offset(batch_last_position);
+
+            if replicate_response.successes.len() != 1
+                || replicate_response.successes[0].replication_position_inclusive()
+                    != &expected_position_inclusive
+            {
+                let message = format!(
+                    "failed to replicate records up to position {expected_position_inclusive:?}"
+                );
+                return Err(IngestV2Error::Internal(message));
+            }
+            from_position_exclusive = expected_position_inclusive;
+        }
+    }
+
+    /// Returns a single-use client for the replication stream between this ingester and
+    /// `target_id`, opening the stream if needed. The stream is opened without holding the lock on
+    /// the ingester state.
+    async fn handoff_replication_client(
+        &self,
+        target_id: &NodeId,
+        deadline: Instant,
+    ) -> IngestV2Result<ReplicationClient> {
+        let state_guard = self.state.lock_partially().await?;
+
+        if let Some(replication_stream) = state_guard.replication_streams.get(target_id) {
+            return Ok(replication_stream.replication_client());
+        }
+        drop(state_guard);
+
+        let connect_future =
+            self.connect_replication_stream(self.self_node_id.clone(), target_id.clone());
+        let replication_stream = handoff_rpc(deadline, connect_future).await?;
+
+        let mut state_guard = self.state.lock_partially().await?;
+        let replication_client = state_guard
+            .replication_streams
+            .entry(target_id.clone())
+            .or_insert(replication_stream)
+            .replication_client();
+        Ok(replication_client)
+    }
+
+    /// Opens a replication stream between this ingester and the follower if one does not already
+    /// exist and initializes the replica shard.
+    async fn init_replica_shard(
+        &self,
+        state: &mut InnerIngesterState,
+        shard: &Shard,
+        follower_id: &NodeId,
+        replication_position_inclusive: Position,
+    ) -> IngestV2Result<()> {
+        let leader_id: NodeId = shard.leader_id.clone().into();
+
+        let replication_client = self
+            .init_replication_stream(
+                &mut state.replication_streams,
+                leader_id,
+                follower_id.clone(),
+            )
+            .await?;
+
+        replication_client
+            .init_replica(shard.clone(), replication_position_inclusive)
+            .await
+            .map_err(|error| IngestV2Error::Internal(error.to_string()))?;
         Ok(())
     }
 
+    /// Asks a follower to delete its replica of a shard in a separate background task because it
+    /// fell out of sync with the primary shard. Deleting the replica prevents indexers from reading
+    /// from it and the control plane from promoting it.
+    fn background_delete_replica_shard(&self, follower_id: NodeId, queue_id: &QueueId) {
+        let Some((index_uid, source_id, shard_id)) = split_queue_id(queue_id) else {
+            warn!("failed to parse queue ID `{queue_id}`");
+            return;
+        };
+        let Some(mut follower) = self.ingester_pool.get(&follower_id) else {
+            return;
+        };
+        let truncate_shards_request = TruncateShardsRequest {
+            ingester_id: follower_id.to_string(),
+            subrequests: vec![TruncateShardsSubrequest {
+                index_uid: Some(index_uid),
+                source_id,
+                shard_id: Some(shard_id),
+                truncate_up_to_position_inclusive: Some(Position::Eof(None)),
+            }],
+        };
+        let future = async move {
+            if let Err(error) = follower.truncate_shards(truncate_shards_request).await {
+                warn!("failed to delete replica shard on ingester `{follower_id}`: {error}");
+            }
+        };
+        tokio::spawn(future);
+    }
+
     /// Resets the local shards in a separate background task.
     fn background_reset_shards(&self) {
         let mut ingester = self.clone();
@@ -362,6 +891,21 @@ impl Ingester {
             }
             Entry::Vacant(entry) => entry,
         };
+        let replication_stream_task_handle = self
+            .connect_replication_stream(leader_id, follower_id)
+            .await?;
+        let replication_client = replication_stream_task_handle.replication_client();
+        entry.insert(replication_stream_task_handle);
+        Ok(replication_client)
+    }
+
+    /// Opens a replication stream between this ingester and the follower and spawns the task that
+    /// drives it.
+    async fn connect_replication_stream(
+        &self,
+        leader_id: NodeId,
+        follower_id: NodeId,
+    ) -> IngestV2Result<ReplicationStreamTaskHandle> {
         let open_request = OpenReplicationStreamRequest {
             leader_id: leader_id.clone().into(),
             follower_id: follower_id.clone().into(),
@@ -390,14 +934,12 @@ impl Ingester {
             .expect("first message should be an open response");
 
         let replication_stream_task_handle = ReplicationStreamTask::spawn(
-            leader_id.clone(),
-            follower_id.clone(),
+            leader_id,
+            follower_id,
             syn_replication_stream_tx,
             ack_replication_stream,
         );
-        let replication_client = replication_stream_task_handle.replication_client();
-        entry.insert(replication_stream_task_handle);
-        Ok(replication_client)
+        Ok(replication_stream_task_handle)
     }
 
     pub fn subscribe(&self, event_broker: &EventBroker) {
@@ -421,8 +963,8 @@ impl Ingester {
         }
         let mut persist_successes = Vec::with_capacity(persist_request.subrequests.len());
         let mut persist_failures = Vec::new();
-        let mut replicate_subrequests: HashMap<NodeId, Vec<(ReplicateSubrequest, QueueId)>> =
-            HashMap::new();
+        let mut replicate_subrequests: HashMap<NodeId, Vec<ReplicateSubrequest>> = HashMap::new();
+        let mut pending_replications: Vec<PendingReplication> = Vec::new();
         let mut local_persist_subrequests: Vec<LocalPersistSubrequest> =
             Vec::with_capacity(persist_request.subrequests.len());
 
@@ -437,13 +979,17 @@ impl Ingester {
         let force_commit = commit_type == CommitTypeV2::Force;
         let leader_id: NodeId = persist_request.leader_id.into();
 
+        let subrequests = self
+            .prepare_wal_doc_batches(persist_request.subrequests)
+            .await;
+
         let mut state_guard =
             with_lock_metrics!(self.state.lock_fully().await, "persist", "write")?;
 
         if state_guard.status() != IngesterStatus::Ready {
-            persist_failures.reserve_exact(persist_request.subrequests.len());
+            persist_failures.reserve_exact(subrequests.len());
 
-            for subrequest in persist_request.subrequests {
+            for (subrequest, _) in subrequests {
                 let persist_failure = PersistFailure {
                     subrequest_id: subrequest.subrequest_id,
                     index_uid: subrequest.index_uid,
@@ -465,9 +1011,34 @@ impl Ingester {
         {
             let mut total_requested_capacity = bytesize::ByteSize::b(0);
 
-            for subrequest in persist_request.subrequests {
+            for (subrequest, prepared_doc_batch_opt) in subrequests {
                 let queue_id = subrequest.queue_id();
 
+                let dedup_id_opt = subrequest
+                    .dedup_id
+                    .clone()
+                    .filter(|dedup_id| !dedup_id.is_empty() && !self.dedup_window.is_zero());
+
+                if let Some(dedup_id) = &dedup_id_opt {
+                    if let Some((shard_id, position)) = state_guard.find_dedup_id(
+                        subrequest.index_uid(),
+                        &subrequest.source_id,
+                        dedup_id,
+                        self.dedup_window,
+                    ) {
+                        debug!("batch `{dedup_id}` was already persisted to shard `{shard_id}`");
+
+                        let persist_success = PersistSuccess {
+                            subrequest_id: subrequest.subrequest_id,
+                            index_uid: subrequest.index_uid,
+                            source_id: subrequest.source_id,
+                            shard_id: Some(shard_id),
+                            replication_position_inclusive: Some(position),
+                        };
+                        persist_successes.push(persist_success);
+                        continue;
+                    }
+                }
                 let Some(shard) = state_guard.shards.get_mut(&queue_id) else {
                     let persist_failure = PersistFailure {
                         subrequest_id: subrequest.subrequest_id,
@@ -491,13 +1062,18 @@ impl Ingester {
                     continue;
                 }
 
-                let follower_id_opt = shard.follower_id_opt().cloned();
+                let follower_ids = shard.follower_ids().to_vec();
+                let write_quorum = shard.write_quorum();
                 let from_position_exclusive = shard.replication_position_inclusive.clone();
 
                 let index_uid = subrequest.index_uid().clone();
-                let doc_batch = match subrequest.doc_batch {
-                    Some(doc_batch) if !doc_batch.is_empty() => doc_batch,
-                    _ => {
+                let PreparedDocBatch {
+                    wal_doc_batch,
+                    rate_limited_capacity,
+                    num_bytes: batch_num_bytes,
+                } = match prepared_doc_batch_opt {
+                    Some(prepared_doc_batch) => prepared_doc_batch,
+                    None => {
                         warn!("received empty persist request");
 
                         let persist_success = PersistSuccess {
@@ -513,7 +1089,7 @@ impl Ingester {
                         continue;
                     }
                 };
-                let requested_capacity = estimate_size(&doc_batch);
+                let requested_capacity = wal_doc_batch.estimate_size();
 
                 if let Err(error) = check_enough_capacity(
                     &state_guard.mrecordlog,
@@ -541,7 +1117,7 @@ impl Ingester {
                     .get_mut(&queue_id)
                     .expect("rate limiter should be initialized");
 
-                if !rate_limiter.acquire_bytes(requested_capacity) {
+                if !rate_limiter.acquire_bytes(rate_limited_capacity) {
                     debug!("failed to persist records to shard `{queue_id}`: rate limited");
 
                     let persist_failure = PersistFailure {
@@ -555,118 +1131,190 @@ impl Ingester {
                     continue;
                 }
 
-                let batch_num_bytes = doc_batch.num_bytes() as u64;
                 rate_meter.update(batch_num_bytes);
                 total_requested_capacity += requested_capacity;
 
-                if let Some(follower_id) = follower_id_opt {
-                    let replicate_subrequest = ReplicateSubrequest {
+                if follower_ids.is_empty() {
+                    local_persist_subrequests.push(LocalPersistSubrequest {
+                        queue_id,
                         subrequest_id: subrequest.subrequest_id,
-                        index_uid: subrequest.index_uid,
+                        index_uid,
                         source_id: subrequest.source_id,
                         shard_id: subrequest.shard_id,
+                        doc_batch: wal_doc_batch,
+                        dedup_id_opt,
+                        expected_position_inclusive: None,
+                    })
+                } else {
+                    let (doc_batch_opt, compressed_doc_batch_opt) =
+                        wal_doc_batch.clone().into_parts();
+                    let replicate_subrequest = ReplicateSubrequest {
+                        subrequest_id: subrequest.subrequest_id,
+                        index_uid: subrequest.index_uid,
+                        source_id: subrequest.source_id.clone(),
+                        shard_id: subrequest.shard_id.clone(),
                         from_position_exclusive: Some(from_position_exclusive),
-                        doc_batch: Some(doc_batch),
+                        doc_batch: doc_batch_opt,
+                        dedup_id: dedup_id_opt.clone(),
+                        compressed_doc_batch: compressed_doc_batch_opt,
+                        mrecord_batch: None,
                     };
-                    replicate_subrequests
-                        .entry(follower_id)
-                        .or_default()
-                        .push((replicate_subrequest, queue_id));
-                } else {
-                    local_persist_subrequests.push(LocalPersistSubrequest {
+                    for follower_id in &follower_ids {
+                        replicate_subrequests
+                            .entry(follower_id.clone())
+                            .or_default()
+                            .push(replicate_subrequest.clone());
+                    }
+                    let local_persist_subrequest = LocalPersistSubrequest {
                         queue_id,
                         subrequest_id: subrequest.subrequest_id,
                         index_uid,
                         source_id: subrequest.source_id,
                         shard_id: subrequest.shard_id,
-                        doc_batch,
+                        doc_batch: wal_doc_batch,
+                        dedup_id_opt,
                         expected_position_inclusive: None,
-                    })
+                    };
+                    pending_replications.push(PendingReplication {
+                        local_persist_subrequest,
+                        follower_ids,
+                        write_quorum,
+                        replicated_follower_ids: Vec::new(),
+                        lost_follower_ids: Vec::new(),
+                        failure_reason_opt: None,
+                    });
                 }
             }
         }
 
-        // replicate to the follower
+        // Keep track of the followers that are out of sync with their primary shard and should no
+        // longer receive replicate requests.
+        let mut followers_to_remove: HashMap<QueueId, HashSet<NodeId>> = HashMap::new();
+
+        // replicate to the followers
         {
             let mut replicate_futures = FuturesUnordered::new();
-            let mut doc_batch_map = HashMap::new();
 
-            for (follower_id, subrequests_with_queue_id) in replicate_subrequests {
+            for (follower_id, subrequests) in replicate_subrequests {
                 let replication_client = state_guard
                     .replication_streams
                     .get(&follower_id)
                     .expect("replication stream should be initialized")
                     .replication_client();
                 let leader_id = self.self_node_id.clone();
-                let mut subrequests = Vec::with_capacity(subrequests_with_queue_id.len());
-                for (subrequest, queue_id) in subrequests_with_queue_id {
-                    let doc_batch = subrequest
-                        .doc_batch
-                        .clone()
-                        .expect("we already verified doc is present and not empty");
-                    doc_batch_map.insert(subrequest.subrequest_id, (doc_batch, queue_id));
-                    subrequests.push(subrequest);
-                }
-                let replicate_future =
-                    replication_client.replicate(leader_id, follower_id, subrequests, commit_type);
-                replicate_futures.push(replicate_future);
+                let replicate_future = replication_client.replicate(
+                    leader_id,
+                    follower_id.clone(),
+                    subrequests,
+                    commit_type,
+                );
+                replicate_futures.push(async move { (follower_id, replicate_future.await) });
             }
-
-            while let Some(replication_result) = replicate_futures.next().await {
+            let pending_replication_idx: HashMap<u32, usize> = pending_replications
+                .iter()
+                .enumerate()
+                .map(|(idx, pending_replication)| {
+                    (
+                        pending_replication.local_persist_subrequest.subrequest_id,
+                        idx,
+                    )
+                })
+                .collect();
+
+            while let Some((follower_id, replication_result)) = replicate_futures.next().await {
                 let replicate_response = match replication_result {
                     Ok(replicate_response) => replicate_response,
-                    Err(_) => {
-                        // TODO: Handle replication error:
-                        // 1. Close and evict all the shards hosted by the follower.
-                        // 2. Close and evict the replication client.
-                        // 3. Return `PersistFailureReason::ShardClosed` to router.
+                    Err(replication_error) => {
+                        rate_limited_warn!(
+                            limit_per_min = 10,
+                            "failed to replicate records to ingester `{follower_id}`: \
+                             {replication_error}"
+                        );
                         continue;
                     }
                 };
                 for replicate_success in replicate_response.successes {
-                    let (doc_batch, queue_id) = doc_batch_map
-                        .remove(&replicate_success.subrequest_id)
-                        .expect("expected known subrequest id");
-                    let local_persist_subrequest = LocalPersistSubrequest {
-                        queue_id,
-                        subrequest_id: replicate_success.subrequest_id,
-                        index_uid: replicate_success.index_uid().clone(),
-                        source_id: replicate_success.source_id,
-                        shard_id: replicate_success.shard_id,
-                        doc_batch,
-                        expected_position_inclusive: replicate_success
-                            .replication_position_inclusive,
-                    };
-                    local_persist_subrequests.push(local_persist_subrequest);
+                    let idx = pending_replication_idx[&replicate_success.subrequest_id];
+                    let pending_replication = &mut pending_replications[idx];
+                    pending_replication
+                        .replicated_follower_ids
+                        .push(follower_id.clone());
+                    pending_replication
+                        .local_persist_subrequest
+                        .expected_position_inclusive =
+                        replicate_success.replication_position_inclusive;
                 }
                 for replicate_failure in replicate_response.failures {
-                    // TODO: If the replica shard is closed, close the primary shard if it is not
-                    // already.
-                    let persist_failure_reason = match replicate_failure.reason() {
-                        ReplicateFailureReason::Unspecified => PersistFailureReason::Unspecified,
-                        ReplicateFailureReason::ShardNotFound => {
-                            PersistFailureReason::ShardNotFound
-                        }
-                        ReplicateFailureReason::ShardClosed => PersistFailureReason::ShardClosed,
-                        ReplicateFailureReason::ResourceExhausted => {
-                            PersistFailureReason::ResourceExhausted
+                    let idx = pending_replication_idx[&replicate_failure.subrequest_id];
+                    let pending_replication = &mut pending_replications[idx];
+                    let replicate_failure_reason = replicate_failure.reason();
+
+                    if matches!(
+                        replicate_failure_reason,
+                        ReplicateFailureReason::ShardNotFound | ReplicateFailureReason::ShardClosed
+                    ) {
+                        pending_replication
+                            .lost_follower_ids
+                            .push(follower_id.clone());
+                    }
+                    pending_replication
+                        .failure_reason_opt
+                        .get_or_insert(replicate_failure_reason);
+                }
+            }
+            // The records of a subrequest are written locally, and therefore acknowledged, only if
+            // enough followers replicated them to reach the write quorum.
+            for pending_replication in pending_replications {
+                let queue_id = &pending_replication.local_persist_subrequest.queue_id;
+                let num_replicas = 1 + pending_replication.replicated_follower_ids.len();
+
+                if num_replicas >= pending_replication.write_quorum {
+                    for follower_id in pending_replication.follower_ids {
+                        if !pending_replication
+                            .replicated_follower_ids
+                            .contains(&follower_id)
+                        {
+                            followers_to_remove
+                                .entry(queue_id.clone())
+                                .or_default()
+                                .insert(follower_id);
                         }
-                    };
-                    let persist_failure = PersistFailure {
-                        subrequest_id: replicate_failure.subrequest_id,
-                        index_uid: replicate_failure.index_uid,
-                        source_id: replicate_failure.source_id,
-                        shard_id: replicate_failure.shard_id,
-                        reason: persist_failure_reason as i32,
-                    };
-                    persist_failures.push(persist_failure);
+                    }
+                    local_persist_subrequests.push(pending_replication.local_persist_subrequest);
+                    continue;
+                }
+                // The followers that replicated the records now hold records that the primary
+                // shard will never have.
+                for follower_id in pending_replication
+                    .replicated_follower_ids
+                    .into_iter()
+                    .chain(pending_replication.lost_follower_ids)
+                {
+                    followers_to_remove
+                        .entry(queue_id.clone())
+                        .or_default()
+                        .insert(follower_id);
                 }
+                let persist_failure_reason = pending_replication
+                    .failure_reason_opt
+                    .map(PersistFailureReason::from)
+                    .unwrap_or(PersistFailureReason::Unspecified);
+                let local_persist_subrequest = pending_replication.local_persist_subrequest;
+                let persist_failure = PersistFailure {
+                    subrequest_id: local_persist_subrequest.subrequest_id,
+                    index_uid: Some(local_persist_subrequest.index_uid),
+                    source_id: local_persist_subrequest.source_id,
+                    shard_id: local_persist_subrequest.shard_id,
+                    reason: persist_failure_reason as i32,
+                };
+                persist_failures.push(persist_failure);
             }
         }
 
         // finally write locally
         {
             let now = Instant::now();
+            let today = today();
             for subrequest in local_persist_subrequests {
                 let queue_id = subrequest.queue_id;
 
@@ -678,6 +1326,7 @@ impl Ingester {
                     &queue_id,
                     subrequest.doc_batch,
                     force_commit,
+                    subrequest.dedup_id_opt.clone(),
                 )
                 .await;
 
@@ -721,15 +1370,29 @@ impl Ingester {
                         )));
                     }
                 }
-                state_guard
+                let shard = state_guard
                     .shards
                     .get_mut(&queue_id)
-                    .expect("primary shard should exist")
-                    .set_replication_position_inclusive(current_position_inclusive.clone(), now);
-
+                    .expect("primary shard should exist");
+                shard.set_replication_position_inclusive(current_position_inclusive.clone(), now);
+
+                if let Some(dedup_id) = subrequest.dedup_id_opt {
+                    shard.dedup_ids.insert(
+                        dedup_id,
+                        current_position_inclusive.clone(),
+                        unix_timestamp_secs(),
+                    );
+                }
+
                 INGEST_METRICS.ingested_num_bytes.inc_by(batch_num_bytes);
                 INGEST_METRICS.ingested_num_docs.inc_by(batch_num_docs);
 
+                state_guard
+                    .daily_ingest_volumes
+                    .entry(subrequest.index_uid.clone())
+                    .or_default()
+                    .record(today, batch_num_bytes);
+
                 let persist_success = PersistSuccess {
                     subrequest_id: subrequest.subrequest_id,
                     index_uid: Some(subrequest.index_uid),
@@ -758,6 +1421,23 @@ impl Ingester {
                 warn!("deleted dangling shard `{queue_id}`");
             }
         }
+        for (queue_id, follower_ids) in followers_to_remove {
+            let Some(shard) = state_guard.shards.get_mut(&queue_id) else {
+                continue;
+            };
+            for follower_id in follower_ids {
+                shard.remove_follower(&follower_id);
+                warn!(
+                    "stopped replicating shard `{queue_id}` to out-of-sync follower \
+                     `{follower_id}`"
+                );
+                self.background_delete_replica_shard(follower_id, &queue_id);
+            }
+            if shard.is_open() && shard.follower_ids().len() + 1 < shard.write_quorum() {
+                shard.close();
+                warn!("closed shard `{queue_id}`: not enough followers left to reach write quorum");
+            }
+        }
         let wal_usage = state_guard.mrecordlog.resource_usage();
         drop(state_guard);
 
@@ -884,14 +1564,23 @@ impl Ingester {
         let now = Instant::now();
 
         for shard in init_shards_request.shards {
+            let max_shard_throughput_opt = init_shards_request
+                .max_shard_throughputs
+                .get(&shard.index_uid().to_string())
+                .copied();
             self.init_primary_shard(
                 &mut state_guard.inner,
                 &mut state_guard.mrecordlog,
                 shard,
                 now,
+                max_shard_throughput_opt,
             )
             .await?;
         }
+        for shard in init_shards_request.promoted_shards {
+            self.promote_replica_shard(&mut state_guard.inner, shard)
+                .await?;
+        }
         Ok(InitShardsResponse {})
     }
 
@@ -945,17 +1634,76 @@ impl Ingester {
         Ok(CloseShardsResponse {})
     }
 
+    async fn get_replica_positions_inner(
+        &mut self,
+        get_replica_positions_request: GetReplicaPositionsRequest,
+    ) -> IngestV2Result<GetReplicaPositionsResponse> {
+        let state_guard = with_lock_metrics!(
+            self.state.lock_partially().await,
+            "get_replica_positions",
+            "read"
+        )?;
+        let mut replica_positions = Vec::new();
+
+        for shard_ids in get_replica_positions_request.shards {
+            for shard_id in &shard_ids.shard_ids {
+                let queue_id = queue_id(shard_ids.index_uid(), &shard_ids.source_id, shard_id);
+
+                let Some(shard) = state_guard.shards.get(&queue_id) else {
+                    continue;
+                };
+                if !shard.is_replica() {
+                    continue;
+                }
+                let replica_position = ReplicaPosition {
+                    index_uid: shard_ids.index_uid.clone(),
+                    source_id: shard_ids.source_id.clone(),
+                    shard_id: Some(shard_id.clone()),
+                    replication_position_inclusive: Some(
+                        shard.replication_position_inclusive.clone(),
+                    ),
+                };
+                replica_positions.push(replica_position);
+            }
+        }
+        Ok(GetReplicaPositionsResponse { replica_positions })
+    }
+
     async fn decommission_inner(
         &mut self,
-        _decommission_request: DecommissionRequest,
+        decommission_request: DecommissionRequest,
     ) -> IngestV2Result<DecommissionResponse> {
-        info!("decommissioning ingester");
-        let mut state_guard = self.state.lock_partially().await?;
+        let decommission_mode = decommission_request.mode();
+        info!(
+            "decommissioning ingester in {} mode",
+            decommission_mode.as_str_name()
+        );
+        let mut state_guard = self.state.lock_fully().await?;
 
         for shard in state_guard.shards.values_mut() {
             shard.close();
         }
         state_guard.set_status(IngesterStatus::Decommissioning);
+        state_guard.decommission_mode = decommission_mode;
+
+        if decommission_mode != DecommissionMode::Handoff {
+            self.check_decommissioning_status(&mut state_guard);
+            return Ok(DecommissionResponse {});
+        }
+        let shard_tails = self.snapshot_shard_tails(&state_guard);
+        drop(state_guard);
+
+        let deadline = Instant::now() + HANDOFF_TIMEOUT;
+        let new_leader_ids = self.handoff_shards(shard_tails, deadline).await;
+
+        let mut state_guard = self.state.lock_fully().await?;
+
+        for (queue_id, new_leader_id) in new_leader_ids {
+            // The shard may have been indexed and deleted in the meantime.
+            if let Some(shard) = state_guard.shards.get_mut(&queue_id) {
+                shard.demote(new_leader_id);
+            }
+        }
         self.check_decommissioning_status(&mut state_guard);
 
         Ok(DecommissionResponse {})
@@ -1055,6 +1803,14 @@ impl IngesterService for Ingester {
     ) -> IngestV2Result<DecommissionResponse> {
         self.decommission_inner(decommission_request).await
     }
+
+    async fn get_replica_positions(
+        &mut self,
+        get_replica_positions_request: GetReplicaPositionsRequest,
+    ) -> IngestV2Result<GetReplicaPositionsResponse> {
+        self.get_replica_positions_inner(get_replica_positions_request)
+            .await
+    }
 }
 
 #[async_trait]
@@ -1106,11 +1862,15 @@ pub async fn wait_for_ingester_status(
 
 pub async fn wait_for_ingester_decommission(
     mut ingester: IngesterServiceClient,
+    decommission_mode: DecommissionMode,
 ) -> anyhow::Result<()> {
     let now = Instant::now();
 
+    let decommission_request = DecommissionRequest {
+        mode: decommission_mode as i32,
+    };
     ingester
-        .decommission(DecommissionRequest {})
+        .decommission(decommission_request)
         .await
         .context("failed to initiate ingester decommission")?;
 
@@ -1123,16 +1883,116 @@ pub async fn wait_for_ingester_decommission(
     Ok(())
 }
 
+/// Lowers the rate limit of a shard to its maximum throughput in bytes per second, if any. The
+/// burst limit is left untouched so that the shard still accepts requests as large as before.
+fn scale_rate_limiter_settings(
+    rate_limiter_settings: RateLimiterSettings,
+    max_shard_throughput_opt: Option<u64>,
+) -> RateLimiterSettings {
+    let Some(max_shard_throughput) = max_shard_throughput_opt else {
+        return rate_limiter_settings;
+    };
+    let rate_limit = rate_limiter_settings
+        .rate_limit
+        .rescale(Duration::from_secs(1));
+
+    if max_shard_throughput >= rate_limit.work() {
+        return rate_limiter_settings;
+    }
+    // The rate limiter must refill at least one permit per refill period.
+    let min_shard_throughput = Duration::from_secs(1)
+        .as_nanos()
+        .div_ceil(rate_limiter_settings.refill_period.as_nanos())
+        as u64;
+    let max_shard_throughput = max_shard_throughput.max(min_shard_throughput);
+
+    RateLimiterSettings {
+        rate_limit: ConstantRate::bytes_per_sec(ByteSize::b(max_shard_throughput)),
+        ..rate_limiter_settings
+    }
+}
+
+/// Runs an RPC issued during the handoff of the shards of a decommissioning ingester, failing if it
+/// does not complete before `deadline`.
+async fn handoff_rpc<T>(
+    deadline: Instant,
+    future: impl Future<Output = IngestV2Result<T>>,
+) -> IngestV2Result<T> {
+    let timeout_duration = deadline.saturating_duration_since(Instant::now());
+
+    match timeout(timeout_duration, future).await {
+        Ok(result) => result,
+        Err(_) => Err(IngestV2Error::Timeout(format!(
+            "handoff timed out after {HANDOFF_TIMEOUT:?}"
+        ))),
+    }
+}
+
+/// Tail of a shard to hand off, snapshotted while the ingester state is locked.
+struct ShardTail {
+    queue_id: QueueId,
+    follower_ids: Vec<NodeId>,
+    truncation_position_inclusive: Position,
+    replication_position_inclusive: Position,
+}
+
+/// A document batch ready to be written to the WAL.
+struct PreparedDocBatch {
+    wal_doc_batch: WalDocBatch,
+    // Rate limits apply to the documents before compression.
+    rate_limited_capacity: ByteSize,
+    num_bytes: u64,
+}
+
+impl PreparedDocBatch {
+    /// Compresses the document batch if compression is enabled. Falls back to the uncompressed
+    /// batch if compression fails.
+    fn new(doc_batch: DocBatchV2, wal_compression_level_opt: Option<i32>) -> Self {
+        let rate_limited_capacity = estimate_size(&doc_batch);
+        let num_bytes = doc_batch.num_bytes() as u64;
+
+        let wal_doc_batch = match wal_compression_level_opt {
+            Some(compression_level) => match doc_batch.compress(compression_level) {
+                Ok(compressed_doc_batch) => WalDocBatch::Compressed(compressed_doc_batch),
+                Err(error) => {
+                    rate_limited_warn!(limit_per_min = 10, "failed to compress doc batch: {error}");
+                    WalDocBatch::Uncompressed(doc_batch)
+                }
+            },
+            None => WalDocBatch::Uncompressed(doc_batch),
+        };
+        Self {
+            wal_doc_batch,
+            rate_limited_capacity,
+            num_bytes,
+        }
+    }
+}
+
 struct LocalPersistSubrequest {
     queue_id: QueueId,
     subrequest_id: u32,
     index_uid: IndexUid,
     source_id: SourceId,
     shard_id: Option<quickwit_proto::types::ShardId>,
-    doc_batch: quickwit_proto::ingest::DocBatchV2,
+    doc_batch: WalDocBatch,
+    dedup_id_opt: Option<String>,
     expected_position_inclusive: Option<Position>,
 }
 
+/// A local persist subrequest waiting for its records to be replicated by the followers of the
+/// shard.
+struct PendingReplication {
+    local_persist_subrequest: LocalPersistSubrequest,
+    follower_ids: Vec<NodeId>,
+    write_quorum: usize,
+    replicated_follower_ids: Vec<NodeId>,
+    /// Followers that closed or no longer host their replica shard.
+    lost_follower_ids: Vec<NodeId>,
+    /// Reason of the first replication failure reported by a follower.
+    failure_reason_opt: Option<ReplicateFailureReason>,
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::mutable_key_type)]
@@ -1144,14 +2004,15 @@ mod tests {
     use bytes::Bytes;
     use quickwit_cluster::{create_cluster_for_test_with_id, ChannelTransport};
     use quickwit_common::shared_consts::INGESTER_PRIMARY_SHARDS_PREFIX;
-    use quickwit_common::tower::ConstantRate;
     use quickwit_config::service::QuickwitService;
-    use quickwit_proto::control_plane::{AdviseResetShardsResponse, MockControlPlaneService};
+    use quickwit_proto::control_plane::{
+        AdviseResetShardsResponse, HandoffShardsResponse, MockControlPlaneService,
+    };
     use quickwit_proto::ingest::ingester::{
         IngesterServiceGrpcServer, IngesterServiceGrpcServerAdapter, PersistSubrequest,
         TruncateShardsSubrequest,
     };
-    use quickwit_proto::ingest::{DocBatchV2, ShardIdPosition, ShardIdPositions, ShardIds};
+    use quickwit_proto::ingest::{ShardIdPosition, ShardIdPositions, ShardIds};
     use quickwit_proto::types::{queue_id, ShardId, SourceUid};
     use tokio::task::yield_now;
     use tokio::time::timeout;
@@ -1161,7 +2022,7 @@ mod tests {
     use crate::ingest_v2::broadcast::ShardInfos;
     use crate::ingest_v2::fetch::tests::{into_fetch_eof, into_fetch_payload};
     use crate::ingest_v2::DEFAULT_IDLE_SHARD_TIMEOUT;
-    use crate::MRecord;
+    use crate::{decoded_mrecords, MRecord};
 
     const MAX_GRPC_MESSAGE_SIZE: ByteSize = ByteSize::mib(1);
 
@@ -1174,6 +2035,8 @@ mod tests {
         rate_limiter_settings: RateLimiterSettings,
         replication_factor: usize,
         idle_shard_timeout: Duration,
+        dedup_window: Duration,
+        wal_compression_level_opt: Option<i32>,
     }
 
     impl Default for IngesterForTest {
@@ -1193,6 +2056,8 @@ mod tests {
                 rate_limiter_settings: RateLimiterSettings::default(),
                 replication_factor: 1,
                 idle_shard_timeout: DEFAULT_IDLE_SHARD_TIMEOUT,
+                dedup_window: Duration::from_secs(600),
+                wal_compression_level_opt: None,
             }
         }
     }
@@ -1236,6 +2101,11 @@ mod tests {
             self
         }
 
+        pub fn with_wal_compression(mut self, compression_level: i32) -> Self {
+            self.wal_compression_level_opt = Some(compression_level);
+            self
+        }
+
         pub async fn build(self) -> (IngesterContext, Ingester) {
             static GOSSIP_ADVERTISE_PORT_SEQUENCE: AtomicU16 = AtomicU16::new(1u16);
 
@@ -1268,6 +2138,8 @@ mod tests {
                 self.rate_limiter_settings,
                 self.replication_factor,
                 self.idle_shard_timeout,
+                self.dedup_window,
+                self.wal_compression_level_opt,
             )
             .await
             .unwrap();
@@ -1478,6 +2350,8 @@ mod tests {
                     ..Default::default()
                 },
             ],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
         };
         ingester.init_shards(init_shards_request).await.unwrap();
 
@@ -1491,6 +2365,7 @@ mod tests {
                     source_id: "test-source".to_string(),
                     shard_id: Some(ShardId::from(1)),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                    dedup_id: None,
                 },
                 PersistSubrequest {
                     subrequest_id: 1,
@@ -1498,6 +2373,7 @@ mod tests {
                     source_id: "test-source".to_string(),
                     shard_id: Some(ShardId::from(1)),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-110", "test-doc-111"])),
+                    dedup_id: None,
                 },
             ],
         };
@@ -1556,6 +2432,170 @@ mod tests {
                 (2, "\0\x01"),
             ],
         );
+
+        let daily_ingest_volume = state_guard.daily_ingest_volumes[&index_uid];
+        assert_eq!(daily_ingest_volume.day, today());
+        assert_eq!(daily_ingest_volume.num_bytes, 12);
+
+        let daily_ingest_volume = state_guard.daily_ingest_volumes[&index_uid2];
+        assert_eq!(daily_ingest_volume.day, today());
+        assert_eq!(daily_ingest_volume.num_bytes, 24);
+    }
+
+    #[tokio::test]
+    async fn test_ingester_persist_deduplicates_batches() {
+        let (ingester_ctx, mut ingester) = IngesterForTest::default().build().await;
+
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+        let init_shards_request = InitShardsRequest {
+            shards: vec![
+                Shard {
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    shard_state: ShardState::Open as i32,
+                    leader_id: ingester_ctx.node_id.to_string(),
+                    ..Default::default()
+                },
+                Shard {
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(2)),
+                    shard_state: ShardState::Open as i32,
+                    leader_id: ingester_ctx.node_id.to_string(),
+                    ..Default::default()
+                },
+            ],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
+        };
+        ingester.init_shards(init_shards_request).await.unwrap();
+
+        let make_persist_request = |shard_id: u64, dedup_id: &str| PersistRequest {
+            leader_id: ingester_ctx.node_id.to_string(),
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![PersistSubrequest {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(shard_id)),
+                doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
+                dedup_id: Some(dedup_id.to_string()),
+            }],
+        };
+        let persist_response = ingester
+            .persist(make_persist_request(1, "test-dedup-id-foo"))
+            .await
+            .unwrap();
+        assert_eq!(persist_response.successes.len(), 1);
+        assert_eq!(persist_response.failures.len(), 0);
+
+        let persist_success = &persist_response.successes[0];
+        assert_eq!(persist_success.shard_id(), ShardId::from(1));
+        assert_eq!(
+            persist_success.replication_position_inclusive,
+            Some(Position::offset(1u64))
+        );
+
+        // The retry is routed to another shard of the same source.
+        let persist_response = ingester
+            .persist(make_persist_request(2, "test-dedup-id-foo"))
+            .await
+            .unwrap();
+        assert_eq!(persist_response.successes.len(), 1);
+        assert_eq!(persist_response.failures.len(), 0);
+
+        let persist_success = &persist_response.successes[0];
+        assert_eq!(persist_success.shard_id(), ShardId::from(1));
+        assert_eq!(
+            persist_success.replication_position_inclusive,
+            Some(Position::offset(1u64))
+        );
+
+        let persist_response = ingester
+            .persist(make_persist_request(1, "test-dedup-id-bar"))
+            .await
+            .unwrap();
+        let persist_success = &persist_response.successes[0];
+        assert_eq!(persist_success.shard_id(), ShardId::from(1));
+        assert_eq!(
+            persist_success.replication_position_inclusive,
+            Some(Position::offset(3u64))
+        );
+
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+        let queue_id_02 = queue_id(&index_uid, "test-source", &ShardId::from(2));
+
+        let mut state_guard = ingester.state.lock_fully().await.unwrap();
+        assert_eq!(
+            state_guard
+                .mrecordlog
+                .range(&queue_id_01, ..)
+                .unwrap()
+                .count(),
+            4
+        );
+        assert_eq!(
+            state_guard
+                .mrecordlog
+                .range(&queue_id_02, ..)
+                .unwrap()
+                .count(),
+            0
+        );
+
+        // The dedup IDs survive a restart.
+        state_guard.set_status(IngesterStatus::Initializing);
+        drop(state_guard);
+
+        ingester
+            .state
+            .init(ingester_ctx.tempdir.path(), RateLimiterSettings::default())
+            .await;
+
+        let init_shards_request = InitShardsRequest {
+            shards: vec![Shard {
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(3)),
+                shard_state: ShardState::Open as i32,
+                leader_id: ingester_ctx.node_id.to_string(),
+                ..Default::default()
+            }],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
+        };
+        ingester.init_shards(init_shards_request).await.unwrap();
+
+        let persist_response = ingester
+            .persist(make_persist_request(3, "test-dedup-id-bar"))
+            .await
+            .unwrap();
+        assert_eq!(persist_response.successes.len(), 1);
+
+        let persist_success = &persist_response.successes[0];
+        assert_eq!(persist_success.shard_id(), ShardId::from(1));
+        assert_eq!(
+            persist_success.replication_position_inclusive,
+            Some(Position::offset(3u64))
+        );
+
+        let state_guard = ingester.state.lock_fully().await.unwrap();
+        state_guard
+            .shards
+            .get(&queue_id_01)
+            .unwrap()
+            .assert_is_closed();
+
+        let queue_id_03 = queue_id(&index_uid, "test-source", &ShardId::from(3));
+        assert_eq!(
+            state_guard
+                .mrecordlog
+                .range(&queue_id_03, ..)
+                .unwrap()
+                .count(),
+            0
+        );
     }
 
     #[tokio::test]
@@ -1582,6 +2622,7 @@ mod tests {
                 source_id: "test-source".to_string(),
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: None,
+                dedup_id: None,
             }],
         };
 
@@ -1594,6 +2635,8 @@ mod tests {
                 leader_id: ingester_ctx.node_id.to_string(),
                 ..Default::default()
             }],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
         };
         ingester.init_shards(init_shards_request).await.unwrap();
 
@@ -1660,6 +2703,7 @@ mod tests {
                 source_id: "test-source".to_string(),
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
+                dedup_id: None,
             }],
         };
         let persist_response = ingester.persist(persist_request).await.unwrap();
@@ -1713,6 +2757,7 @@ mod tests {
                 source_id: "test-source".to_string(),
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
+                dedup_id: None,
             }],
         };
         let persist_response = ingester.persist(persist_request).await.unwrap();
@@ -1779,6 +2824,8 @@ mod tests {
                     ..Default::default()
                 },
             ],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
         };
         leader.init_shards(init_shards_request).await.unwrap();
 
@@ -1792,6 +2839,7 @@ mod tests {
                     source_id: "test-source".to_string(),
                     shard_id: Some(ShardId::from(1)),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                    dedup_id: None,
                 },
                 PersistSubrequest {
                     subrequest_id: 1,
@@ -1799,6 +2847,7 @@ mod tests {
                     source_id: "test-source".to_string(),
                     shard_id: Some(ShardId::from(1)),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-110", "test-doc-111"])),
+                    dedup_id: None,
                 },
             ],
         };
@@ -1889,27 +2938,14 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_ingester_persist_replicate_grpc() {
+    async fn test_ingester_persist_replicate_compressed() {
         let (leader_ctx, mut leader) = IngesterForTest::default()
             .with_node_id("test-leader")
             .with_replication()
+            .with_wal_compression(3)
             .build()
             .await;
 
-        let leader_grpc_server_adapter = IngesterServiceGrpcServerAdapter::new(leader.clone());
-        let leader_grpc_server = IngesterServiceGrpcServer::new(leader_grpc_server_adapter);
-        let leader_socket_addr: SocketAddr = "127.0.0.1:6666".parse().unwrap();
-
-        tokio::spawn({
-            async move {
-                Server::builder()
-                    .add_service(leader_grpc_server)
-                    .serve(leader_socket_addr)
-                    .await
-                    .unwrap();
-            }
-        });
-
         let (follower_ctx, follower) = IngesterForTest::default()
             .with_node_id("test-follower")
             .with_ingester_pool(&leader_ctx.ingester_pool)
@@ -1917,153 +2953,851 @@ mod tests {
             .build()
             .await;
 
-        let follower_grpc_server_adapter = IngesterServiceGrpcServerAdapter::new(follower.clone());
-        let follower_grpc_server = IngesterServiceGrpcServer::new(follower_grpc_server_adapter);
-        let follower_socket_addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        leader_ctx.ingester_pool.insert(
+            follower_ctx.node_id.clone(),
+            IngesterServiceClient::new(follower.clone()),
+        );
+
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+
+        let init_shards_request = InitShardsRequest {
+            shards: vec![Shard {
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                shard_state: ShardState::Open as i32,
+                leader_id: leader_ctx.node_id.to_string(),
+                follower_id: Some(follower_ctx.node_id.to_string()),
+                ..Default::default()
+            }],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
+        };
+        leader.init_shards(init_shards_request).await.unwrap();
+
+        let doc_batch = DocBatchV2::for_test(["test-doc-010", "test-doc-011"]);
+        let persist_request = PersistRequest {
+            leader_id: "test-leader".to_string(),
+            commit_type: CommitTypeV2::Force as i32,
+            subrequests: vec![PersistSubrequest {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                doc_batch: Some(doc_batch.clone()),
+                dedup_id: None,
+            }],
+        };
+        let persist_response = leader.persist(persist_request).await.unwrap();
+        assert_eq!(persist_response.successes.len(), 1);
+        assert_eq!(persist_response.failures.len(), 0);
+
+        // The documents are written to the WAL as a single compressed record.
+        let persist_success = &persist_response.successes[0];
+        assert_eq!(
+            persist_success.replication_position_inclusive,
+            Some(Position::offset(1u64))
+        );
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+        let expected_mrecords = [
+            MRecord::CompressedDocs(doc_batch.compress(3).unwrap().payload),
+            MRecord::Commit,
+        ];
+        let leader_state_guard = leader.state.lock_fully().await.unwrap();
+        let leader_mrecords: Vec<MRecord> = leader_state_guard
+            .mrecordlog
+            .range(&queue_id_01, ..)
+            .unwrap()
+            .flat_map(|record| MRecord::decode(&record.payload[..]))
+            .collect();
+        assert_eq!(leader_mrecords, expected_mrecords);
+        drop(leader_state_guard);
+
+        let follower_state_guard = follower.state.lock_fully().await.unwrap();
+        let replica_shard_01 = follower_state_guard.shards.get(&queue_id_01).unwrap();
+        replica_shard_01.assert_replication_position(Position::offset(1u64));
+
+        let follower_mrecords: Vec<MRecord> = follower_state_guard
+            .mrecordlog
+            .range(&queue_id_01, ..)
+            .unwrap()
+            .flat_map(|record| MRecord::decode(&record.payload[..]))
+            .collect();
+        assert_eq!(follower_mrecords, expected_mrecords);
+        drop(follower_state_guard);
+
+        // Fetch streams decompress the documents.
+        let open_fetch_stream_request = OpenFetchStreamRequest {
+            client_id: "test-client".to_string(),
+            index_uid: Some(index_uid.clone()),
+            source_id: "test-source".to_string(),
+            shard_id: Some(ShardId::from(1)),
+            from_position_exclusive: Some(Position::Beginning),
+        };
+        let mut fetch_stream = leader
+            .open_fetch_stream(open_fetch_stream_request)
+            .await
+            .unwrap();
+
+        let fetch_response = fetch_stream.next().await.unwrap().unwrap();
+        let fetch_payload = into_fetch_payload(fetch_response);
+
+        assert_eq!(
+            fetch_payload.to_position_inclusive(),
+            Position::offset(1u64)
+        );
+        let mrecords: Vec<MRecord> =
+            decoded_mrecords(fetch_payload.mrecord_batch.as_ref().unwrap()).collect();
+        assert_eq!(
+            mrecords,
+            [
+                MRecord::new_doc("test-doc-010"),
+                MRecord::new_doc("test-doc-011"),
+                MRecord::Commit,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingester_persist_replicate_grpc() {
+        let (leader_ctx, mut leader) = IngesterForTest::default()
+            .with_node_id("test-leader")
+            .with_replication()
+            .build()
+            .await;
+
+        let leader_grpc_server_adapter = IngesterServiceGrpcServerAdapter::new(leader.clone());
+        let leader_grpc_server = IngesterServiceGrpcServer::new(leader_grpc_server_adapter);
+        let leader_socket_addr: SocketAddr = "127.0.0.1:6666".parse().unwrap();
+
+        tokio::spawn({
+            async move {
+                Server::builder()
+                    .add_service(leader_grpc_server)
+                    .serve(leader_socket_addr)
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let (follower_ctx, follower) = IngesterForTest::default()
+            .with_node_id("test-follower")
+            .with_ingester_pool(&leader_ctx.ingester_pool)
+            .with_replication()
+            .build()
+            .await;
+
+        let follower_grpc_server_adapter = IngesterServiceGrpcServerAdapter::new(follower.clone());
+        let follower_grpc_server = IngesterServiceGrpcServer::new(follower_grpc_server_adapter);
+        let follower_socket_addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+
+        tokio::spawn({
+            async move {
+                Server::builder()
+                    .add_service(follower_grpc_server)
+                    .serve(follower_socket_addr)
+                    .await
+                    .unwrap();
+            }
+        });
+        let follower_channel = Endpoint::from_static("http://127.0.0.1:7777").connect_lazy();
+        let follower_grpc_client = IngesterServiceClient::from_channel(
+            "127.0.0.1:7777".parse().unwrap(),
+            follower_channel,
+            MAX_GRPC_MESSAGE_SIZE,
+        );
+
+        leader_ctx
+            .ingester_pool
+            .insert(follower_ctx.node_id.clone(), follower_grpc_client);
+
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+        let index_uid2: IndexUid = IndexUid::for_test("test-index", 1);
+
+        let init_shards_request = InitShardsRequest {
+            shards: vec![
+                Shard {
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    shard_state: ShardState::Open as i32,
+                    leader_id: leader_ctx.node_id.to_string(),
+                    follower_id: Some(follower_ctx.node_id.to_string()),
+                    ..Default::default()
+                },
+                Shard {
+                    index_uid: Some(index_uid2.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    shard_state: ShardState::Open as i32,
+                    leader_id: leader_ctx.node_id.to_string(),
+                    follower_id: Some(follower_ctx.node_id.to_string()),
+                    ..Default::default()
+                },
+            ],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
+        };
+        leader.init_shards(init_shards_request).await.unwrap();
+
+        let persist_request = PersistRequest {
+            leader_id: "test-leader".to_string(),
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![
+                PersistSubrequest {
+                    subrequest_id: 0,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                    dedup_id: None,
+                },
+                PersistSubrequest {
+                    subrequest_id: 1,
+                    index_uid: Some(index_uid2.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    doc_batch: Some(DocBatchV2::for_test(["test-doc-110", "test-doc-111"])),
+                    dedup_id: None,
+                },
+            ],
+        };
+        let persist_response = leader.persist(persist_request).await.unwrap();
+        assert_eq!(persist_response.leader_id, "test-leader");
+        assert_eq!(persist_response.successes.len(), 2);
+        assert_eq!(persist_response.failures.len(), 0);
+
+        let persist_success_0 = &persist_response.successes[0];
+        assert_eq!(persist_success_0.subrequest_id, 0);
+        assert_eq!(persist_success_0.index_uid(), &index_uid);
+        assert_eq!(persist_success_0.source_id, "test-source");
+        assert_eq!(persist_success_0.shard_id(), ShardId::from(1));
+        assert_eq!(
+            persist_success_0.replication_position_inclusive,
+            Some(Position::offset(0u64))
+        );
+
+        let persist_success_1 = &persist_response.successes[1];
+        assert_eq!(persist_success_1.subrequest_id, 1);
+        assert_eq!(persist_success_1.index_uid(), &index_uid2);
+        assert_eq!(persist_success_1.source_id, "test-source");
+        assert_eq!(persist_success_1.shard_id(), ShardId::from(1));
+        assert_eq!(
+            persist_success_1.replication_position_inclusive,
+            Some(Position::offset(1u64))
+        );
+
+        let leader_state_guard = leader.state.lock_fully().await.unwrap();
+        assert_eq!(leader_state_guard.shards.len(), 2);
+
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+        let primary_shard_01 = leader_state_guard.shards.get(&queue_id_01).unwrap();
+        primary_shard_01.assert_is_primary();
+        primary_shard_01.assert_is_open();
+        primary_shard_01.assert_replication_position(Position::offset(0u64));
+
+        leader_state_guard.mrecordlog.assert_records_eq(
+            &queue_id_01,
+            ..,
+            &[(0, "\0\0test-doc-010")],
+        );
+
+        let queue_id_11 = queue_id(&index_uid2, "test-source", &ShardId::from(1));
+        let primary_shard_11 = leader_state_guard.shards.get(&queue_id_11).unwrap();
+        primary_shard_11.assert_is_primary();
+        primary_shard_11.assert_is_open();
+        primary_shard_11.assert_replication_position(Position::offset(1u64));
+
+        leader_state_guard.mrecordlog.assert_records_eq(
+            &queue_id_11,
+            ..,
+            &[(0, "\0\0test-doc-110"), (1, "\0\0test-doc-111")],
+        );
+
+        let follower_state_guard = follower.state.lock_fully().await.unwrap();
+        assert_eq!(follower_state_guard.shards.len(), 2);
+
+        let replica_shard_01 = follower_state_guard.shards.get(&queue_id_01).unwrap();
+        replica_shard_01.assert_is_replica();
+        replica_shard_01.assert_is_open();
+        replica_shard_01.assert_replication_position(Position::offset(0u64));
+
+        follower_state_guard.mrecordlog.assert_records_eq(
+            &queue_id_01,
+            ..,
+            &[(0, "\0\0test-doc-010")],
+        );
+
+        let replica_shard_11 = follower_state_guard.shards.get(&queue_id_11).unwrap();
+        replica_shard_11.assert_is_replica();
+        replica_shard_11.assert_is_open();
+        replica_shard_11.assert_replication_position(Position::offset(1u64));
+
+        follower_state_guard.mrecordlog.assert_records_eq(
+            &queue_id_11,
+            ..,
+            &[(0, "\0\0test-doc-110"), (1, "\0\0test-doc-111")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingester_persist_replicate_with_quorum() {
+        let (leader_ctx, mut leader) = IngesterForTest::default()
+            .with_node_id("test-leader")
+            .with_replication()
+            .build()
+            .await;
+
+        let (follower_ctx_1, follower_1) = IngesterForTest::default()
+            .with_node_id("test-follower-1")
+            .with_ingester_pool(&leader_ctx.ingester_pool)
+            .with_replication()
+            .build()
+            .await;
+
+        let (follower_ctx_2, follower_2) = IngesterForTest::default()
+            .with_node_id("test-follower-2")
+            .with_ingester_pool(&leader_ctx.ingester_pool)
+            .with_replication()
+            .build()
+            .await;
+
+        leader_ctx.ingester_pool.insert(
+            follower_ctx_1.node_id.clone(),
+            IngesterServiceClient::new(follower_1.clone()),
+        );
+        leader_ctx.ingester_pool.insert(
+            follower_ctx_2.node_id.clone(),
+            IngesterServiceClient::new(follower_2.clone()),
+        );
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+
+        let init_shards_request = InitShardsRequest {
+            shards: vec![Shard {
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                shard_state: ShardState::Open as i32,
+                leader_id: leader_ctx.node_id.to_string(),
+                follower_id: Some(follower_ctx_1.node_id.to_string()),
+                additional_follower_ids: vec![follower_ctx_2.node_id.to_string()],
+                ..Default::default()
+            }],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
+        };
+        leader.init_shards(init_shards_request).await.unwrap();
+
+        let persist_request = PersistRequest {
+            leader_id: "test-leader".to_string(),
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![PersistSubrequest {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                dedup_id: None,
+            }],
+        };
+        let persist_response = leader.persist(persist_request.clone()).await.unwrap();
+        assert_eq!(persist_response.successes.len(), 1);
+        assert_eq!(persist_response.failures.len(), 0);
+
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+
+        for follower in [&follower_1, &follower_2] {
+            let follower_state_guard = follower.state.lock_fully().await.unwrap();
+            let replica_shard_01 = follower_state_guard.shards.get(&queue_id_01).unwrap();
+            replica_shard_01.assert_is_replica();
+            replica_shard_01.assert_replication_position(Position::offset(0u64));
+        }
+        // The second follower closes its replica: the records are still acknowledged by the leader
+        // and the first follower, which form a quorum, and the second follower is evicted.
+        follower_2
+            .state
+            .lock_fully()
+            .await
+            .unwrap()
+            .shards
+            .get_mut(&queue_id_01)
+            .unwrap()
+            .close();
+
+        let persist_response = leader.persist(persist_request.clone()).await.unwrap();
+        assert_eq!(persist_response.successes.len(), 1);
+        assert_eq!(persist_response.failures.len(), 0);
+
+        let persist_success = &persist_response.successes[0];
+        assert_eq!(
+            persist_success.replication_position_inclusive,
+            Some(Position::offset(1u64))
+        );
+        let leader_state_guard = leader.state.lock_fully().await.unwrap();
+        let primary_shard_01 = leader_state_guard.shards.get(&queue_id_01).unwrap();
+        primary_shard_01.assert_is_primary();
+        primary_shard_01.assert_is_open();
+        assert_eq!(
+            primary_shard_01.follower_ids(),
+            [follower_ctx_1.node_id.clone()]
+        );
+        drop(leader_state_guard);
+
+        // The first follower closes its replica too: the quorum can no longer be reached and the
+        // primary shard is closed.
+        follower_1
+            .state
+            .lock_fully()
+            .await
+            .unwrap()
+            .shards
+            .get_mut(&queue_id_01)
+            .unwrap()
+            .close();
+
+        let persist_response = leader.persist(persist_request).await.unwrap();
+        assert_eq!(persist_response.successes.len(), 0);
+        assert_eq!(persist_response.failures.len(), 1);
+
+        let persist_failure = &persist_response.failures[0];
+        assert_eq!(persist_failure.subrequest_id, 0);
+        assert_eq!(persist_failure.reason(), PersistFailureReason::ShardClosed);
+
+        let leader_state_guard = leader.state.lock_fully().await.unwrap();
+        let primary_shard_01 = leader_state_guard.shards.get(&queue_id_01).unwrap();
+        primary_shard_01.assert_is_closed();
+        primary_shard_01.assert_replication_position(Position::offset(1u64));
+        assert!(primary_shard_01.follower_ids().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ingester_promote_replica_shard() {
+        let (leader_ctx, mut leader) = IngesterForTest::default()
+            .with_node_id("test-leader")
+            .with_replication()
+            .build()
+            .await;
+
+        let (follower_ctx_1, mut follower_1) = IngesterForTest::default()
+            .with_node_id("test-follower-1")
+            .with_ingester_pool(&leader_ctx.ingester_pool)
+            .with_replication()
+            .build()
+            .await;
+
+        let (follower_ctx_2, follower_2) = IngesterForTest::default()
+            .with_node_id("test-follower-2")
+            .with_ingester_pool(&leader_ctx.ingester_pool)
+            .with_replication()
+            .build()
+            .await;
+
+        leader_ctx.ingester_pool.insert(
+            follower_ctx_1.node_id.clone(),
+            IngesterServiceClient::new(follower_1.clone()),
+        );
+        leader_ctx.ingester_pool.insert(
+            follower_ctx_2.node_id.clone(),
+            IngesterServiceClient::new(follower_2.clone()),
+        );
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+
+        let shard = Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: "test-source".to_string(),
+            shard_id: Some(ShardId::from(1)),
+            shard_state: ShardState::Open as i32,
+            leader_id: leader_ctx.node_id.to_string(),
+            follower_id: Some(follower_ctx_1.node_id.to_string()),
+            additional_follower_ids: vec![follower_ctx_2.node_id.to_string()],
+            ..Default::default()
+        };
+        let init_shards_request = InitShardsRequest {
+            shards: vec![shard.clone()],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
+        };
+        leader.init_shards(init_shards_request).await.unwrap();
+
+        let persist_request = PersistRequest {
+            leader_id: "test-leader".to_string(),
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![PersistSubrequest {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                dedup_id: None,
+            }],
+        };
+        leader.persist(persist_request).await.unwrap();
+
+        // The leader left the cluster: the control plane promotes the first follower.
+        let promoted_shard = Shard {
+            leader_id: follower_ctx_1.node_id.to_string(),
+            follower_id: Some(follower_ctx_2.node_id.to_string()),
+            additional_follower_ids: Vec::new(),
+            ..shard
+        };
+        let init_shards_request = InitShardsRequest {
+            shards: Vec::new(),
+            promoted_shards: vec![promoted_shard],
+            max_shard_throughputs: HashMap::new(),
+        };
+        follower_1.init_shards(init_shards_request).await.unwrap();
+
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+
+        let follower_state_guard = follower_1.state.lock_fully().await.unwrap();
+        let primary_shard_01 = follower_state_guard.shards.get(&queue_id_01).unwrap();
+        primary_shard_01.assert_is_primary();
+        primary_shard_01.assert_is_open();
+        assert_eq!(
+            primary_shard_01.follower_ids(),
+            [follower_ctx_2.node_id.clone()]
+        );
+        drop(follower_state_guard);
+
+        let persist_request = PersistRequest {
+            leader_id: "test-follower-1".to_string(),
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![PersistSubrequest {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                doc_batch: Some(DocBatchV2::for_test(["test-doc-011"])),
+                dedup_id: None,
+            }],
+        };
+        let persist_response = follower_1.persist(persist_request).await.unwrap();
+        assert_eq!(persist_response.successes.len(), 1);
+        assert_eq!(persist_response.failures.len(), 0);
+
+        let persist_success = &persist_response.successes[0];
+        assert_eq!(
+            persist_success.replication_position_inclusive,
+            Some(Position::offset(1u64))
+        );
+        let follower_state_guard = follower_2.state.lock_fully().await.unwrap();
+        let replica_shard_01 = follower_state_guard.shards.get(&queue_id_01).unwrap();
+        replica_shard_01.assert_is_replica();
+        replica_shard_01.assert_replication_position(Position::offset(1u64));
+
+        follower_state_guard.mrecordlog.assert_records_eq(
+            &queue_id_01,
+            ..,
+            &[(0, "\0\0test-doc-010"), (1, "\0\0test-doc-011")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingester_decommission_handoff_to_follower() {
+        let mut mock_control_plane = MockControlPlaneService::new();
+        mock_control_plane
+            .expect_advise_reset_shards()
+            .returning(|_| Ok(AdviseResetShardsResponse::default()));
+        mock_control_plane
+            .expect_handoff_shards()
+            .once()
+            .returning(|request| {
+                assert_eq!(request.former_leader_id, "test-leader");
+                assert_eq!(request.shards.len(), 1);
+
+                let shard = &request.shards[0];
+                assert_eq!(shard.shard_id(), ShardId::from(1));
+                assert_eq!(shard.leader_id, "test-follower");
+                assert!(shard.follower_id.is_none());
+                assert!(shard.is_closed());
+                Ok(HandoffShardsResponse::default())
+            });
+        let control_plane = ControlPlaneServiceClient::from(mock_control_plane);
+
+        let (leader_ctx, mut leader) = IngesterForTest::default()
+            .with_node_id("test-leader")
+            .with_control_plane(control_plane)
+            .with_replication()
+            .build()
+            .await;
+
+        let (follower_ctx, follower) = IngesterForTest::default()
+            .with_node_id("test-follower")
+            .with_ingester_pool(&leader_ctx.ingester_pool)
+            .with_replication()
+            .build()
+            .await;
+
+        leader_ctx.ingester_pool.insert(
+            follower_ctx.node_id.clone(),
+            IngesterServiceClient::new(follower.clone()),
+        );
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+
+        let shard = Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: "test-source".to_string(),
+            shard_id: Some(ShardId::from(1)),
+            shard_state: ShardState::Open as i32,
+            leader_id: leader_ctx.node_id.to_string(),
+            follower_id: Some(follower_ctx.node_id.to_string()),
+            ..Default::default()
+        };
+        let init_shards_request = InitShardsRequest {
+            shards: vec![shard],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
+        };
+        leader.init_shards(init_shards_request).await.unwrap();
+
+        let persist_request = PersistRequest {
+            leader_id: "test-leader".to_string(),
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![PersistSubrequest {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                dedup_id: None,
+            }],
+        };
+        leader.persist(persist_request).await.unwrap();
+
+        let decommission_request = DecommissionRequest {
+            mode: DecommissionMode::Handoff as i32,
+        };
+        leader.decommission(decommission_request).await.unwrap();
+
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+
+        let leader_state_guard = leader.state.lock_fully().await.unwrap();
+        assert_eq!(leader_state_guard.status(), IngesterStatus::Decommissioned);
+
+        let shard_01 = leader_state_guard.shards.get(&queue_id_01).unwrap();
+        shard_01.assert_is_replica();
+        shard_01.assert_is_closed();
+        drop(leader_state_guard);
+
+        let follower_state_guard = follower.state.lock_fully().await.unwrap();
+        let solo_shard_01 = follower_state_guard.shards.get(&queue_id_01).unwrap();
+        solo_shard_01.assert_is_solo();
+        solo_shard_01.assert_is_closed();
+        solo_shard_01.assert_replication_position(Position::offset(0u64));
+
+        follower_state_guard.mrecordlog.assert_records_eq(
+            &queue_id_01,
+            ..,
+            &[(0, "\0\0test-doc-010")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingester_decommission_handoff_copy_shard_tail() {
+        let mut mock_control_plane = MockControlPlaneService::new();
+        mock_control_plane
+            .expect_advise_reset_shards()
+            .returning(|_| Ok(AdviseResetShardsResponse::default()));
+        mock_control_plane
+            .expect_handoff_shards()
+            .once()
+            .returning(|request| {
+                assert_eq!(request.former_leader_id, "test-leader");
+                assert_eq!(request.shards.len(), 1);
+
+                let shard = &request.shards[0];
+                assert_eq!(shard.shard_id(), ShardId::from(1));
+                assert_eq!(shard.leader_id, "test-ingester");
+                assert!(shard.is_closed());
+                Ok(HandoffShardsResponse::default())
+            });
+        let control_plane = ControlPlaneServiceClient::from(mock_control_plane);
+
+        let (leader_ctx, mut leader) = IngesterForTest::default()
+            .with_node_id("test-leader")
+            .with_control_plane(control_plane)
+            .build()
+            .await;
+
+        let (ingester_ctx, ingester) = IngesterForTest::default()
+            .with_ingester_pool(&leader_ctx.ingester_pool)
+            .build()
+            .await;
+
+        leader_ctx.ingester_pool.insert(
+            ingester_ctx.node_id.clone(),
+            IngesterServiceClient::new(ingester.clone()),
+        );
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+
+        let shard = Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: "test-source".to_string(),
+            shard_id: Some(ShardId::from(1)),
+            shard_state: ShardState::Open as i32,
+            leader_id: leader_ctx.node_id.to_string(),
+            ..Default::default()
+        };
+        let init_shards_request = InitShardsRequest {
+            shards: vec![shard],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
+        };
+        leader.init_shards(init_shards_request).await.unwrap();
+
+        let persist_request = PersistRequest {
+            leader_id: "test-leader".to_string(),
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![PersistSubrequest {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                doc_batch: Some(DocBatchV2::for_test([
+                    "test-doc-010",
+                    "test-doc-011",
+                    "test-doc-012",
+                ])),
+                dedup_id: None,
+            }],
+        };
+        leader.persist(persist_request).await.unwrap();
+
+        let truncate_shards_request = TruncateShardsRequest {
+            ingester_id: leader_ctx.node_id.to_string(),
+            subrequests: vec![TruncateShardsSubrequest {
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                truncate_up_to_position_inclusive: Some(Position::offset(0u64)),
+            }],
+        };
+        leader
+            .truncate_shards(truncate_shards_request)
+            .await
+            .unwrap();
+
+        let decommission_request = DecommissionRequest {
+            mode: DecommissionMode::Handoff as i32,
+        };
+        leader.decommission(decommission_request).await.unwrap();
+
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+
+        let leader_state_guard = leader.state.lock_fully().await.unwrap();
+        assert_eq!(leader_state_guard.status(), IngesterStatus::Decommissioned);
+        leader_state_guard
+            .shards
+            .get(&queue_id_01)
+            .unwrap()
+            .assert_is_replica();
+        drop(leader_state_guard);
+
+        let state_guard = ingester.state.lock_fully().await.unwrap();
+        let solo_shard_01 = state_guard.shards.get(&queue_id_01).unwrap();
+        solo_shard_01.assert_is_solo();
+        solo_shard_01.assert_is_closed();
+        solo_shard_01.assert_replication_position(Position::offset(2u64));
+        solo_shard_01.assert_truncation_position(Position::offset(0u64));
 
-        tokio::spawn({
-            async move {
-                Server::builder()
-                    .add_service(follower_grpc_server)
-                    .serve(follower_socket_addr)
-                    .await
-                    .unwrap();
-            }
-        });
-        let follower_channel = Endpoint::from_static("http://127.0.0.1:7777").connect_lazy();
-        let follower_grpc_client = IngesterServiceClient::from_channel(
-            "127.0.0.1:7777".parse().unwrap(),
-            follower_channel,
-            MAX_GRPC_MESSAGE_SIZE,
+        state_guard.mrecordlog.assert_records_eq(
+            &queue_id_01,
+            ..,
+            &[(1, "\0\0test-doc-011"), (2, "\0\0test-doc-012")],
         );
+    }
+
+    #[tokio::test]
+    async fn test_ingester_decommission_handoff_copy_timeout() {
+        let mut mock_control_plane = MockControlPlaneService::new();
+        mock_control_plane
+            .expect_advise_reset_shards()
+            .returning(|_| Ok(AdviseResetShardsResponse::default()));
+        mock_control_plane.expect_handoff_shards().never();
+        let control_plane = ControlPlaneServiceClient::from(mock_control_plane);
 
+        let (leader_ctx, mut leader) = IngesterForTest::default()
+            .with_node_id("test-leader")
+            .with_control_plane(control_plane)
+            .build()
+            .await;
+
+        // The ingester never acknowledges the opening of the replication stream.
+        let mut mock_ingester = IngesterServiceClient::mock();
+        mock_ingester
+            .expect_open_replication_stream()
+            .once()
+            .returning(|_| Ok(ServiceStream::new(Box::pin(futures::stream::pending()))));
+        mock_ingester
+            .expect_truncate_shards()
+            .returning(|_| Ok(TruncateShardsResponse {}));
         leader_ctx
             .ingester_pool
-            .insert(follower_ctx.node_id.clone(), follower_grpc_client);
+            .insert("test-ingester".into(), mock_ingester.into());
 
         let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
-        let index_uid2: IndexUid = IndexUid::for_test("test-index", 1);
 
+        let shard = Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: "test-source".to_string(),
+            shard_id: Some(ShardId::from(1)),
+            shard_state: ShardState::Open as i32,
+            leader_id: leader_ctx.node_id.to_string(),
+            ..Default::default()
+        };
         let init_shards_request = InitShardsRequest {
-            shards: vec![
-                Shard {
-                    index_uid: Some(index_uid.clone()),
-                    source_id: "test-source".to_string(),
-                    shard_id: Some(ShardId::from(1)),
-                    shard_state: ShardState::Open as i32,
-                    leader_id: leader_ctx.node_id.to_string(),
-                    follower_id: Some(follower_ctx.node_id.to_string()),
-                    ..Default::default()
-                },
-                Shard {
-                    index_uid: Some(index_uid2.clone()),
-                    source_id: "test-source".to_string(),
-                    shard_id: Some(ShardId::from(1)),
-                    shard_state: ShardState::Open as i32,
-                    leader_id: leader_ctx.node_id.to_string(),
-                    follower_id: Some(follower_ctx.node_id.to_string()),
-                    ..Default::default()
-                },
-            ],
+            shards: vec![shard],
+            promoted_shards: Vec::new(),
+            max_shard_throughputs: HashMap::new(),
         };
         leader.init_shards(init_shards_request).await.unwrap();
 
         let persist_request = PersistRequest {
             leader_id: "test-leader".to_string(),
             commit_type: CommitTypeV2::Auto as i32,
-            subrequests: vec![
-                PersistSubrequest {
-                    subrequest_id: 0,
-                    index_uid: Some(index_uid.clone()),
-                    source_id: "test-source".to_string(),
-                    shard_id: Some(ShardId::from(1)),
-                    doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
-                },
-                PersistSubrequest {
-                    subrequest_id: 1,
-                    index_uid: Some(index_uid2.clone()),
-                    source_id: "test-source".to_string(),
-                    shard_id: Some(ShardId::from(1)),
-                    doc_batch: Some(DocBatchV2::for_test(["test-doc-110", "test-doc-111"])),
-                },
-            ],
+            subrequests: vec![PersistSubrequest {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                dedup_id: None,
+            }],
         };
-        let persist_response = leader.persist(persist_request).await.unwrap();
-        assert_eq!(persist_response.leader_id, "test-leader");
-        assert_eq!(persist_response.successes.len(), 2);
-        assert_eq!(persist_response.failures.len(), 0);
+        leader.persist(persist_request).await.unwrap();
 
-        let persist_success_0 = &persist_response.successes[0];
-        assert_eq!(persist_success_0.subrequest_id, 0);
-        assert_eq!(persist_success_0.index_uid(), &index_uid);
-        assert_eq!(persist_success_0.source_id, "test-source");
-        assert_eq!(persist_success_0.shard_id(), ShardId::from(1));
-        assert_eq!(
-            persist_success_0.replication_position_inclusive,
-            Some(Position::offset(0u64))
-        );
+        let decommission_request = DecommissionRequest {
+            mode: DecommissionMode::Handoff as i32,
+        };
+        let decommission_handle = tokio::spawn({
+            let mut leader = leader.clone();
+            async move { leader.decommission(decommission_request).await }
+        });
+        // The ingester state is not locked while the records of the shard are copied.
+        sleep(Duration::from_millis(100)).await;
 
-        let persist_success_1 = &persist_response.successes[1];
-        assert_eq!(persist_success_1.subrequest_id, 1);
-        assert_eq!(persist_success_1.index_uid(), &index_uid2);
-        assert_eq!(persist_success_1.source_id, "test-source");
-        assert_eq!(persist_success_1.shard_id(), ShardId::from(1));
-        assert_eq!(
-            persist_success_1.replication_position_inclusive,
-            Some(Position::offset(1u64))
-        );
+        let leader_state_guard = timeout(Duration::from_millis(100), leader.state.lock_fully())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(leader_state_guard.status(), IngesterStatus::Decommissioning);
+        drop(leader_state_guard);
 
-        let leader_state_guard = leader.state.lock_fully().await.unwrap();
-        assert_eq!(leader_state_guard.shards.len(), 2);
+        timeout(HANDOFF_TIMEOUT * 2, decommission_handle)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
 
+        // The shard is drained instead.
         let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
-        let primary_shard_01 = leader_state_guard.shards.get(&queue_id_01).unwrap();
-        primary_shard_01.assert_is_primary();
-        primary_shard_01.assert_is_open();
-        primary_shard_01.assert_replication_position(Position::offset(0u64));
 
-        leader_state_guard.mrecordlog.assert_records_eq(
-            &queue_id_01,
-            ..,
-            &[(0, "\0\0test-doc-010")],
-        );
-
-        let queue_id_11 = queue_id(&index_uid2, "test-source", &ShardId::from(1));
-        let primary_shard_11 = leader_state_guard.shards.get(&queue_id_11).unwrap();
-        primary_shard_11.assert_is_primary();
-        primary_shard_11.assert_is_open();
-        primary_shard_11.assert_replication_position(Position::offset(1u64));
-
-        leader_state_guard.mrecordlog.assert_records_eq(
-            &queue_id_11,
-            ..,
-            &[(0, "\0\0test-doc-110"), (1, "\0\0test-doc-111")],
-        );
-
-        let follower_state_guard = follower.state.lock_fully().await.unwrap();
-        assert_eq!(follower_state_guard.shards.len(), 2);
-
-        let replica_shard_01 = follower_state_guard.shards.get(&queue_id_01).unwrap();
-        replica_shard_01.assert_is_replica();
-        replica_shard_01.assert_is_open();
-        replica_shard_01.assert_replication_position(Position::offset(0u64));
-
-        follower_state_guard.mrecordlog.assert_records_eq(
-            &queue_id_01,
-            ..,
-            &[(0, "\0\0test-doc-010")],
-        );
-
-        let replica_shard_11 = follower_state_guard.shards.get(&queue_id_11).unwrap();
-        replica_shard_11.assert_is_replica();
-        replica_shard_11.assert_is_open();
-        replica_shard_11.assert_replication_position(Position::offset(1u64));
+        let leader_state_guard = leader.state.lock_fully().await.unwrap();
+        assert_eq!(leader_state_guard.status(), IngesterStatus::Decommissioning);
 
-        follower_state_guard.mrecordlog.assert_records_eq(
-            &queue_id_11,
-            ..,
-            &[(0, "\0\0test-doc-110"), (1, "\0\0test-doc-111")],
-        );
+        let solo_shard_01 = leader_state_guard.shards.get(&queue_id_01).unwrap();
+        solo_shard_01.assert_is_solo();
+        solo_shard_01.assert_is_closed();
     }
 
     #[tokio::test]
@@ -2094,6 +3828,7 @@ mod tests {
                 source_id: "test-source".to_string(),
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                dedup_id: None,
             }],
         };
         let persist_response = ingester.persist(persist_request).await.unwrap();
@@ -2145,6 +3880,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 primary_shard,
                 Instant::now(),
+                None,
             )
             .await
             .unwrap();
@@ -2160,6 +3896,7 @@ mod tests {
                 source_id: "test-source".to_string(),
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                dedup_id: None,
             }],
         };
         let persist_response = ingester.persist(persist_request).await.unwrap();
@@ -2213,6 +3950,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 primary_shard,
                 Instant::now(),
+                None,
             )
             .await
             .unwrap();
@@ -2228,6 +3966,7 @@ mod tests {
                 source_id: "test-source".to_string(),
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-010"])),
+                dedup_id: None,
             }],
         };
         let persist_response = ingester.persist(persist_request).await.unwrap();
@@ -2330,6 +4069,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard,
                 Instant::now(),
+                None,
             )
             .await
             .unwrap();
@@ -2442,6 +4182,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_01,
                 now,
+                None,
             )
             .await
             .unwrap();
@@ -2451,6 +4192,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_02,
                 now,
+                None,
             )
             .await
             .unwrap();
@@ -2638,6 +4380,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_01,
                 now,
+                None,
             )
             .await
             .unwrap();
@@ -2647,6 +4390,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_02,
                 now,
+                None,
             )
             .await
             .unwrap();
@@ -2709,6 +4453,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_17,
                 now,
+                None,
             )
             .await
             .unwrap();
@@ -2718,6 +4463,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_18,
                 now,
+                None,
             )
             .await
             .unwrap();
@@ -2767,6 +4513,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard,
                 Instant::now(),
+                None,
             )
             .await
             .unwrap();
@@ -2816,6 +4563,60 @@ mod tests {
         assert_eq!(fetch_eof.eof_position(), Position::Beginning.as_eof());
     }
 
+    #[tokio::test]
+    async fn test_ingester_get_replica_positions() {
+        let (_ingester_ctx, mut ingester) = IngesterForTest::default().build().await;
+
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+        let queue_id_02 = queue_id(&index_uid, "test-source", &ShardId::from(2));
+
+        let mut state_guard = ingester.state.lock_fully().await.unwrap();
+        state_guard.shards.insert(
+            queue_id_01,
+            IngesterShard::new_primary(
+                vec!["test-follower".into()],
+                ShardState::Open,
+                Position::offset(12u64),
+                Position::Beginning,
+                Instant::now(),
+            ),
+        );
+        state_guard.shards.insert(
+            queue_id_02,
+            IngesterShard::new_replica(
+                "test-leader".into(),
+                ShardState::Open,
+                Position::offset(7u64),
+                Position::Beginning,
+                Instant::now(),
+            ),
+        );
+        drop(state_guard);
+
+        let get_replica_positions_request = GetReplicaPositionsRequest {
+            shards: vec![ShardIds {
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_ids: vec![ShardId::from(1), ShardId::from(2), ShardId::from(1337)],
+            }],
+        };
+        let response = ingester
+            .get_replica_positions(get_replica_positions_request)
+            .await
+            .unwrap();
+        assert_eq!(response.replica_positions.len(), 1);
+
+        let replica_position = &response.replica_positions[0];
+        assert_eq!(replica_position.index_uid(), &index_uid);
+        assert_eq!(replica_position.source_id, "test-source");
+        assert_eq!(replica_position.shard_id(), ShardId::from(2));
+        assert_eq!(
+            replica_position.replication_position_inclusive(),
+            Position::offset(7u64)
+        );
+    }
+
     #[tokio::test]
     async fn test_ingester_open_observation_stream() {
         let (ingester_ctx, mut ingester) = IngesterForTest::default().build().await;
@@ -2876,6 +4677,24 @@ mod tests {
 
         ingester.check_decommissioning_status(&mut state_guard);
         assert_eq!(state_guard.status(), IngesterStatus::Decommissioned);
+        // In handoff mode, replica shards do not hold up the decommission.
+        state_guard.set_status(IngesterStatus::Decommissioning);
+        state_guard.decommission_mode = DecommissionMode::Handoff;
+
+        let queue_id_02 = queue_id(&index_uid, "test-source", &ShardId::from(2));
+
+        state_guard.shards.insert(
+            queue_id_02.clone(),
+            IngesterShard::new_replica(
+                "test-new-leader".into(),
+                ShardState::Closed,
+                Position::offset(12u64),
+                Position::Beginning,
+                Instant::now(),
+            ),
+        );
+        ingester.check_decommissioning_status(&mut state_guard);
+        assert_eq!(state_guard.status(), IngesterStatus::Decommissioned);
     }
 
     #[tokio::test]
@@ -2912,6 +4731,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_01,
                 now,
+                None,
             )
             .await
             .unwrap();
@@ -2921,6 +4741,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_02,
                 now,
+                None,
             )
             .await
             .unwrap();
@@ -2957,6 +4778,7 @@ mod tests {
                 (ShardId::from(2), Position::eof(0u64)),
                 (ShardId::from(1337), Position::offset(1337u64)),
             ],
+            published_split_ids: Vec::new(),
         };
         event_broker.publish(shard_position_update.clone());
 
@@ -3015,6 +4837,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_01,
                 now - idle_shard_timeout,
+                None,
             )
             .await
             .unwrap();
@@ -3024,6 +4847,7 @@ mod tests {
                 &mut state_guard.mrecordlog,
                 shard_02,
                 now,
+                None,
             )
             .await
             .unwrap();
@@ -3044,4 +4868,92 @@ mod tests {
             .assert_is_open();
         drop(state_guard);
     }
+
+    #[test]
+    fn test_scale_rate_limiter_settings() {
+        let rate_limiter_settings = RateLimiterSettings::default();
+
+        let scaled_settings = scale_rate_limiter_settings(rate_limiter_settings, None);
+        assert_eq!(
+            scaled_settings.rate_limit.work(),
+            rate_limiter_settings.rate_limit.work()
+        );
+
+        let scaled_settings =
+            scale_rate_limiter_settings(rate_limiter_settings, Some(ByteSize::mb(10).as_u64()));
+        assert_eq!(
+            scaled_settings.rate_limit.work(),
+            rate_limiter_settings.rate_limit.work()
+        );
+
+        let scaled_settings =
+            scale_rate_limiter_settings(rate_limiter_settings, Some(ByteSize::kb(500).as_u64()));
+        assert_eq!(scaled_settings.rate_limit.work(), 500_000);
+        assert_eq!(scaled_settings.rate_limit.period(), Duration::from_secs(1));
+        assert_eq!(
+            scaled_settings.burst_limit,
+            rate_limiter_settings.burst_limit
+        );
+
+        // The rate limiter refills at least one permit per refill period.
+        let scaled_settings = scale_rate_limiter_settings(rate_limiter_settings, Some(1));
+        assert_eq!(scaled_settings.rate_limit.work(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_ingester_prepare_wal_doc_batches() {
+        let (_ingester_ctx, ingester) = IngesterForTest::default()
+            .with_wal_compression(3)
+            .build()
+            .await;
+
+        let num_bytes = SPAWN_BLOCKING_COMPRESSION_NUM_BYTES + 1;
+        let large_doc_batch = DocBatchV2 {
+            doc_buffer: Bytes::from(vec![b'x'; num_bytes]),
+            doc_lengths: vec![num_bytes as u32],
+        };
+        let subrequests = vec![
+            PersistSubrequest {
+                subrequest_id: 0,
+                doc_batch: Some(large_doc_batch),
+                ..Default::default()
+            },
+            PersistSubrequest {
+                subrequest_id: 1,
+                doc_batch: Some(DocBatchV2::default()),
+                ..Default::default()
+            },
+            PersistSubrequest {
+                subrequest_id: 2,
+                doc_batch: None,
+                ..Default::default()
+            },
+        ];
+        let prepared_doc_batches = ingester.prepare_wal_doc_batches(subrequests).await;
+        assert_eq!(prepared_doc_batches.len(), 3);
+
+        let (subrequest, prepared_doc_batch_opt) = &prepared_doc_batches[0];
+        assert_eq!(subrequest.subrequest_id, 0);
+        assert!(subrequest.doc_batch.is_none());
+
+        let prepared_doc_batch = prepared_doc_batch_opt.as_ref().unwrap();
+        assert_eq!(prepared_doc_batch.num_bytes, num_bytes as u64);
+        assert!(prepared_doc_batch.rate_limited_capacity.as_u64() >= num_bytes as u64);
+
+        let WalDocBatch::Compressed(compressed_doc_batch) = &prepared_doc_batch.wal_doc_batch
+        else {
+            panic!("expected compressed doc batch");
+        };
+        assert_eq!(compressed_doc_batch.num_docs, 1);
+        assert_eq!(compressed_doc_batch.num_bytes, num_bytes as u64);
+        assert!(compressed_doc_batch.payload.len() < num_bytes);
+
+        let (subrequest, prepared_doc_batch_opt) = &prepared_doc_batches[1];
+        assert_eq!(subrequest.subrequest_id, 1);
+        assert!(prepared_doc_batch_opt.is_none());
+
+        let (subrequest, prepared_doc_batch_opt) = &prepared_doc_batches[2];
+        assert_eq!(subrequest.subrequest_id, 2);
+        assert!(prepared_doc_batch_opt.is_none());
+    }
 }
@@ -0,0 +1,1129 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Kafka-compatible produce endpoint. Ingesters can optionally accept the Kafka Produce API on a
+//! dedicated port so that Kafka producers can write documents directly into the WAL. Topics map to
+//! indexes and partitions map to the open shards of the index's ingest v2 source, sorted by shard
+//! ID.
+
+mod protocol;
+mod records;
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use bytesize::ByteSize;
+use quickwit_cluster::Cluster;
+use quickwit_common::rate_limited_warn;
+use quickwit_common::rendezvous_hasher::node_affinity;
+use quickwit_config::INGEST_V2_SOURCE_ID;
+use quickwit_proto::control_plane::{
+    ControlPlaneService, ControlPlaneServiceClient, GetOrCreateOpenShardsFailureReason,
+    GetOrCreateOpenShardsRequest, GetOrCreateOpenShardsSubrequest,
+};
+use quickwit_proto::ingest::ingester::{
+    IngesterService, IngesterServiceClient, PersistFailureReason, PersistRequest, PersistSubrequest,
+};
+use quickwit_proto::ingest::{CommitTypeV2, IngestV2Error, Shard};
+use quickwit_proto::types::{IndexId, IndexUid, NodeId};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tokio::time::timeout;
+use tracing::{debug, error, info, warn};
+
+use self::protocol::{
+    encode_api_versions_response, is_supported_api_version, Decoder, ErrorCode, KafkaProtocolError,
+    KafkaProtocolResult, MetadataBroker, MetadataPartition, MetadataRequest, MetadataResponse,
+    MetadataTopic, ProducePartitionData, ProducePartitionResponse, ProduceRequest, ProduceResponse,
+    ProduceTopicResponse, RequestHeader, API_KEY_API_VERSIONS, API_KEY_METADATA, API_KEY_PRODUCE,
+};
+use self::records::decode_record_values;
+use super::ingester::PERSIST_REQUEST_TIMEOUT;
+use super::DocBatchV2Builder;
+
+/// Chitchat key under which ingesters advertise the address of their Kafka produce endpoint.
+const KAFKA_ADVERTISE_ADDR_KEY: &str = "ingester.kafka_advertise_addr";
+
+/// Maximum number of Kafka client connections served concurrently. Connections accepted beyond
+/// this limit are closed right away.
+const MAX_CONCURRENT_CONNECTIONS: usize = 1024;
+
+/// Duration after which connections on which no request starts are closed, like Kafka brokers do
+/// with their default `connections.max.idle.ms` setting.
+const CONNECTION_IDLE_TIMEOUT: Duration = if cfg!(any(test, feature = "testsuite")) {
+    Duration::from_secs(1)
+} else {
+    Duration::from_secs(10 * 60)
+};
+
+/// Duration after which connections whose client stops sending the body of a request midway are
+/// closed.
+const REQUEST_READ_TIMEOUT: Duration = if cfg!(any(test, feature = "testsuite")) {
+    Duration::from_millis(100)
+} else {
+    Duration::from_secs(30)
+};
+
+/// The partitions of a topic: the open shards of the index's ingest v2 source sorted by shard ID.
+/// The partition index of a shard is its position in the table.
+#[derive(Debug)]
+struct PartitionTable {
+    index_uid: IndexUid,
+    shards: Vec<Shard>,
+}
+
+impl PartitionTable {
+    fn new(index_uid: IndexUid, mut shards: Vec<Shard>) -> Self {
+        shards.sort_unstable_by(|left, right| left.shard_id.cmp(&right.shard_id));
+        Self { index_uid, shards }
+    }
+
+    fn shard(&self, partition_index: i32) -> Option<&Shard> {
+        usize::try_from(partition_index)
+            .ok()
+            .and_then(|partition_index| self.shards.get(partition_index))
+    }
+}
+
+/// Serves the subset of the Kafka protocol required by producers: the ApiVersions, Metadata, and
+/// Produce APIs.
+#[derive(Clone)]
+struct KafkaProduceService {
+    self_node_id: NodeId,
+    cluster: Cluster,
+    control_plane: ControlPlaneServiceClient,
+    ingester: IngesterServiceClient,
+    max_request_size: ByteSize,
+    // When the WAL is compressed, each doc batch is written as a single record, so the WAL
+    // positions of the individual records are unknown.
+    wal_compression_enabled: bool,
+    partition_tables: Arc<Mutex<HashMap<IndexId, Arc<PartitionTable>>>>,
+}
+
+/// Starts accepting Kafka clients on `listen_addr` and advertises `advertise_addr` to the other
+/// nodes of the cluster, which return it in their metadata responses. Produce requests are
+/// persisted by the local `ingester`, whose WAL is compressed if `wal_compression_enabled` is set.
+pub async fn start_kafka_produce_server(
+    listen_addr: SocketAddr,
+    advertise_addr: SocketAddr,
+    cluster: Cluster,
+    control_plane: ControlPlaneServiceClient,
+    ingester: IngesterServiceClient,
+    max_request_size: ByteSize,
+    wal_compression_enabled: bool,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen_addr).await?;
+    info!("starting Kafka produce server listening on {listen_addr}");
+
+    cluster
+        .set_self_key_value(KAFKA_ADVERTISE_ADDR_KEY, advertise_addr)
+        .await;
+    let service = KafkaProduceService {
+        self_node_id: cluster.self_node_id().into(),
+        cluster,
+        control_plane,
+        ingester,
+        max_request_size,
+        wal_compression_enabled,
+        partition_tables: Arc::default(),
+    };
+    let connection_semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_CONNECTIONS));
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer_addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    error!(%error, "failed to accept Kafka client connection");
+                    continue;
+                }
+            };
+            let Ok(connection_permit) = connection_semaphore.clone().try_acquire_owned() else {
+                rate_limited_warn!(
+                    limit_per_min = 10,
+                    "closing connection with Kafka client `{peer_addr}`: too many connections"
+                );
+                continue;
+            };
+            let service_clone = service.clone();
+
+            tokio::spawn(async move {
+                if let Err(error) = service_clone.handle_connection(stream).await {
+                    debug!(%error, "closing connection with Kafka client `{peer_addr}`");
+                }
+                drop(connection_permit);
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Derives a stable Kafka broker ID from a node ID.
+fn broker_id(node_id: &str) -> i32 {
+    (node_affinity(node_id, &KAFKA_ADVERTISE_ADDR_KEY) >> 33) as i32
+}
+
+impl KafkaProduceService {
+    /// Reads the requests of a client one at a time and writes back the responses in order, as
+    /// expected by Kafka clients. The connection is closed if the client stays idle for
+    /// [`CONNECTION_IDLE_TIMEOUT`] or takes more than [`REQUEST_READ_TIMEOUT`] to send the body of
+    /// a request.
+    async fn handle_connection(&self, mut stream: TcpStream) -> io::Result<()> {
+        loop {
+            let request_size = match timeout(CONNECTION_IDLE_TIMEOUT, stream.read_i32()).await {
+                Ok(Ok(request_size)) => request_size,
+                Ok(Err(error)) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Ok(Err(error)) => return Err(error),
+                Err(_) => {
+                    let message = "connection idle for too long";
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, message));
+                }
+            };
+            if request_size < 0 || request_size as u64 > self.max_request_size.as_u64() {
+                let message = format!(
+                    "request size `{request_size}` exceeds limit `{}`",
+                    self.max_request_size
+                );
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+            // The request is read as it arrives instead of allocating its announced size upfront.
+            let mut request = Vec::new();
+            let read_future = (&mut stream)
+                .take(request_size as u64)
+                .read_to_end(&mut request);
+
+            match timeout(REQUEST_READ_TIMEOUT, read_future).await {
+                Ok(Ok(num_bytes)) if num_bytes == request_size as usize => {}
+                Ok(Ok(_)) => {
+                    let message = "connection closed in the middle of a request";
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, message));
+                }
+                Ok(Err(error)) => return Err(error),
+                Err(_) => {
+                    let message = format!(
+                        "request not received within {} seconds",
+                        REQUEST_READ_TIMEOUT.as_secs_f32()
+                    );
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, message));
+                }
+            }
+
+            let response_opt = self
+                .handle_request(Bytes::from(request))
+                .await
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+            if let Some(response) = response_opt {
+                stream.write_all(&response).await?;
+            }
+        }
+    }
+
+    /// Handles a request and returns the response framed with its size, or `None` if the client
+    /// does not expect a response.
+    async fn handle_request(&self, request: Bytes) -> KafkaProtocolResult<Option<BytesMut>> {
+        let mut decoder = Decoder::new(request);
+        let header = RequestHeader::decode(&mut decoder)?;
+
+        let mut response = BytesMut::new();
+        // The size of the response is filled in once the body is encoded.
+        response.put_i32(0);
+        response.put_i32(header.correlation_id);
+
+        match header.api_key {
+            API_KEY_API_VERSIONS => {
+                if is_supported_api_version(header.api_key, header.api_version) {
+                    encode_api_versions_response(
+                        header.api_version,
+                        ErrorCode::None,
+                        &mut response,
+                    );
+                } else {
+                    encode_api_versions_response(0, ErrorCode::UnsupportedVersion, &mut response);
+                }
+            }
+            API_KEY_METADATA if is_supported_api_version(header.api_key, header.api_version) => {
+                let metadata_request = MetadataRequest::decode(header.api_version, &mut decoder)?;
+                let metadata_response = self.metadata(metadata_request).await;
+                metadata_response.encode(header.api_version, &mut response);
+            }
+            API_KEY_PRODUCE if is_supported_api_version(header.api_key, header.api_version) => {
+                let produce_request = ProduceRequest::decode(&mut decoder)?;
+                let acks = produce_request.acks;
+                let produce_response = self.produce(produce_request).await;
+
+                if acks == 0 {
+                    return Ok(None);
+                }
+                produce_response.encode(header.api_version, &mut response);
+            }
+            _ => {
+                return Err(KafkaProtocolError::UnsupportedVersion {
+                    api_key: header.api_key,
+                    api_version: header.api_version,
+                });
+            }
+        }
+        let response_size = (response.len() - 4) as i32;
+        response[..4].copy_from_slice(&response_size.to_be_bytes());
+        Ok(Some(response))
+    }
+
+    /// Returns the ingesters exposing a Kafka produce endpoint, keyed by node ID.
+    async fn brokers(&self) -> HashMap<String, MetadataBroker> {
+        let chitchat = self.cluster.chitchat().await;
+        let chitchat_lock = chitchat.lock().await;
+        let mut brokers = HashMap::new();
+
+        for chitchat_id in chitchat_lock.live_nodes() {
+            let Some(advertise_addr) = chitchat_lock
+                .node_state(chitchat_id)
+                .and_then(|node_state| node_state.get(KAFKA_ADVERTISE_ADDR_KEY))
+                .and_then(|advertise_addr| advertise_addr.parse::<SocketAddr>().ok())
+            else {
+                continue;
+            };
+            let broker = MetadataBroker {
+                node_id: broker_id(&chitchat_id.node_id),
+                host: advertise_addr.ip().to_string(),
+                port: advertise_addr.port() as i32,
+            };
+            brokers.insert(chitchat_id.node_id.clone(), broker);
+        }
+        brokers
+    }
+
+    async fn metadata(&self, metadata_request: MetadataRequest) -> MetadataResponse {
+        let brokers = self.brokers().await;
+        // Listing all the indexes is not supported, so producers must request the topics they
+        // write to.
+        let index_ids = metadata_request.topics.unwrap_or_default();
+        let mut partition_tables = self.refresh_partition_tables(&index_ids).await;
+        let mut topics = Vec::with_capacity(index_ids.len());
+
+        for index_id in index_ids {
+            let partition_table = match partition_tables.remove(&index_id) {
+                Some(Ok(partition_table)) => partition_table,
+                Some(Err(error_code)) => {
+                    let topic = MetadataTopic {
+                        error_code,
+                        name: index_id,
+                        partitions: Vec::new(),
+                    };
+                    topics.push(topic);
+                    continue;
+                }
+                // The index is requested twice.
+                None => continue,
+            };
+            let partitions = partition_table
+                .shards
+                .iter()
+                .enumerate()
+                .map(|(partition_index, shard)| {
+                    let Some(leader) = brokers.get(&shard.leader_id) else {
+                        return MetadataPartition {
+                            error_code: ErrorCode::LeaderNotAvailable,
+                            partition_index: partition_index as i32,
+                            leader_id: -1,
+                            replica_nodes: Vec::new(),
+                        };
+                    };
+                    let replica_nodes = Some(leader.node_id)
+                        .into_iter()
+                        .chain(
+                            shard
+                                .follower_ids()
+                                .map(|follower_id| broker_id(follower_id.as_str())),
+                        )
+                        .collect();
+                    MetadataPartition {
+                        error_code: ErrorCode::None,
+                        partition_index: partition_index as i32,
+                        leader_id: leader.node_id,
+                        replica_nodes,
+                    }
+                })
+                .collect();
+            let topic = MetadataTopic {
+                error_code: ErrorCode::None,
+                name: index_id,
+                partitions,
+            };
+            topics.push(topic);
+        }
+        MetadataResponse {
+            brokers: brokers.into_values().collect(),
+            cluster_id: Some(self.cluster.cluster_id().to_string()),
+            controller_id: broker_id(self.self_node_id.as_str()),
+            topics,
+        }
+    }
+
+    /// Fetches the open shards of the ingest v2 source of the indexes from the control plane and
+    /// updates the partition tables.
+    async fn refresh_partition_tables(
+        &self,
+        index_ids: &[IndexId],
+    ) -> HashMap<IndexId, Result<Arc<PartitionTable>, ErrorCode>> {
+        let mut partition_tables = HashMap::with_capacity(index_ids.len());
+
+        if index_ids.is_empty() {
+            return partition_tables;
+        }
+        let subrequests = index_ids
+            .iter()
+            .enumerate()
+            .map(
+                |(subrequest_id, index_id)| GetOrCreateOpenShardsSubrequest {
+                    subrequest_id: subrequest_id as u32,
+                    index_id: index_id.clone(),
+                    source_id: INGEST_V2_SOURCE_ID.to_string(),
+                },
+            )
+            .collect();
+        let request = GetOrCreateOpenShardsRequest {
+            subrequests,
+            closed_shards: Vec::new(),
+            unavailable_leaders: Vec::new(),
+        };
+        let response = match self
+            .control_plane
+            .clone()
+            .get_or_create_open_shards(request)
+            .await
+        {
+            Ok(response) => response,
+            Err(error) => {
+                warn!("failed to get open shards from control plane: {error}");
+
+                for index_id in index_ids {
+                    partition_tables.insert(index_id.clone(), Err(ErrorCode::LeaderNotAvailable));
+                }
+                return partition_tables;
+            }
+        };
+        let mut partition_tables_guard = self
+            .partition_tables
+            .lock()
+            .expect("lock should not be poisoned");
+
+        for success in response.successes {
+            let index_uid = success.index_uid().clone();
+            let index_id = index_uid.index_id.clone();
+            let partition_table = Arc::new(PartitionTable::new(index_uid, success.open_shards));
+            partition_tables_guard.insert(index_id.clone(), partition_table.clone());
+            partition_tables.insert(index_id, Ok(partition_table));
+        }
+        for failure in response.failures {
+            let error_code = match failure.reason() {
+                GetOrCreateOpenShardsFailureReason::IndexNotFound
+                | GetOrCreateOpenShardsFailureReason::SourceNotFound => {
+                    ErrorCode::UnknownTopicOrPartition
+                }
+                GetOrCreateOpenShardsFailureReason::QuotaExceeded => {
+                    ErrorCode::ThrottlingQuotaExceeded
+                }
+                _ => ErrorCode::LeaderNotAvailable,
+            };
+            partition_tables_guard.remove(&failure.index_id);
+            partition_tables.insert(failure.index_id, Err(error_code));
+        }
+        partition_tables
+    }
+
+    /// Returns the partition tables of the indexes, fetching the missing ones from the control
+    /// plane.
+    async fn partition_tables(
+        &self,
+        index_ids: &[&str],
+    ) -> HashMap<IndexId, Result<Arc<PartitionTable>, ErrorCode>> {
+        let mut partition_tables = HashMap::with_capacity(index_ids.len());
+        let mut missing_index_ids = Vec::new();
+
+        {
+            let partition_tables_guard = self
+                .partition_tables
+                .lock()
+                .expect("lock should not be poisoned");
+
+            for index_id in index_ids {
+                if let Some(partition_table) = partition_tables_guard.get(*index_id) {
+                    partition_tables.insert(index_id.to_string(), Ok(partition_table.clone()));
+                } else if !missing_index_ids.iter().any(|missing| missing == index_id) {
+                    missing_index_ids.push(index_id.to_string());
+                }
+            }
+        }
+        partition_tables.extend(self.refresh_partition_tables(&missing_index_ids).await);
+        partition_tables
+    }
+
+    fn invalidate_partition_table(&self, index_id: &str) {
+        self.partition_tables
+            .lock()
+            .expect("lock should not be poisoned")
+            .remove(index_id);
+    }
+
+    /// Persists the records of all the partitions of the request with a single persist request
+    /// to the local ingester. Partitions whose shard is led by another ingester are rejected so
+    /// that producers refresh their metadata.
+    async fn produce(&self, produce_request: ProduceRequest) -> ProduceResponse {
+        let index_ids: Vec<&str> = produce_request
+            .topics
+            .iter()
+            .map(|topic| topic.name.as_str())
+            .collect();
+        let partition_tables = self.partition_tables(&index_ids).await;
+
+        let mut topics = Vec::with_capacity(produce_request.topics.len());
+        // Persist subrequest IDs are the positions of the (topic, partition, number of docs)
+        // tuples in this vector.
+        let mut pending_partitions: Vec<(usize, usize, i64)> = Vec::new();
+        let mut persist_subrequests = Vec::new();
+        // Decompressed records are bounded across all the partitions of the request.
+        let mut decompression_budget = self.max_request_size.as_u64() as usize;
+
+        for (topic_index, topic_data) in produce_request.topics.into_iter().enumerate() {
+            let partition_table_result = partition_tables
+                .get(&topic_data.name)
+                .cloned()
+                .unwrap_or(Err(ErrorCode::UnknownServerError));
+            let mut partitions = Vec::with_capacity(topic_data.partitions.len());
+
+            for (partition_position, partition_data) in
+                topic_data.partitions.into_iter().enumerate()
+            {
+                let partition_index = partition_data.partition_index;
+                let error_code = match self
+                    .prepare_persist_subrequest(
+                        partition_table_result.as_deref(),
+                        partition_data,
+                        persist_subrequests.len() as u32,
+                        &mut decompression_budget,
+                    )
+                    .await
+                {
+                    Ok(Some((persist_subrequest, num_docs))) => {
+                        persist_subrequests.push(persist_subrequest);
+                        pending_partitions.push((topic_index, partition_position, num_docs));
+                        ErrorCode::None
+                    }
+                    Ok(None) => ErrorCode::None,
+                    Err(error_code) => error_code,
+                };
+                let partition = ProducePartitionResponse {
+                    partition_index,
+                    error_code,
+                    base_offset: -1,
+                };
+                partitions.push(partition);
+            }
+            let topic = ProduceTopicResponse {
+                name: topic_data.name,
+                partitions,
+            };
+            topics.push(topic);
+        }
+        let mut produce_response = ProduceResponse { topics };
+
+        if persist_subrequests.is_empty() {
+            return produce_response;
+        }
+        let persist_request = PersistRequest {
+            leader_id: self.self_node_id.clone().into(),
+            subrequests: persist_subrequests,
+            commit_type: CommitTypeV2::Auto as i32,
+        };
+        let persist_result = tokio::time::timeout(
+            PERSIST_REQUEST_TIMEOUT,
+            self.ingester.clone().persist(persist_request),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            let message = format!(
+                "persist request timed out after {} seconds",
+                PERSIST_REQUEST_TIMEOUT.as_secs()
+            );
+            Err(IngestV2Error::Timeout(message))
+        });
+        let persist_response = match persist_result {
+            Ok(persist_response) => persist_response,
+            Err(error) => {
+                warn!("failed to persist Kafka records: {error}");
+                let error_code = match error {
+                    IngestV2Error::Timeout(_) | IngestV2Error::TooManyRequests => {
+                        ErrorCode::RequestTimedOut
+                    }
+                    _ => ErrorCode::UnknownServerError,
+                };
+                for (topic_index, partition_position, _) in pending_partitions {
+                    produce_response.topics[topic_index].partitions[partition_position]
+                        .error_code = error_code;
+                }
+                return produce_response;
+            }
+        };
+        // The base offsets of the partitions are left unknown (-1) when the WAL is compressed.
+        if !self.wal_compression_enabled {
+            for persist_success in persist_response.successes {
+                let (topic_index, partition_position, num_docs) =
+                    pending_partitions[persist_success.subrequest_id as usize];
+                let base_offset = persist_success
+                    .replication_position_inclusive
+                    .and_then(|position| position.as_i64())
+                    .map(|last_offset| last_offset - num_docs + 1)
+                    .unwrap_or(-1);
+                produce_response.topics[topic_index].partitions[partition_position].base_offset =
+                    base_offset;
+            }
+        }
+        for persist_failure in persist_response.failures {
+            let (topic_index, partition_position, _) =
+                pending_partitions[persist_failure.subrequest_id as usize];
+            let error_code = match persist_failure.reason() {
+                PersistFailureReason::ShardNotFound | PersistFailureReason::ShardClosed => {
+                    if let Some(index_uid) = &persist_failure.index_uid {
+                        self.invalidate_partition_table(&index_uid.index_id);
+                    }
+                    ErrorCode::NotLeaderOrFollower
+                }
+                PersistFailureReason::ResourceExhausted => ErrorCode::KafkaStorageError,
+                PersistFailureReason::RateLimited => ErrorCode::RequestTimedOut,
+                PersistFailureReason::Unspecified => ErrorCode::UnknownServerError,
+            };
+            produce_response.topics[topic_index].partitions[partition_position].error_code =
+                error_code;
+        }
+        produce_response
+    }
+
+    /// Maps a partition to its shard and decodes its records, decrementing `decompression_budget`
+    /// by the number of decompressed bytes. Returns `None` if the partition holds no documents to
+    /// persist.
+    async fn prepare_persist_subrequest(
+        &self,
+        partition_table_result: Result<&PartitionTable, &ErrorCode>,
+        partition_data: ProducePartitionData,
+        subrequest_id: u32,
+        decompression_budget: &mut usize,
+    ) -> Result<Option<(PersistSubrequest, i64)>, ErrorCode> {
+        let partition_table = partition_table_result.map_err(|error_code| *error_code)?;
+        let shard = partition_table
+            .shard(partition_data.partition_index)
+            .ok_or(ErrorCode::UnknownTopicOrPartition)?;
+
+        if self.self_node_id != shard.leader_id {
+            return Err(ErrorCode::NotLeaderOrFollower);
+        }
+        let Some(records) = partition_data.records else {
+            return Ok(None);
+        };
+        let mut remaining_decompression_budget = *decompression_budget;
+        // Decompressing records is CPU-bound.
+        let (values, remaining_decompression_budget) = tokio::task::spawn_blocking(move || {
+            decode_record_values(records, &mut remaining_decompression_budget)
+                .map(|values| (values, remaining_decompression_budget))
+        })
+        .await
+        .map_err(|_| ErrorCode::UnknownServerError)?
+        .map_err(|error| {
+            warn!(%error, "failed to decode Kafka records");
+
+            match error {
+                KafkaProtocolError::UnsupportedCompression(_) => {
+                    ErrorCode::UnsupportedCompressionType
+                }
+                _ => ErrorCode::CorruptMessage,
+            }
+        })?;
+        *decompression_budget = remaining_decompression_budget;
+
+        let mut doc_batch_builder = DocBatchV2Builder::default();
+
+        for value in &values {
+            doc_batch_builder.add_doc(value);
+        }
+        let Some(doc_batch) = doc_batch_builder.build() else {
+            return Ok(None);
+        };
+        let persist_subrequest = PersistSubrequest {
+            subrequest_id,
+            index_uid: Some(partition_table.index_uid.clone()),
+            source_id: shard.source_id.clone(),
+            shard_id: shard.shard_id.clone(),
+            doc_batch: Some(doc_batch),
+            dedup_id: None,
+        };
+        Ok(Some((persist_subrequest, values.len() as i64)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_cluster::{create_cluster_for_test, ChannelTransport};
+    use quickwit_proto::control_plane::{
+        GetOrCreateOpenShardsFailure, GetOrCreateOpenShardsResponse, GetOrCreateOpenShardsSuccess,
+    };
+    use quickwit_proto::ingest::ingester::{PersistFailure, PersistResponse, PersistSuccess};
+    use quickwit_proto::ingest::{DocBatchV2, ShardState};
+    use quickwit_proto::types::{Position, ShardId};
+
+    use super::protocol::ProduceTopicData;
+    use super::records::test_utils::encode_record_batch;
+    use super::*;
+
+    async fn setup_kafka_produce_service(
+        control_plane: ControlPlaneServiceClient,
+        ingester: IngesterServiceClient,
+    ) -> KafkaProduceService {
+        let transport = ChannelTransport::default();
+        let cluster = create_cluster_for_test(Vec::new(), &["indexer"], &transport, true)
+            .await
+            .unwrap();
+        cluster
+            .set_self_key_value(KAFKA_ADVERTISE_ADDR_KEY, "127.0.0.1:9092")
+            .await;
+        KafkaProduceService {
+            self_node_id: cluster.self_node_id().into(),
+            cluster,
+            control_plane,
+            ingester,
+            max_request_size: ByteSize::mib(1),
+            wal_compression_enabled: false,
+            partition_tables: Arc::default(),
+        }
+    }
+
+    fn get_or_create_open_shards_response(
+        self_node_id: &str,
+        request: &GetOrCreateOpenShardsRequest,
+    ) -> GetOrCreateOpenShardsResponse {
+        let index_uid = IndexUid::for_test("test-index", 0);
+        let mut response = GetOrCreateOpenShardsResponse::default();
+
+        for subrequest in &request.subrequests {
+            assert_eq!(subrequest.source_id, INGEST_V2_SOURCE_ID);
+
+            if subrequest.index_id == "test-index" {
+                let success = GetOrCreateOpenShardsSuccess {
+                    subrequest_id: subrequest.subrequest_id,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: INGEST_V2_SOURCE_ID.to_string(),
+                    open_shards: vec![
+                        Shard {
+                            index_uid: Some(index_uid.clone()),
+                            source_id: INGEST_V2_SOURCE_ID.to_string(),
+                            shard_id: Some(ShardId::from(2)),
+                            shard_state: ShardState::Open as i32,
+                            leader_id: self_node_id.to_string(),
+                            follower_id: Some("test-ingester-1".to_string()),
+                            ..Default::default()
+                        },
+                        Shard {
+                            index_uid: Some(index_uid.clone()),
+                            source_id: INGEST_V2_SOURCE_ID.to_string(),
+                            shard_id: Some(ShardId::from(1)),
+                            shard_state: ShardState::Open as i32,
+                            leader_id: "test-ingester-1".to_string(),
+                            ..Default::default()
+                        },
+                    ],
+                    doc_mapping_json: String::new(),
+                    max_ingest_throughput: None,
+                };
+                response.successes.push(success);
+            } else {
+                let failure = GetOrCreateOpenShardsFailure {
+                    subrequest_id: subrequest.subrequest_id,
+                    index_id: subrequest.index_id.clone(),
+                    source_id: INGEST_V2_SOURCE_ID.to_string(),
+                    reason: GetOrCreateOpenShardsFailureReason::IndexNotFound as i32,
+                };
+                response.failures.push(failure);
+            }
+        }
+        response
+    }
+
+    #[tokio::test]
+    async fn test_kafka_produce_service_api_versions() {
+        let service = setup_kafka_produce_service(
+            ControlPlaneServiceClient::mock().into(),
+            IngesterServiceClient::mock().into(),
+        )
+        .await;
+
+        for (api_version, expected_error_code) in [(2, 0), (3, 35)] {
+            let mut request = BytesMut::new();
+            request.put_i16(API_KEY_API_VERSIONS);
+            request.put_i16(api_version);
+            request.put_i32(42);
+            request.put_i16(-1);
+
+            let response = service
+                .handle_request(request.freeze())
+                .await
+                .unwrap()
+                .unwrap();
+
+            let mut decoder = Decoder::new(response.freeze());
+            let response_size = decoder.read_i32().unwrap();
+            assert_eq!(response_size as usize, decoder.remaining());
+            assert_eq!(decoder.read_i32().unwrap(), 42);
+            assert_eq!(decoder.read_i16().unwrap(), expected_error_code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_kafka_produce_service_rejects_unsupported_versions() {
+        let service = setup_kafka_produce_service(
+            ControlPlaneServiceClient::mock().into(),
+            IngesterServiceClient::mock().into(),
+        )
+        .await;
+
+        let mut request = BytesMut::new();
+        request.put_i16(API_KEY_PRODUCE);
+        request.put_i16(2);
+        request.put_i32(42);
+        request.put_i16(-1);
+
+        let error = service.handle_request(request.freeze()).await.unwrap_err();
+        assert!(matches!(
+            error,
+            KafkaProtocolError::UnsupportedVersion {
+                api_key: API_KEY_PRODUCE,
+                api_version: 2
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_kafka_produce_service_metadata() {
+        let mut control_plane_mock = ControlPlaneServiceClient::mock();
+        let (self_node_id_tx, self_node_id_rx) = std::sync::mpsc::channel::<String>();
+        control_plane_mock
+            .expect_get_or_create_open_shards()
+            .once()
+            .returning(move |request| {
+                let self_node_id = self_node_id_rx.recv().unwrap();
+                assert_eq!(request.subrequests.len(), 2);
+                Ok(get_or_create_open_shards_response(&self_node_id, &request))
+            });
+        let service = setup_kafka_produce_service(
+            control_plane_mock.into(),
+            IngesterServiceClient::mock().into(),
+        )
+        .await;
+        self_node_id_tx
+            .send(service.self_node_id.to_string())
+            .unwrap();
+        let self_broker_id = broker_id(service.self_node_id.as_str());
+
+        let metadata_request = MetadataRequest {
+            topics: Some(vec![
+                "test-index".to_string(),
+                "test-index-not-found".to_string(),
+            ]),
+        };
+        let metadata_response = service.metadata(metadata_request).await;
+        assert_eq!(
+            metadata_response.brokers,
+            [MetadataBroker {
+                node_id: self_broker_id,
+                host: "127.0.0.1".to_string(),
+                port: 9092,
+            }]
+        );
+        assert_eq!(metadata_response.controller_id, self_broker_id);
+        assert_eq!(metadata_response.topics.len(), 2);
+
+        let topic = &metadata_response.topics[0];
+        assert_eq!(topic.error_code, ErrorCode::None);
+        assert_eq!(topic.name, "test-index");
+        assert_eq!(
+            topic.partitions,
+            [
+                MetadataPartition {
+                    error_code: ErrorCode::LeaderNotAvailable,
+                    partition_index: 0,
+                    leader_id: -1,
+                    replica_nodes: Vec::new(),
+                },
+                MetadataPartition {
+                    error_code: ErrorCode::None,
+                    partition_index: 1,
+                    leader_id: self_broker_id,
+                    replica_nodes: vec![self_broker_id, broker_id("test-ingester-1")],
+                },
+            ]
+        );
+        let topic = &metadata_response.topics[1];
+        assert_eq!(topic.error_code, ErrorCode::UnknownTopicOrPartition);
+        assert_eq!(topic.name, "test-index-not-found");
+        assert!(topic.partitions.is_empty());
+
+        let metadata_response = service.metadata(MetadataRequest::default()).await;
+        assert!(metadata_response.topics.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_kafka_produce_service_produce() {
+        let mut control_plane_mock = ControlPlaneServiceClient::mock();
+        let (self_node_id_tx, self_node_id_rx) = std::sync::mpsc::channel::<String>();
+        control_plane_mock
+            .expect_get_or_create_open_shards()
+            .times(2)
+            .returning(move |request| {
+                let self_node_id = self_node_id_rx.recv().unwrap();
+                assert_eq!(request.subrequests.len(), 1);
+                Ok(get_or_create_open_shards_response(&self_node_id, &request))
+            });
+        let mut ingester_mock = IngesterServiceClient::mock();
+        ingester_mock.expect_persist().once().returning(|request| {
+            assert_eq!(request.subrequests.len(), 1);
+            assert_eq!(request.commit_type(), CommitTypeV2::Auto);
+
+            let subrequest = &request.subrequests[0];
+            assert_eq!(subrequest.subrequest_id, 0);
+            assert_eq!(subrequest.index_uid(), &IndexUid::for_test("test-index", 0));
+            assert_eq!(subrequest.source_id, INGEST_V2_SOURCE_ID);
+            assert_eq!(subrequest.shard_id(), ShardId::from(2));
+            assert_eq!(
+                subrequest.doc_batch,
+                Some(DocBatchV2::for_test(["test-doc-foo", "test-doc-bar"]))
+            );
+            let response = PersistResponse {
+                leader_id: request.leader_id,
+                successes: vec![PersistSuccess {
+                    subrequest_id: 0,
+                    index_uid: Some(IndexUid::for_test("test-index", 0)),
+                    source_id: INGEST_V2_SOURCE_ID.to_string(),
+                    shard_id: Some(ShardId::from(2)),
+                    replication_position_inclusive: Some(Position::offset(10u64)),
+                }],
+                failures: Vec::new(),
+            };
+            Ok(response)
+        });
+        ingester_mock.expect_persist().once().returning(|request| {
+            let response = PersistResponse {
+                leader_id: request.leader_id,
+                successes: Vec::new(),
+                failures: vec![PersistFailure {
+                    subrequest_id: 0,
+                    index_uid: Some(IndexUid::for_test("test-index", 0)),
+                    source_id: INGEST_V2_SOURCE_ID.to_string(),
+                    shard_id: Some(ShardId::from(2)),
+                    reason: PersistFailureReason::ShardClosed as i32,
+                }],
+            };
+            Ok(response)
+        });
+        let service =
+            setup_kafka_produce_service(control_plane_mock.into(), ingester_mock.into()).await;
+        let self_node_id = service.self_node_id.to_string();
+        self_node_id_tx.send(self_node_id.clone()).unwrap();
+        self_node_id_tx.send(self_node_id).unwrap();
+
+        let records = encode_record_batch(&[Some(b"test-doc-foo"), Some(b"test-doc-bar")], 0);
+        let produce_request = || ProduceRequest {
+            acks: -1,
+            topics: vec![ProduceTopicData {
+                name: "test-index".to_string(),
+                partitions: vec![
+                    ProducePartitionData {
+                        partition_index: 0,
+                        records: Some(records.clone().freeze()),
+                    },
+                    ProducePartitionData {
+                        partition_index: 1,
+                        records: Some(records.clone().freeze()),
+                    },
+                    ProducePartitionData {
+                        partition_index: 2,
+                        records: Some(records.clone().freeze()),
+                    },
+                ],
+            }],
+        };
+        let produce_response = service.produce(produce_request()).await;
+        assert_eq!(
+            produce_response.topics,
+            [ProduceTopicResponse {
+                name: "test-index".to_string(),
+                partitions: vec![
+                    ProducePartitionResponse {
+                        partition_index: 0,
+                        error_code: ErrorCode::NotLeaderOrFollower,
+                        base_offset: -1,
+                    },
+                    ProducePartitionResponse {
+                        partition_index: 1,
+                        error_code: ErrorCode::None,
+                        base_offset: 9,
+                    },
+                    ProducePartitionResponse {
+                        partition_index: 2,
+                        error_code: ErrorCode::UnknownTopicOrPartition,
+                        base_offset: -1,
+                    },
+                ],
+            }]
+        );
+        // The partition table is cached.
+        let produce_response = service.produce(produce_request()).await;
+        assert_eq!(
+            produce_response.topics[0].partitions[1].error_code,
+            ErrorCode::NotLeaderOrFollower
+        );
+        // The shard is closed, so the partition table is invalidated and fetched again.
+        assert!(service.partition_tables.lock().unwrap().is_empty());
+
+        let partition_tables = service.partition_tables(&["test-index"]).await;
+        assert_eq!(
+            partition_tables["test-index"]
+                .as_ref()
+                .unwrap()
+                .shards
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kafka_produce_service_produce_compressed() {
+        let mut control_plane_mock = ControlPlaneServiceClient::mock();
+        let (self_node_id_tx, self_node_id_rx) = std::sync::mpsc::channel::<String>();
+        control_plane_mock
+            .expect_get_or_create_open_shards()
+            .once()
+            .returning(move |request| {
+                let self_node_id = self_node_id_rx.recv().unwrap();
+                Ok(get_or_create_open_shards_response(&self_node_id, &request))
+            });
+        let mut ingester_mock = IngesterServiceClient::mock();
+        ingester_mock
+            .expect_persist()
+            .times(2)
+            .returning(|request| {
+                assert_eq!(request.subrequests.len(), 1);
+
+                let response = PersistResponse {
+                    leader_id: request.leader_id,
+                    successes: vec![PersistSuccess {
+                        subrequest_id: 0,
+                        index_uid: Some(IndexUid::for_test("test-index", 0)),
+                        source_id: INGEST_V2_SOURCE_ID.to_string(),
+                        shard_id: Some(ShardId::from(2)),
+                        replication_position_inclusive: Some(Position::offset(10u64)),
+                    }],
+                    failures: Vec::new(),
+                };
+                Ok(response)
+            });
+        let mut service =
+            setup_kafka_produce_service(control_plane_mock.into(), ingester_mock.into()).await;
+        service.wal_compression_enabled = true;
+        self_node_id_tx
+            .send(service.self_node_id.to_string())
+            .unwrap();
+
+        // The WAL holds each doc batch as a single record, so the base offset is unknown.
+        let records = encode_record_batch(&[Some(b"test-doc-foo"), Some(b"test-doc-bar")], 0);
+        let produce_request = ProduceRequest {
+            acks: -1,
+            topics: vec![ProduceTopicData {
+                name: "test-index".to_string(),
+                partitions: vec![ProducePartitionData {
+                    partition_index: 1,
+                    records: Some(records.freeze()),
+                }],
+            }],
+        };
+        let produce_response = service.produce(produce_request).await;
+        let partition = &produce_response.topics[0].partitions[0];
+        assert_eq!(partition.error_code, ErrorCode::None);
+        assert_eq!(partition.base_offset, -1);
+
+        // The decompressed records are bounded across all the partitions of the request.
+        service.max_request_size = ByteSize::b(100);
+        // zstd
+        let records = encode_record_batch(&[Some(&[b'x'; 40])], 4);
+        let produce_request = ProduceRequest {
+            acks: -1,
+            topics: vec![ProduceTopicData {
+                name: "test-index".to_string(),
+                partitions: vec![
+                    ProducePartitionData {
+                        partition_index: 1,
+                        records: Some(records.clone().freeze()),
+                    },
+                    ProducePartitionData {
+                        partition_index: 1,
+                        records: Some(records.freeze()),
+                    },
+                ],
+            }],
+        };
+        let produce_response = service.produce(produce_request).await;
+        let partitions = &produce_response.topics[0].partitions;
+        assert_eq!(partitions[0].error_code, ErrorCode::None);
+        assert_eq!(partitions[1].error_code, ErrorCode::CorruptMessage);
+    }
+
+    #[tokio::test]
+    async fn test_kafka_produce_service_drops_stalled_connections() {
+        let service = setup_kafka_produce_service(
+            ControlPlaneServiceClient::mock().into(),
+            IngesterServiceClient::mock().into(),
+        )
+        .await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen_addr = listener.local_addr().unwrap();
+        let mut client_stream = TcpStream::connect(listen_addr).await.unwrap();
+        let (server_stream, _) = listener.accept().await.unwrap();
+
+        // The client announces a 100-byte request but stops after sending a few bytes of it.
+        client_stream.write_i32(100).await.unwrap();
+        client_stream.write_all(&[0; 10]).await.unwrap();
+
+        let error = timeout(
+            REQUEST_READ_TIMEOUT * 10,
+            service.handle_connection(server_stream),
+        )
+        .await
+        .unwrap()
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        // The connection is closed on the server side.
+        let mut buffer = [0; 1];
+        let read_result = client_stream.read(&mut buffer).await;
+        assert!(matches!(read_result, Ok(0) | Err(_)));
+    }
+}
@@ -0,0 +1,648 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Minimal subset of the Kafka wire protocol: the ApiVersions, Metadata, and Produce APIs, in their
+//! non-flexible versions only. See <https://kafka.apache.org/protocol> for the specification.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+pub(super) const API_KEY_PRODUCE: i16 = 0;
+pub(super) const API_KEY_METADATA: i16 = 3;
+pub(super) const API_KEY_API_VERSIONS: i16 = 18;
+
+/// APIs supported by the server along with their minimum and maximum versions. Clients negotiate
+/// the versions they use with the ApiVersions API.
+pub(super) const SUPPORTED_API_VERSIONS: [(i16, i16, i16); 3] = [
+    (API_KEY_PRODUCE, 3, 7),
+    (API_KEY_METADATA, 0, 4),
+    (API_KEY_API_VERSIONS, 0, 2),
+];
+
+pub(super) fn is_supported_api_version(api_key: i16, api_version: i16) -> bool {
+    SUPPORTED_API_VERSIONS
+        .iter()
+        .any(|(key, min_version, max_version)| {
+            *key == api_key && (*min_version..=*max_version).contains(&api_version)
+        })
+}
+
+/// Subset of the Kafka error codes returned by the server.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(i16)]
+pub(super) enum ErrorCode {
+    UnknownServerError = -1,
+    None = 0,
+    CorruptMessage = 2,
+    UnknownTopicOrPartition = 3,
+    LeaderNotAvailable = 5,
+    NotLeaderOrFollower = 6,
+    RequestTimedOut = 7,
+    UnsupportedVersion = 35,
+    KafkaStorageError = 56,
+    UnsupportedCompressionType = 76,
+    ThrottlingQuotaExceeded = 89,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(super) enum KafkaProtocolError {
+    #[error("unexpected end of message")]
+    UnexpectedEof,
+    #[error("invalid {0}")]
+    Invalid(&'static str),
+    #[error("unsupported version {api_version} for API key {api_key}")]
+    UnsupportedVersion { api_key: i16, api_version: i16 },
+    #[error("unsupported compression codec {0}")]
+    UnsupportedCompression(i16),
+    #[error("unsupported record batch magic byte {0}")]
+    UnsupportedMagic(i8),
+}
+
+pub(super) type KafkaProtocolResult<T> = Result<T, KafkaProtocolError>;
+
+/// Reads the primitive types of the Kafka protocol from a buffer.
+pub(super) struct Decoder {
+    buffer: Bytes,
+}
+
+impl Decoder {
+    pub fn new(buffer: Bytes) -> Self {
+        Self { buffer }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buffer.remaining()
+    }
+
+    fn ensure_remaining(&self, num_bytes: usize) -> KafkaProtocolResult<()> {
+        if self.buffer.remaining() < num_bytes {
+            return Err(KafkaProtocolError::UnexpectedEof);
+        }
+        Ok(())
+    }
+
+    pub fn read_i8(&mut self) -> KafkaProtocolResult<i8> {
+        self.ensure_remaining(1)?;
+        Ok(self.buffer.get_i8())
+    }
+
+    pub fn read_i16(&mut self) -> KafkaProtocolResult<i16> {
+        self.ensure_remaining(2)?;
+        Ok(self.buffer.get_i16())
+    }
+
+    pub fn read_i32(&mut self) -> KafkaProtocolResult<i32> {
+        self.ensure_remaining(4)?;
+        Ok(self.buffer.get_i32())
+    }
+
+    pub fn read_i64(&mut self) -> KafkaProtocolResult<i64> {
+        self.ensure_remaining(8)?;
+        Ok(self.buffer.get_i64())
+    }
+
+    pub fn read_bool(&mut self) -> KafkaProtocolResult<bool> {
+        Ok(self.read_i8()? != 0)
+    }
+
+    pub fn read_bytes(&mut self, num_bytes: usize) -> KafkaProtocolResult<Bytes> {
+        self.ensure_remaining(num_bytes)?;
+        Ok(self.buffer.split_to(num_bytes))
+    }
+
+    pub fn read_string(&mut self) -> KafkaProtocolResult<String> {
+        self.read_nullable_string()?
+            .ok_or(KafkaProtocolError::Invalid("null string"))
+    }
+
+    pub fn read_nullable_string(&mut self) -> KafkaProtocolResult<Option<String>> {
+        let len = self.read_i16()?;
+
+        if len < 0 {
+            return Ok(None);
+        }
+        let bytes = self.read_bytes(len as usize)?;
+        let string =
+            String::from_utf8(bytes.to_vec()).map_err(|_| KafkaProtocolError::Invalid("string"))?;
+        Ok(Some(string))
+    }
+
+    pub fn read_nullable_bytes(&mut self) -> KafkaProtocolResult<Option<Bytes>> {
+        let len = self.read_i32()?;
+
+        if len < 0 {
+            return Ok(None);
+        }
+        self.read_bytes(len as usize).map(Some)
+    }
+
+    /// Reads an array of items, returning `None` if the array is null.
+    pub fn read_nullable_array<T>(
+        &mut self,
+        mut read_item: impl FnMut(&mut Self) -> KafkaProtocolResult<T>,
+    ) -> KafkaProtocolResult<Option<Vec<T>>> {
+        let len = self.read_i32()?;
+
+        if len < 0 {
+            return Ok(None);
+        }
+        // Each item is at least one byte long, which bounds the capacity to allocate.
+        let mut items = Vec::with_capacity((len as usize).min(self.remaining()));
+
+        for _ in 0..len {
+            items.push(read_item(self)?);
+        }
+        Ok(Some(items))
+    }
+
+    pub fn read_array<T>(
+        &mut self,
+        read_item: impl FnMut(&mut Self) -> KafkaProtocolResult<T>,
+    ) -> KafkaProtocolResult<Vec<T>> {
+        self.read_nullable_array(read_item)?
+            .ok_or(KafkaProtocolError::Invalid("null array"))
+    }
+
+    /// Reads a zigzag-encoded variable-length integer.
+    pub fn read_varlong(&mut self) -> KafkaProtocolResult<i64> {
+        let mut value: u64 = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_i8()? as u8;
+            value |= ((byte & 0x7F) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+        Err(KafkaProtocolError::Invalid("varint"))
+    }
+
+    pub fn read_varint(&mut self) -> KafkaProtocolResult<i32> {
+        i32::try_from(self.read_varlong()?).map_err(|_| KafkaProtocolError::Invalid("varint"))
+    }
+
+    /// Reads a byte array prefixed with its varint-encoded length, returning `None` if the array
+    /// is null.
+    pub fn read_varint_bytes(&mut self) -> KafkaProtocolResult<Option<Bytes>> {
+        let len = self.read_varint()?;
+
+        if len < 0 {
+            return Ok(None);
+        }
+        self.read_bytes(len as usize).map(Some)
+    }
+}
+
+fn put_string(buffer: &mut BytesMut, string: &str) {
+    buffer.put_i16(string.len() as i16);
+    buffer.put_slice(string.as_bytes());
+}
+
+fn put_nullable_string(buffer: &mut BytesMut, string_opt: Option<&str>) {
+    if let Some(string) = string_opt {
+        put_string(buffer, string);
+    } else {
+        buffer.put_i16(-1);
+    }
+}
+
+fn put_array<T>(buffer: &mut BytesMut, items: &[T], mut put_item: impl FnMut(&mut BytesMut, &T)) {
+    buffer.put_i32(items.len() as i32);
+
+    for item in items {
+        put_item(buffer, item);
+    }
+}
+
+/// Header of the requests using a non-flexible version (request header v1).
+#[derive(Debug)]
+pub(super) struct RequestHeader {
+    pub api_key: i16,
+    pub api_version: i16,
+    pub correlation_id: i32,
+}
+
+impl RequestHeader {
+    pub fn decode(decoder: &mut Decoder) -> KafkaProtocolResult<Self> {
+        let api_key = decoder.read_i16()?;
+        let api_version = decoder.read_i16()?;
+        let correlation_id = decoder.read_i32()?;
+        // client_id
+        decoder.read_nullable_string()?;
+
+        let request_header = Self {
+            api_key,
+            api_version,
+            correlation_id,
+        };
+        Ok(request_header)
+    }
+}
+
+/// Encodes the body of an ApiVersions response. Clients that send a request with a version that
+/// the server does not support expect a v0 response carrying the `UNSUPPORTED_VERSION` error code
+/// and the supported versions, so that they can retry with a lower version.
+pub(super) fn encode_api_versions_response(
+    api_version: i16,
+    error_code: ErrorCode,
+    buffer: &mut BytesMut,
+) {
+    buffer.put_i16(error_code as i16);
+    put_array(
+        buffer,
+        &SUPPORTED_API_VERSIONS,
+        |buffer, (api_key, min_version, max_version)| {
+            buffer.put_i16(*api_key);
+            buffer.put_i16(*min_version);
+            buffer.put_i16(*max_version);
+        },
+    );
+    if api_version >= 1 {
+        // throttle_time_ms
+        buffer.put_i32(0);
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct MetadataRequest {
+    /// Topics to describe. `None` or empty means all the topics.
+    pub topics: Option<Vec<String>>,
+}
+
+impl MetadataRequest {
+    pub fn decode(api_version: i16, decoder: &mut Decoder) -> KafkaProtocolResult<Self> {
+        let topics = decoder.read_nullable_array(Decoder::read_string)?;
+
+        if api_version >= 4 {
+            // allow_auto_topic_creation: indexes are never created on the fly.
+            decoder.read_bool()?;
+        }
+        Ok(Self { topics })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) struct MetadataBroker {
+    pub node_id: i32,
+    pub host: String,
+    pub port: i32,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(super) struct MetadataPartition {
+    pub error_code: ErrorCode,
+    pub partition_index: i32,
+    pub leader_id: i32,
+    pub replica_nodes: Vec<i32>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(super) struct MetadataTopic {
+    pub error_code: ErrorCode,
+    pub name: String,
+    pub partitions: Vec<MetadataPartition>,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct MetadataResponse {
+    pub brokers: Vec<MetadataBroker>,
+    pub cluster_id: Option<String>,
+    pub controller_id: i32,
+    pub topics: Vec<MetadataTopic>,
+}
+
+impl MetadataResponse {
+    pub fn encode(&self, api_version: i16, buffer: &mut BytesMut) {
+        if api_version >= 3 {
+            // throttle_time_ms
+            buffer.put_i32(0);
+        }
+        put_array(buffer, &self.brokers, |buffer, broker| {
+            buffer.put_i32(broker.node_id);
+            put_string(buffer, &broker.host);
+            buffer.put_i32(broker.port);
+
+            if api_version >= 1 {
+                // rack
+                put_nullable_string(buffer, None);
+            }
+        });
+        if api_version >= 2 {
+            put_nullable_string(buffer, self.cluster_id.as_deref());
+        }
+        if api_version >= 1 {
+            buffer.put_i32(self.controller_id);
+        }
+        put_array(buffer, &self.topics, |buffer, topic| {
+            buffer.put_i16(topic.error_code as i16);
+            put_string(buffer, &topic.name);
+
+            if api_version >= 1 {
+                // is_internal
+                buffer.put_i8(0);
+            }
+            put_array(buffer, &topic.partitions, |buffer, partition| {
+                buffer.put_i16(partition.error_code as i16);
+                buffer.put_i32(partition.partition_index);
+                buffer.put_i32(partition.leader_id);
+                put_array(buffer, &partition.replica_nodes, |buffer, node_id| {
+                    buffer.put_i32(*node_id)
+                });
+                // isr_nodes: all the replicas are considered in sync.
+                put_array(buffer, &partition.replica_nodes, |buffer, node_id| {
+                    buffer.put_i32(*node_id)
+                });
+            });
+        });
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct ProducePartitionData {
+    pub partition_index: i32,
+    pub records: Option<Bytes>,
+}
+
+#[derive(Debug)]
+pub(super) struct ProduceTopicData {
+    pub name: String,
+    pub partitions: Vec<ProducePartitionData>,
+}
+
+#[derive(Debug)]
+pub(super) struct ProduceRequest {
+    /// Number of acknowledgments required before responding. The server does not respond to
+    /// requests with `acks` set to 0.
+    pub acks: i16,
+    pub topics: Vec<ProduceTopicData>,
+}
+
+impl ProduceRequest {
+    /// Decodes a produce request. Only versions 3 and above, which carry record batches (magic
+    /// v2), are supported.
+    pub fn decode(decoder: &mut Decoder) -> KafkaProtocolResult<Self> {
+        // transactional_id
+        decoder.read_nullable_string()?;
+        let acks = decoder.read_i16()?;
+        // timeout_ms
+        decoder.read_i32()?;
+        let topics = decoder.read_array(|decoder| {
+            let name = decoder.read_string()?;
+            let partitions = decoder.read_array(|decoder| {
+                let partition_index = decoder.read_i32()?;
+                let records = decoder.read_nullable_bytes()?;
+                Ok(ProducePartitionData {
+                    partition_index,
+                    records,
+                })
+            })?;
+            Ok(ProduceTopicData { name, partitions })
+        })?;
+        Ok(Self { acks, topics })
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(super) struct ProducePartitionResponse {
+    pub partition_index: i32,
+    pub error_code: ErrorCode,
+    pub base_offset: i64,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(super) struct ProduceTopicResponse {
+    pub name: String,
+    pub partitions: Vec<ProducePartitionResponse>,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ProduceResponse {
+    pub topics: Vec<ProduceTopicResponse>,
+}
+
+impl ProduceResponse {
+    pub fn encode(&self, api_version: i16, buffer: &mut BytesMut) {
+        put_array(buffer, &self.topics, |buffer, topic| {
+            put_string(buffer, &topic.name);
+            put_array(buffer, &topic.partitions, |buffer, partition| {
+                buffer.put_i32(partition.partition_index);
+                buffer.put_i16(partition.error_code as i16);
+                buffer.put_i64(partition.base_offset);
+                // log_append_time_ms: -1 means that the create time of the records is used.
+                buffer.put_i64(-1);
+
+                if api_version >= 5 {
+                    // log_start_offset
+                    buffer.put_i64(-1);
+                }
+            });
+        });
+        // throttle_time_ms
+        buffer.put_i32(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoder_primitives() {
+        let mut buffer = BytesMut::new();
+        buffer.put_i8(-1);
+        buffer.put_i16(-2);
+        buffer.put_i32(-3);
+        buffer.put_i64(-4);
+        put_string(&mut buffer, "test-string");
+        put_nullable_string(&mut buffer, None);
+        buffer.put_i32(3);
+        buffer.put_slice(b"foo");
+        buffer.put_i32(-1);
+        // Zigzag-encoded varints: 1, -1, 150.
+        buffer.put_slice(&[0x02, 0x01, 0xAC, 0x02]);
+
+        let mut decoder = Decoder::new(buffer.freeze());
+        assert_eq!(decoder.read_i8().unwrap(), -1);
+        assert_eq!(decoder.read_i16().unwrap(), -2);
+        assert_eq!(decoder.read_i32().unwrap(), -3);
+        assert_eq!(decoder.read_i64().unwrap(), -4);
+        assert_eq!(decoder.read_string().unwrap(), "test-string");
+        assert!(decoder.read_nullable_string().unwrap().is_none());
+        assert_eq!(decoder.read_nullable_bytes().unwrap().unwrap(), "foo");
+        assert!(decoder.read_nullable_bytes().unwrap().is_none());
+        assert_eq!(decoder.read_varint().unwrap(), 1);
+        assert_eq!(decoder.read_varint().unwrap(), -1);
+        assert_eq!(decoder.read_varint().unwrap(), 150);
+        assert_eq!(decoder.remaining(), 0);
+
+        let error = decoder.read_i32().unwrap_err();
+        assert!(matches!(error, KafkaProtocolError::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_decode_metadata_request() {
+        let mut buffer = BytesMut::new();
+        buffer.put_i32(-1);
+        buffer.put_i8(1);
+
+        let mut decoder = Decoder::new(buffer.freeze());
+        let metadata_request = MetadataRequest::decode(4, &mut decoder).unwrap();
+        assert!(metadata_request.topics.is_none());
+
+        let mut buffer = BytesMut::new();
+        put_array(&mut buffer, &["test-index"], |buffer, topic| {
+            put_string(buffer, topic)
+        });
+
+        let mut decoder = Decoder::new(buffer.freeze());
+        let metadata_request = MetadataRequest::decode(0, &mut decoder).unwrap();
+        assert_eq!(metadata_request.topics.unwrap(), ["test-index"]);
+    }
+
+    #[test]
+    fn test_decode_produce_request() {
+        let mut buffer = BytesMut::new();
+        put_nullable_string(&mut buffer, None);
+        buffer.put_i16(-1);
+        buffer.put_i32(30_000);
+        buffer.put_i32(1);
+        put_string(&mut buffer, "test-index");
+        buffer.put_i32(2);
+        buffer.put_i32(0);
+        buffer.put_i32(3);
+        buffer.put_slice(b"foo");
+        buffer.put_i32(1);
+        buffer.put_i32(-1);
+
+        let mut decoder = Decoder::new(buffer.freeze());
+        let produce_request = ProduceRequest::decode(&mut decoder).unwrap();
+        assert_eq!(produce_request.acks, -1);
+        assert_eq!(produce_request.topics.len(), 1);
+
+        let topic = &produce_request.topics[0];
+        assert_eq!(topic.name, "test-index");
+        assert_eq!(topic.partitions.len(), 2);
+        assert_eq!(topic.partitions[0].partition_index, 0);
+        assert_eq!(topic.partitions[0].records.as_ref().unwrap(), "foo");
+        assert_eq!(topic.partitions[1].partition_index, 1);
+        assert!(topic.partitions[1].records.is_none());
+    }
+
+    #[test]
+    fn test_encode_api_versions_response() {
+        let mut buffer = BytesMut::new();
+        encode_api_versions_response(0, ErrorCode::UnsupportedVersion, &mut buffer);
+
+        let mut decoder = Decoder::new(buffer.freeze());
+        assert_eq!(decoder.read_i16().unwrap(), 35);
+
+        let api_versions = decoder
+            .read_array(|decoder| {
+                Ok((
+                    decoder.read_i16()?,
+                    decoder.read_i16()?,
+                    decoder.read_i16()?,
+                ))
+            })
+            .unwrap();
+        assert_eq!(api_versions, SUPPORTED_API_VERSIONS);
+        assert_eq!(decoder.remaining(), 0);
+
+        assert!(is_supported_api_version(API_KEY_PRODUCE, 7));
+        assert!(!is_supported_api_version(API_KEY_PRODUCE, 2));
+        assert!(!is_supported_api_version(API_KEY_METADATA, 5));
+    }
+
+    #[test]
+    fn test_encode_metadata_response() {
+        let metadata_response = MetadataResponse {
+            brokers: vec![MetadataBroker {
+                node_id: 42,
+                host: "10.0.0.1".to_string(),
+                port: 9092,
+            }],
+            cluster_id: Some("test-cluster".to_string()),
+            controller_id: 42,
+            topics: vec![MetadataTopic {
+                error_code: ErrorCode::None,
+                name: "test-index".to_string(),
+                partitions: vec![MetadataPartition {
+                    error_code: ErrorCode::None,
+                    partition_index: 0,
+                    leader_id: 42,
+                    replica_nodes: vec![42],
+                }],
+            }],
+        };
+        let mut buffer = BytesMut::new();
+        metadata_response.encode(4, &mut buffer);
+
+        let mut decoder = Decoder::new(buffer.freeze());
+        assert_eq!(decoder.read_i32().unwrap(), 0);
+        assert_eq!(decoder.read_i32().unwrap(), 1);
+        assert_eq!(decoder.read_i32().unwrap(), 42);
+        assert_eq!(decoder.read_string().unwrap(), "10.0.0.1");
+        assert_eq!(decoder.read_i32().unwrap(), 9092);
+        assert!(decoder.read_nullable_string().unwrap().is_none());
+        assert_eq!(
+            decoder.read_nullable_string().unwrap().unwrap(),
+            "test-cluster"
+        );
+        assert_eq!(decoder.read_i32().unwrap(), 42);
+        assert_eq!(decoder.read_i32().unwrap(), 1);
+        assert_eq!(decoder.read_i16().unwrap(), 0);
+        assert_eq!(decoder.read_string().unwrap(), "test-index");
+        assert!(!decoder.read_bool().unwrap());
+        assert_eq!(decoder.read_i32().unwrap(), 1);
+        assert_eq!(decoder.read_i16().unwrap(), 0);
+        assert_eq!(decoder.read_i32().unwrap(), 0);
+        assert_eq!(decoder.read_i32().unwrap(), 42);
+        assert_eq!(decoder.read_array(Decoder::read_i32).unwrap(), [42]);
+        assert_eq!(decoder.read_array(Decoder::read_i32).unwrap(), [42]);
+        assert_eq!(decoder.remaining(), 0);
+    }
+
+    #[test]
+    fn test_encode_produce_response() {
+        let produce_response = ProduceResponse {
+            topics: vec![ProduceTopicResponse {
+                name: "test-index".to_string(),
+                partitions: vec![ProducePartitionResponse {
+                    partition_index: 1,
+                    error_code: ErrorCode::NotLeaderOrFollower,
+                    base_offset: -1,
+                }],
+            }],
+        };
+        let mut buffer = BytesMut::new();
+        produce_response.encode(7, &mut buffer);
+
+        let mut decoder = Decoder::new(buffer.freeze());
+        assert_eq!(decoder.read_i32().unwrap(), 1);
+        assert_eq!(decoder.read_string().unwrap(), "test-index");
+        assert_eq!(decoder.read_i32().unwrap(), 1);
+        assert_eq!(decoder.read_i32().unwrap(), 1);
+        assert_eq!(decoder.read_i16().unwrap(), 6);
+        assert_eq!(decoder.read_i64().unwrap(), -1);
+        assert_eq!(decoder.read_i64().unwrap(), -1);
+        assert_eq!(decoder.read_i64().unwrap(), -1);
+        assert_eq!(decoder.read_i32().unwrap(), 0);
+        assert_eq!(decoder.remaining(), 0);
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of the record batches (magic v2) carried by produce requests. See
+//! <https://kafka.apache.org/documentation/#recordbatch> for the specification.
+
+use std::io::Read;
+
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+
+use super::protocol::{Decoder, KafkaProtocolError, KafkaProtocolResult};
+
+const RECORD_BATCH_MAGIC: i8 = 2;
+
+const COMPRESSION_CODEC_MASK: i16 = 0x07;
+
+const COMPRESSION_CODEC_NONE: i16 = 0;
+
+const COMPRESSION_CODEC_GZIP: i16 = 1;
+
+const COMPRESSION_CODEC_ZSTD: i16 = 4;
+
+const CONTROL_BATCH_FLAG: i16 = 0x20;
+
+/// Decodes the record batches of a partition and returns the values of the records, which are
+/// the documents to ingest. Records with a null or empty value and control batches, which are
+/// written by transactional producers, are skipped. Headers and keys are ignored.
+///
+/// The records of compressed batches are decompressed, up to `decompression_budget` bytes in
+/// total. The budget is decremented by the number of decompressed bytes so that it can be shared
+/// by all the partitions of a produce request.
+pub(super) fn decode_record_values(
+    records: Bytes,
+    decompression_budget: &mut usize,
+) -> KafkaProtocolResult<Vec<Bytes>> {
+    let mut decoder = Decoder::new(records);
+    let mut values = Vec::new();
+
+    while decoder.remaining() > 0 {
+        // base_offset: the offsets are assigned by the server.
+        decoder.read_i64()?;
+        let batch_len = decoder.read_i32()?;
+
+        if batch_len < 0 {
+            return Err(KafkaProtocolError::Invalid("record batch length"));
+        }
+        let batch = decoder.read_bytes(batch_len as usize)?;
+        decode_record_batch(batch, decompression_budget, &mut values)?;
+    }
+    Ok(values)
+}
+
+/// Decodes a record batch, starting after the `batch_length` field.
+fn decode_record_batch(
+    batch: Bytes,
+    decompression_budget: &mut usize,
+    values: &mut Vec<Bytes>,
+) -> KafkaProtocolResult<()> {
+    let mut decoder = Decoder::new(batch);
+    // partition_leader_epoch
+    decoder.read_i32()?;
+    let magic = decoder.read_i8()?;
+
+    if magic != RECORD_BATCH_MAGIC {
+        return Err(KafkaProtocolError::UnsupportedMagic(magic));
+    }
+    // The CRC is not verified: the connection is trusted to not corrupt data, and the WAL
+    // protects the records with its own checksums.
+    decoder.read_i32()?;
+    let attributes = decoder.read_i16()?;
+    // last_offset_delta, base_timestamp, max_timestamp, producer_id, producer_epoch,
+    // base_sequence
+    decoder.read_bytes(4 + 8 + 8 + 8 + 2 + 4)?;
+    let num_records = decoder.read_i32()?;
+
+    if attributes & CONTROL_BATCH_FLAG != 0 {
+        return Ok(());
+    }
+    let compressed_records = decoder.read_bytes(decoder.remaining())?;
+    let compression_codec = attributes & COMPRESSION_CODEC_MASK;
+
+    let records = match compression_codec {
+        COMPRESSION_CODEC_NONE => compressed_records,
+        COMPRESSION_CODEC_GZIP => decompress(
+            GzDecoder::new(compressed_records.as_ref()),
+            decompression_budget,
+        )?,
+        COMPRESSION_CODEC_ZSTD => {
+            let zstd_decoder = zstd::stream::read::Decoder::new(compressed_records.as_ref())
+                .map_err(|_| KafkaProtocolError::Invalid("zstd compressed records"))?;
+            decompress(zstd_decoder, decompression_budget)?
+        }
+        _ => {
+            return Err(KafkaProtocolError::UnsupportedCompression(
+                compression_codec,
+            ))
+        }
+    };
+    let mut decoder = Decoder::new(records);
+
+    for _ in 0..num_records {
+        let record_len = decoder.read_varint()?;
+
+        if record_len < 0 {
+            return Err(KafkaProtocolError::Invalid("record length"));
+        }
+        let record = decoder.read_bytes(record_len as usize)?;
+
+        if let Some(value) = decode_record_value(record)? {
+            values.push(value);
+        }
+    }
+    Ok(())
+}
+
+fn decode_record_value(record: Bytes) -> KafkaProtocolResult<Option<Bytes>> {
+    let mut decoder = Decoder::new(record);
+    // attributes
+    decoder.read_i8()?;
+    // timestamp_delta
+    decoder.read_varlong()?;
+    // offset_delta
+    decoder.read_varint()?;
+    // key
+    decoder.read_varint_bytes()?;
+    let value_opt = decoder.read_varint_bytes()?;
+    Ok(value_opt.filter(|value| !value.is_empty()))
+}
+
+fn decompress(reader: impl Read, decompression_budget: &mut usize) -> KafkaProtocolResult<Bytes> {
+    let mut decompressed = Vec::new();
+    reader
+        .take(*decompression_budget as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|_| KafkaProtocolError::Invalid("compressed records"))?;
+
+    if decompressed.len() > *decompression_budget {
+        return Err(KafkaProtocolError::Invalid("decompressed records size"));
+    }
+    *decompression_budget -= decompressed.len();
+    Ok(Bytes::from(decompressed))
+}
+
+#[cfg(test)]
+pub(super) mod test_utils {
+    use std::io::Write;
+
+    use bytes::{BufMut, BytesMut};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    fn put_varlong(buffer: &mut BytesMut, value: i64) {
+        let mut value = ((value << 1) ^ (value >> 63)) as u64;
+
+        while value >= 0x80 {
+            buffer.put_u8((value as u8) | 0x80);
+            value >>= 7;
+        }
+        buffer.put_u8(value as u8);
+    }
+
+    fn put_varint_bytes(buffer: &mut BytesMut, bytes_opt: Option<&[u8]>) {
+        if let Some(bytes) = bytes_opt {
+            put_varlong(buffer, bytes.len() as i64);
+            buffer.put_slice(bytes);
+        } else {
+            put_varlong(buffer, -1);
+        }
+    }
+
+    /// Encodes a record batch holding records with the given values, framed as in produce
+    /// requests.
+    pub fn encode_record_batch(values: &[Option<&[u8]>], attributes: i16) -> BytesMut {
+        let mut records = BytesMut::new();
+
+        for (offset_delta, value_opt) in values.iter().enumerate() {
+            let mut record = BytesMut::new();
+            record.put_i8(0);
+            put_varlong(&mut record, 0);
+            put_varlong(&mut record, offset_delta as i64);
+            put_varint_bytes(&mut record, Some(b"test-key"));
+            put_varint_bytes(&mut record, *value_opt);
+            // headers
+            put_varlong(&mut record, 1);
+            put_varint_bytes(&mut record, Some(b"test-header-key"));
+            put_varint_bytes(&mut record, Some(b"test-header-value"));
+
+            put_varlong(&mut records, record.len() as i64);
+            records.put_slice(&record);
+        }
+        let records: Vec<u8> = match attributes & COMPRESSION_CODEC_MASK {
+            COMPRESSION_CODEC_GZIP => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&records).unwrap();
+                encoder.finish().unwrap()
+            }
+            COMPRESSION_CODEC_ZSTD => zstd::encode_all(records.as_ref(), 0).unwrap(),
+            _ => records.to_vec(),
+        };
+        let mut batch = BytesMut::new();
+        batch.put_i32(0);
+        batch.put_i8(RECORD_BATCH_MAGIC);
+        batch.put_i32(0);
+        batch.put_i16(attributes);
+        batch.put_i32(values.len() as i32 - 1);
+        batch.put_i64(0);
+        batch.put_i64(0);
+        batch.put_i64(-1);
+        batch.put_i16(-1);
+        batch.put_i32(-1);
+        batch.put_i32(values.len() as i32);
+        batch.put_slice(&records);
+
+        let mut buffer = BytesMut::new();
+        buffer.put_i64(0);
+        buffer.put_i32(batch.len() as i32);
+        buffer.put_slice(&batch);
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::encode_record_batch;
+    use super::*;
+
+    #[test]
+    fn test_decode_record_values() {
+        let mut records = encode_record_batch(&[Some(b"test-doc-foo"), None, Some(b"")], 0);
+        records.extend_from_slice(&encode_record_batch(&[Some(b"test-doc-bar")], 0));
+
+        let values = decode_record_values(records.freeze(), &mut 1024).unwrap();
+        assert_eq!(values, ["test-doc-foo", "test-doc-bar"]);
+
+        let values = decode_record_values(Bytes::new(), &mut 1024).unwrap();
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn test_decode_record_values_compressed() {
+        for compression_codec in [COMPRESSION_CODEC_GZIP, COMPRESSION_CODEC_ZSTD] {
+            let records = encode_record_batch(
+                &[Some(b"test-doc-foo"), Some(b"test-doc-bar")],
+                compression_codec,
+            );
+            let values = decode_record_values(records.clone().freeze(), &mut 1024).unwrap();
+            assert_eq!(values, ["test-doc-foo", "test-doc-bar"]);
+
+            let error = decode_record_values(records.freeze(), &mut 16).unwrap_err();
+            assert!(matches!(error, KafkaProtocolError::Invalid(_)));
+        }
+        // Snappy
+        let records = encode_record_batch(&[Some(b"test-doc")], 2);
+        let error = decode_record_values(records.freeze(), &mut 1024).unwrap_err();
+        assert!(matches!(
+            error,
+            KafkaProtocolError::UnsupportedCompression(2)
+        ));
+    }
+
+    #[test]
+    fn test_decode_record_values_decompression_budget() {
+        let records = encode_record_batch(&[Some(b"test-doc")], COMPRESSION_CODEC_ZSTD);
+
+        let mut decompression_budget = 1024;
+        decode_record_values(records.clone().freeze(), &mut decompression_budget).unwrap();
+        let batch_decompressed_num_bytes = 1024 - decompression_budget;
+        assert!(batch_decompressed_num_bytes > 0);
+
+        let mut two_batches = records.clone();
+        two_batches.extend_from_slice(&records);
+
+        let mut decompression_budget = 2 * batch_decompressed_num_bytes;
+        let values =
+            decode_record_values(two_batches.clone().freeze(), &mut decompression_budget).unwrap();
+        assert_eq!(values, ["test-doc", "test-doc"]);
+        assert_eq!(decompression_budget, 0);
+
+        let mut decompression_budget = 2 * batch_decompressed_num_bytes - 1;
+        let error =
+            decode_record_values(two_batches.freeze(), &mut decompression_budget).unwrap_err();
+        assert!(matches!(error, KafkaProtocolError::Invalid(_)));
+    }
+
+    #[test]
+    fn test_decode_record_values_skips_control_batches() {
+        let records = encode_record_batch(&[Some(b"test-commit-marker")], CONTROL_BATCH_FLAG);
+        let values = decode_record_values(records.freeze(), &mut 1024).unwrap();
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn test_decode_record_values_invalid() {
+        let mut records = encode_record_batch(&[Some(b"test-doc")], 0);
+        records.truncate(records.len() - 1);
+        let error = decode_record_values(records.freeze(), &mut 1024).unwrap_err();
+        assert!(matches!(error, KafkaProtocolError::UnexpectedEof));
+
+        let mut records = encode_record_batch(&[Some(b"test-doc")], 0);
+        // magic byte
+        records[16] = 1;
+        let error = decode_record_values(records.freeze(), &mut 1024).unwrap_err();
+        assert!(matches!(error, KafkaProtocolError::UnsupportedMagic(1)));
+    }
+}
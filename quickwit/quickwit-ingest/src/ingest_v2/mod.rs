@@ -19,18 +19,24 @@
 
 mod broadcast;
 mod debouncing;
+mod dedup;
+mod doc_mapper;
 mod fetch;
 mod idle;
+mod ingest_volume;
 mod ingester;
+mod kafka;
 mod metrics;
 mod models;
 mod mrecord;
 mod mrecordlog_utils;
+mod publish_tracker;
 mod rate_meter;
 mod replication;
 mod router;
 mod routing_table;
 mod state;
+mod wal_inspector;
 mod workbench;
 
 use std::collections::HashMap;
@@ -49,10 +55,15 @@ use quickwit_proto::types::{IndexId, NodeId};
 use tracing::{error, info};
 
 pub use self::fetch::{FetchStreamError, MultiFetchStream};
+pub use self::ingest_volume::{
+    setup_ingest_volume_update_listener, today, DailyIngestVolume, IngestVolumeUpdate,
+};
 pub use self::ingester::{wait_for_ingester_decommission, wait_for_ingester_status, Ingester};
+pub use self::kafka::start_kafka_produce_server;
 use self::mrecord::MRECORD_HEADER_LEN;
 pub use self::mrecord::{decoded_mrecords, MRecord};
 pub use self::router::IngestRouter;
+pub use self::wal_inspector::{WalInspector, WalRecord, WalShardSummary, WalVerifyReport};
 
 pub type IngesterPool = Pool<NodeId, IngesterServiceClient>;
 
@@ -166,6 +177,7 @@ impl IngestRequestV2Builder {
                     index_id,
                     source_id: source_id.to_string(),
                     doc_batch: Some(doc_batch),
+                    dedup_id: None,
                 };
                 Some(ingest_subrequest)
             })
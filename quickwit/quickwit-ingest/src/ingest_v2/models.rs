@@ -23,10 +23,17 @@ use quickwit_proto::ingest::ShardState;
 use quickwit_proto::types::{NodeId, Position};
 use tokio::sync::watch;
 
+use super::dedup::DedupIds;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub(super) enum IngesterShardType {
-    /// A primary shard hosted on a leader and replicated on a follower.
-    Primary { follower_id: NodeId },
+    /// A primary shard hosted on a leader and replicated on one or several followers.
+    Primary {
+        follower_ids: Vec<NodeId>,
+        /// Number of copies of the shard, including the primary one, at the time it was
+        /// initialized.
+        replication_factor: usize,
+    },
     /// A replica shard hosted on a follower.
     Replica { leader_id: NodeId },
     /// A shard hosted on a single node when the replication factor is set to 1.
@@ -48,11 +55,13 @@ pub(super) struct IngesterShard {
     pub shard_status_rx: watch::Receiver<ShardStatus>,
     /// Instant at which the shard was last written to.
     pub last_write_instant: Instant,
+    /// Dedup IDs of the batches recently persisted in the shard.
+    pub dedup_ids: DedupIds,
 }
 
 impl IngesterShard {
     pub fn new_primary(
-        follower_id: NodeId,
+        follower_ids: Vec<NodeId>,
         shard_state: ShardState,
         replication_position_inclusive: Position,
         truncation_position_inclusive: Position,
@@ -61,13 +70,17 @@ impl IngesterShard {
         let shard_status = (shard_state, replication_position_inclusive.clone());
         let (shard_status_tx, shard_status_rx) = watch::channel(shard_status);
         Self {
-            shard_type: IngesterShardType::Primary { follower_id },
+            shard_type: IngesterShardType::Primary {
+                replication_factor: follower_ids.len() + 1,
+                follower_ids,
+            },
             shard_state,
             replication_position_inclusive,
             truncation_position_inclusive,
             shard_status_tx,
             shard_status_rx,
             last_write_instant: now,
+            dedup_ids: DedupIds::default(),
         }
     }
 
@@ -88,6 +101,7 @@ impl IngesterShard {
             shard_status_tx,
             shard_status_rx,
             last_write_instant: now,
+            dedup_ids: DedupIds::default(),
         }
     }
 
@@ -107,17 +121,66 @@ impl IngesterShard {
             shard_status_tx,
             shard_status_rx,
             last_write_instant: now,
+            dedup_ids: DedupIds::default(),
         }
     }
 
-    pub fn follower_id_opt(&self) -> Option<&NodeId> {
+    pub fn follower_ids(&self) -> &[NodeId] {
         match &self.shard_type {
-            IngesterShardType::Primary { follower_id } => Some(follower_id),
-            IngesterShardType::Replica { .. } => None,
-            IngesterShardType::Solo => None,
+            IngesterShardType::Primary { follower_ids, .. } => follower_ids,
+            IngesterShardType::Replica { .. } => &[],
+            IngesterShardType::Solo => &[],
+        }
+    }
+
+    /// Returns the minimum number of copies, including the primary one, that must be written for a
+    /// persist request to succeed.
+    pub fn write_quorum(&self) -> usize {
+        match &self.shard_type {
+            IngesterShardType::Primary {
+                replication_factor, ..
+            } => replication_factor / 2 + 1,
+            IngesterShardType::Replica { .. } => 1,
+            IngesterShardType::Solo => 1,
+        }
+    }
+
+    /// Stops replicating the shard to the given follower, for instance because it failed to
+    /// replicate a batch that was otherwise persisted by a quorum of ingesters and is now out of
+    /// sync.
+    pub fn remove_follower(&mut self, follower_id: &NodeId) {
+        if let IngesterShardType::Primary { follower_ids, .. } = &mut self.shard_type {
+            follower_ids.retain(|node_id| node_id != follower_id);
+        }
+    }
+
+    /// Makes a replica shard follow a new leader after the control plane promoted another follower
+    /// of the shard.
+    pub fn set_leader_id(&mut self, new_leader_id: NodeId) {
+        if let IngesterShardType::Replica { leader_id } = &mut self.shard_type {
+            *leader_id = new_leader_id;
         }
     }
 
+    /// Promotes a replica shard to a primary shard replicated on the given followers, or to a solo
+    /// shard if there are none left.
+    pub fn promote(&mut self, follower_ids: Vec<NodeId>) {
+        self.shard_type = if follower_ids.is_empty() {
+            IngesterShardType::Solo
+        } else {
+            IngesterShardType::Primary {
+                replication_factor: follower_ids.len() + 1,
+                follower_ids,
+            }
+        };
+    }
+
+    /// Turns a shard into a replica of the given leader after it was handed off to another
+    /// ingester.
+    pub fn demote(&mut self, leader_id: NodeId) {
+        self.shard_type = IngesterShardType::Replica { leader_id };
+    }
+
     pub fn close(&mut self) {
         self.shard_state = ShardState::Closed;
         self.notify_shard_status();
@@ -220,7 +283,7 @@ mod tests {
     #[test]
     fn test_new_primary_shard() {
         let primary_shard = IngesterShard::new_primary(
-            "test-follower".into(),
+            vec!["test-follower-1".into(), "test-follower-2".into()],
             ShardState::Closed,
             Position::offset(42u64),
             Position::Beginning,
@@ -228,9 +291,11 @@ mod tests {
         );
         assert!(matches!(
             &primary_shard.shard_type,
-            IngesterShardType::Primary { follower_id } if *follower_id == "test-follower"
+            IngesterShardType::Primary { follower_ids, replication_factor: 3 }
+                if *follower_ids == ["test-follower-1", "test-follower-2"]
         ));
         assert!(!primary_shard.is_replica());
+        assert_eq!(primary_shard.write_quorum(), 2);
         assert_eq!(primary_shard.shard_state, ShardState::Closed);
         assert_eq!(
             primary_shard.replication_position_inclusive,
@@ -242,6 +307,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_primary_shard_remove_follower() {
+        let mut primary_shard = IngesterShard::new_primary(
+            vec!["test-follower-1".into(), "test-follower-2".into()],
+            ShardState::Open,
+            Position::Beginning,
+            Position::Beginning,
+            Instant::now(),
+        );
+        primary_shard.remove_follower(&"test-follower-1".into());
+        assert_eq!(primary_shard.follower_ids(), ["test-follower-2"]);
+        // The write quorum does not change when a follower is removed.
+        assert_eq!(primary_shard.write_quorum(), 2);
+    }
+
+    #[test]
+    fn test_promote_replica_shard() {
+        let mut replica_shard = IngesterShard::new_replica(
+            "test-leader".into(),
+            ShardState::Open,
+            Position::offset(42u64),
+            Position::Beginning,
+            Instant::now(),
+        );
+        assert_eq!(replica_shard.write_quorum(), 1);
+
+        replica_shard.promote(vec!["test-follower".into()]);
+        replica_shard.assert_is_primary();
+        assert_eq!(replica_shard.follower_ids(), ["test-follower"]);
+        assert_eq!(replica_shard.write_quorum(), 2);
+        assert_eq!(
+            replica_shard.replication_position_inclusive,
+            Position::offset(42u64)
+        );
+
+        let mut replica_shard = IngesterShard::new_replica(
+            "test-leader".into(),
+            ShardState::Open,
+            Position::offset(42u64),
+            Position::Beginning,
+            Instant::now(),
+        );
+        replica_shard.promote(Vec::new());
+        replica_shard.assert_is_solo();
+    }
+
+    #[test]
+    fn test_demote_shard() {
+        let mut solo_shard = IngesterShard::new_solo(
+            ShardState::Closed,
+            Position::offset(42u64),
+            Position::Beginning,
+            Instant::now(),
+        );
+        solo_shard.demote("test-new-leader".into());
+        assert!(matches!(
+            &solo_shard.shard_type,
+            IngesterShardType::Replica { leader_id } if *leader_id == "test-new-leader"
+        ));
+        assert_eq!(
+            solo_shard.replication_position_inclusive,
+            Position::offset(42u64)
+        );
+    }
+
     #[test]
     fn test_new_replica_shard() {
         let replica_shard = IngesterShard::new_replica(
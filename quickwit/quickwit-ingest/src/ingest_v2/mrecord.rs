@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use quickwit_proto::ingest::MRecordBatch;
 use tracing::warn;
 
@@ -38,10 +38,33 @@ const DOC_HEADER_V0: &[u8; MRECORD_HEADER_LEN] = &[HeaderVersion::V0 as u8, 0];
 /// `Commit` header v0 composed of the header version and the `Commit = 1` record type.
 const COMMIT_HEADER_V0: &[u8; MRECORD_HEADER_LEN] = &[HeaderVersion::V0 as u8, 1];
 
+/// `DedupId` header v0 composed of the header version and the `DedupId = 2` record type.
+pub(super) const DEDUP_ID_HEADER_V0: &[u8; MRECORD_HEADER_LEN] = &[HeaderVersion::V0 as u8, 2];
+
+/// `CompressedDocs` header v0 composed of the header version and the `CompressedDocs = 3` record
+/// type.
+pub(super) const COMPRESSED_DOCS_HEADER_V0: &[u8; MRECORD_HEADER_LEN] =
+    &[HeaderVersion::V0 as u8, 3];
+
+/// Maximum size of the documents of a `CompressedDocs` record once decompressed. The records are
+/// built from persist requests, which are smaller than a gRPC message, so larger records can only
+/// be corrupted.
+pub(super) const MAX_DECOMPRESSED_DOCS_NUM_BYTES: usize = 256 * 1024 * 1024;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum MRecord {
     Doc(Bytes),
     Commit,
+    /// Marks the end of a batch persisted with a client-supplied dedup ID. The timestamp,
+    /// expressed in seconds since the Unix epoch, records when the batch was persisted so that
+    /// the dedup window can be restored after a restart.
+    DedupId {
+        dedup_id: String,
+        timestamp: u64,
+    },
+    /// A document batch encoded with protobuf and compressed with zstd. These records only live
+    /// in the WAL: fetch tasks decompress them into `Doc` records.
+    CompressedDocs(Bytes),
 }
 
 impl MRecord {
@@ -49,6 +72,16 @@ impl MRecord {
         match &self {
             Self::Doc(doc) => DOC_HEADER_V0.chain(doc.clone()),
             Self::Commit => COMMIT_HEADER_V0.chain(Bytes::new()),
+            Self::DedupId {
+                dedup_id,
+                timestamp,
+            } => {
+                let mut payload = BytesMut::with_capacity(8 + dedup_id.len());
+                payload.put_u64(*timestamp);
+                payload.put_slice(dedup_id.as_bytes());
+                DEDUP_ID_HEADER_V0.chain(payload.freeze())
+            }
+            Self::CompressedDocs(payload) => COMPRESSED_DOCS_HEADER_V0.chain(payload.clone()),
         }
     }
 
@@ -70,6 +103,26 @@ impl MRecord {
                 Self::Doc(doc)
             }
             1 => Self::Commit,
+            2 => {
+                if buf.remaining() < 8 {
+                    warn!("invalid dedup ID mrecord");
+                    return None;
+                }
+                let timestamp = buf.get_u64();
+                let dedup_id_bytes = buf.copy_to_bytes(buf.remaining());
+                let Ok(dedup_id) = String::from_utf8(dedup_id_bytes.to_vec()) else {
+                    warn!("invalid dedup ID mrecord");
+                    return None;
+                };
+                Self::DedupId {
+                    dedup_id,
+                    timestamp,
+                }
+            }
+            3 => {
+                let payload = buf.copy_to_bytes(buf.remaining());
+                Self::CompressedDocs(payload)
+            }
             other => {
                 warn!("unknown mrecord type `{other}`");
                 return None;
@@ -90,6 +143,8 @@ pub fn decoded_mrecords(mrecord_batch: &MRecordBatch) -> impl Iterator<Item = MR
 
 #[cfg(test)]
 mod tests {
+    use quickwit_proto::ingest::DocBatchV2;
+
     use super::*;
 
     #[test]
@@ -115,4 +170,28 @@ mod tests {
         let decoded_record = MRecord::decode(encoded_record).unwrap();
         assert_eq!(record, decoded_record);
     }
+
+    #[test]
+    fn test_mrecord_dedup_id_roundtrip() {
+        let record = MRecord::DedupId {
+            dedup_id: "test-dedup-id".to_string(),
+            timestamp: 1_700_000_000,
+        };
+        let encoded_record = record.encode();
+        let decoded_record = MRecord::decode(encoded_record).unwrap();
+        assert_eq!(record, decoded_record);
+
+        assert!(MRecord::decode(&[HeaderVersion::V0 as u8, 2u8, 0u8][..]).is_none());
+    }
+
+    #[test]
+    fn test_mrecord_compressed_docs_roundtrip() {
+        let compressed_doc_batch = DocBatchV2::for_test(["test-doc-foo", "test-doc-bar"])
+            .compress(3)
+            .unwrap();
+        let record = MRecord::CompressedDocs(compressed_doc_batch.payload);
+        let encoded_record = record.encode();
+        let decoded_record = MRecord::decode(encoded_record).unwrap();
+        assert_eq!(record, decoded_record);
+    }
 }
@@ -17,20 +17,88 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::io;
-use std::iter::once;
 use std::ops::RangeInclusive;
+use std::{io, iter};
 
+use bytes::Bytes;
 use bytesize::ByteSize;
 #[cfg(feature = "failpoints")]
 use fail::fail_point;
+use itertools::Either;
 use mrecordlog::error::{AppendError, DeleteQueueError};
-use quickwit_proto::ingest::DocBatchV2;
+use quickwit_proto::ingest::{CompressedDocBatch, DocBatchV2, MRecordBatch};
 use quickwit_proto::types::{Position, QueueId};
 
+use super::dedup::{unix_timestamp_secs, DedupIds};
+use super::estimate_size;
+use super::mrecord::{DEDUP_ID_HEADER_V0, MRECORD_HEADER_LEN};
 use crate::mrecordlog_async::MultiRecordLogAsync;
 use crate::MRecord;
 
+/// A document batch as it is written to the WAL, either as is or compressed into a single record.
+#[derive(Debug, Clone)]
+pub(super) enum WalDocBatch {
+    Uncompressed(DocBatchV2),
+    Compressed(CompressedDocBatch),
+}
+
+impl WalDocBatch {
+    /// Builds a [`WalDocBatch`] from the fields of a replicate subrequest. Returns `None` if the
+    /// batch is absent or empty.
+    pub fn from_parts(
+        doc_batch_opt: Option<DocBatchV2>,
+        compressed_doc_batch_opt: Option<CompressedDocBatch>,
+    ) -> Option<Self> {
+        if let Some(compressed_doc_batch) = compressed_doc_batch_opt {
+            return (!compressed_doc_batch.is_empty())
+                .then_some(Self::Compressed(compressed_doc_batch));
+        }
+        doc_batch_opt
+            .filter(|doc_batch| !doc_batch.is_empty())
+            .map(Self::Uncompressed)
+    }
+
+    pub fn num_docs(&self) -> usize {
+        match self {
+            Self::Uncompressed(doc_batch) => doc_batch.num_docs(),
+            Self::Compressed(compressed_doc_batch) => compressed_doc_batch.num_docs as usize,
+        }
+    }
+
+    /// Returns the number of bytes of the documents before compression.
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            Self::Uncompressed(doc_batch) => doc_batch.num_bytes(),
+            Self::Compressed(compressed_doc_batch) => compressed_doc_batch.num_bytes as usize,
+        }
+    }
+
+    /// Estimates the size of the batch once written to the WAL.
+    pub fn estimate_size(&self) -> ByteSize {
+        match self {
+            Self::Uncompressed(doc_batch) => estimate_size(doc_batch),
+            Self::Compressed(compressed_doc_batch) => {
+                ByteSize((compressed_doc_batch.payload.len() + MRECORD_HEADER_LEN) as u64)
+            }
+        }
+    }
+
+    /// Splits the batch into the `doc_batch` and `compressed_doc_batch` fields of a replicate
+    /// subrequest.
+    pub fn into_parts(self) -> (Option<DocBatchV2>, Option<CompressedDocBatch>) {
+        match self {
+            Self::Uncompressed(doc_batch) => (Some(doc_batch), None),
+            Self::Compressed(compressed_doc_batch) => (None, Some(compressed_doc_batch)),
+        }
+    }
+}
+
+impl From<DocBatchV2> for WalDocBatch {
+    fn from(doc_batch: DocBatchV2) -> Self {
+        Self::Uncompressed(doc_batch)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub(super) enum AppendDocBatchError {
     #[error("IO error: {0}")]
@@ -39,7 +107,10 @@ pub(super) enum AppendDocBatchError {
     QueueNotFound(QueueId),
 }
 
-/// Appends a non-empty document batch to the WAL queue `queue_id`.
+/// Appends a non-empty document batch to the WAL queue `queue_id`. A compressed batch is written
+/// as a single `CompressedDocs` record. When `dedup_id_opt` is set, a `DedupId` record is appended
+/// after the documents so that the position of the batch returned by this function is also the
+/// position of the dedup ID.
 ///
 /// # Panics
 ///
@@ -47,37 +118,36 @@ pub(super) enum AppendDocBatchError {
 pub(super) async fn append_non_empty_doc_batch(
     mrecordlog: &mut MultiRecordLogAsync,
     queue_id: &QueueId,
-    doc_batch: DocBatchV2,
+    doc_batch: impl Into<WalDocBatch>,
     force_commit: bool,
+    dedup_id_opt: Option<String>,
 ) -> Result<Position, AppendDocBatchError> {
-    let append_result = if force_commit {
-        let encoded_mrecords = doc_batch
-            .docs()
-            .map(|doc| MRecord::Doc(doc).encode())
-            .chain(once(MRecord::Commit.encode()));
-
-        #[cfg(feature = "failpoints")]
-        fail_point!("ingester:append_records", |_| {
-            let io_error = io::Error::from(io::ErrorKind::PermissionDenied);
-            Err(AppendDocBatchError::Io(io_error))
-        });
+    let commit_opt = force_commit.then_some(MRecord::Commit);
+    let dedup_id_record_opt = dedup_id_opt.map(|dedup_id| MRecord::DedupId {
+        dedup_id,
+        timestamp: unix_timestamp_secs(),
+    });
+    let doc_mrecords = match doc_batch.into() {
+        WalDocBatch::Uncompressed(doc_batch) => Either::Left(doc_batch.docs().map(MRecord::Doc)),
+        WalDocBatch::Compressed(compressed_doc_batch) => Either::Right(iter::once(
+            MRecord::CompressedDocs(compressed_doc_batch.payload),
+        )),
+    };
+    let encoded_mrecords = doc_mrecords
+        .chain(commit_opt)
+        .chain(dedup_id_record_opt)
+        .map(|mrecord| mrecord.encode());
 
-        mrecordlog
-            .append_records(queue_id, None, encoded_mrecords)
-            .await
-    } else {
-        let encoded_mrecords = doc_batch.docs().map(|doc| MRecord::Doc(doc).encode());
+    #[cfg(feature = "failpoints")]
+    fail_point!("ingester:append_records", |_| {
+        let io_error = io::Error::from(io::ErrorKind::PermissionDenied);
+        Err(AppendDocBatchError::Io(io_error))
+    });
 
-        #[cfg(feature = "failpoints")]
-        fail_point!("ingester:append_records", |_| {
-            let io_error = io::Error::from(io::ErrorKind::PermissionDenied);
-            Err(AppendDocBatchError::Io(io_error))
-        });
+    let append_result = mrecordlog
+        .append_records(queue_id, None, encoded_mrecords)
+        .await;
 
-        mrecordlog
-            .append_records(queue_id, None, encoded_mrecords)
-            .await
-    };
     match append_result {
         Ok(Some(offset)) => Ok(Position::offset(offset)),
         Ok(None) => panic!("`doc_batch` should not be empty"),
@@ -91,6 +161,43 @@ pub(super) async fn append_non_empty_doc_batch(
     }
 }
 
+/// Appends a non-empty batch of encoded records to the WAL queue `queue_id` as is, starting right
+/// after `from_position_exclusive`. This is used to copy the tail of a shard to another ingester,
+/// which must keep the positions of the records.
+///
+/// # Panics
+///
+/// Panics if `mrecord_batch` is empty or if `from_position_exclusive` is behind the last record of
+/// the queue.
+pub(super) async fn append_non_empty_mrecord_batch(
+    mrecordlog: &mut MultiRecordLogAsync,
+    queue_id: &QueueId,
+    from_position_exclusive: &Position,
+    mrecord_batch: MRecordBatch,
+) -> Result<Position, AppendDocBatchError> {
+    let start_position = from_position_exclusive
+        .as_u64()
+        .map(|position| position + 1)
+        .unwrap_or_default();
+    let encoded_mrecords: Vec<Bytes> = mrecord_batch.encoded_mrecords().collect();
+
+    let append_result = mrecordlog
+        .append_records(queue_id, Some(start_position), encoded_mrecords.into_iter())
+        .await;
+
+    match append_result {
+        Ok(Some(offset)) => Ok(Position::offset(offset)),
+        Ok(None) => panic!("`mrecord_batch` should not be empty"),
+        Err(AppendError::IoError(io_error)) => Err(AppendDocBatchError::Io(io_error)),
+        Err(AppendError::MissingQueue(queue_id)) => {
+            Err(AppendDocBatchError::QueueNotFound(queue_id))
+        }
+        Err(AppendError::Past) => {
+            panic!("`from_position_exclusive` should not be behind the last record of the queue")
+        }
+    }
+}
+
 /// Error returned when the mrecordlog does not have enough capacity to store some records.
 #[derive(Debug, Clone, Copy, thiserror::Error)]
 pub(super) enum NotEnoughCapacityError {
@@ -173,6 +280,33 @@ pub(super) fn queue_position_range(
     Some(first_position..=last_position)
 }
 
+/// Rebuilds the dedup IDs of a shard from the `DedupId` records stored in its WAL queue.
+///
+/// Only the records that have not been truncated yet are recovered: the dedup IDs of batches
+/// already indexed and published when the ingester restarts are lost, even if they were persisted
+/// within the dedup window.
+pub(super) fn recover_dedup_ids(mrecordlog: &MultiRecordLogAsync, queue_id: &QueueId) -> DedupIds {
+    let mut dedup_ids = DedupIds::default();
+
+    let Ok(records) = mrecordlog.range(queue_id, ..) else {
+        return dedup_ids;
+    };
+    for record in records {
+        // Avoid decoding (and copying) the documents.
+        if !record.payload.starts_with(DEDUP_ID_HEADER_V0) {
+            continue;
+        }
+        if let Some(MRecord::DedupId {
+            dedup_id,
+            timestamp,
+        }) = MRecord::decode(&record.payload[..])
+        {
+            dedup_ids.insert(dedup_id, Position::offset(record.position), timestamp);
+        }
+    }
+    dedup_ids
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -186,7 +320,7 @@ mod tests {
         let doc_batch = DocBatchV2::for_test(["test-doc-foo"]);
 
         let append_error =
-            append_non_empty_doc_batch(&mut mrecordlog, &queue_id, doc_batch.clone(), false)
+            append_non_empty_doc_batch(&mut mrecordlog, &queue_id, doc_batch.clone(), false, None)
                 .await
                 .unwrap_err();
 
@@ -198,16 +332,182 @@ mod tests {
         mrecordlog.create_queue(&queue_id).await.unwrap();
 
         let position =
-            append_non_empty_doc_batch(&mut mrecordlog, &queue_id, doc_batch.clone(), false)
+            append_non_empty_doc_batch(&mut mrecordlog, &queue_id, doc_batch.clone(), false, None)
                 .await
                 .unwrap();
         assert_eq!(position, Position::offset(0u64));
 
         let position =
-            append_non_empty_doc_batch(&mut mrecordlog, &queue_id, doc_batch.clone(), true)
+            append_non_empty_doc_batch(&mut mrecordlog, &queue_id, doc_batch.clone(), true, None)
                 .await
                 .unwrap();
         assert_eq!(position, Position::offset(2u64));
+
+        let position = append_non_empty_doc_batch(
+            &mut mrecordlog,
+            &queue_id,
+            doc_batch.clone(),
+            false,
+            Some("test-dedup-id".to_string()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(position, Position::offset(4u64));
+    }
+
+    #[tokio::test]
+    async fn test_append_non_empty_mrecord_batch() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut mrecordlog = MultiRecordLogAsync::open(tempdir.path()).await.unwrap();
+
+        let queue_id = "test-queue".to_string();
+        let mrecord_batch = MRecordBatch::for_test(["test-doc-foo", "test-doc-bar"]).unwrap();
+
+        let append_error = append_non_empty_mrecord_batch(
+            &mut mrecordlog,
+            &queue_id,
+            &Position::Beginning,
+            mrecord_batch.clone(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(
+            append_error,
+            AppendDocBatchError::QueueNotFound(..)
+        ));
+
+        mrecordlog.create_queue(&queue_id).await.unwrap();
+
+        let position = append_non_empty_mrecord_batch(
+            &mut mrecordlog,
+            &queue_id,
+            &Position::Beginning,
+            mrecord_batch.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(position, Position::offset(1u64));
+
+        let position = append_non_empty_mrecord_batch(
+            &mut mrecordlog,
+            &queue_id,
+            &Position::offset(1u64),
+            mrecord_batch,
+        )
+        .await
+        .unwrap();
+        assert_eq!(position, Position::offset(3u64));
+
+        mrecordlog.assert_records_eq(
+            &queue_id,
+            ..,
+            &[
+                (0, "test-doc-foo"),
+                (1, "test-doc-bar"),
+                (2, "test-doc-foo"),
+                (3, "test-doc-bar"),
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_append_non_empty_compressed_doc_batch() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut mrecordlog = MultiRecordLogAsync::open(tempdir.path()).await.unwrap();
+
+        let queue_id = "test-queue".to_string();
+        mrecordlog.create_queue(&queue_id).await.unwrap();
+
+        let doc_batch = DocBatchV2::for_test(["test-doc-foo", "test-doc-bar"]);
+        let compressed_doc_batch = doc_batch.compress(3).unwrap();
+        let wal_doc_batch = WalDocBatch::Compressed(compressed_doc_batch.clone());
+
+        assert_eq!(wal_doc_batch.num_docs(), 2);
+        assert_eq!(wal_doc_batch.num_bytes(), 24);
+        assert_eq!(
+            wal_doc_batch.estimate_size(),
+            ByteSize((compressed_doc_batch.payload.len() + MRECORD_HEADER_LEN) as u64)
+        );
+        let position =
+            append_non_empty_doc_batch(&mut mrecordlog, &queue_id, wal_doc_batch, true, None)
+                .await
+                .unwrap();
+        assert_eq!(position, Position::offset(1u64));
+
+        let mrecords: Vec<MRecord> = mrecordlog
+            .range(&queue_id, ..)
+            .unwrap()
+            .flat_map(|record| MRecord::decode(&record.payload[..]))
+            .collect();
+        assert_eq!(
+            mrecords,
+            [
+                MRecord::CompressedDocs(compressed_doc_batch.payload),
+                MRecord::Commit
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wal_doc_batch_from_parts() {
+        assert!(WalDocBatch::from_parts(None, None).is_none());
+        assert!(WalDocBatch::from_parts(Some(DocBatchV2::default()), None).is_none());
+        assert!(WalDocBatch::from_parts(None, Some(CompressedDocBatch::default())).is_none());
+
+        let doc_batch = DocBatchV2::for_test(["test-doc-foo"]);
+        let wal_doc_batch = WalDocBatch::from_parts(Some(doc_batch.clone()), None).unwrap();
+        assert!(matches!(wal_doc_batch, WalDocBatch::Uncompressed(_)));
+
+        let compressed_doc_batch = doc_batch.compress(3).unwrap();
+        let wal_doc_batch = WalDocBatch::from_parts(None, Some(compressed_doc_batch)).unwrap();
+        assert!(matches!(wal_doc_batch, WalDocBatch::Compressed(_)));
+    }
+
+    #[tokio::test]
+    async fn test_recover_dedup_ids() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut mrecordlog = MultiRecordLogAsync::open(tempdir.path()).await.unwrap();
+
+        let queue_id = "test-queue".to_string();
+        let dedup_ids = recover_dedup_ids(&mrecordlog, &queue_id);
+        assert!(dedup_ids.get("test-dedup-id-foo").is_none());
+
+        mrecordlog.create_queue(&queue_id).await.unwrap();
+
+        let doc_batch = DocBatchV2::for_test(["test-doc-foo", "test-doc-bar"]);
+        append_non_empty_doc_batch(
+            &mut mrecordlog,
+            &queue_id,
+            doc_batch.clone(),
+            true,
+            Some("test-dedup-id-foo".to_string()),
+        )
+        .await
+        .unwrap();
+        append_non_empty_doc_batch(&mut mrecordlog, &queue_id, doc_batch.clone(), false, None)
+            .await
+            .unwrap();
+        append_non_empty_doc_batch(
+            &mut mrecordlog,
+            &queue_id,
+            doc_batch,
+            false,
+            Some("test-dedup-id-bar".to_string()),
+        )
+        .await
+        .unwrap();
+
+        let dedup_ids = recover_dedup_ids(&mrecordlog, &queue_id);
+        assert_eq!(dedup_ids.len(), 2);
+        assert_eq!(
+            dedup_ids.get("test-dedup-id-foo"),
+            Some(&Position::offset(3u64))
+        );
+        assert_eq!(
+            dedup_ids.get("test-dedup-id-bar"),
+            Some(&Position::offset(8u64))
+        );
     }
 
     // This test should be run manually and independently of other tests with the `failpoints`
@@ -228,9 +528,10 @@ mod tests {
         mrecordlog.create_queue(&queue_id).await.unwrap();
 
         let doc_batch = DocBatchV2::for_test(["test-doc-foo"]);
-        let append_error = append_non_empty_doc_batch(&mut mrecordlog, &queue_id, doc_batch, false)
-            .await
-            .unwrap_err();
+        let append_error =
+            append_non_empty_doc_batch(&mut mrecordlog, &queue_id, doc_batch, false, None)
+                .await
+                .unwrap_err();
 
         assert!(matches!(append_error, AppendDocBatchError::Io(..)));
 
@@ -0,0 +1,325 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, VecDeque};
+
+use quickwit_proto::types::{IndexUid, Position, ShardId, SourceId, SourceUid, SplitId};
+use tokio::sync::oneshot;
+
+/// Publish position of a shard along with the IDs of the splits whose publication advanced it.
+pub(super) type PublishedPosition = (Position, Vec<SplitId>);
+
+/// Number of publications recorded per shard, used to resolve the requests whose documents were
+/// already published when they start waiting.
+const MAX_RECORDED_PUBLICATIONS_PER_SHARD: usize = 16;
+
+struct PublishWaiter {
+    target_position: Position,
+    // Splits published since the waiter was registered.
+    published_split_ids: Vec<SplitId>,
+    published_position_tx: oneshot::Sender<PublishedPosition>,
+}
+
+/// Keeps track of the publish positions of the shards of the cluster, as reported by the
+/// `ShardPositionsUpdate` events, and notifies the ingest requests with a `wait_for` or `force`
+/// commit type once the documents they persisted are published.
+#[derive(Default)]
+pub(super) struct PublishTracker {
+    // Latest publications of each shard, in increasing publish position order.
+    published_positions: HashMap<(SourceUid, ShardId), VecDeque<PublishedPosition>>,
+    waiters: HashMap<(SourceUid, ShardId), Vec<PublishWaiter>>,
+}
+
+impl PublishTracker {
+    /// Returns a receiver that resolves once the publish position of the shard reaches
+    /// `target_position`, or the shard reaches EOF, with the IDs of the splits published since the
+    /// call. If the target position was already reached, the receiver resolves immediately with
+    /// the IDs of the splits of the publication that reached it, provided it is still recorded.
+    pub fn wait_for_position(
+        &mut self,
+        source_uid: SourceUid,
+        shard_id: ShardId,
+        target_position: Position,
+    ) -> oneshot::Receiver<PublishedPosition> {
+        let (published_position_tx, published_position_rx) = oneshot::channel();
+        let key = (source_uid, shard_id);
+
+        if let Some(publications) = self.published_positions.get(&key) {
+            let is_target_reached = publications
+                .back()
+                .map(|(published_position, _)| *published_position >= target_position)
+                .unwrap_or(false);
+
+            if is_target_reached {
+                let publication = publications
+                    .iter()
+                    .find(|(published_position, _)| *published_position >= target_position)
+                    .expect("the last publication should reach the target position");
+                let (published_position, mut published_split_ids) = publication.clone();
+
+                if publications.len() == MAX_RECORDED_PUBLICATIONS_PER_SHARD
+                    && publications.front() == Some(publication)
+                {
+                    // Older publications were evicted and may have reached the target position
+                    // already: the splits holding the documents are unknown.
+                    published_split_ids.clear();
+                }
+                let _ = published_position_tx.send((published_position, published_split_ids));
+                return published_position_rx;
+            }
+        }
+        let waiters = self.waiters.entry(key).or_default();
+        // Drop the waiters of the requests that timed out in the meantime.
+        waiters.retain(|waiter| !waiter.published_position_tx.is_closed());
+        waiters.push(PublishWaiter {
+            target_position,
+            published_split_ids: Vec::new(),
+            published_position_tx,
+        });
+        published_position_rx
+    }
+
+    /// Records the new publish positions of the shards of a source and notifies the waiters whose
+    /// target position was reached.
+    pub fn apply_update(
+        &mut self,
+        source_uid: &SourceUid,
+        updated_shard_positions: &[(ShardId, Position)],
+        published_split_ids: &[SplitId],
+    ) {
+        for (shard_id, position) in updated_shard_positions {
+            let key = (source_uid.clone(), shard_id.clone());
+
+            if let Some(waiters) = self.waiters.remove(&key) {
+                let mut pending_waiters = Vec::with_capacity(waiters.len());
+
+                for mut waiter in waiters {
+                    waiter
+                        .published_split_ids
+                        .extend(published_split_ids.iter().cloned());
+
+                    if *position >= waiter.target_position {
+                        let _ = waiter
+                            .published_position_tx
+                            .send((position.clone(), waiter.published_split_ids));
+                    } else if !waiter.published_position_tx.is_closed() {
+                        pending_waiters.push(waiter);
+                    }
+                }
+                if !pending_waiters.is_empty() {
+                    self.waiters.insert(key.clone(), pending_waiters);
+                }
+            }
+            if position.is_eof() {
+                // No more documents will be persisted in the shard.
+                self.published_positions.remove(&key);
+            } else {
+                let publications = self.published_positions.entry(key).or_default();
+
+                if publications.len() == MAX_RECORDED_PUBLICATIONS_PER_SHARD {
+                    publications.pop_front();
+                }
+                publications.push_back((position.clone(), published_split_ids.to_vec()));
+            }
+        }
+    }
+
+    /// Forgets the publications of deleted shards. Their waiters are dropped, which resolves their
+    /// receivers with an error.
+    pub fn remove_shards(&mut self, source_uid: &SourceUid, shard_ids: &[ShardId]) {
+        for shard_id in shard_ids {
+            let key = (source_uid.clone(), shard_id.clone());
+            self.published_positions.remove(&key);
+            self.waiters.remove(&key);
+        }
+    }
+
+    /// Forgets the publications of the shards of a deleted source.
+    pub fn remove_source(&mut self, index_uid: &IndexUid, source_id: &SourceId) {
+        self.retain(|source_uid| {
+            source_uid.index_uid != *index_uid || source_uid.source_id != *source_id
+        });
+    }
+
+    /// Forgets the publications of the shards of a deleted index.
+    pub fn remove_index(&mut self, index_uid: &IndexUid) {
+        self.retain(|source_uid| source_uid.index_uid != *index_uid);
+    }
+
+    fn retain(&mut self, predicate: impl Fn(&SourceUid) -> bool) {
+        self.published_positions
+            .retain(|(source_uid, _), _| predicate(source_uid));
+        self.waiters
+            .retain(|(source_uid, _), _| predicate(source_uid));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_tracker() {
+        let mut publish_tracker = PublishTracker::default();
+        let source_uid = SourceUid {
+            index_uid: IndexUid::for_test("test-index", 0),
+            source_id: "test-source".to_string(),
+        };
+        let mut published_position_rx_0 = publish_tracker.wait_for_position(
+            source_uid.clone(),
+            ShardId::from(1),
+            Position::offset(10u64),
+        );
+        let published_position_rx_1 = publish_tracker.wait_for_position(
+            source_uid.clone(),
+            ShardId::from(1),
+            Position::offset(5u64),
+        );
+        let published_position_rx_2 = publish_tracker.wait_for_position(
+            source_uid.clone(),
+            ShardId::from(2),
+            Position::offset(5u64),
+        );
+        publish_tracker.apply_update(
+            &source_uid,
+            &[(ShardId::from(1), Position::offset(7u64))],
+            &["test-split-foo".to_string()],
+        );
+        published_position_rx_0.try_recv().unwrap_err();
+
+        let (published_position, published_split_ids) = published_position_rx_1.await.unwrap();
+        assert_eq!(published_position, Position::offset(7u64));
+        assert_eq!(published_split_ids, ["test-split-foo"]);
+
+        publish_tracker.apply_update(
+            &source_uid,
+            &[(ShardId::from(1), Position::offset(12u64))],
+            &["test-split-bar".to_string()],
+        );
+        let (published_position, published_split_ids) = published_position_rx_0.await.unwrap();
+        assert_eq!(published_position, Position::offset(12u64));
+        assert_eq!(published_split_ids, ["test-split-foo", "test-split-bar"]);
+
+        // The target position was already reached.
+        let published_position_rx_3 = publish_tracker.wait_for_position(
+            source_uid.clone(),
+            ShardId::from(1),
+            Position::offset(11u64),
+        );
+        let (published_position, published_split_ids) = published_position_rx_3.await.unwrap();
+        assert_eq!(published_position, Position::offset(12u64));
+        assert_eq!(published_split_ids, ["test-split-bar"]);
+
+        // The target position was reached by an earlier publication.
+        let published_position_rx_4 = publish_tracker.wait_for_position(
+            source_uid.clone(),
+            ShardId::from(1),
+            Position::offset(6u64),
+        );
+        let (published_position, published_split_ids) = published_position_rx_4.await.unwrap();
+        assert_eq!(published_position, Position::offset(7u64));
+        assert_eq!(published_split_ids, ["test-split-foo"]);
+
+        for offset in 13..13 + MAX_RECORDED_PUBLICATIONS_PER_SHARD as u64 {
+            publish_tracker.apply_update(
+                &source_uid,
+                &[(ShardId::from(1), Position::offset(offset))],
+                &[format!("test-split-{offset}")],
+            );
+        }
+        let published_position_rx_5 = publish_tracker.wait_for_position(
+            source_uid.clone(),
+            ShardId::from(1),
+            Position::offset(14u64),
+        );
+        let (published_position, published_split_ids) = published_position_rx_5.await.unwrap();
+        assert_eq!(published_position, Position::offset(14u64));
+        assert_eq!(published_split_ids, ["test-split-14"]);
+
+        // The publication that reached the target position was evicted.
+        let published_position_rx_6 = publish_tracker.wait_for_position(
+            source_uid.clone(),
+            ShardId::from(1),
+            Position::offset(6u64),
+        );
+        let (published_position, published_split_ids) = published_position_rx_6.await.unwrap();
+        assert_eq!(published_position, Position::offset(13u64));
+        assert!(published_split_ids.is_empty());
+
+        publish_tracker.apply_update(&source_uid, &[(ShardId::from(2), Position::eof(3u64))], &[]);
+        let (published_position, published_split_ids) = published_position_rx_2.await.unwrap();
+        assert_eq!(published_position, Position::eof(3u64));
+        assert!(published_split_ids.is_empty());
+
+        assert!(publish_tracker.waiters.is_empty());
+        assert_eq!(publish_tracker.published_positions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_publish_tracker_remove_deleted_shards() {
+        let mut publish_tracker = PublishTracker::default();
+        let index_uid_0 = IndexUid::for_test("test-index-0", 0);
+        let index_uid_1 = IndexUid::for_test("test-index-1", 0);
+
+        let source_uid_00 = SourceUid {
+            index_uid: index_uid_0.clone(),
+            source_id: "test-source-0".to_string(),
+        };
+        let source_uid_01 = SourceUid {
+            index_uid: index_uid_0.clone(),
+            source_id: "test-source-1".to_string(),
+        };
+        let source_uid_10 = SourceUid {
+            index_uid: index_uid_1.clone(),
+            source_id: "test-source-0".to_string(),
+        };
+        for source_uid in [&source_uid_00, &source_uid_01, &source_uid_10] {
+            publish_tracker.apply_update(
+                source_uid,
+                &[
+                    (ShardId::from(1), Position::offset(1u64)),
+                    (ShardId::from(2), Position::offset(1u64)),
+                ],
+                &[],
+            );
+        }
+        let published_position_rx = publish_tracker.wait_for_position(
+            source_uid_00.clone(),
+            ShardId::from(1),
+            Position::offset(2u64),
+        );
+        assert_eq!(publish_tracker.published_positions.len(), 6);
+        assert_eq!(publish_tracker.waiters.len(), 1);
+
+        publish_tracker.remove_shards(&source_uid_00, &[ShardId::from(1)]);
+        assert_eq!(publish_tracker.published_positions.len(), 5);
+        assert!(publish_tracker.waiters.is_empty());
+        published_position_rx.await.unwrap_err();
+
+        publish_tracker.remove_source(&index_uid_0, &"test-source-1".to_string());
+        assert_eq!(publish_tracker.published_positions.len(), 3);
+
+        publish_tracker.remove_index(&index_uid_0);
+        assert_eq!(publish_tracker.published_positions.len(), 2);
+
+        for key in publish_tracker.published_positions.keys() {
+            assert_eq!(key.0, source_uid_10);
+        }
+    }
+}
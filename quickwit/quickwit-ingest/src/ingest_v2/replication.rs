@@ -22,6 +22,7 @@ use std::time::{Duration, Instant};
 
 use bytesize::ByteSize;
 use futures::{Future, StreamExt};
+use itertools::Either;
 use mrecordlog::error::CreateQueueError;
 use quickwit_common::{rate_limited_warn, ServiceStream};
 use quickwit_proto::ingest::ingester::{
@@ -41,9 +42,11 @@ use super::metrics::report_wal_usage;
 use super::models::IngesterShard;
 use super::mrecordlog_utils::check_enough_capacity;
 use super::state::IngesterState;
-use crate::ingest_v2::mrecordlog_utils::{append_non_empty_doc_batch, AppendDocBatchError};
+use crate::ingest_v2::mrecordlog_utils::{
+    append_non_empty_doc_batch, append_non_empty_mrecord_batch, AppendDocBatchError, WalDocBatch,
+};
 use crate::metrics::INGEST_METRICS;
-use crate::{estimate_size, with_lock_metrics};
+use crate::with_lock_metrics;
 
 pub(super) const SYN_REPLICATION_STREAM_CAPACITY: usize = 5;
 
@@ -318,10 +321,12 @@ impl ReplicationClient {
     pub fn init_replica(
         self,
         replica_shard: Shard,
+        replication_position_inclusive: Position,
     ) -> impl Future<Output = Result<InitReplicaResponse, ReplicationError>> + Send + 'static {
         let init_replica_request = InitReplicaRequest {
             replica_shard: Some(replica_shard),
             replication_seqno: 0, // replication number are generated further down
+            replication_position_inclusive: Some(replication_position_inclusive),
         };
         let replication_request = ReplicationRequest::Init(init_replica_request);
 
@@ -456,6 +461,37 @@ impl ReplicationTask {
         let mut state_guard =
             with_lock_metrics!(self.state.lock_fully(), "init_replica", "write").await?;
 
+        if let Some(existing_replica_shard) = state_guard.shards.get_mut(&queue_id) {
+            // Another follower of the shard was promoted and asks this follower to follow it.
+            if !existing_replica_shard.is_replica() {
+                let message = format!("shard `{queue_id}` is not a replica shard");
+                return Err(IngestV2Error::Internal(message));
+            }
+            let replication_position_inclusive = init_replica_request
+                .replication_position_inclusive
+                .unwrap_or_default();
+
+            if existing_replica_shard.replication_position_inclusive
+                != replication_position_inclusive
+            {
+                warn!(
+                    "replica shard `{queue_id}` is out of sync with new leader `{}`",
+                    replica_shard.leader_id
+                );
+                let message = format!(
+                    "replica shard `{queue_id}` is at position {:?}, expected {:?}",
+                    existing_replica_shard.replication_position_inclusive,
+                    replication_position_inclusive
+                );
+                return Err(IngestV2Error::Internal(message));
+            }
+            existing_replica_shard.set_leader_id(replica_shard.leader_id.into());
+
+            let init_replica_response = InitReplicaResponse {
+                replication_seqno: init_replica_request.replication_seqno,
+            };
+            return Ok(init_replica_response);
+        }
         match state_guard.mrecordlog.create_queue(&queue_id).await {
             Ok(_) => {}
             Err(CreateQueueError::AlreadyExists) => {
@@ -469,11 +505,16 @@ impl ReplicationTask {
                 return Err(IngestV2Error::Internal(message));
             }
         };
+        // A replica shard initialized by a decommissioning leader that hands off the shard starts
+        // at the position up to which the shard has been truncated.
+        let replication_position_inclusive = init_replica_request
+            .replication_position_inclusive
+            .unwrap_or_default();
         let replica_shard = IngesterShard::new_replica(
             replica_shard.leader_id.into(),
             ShardState::Open,
-            Position::Beginning,
-            Position::Beginning,
+            replication_position_inclusive.clone(),
+            replication_position_inclusive,
             Instant::now(),
         );
         state_guard.shards.insert(queue_id, replica_shard);
@@ -579,9 +620,17 @@ impl ReplicationTask {
             if shard.replication_position_inclusive != from_position_exclusive {
                 // TODO
             }
-            let doc_batch = match subrequest.doc_batch {
-                Some(doc_batch) if !doc_batch.is_empty() => doc_batch,
-                _ => {
+            let doc_batch_opt =
+                WalDocBatch::from_parts(subrequest.doc_batch, subrequest.compressed_doc_batch);
+            let mrecord_batch_opt = subrequest
+                .mrecord_batch
+                .filter(|mrecord_batch| !mrecord_batch.is_empty());
+
+            // Batches of records handed off by a decommissioning leader are appended as is.
+            let batch = match (doc_batch_opt, mrecord_batch_opt) {
+                (_, Some(mrecord_batch)) => Either::Right(mrecord_batch),
+                (Some(doc_batch), None) => Either::Left(doc_batch),
+                (None, None) => {
                     warn!("received empty replicate request");
 
                     let replicate_success = ReplicateSuccess {
@@ -597,11 +646,32 @@ impl ReplicationTask {
                     continue;
                 }
             };
-
-            let batch_num_bytes = doc_batch.num_bytes() as u64;
-            let batch_num_docs = doc_batch.num_docs() as u64;
-
-            let requested_capacity = estimate_size(&doc_batch);
+            // The records of a handed off batch keep their positions, so the replica must be
+            // exactly in sync with the leader.
+            if batch.is_right() && shard.replication_position_inclusive != from_position_exclusive {
+                warn!(
+                    "failed to replicate records to shard `{queue_id}`: expected position {:?}, \
+                     got {:?}",
+                    shard.replication_position_inclusive, from_position_exclusive
+                );
+                let replicate_failure = ReplicateFailure {
+                    subrequest_id: subrequest.subrequest_id,
+                    index_uid: subrequest.index_uid,
+                    source_id: subrequest.source_id,
+                    shard_id: subrequest.shard_id,
+                    reason: ReplicateFailureReason::Unspecified as i32,
+                };
+                replicate_failures.push(replicate_failure);
+                continue;
+            }
+            let (batch_num_bytes, batch_num_docs, requested_capacity) = match &batch {
+                Either::Left(doc_batch) => (
+                    doc_batch.num_bytes() as u64,
+                    doc_batch.num_docs() as u64,
+                    doc_batch.estimate_size(),
+                ),
+                Either::Right(mrecord_batch) => (0, 0, mrecord_batch.estimate_size()),
+            };
 
             if let Err(error) = check_enough_capacity(
                 &state_guard.mrecordlog,
@@ -624,13 +694,27 @@ impl ReplicationTask {
                 replicate_failures.push(replicate_failure);
                 continue;
             };
-            let append_result = append_non_empty_doc_batch(
-                &mut state_guard.mrecordlog,
-                &queue_id,
-                doc_batch,
-                force_commit,
-            )
-            .await;
+            let append_result = match batch {
+                Either::Left(doc_batch) => {
+                    append_non_empty_doc_batch(
+                        &mut state_guard.mrecordlog,
+                        &queue_id,
+                        doc_batch,
+                        force_commit,
+                        subrequest.dedup_id,
+                    )
+                    .await
+                }
+                Either::Right(mrecord_batch) => {
+                    append_non_empty_mrecord_batch(
+                        &mut state_guard.mrecordlog,
+                        &queue_id,
+                        &from_position_exclusive,
+                        mrecord_batch,
+                    )
+                    .await
+                }
+            };
 
             let current_position_inclusive = match append_result {
                 Ok(current_position_inclusive) => current_position_inclusive,
@@ -763,7 +847,7 @@ impl Drop for ReplicationTaskHandle {
 mod tests {
 
     use quickwit_proto::ingest::ingester::{ReplicateSubrequest, ReplicateSuccess};
-    use quickwit_proto::ingest::{DocBatchV2, Shard};
+    use quickwit_proto::ingest::{DocBatchV2, MRecordBatch, Shard};
     use quickwit_proto::types::{queue_id, IndexUid, ShardId};
 
     use super::*;
@@ -863,7 +947,7 @@ mod tests {
         };
         let init_replica_response = replication_stream_task_handle
             .replication_client()
-            .init_replica(replica_shard)
+            .init_replica(replica_shard, Position::Beginning)
             .await
             .unwrap();
         assert_eq!(init_replica_response.replication_seqno, 0);
@@ -933,6 +1017,9 @@ mod tests {
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
                 from_position_exclusive: Some(Position::Beginning),
+                dedup_id: None,
+                compressed_doc_batch: None,
+                mrecord_batch: None,
             },
             ReplicateSubrequest {
                 subrequest_id: 1,
@@ -941,6 +1028,9 @@ mod tests {
                 shard_id: Some(ShardId::from(2)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-bar", "test-doc-baz"])),
                 from_position_exclusive: Some(Position::Beginning),
+                dedup_id: None,
+                compressed_doc_batch: None,
+                mrecord_batch: None,
             },
             ReplicateSubrequest {
                 subrequest_id: 2,
@@ -949,6 +1039,9 @@ mod tests {
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-qux", "test-doc-tux"])),
                 from_position_exclusive: Some(Position::offset(0u64)),
+                dedup_id: None,
+                compressed_doc_batch: None,
+                mrecord_batch: None,
             },
         ];
         let replicate_response = replication_stream_task_handle
@@ -1070,6 +1163,7 @@ mod tests {
                 ..Default::default()
             }),
             replication_seqno: 0,
+            replication_position_inclusive: Some(Position::Beginning),
         };
         let syn_replication_message =
             SynReplicationMessage::new_init_replica_request(init_replica_request);
@@ -1093,6 +1187,7 @@ mod tests {
                 ..Default::default()
             }),
             replication_seqno: 1,
+            replication_position_inclusive: Some(Position::Beginning),
         };
         let syn_replication_message =
             SynReplicationMessage::new_init_replica_request(init_replica_request);
@@ -1116,6 +1211,7 @@ mod tests {
                 ..Default::default()
             }),
             replication_seqno: 2,
+            replication_position_inclusive: Some(Position::Beginning),
         };
         let syn_replication_message =
             SynReplicationMessage::new_init_replica_request(init_replica_request);
@@ -1169,6 +1265,9 @@ mod tests {
                     shard_id: Some(ShardId::from(1)),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
                     from_position_exclusive: Some(Position::Beginning),
+                    dedup_id: None,
+                    compressed_doc_batch: None,
+                    mrecord_batch: None,
                 },
                 ReplicateSubrequest {
                     subrequest_id: 1,
@@ -1177,6 +1276,9 @@ mod tests {
                     shard_id: Some(ShardId::from(2)),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-bar", "test-doc-baz"])),
                     from_position_exclusive: Some(Position::Beginning),
+                    dedup_id: None,
+                    compressed_doc_batch: None,
+                    mrecord_batch: None,
                 },
                 ReplicateSubrequest {
                     subrequest_id: 2,
@@ -1185,6 +1287,9 @@ mod tests {
                     shard_id: Some(ShardId::from(1)),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-qux", "test-doc-tux"])),
                     from_position_exclusive: Some(Position::Beginning),
+                    dedup_id: None,
+                    compressed_doc_batch: None,
+                    mrecord_batch: None,
                 },
             ],
             replication_seqno: 3,
@@ -1260,6 +1365,9 @@ mod tests {
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-moo"])),
                 from_position_exclusive: Some(Position::offset(0u64)),
+                dedup_id: None,
+                compressed_doc_batch: None,
+                mrecord_batch: None,
             }],
             replication_seqno: 4,
         };
@@ -1295,6 +1403,125 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_replication_task_replicate_mrecord_batch() {
+        let leader_id: NodeId = "test-leader".into();
+        let follower_id: NodeId = "test-follower".into();
+        let (_temp_dir, state) = IngesterState::for_test().await;
+        let (syn_replication_stream_tx, syn_replication_stream) =
+            ServiceStream::new_bounded(SYN_REPLICATION_STREAM_CAPACITY);
+        let (ack_replication_stream_tx, mut ack_replication_stream) =
+            ServiceStream::new_unbounded();
+
+        let disk_capacity = ByteSize::mb(256);
+        let memory_capacity = ByteSize::mb(1);
+
+        let _replication_task_handle = ReplicationTask::spawn(
+            leader_id,
+            follower_id,
+            state.clone(),
+            syn_replication_stream,
+            ack_replication_stream_tx,
+            disk_capacity,
+            memory_capacity,
+        );
+
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+
+        // The leader hands off a shard truncated up to position 4.
+        let init_replica_request = InitReplicaRequest {
+            replica_shard: Some(Shard {
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                shard_state: ShardState::Open as i32,
+                leader_id: "test-leader".to_string(),
+                follower_id: Some("test-follower".to_string()),
+                ..Default::default()
+            }),
+            replication_seqno: 0,
+            replication_position_inclusive: Some(Position::offset(4u64)),
+        };
+        let syn_replication_message =
+            SynReplicationMessage::new_init_replica_request(init_replica_request);
+        syn_replication_stream_tx
+            .send(syn_replication_message)
+            .await
+            .unwrap();
+        let ack_replication_message = ack_replication_stream.next().await.unwrap().unwrap();
+        into_init_replica_response(ack_replication_message);
+
+        let replicate_request = ReplicateRequest {
+            leader_id: "test-leader".to_string(),
+            follower_id: "test-follower".to_string(),
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![
+                ReplicateSubrequest {
+                    subrequest_id: 0,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    doc_batch: None,
+                    from_position_exclusive: Some(Position::offset(4u64)),
+                    dedup_id: None,
+                    compressed_doc_batch: None,
+                    mrecord_batch: MRecordBatch::for_test(["test-doc-foo", "test-doc-bar"]),
+                },
+                ReplicateSubrequest {
+                    subrequest_id: 1,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    doc_batch: None,
+                    from_position_exclusive: Some(Position::offset(4u64)),
+                    dedup_id: None,
+                    compressed_doc_batch: None,
+                    mrecord_batch: MRecordBatch::for_test(["test-doc-baz"]),
+                },
+            ],
+            replication_seqno: 1,
+        };
+        let syn_replication_message =
+            SynReplicationMessage::new_replicate_request(replicate_request);
+        syn_replication_stream_tx
+            .send(syn_replication_message)
+            .await
+            .unwrap();
+        let ack_replication_message = ack_replication_stream.next().await.unwrap().unwrap();
+        let replicate_response = into_replicate_response(ack_replication_message);
+
+        assert_eq!(replicate_response.successes.len(), 1);
+        assert_eq!(replicate_response.failures.len(), 1);
+
+        let replicate_success_0 = &replicate_response.successes[0];
+        assert_eq!(replicate_success_0.subrequest_id, 0);
+        assert_eq!(
+            replicate_success_0.replication_position_inclusive(),
+            Position::offset(6u64)
+        );
+
+        // The second subrequest overlaps with the records of the first one.
+        let replicate_failure_1 = &replicate_response.failures[0];
+        assert_eq!(replicate_failure_1.subrequest_id, 1);
+        assert_eq!(
+            replicate_failure_1.reason(),
+            ReplicateFailureReason::Unspecified
+        );
+
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+
+        let state_guard = state.lock_fully().await.unwrap();
+        let replica_shard_01 = state_guard.shards.get(&queue_id_01).unwrap();
+        replica_shard_01.assert_replication_position(Position::offset(6u64));
+        replica_shard_01.assert_truncation_position(Position::offset(4u64));
+
+        state_guard.mrecordlog.assert_records_eq(
+            &queue_id_01,
+            ..,
+            &[(5, "test-doc-foo"), (6, "test-doc-bar")],
+        );
+    }
+
     #[tokio::test]
     async fn test_replication_task_shard_closed() {
         let leader_id: NodeId = "test-leader".into();
@@ -1345,6 +1572,9 @@ mod tests {
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
                 from_position_exclusive: Position::offset(0u64).into(),
+                dedup_id: None,
+                compressed_doc_batch: None,
+                mrecord_batch: None,
             }],
             replication_seqno: 0,
         };
@@ -1421,6 +1651,9 @@ mod tests {
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
                 from_position_exclusive: Position::offset(0u64).into(),
+                dedup_id: None,
+                compressed_doc_batch: None,
+                mrecord_batch: None,
             }],
             replication_seqno: 0,
         };
@@ -1516,6 +1749,9 @@ mod tests {
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
                 from_position_exclusive: Position::offset(0u64).into(),
+                dedup_id: None,
+                compressed_doc_batch: None,
+                mrecord_batch: None,
             }],
             replication_seqno: 0,
         };
@@ -1598,6 +1834,9 @@ mod tests {
                 shard_id: Some(ShardId::from(1)),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
                 from_position_exclusive: Some(Position::Beginning),
+                dedup_id: None,
+                compressed_doc_batch: None,
+                mrecord_batch: None,
             }],
             replication_seqno: 0,
         };
@@ -23,10 +23,12 @@ use std::sync::{Arc, Weak};
 use std::time::Duration;
 
 use async_trait::async_trait;
+use bytesize::ByteSize;
 use futures::stream::FuturesUnordered;
 use futures::{Future, StreamExt};
 use quickwit_common::metrics::{GaugeGuard, MEMORY_METRICS};
 use quickwit_common::pubsub::{EventBroker, EventSubscriber};
+use quickwit_doc_mapper::DocMapper;
 use quickwit_proto::control_plane::{
     ControlPlaneService, ControlPlaneServiceClient, GetOrCreateOpenShardsRequest,
     GetOrCreateOpenShardsSubrequest,
@@ -37,7 +39,10 @@ use quickwit_proto::ingest::ingester::{
 };
 use quickwit_proto::ingest::router::{IngestRequestV2, IngestResponseV2, IngestRouterService};
 use quickwit_proto::ingest::{CommitTypeV2, IngestV2Error, IngestV2Result, ShardState};
-use quickwit_proto::types::{IndexUid, NodeId, ShardId, SourceId, SubrequestId};
+use quickwit_proto::metastore::{DeleteIndexRequest, DeleteShardsRequest, DeleteSourceRequest};
+use quickwit_proto::types::{
+    IndexId, IndexUid, NodeId, ShardId, SourceId, SourceUid, SubrequestId,
+};
 use tokio::sync::{Mutex, Semaphore};
 use tracing::{error, info, warn};
 
@@ -45,7 +50,10 @@ use super::broadcast::LocalShardsUpdate;
 use super::debouncing::{
     DebouncedGetOrCreateOpenShardsRequest, GetOrCreateOpenShardsRequestDebouncer,
 };
+use super::dedup::{unix_timestamp_secs, DedupIds};
+use super::doc_mapper::{try_build_doc_mapper, validate_doc_batch};
 use super::ingester::PERSIST_REQUEST_TIMEOUT;
+use super::publish_tracker::PublishTracker;
 use super::routing_table::RoutingTable;
 use super::workbench::IngestWorkbench;
 use super::IngesterPool;
@@ -58,6 +66,14 @@ pub(super) const INGEST_REQUEST_TIMEOUT: Duration = if cfg!(any(test, feature =
     Duration::from_secs(35)
 };
 
+/// Duration after which ingest requests with a `wait_for` or `force` commit type stop waiting for
+/// the publication of their documents.
+const PUBLISH_WAIT_TIMEOUT: Duration = if cfg!(any(test, feature = "testsuite")) {
+    Duration::from_millis(100)
+} else {
+    Duration::from_secs(5 * 60)
+};
+
 const MAX_PERSIST_ATTEMPTS: usize = 5;
 
 type PersistResult = (PersistRequestSummary, IngestV2Result<PersistResponse>);
@@ -69,6 +85,10 @@ pub struct IngestRouter {
     ingester_pool: IngesterPool,
     state: Arc<Mutex<RouterState>>,
     replication_factor: usize,
+    // Whether documents are parsed against the doc mapping of their index before being persisted.
+    validate_docs: bool,
+    // Period of time during which the ingesters remember the dedup IDs of persisted batches.
+    dedup_window: Duration,
     // Limits the number of ingest requests in-flight to some capacity in bytes.
     ingest_semaphore: Arc<Semaphore>,
 }
@@ -78,6 +98,61 @@ struct RouterState {
     debouncer: GetOrCreateOpenShardsRequestDebouncer,
     // Holds the routing table mapping index and source IDs to shards.
     routing_table: RoutingTable,
+    // Holds the doc mappers used to validate documents, keyed by index and source IDs. The doc
+    // mapper is `None` when the documents of the source cannot be validated.
+    doc_mappers: HashMap<(IndexId, SourceId), (IndexUid, Option<Arc<dyn DocMapper>>)>,
+    // Notifies the ingest requests with a `wait_for` or `force` commit type once their documents
+    // are published.
+    publish_tracker: PublishTracker,
+    // Leaders the batches carrying a dedup ID were first routed to.
+    dedup_id_leaders: DedupIds<NodeId>,
+    // Maximum ingest throughput in bytes per second of the indexes with a throughput quota, as
+    // returned by the control plane.
+    max_ingest_throughputs: HashMap<IndexUid, u64>,
+    // Ingestion rate in bytes per second of the open shards of each source, as broadcast by their
+    // leaders.
+    ingestion_rates: HashMap<(NodeId, SourceUid), u64>,
+}
+
+impl RouterState {
+    /// Returns the doc mapper for a given index and source if it is known and up-to-date with the
+    /// incarnation of the index in the routing table.
+    fn find_doc_mapper(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> Option<(&IndexUid, Option<&Arc<dyn DocMapper>>)> {
+        let key = (index_id.to_string(), source_id.to_string());
+        let (index_uid, doc_mapper_opt) = self.doc_mappers.get(&key)?;
+        let entry = self.routing_table.find_entry(index_id, source_id)?;
+
+        if entry.index_uid != *index_uid {
+            return None;
+        }
+        Some((index_uid, doc_mapper_opt.as_ref()))
+    }
+
+    /// Returns whether the ingestion rate of the open shards of an index across the cluster
+    /// reaches its throughput quota. The rates broadcast by the leaders that left the cluster are
+    /// ignored.
+    fn is_throughput_quota_exceeded(
+        &self,
+        index_uid: &IndexUid,
+        ingester_pool: &IngesterPool,
+    ) -> bool {
+        let Some(max_ingest_throughput) = self.max_ingest_throughputs.get(index_uid) else {
+            return false;
+        };
+        let ingestion_rate: u64 = self
+            .ingestion_rates
+            .iter()
+            .filter(|((leader_id, source_uid), _)| {
+                source_uid.index_uid == *index_uid && ingester_pool.contains_key(leader_id)
+            })
+            .map(|(_, ingestion_rate)| ingestion_rate)
+            .sum();
+        ingestion_rate >= *max_ingest_throughput
+    }
 }
 
 impl fmt::Debug for IngestRouter {
@@ -95,6 +170,8 @@ impl IngestRouter {
         control_plane: ControlPlaneServiceClient,
         ingester_pool: IngesterPool,
         replication_factor: usize,
+        validate_docs: bool,
+        dedup_window: Duration,
     ) -> Self {
         let state = Arc::new(Mutex::new(RouterState {
             debouncer: GetOrCreateOpenShardsRequestDebouncer::default(),
@@ -102,6 +179,11 @@ impl IngestRouter {
                 self_node_id: self_node_id.clone(),
                 table: HashMap::default(),
             },
+            doc_mappers: HashMap::default(),
+            publish_tracker: PublishTracker::default(),
+            dedup_id_leaders: DedupIds::default(),
+            max_ingest_throughputs: HashMap::default(),
+            ingestion_rates: HashMap::default(),
         }));
         let ingest_semaphore_permits = get_ingest_router_buffer_size().as_u64() as usize;
         let ingest_semaphore = Arc::new(Semaphore::new(ingest_semaphore_permits));
@@ -112,6 +194,8 @@ impl IngestRouter {
             ingester_pool,
             state,
             replication_factor,
+            validate_docs,
+            dedup_window,
             ingest_semaphore,
         }
     }
@@ -122,7 +206,16 @@ impl IngestRouter {
             .subscribe::<LocalShardsUpdate>(weak_router_state.clone())
             .forever();
         event_broker
-            .subscribe::<ShardPositionsUpdate>(weak_router_state)
+            .subscribe::<ShardPositionsUpdate>(weak_router_state.clone())
+            .forever();
+        event_broker
+            .subscribe::<DeleteShardsRequest>(weak_router_state.clone())
+            .forever();
+        event_broker
+            .subscribe::<DeleteSourceRequest>(weak_router_state.clone())
+            .forever();
+        event_broker
+            .subscribe::<DeleteIndexRequest>(weak_router_state)
             .forever();
     }
 
@@ -148,13 +241,21 @@ impl IngestRouter {
                 None
             }
         }) {
-            if !state_guard.routing_table.has_open_shards(
+            let has_open_shards = state_guard.routing_table.has_open_shards(
                 &subrequest.index_id,
                 &subrequest.source_id,
                 ingester_pool,
                 &mut debounced_request.closed_shards,
                 unavailable_leaders,
-            ) {
+            );
+            // The doc mapping of the index is returned by the control plane along with the open
+            // shards, so we also reach out to it when we do not know the doc mapping yet.
+            let needs_doc_mapper = self.validate_docs
+                && state_guard
+                    .find_doc_mapper(&subrequest.index_id, &subrequest.source_id)
+                    .is_none();
+
+            if !has_open_shards || needs_doc_mapper {
                 let acquire_result = state_guard
                     .debouncer
                     .acquire(&subrequest.index_id, &subrequest.source_id);
@@ -231,8 +332,24 @@ impl IngestRouter {
         let mut state_guard = self.state.lock().await;
 
         for success in response.successes {
+            let index_uid = success.index_uid().clone();
+
+            if self.validate_docs {
+                let doc_mapper_opt = try_build_doc_mapper(&index_uid, &success.doc_mapping_json);
+                let key = (index_uid.index_id.clone(), success.source_id.clone());
+                state_guard
+                    .doc_mappers
+                    .insert(key, (index_uid.clone(), doc_mapper_opt));
+            }
+            if let Some(max_ingest_throughput) = success.max_ingest_throughput {
+                state_guard
+                    .max_ingest_throughputs
+                    .insert(index_uid.clone(), max_ingest_throughput);
+            } else {
+                state_guard.max_ingest_throughputs.remove(&index_uid);
+            }
             state_guard.routing_table.replace_shards(
-                success.index_uid().clone(),
+                index_uid,
                 success.source_id,
                 success.open_shards,
             );
@@ -312,6 +429,76 @@ impl IngestRouter {
         }
     }
 
+    /// Parses the documents of the pending subrequests that have not been validated yet against
+    /// the doc mapping of their index. Invalid documents are removed from the subrequests and
+    /// reported as parse failures.
+    async fn validate_pending_subrequests(&self, workbench: &mut IngestWorkbench) {
+        if !self.validate_docs {
+            return;
+        }
+        let mut validations = Vec::new();
+        let state_guard = self.state.lock().await;
+
+        for subworkbench in workbench.subworkbenches.values() {
+            if !subworkbench.is_pending() || subworkbench.is_validated {
+                continue;
+            }
+            let subrequest = &subworkbench.subrequest;
+
+            let Some((index_uid, doc_mapper_opt)) =
+                state_guard.find_doc_mapper(&subrequest.index_id, &subrequest.source_id)
+            else {
+                continue;
+            };
+            validations.push((
+                subrequest.subrequest_id,
+                index_uid.clone(),
+                doc_mapper_opt.cloned(),
+            ));
+        }
+        drop(state_guard);
+
+        let mut pending_validations = Vec::with_capacity(validations.len());
+
+        for (subrequest_id, index_uid, doc_mapper_opt) in validations {
+            let Some(subworkbench) = workbench.subworkbenches.get_mut(&subrequest_id) else {
+                continue;
+            };
+            // Nothing to validate: leave empty subrequests as they are.
+            let Some(doc_batch) = subworkbench.subrequest.doc_batch.take() else {
+                continue;
+            };
+            pending_validations.push((subrequest_id, index_uid, doc_batch, doc_mapper_opt));
+        }
+        if pending_validations.is_empty() {
+            return;
+        }
+        // Parsing the documents is CPU-bound.
+        let validation_results = tokio::task::spawn_blocking(move || {
+            pending_validations
+                .into_iter()
+                .map(|(subrequest_id, index_uid, doc_batch, doc_mapper_opt)| {
+                    let (doc_batch_opt, parse_failures) = match doc_mapper_opt {
+                        Some(doc_mapper) => validate_doc_batch(doc_batch, &*doc_mapper),
+                        None => (Some(doc_batch), Vec::new()),
+                    };
+                    (subrequest_id, index_uid, doc_batch_opt, parse_failures)
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .expect("doc validation task should not panic");
+
+        for (subrequest_id, index_uid, doc_batch_opt, parse_failures) in validation_results {
+            workbench.record_doc_validation(
+                subrequest_id,
+                index_uid,
+                doc_batch_opt,
+                parse_failures,
+            );
+        }
+    }
+
     async fn batch_persist(&mut self, workbench: &mut IngestWorkbench, commit_type: CommitTypeV2) {
         let debounced_request = self
             .make_get_or_create_open_shard_request(workbench, &self.ingester_pool)
@@ -320,33 +507,64 @@ impl IngestRouter {
         self.populate_routing_table_debounced(workbench, debounced_request)
             .await;
 
+        self.validate_pending_subrequests(workbench).await;
+
         // List of subrequest IDs for which no shards are available to route the subrequests to.
         let mut no_shards_available_subrequest_ids = Vec::new();
+        // List of subrequest IDs targeting an index whose throughput quota is exceeded.
+        let mut quota_exceeded_subrequest_ids = Vec::new();
 
         let mut per_leader_persist_subrequests: HashMap<&LeaderId, Vec<PersistSubrequest>> =
             HashMap::new();
 
-        let state_guard = self.state.lock().await;
+        let mut state_guard = self.state.lock().await;
+        let state = &mut *state_guard;
+
+        let now = unix_timestamp_secs();
+        state.dedup_id_leaders.evict_expired(now, self.dedup_window);
 
         // TODO: Here would be the most optimal place to split the body of the HTTP request into
         // lines, validate, transform and then pack the docs into compressed batches routed
         // to the right shards.
 
         for subrequest in workbench.pending_subrequests() {
-            let Some(shard) = state_guard
+            let entry_opt = state
                 .routing_table
-                .find_entry(&subrequest.index_id, &subrequest.source_id)
-                .and_then(|entry| entry.next_open_shard_round_robin(&self.ingester_pool))
-            else {
+                .find_entry(&subrequest.index_id, &subrequest.source_id);
+
+            if let Some(entry) = entry_opt {
+                if state.is_throughput_quota_exceeded(&entry.index_uid, &self.ingester_pool) {
+                    quota_exceeded_subrequest_ids.push(subrequest.subrequest_id);
+                    continue;
+                }
+            }
+            let Some(shard) = entry_opt.and_then(|entry| match &subrequest.dedup_id {
+                Some(dedup_id) if !dedup_id.is_empty() => entry.open_shard_for_dedup_id(
+                    &self.ingester_pool,
+                    dedup_id,
+                    state.dedup_id_leaders.get(dedup_id),
+                ),
+                _ => entry.next_open_shard_round_robin(&self.ingester_pool),
+            }) else {
                 no_shards_available_subrequest_ids.push(subrequest.subrequest_id);
                 continue;
             };
+            if let Some(dedup_id) = &subrequest.dedup_id {
+                if !dedup_id.is_empty()
+                    && state.dedup_id_leaders.get(dedup_id) != Some(&shard.leader_id)
+                {
+                    state
+                        .dedup_id_leaders
+                        .insert(dedup_id.clone(), shard.leader_id.clone(), now);
+                }
+            }
             let persist_subrequest = PersistSubrequest {
                 subrequest_id: subrequest.subrequest_id,
                 index_uid: shard.index_uid.clone().into(),
                 source_id: shard.source_id.clone(),
                 shard_id: Some(shard.shard_id.clone()),
                 doc_batch: subrequest.doc_batch.clone(),
+                dedup_id: subrequest.dedup_id.clone(),
             };
             per_leader_persist_subrequests
                 .entry(&shard.leader_id)
@@ -396,6 +614,9 @@ impl IngestRouter {
         for subrequest_id in no_shards_available_subrequest_ids {
             workbench.record_no_shards_available(subrequest_id);
         }
+        for subrequest_id in quota_exceeded_subrequest_ids {
+            workbench.record_quota_exceeded(subrequest_id);
+        }
         self.process_persist_results(workbench, persist_futures)
             .await;
     }
@@ -432,6 +653,63 @@ impl IngestRouter {
             IngestV2Error::Timeout(message)
         })
     }
+
+    /// Waits for the indexers to publish the documents of the successful subrequests and fills
+    /// their published split IDs and position. Subrequests whose documents are not published
+    /// within `timeout_duration` are returned as is.
+    async fn wait_for_publication(
+        &self,
+        ingest_response: &mut IngestResponseV2,
+        timeout_duration: Duration,
+    ) {
+        let mut published_position_futures = FuturesUnordered::new();
+
+        let mut state_guard = self.state.lock().await;
+
+        for (success_idx, success) in ingest_response.successes.iter().enumerate() {
+            let (Some(index_uid), Some(shard_id), Some(replication_position_inclusive)) = (
+                &success.index_uid,
+                &success.shard_id,
+                &success.replication_position_inclusive,
+            ) else {
+                // None of the documents of the subrequest were persisted.
+                continue;
+            };
+            let source_uid = SourceUid {
+                index_uid: index_uid.clone(),
+                source_id: success.source_id.clone(),
+            };
+            let published_position_rx = state_guard.publish_tracker.wait_for_position(
+                source_uid,
+                shard_id.clone(),
+                replication_position_inclusive.clone(),
+            );
+            published_position_futures
+                .push(async move { (success_idx, published_position_rx.await) });
+        }
+        drop(state_guard);
+
+        let wait_result = tokio::time::timeout(timeout_duration, async {
+            while let Some((success_idx, published_position_res)) =
+                published_position_futures.next().await
+            {
+                let Ok((published_position, published_split_ids)) = published_position_res else {
+                    continue;
+                };
+                let success = &mut ingest_response.successes[success_idx];
+                success.published_position_inclusive = Some(published_position);
+                success.published_split_ids = published_split_ids;
+            }
+        })
+        .await;
+
+        if wait_result.is_err() {
+            warn!(
+                "timed out after {} seconds waiting for the publication of ingested documents",
+                timeout_duration.as_secs()
+            );
+        }
+    }
 }
 
 #[async_trait]
@@ -444,14 +722,26 @@ impl IngestRouterService for IngestRouter {
 
         let mut gauge_guard = GaugeGuard::from_gauge(&MEMORY_METRICS.in_flight.ingest_router);
         gauge_guard.add(request_size_bytes as i64);
-        let _permit = self
+        let permit = self
             .ingest_semaphore
             .clone()
             .try_acquire_many_owned(request_size_bytes as u32)
             .map_err(|_| IngestV2Error::TooManyRequests)?;
 
-        self.ingest_timeout(ingest_request, INGEST_REQUEST_TIMEOUT)
-            .await
+        let commit_type = ingest_request.commit_type();
+        let mut ingest_response = self
+            .ingest_timeout(ingest_request, INGEST_REQUEST_TIMEOUT)
+            .await?;
+
+        if matches!(commit_type, CommitTypeV2::WaitFor | CommitTypeV2::Force) {
+            // The in-flight memory is released while waiting for the publication.
+            drop(permit);
+            drop(gauge_guard);
+
+            self.wait_for_publication(&mut ingest_response, PUBLISH_WAIT_TIMEOUT)
+                .await;
+        }
+        Ok(ingest_response)
     }
 }
 
@@ -470,10 +760,14 @@ impl EventSubscriber<LocalShardsUpdate> for WeakRouterState {
 
         let mut open_shard_ids: Vec<ShardId> = Vec::new();
         let mut closed_shard_ids: Vec<ShardId> = Vec::new();
+        let mut ingestion_rate: u64 = 0;
 
         for shard_info in local_shards_update.shard_infos {
             match shard_info.shard_state {
-                ShardState::Open => open_shard_ids.push(shard_info.shard_id),
+                ShardState::Open => {
+                    ingestion_rate += ByteSize::mib(shard_info.ingestion_rate.0 as u64).as_u64();
+                    open_shard_ids.push(shard_info.shard_id);
+                }
                 ShardState::Closed => closed_shard_ids.push(shard_info.shard_id),
                 ShardState::Unavailable | ShardState::Unspecified => {
                     // Ingesters never broadcast the `Unavailable`` state because, from their point
@@ -483,6 +777,20 @@ impl EventSubscriber<LocalShardsUpdate> for WeakRouterState {
         }
         let mut state_guard = state.lock().await;
 
+        let ingestion_rate_key = (
+            leader_id.clone(),
+            SourceUid {
+                index_uid: index_uid.clone(),
+                source_id: source_id.clone(),
+            },
+        );
+        if ingestion_rate > 0 {
+            state_guard
+                .ingestion_rates
+                .insert(ingestion_rate_key, ingestion_rate);
+        } else {
+            state_guard.ingestion_rates.remove(&ingestion_rate_key);
+        }
         state_guard
             .routing_table
             .close_shards(&index_uid, &source_id, &closed_shard_ids);
@@ -504,13 +812,18 @@ impl EventSubscriber<ShardPositionsUpdate> for WeakRouterState {
         };
         let mut deleted_shard_ids: Vec<ShardId> = Vec::new();
 
-        for (shard_id, shard_position) in shard_positions_update.updated_shard_positions {
+        for (shard_id, shard_position) in &shard_positions_update.updated_shard_positions {
             if shard_position.is_eof() {
-                deleted_shard_ids.push(shard_id);
+                deleted_shard_ids.push(shard_id.clone());
             }
         }
         let mut state_guard = state.lock().await;
 
+        state_guard.publish_tracker.apply_update(
+            &shard_positions_update.source_uid,
+            &shard_positions_update.updated_shard_positions,
+            &shard_positions_update.published_split_ids,
+        );
         let index_uid = shard_positions_update.source_uid.index_uid;
         let source_id = shard_positions_update.source_uid.source_id;
 
@@ -520,6 +833,58 @@ impl EventSubscriber<ShardPositionsUpdate> for WeakRouterState {
     }
 }
 
+#[async_trait]
+impl EventSubscriber<DeleteShardsRequest> for WeakRouterState {
+    async fn handle_event(&mut self, delete_shards_request: DeleteShardsRequest) {
+        // Without `force`, only the shards at EOF are deleted and their publications are already
+        // forgotten.
+        if !delete_shards_request.force {
+            return;
+        }
+        let Some(state) = self.0.upgrade() else {
+            return;
+        };
+        let source_uid = SourceUid {
+            index_uid: delete_shards_request.index_uid().clone(),
+            source_id: delete_shards_request.source_id,
+        };
+        let mut state_guard = state.lock().await;
+
+        state_guard
+            .publish_tracker
+            .remove_shards(&source_uid, &delete_shards_request.shard_ids);
+    }
+}
+
+#[async_trait]
+impl EventSubscriber<DeleteSourceRequest> for WeakRouterState {
+    async fn handle_event(&mut self, delete_source_request: DeleteSourceRequest) {
+        let Some(state) = self.0.upgrade() else {
+            return;
+        };
+        let mut state_guard = state.lock().await;
+
+        state_guard.publish_tracker.remove_source(
+            delete_source_request.index_uid(),
+            &delete_source_request.source_id,
+        );
+    }
+}
+
+#[async_trait]
+impl EventSubscriber<DeleteIndexRequest> for WeakRouterState {
+    async fn handle_event(&mut self, delete_index_request: DeleteIndexRequest) {
+        let Some(state) = self.0.upgrade() else {
+            return;
+        };
+        let mut state_guard = state.lock().await;
+
+        state_guard
+            .publish_tracker
+            .remove_index(delete_index_request.index_uid());
+    }
+}
+
 pub(super) struct PersistRequestSummary {
     pub leader_id: NodeId,
     pub subrequest_ids: Vec<SubrequestId>,
@@ -536,7 +901,7 @@ mod tests {
     use quickwit_proto::ingest::ingester::{
         IngesterServiceClient, PersistFailure, PersistResponse, PersistSuccess,
     };
-    use quickwit_proto::ingest::router::IngestSubrequest;
+    use quickwit_proto::ingest::router::{IngestSubrequest, IngestSuccess, ParseFailureReason};
     use quickwit_proto::ingest::{CommitTypeV2, DocBatchV2, Shard, ShardIds, ShardState};
     use quickwit_proto::types::{Position, SourceUid};
     use tokio::task::yield_now;
@@ -558,6 +923,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let mut workbench = IngestWorkbench::default();
         let (get_or_create_open_shard_request_opt, rendezvous) = router
@@ -749,6 +1116,8 @@ mod tests {
                                 shard_state: ShardState::Open as i32,
                                 ..Default::default()
                             }],
+                            doc_mapping_json: String::new(),
+                            max_ingest_throughput: None,
                         },
                         GetOrCreateOpenShardsSuccess {
                             subrequest_id: 1,
@@ -770,6 +1139,8 @@ mod tests {
                                     ..Default::default()
                                 },
                             ],
+                            doc_mapping_json: String::new(),
+                            max_ingest_throughput: None,
                         },
                     ],
                     failures: vec![
@@ -797,6 +1168,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let ingest_subrequests = vec![
             IngestSubrequest {
@@ -911,6 +1284,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let ingest_subrequests = vec![IngestSubrequest {
             subrequest_id: 0,
@@ -956,6 +1331,8 @@ mod tests {
                             leader_id: "test-ingester".into(),
                             ..Default::default()
                         }],
+                        doc_mapping_json: String::new(),
+                        max_ingest_throughput: None,
                     }],
                     ..Default::default()
                 };
@@ -969,6 +1346,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let ingest_subrequests = vec![IngestSubrequest {
             subrequest_id: 0,
@@ -987,6 +1366,88 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_router_batch_persist_records_quota_exceeded() {
+        let self_node_id = "test-router".into();
+        let mut control_plane_mock = ControlPlaneServiceClient::mock();
+        control_plane_mock
+            .expect_get_or_create_open_shards()
+            .once()
+            .returning(move |_request| {
+                let response = GetOrCreateOpenShardsResponse {
+                    successes: vec![GetOrCreateOpenShardsSuccess {
+                        subrequest_id: 0,
+                        index_uid: Some(IndexUid::for_test("test-index", 0)),
+                        source_id: "test-source".to_string(),
+                        open_shards: vec![Shard {
+                            index_uid: Some(IndexUid::for_test("test-index", 0)),
+                            source_id: "test-source".to_string(),
+                            shard_id: Some(ShardId::from(1)),
+                            shard_state: ShardState::Open as i32,
+                            leader_id: "test-ingester".into(),
+                            ..Default::default()
+                        }],
+                        doc_mapping_json: String::new(),
+                        max_ingest_throughput: Some(ByteSize::mib(4).as_u64()),
+                    }],
+                    ..Default::default()
+                };
+                Ok(response)
+            });
+        let control_plane: ControlPlaneServiceClient = control_plane_mock.into();
+        let ingester_pool = IngesterPool::default();
+        // The ingester mock panics if it receives a persist request.
+        let ingester = IngesterServiceClient::mock();
+        ingester_pool.insert("test-ingester".into(), ingester.into());
+
+        let replication_factor = 1;
+        let mut router = IngestRouter::new(
+            self_node_id,
+            control_plane,
+            ingester_pool.clone(),
+            replication_factor,
+            false,
+            Duration::from_secs(600),
+        );
+        let event_broker = EventBroker::default();
+        router.subscribe(&event_broker);
+
+        // The quota applies to the index: the ingestion rate of its other sources counts too.
+        let local_shards_update = LocalShardsUpdate {
+            leader_id: "test-ingester".into(),
+            source_uid: SourceUid {
+                index_uid: IndexUid::for_test("test-index", 0),
+                source_id: "other-source".to_string(),
+            },
+            shard_infos: BTreeSet::from_iter([ShardInfo {
+                shard_id: ShardId::from(1),
+                shard_state: ShardState::Open,
+                ingestion_rate: RateMibPerSec(4),
+            }]),
+        };
+        event_broker.publish(local_shards_update);
+
+        // Yield so that the event is processed.
+        yield_now().await;
+
+        let ingest_subrequests = vec![IngestSubrequest {
+            subrequest_id: 0,
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            ..Default::default()
+        }];
+        let mut workbench = IngestWorkbench::new(ingest_subrequests, 2);
+        let commit_type = CommitTypeV2::Auto;
+        router.batch_persist(&mut workbench, commit_type).await;
+
+        let subworkbench = workbench.subworkbenches.get(&0).unwrap();
+        assert!(matches!(
+            subworkbench.last_failure_opt,
+            Some(SubworkbenchFailure::QuotaExceeded)
+        ));
+        assert!(!subworkbench.is_pending());
+    }
+
     #[tokio::test]
     async fn test_router_process_persist_results_record_persist_successes() {
         let self_node_id = "test-router".into();
@@ -998,6 +1459,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let ingest_subrequests = vec![IngestSubrequest {
             subrequest_id: 0,
@@ -1049,6 +1512,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let ingest_subrequests = vec![IngestSubrequest {
             subrequest_id: 0,
@@ -1100,6 +1565,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let index_uid: IndexUid = IndexUid::for_test("test-index-0", 0);
         let mut state_guard = router.state.lock().await;
@@ -1192,6 +1659,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let ingest_subrequests = vec![
             IngestSubrequest {
@@ -1271,6 +1740,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let index_uid: IndexUid = IndexUid::for_test("test-index-0", 0);
         let index_uid2: IndexUid = IndexUid::for_test("test-index-1", 0);
@@ -1442,12 +1913,14 @@ mod tests {
                     index_id: "test-index-0".to_string(),
                     source_id: "test-source".to_string(),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-foo", "test-doc-bar"])),
+                    dedup_id: None,
                 },
                 IngestSubrequest {
                     subrequest_id: 1,
                     index_id: "test-index-1".to_string(),
                     source_id: "test-source".to_string(),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-qux"])),
+                    dedup_id: None,
                 },
             ],
             commit_type: CommitTypeV2::Auto as i32,
@@ -1461,12 +1934,14 @@ mod tests {
                     index_id: "test-index-0".to_string(),
                     source_id: "test-source".to_string(),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-moo", "test-doc-baz"])),
+                    dedup_id: None,
                 },
                 IngestSubrequest {
                     subrequest_id: 1,
                     index_id: "test-index-1".to_string(),
                     source_id: "test-source".to_string(),
                     doc_batch: Some(DocBatchV2::for_test(["test-doc-tux"])),
+                    dedup_id: None,
                 },
             ],
             commit_type: CommitTypeV2::Auto as i32,
@@ -1485,6 +1960,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let mut state_guard = router.state.lock().await;
         let index_uid: IndexUid = IndexUid::for_test("test-index-0", 0);
@@ -1575,12 +2052,155 @@ mod tests {
                 index_id: "test-index-0".to_string(),
                 source_id: "test-source".to_string(),
                 doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
+                dedup_id: None,
             }],
             commit_type: CommitTypeV2::Auto as i32,
         };
         router.ingest(ingest_request).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_router_ingest_validates_docs() {
+        let self_node_id = "test-router".into();
+        let index_uid: IndexUid = IndexUid::for_test("test-index-0", 0);
+
+        let mut control_plane_mock = ControlPlaneServiceClient::mock();
+        let index_uid_clone = index_uid.clone();
+        control_plane_mock
+            .expect_get_or_create_open_shards()
+            .once()
+            .returning(move |request| {
+                // The second subrequest targets the same source and is debounced.
+                assert_eq!(request.subrequests.len(), 1);
+
+                let doc_mapping_json = r#"{
+                    "mode": "strict",
+                    "field_mappings": [{"name": "status", "type": "u64"}]
+                }"#;
+                let success = GetOrCreateOpenShardsSuccess {
+                    subrequest_id: 0,
+                    index_uid: Some(index_uid_clone.clone()),
+                    source_id: "test-source".to_string(),
+                    open_shards: vec![Shard {
+                        index_uid: Some(index_uid_clone.clone()),
+                        source_id: "test-source".to_string(),
+                        shard_id: Some(ShardId::from(1)),
+                        shard_state: ShardState::Open as i32,
+                        leader_id: "test-ingester-0".to_string(),
+                        ..Default::default()
+                    }],
+                    doc_mapping_json: doc_mapping_json.to_string(),
+                    max_ingest_throughput: None,
+                };
+                let response = GetOrCreateOpenShardsResponse {
+                    successes: vec![success],
+                    failures: Vec::new(),
+                };
+                Ok(response)
+            });
+        let control_plane: ControlPlaneServiceClient = control_plane_mock.into();
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 1;
+        let mut router = IngestRouter::new(
+            self_node_id,
+            control_plane,
+            ingester_pool.clone(),
+            replication_factor,
+            true,
+            Duration::from_secs(600),
+        );
+        let mut ingester_mock_0 = IngesterServiceClient::mock();
+        let index_uid_clone = index_uid.clone();
+        ingester_mock_0
+            .expect_persist()
+            .once()
+            .returning(move |request| {
+                assert_eq!(request.subrequests.len(), 1);
+
+                let subrequest = &request.subrequests[0];
+                assert_eq!(subrequest.subrequest_id, 0);
+                assert_eq!(
+                    subrequest.doc_batch,
+                    Some(DocBatchV2::for_test([r#"{"status": 200}"#]))
+                );
+
+                let response = PersistResponse {
+                    leader_id: request.leader_id,
+                    successes: vec![PersistSuccess {
+                        subrequest_id: 0,
+                        index_uid: Some(index_uid_clone.clone()),
+                        source_id: "test-source".to_string(),
+                        shard_id: Some(ShardId::from(1)),
+                        replication_position_inclusive: Some(Position::offset(0u64)),
+                    }],
+                    failures: Vec::new(),
+                };
+                Ok(response)
+            });
+        let ingester_0: IngesterServiceClient = ingester_mock_0.into();
+        ingester_pool.insert("test-ingester-0".into(), ingester_0.clone());
+
+        let ingest_request = IngestRequestV2 {
+            subrequests: vec![
+                IngestSubrequest {
+                    subrequest_id: 0,
+                    index_id: "test-index-0".to_string(),
+                    source_id: "test-source".to_string(),
+                    doc_batch: Some(DocBatchV2::for_test([
+                        r#"{"status": "foo"}"#,
+                        r#"{"status": 200}"#,
+                    ])),
+                    dedup_id: None,
+                },
+                IngestSubrequest {
+                    subrequest_id: 1,
+                    index_id: "test-index-0".to_string(),
+                    source_id: "test-source".to_string(),
+                    doc_batch: Some(DocBatchV2::for_test(["not-json"])),
+                    dedup_id: None,
+                },
+            ],
+            commit_type: CommitTypeV2::Auto as i32,
+        };
+        let response = router.ingest(ingest_request).await.unwrap();
+        assert_eq!(response.successes.len(), 2);
+        assert_eq!(response.failures.len(), 0);
+
+        let success = &response.successes[0];
+        assert_eq!(success.subrequest_id, 0);
+        assert_eq!(success.shard_id(), ShardId::from(1));
+        assert_eq!(success.parse_failures.len(), 1);
+        assert_eq!(success.parse_failures[0].doc_ordinal, 0);
+        assert_eq!(
+            success.parse_failures[0].reason(),
+            ParseFailureReason::InvalidSchema
+        );
+
+        let success = &response.successes[1];
+        assert_eq!(success.subrequest_id, 1);
+        assert!(success.shard_id.is_none());
+        assert_eq!(success.parse_failures.len(), 1);
+        assert_eq!(
+            success.parse_failures[0].reason(),
+            ParseFailureReason::InvalidJson
+        );
+
+        // The doc mapper is cached, so the router does not reach out to the control plane again.
+        let ingest_request = IngestRequestV2 {
+            subrequests: vec![IngestSubrequest {
+                subrequest_id: 0,
+                index_id: "test-index-0".to_string(),
+                source_id: "test-source".to_string(),
+                doc_batch: Some(DocBatchV2::for_test(["not-json"])),
+                dedup_id: None,
+            }],
+            commit_type: CommitTypeV2::Auto as i32,
+        };
+        let response = router.ingest(ingest_request).await.unwrap();
+        assert_eq!(response.successes.len(), 1);
+        assert_eq!(response.successes[0].parse_failures.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_router_updates_routing_table_on_chitchat_events() {
         let self_node_id = "test-router".into();
@@ -1592,6 +2212,8 @@ mod tests {
             control_plane,
             ingester_pool.clone(),
             replication_factor,
+            false,
+            Duration::from_secs(600),
         );
         let event_broker = EventBroker::default();
         router.subscribe(&event_broker);
@@ -1654,6 +2276,7 @@ mod tests {
                 source_id: "test-source".to_string(),
             },
             updated_shard_positions: vec![(ShardId::from(1), Position::eof(0u64))],
+            published_split_ids: Vec::new(),
         };
         event_broker.publish(shard_positions_update);
 
@@ -1670,4 +2293,136 @@ mod tests {
         assert_eq!(shards[0].shard_id, ShardId::from(2));
         drop(state_guard);
     }
+
+    #[tokio::test]
+    async fn test_router_wait_for_publication() {
+        let self_node_id = "test-router".into();
+        let control_plane: ControlPlaneServiceClient = ControlPlaneServiceClient::mock().into();
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 1;
+        let router = IngestRouter::new(
+            self_node_id,
+            control_plane,
+            ingester_pool,
+            replication_factor,
+            false,
+            Duration::from_secs(600),
+        );
+        let event_broker = EventBroker::default();
+        router.subscribe(&event_broker);
+
+        let index_uid = IndexUid::for_test("test-index-0", 0);
+
+        let mut ingest_response = IngestResponseV2 {
+            successes: vec![
+                IngestSuccess {
+                    subrequest_id: 0,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    replication_position_inclusive: Some(Position::offset(3u64)),
+                    ..Default::default()
+                },
+                IngestSuccess {
+                    subrequest_id: 1,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(2)),
+                    replication_position_inclusive: Some(Position::offset(5u64)),
+                    ..Default::default()
+                },
+            ],
+            failures: Vec::new(),
+        };
+        let shard_positions_update = ShardPositionsUpdate {
+            source_uid: SourceUid {
+                index_uid: index_uid.clone(),
+                source_id: "test-source".to_string(),
+            },
+            updated_shard_positions: vec![
+                (ShardId::from(1), Position::offset(4u64)),
+                (ShardId::from(2), Position::offset(2u64)),
+            ],
+            published_split_ids: vec!["test-split".to_string()],
+        };
+        event_broker.publish(shard_positions_update);
+
+        // Yield so that the event is processed.
+        yield_now().await;
+
+        router
+            .wait_for_publication(&mut ingest_response, Duration::from_millis(50))
+            .await;
+
+        let success_0 = &ingest_response.successes[0];
+        assert_eq!(success_0.published_split_ids, ["test-split"]);
+        assert_eq!(
+            success_0.published_position_inclusive,
+            Some(Position::offset(4u64))
+        );
+        // The documents of the second subrequest were not published before the timeout.
+        let success_1 = &ingest_response.successes[1];
+        assert!(success_1.published_split_ids.is_empty());
+        assert!(success_1.published_position_inclusive.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_router_forgets_publications_of_deleted_indexes() {
+        let self_node_id = "test-router".into();
+        let control_plane: ControlPlaneServiceClient = ControlPlaneServiceClient::mock().into();
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 1;
+        let router = IngestRouter::new(
+            self_node_id,
+            control_plane,
+            ingester_pool,
+            replication_factor,
+            false,
+            Duration::from_secs(600),
+        );
+        let event_broker = EventBroker::default();
+        router.subscribe(&event_broker);
+
+        let index_uid = IndexUid::for_test("test-index-0", 0);
+
+        let shard_positions_update = ShardPositionsUpdate {
+            source_uid: SourceUid {
+                index_uid: index_uid.clone(),
+                source_id: "test-source".to_string(),
+            },
+            updated_shard_positions: vec![(ShardId::from(1), Position::offset(4u64))],
+            published_split_ids: vec!["test-split".to_string()],
+        };
+        event_broker.publish(shard_positions_update);
+
+        // Yield so that the event is processed.
+        yield_now().await;
+
+        let delete_index_request = DeleteIndexRequest {
+            index_uid: Some(index_uid.clone()),
+        };
+        event_broker.publish(delete_index_request);
+
+        // Yield so that the event is processed.
+        yield_now().await;
+
+        let mut ingest_response = IngestResponseV2 {
+            successes: vec![IngestSuccess {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                replication_position_inclusive: Some(Position::offset(3u64)),
+                ..Default::default()
+            }],
+            failures: Vec::new(),
+        };
+        router
+            .wait_for_publication(&mut ingest_response, Duration::from_millis(50))
+            .await;
+
+        let success = &ingest_response.successes[0];
+        assert!(success.published_split_ids.is_empty());
+        assert!(success.published_position_inclusive.is_none());
+    }
 }
@@ -21,6 +21,7 @@ use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+use quickwit_common::rendezvous_hasher::node_affinity;
 use quickwit_proto::ingest::{Shard, ShardIds, ShardState};
 use quickwit_proto::types::{IndexId, IndexUid, NodeId, ShardId, SourceId};
 use tracing::{info, warn};
@@ -166,6 +167,42 @@ impl RoutingTableEntry {
         None
     }
 
+    /// Returns the open shard to which a batch identified by `dedup_id` should be routed. Retries
+    /// of the same batch must land on the same ingester, which is in charge of deduplicating them
+    /// across all the shards of the source it leads: the shard is picked among the shards led by
+    /// `pinned_leader_id_opt`, the leader the batch was first routed to, if it still leads an
+    /// open shard. Otherwise, the leader is selected by rendezvous hashing of the dedup ID.
+    pub fn open_shard_for_dedup_id(
+        &self,
+        ingester_pool: &IngesterPool,
+        dedup_id: &str,
+        pinned_leader_id_opt: Option<&NodeId>,
+    ) -> Option<&RoutingEntry> {
+        let open_shards = || {
+            self.local_shards
+                .iter()
+                .chain(&self.remote_shards)
+                .filter(|shard| {
+                    shard.shard_state.is_open() && ingester_pool.contains_key(&shard.leader_id)
+                })
+        };
+        if let Some(pinned_leader_id) = pinned_leader_id_opt {
+            let pinned_shard_opt = open_shards()
+                .filter(|shard| shard.leader_id == *pinned_leader_id)
+                .max_by_key(|shard| node_affinity(&shard.shard_id, &dedup_id));
+
+            if pinned_shard_opt.is_some() {
+                return pinned_shard_opt;
+            }
+        }
+        open_shards().max_by_key(|shard| {
+            (
+                node_affinity(&shard.leader_id, &dedup_id),
+                node_affinity(&shard.shard_id, &dedup_id),
+            )
+        })
+    }
+
     /// Inserts the open shards the routing table is not aware of.
     fn insert_open_shards(
         &mut self,
@@ -755,6 +792,95 @@ mod tests {
         assert_eq!(shard.shard_id, ShardId::from(2));
     }
 
+    #[test]
+    fn test_routing_table_entry_open_shard_for_dedup_id() {
+        let index_uid: IndexUid = IndexUid::from_parts("test-index", 0);
+        let source_id: SourceId = "test-source".into();
+        let table_entry = RoutingTableEntry::empty(index_uid.clone(), source_id.clone());
+        let ingester_pool = IngesterPool::default();
+
+        let shard_opt = table_entry.open_shard_for_dedup_id(&ingester_pool, "test-dedup-id", None);
+        assert!(shard_opt.is_none());
+
+        ingester_pool.insert(
+            "test-ingester-0".into(),
+            IngesterServiceClient::mock().into(),
+        );
+        ingester_pool.insert(
+            "test-ingester-1".into(),
+            IngesterServiceClient::mock().into(),
+        );
+
+        let table_entry = RoutingTableEntry {
+            index_uid: index_uid.clone(),
+            source_id: source_id.clone(),
+            local_shards: vec![
+                RoutingEntry {
+                    index_uid: index_uid.clone(),
+                    source_id: "test-source".to_string(),
+                    shard_id: ShardId::from(1),
+                    shard_state: ShardState::Closed,
+                    leader_id: "test-ingester-0".into(),
+                },
+                RoutingEntry {
+                    index_uid: index_uid.clone(),
+                    source_id: "test-source".to_string(),
+                    shard_id: ShardId::from(2),
+                    shard_state: ShardState::Open,
+                    leader_id: "test-ingester-0".into(),
+                },
+            ],
+            local_round_robin_idx: AtomicUsize::default(),
+            remote_shards: vec![
+                RoutingEntry {
+                    index_uid: index_uid.clone(),
+                    source_id: "test-source".to_string(),
+                    shard_id: ShardId::from(3),
+                    shard_state: ShardState::Open,
+                    leader_id: "test-ingester-1".into(),
+                },
+                RoutingEntry {
+                    index_uid: index_uid.clone(),
+                    source_id: "test-source".to_string(),
+                    shard_id: ShardId::from(4),
+                    shard_state: ShardState::Open,
+                    leader_id: "test-ingester-2".into(),
+                },
+            ],
+            remote_round_robin_idx: AtomicUsize::default(),
+        };
+        let mut selected_shard_ids = HashSet::new();
+
+        for i in 0..100 {
+            let dedup_id = format!("test-dedup-id-{i}");
+            let shard = table_entry
+                .open_shard_for_dedup_id(&ingester_pool, &dedup_id, None)
+                .unwrap();
+            assert!(shard.shard_id == ShardId::from(2) || shard.shard_id == ShardId::from(3));
+
+            // Retries of the same batch are routed to the same shard.
+            let retry_shard = table_entry
+                .open_shard_for_dedup_id(&ingester_pool, &dedup_id, None)
+                .unwrap();
+            assert_eq!(retry_shard.shard_id, shard.shard_id);
+
+            // Retries stick to the leader the batch was first routed to.
+            let pinned_shard = table_entry
+                .open_shard_for_dedup_id(&ingester_pool, &dedup_id, Some(&"test-ingester-0".into()))
+                .unwrap();
+            assert_eq!(pinned_shard.shard_id, ShardId::from(2));
+
+            // Unless it no longer leads any open shard.
+            let unpinned_shard = table_entry
+                .open_shard_for_dedup_id(&ingester_pool, &dedup_id, Some(&"test-ingester-2".into()))
+                .unwrap();
+            assert_eq!(unpinned_shard.shard_id, shard.shard_id);
+
+            selected_shard_ids.insert(shard.shard_id.clone());
+        }
+        assert_eq!(selected_shard_ids.len(), 2);
+    }
+
     #[test]
     fn test_routing_table_entry_insert_open_shards() {
         let index_uid_0: IndexUid = IndexUid::from_parts("test-index", 0);
@@ -28,16 +28,20 @@ use mrecordlog::error::{DeleteQueueError, TruncateError};
 use quickwit_common::pretty::PrettyDisplay;
 use quickwit_common::rate_limiter::{RateLimiter, RateLimiterSettings};
 use quickwit_proto::control_plane::AdviseResetShardsResponse;
-use quickwit_proto::ingest::ingester::IngesterStatus;
+use quickwit_proto::ingest::ingester::{DecommissionMode, IngesterStatus};
 use quickwit_proto::ingest::{IngestV2Error, IngestV2Result, ShardState};
-use quickwit_proto::types::{Position, QueueId};
+use quickwit_proto::types::{IndexUid, Position, QueueId, ShardId, SourceId};
 use tokio::sync::{watch, Mutex, MutexGuard, RwLock, RwLockMappedWriteGuard, RwLockWriteGuard};
 use tracing::{error, info};
 
+use super::dedup::unix_timestamp_secs;
+use super::ingest_volume::DailyIngestVolumes;
 use super::models::IngesterShard;
 use super::rate_meter::RateMeter;
 use super::replication::{ReplicationStreamTaskHandle, ReplicationTaskHandle};
-use crate::ingest_v2::mrecordlog_utils::{force_delete_queue, queue_position_range};
+use crate::ingest_v2::mrecordlog_utils::{
+    force_delete_queue, queue_position_range, recover_dedup_ids,
+};
 use crate::mrecordlog_async::MultiRecordLogAsync;
 use crate::{FollowerId, LeaderId};
 
@@ -62,6 +66,10 @@ pub(super) struct InnerIngesterState {
     pub replication_streams: HashMap<FollowerId, ReplicationStreamTaskHandle>,
     // Replication tasks running for each replication stream opened with leaders.
     pub replication_tasks: HashMap<LeaderId, ReplicationTaskHandle>,
+    // Volume of data ingested today as a leader for each index.
+    pub daily_ingest_volumes: DailyIngestVolumes,
+    // Mode requested when the ingester was asked to decommission.
+    pub decommission_mode: DecommissionMode,
     status: IngesterStatus,
     status_tx: watch::Sender<IngesterStatus>,
 }
@@ -75,6 +83,32 @@ impl InnerIngesterState {
         self.status = status;
         self.status_tx.send(status).expect("channel should be open");
     }
+
+    /// Looks up `dedup_id` among the shards of the source and returns the shard ID and position
+    /// of the batch previously persisted with it, if any. Dedup IDs older than `dedup_window` are
+    /// evicted along the way.
+    pub fn find_dedup_id(
+        &mut self,
+        index_uid: &IndexUid,
+        source_id: &SourceId,
+        dedup_id: &str,
+        dedup_window: Duration,
+    ) -> Option<(ShardId, Position)> {
+        let now = unix_timestamp_secs();
+        let queue_id_prefix = format!("{index_uid}/{source_id}/");
+
+        for (queue_id, shard) in self.shards.iter_mut() {
+            let Some(shard_id) = queue_id.strip_prefix(&queue_id_prefix) else {
+                continue;
+            };
+            shard.dedup_ids.evict_expired(now, dedup_window);
+
+            if let Some(position) = shard.dedup_ids.get(dedup_id) {
+                return Some((ShardId::from(shard_id), position.clone()));
+            }
+        }
+        None
+    }
 }
 
 impl IngesterState {
@@ -86,6 +120,8 @@ impl IngesterState {
             rate_trackers: Default::default(),
             replication_streams: Default::default(),
             replication_tasks: Default::default(),
+            daily_ingest_volumes: Default::default(),
+            decommission_mode: DecommissionMode::default(),
             status,
             status_tx,
         };
@@ -127,8 +163,8 @@ impl IngesterState {
     }
 
     /// Initializes the internal state of the ingester. It loads the local WAL, then lists all its
-    /// queues. Empty queues are deleted, while non-empty queues are recovered. However, the
-    /// corresponding shards are closed and become read-only.
+    /// queues. Empty queues are deleted, while non-empty queues are recovered along with their
+    /// dedup IDs. However, the corresponding shards are closed and become read-only.
     pub async fn init(&self, wal_dir_path: &Path, rate_limiter_settings: RateLimiterSettings) {
         let mut inner_guard = self.inner.lock().await;
         let mut mrecordlog_guard = self.mrecordlog.write().await;
@@ -177,12 +213,13 @@ impl IngesterState {
                 } else {
                     Position::offset(*position_range.start() - 1)
                 };
-                let solo_shard = IngesterShard::new_solo(
+                let mut solo_shard = IngesterShard::new_solo(
                     ShardState::Closed,
                     replication_position_inclusive,
                     truncation_position_inclusive,
                     now,
                 );
+                solo_shard.dedup_ids = recover_dedup_ids(&mrecordlog, &queue_id);
                 inner_guard.shards.insert(queue_id.clone(), solo_shard);
 
                 let rate_limiter = RateLimiter::from_settings(rate_limiter_settings);
@@ -0,0 +1,379 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::ops::{Bound, RangeInclusive};
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use mrecordlog::error::TruncateError;
+use quickwit_proto::ingest::CompressedDocBatch;
+use quickwit_proto::types::QueueId;
+
+use super::mrecord::{MRecord, MAX_DECOMPRESSED_DOCS_NUM_BYTES};
+use super::mrecordlog_utils::queue_position_range;
+use crate::mrecordlog_async::MultiRecordLogAsync;
+
+/// Summary of a shard stored in a WAL.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WalShardSummary {
+    pub queue_id: QueueId,
+    /// First and last positions of the records of the shard. `None` if the shard is empty.
+    pub position_range_opt: Option<RangeInclusive<u64>>,
+    pub num_records: usize,
+}
+
+/// Record read from a WAL.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WalRecord {
+    pub position: u64,
+    /// `None` if the record could not be decoded.
+    pub mrecord_opt: Option<MRecord>,
+}
+
+/// Outcome of the verification of a WAL.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct WalVerifyReport {
+    pub num_shards: usize,
+    pub num_records: usize,
+    /// Records that could not be decoded or decompressed, along with the reason.
+    pub corrupted_records: Vec<(QueueId, u64, String)>,
+    /// Ranges of positions missing from the shards. The records they held were stored in blocks
+    /// that failed their checksum and were skipped when the WAL was replayed.
+    pub missing_positions: Vec<(QueueId, RangeInclusive<u64>)>,
+}
+
+/// Inspects and repairs the WAL of an ingester offline. It must not be used while an ingester is
+/// running on the same WAL directory.
+///
+/// Unlike `IngesterState::load`, which deletes the empty shards it recovers, the inspector opens
+/// the WAL as is and leaves it untouched until a shard is explicitly truncated.
+pub struct WalInspector {
+    mrecordlog: MultiRecordLogAsync,
+}
+
+impl WalInspector {
+    /// Opens the WAL located in `wal_dir_path`. Opening a WAL replays it and verifies the checksums
+    /// of its blocks. The blocks that fail their checksum are skipped along with the records they
+    /// hold, which [`Self::verify`] reports as missing positions. Opening only fails if the WAL
+    /// cannot be read or replayed at all.
+    pub async fn open(wal_dir_path: &Path) -> anyhow::Result<Self> {
+        if !wal_dir_path.is_dir() {
+            bail!("WAL directory `{}` does not exist", wal_dir_path.display());
+        }
+        let mrecordlog = MultiRecordLogAsync::open(wal_dir_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to open WAL `{}`: the WAL cannot be replayed and must be moved aside \
+                     for the ingester to start",
+                    wal_dir_path.display()
+                )
+            })?;
+        Ok(Self { mrecordlog })
+    }
+
+    /// Lists the shards stored in the WAL, sorted by queue ID.
+    pub fn list_shards(&self) -> Vec<WalShardSummary> {
+        let mut queue_ids: Vec<QueueId> = self
+            .mrecordlog
+            .list_queues()
+            .map(|queue_id| queue_id.to_string())
+            .collect();
+        queue_ids.sort_unstable();
+
+        queue_ids
+            .into_iter()
+            .map(|queue_id| {
+                let position_range_opt = queue_position_range(&self.mrecordlog, &queue_id);
+                let num_records = self
+                    .mrecordlog
+                    .range(&queue_id, ..)
+                    .map(|records| records.count())
+                    .unwrap_or(0);
+                WalShardSummary {
+                    queue_id,
+                    position_range_opt,
+                    num_records,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns at most `max_records` records of a shard, starting at `from_position_inclusive`
+    /// and ending at `to_position_inclusive_opt` if set.
+    pub fn dump_records(
+        &self,
+        queue_id: &str,
+        from_position_inclusive: u64,
+        to_position_inclusive_opt: Option<u64>,
+        max_records: usize,
+    ) -> anyhow::Result<Vec<WalRecord>> {
+        let end_bound = match to_position_inclusive_opt {
+            Some(to_position_inclusive) => Bound::Included(to_position_inclusive),
+            None => Bound::Unbounded,
+        };
+        let records = self
+            .mrecordlog
+            .range(
+                queue_id,
+                (Bound::Included(from_position_inclusive), end_bound),
+            )
+            .map_err(|_| anyhow::anyhow!("shard `{queue_id}` not found"))?
+            .take(max_records)
+            .map(|record| WalRecord {
+                position: record.position,
+                mrecord_opt: MRecord::decode(&record.payload[..]),
+            })
+            .collect();
+        Ok(records)
+    }
+
+    /// Deletes the records of a shard up to `truncate_up_to_position_inclusive`.
+    pub async fn truncate_shard(
+        &mut self,
+        queue_id: &str,
+        truncate_up_to_position_inclusive: u64,
+    ) -> anyhow::Result<()> {
+        match self
+            .mrecordlog
+            .truncate(queue_id, truncate_up_to_position_inclusive)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(TruncateError::MissingQueue(_)) => bail!("shard `{queue_id}` not found"),
+            Err(TruncateError::IoError(io_error)) => {
+                Err(io_error).with_context(|| format!("failed to truncate shard `{queue_id}`"))
+            }
+        }
+    }
+
+    /// Decodes all the records of the WAL and reports those that are corrupted, as well as the
+    /// positions missing from the shards. Compressed records are decompressed as well.
+    pub fn verify(&self) -> WalVerifyReport {
+        let mut report = WalVerifyReport::default();
+
+        for shard_summary in self.list_shards() {
+            let queue_id = shard_summary.queue_id;
+            report.num_shards += 1;
+
+            let Ok(records) = self.mrecordlog.range(&queue_id, ..) else {
+                continue;
+            };
+            let mut previous_position_opt: Option<u64> = None;
+
+            for record in records {
+                report.num_records += 1;
+
+                if let Some(previous_position) = previous_position_opt {
+                    if record.position > previous_position + 1 {
+                        report.missing_positions.push((
+                            queue_id.clone(),
+                            previous_position + 1..=record.position - 1,
+                        ));
+                    }
+                }
+                previous_position_opt = Some(record.position);
+
+                let corruption_opt = match MRecord::decode(&record.payload[..]) {
+                    Some(MRecord::CompressedDocs(payload)) => {
+                        CompressedDocBatch::decompress_payload(
+                            &payload,
+                            MAX_DECOMPRESSED_DOCS_NUM_BYTES,
+                        )
+                        .err()
+                        .map(|error| format!("failed to decompress documents: {error}"))
+                    }
+                    Some(_) => None,
+                    None => Some("failed to decode record".to_string()),
+                };
+                if let Some(corruption) = corruption_opt {
+                    report
+                        .corrupted_records
+                        .push((queue_id.clone(), record.position, corruption));
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use quickwit_proto::ingest::DocBatchV2;
+
+    use super::*;
+    use crate::ingest_v2::mrecordlog_utils::{append_non_empty_doc_batch, WalDocBatch};
+
+    #[tokio::test]
+    async fn test_wal_inspector() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let wal_dir_path = tempdir.path().join("wal");
+
+        WalInspector::open(&wal_dir_path).await.err().unwrap();
+
+        std::fs::create_dir(&wal_dir_path).unwrap();
+
+        let mut mrecordlog = MultiRecordLogAsync::open(&wal_dir_path).await.unwrap();
+
+        let queue_id_foo = "test-index:0/test-source/foo".to_string();
+        mrecordlog.create_queue(&queue_id_foo).await.unwrap();
+
+        let doc_batch = DocBatchV2::for_test(["test-doc-foo", "test-doc-bar"]);
+        append_non_empty_doc_batch(&mut mrecordlog, &queue_id_foo, doc_batch, true, None)
+            .await
+            .unwrap();
+
+        let compressed_doc_batch = DocBatchV2::for_test(["test-doc-baz"]).compress(3).unwrap();
+        append_non_empty_doc_batch(
+            &mut mrecordlog,
+            &queue_id_foo,
+            WalDocBatch::Compressed(compressed_doc_batch),
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let corrupted_mrecord = MRecord::CompressedDocs(Bytes::from_static(b"not-zstd"));
+        mrecordlog
+            .append_records(
+                &queue_id_foo,
+                None,
+                std::iter::once(corrupted_mrecord.encode()),
+            )
+            .await
+            .unwrap();
+
+        let queue_id_bar = "test-index:0/test-source/bar".to_string();
+        mrecordlog.create_queue(&queue_id_bar).await.unwrap();
+        drop(mrecordlog);
+
+        let mut wal_inspector = WalInspector::open(&wal_dir_path).await.unwrap();
+
+        let shard_summaries = wal_inspector.list_shards();
+        assert_eq!(shard_summaries.len(), 2);
+        assert_eq!(shard_summaries[0].queue_id, queue_id_bar);
+        assert!(shard_summaries[0].position_range_opt.is_none());
+        assert_eq!(shard_summaries[0].num_records, 0);
+
+        assert_eq!(shard_summaries[1].queue_id, queue_id_foo);
+        assert_eq!(shard_summaries[1].position_range_opt, Some(0..=4));
+        assert_eq!(shard_summaries[1].num_records, 5);
+
+        let records = wal_inspector
+            .dump_records(&queue_id_foo, 1, Some(3), 2)
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].position, 1);
+        assert_eq!(
+            records[0].mrecord_opt,
+            Some(MRecord::Doc(Bytes::from_static(b"test-doc-bar")))
+        );
+        assert_eq!(records[1].position, 2);
+        assert_eq!(records[1].mrecord_opt, Some(MRecord::Commit));
+
+        wal_inspector
+            .dump_records("test-index:0/test-source/baz", 0, None, 10)
+            .unwrap_err();
+
+        let report = wal_inspector.verify();
+        assert_eq!(report.num_shards, 2);
+        assert_eq!(report.num_records, 5);
+        assert_eq!(report.corrupted_records.len(), 1);
+        assert_eq!(report.corrupted_records[0].0, queue_id_foo);
+        assert_eq!(report.corrupted_records[0].1, 4);
+        assert!(report.missing_positions.is_empty());
+
+        wal_inspector
+            .truncate_shard(&queue_id_foo, 2)
+            .await
+            .unwrap();
+        wal_inspector
+            .truncate_shard("test-index:0/test-source/baz", 2)
+            .await
+            .unwrap_err();
+        drop(wal_inspector);
+
+        let wal_inspector = WalInspector::open(&wal_dir_path).await.unwrap();
+        let shard_summaries = wal_inspector.list_shards();
+        assert_eq!(shard_summaries[1].position_range_opt, Some(3..=4));
+        assert_eq!(shard_summaries[1].num_records, 2);
+    }
+
+    #[tokio::test]
+    async fn test_wal_inspector_corrupted_block() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let wal_dir_path = tempdir.path();
+
+        let mut mrecordlog = MultiRecordLogAsync::open(wal_dir_path).await.unwrap();
+
+        let queue_id = "test-index:0/test-source/foo".to_string();
+        mrecordlog.create_queue(&queue_id).await.unwrap();
+
+        // 100 records of 1KiB span several 32KiB blocks.
+        for _ in 0..100 {
+            let mrecord = MRecord::new_doc(vec![b'x'; 1_000]);
+            mrecordlog
+                .append_records(&queue_id, None, std::iter::once(mrecord.encode()))
+                .await
+                .unwrap();
+        }
+        drop(mrecordlog);
+
+        let wal_file_path = std::fs::read_dir(wal_dir_path)
+            .unwrap()
+            .map(|dir_entry| dir_entry.unwrap().path())
+            .find(|path| {
+                path.file_name()
+                    .and_then(|file_name| file_name.to_str())
+                    .map(|file_name| file_name.starts_with("wal-"))
+                    .unwrap_or(false)
+            })
+            .unwrap();
+        let mut wal_file_content = std::fs::read(&wal_file_path).unwrap();
+        assert!(wal_file_content.len() > 64 * 1024);
+
+        // Corrupt the second block.
+        for byte in &mut wal_file_content[40_000..40_016] {
+            *byte ^= 0xFF;
+        }
+        std::fs::write(&wal_file_path, wal_file_content).unwrap();
+
+        let wal_inspector = WalInspector::open(wal_dir_path).await.unwrap();
+
+        let shard_summaries = wal_inspector.list_shards();
+        assert_eq!(shard_summaries.len(), 1);
+        assert_eq!(shard_summaries[0].queue_id, queue_id);
+        assert_eq!(shard_summaries[0].position_range_opt, Some(0..=99));
+        assert!(shard_summaries[0].num_records < 100);
+
+        let report = wal_inspector.verify();
+        assert_eq!(report.num_shards, 1);
+        assert!(report.corrupted_records.is_empty());
+        assert_eq!(report.missing_positions.len(), 1);
+
+        let (missing_queue_id, missing_positions) = &report.missing_positions[0];
+        assert_eq!(*missing_queue_id, queue_id);
+        assert_eq!(
+            missing_positions.clone().count(),
+            100 - shard_summaries[0].num_records
+        );
+    }
+}
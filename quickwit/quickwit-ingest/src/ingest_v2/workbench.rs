@@ -25,9 +25,10 @@ use quickwit_proto::control_plane::{
 use quickwit_proto::ingest::ingester::{PersistFailure, PersistFailureReason, PersistSuccess};
 use quickwit_proto::ingest::router::{
     IngestFailure, IngestFailureReason, IngestResponseV2, IngestSubrequest, IngestSuccess,
+    ParseFailure,
 };
-use quickwit_proto::ingest::IngestV2Error;
-use quickwit_proto::types::{NodeId, SubrequestId};
+use quickwit_proto::ingest::{DocBatchV2, IngestV2Error};
+use quickwit_proto::types::{IndexUid, NodeId, SubrequestId};
 use tracing::warn;
 
 use super::router::PersistRequestSummary;
@@ -115,6 +116,7 @@ impl IngestWorkbench {
             GetOrCreateOpenShardsFailureReason::NoIngestersAvailable => {
                 SubworkbenchFailure::NoShardsAvailable
             }
+            GetOrCreateOpenShardsFailureReason::QuotaExceeded => SubworkbenchFailure::QuotaExceeded,
             GetOrCreateOpenShardsFailureReason::Unspecified => {
                 warn!(
                     "failure reason for subrequest `{}` is unspecified",
@@ -139,6 +141,37 @@ impl IngestWorkbench {
         subworkbench.persist_success_opt = Some(persist_success);
     }
 
+    /// Records the outcome of the validation of the documents of a subrequest: invalid documents
+    /// are removed from the subrequest and reported in the ingest response. If no valid documents
+    /// remain, the subrequest is considered successful since there is nothing left to persist.
+    pub fn record_doc_validation(
+        &mut self,
+        subrequest_id: SubrequestId,
+        index_uid: IndexUid,
+        doc_batch_opt: Option<DocBatchV2>,
+        parse_failures: Vec<ParseFailure>,
+    ) {
+        let Some(subworkbench) = self.subworkbenches.get_mut(&subrequest_id) else {
+            warn!("could not find subrequest `{}` in workbench", subrequest_id);
+            return;
+        };
+        subworkbench.is_validated = true;
+        subworkbench.parse_failures = parse_failures;
+
+        if doc_batch_opt.is_some() {
+            subworkbench.subrequest.doc_batch = doc_batch_opt;
+            return;
+        }
+        let persist_success = PersistSuccess {
+            subrequest_id,
+            index_uid: Some(index_uid),
+            source_id: subworkbench.subrequest.source_id.clone(),
+            ..Default::default()
+        };
+        self.num_successes += 1;
+        subworkbench.persist_success_opt = Some(persist_success);
+    }
+
     pub fn record_persist_error(
         &mut self,
         persist_error: IngestV2Error,
@@ -184,6 +217,10 @@ impl IngestWorkbench {
         self.record_failure(subrequest_id, SubworkbenchFailure::NoShardsAvailable);
     }
 
+    pub fn record_quota_exceeded(&mut self, subrequest_id: SubrequestId) {
+        self.record_failure(subrequest_id, SubworkbenchFailure::QuotaExceeded);
+    }
+
     /// Marks a node as unavailable for the span of the workbench.
     ///
     /// Remaining attempts will treat the node as if it was not in the ingester pool.
@@ -208,6 +245,9 @@ impl IngestWorkbench {
                     source_id: persist_success.source_id,
                     shard_id: persist_success.shard_id,
                     replication_position_inclusive: persist_success.replication_position_inclusive,
+                    parse_failures: subworkbench.parse_failures,
+                    published_split_ids: Vec::new(),
+                    published_position_inclusive: None,
                 };
                 successes.push(success);
             } else if let Some(failure) = subworkbench.last_failure_opt {
@@ -254,6 +294,9 @@ pub(super) enum SubworkbenchFailure {
     // The routing table entry for this source is empty, shards are all closed, or their leaders
     // are unavailable.
     NoShardsAvailable,
+    // The daily ingest quota of the index is exhausted or its ingestion rate reaches its
+    // throughput quota.
+    QuotaExceeded,
     // This is an error returned by the ingester: e.g. shard not found, shard closed, rate
     // limited, resource exhausted, etc.
     Persist(PersistFailureReason),
@@ -270,6 +313,7 @@ impl SubworkbenchFailure {
             Self::SourceNotFound => IngestFailureReason::SourceNotFound,
             Self::Internal(_) => IngestFailureReason::Internal,
             Self::NoShardsAvailable => IngestFailureReason::NoShardsAvailable,
+            Self::QuotaExceeded => IngestFailureReason::QuotaExceeded,
             // In our last attempt, we did not manage to reach the ingester.
             // We can consider that as a no shards available.
             Self::Unavailable => IngestFailureReason::NoShardsAvailable,
@@ -285,6 +329,11 @@ pub(super) struct IngestSubworkbench {
     pub last_failure_opt: Option<SubworkbenchFailure>,
     /// The number of persist attempts for this subrequest.
     pub num_attempts: usize,
+    /// Whether the documents of the subrequest have been validated against the doc mapping of the
+    /// index.
+    pub is_validated: bool,
+    /// The documents of the subrequest that failed validation.
+    pub parse_failures: Vec<ParseFailure>,
 }
 
 impl IngestSubworkbench {
@@ -302,11 +351,13 @@ impl IngestSubworkbench {
     /// Returns `false` if and only if the last attempt suggests retrying will fail.
     /// e.g.:
     /// - the index does not exist
-    /// - the source does not exist.
+    /// - the source does not exist
+    /// - the daily ingest quota of the index is exhausted.
     fn last_failure_is_transient(&self) -> bool {
         match self.last_failure_opt {
             Some(SubworkbenchFailure::IndexNotFound) => false,
             Some(SubworkbenchFailure::SourceNotFound) => false,
+            Some(SubworkbenchFailure::QuotaExceeded) => false,
             Some(SubworkbenchFailure::Internal(_)) => true,
             Some(SubworkbenchFailure::NoShardsAvailable) => true,
             Some(SubworkbenchFailure::Persist(_)) => true,
@@ -351,6 +402,9 @@ mod tests {
         subworkbench.last_failure_opt = Some(SubworkbenchFailure::SourceNotFound);
         assert!(!subworkbench.is_pending());
         assert!(!subworkbench.last_failure_is_transient());
+        subworkbench.last_failure_opt = Some(SubworkbenchFailure::QuotaExceeded);
+        assert!(!subworkbench.is_pending());
+        assert!(!subworkbench.last_failure_is_transient());
 
         subworkbench.last_failure_opt = Some(SubworkbenchFailure::Persist(
             PersistFailureReason::RateLimited,
@@ -616,4 +670,69 @@ mod tests {
         ));
         assert_eq!(subworkbench.num_attempts, 1);
     }
+
+    #[test]
+    fn test_ingest_workbench_record_doc_validation() {
+        let index_uid = IndexUid::for_test("test-index", 0);
+        let ingest_subrequests = vec![
+            IngestSubrequest {
+                subrequest_id: 0,
+                source_id: "test-source".to_string(),
+                doc_batch: Some(DocBatchV2::for_test(["", "test-doc-foo"])),
+                ..Default::default()
+            },
+            IngestSubrequest {
+                subrequest_id: 1,
+                source_id: "test-source".to_string(),
+                doc_batch: Some(DocBatchV2::for_test([""])),
+                ..Default::default()
+            },
+        ];
+        let mut workbench = IngestWorkbench::new(ingest_subrequests, 1);
+
+        let parse_failure = ParseFailure {
+            doc_ordinal: 0,
+            ..Default::default()
+        };
+        workbench.record_doc_validation(
+            0,
+            index_uid.clone(),
+            Some(DocBatchV2::for_test(["test-doc-foo"])),
+            vec![parse_failure.clone()],
+        );
+        workbench.record_doc_validation(1, index_uid.clone(), None, vec![parse_failure]);
+
+        assert_eq!(workbench.num_successes, 1);
+
+        let subworkbench = workbench.subworkbenches.get(&0).unwrap();
+        assert!(subworkbench.is_validated);
+        assert!(subworkbench.is_pending());
+        assert_eq!(
+            subworkbench
+                .subrequest
+                .doc_batch
+                .as_ref()
+                .unwrap()
+                .num_docs(),
+            1
+        );
+        assert_eq!(subworkbench.parse_failures.len(), 1);
+
+        let subworkbench = workbench.subworkbenches.get(&1).unwrap();
+        assert!(subworkbench.is_validated);
+        assert!(!subworkbench.is_pending());
+
+        workbench.record_no_shards_available(0);
+
+        let response = workbench.into_ingest_response();
+        assert_eq!(response.successes.len(), 1);
+        assert_eq!(response.failures.len(), 1);
+
+        let success = &response.successes[0];
+        assert_eq!(success.subrequest_id, 1);
+        assert_eq!(success.index_uid(), &index_uid);
+        assert_eq!(success.source_id, "test-source");
+        assert!(success.shard_id.is_none());
+        assert_eq!(success.parse_failures.len(), 1);
+    }
 }
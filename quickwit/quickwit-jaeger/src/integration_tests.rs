@@ -35,7 +35,9 @@ use quickwit_ingest::{
     IngesterPool, QUEUES_DIR_NAME,
 };
 use quickwit_metastore::{AddSourceRequestExt, CreateIndexRequestExt, FileBackedMetastore};
-use quickwit_opentelemetry::otlp::{make_resource_spans_for_test, OtlpGrpcTracesService};
+use quickwit_opentelemetry::otlp::{
+    make_resource_spans_for_test, OtlpGrpcTracesService, TraceId, OTEL_TRACES_INDEX_ID,
+};
 use quickwit_proto::jaeger::storage::v1::span_reader_plugin_server::SpanReaderPlugin;
 use quickwit_proto::jaeger::storage::v1::{
     FindTraceIDsRequest, GetOperationsRequest, GetServicesRequest, GetTraceRequest, Operation,
@@ -46,6 +48,7 @@ use quickwit_proto::metastore::{
 };
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceService;
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::ExportTraceServiceRequest;
+use quickwit_proto::search::SearchRequest;
 use quickwit_proto::types::{IndexUid, PipelineUid};
 use quickwit_search::{
     start_searcher_service, SearchJobPlacer, SearchService, SearchServiceClient, SearcherContext,
@@ -55,7 +58,7 @@ use quickwit_storage::StorageResolver;
 use tempfile::TempDir;
 use tokio_stream::StreamExt;
 
-use crate::JaegerService;
+use crate::{build_trace_ids_query, JaegerService};
 
 #[tokio::test]
 async fn test_otel_jaeger_integration() {
@@ -90,7 +93,7 @@ async fn test_otel_jaeger_integration() {
 
     let search_service =
         searcher_for_test(&cluster, metastore.clone(), storage_resolver.clone()).await;
-    let jaeger_service = JaegerService::new(JaegerConfig::default(), search_service);
+    let jaeger_service = JaegerService::new(JaegerConfig::default(), search_service.clone());
 
     cluster
         .wait_for_ready_members(|members| members.len() == 1, Duration::from_secs(5))
@@ -293,6 +296,41 @@ async fn test_otel_jaeger_integration() {
         assert_eq!(process.tags[0].key, "tags");
         assert_eq!(process.tags[0].v_str, r#"["foo"]"#);
     }
+    {
+        // Test that trace lookups skip the splits that do not contain the trace.
+        let mut resource_spans = make_resource_spans_for_test();
+        for span in resource_spans
+            .iter_mut()
+            .flat_map(|resource_spans| &mut resource_spans.scope_spans)
+            .flat_map(|scope_spans| &mut scope_spans.spans)
+        {
+            span.trace_id = vec![9; 16];
+        }
+        let export_trace_request = ExportTraceServiceRequest { resource_spans };
+        traces_service
+            .export(tonic::Request::new(export_trace_request))
+            .await
+            .unwrap();
+
+        let query_ast = build_trace_ids_query(&[TraceId::new([1; 16])]);
+        let search_request = SearchRequest {
+            index_id_patterns: vec![OTEL_TRACES_INDEX_ID.to_string()],
+            query_ast: serde_json::to_string(&query_ast).unwrap(),
+            max_hits: 10,
+            profile: true,
+            ..Default::default()
+        };
+        let search_response = search_service.root_search(search_request).await.unwrap();
+        assert_eq!(search_response.num_hits, 1);
+
+        let split_profiles = search_response.profile.unwrap().split_profiles;
+        assert_eq!(split_profiles.len(), 2);
+        let num_pruned_splits = split_profiles
+            .iter()
+            .filter(|split_profile| split_profile.pruned_by_bloom_filters)
+            .count();
+        assert_eq!(num_pruned_splits, 1);
+    }
     _indexer_handle.quit().await;
     universe.assert_quit().await;
 }
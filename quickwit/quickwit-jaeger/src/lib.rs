@@ -34,17 +34,18 @@ use quickwit_opentelemetry::otlp::{
     TraceId, OTEL_TRACES_INDEX_ID,
 };
 use quickwit_proto::jaeger::api_v2::{
-    KeyValue as JaegerKeyValue, Log as JaegerLog, Process as JaegerProcess, Span as JaegerSpan,
-    SpanRef as JaegerSpanRef, SpanRefType as JaegerSpanRefType, ValueType,
+    DependencyLink, KeyValue as JaegerKeyValue, Log as JaegerLog, Process as JaegerProcess,
+    Span as JaegerSpan, SpanRef as JaegerSpanRef, SpanRefType as JaegerSpanRefType, ValueType,
 };
+use quickwit_proto::jaeger::storage::v1::dependencies_reader_plugin_server::DependenciesReaderPlugin;
 use quickwit_proto::jaeger::storage::v1::span_reader_plugin_server::SpanReaderPlugin;
 use quickwit_proto::jaeger::storage::v1::{
-    FindTraceIDsRequest, FindTraceIDsResponse, FindTracesRequest, GetOperationsRequest,
-    GetOperationsResponse, GetServicesRequest, GetServicesResponse, GetTraceRequest, Operation,
-    SpansResponseChunk, TraceQueryParameters,
+    FindTraceIDsRequest, FindTraceIDsResponse, FindTracesRequest, GetDependenciesRequest,
+    GetDependenciesResponse, GetOperationsRequest, GetOperationsResponse, GetServicesRequest,
+    GetServicesResponse, GetTraceRequest, Operation, SpansResponseChunk, TraceQueryParameters,
 };
 use quickwit_proto::opentelemetry::proto::trace::v1::status::StatusCode as OtlpStatusCode;
-use quickwit_proto::search::{CountHits, ListTermsRequest, SearchRequest};
+use quickwit_proto::search::{CountHits, ListTermsRequest, SearchRequest, SortField, SortOrder};
 use quickwit_query::query_ast::{BoolQuery, QueryAst, RangeQuery, TermQuery};
 use quickwit_search::{FindTraceIdsCollector, SearchService};
 use serde::Deserialize;
@@ -73,6 +74,10 @@ type JaegerResult<T> = Result<T, Status>;
 
 type SpanStream = ReceiverStream<Result<SpansResponseChunk, Status>>;
 
+/// Maximum number of spans joined to compute the dependencies between services. The spans are
+/// fetched in pages of `max_fetch_spans` spans.
+const MAX_DEPENDENCY_SPANS: u64 = 1_000_000;
+
 #[derive(Clone)]
 pub struct JaegerService {
     search_service: Arc<dyn SearchService>,
@@ -244,6 +249,101 @@ impl JaegerService {
         Ok(response)
     }
 
+    #[instrument("get_dependencies", skip_all)]
+    pub async fn get_dependencies_for_indexes(
+        &self,
+        request: GetDependenciesRequest,
+        index_id_patterns: Vec<String>,
+    ) -> JaegerResult<GetDependenciesResponse> {
+        debug!(request=?request, index_ids=?index_id_patterns, "`get_dependencies` request");
+
+        let end_timestamp = request
+            .end_time
+            .map(|ts| ts.seconds)
+            .unwrap_or_else(|| OffsetDateTime::now_utc().unix_timestamp());
+        let start_timestamp = request
+            .start_time
+            .map(|ts| ts.seconds)
+            .unwrap_or(end_timestamp - self.lookback_period_secs);
+
+        if start_timestamp > end_timestamp {
+            return Err(Status::invalid_argument(
+                "Start time must be less than or equal to end time.",
+            ));
+        }
+        let query_ast = serde_json::to_string(&QueryAst::MatchAll)
+            .map_err(|err| Status::internal(err.to_string()))?;
+        // The spans are paginated in the doc order, the only order that does not require to sort
+        // all the spans of the window.
+        let sort_fields = vec![SortField {
+            field_name: "_shard_doc".to_string(),
+            sort_order: SortOrder::Asc as i32,
+            sort_datetime_format: None,
+        }];
+        let mut dependency_links_builder = DependencyLinksBuilder::default();
+        let mut search_after = None;
+        let mut num_spans = 0;
+
+        loop {
+            // Jaeger end times are inclusive, Quickwit's are exclusive. The spans are only counted
+            // on the first page, in order to fail fast when the window holds too many spans.
+            let search_request = SearchRequest {
+                index_id_patterns: index_id_patterns.clone(),
+                query_ast: query_ast.clone(),
+                start_timestamp: Some(start_timestamp),
+                end_timestamp: Some(end_timestamp + 1),
+                max_hits: self.max_fetch_spans,
+                sort_fields: sort_fields.clone(),
+                search_after: search_after.take(),
+                count_hits: if num_spans == 0 {
+                    CountHits::CountAll.into()
+                } else {
+                    CountHits::Underestimate.into()
+                },
+                ..Default::default()
+            };
+            let search_response = self.search_service.root_search(search_request).await?;
+
+            if num_spans == 0 && search_response.num_hits > MAX_DEPENDENCY_SPANS {
+                return Err(Status::resource_exhausted(format!(
+                    "The time window contains {} spans, which exceeds the maximum of \
+                     {MAX_DEPENDENCY_SPANS} spans used to compute dependencies. Narrow down the \
+                     time window.",
+                    search_response.num_hits
+                )));
+            }
+            let num_spans_in_page = search_response.hits.len() as u64;
+            num_spans += num_spans_in_page;
+
+            for hit in &search_response.hits {
+                dependency_links_builder.add_span(json_deserialize(&hit.json, "span")?);
+            }
+            if num_spans_in_page < self.max_fetch_spans {
+                break;
+            }
+            if num_spans >= MAX_DEPENDENCY_SPANS {
+                return Err(Status::resource_exhausted(format!(
+                    "The time window contains more than {MAX_DEPENDENCY_SPANS} spans, the maximum \
+                     used to compute dependencies. Narrow down the time window."
+                )));
+            }
+            search_after = search_response
+                .hits
+                .last()
+                .and_then(|hit| hit.partial_hit.clone());
+
+            if search_after.is_none() {
+                return Err(Status::internal(
+                    "Failed to paginate the spans: the last hit has no partial hit.",
+                ));
+            }
+        }
+        let dependencies = dependency_links_builder.build();
+        debug!(num_dependencies=%dependencies.len(), "`get_dependencies` response");
+        let response = GetDependenciesResponse { dependencies };
+        Ok(response)
+    }
+
     #[instrument("find_trace_ids", skip_all fields(service_name=%trace_query.service_name, operation_name=%trace_query.operation_name))]
     async fn find_trace_ids(
         &self,
@@ -308,18 +408,7 @@ impl JaegerService {
             return Ok(ReceiverStream::new(rx));
         }
         let num_traces = trace_ids.len() as u64;
-        let mut query = BoolQuery::default();
-
-        for trace_id in trace_ids {
-            let value = trace_id.hex_display();
-            let term_query = TermQuery {
-                field: "trace_id".to_string(),
-                value,
-            };
-            query.should.push(term_query.into());
-        }
-
-        let query_ast: QueryAst = query.into();
+        let query_ast = build_trace_ids_query(trace_ids);
         let query_ast =
             serde_json::to_string(&query_ast).map_err(|err| Status::internal(err.to_string()))?;
 
@@ -547,6 +636,22 @@ impl SpanReaderPlugin for JaegerService {
     }
 }
 
+#[async_trait]
+impl DependenciesReaderPlugin for JaegerService {
+    async fn get_dependencies(
+        &self,
+        request: Request<GetDependenciesRequest>,
+    ) -> Result<Response<GetDependenciesResponse>, Status> {
+        let index_id_patterns =
+            extract_otel_traces_index_id_patterns_from_metadata(request.metadata())?;
+        metrics!(
+            self.get_dependencies_for_indexes(request.into_inner(), index_id_patterns)
+                .await,
+            [get_dependencies, OTEL_TRACES_INDEX_ID]
+        );
+    }
+}
+
 fn extract_term(term_bytes: &[u8]) -> String {
     tantivy::Term::wrap(term_bytes)
         .value()
@@ -730,6 +835,23 @@ fn build_search_query(
     }
 }
 
+/// Builds a query matching the spans of the given traces. The query only holds terms on the
+/// `trace_id` field, so that the searchers skip the splits whose bloom filters do not contain any
+/// of the trace IDs.
+fn build_trace_ids_query(trace_ids: &[TraceId]) -> QueryAst {
+    let mut query = BoolQuery::default();
+
+    for trace_id in trace_ids {
+        let value = trace_id.hex_display();
+        let term_query = TermQuery {
+            field: "trace_id".to_string(),
+            value,
+        };
+        query.should.push(term_query.into());
+    }
+    query.into()
+}
+
 fn build_aggregations_query(num_traces: usize) -> String {
     let query = serde_json::to_string(&FindTraceIdsCollector {
         num_traces,
@@ -1055,6 +1177,92 @@ fn collect_trace_ids(trace_ids_json: &str) -> Result<(Vec<TraceId>, TimeInterval
     Ok((trace_ids, start..=end))
 }
 
+/// The subset of the span fields required to compute the dependencies between services.
+#[derive(Deserialize)]
+struct DependencySpan {
+    trace_id: TraceId,
+    span_id: SpanId,
+    #[serde(default)]
+    parent_span_id: Option<SpanId>,
+    service_name: String,
+}
+
+/// Joins each span with its parent span and counts the calls between distinct services. The spans
+/// can be added in any order, and the spans whose parent is never added are ignored.
+#[derive(Default)]
+struct DependencyLinksBuilder {
+    service_names: Vec<String>,
+    service_ids: HashMap<String, usize>,
+    span_service_ids: HashMap<(TraceId, SpanId), usize>,
+    /// Service IDs of the spans whose parent span was not added yet, keyed by parent span.
+    pending_child_service_ids: HashMap<(TraceId, SpanId), Vec<usize>>,
+    call_counts: HashMap<(usize, usize), u64>,
+}
+
+impl DependencyLinksBuilder {
+    fn add_span(&mut self, span: DependencySpan) {
+        let service_id = if let Some(service_id) = self.service_ids.get(&span.service_name) {
+            *service_id
+        } else {
+            let service_id = self.service_names.len();
+            self.service_ids
+                .insert(span.service_name.clone(), service_id);
+            self.service_names.push(span.service_name);
+            service_id
+        };
+        self.span_service_ids
+            .insert((span.trace_id, span.span_id), service_id);
+
+        if let Some(child_service_ids) = self
+            .pending_child_service_ids
+            .remove(&(span.trace_id, span.span_id))
+        {
+            for child_service_id in child_service_ids {
+                self.record_call(service_id, child_service_id);
+            }
+        }
+        let Some(parent_span_id) = span.parent_span_id else {
+            return;
+        };
+        let parent_span_key = (span.trace_id, parent_span_id);
+
+        if let Some(parent_service_id) = self.span_service_ids.get(&parent_span_key) {
+            self.record_call(*parent_service_id, service_id);
+        } else {
+            self.pending_child_service_ids
+                .entry(parent_span_key)
+                .or_default()
+                .push(service_id);
+        }
+    }
+
+    fn record_call(&mut self, parent_service_id: usize, child_service_id: usize) {
+        if parent_service_id != child_service_id {
+            *self
+                .call_counts
+                .entry((parent_service_id, child_service_id))
+                .or_default() += 1;
+        }
+    }
+
+    fn build(self) -> Vec<DependencyLink> {
+        self.call_counts
+            .into_iter()
+            .map(
+                |((parent_service_id, child_service_id), call_count)| DependencyLink {
+                    parent: self.service_names[parent_service_id].clone(),
+                    child: self.service_names[child_service_id].clone(),
+                    call_count,
+                    source: String::new(),
+                },
+            )
+            .sorted_by(|left, right| {
+                (&left.parent, &left.child).cmp(&(&right.parent, &right.child))
+            })
+            .collect()
+    }
+}
+
 fn json_deserialize<'a, T>(json: &'a str, label: &'static str) -> Result<T, Status>
 where T: Deserialize<'a> {
     match serde_json::from_str(json) {
@@ -1070,6 +1278,8 @@ where T: Deserialize<'a> {
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroU64;
+
     use quickwit_opentelemetry::otlp::{OtelSignal, OTEL_TRACES_INDEX_ID_PATTERN};
     use quickwit_proto::jaeger::api_v2::ValueType;
     use quickwit_search::{encode_term_for_test, MockSearchService, QuickwitAggregations};
@@ -2416,6 +2626,246 @@ mod tests {
         }
     }
 
+    fn search_service_returning_spans(spans_json: &'static [&'static str]) -> MockSearchService {
+        let mut service = MockSearchService::new();
+        service.expect_root_search().return_once(move |_| {
+            let hits = spans_json
+                .iter()
+                .map(|json| quickwit_proto::search::Hit {
+                    json: json.to_string(),
+                    ..Default::default()
+                })
+                .collect();
+            Ok(quickwit_proto::search::SearchResponse {
+                num_hits: spans_json.len() as u64,
+                hits,
+                ..Default::default()
+            })
+        });
+        service
+    }
+
+    #[tokio::test]
+    async fn test_get_dependencies_computes_dependency_links() {
+        let service = search_service_returning_spans(&[
+            r#"{"trace_id": "00000000000000000000000000000001", "span_id": "0000000000000001", "service_name": "frontend"}"#,
+            r#"{"trace_id": "00000000000000000000000000000001", "span_id": "0000000000000002", "parent_span_id": "0000000000000001", "service_name": "frontend"}"#,
+            r#"{"trace_id": "00000000000000000000000000000001", "span_id": "0000000000000003", "parent_span_id": "0000000000000002", "service_name": "backend"}"#,
+            r#"{"trace_id": "00000000000000000000000000000001", "span_id": "0000000000000004", "parent_span_id": "0000000000000003", "service_name": "database"}"#,
+            r#"{"trace_id": "00000000000000000000000000000002", "span_id": "0000000000000001", "service_name": "frontend"}"#,
+            r#"{"trace_id": "00000000000000000000000000000002", "span_id": "0000000000000002", "parent_span_id": "0000000000000001", "service_name": "backend"}"#,
+            // The parent span belongs to another trace.
+            r#"{"trace_id": "00000000000000000000000000000003", "span_id": "0000000000000005", "parent_span_id": "0000000000000003", "service_name": "cache"}"#,
+        ]);
+        let jaeger = JaegerService::new(JaegerConfig::default(), Arc::new(service));
+
+        let dependency_links = jaeger
+            .get_dependencies_for_indexes(
+                GetDependenciesRequest::default(),
+                vec![OTEL_TRACES_INDEX_ID_PATTERN.to_string()],
+            )
+            .await
+            .unwrap()
+            .dependencies;
+        assert_eq!(
+            dependency_links,
+            [
+                DependencyLink {
+                    parent: "backend".to_string(),
+                    child: "database".to_string(),
+                    call_count: 1,
+                    source: String::new(),
+                },
+                DependencyLink {
+                    parent: "frontend".to_string(),
+                    child: "backend".to_string(),
+                    call_count: 2,
+                    source: String::new(),
+                },
+            ]
+        );
+
+        let service = search_service_returning_spans(&[r#"{"span_id": "0000000000000001"}"#]);
+        let jaeger = JaegerService::new(JaegerConfig::default(), Arc::new(service));
+
+        jaeger
+            .get_dependencies_for_indexes(
+                GetDependenciesRequest::default(),
+                vec![OTEL_TRACES_INDEX_ID_PATTERN.to_string()],
+            )
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_get_dependencies() {
+        let mut service = MockSearchService::new();
+        service
+            .expect_root_search()
+            .withf(|req| {
+                req.index_id_patterns == vec![OTEL_TRACES_INDEX_ID_PATTERN]
+                    && req.start_timestamp == Some(1_000)
+                    && req.end_timestamp == Some(2_001)
+            })
+            .return_once(|_| {
+                let hits = [
+                    r#"{"trace_id": "00000000000000000000000000000001", "span_id": "0000000000000001", "service_name": "frontend"}"#,
+                    r#"{"trace_id": "00000000000000000000000000000001", "span_id": "0000000000000002", "parent_span_id": "0000000000000001", "service_name": "backend"}"#,
+                ]
+                .into_iter()
+                .map(|json| quickwit_proto::search::Hit {
+                    json: json.to_string(),
+                    ..Default::default()
+                })
+                .collect();
+                Ok(quickwit_proto::search::SearchResponse {
+                    num_hits: 2,
+                    hits,
+                    ..Default::default()
+                })
+            });
+
+        let service = Arc::new(service);
+        let jaeger = JaegerService::new(JaegerConfig::default(), service);
+
+        let request = tonic::Request::new(GetDependenciesRequest {
+            start_time: Some(WellKnownTimestamp {
+                seconds: 1_000,
+                nanos: 0,
+            }),
+            end_time: Some(WellKnownTimestamp {
+                seconds: 2_000,
+                nanos: 0,
+            }),
+        });
+        let response = jaeger.get_dependencies(request).await.unwrap().into_inner();
+        assert_eq!(
+            response.dependencies,
+            [DependencyLink {
+                parent: "frontend".to_string(),
+                child: "backend".to_string(),
+                call_count: 1,
+                source: String::new(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_dependencies_paginates_spans() {
+        let mut service = MockSearchService::new();
+        service
+            .expect_root_search()
+            .times(2)
+            .returning(|req| {
+                assert_eq!(req.max_hits, 2);
+                assert_eq!(req.sort_fields[0].field_name, "_shard_doc");
+
+                let (num_hits, hits) = match req.search_after {
+                    None => {
+                        assert_eq!(req.count_hits(), CountHits::CountAll);
+                        let hits = vec![
+                            (
+                                r#"{"trace_id": "00000000000000000000000000000001", "span_id": "0000000000000002", "parent_span_id": "0000000000000001", "service_name": "backend"}"#,
+                                0,
+                            ),
+                            (
+                                r#"{"trace_id": "00000000000000000000000000000001", "span_id": "0000000000000003", "parent_span_id": "0000000000000002", "service_name": "db"}"#,
+                                1,
+                            ),
+                        ];
+                        (3, hits)
+                    }
+                    Some(partial_hit) => {
+                        assert_eq!(partial_hit.doc_id, 1);
+                        let hits = vec![(
+                            r#"{"trace_id": "00000000000000000000000000000001", "span_id": "0000000000000001", "service_name": "frontend"}"#,
+                            2,
+                        )];
+                        (1, hits)
+                    }
+                };
+                let hits = hits
+                    .into_iter()
+                    .map(|(json, doc_id)| quickwit_proto::search::Hit {
+                        json: json.to_string(),
+                        partial_hit: Some(quickwit_proto::search::PartialHit {
+                            doc_id,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    })
+                    .collect();
+                Ok(quickwit_proto::search::SearchResponse {
+                    num_hits,
+                    hits,
+                    ..Default::default()
+                })
+            });
+
+        let service = Arc::new(service);
+        let jaeger_config = JaegerConfig {
+            max_fetch_spans: NonZeroU64::new(2).unwrap(),
+            ..Default::default()
+        };
+        let jaeger = JaegerService::new(jaeger_config, service);
+
+        let request = tonic::Request::new(GetDependenciesRequest {
+            start_time: Some(WellKnownTimestamp {
+                seconds: 1_000,
+                nanos: 0,
+            }),
+            end_time: Some(WellKnownTimestamp {
+                seconds: 2_000,
+                nanos: 0,
+            }),
+        });
+        let response = jaeger.get_dependencies(request).await.unwrap().into_inner();
+        assert_eq!(
+            response.dependencies,
+            [
+                DependencyLink {
+                    parent: "backend".to_string(),
+                    child: "db".to_string(),
+                    call_count: 1,
+                    source: String::new(),
+                },
+                DependencyLink {
+                    parent: "frontend".to_string(),
+                    child: "backend".to_string(),
+                    call_count: 1,
+                    source: String::new(),
+                }
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_dependencies_too_many_spans() {
+        let mut service = MockSearchService::new();
+        service.expect_root_search().return_once(|_| {
+            Ok(quickwit_proto::search::SearchResponse {
+                num_hits: MAX_DEPENDENCY_SPANS + 1,
+                ..Default::default()
+            })
+        });
+
+        let service = Arc::new(service);
+        let jaeger = JaegerService::new(JaegerConfig::default(), service);
+
+        let request = tonic::Request::new(GetDependenciesRequest {
+            start_time: Some(WellKnownTimestamp {
+                seconds: 1_000,
+                nanos: 0,
+            }),
+            end_time: Some(WellKnownTimestamp {
+                seconds: 2_000,
+                nanos: 0,
+            }),
+        });
+        let status = jaeger.get_dependencies(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    }
+
     #[tokio::test]
     async fn test_get_services() {
         let mut service = MockSearchService::new();
@@ -42,7 +42,7 @@ use quickwit_proto::indexing::IndexingPipelineId;
 use quickwit_proto::metastore::{IndexMetadataRequest, MetastoreService, MetastoreServiceClient};
 use quickwit_proto::types::{IndexUid, PipelineUid};
 use quickwit_search::SearchJobPlacer;
-use quickwit_storage::Storage;
+use quickwit_storage::{Storage, StorageResolver};
 use serde::Serialize;
 use tokio::join;
 use tracing::info;
@@ -83,6 +83,7 @@ pub struct DeleteTaskPipeline {
     metastore: MetastoreServiceClient,
     search_job_placer: SearchJobPlacer,
     index_storage: Arc<dyn Storage>,
+    storage_resolver: StorageResolver,
     delete_service_task_dir: PathBuf,
     handles: Option<DeletePipelineHandle>,
     max_concurrent_split_uploads: usize,
@@ -135,6 +136,7 @@ impl DeleteTaskPipeline {
         metastore: MetastoreServiceClient,
         search_job_placer: SearchJobPlacer,
         index_storage: Arc<dyn Storage>,
+        storage_resolver: StorageResolver,
         delete_service_task_dir: PathBuf,
         max_concurrent_split_uploads: usize,
         merge_scheduler_service: Mailbox<MergeSchedulerService>,
@@ -145,6 +147,7 @@ impl DeleteTaskPipeline {
             metastore,
             search_job_placer,
             index_storage,
+            storage_resolver,
             delete_service_task_dir,
             handles: Default::default(),
             max_concurrent_split_uploads,
@@ -191,7 +194,15 @@ impl DeleteTaskPipeline {
         let doc_mapper =
             build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)?;
         let tag_fields = doc_mapper.tag_named_fields()?;
-        let packager = Packager::new("MergePackager", tag_fields, uploader_mailbox);
+        let min_max_fields = doc_mapper.min_max_named_fields()?;
+        let bloom_filter_fields = doc_mapper.bloom_filter_named_fields()?;
+        let packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            min_max_fields,
+            bloom_filter_fields,
+            uploader_mailbox,
+        );
         let (packager_mailbox, packager_supervisor_handler) = ctx.spawn_actor().supervise(packager);
         let index_pipeline_id = IndexingPipelineId {
             index_uid: self.index_uid.clone(),
@@ -221,6 +232,7 @@ impl DeleteTaskPipeline {
         let merge_split_downloader = MergeSplitDownloader {
             scratch_directory,
             split_store,
+            storage_resolver: self.storage_resolver.clone(),
             executor_mailbox: delete_executor_mailbox,
             io_controls: split_download_io_controls,
         };
@@ -393,6 +405,7 @@ mod tests {
             metastore.clone(),
             search_job_placer,
             test_sandbox.storage(),
+            test_sandbox.storage_resolver(),
             delete_service_task_dir.path().into(),
             4,
             merge_scheduler_service,
@@ -474,6 +487,7 @@ mod tests {
             metastore.clone(),
             search_job_placer,
             test_sandbox.storage(),
+            test_sandbox.storage_resolver(),
             delete_service_task_dir.path().into(),
             4,
             merge_scheduler_mailbox,
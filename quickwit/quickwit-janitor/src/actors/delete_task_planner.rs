@@ -428,9 +428,12 @@ mod tests {
     use quickwit_indexing::TestSandbox;
     use quickwit_metastore::{
         IndexMetadataResponseExt, ListSplitsRequestExt, MetastoreServiceStreamSplitsExt,
-        SplitMetadata,
+        SplitMaturity, SplitMetadata, StageSplitsRequestExt,
+    };
+    use quickwit_proto::metastore::{
+        DeleteQuery, IndexMetadataRequest, ListSplitsRequest, PublishSplitsRequest,
+        StageSplitsRequest,
     };
-    use quickwit_proto::metastore::{DeleteQuery, IndexMetadataRequest, ListSplitsRequest};
     use quickwit_proto::search::{LeafSearchRequest, LeafSearchResponse};
     use quickwit_search::{searcher_pool_for_test, MockSearchService};
 
@@ -611,4 +614,139 @@ mod tests {
         test_sandbox.assert_quit().await;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_delete_task_planner_plans_deletes_on_cold_splits() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let index_id = "test-delete-task-planner-cold-splits";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let indexing_settings_yaml = r#"
+            merge_policy:
+                type: no_merge
+        "#;
+        let test_sandbox = TestSandbox::create(
+            index_id,
+            doc_mapping_yaml,
+            indexing_settings_yaml,
+            &["body"],
+        )
+        .await?;
+        let universe = test_sandbox.universe();
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "delete"})])
+            .await?;
+        let mut metastore = test_sandbox.metastore();
+        let index_metadata_request = IndexMetadataRequest::for_index_id(index_id.to_string());
+        let index_metadata = metastore
+            .index_metadata(index_metadata_request)
+            .await
+            .unwrap()
+            .deserialize_index_metadata()
+            .unwrap();
+        let index_uid = index_metadata.index_uid.clone();
+        let index_config = index_metadata.into_index_config();
+        let split_metadata = metastore
+            .list_splits(ListSplitsRequest::try_from_index_uid(index_uid.clone()).unwrap())
+            .await
+            .unwrap()
+            .collect_splits_metadata()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+
+        // Replaces the split with a split in cold storage, as the index lifecycle policy does.
+        let cold_storage_uri = format!("ram:///cold/{index_id}");
+        let cold_split_metadata = SplitMetadata {
+            split_id: "cold-split".to_string(),
+            storage_uri: Some(Uri::for_test(&cold_storage_uri)),
+            maturity: SplitMaturity::Mature,
+            ..split_metadata.clone()
+        };
+        let stage_splits_request =
+            StageSplitsRequest::try_from_split_metadata(index_uid.clone(), &cold_split_metadata)
+                .unwrap();
+        metastore.stage_splits(stage_splits_request).await?;
+        let publish_splits_request = PublishSplitsRequest {
+            index_uid: Some(index_uid.clone()),
+            staged_split_ids: vec![cold_split_metadata.split_id.clone()],
+            replaced_split_ids: vec![split_metadata.split_id.clone()],
+            index_checkpoint_delta_json_opt: None,
+            publish_token_opt: None,
+        };
+        metastore.publish_splits(publish_splits_request).await?;
+
+        let doc_mapper =
+            build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)?;
+        let doc_mapper_str = serde_json::to_string(&doc_mapper)?;
+        metastore
+            .create_delete_task(DeleteQuery {
+                index_uid: Some(index_uid.clone()),
+                start_timestamp: None,
+                end_timestamp: None,
+                query_ast: quickwit_query::query_ast::qast_json_helper("body:delete", &[]),
+            })
+            .await?;
+
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_leaf_search().times(1).returning(
+            move |request: LeafSearchRequest| {
+                // The leaf search reads the split from the cold storage.
+                let split_offsets = &request.split_offsets[0];
+                assert_eq!(split_offsets.split_id, "cold-split");
+                assert_eq!(
+                    split_offsets.storage_uri.as_deref(),
+                    Some(cold_storage_uri.as_str())
+                );
+                Ok(LeafSearchResponse {
+                    num_hits: 1,
+                    ..Default::default()
+                })
+            },
+        );
+        let searcher_pool = searcher_pool_for_test([("127.0.0.1:1000", mock_search_service)]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool);
+        let merge_scheduler_mailbox = universe.get_or_spawn_one();
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            universe.create_test_mailbox();
+        let delete_planner = DeleteTaskPlanner::new(
+            index_uid.clone(),
+            index_config.index_uri.clone(),
+            doc_mapper_str,
+            metastore.clone(),
+            search_job_placer,
+            merge_split_downloader_mailbox,
+            merge_scheduler_mailbox,
+        );
+        let (_delete_planner_mailbox, delete_planner_handle) =
+            universe.spawn_builder().spawn(delete_planner);
+        delete_planner_handle.process_pending_and_observe().await;
+
+        let downloader_msgs: Vec<MergeTask> = merge_split_downloader_inbox.drain_for_test_typed();
+        assert_eq!(downloader_msgs.len(), 1);
+        assert_eq!(downloader_msgs[0].splits[0].split_id(), "cold-split");
+        assert_eq!(
+            downloader_msgs[0].splits[0].storage_uri,
+            cold_split_metadata.storage_uri
+        );
+        // The delete opstamp of the cold split is only updated once the delete is applied.
+        let all_splits = metastore
+            .list_splits(ListSplitsRequest::try_from_index_uid(index_uid).unwrap())
+            .await
+            .unwrap()
+            .collect_splits_metadata()
+            .await
+            .unwrap();
+        let cold_split = all_splits
+            .iter()
+            .find(|split| split.split_id() == "cold-split")
+            .unwrap();
+        assert_eq!(cold_split.delete_opstamp, 0);
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
 }
@@ -180,6 +180,7 @@ impl DeleteTaskService {
             self.metastore.clone(),
             self.search_job_placer.clone(),
             index_storage,
+            self.storage_resolver.clone(),
             self.delete_service_task_dir.clone(),
             self.max_concurrent_split_uploads,
             self.merge_scheduler_service.clone(),
@@ -122,6 +122,7 @@ impl GarbageCollector {
             let gc_res = run_garbage_collect(
                 index_uid.clone(),
                 storage,
+                &storage_resolver,
                 metastore,
                 STAGED_GRACE_PERIOD,
                 DELETION_GRACE_PERIOD,
@@ -333,6 +334,7 @@ mod tests {
         let result = run_garbage_collect(
             index_uid,
             Arc::new(mock_storage),
+            &StorageResolver::for_test(),
             MetastoreServiceClient::from(mock_metastore),
             STAGED_GRACE_PERIOD,
             DELETION_GRACE_PERIOD,
@@ -0,0 +1,406 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use itertools::Itertools;
+use quickwit_actors::{Actor, ActorContext, Handler};
+use quickwit_common::temp_dir;
+use quickwit_config::IndexConfig;
+use quickwit_metastore::ListIndexesMetadataResponseExt;
+use quickwit_proto::metastore::{
+    ListIndexesMetadataRequest, MetastoreService, MetastoreServiceClient,
+};
+use quickwit_proto::types::IndexUid;
+use quickwit_storage::StorageResolver;
+use serde::Serialize;
+use tracing::{debug, error, info};
+
+use super::retention_policy_executor::compute_deleted_indexes;
+use crate::lifecycle_policy_execution::run_execute_lifecycle_policy;
+
+pub const LIFECYCLE_POLICY_EXECUTOR_DIR_NAME: &str = "lifecycle-policy-executor";
+
+const RUN_INTERVAL: Duration = Duration::from_secs(60 * 60); // 1 hours
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LifecyclePolicyExecutorCounters {
+    /// The number of refresh the config passes.
+    pub num_refresh_passes: usize,
+
+    /// The number of execution passes.
+    pub num_execution_passes: usize,
+
+    /// The number of splits moved to cold storage.
+    pub num_moved_splits: usize,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+#[derive(Debug)]
+struct Execute {
+    index_uid: IndexUid,
+}
+
+/// An actor for scheduling lifecycle policy execution on all indexes.
+/// It keeps a list of indexes that have a lifecycle policy configured
+/// in a cache and periodically update this list.
+pub struct LifecyclePolicyExecutor {
+    metastore: MetastoreServiceClient,
+    storage_resolver: StorageResolver,
+    /// Directory where the split files are downloaded before being uploaded to the cold storage.
+    scratch_directory: PathBuf,
+    /// A map of index_id to index config that are managed by this executor.
+    index_configs: HashMap<String, IndexConfig>,
+    counters: LifecyclePolicyExecutorCounters,
+}
+
+impl LifecyclePolicyExecutor {
+    pub async fn new(
+        metastore: MetastoreServiceClient,
+        storage_resolver: StorageResolver,
+        data_dir_path: &Path,
+    ) -> anyhow::Result<Self> {
+        let scratch_directory_path = data_dir_path.join(LIFECYCLE_POLICY_EXECUTOR_DIR_NAME);
+        let scratch_directory =
+            temp_dir::create_or_purge_directory(scratch_directory_path.as_path()).await?;
+        Ok(Self {
+            metastore,
+            storage_resolver,
+            scratch_directory,
+            index_configs: HashMap::new(),
+            counters: LifecyclePolicyExecutorCounters::default(),
+        })
+    }
+
+    /// Indexes refresh Loop handler logic.
+    /// Should not return an error to prevent the actor from crashing.
+    async fn handle_refresh_loop(&mut self, ctx: &ActorContext<Self>) {
+        debug!("loading indexes from the metastore");
+        self.counters.num_refresh_passes += 1;
+
+        let response = match self
+            .metastore
+            .list_indexes_metadata(ListIndexesMetadataRequest::all())
+            .await
+        {
+            Ok(response) => response,
+            Err(error) => {
+                error!(%error, "failed to list indexes from the metastore");
+                return;
+            }
+        };
+        let indexes = match response.deserialize_indexes_metadata().await {
+            Ok(indexes) => indexes,
+            Err(error) => {
+                error!(%error, "failed to deserialize indexes metadata");
+                return;
+            }
+        };
+        let deleted_indexes = compute_deleted_indexes(
+            self.index_configs.keys().map(String::as_str),
+            indexes
+                .iter()
+                .map(|index_metadata| index_metadata.index_id()),
+        );
+        if !deleted_indexes.is_empty() {
+            debug!(index_ids=%deleted_indexes.iter().join(", "), "deleting indexes from cache");
+            for index_id in deleted_indexes {
+                self.index_configs.remove(&index_id);
+            }
+        }
+        for index_metadata in indexes {
+            let index_uid = index_metadata.index_uid.clone();
+            let index_config = index_metadata.into_index_config();
+            // We only care about indexes with a lifecycle policy configured.
+            let Some(lifecycle_policy) = &index_config.lifecycle_policy_opt else {
+                self.index_configs.remove(&index_config.index_id);
+                continue;
+            };
+            // Insert or update the index in the cache.
+            if let Some(value) = self.index_configs.get_mut(&index_config.index_id) {
+                *value = index_config;
+                continue;
+            }
+            if let Ok(next_interval) = lifecycle_policy.duration_until_next_evaluation() {
+                let message = Execute { index_uid };
+                info!(index_id=?index_config.index_id, scheduled_in=?next_interval, "lifecycle-policy-schedule-operation");
+                // Inserts & schedule the index's first lifecycle policy execution.
+                self.index_configs
+                    .insert(index_config.index_id.clone(), index_config);
+                ctx.schedule_self_msg(next_interval, message);
+            } else {
+                error!(index_id=%index_config.index_id, "couldn't extract the index next schedule time");
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Actor for LifecyclePolicyExecutor {
+    type ObservableState = LifecyclePolicyExecutorCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "LifecyclePolicyExecutor".to_string()
+    }
+
+    async fn initialize(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle(Loop, ctx).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for LifecyclePolicyExecutor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Loop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle_refresh_loop(ctx).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Execute> for LifecyclePolicyExecutor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: Execute,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        info!(index_id=%message.index_uid.index_id, "lifecycle-policy-execute-operation");
+        self.counters.num_execution_passes += 1;
+
+        let Some(index_config) = self.index_configs.get(&message.index_uid.index_id) else {
+            debug!(index_id=%message.index_uid.index_id, "the index might have been deleted");
+            return Ok(());
+        };
+        let lifecycle_policy = index_config
+            .lifecycle_policy_opt
+            .as_ref()
+            .expect("index should have a lifecycle policy configured");
+
+        let execution_result = run_execute_lifecycle_policy(
+            message.index_uid.clone(),
+            &index_config.index_uri,
+            self.metastore.clone(),
+            &self.storage_resolver,
+            &self.scratch_directory,
+            lifecycle_policy,
+            ctx,
+        )
+        .await;
+        match execution_result {
+            Ok(splits) => self.counters.num_moved_splits += splits.len(),
+            Err(error) => {
+                error!(index_id=%message.index_uid.index_id, error=?error, "failed to execute the lifecycle policy on the index");
+            }
+        }
+        if let Ok(next_interval) = lifecycle_policy.duration_until_next_evaluation() {
+            info!(index_id=?index_config.index_id, scheduled_in=?next_interval, "lifecycle-policy-schedule-operation");
+            ctx.schedule_self_msg(next_interval, message);
+        } else {
+            // The index is added back and rescheduled by the next cache refresh loop.
+            self.index_configs.remove(&message.index_uid.index_id);
+            error!(index_id=%message.index_uid.index_id, "couldn't extract the index next schedule interval");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::RangeInclusive;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+
+    use quickwit_actors::Universe;
+    use quickwit_common::uri::Uri;
+    use quickwit_common::{split_file, ServiceStream};
+    use quickwit_config::LifecyclePolicy;
+    use quickwit_metastore::{
+        IndexMetadata, ListSplitsRequestExt, ListSplitsResponseExt, Split, SplitMaturity,
+        SplitMetadata, SplitState, StageSplitsRequestExt,
+    };
+    use quickwit_proto::metastore::{
+        EmptyResponse, ListIndexesMetadataResponse, ListSplitsResponse,
+    };
+
+    use super::*;
+
+    const EVALUATION_SCHEDULE: &str = "hourly";
+
+    fn lifecycle_policy_for_test(index_id: &str) -> LifecyclePolicy {
+        LifecyclePolicy {
+            cold_after: "1 hour".to_string(),
+            cold_storage_uri: Uri::from_str(&format!("ram:///cold/{index_id}")).unwrap(),
+            evaluation_schedule: EVALUATION_SCHEDULE.to_string(),
+        }
+    }
+
+    fn make_indexes(index_ids: &[(&str, bool)]) -> Vec<IndexMetadata> {
+        index_ids
+            .iter()
+            .map(|(index_id, has_lifecycle_policy)| {
+                let mut index_config =
+                    IndexConfig::for_test(index_id, &format!("ram:///indexes/{index_id}"));
+                if *has_lifecycle_policy {
+                    index_config.lifecycle_policy_opt = Some(lifecycle_policy_for_test(index_id));
+                }
+                IndexMetadata::new(index_config)
+            })
+            .collect()
+    }
+
+    fn make_split(
+        split_id: &str,
+        time_range: Option<RangeInclusive<i64>>,
+        storage_uri_opt: Option<&str>,
+    ) -> Split {
+        Split {
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                footer_offsets: 5..20,
+                time_range,
+                storage_uri: storage_uri_opt.map(|storage_uri| Uri::for_test(storage_uri)),
+                ..Default::default()
+            },
+            split_state: SplitState::Published,
+            update_timestamp: 0,
+            publish_timestamp: Some(100),
+        }
+    }
+
+    fn shift_time_by() -> Duration {
+        lifecycle_policy_for_test("index-1")
+            .duration_until_next_evaluation()
+            .unwrap()
+            + Duration::from_secs(1)
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_policy_execution_moves_aged_splits() -> anyhow::Result<()> {
+        let storage_resolver = StorageResolver::for_test();
+        let index_storage = storage_resolver
+            .resolve(&Uri::for_test("ram:///indexes/index-1"))
+            .await?;
+        index_storage
+            .put(Path::new("split-1.split"), Box::new(b"split-1".to_vec()))
+            .await?;
+
+        let mut mock_metastore = MetastoreServiceClient::mock();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .times(..)
+            .returning(|_list_indexes_request| {
+                let indexes_metadata = make_indexes(&[("index-1", true), ("index-2", false)]);
+                Ok(ListIndexesMetadataResponse::for_test(indexes_metadata))
+            });
+        mock_metastore
+            .expect_list_splits()
+            .times(1)
+            .returning(|list_splits_request| {
+                let query = list_splits_request.deserialize_list_splits_query().unwrap();
+                assert_eq!(query.index_uids[0].index_id, "index-1");
+                assert_eq!(query.split_states, &[SplitState::Published]);
+                let splits = vec![
+                    make_split("split-1", Some(1000..=5000), None),
+                    make_split("split-2", Some(1000..=5000), Some("ram:///cold/index-1")),
+                    make_split("split-3", None, None),
+                ];
+                let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+                Ok(ServiceStream::from(vec![Ok(splits_response)]))
+            });
+        let cold_split_id_opt: Arc<Mutex<Option<String>>> = Arc::default();
+        let cold_split_id_opt_clone = cold_split_id_opt.clone();
+        mock_metastore
+            .expect_stage_splits()
+            .times(1)
+            .returning(move |stage_splits_request| {
+                let splits_metadata = stage_splits_request.deserialize_splits_metadata().unwrap();
+                assert_eq!(splits_metadata.len(), 1);
+                assert_ne!(splits_metadata[0].split_id, "split-1");
+                assert_eq!(
+                    splits_metadata[0].storage_uri,
+                    Some(Uri::for_test("ram:///cold/index-1"))
+                );
+                assert_eq!(splits_metadata[0].maturity, SplitMaturity::Mature);
+                assert_eq!(splits_metadata[0].time_range, Some(1000..=5000));
+                *cold_split_id_opt_clone.lock().unwrap() =
+                    Some(splits_metadata[0].split_id.clone());
+                Ok(EmptyResponse {})
+            });
+        mock_metastore
+            .expect_publish_splits()
+            .times(1)
+            .returning(|publish_splits_request| {
+                assert_eq!(publish_splits_request.index_uid().index_id, "index-1");
+                assert_eq!(publish_splits_request.staged_split_ids.len(), 1);
+                assert_eq!(publish_splits_request.replaced_split_ids, ["split-1"]);
+                Ok(EmptyResponse {})
+            });
+
+        let temp_dir = tempfile::tempdir()?;
+        let lifecycle_policy_executor = LifecyclePolicyExecutor::new(
+            MetastoreServiceClient::from(mock_metastore),
+            storage_resolver.clone(),
+            temp_dir.path(),
+        )
+        .await?;
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(lifecycle_policy_executor);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_execution_passes, 0);
+
+        universe.sleep(shift_time_by()).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_execution_passes, 1);
+        assert_eq!(counters.num_moved_splits, 1);
+
+        let cold_split_id = cold_split_id_opt.lock().unwrap().clone().unwrap();
+        let cold_storage = storage_resolver
+            .resolve(&Uri::for_test("ram:///cold/index-1"))
+            .await?;
+        let cold_split_bytes = cold_storage
+            .get_all(Path::new(&split_file(cold_split_id)))
+            .await?;
+        assert_eq!(cold_split_bytes.as_slice(), b"split-1");
+
+        universe.assert_quit().await;
+        Ok(())
+    }
+}
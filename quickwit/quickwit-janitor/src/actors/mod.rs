@@ -21,8 +21,12 @@ mod delete_task_pipeline;
 mod delete_task_planner;
 mod delete_task_service;
 mod garbage_collector;
+mod lifecycle_policy_executor;
 mod retention_policy_executor;
+mod rollup_executor;
 
 pub use delete_task_service::{DeleteTaskService, DELETE_SERVICE_TASK_DIR_NAME};
 pub use garbage_collector::GarbageCollector;
+pub use lifecycle_policy_executor::{LifecyclePolicyExecutor, LIFECYCLE_POLICY_EXECUTOR_DIR_NAME};
 pub use retention_policy_executor::RetentionPolicyExecutor;
+pub use rollup_executor::RollupExecutor;
@@ -32,7 +32,8 @@ use quickwit_proto::types::IndexUid;
 use serde::Serialize;
 use tracing::{debug, error, info};
 
-use crate::retention_policy_execution::run_execute_retention_policy;
+use crate::retention_policy_execution::{run_execute_retention_policy, run_execute_retention_rule};
+use crate::rollup_execution::fetch_rolled_up_until;
 
 const RUN_INTERVAL: Duration = Duration::from_secs(60 * 60); // 1 hours
 
@@ -46,6 +47,9 @@ pub struct RetentionPolicyExecutorCounters {
 
     /// The number of expired splits.
     pub num_expired_splits: usize,
+
+    /// The number of delete tasks created by retention rules.
+    pub num_retention_delete_tasks: usize,
 }
 
 #[derive(Debug)]
@@ -212,10 +216,30 @@ impl Handler<Execute> for RetentionPolicyExecutor {
             .as_ref()
             .expect("Expected index to have retention policy configure.");
 
+        // Documents are retained until the rollup policy of the index has rolled them up.
+        let rolled_up_until_opt = if let Some(rollup_policy) = &index_config.rollup_policy_opt {
+            let rolled_up_until_result = ctx
+                .protect_future(fetch_rolled_up_until(
+                    &mut self.metastore,
+                    message.index_uid.clone(),
+                    rollup_policy,
+                ))
+                .await;
+            match rolled_up_until_result {
+                Ok(rolled_up_until_opt) => Some(rolled_up_until_opt.unwrap_or(i64::MIN)),
+                Err(error) => {
+                    error!(index_id=%message.index_uid.index_id, error=?error, "Failed to fetch the rollup checkpoint of the index.");
+                    Some(i64::MIN)
+                }
+            }
+        } else {
+            None
+        };
         let execution_result = run_execute_retention_policy(
             message.index_uid.clone(),
             self.metastore.clone(),
             retention_policy,
+            rolled_up_until_opt,
             ctx,
         )
         .await;
@@ -225,6 +249,25 @@ impl Handler<Execute> for RetentionPolicyExecutor {
                 error!(index_id=%message.index_uid.index_id, error=?error, "Failed to execute the retention policy on the index.")
             }
         }
+        for retention_rule in &retention_policy.rules {
+            let execution_result = run_execute_retention_rule(
+                message.index_uid.clone(),
+                self.metastore.clone(),
+                retention_rule,
+                rolled_up_until_opt,
+                ctx,
+            )
+            .await;
+            match execution_result {
+                Ok((splits, delete_task_opt)) => {
+                    self.counters.num_expired_splits += splits.len();
+                    self.counters.num_retention_delete_tasks += delete_task_opt.iter().count();
+                }
+                Err(error) => {
+                    error!(index_id=%message.index_uid.index_id, tag=%retention_rule.tag, error=?error, "Failed to execute the retention rule on the index.")
+                }
+            }
+        }
 
         if let Ok(next_interval) = retention_policy.duration_until_next_evaluation() {
             info!(index_id=?index_config.index_id, scheduled_in=?next_interval, "retention-policy-schedule-operation");
@@ -241,7 +284,7 @@ impl Handler<Execute> for RetentionPolicyExecutor {
 }
 
 /// Extract the list of deleted indexes.
-fn compute_deleted_indexes<'a>(
+pub(super) fn compute_deleted_indexes<'a>(
     cached_indexes: impl Iterator<Item = &'a str>,
     indexes: impl Iterator<Item = &'a str>,
 ) -> HashSet<String> {
@@ -260,13 +303,14 @@ mod tests {
     use mockall::Sequence;
     use quickwit_actors::Universe;
     use quickwit_common::ServiceStream;
-    use quickwit_config::RetentionPolicy;
+    use quickwit_config::{RetentionPolicy, RetentionRule};
     use quickwit_metastore::{
         IndexMetadata, ListSplitsRequestExt, ListSplitsResponseExt, Split, SplitMetadata,
         SplitState,
     };
     use quickwit_proto::metastore::{
-        EmptyResponse, ListIndexesMetadataResponse, ListSplitsResponse,
+        DeleteQuery, DeleteTask, EmptyResponse, ListDeleteTasksResponse,
+        ListIndexesMetadataResponse, ListSplitsResponse,
     };
 
     use super::*;
@@ -313,6 +357,7 @@ mod tests {
             index.retention_policy_opt = Some(RetentionPolicy {
                 retention_period: retention_period.to_string(),
                 evaluation_schedule: EVALUATION_SCHEDULE.to_string(),
+                rules: Vec::new(),
             })
         }
         index
@@ -346,6 +391,7 @@ mod tests {
         let scheduler = RetentionPolicy {
             retention_period: "".to_string(),
             evaluation_schedule: EVALUATION_SCHEDULE.to_string(),
+            rules: Vec::new(),
         };
 
         scheduler.duration_until_next_evaluation().unwrap() + Duration::from_secs(1)
@@ -504,4 +550,206 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_retention_rule_execution_calls_dependencies() -> anyhow::Result<()> {
+        let mut mock_metastore = MetastoreServiceClient::mock();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .times(..)
+            .returning(|_list_indexes_request| {
+                let mut index_config = make_index("index-1", Some("10 years"));
+                index_config
+                    .retention_policy_opt
+                    .as_mut()
+                    .unwrap()
+                    .rules
+                    .push(RetentionRule {
+                        tag: "owner:free".to_string(),
+                        retention_period: "1 hour".to_string(),
+                    });
+                let indexes_metadata = vec![IndexMetadata::new(index_config)];
+                Ok(ListIndexesMetadataResponse::for_test(indexes_metadata))
+            });
+
+        mock_metastore
+            .expect_list_splits()
+            .times(2)
+            .returning(|list_splits_request| {
+                let query = list_splits_request.deserialize_list_splits_query().unwrap();
+                assert_eq!(query.split_states, &[SplitState::Published]);
+                // Only the retention rule filters splits on their tags.
+                if query.tags.is_none() {
+                    return Ok(ServiceStream::empty());
+                }
+                let mut exclusive_split = make_split("split-1", Some(1000..=5000));
+                exclusive_split.split_metadata.tags =
+                    ["owner!".to_string(), "owner:free".to_string()].into();
+                let mut mixed_split = make_split("split-2", Some(1000..=5000));
+                mixed_split.split_metadata.tags = [
+                    "owner!".to_string(),
+                    "owner:free".to_string(),
+                    "owner:paid".to_string(),
+                ]
+                .into();
+                let untagged_split = make_split("split-3", None);
+                let splits = vec![exclusive_split, mixed_split, untagged_split];
+                let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+                Ok(ServiceStream::from(vec![Ok(splits_response)]))
+            });
+
+        mock_metastore
+            .expect_mark_splits_for_deletion()
+            .times(1)
+            .returning(|mark_splits_for_deletion_request| {
+                assert_eq!(mark_splits_for_deletion_request.split_ids, ["split-1"]);
+                Ok(EmptyResponse {})
+            });
+
+        mock_metastore
+            .expect_list_delete_tasks()
+            .times(1)
+            .returning(|_| Ok(ListDeleteTasksResponse::default()));
+
+        mock_metastore
+            .expect_create_delete_task()
+            .times(1)
+            .returning(|delete_query| {
+                assert_eq!(delete_query.index_uid.as_ref().unwrap().index_id, "index-1");
+                assert!(delete_query.start_timestamp.is_none());
+                assert!(delete_query.end_timestamp.is_some());
+                let query_ast: serde_json::Value =
+                    serde_json::from_str(&delete_query.query_ast).unwrap();
+                assert_eq!(
+                    query_ast,
+                    serde_json::json!({"type": "term", "field": "owner", "value": "free"})
+                );
+                Ok(DeleteTask {
+                    create_timestamp: 0,
+                    opstamp: 1,
+                    delete_query: Some(delete_query),
+                })
+            });
+
+        let retention_policy_executor =
+            RetentionPolicyExecutor::new(MetastoreServiceClient::from(mock_metastore));
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(retention_policy_executor);
+
+        universe.sleep(shift_time_by()).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_execution_passes, 1);
+        assert_eq!(counters.num_expired_splits, 1);
+        assert_eq!(counters.num_retention_delete_tasks, 1);
+        universe.assert_quit().await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retention_rule_execution_skips_covered_documents() -> anyhow::Result<()> {
+        let query_ast_json = r#"{"type":"term","field":"owner","value":"free"}"#;
+
+        let mut mock_metastore = MetastoreServiceClient::mock();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .times(..)
+            .returning(|_list_indexes_request| {
+                let mut index_config = make_index("index-1", Some("10 years"));
+                index_config
+                    .retention_policy_opt
+                    .as_mut()
+                    .unwrap()
+                    .rules
+                    .push(RetentionRule {
+                        tag: "owner:free".to_string(),
+                        retention_period: "1 hour".to_string(),
+                    });
+                let indexes_metadata = vec![IndexMetadata::new(index_config)];
+                Ok(ListIndexesMetadataResponse::for_test(indexes_metadata))
+            });
+
+        // The first evaluation only finds the documents of `split-1`, which are covered by the
+        // existing delete task. The second evaluation finds the documents of `split-2`, which
+        // expired after the end of the existing delete task.
+        let mut sequence = Sequence::new();
+        for split_ids in [&["split-1"][..], &["split-1", "split-2"][..]] {
+            mock_metastore
+                .expect_list_splits()
+                .times(1)
+                .in_sequence(&mut sequence)
+                .returning(|_| Ok(ServiceStream::empty()));
+            mock_metastore
+                .expect_list_delete_tasks()
+                .times(1)
+                .in_sequence(&mut sequence)
+                .returning(move |_| {
+                    let delete_task = DeleteTask {
+                        create_timestamp: 0,
+                        opstamp: 1,
+                        delete_query: Some(DeleteQuery {
+                            index_uid: Some(IndexUid::for_test("index-1", 0)),
+                            start_timestamp: None,
+                            end_timestamp: Some(6000),
+                            query_ast: query_ast_json.to_string(),
+                        }),
+                    };
+                    Ok(ListDeleteTasksResponse {
+                        delete_tasks: vec![delete_task],
+                    })
+                });
+            mock_metastore
+                .expect_list_splits()
+                .times(1)
+                .in_sequence(&mut sequence)
+                .returning(move |_| {
+                    let splits = split_ids
+                        .iter()
+                        .zip([1000..=5000, 5000..=7000])
+                        .map(|(split_id, time_range)| {
+                            let mut mixed_split = make_split(split_id, Some(time_range));
+                            mixed_split.split_metadata.tags = [
+                                "owner!".to_string(),
+                                "owner:free".to_string(),
+                                "owner:paid".to_string(),
+                            ]
+                            .into();
+                            mixed_split
+                        })
+                        .collect();
+                    let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+                    Ok(ServiceStream::from(vec![Ok(splits_response)]))
+                });
+        }
+        mock_metastore
+            .expect_create_delete_task()
+            .times(1)
+            .returning(|delete_query| {
+                assert_eq!(delete_query.start_timestamp, Some(6000));
+                assert!(delete_query.end_timestamp.unwrap() > 6000);
+                Ok(DeleteTask {
+                    create_timestamp: 0,
+                    opstamp: 2,
+                    delete_query: Some(delete_query),
+                })
+            });
+
+        let retention_policy_executor =
+            RetentionPolicyExecutor::new(MetastoreServiceClient::from(mock_metastore));
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(retention_policy_executor);
+
+        universe.sleep(shift_time_by()).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_execution_passes, 1);
+        assert_eq!(counters.num_retention_delete_tasks, 0);
+
+        universe.sleep(shift_time_by()).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_execution_passes, 2);
+        assert_eq!(counters.num_retention_delete_tasks, 1);
+        universe.assert_quit().await;
+
+        Ok(())
+    }
 }
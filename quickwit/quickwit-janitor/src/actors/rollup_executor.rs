@@ -0,0 +1,398 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use itertools::Itertools;
+use quickwit_actors::{Actor, ActorContext, Handler};
+use quickwit_config::IndexConfig;
+use quickwit_ingest::IngestServiceClient;
+use quickwit_metastore::ListIndexesMetadataResponseExt;
+use quickwit_proto::metastore::{
+    ListIndexesMetadataRequest, MetastoreService, MetastoreServiceClient,
+};
+use quickwit_proto::types::IndexUid;
+use quickwit_search::SearchService;
+use serde::Serialize;
+use tracing::{debug, error, info};
+
+use super::retention_policy_executor::compute_deleted_indexes;
+use crate::rollup_execution::run_execute_rollup_policy;
+
+const RUN_INTERVAL: Duration = Duration::from_secs(60 * 60); // 1 hours
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RollupExecutorCounters {
+    /// The number of refresh the config passes.
+    pub num_refresh_passes: usize,
+
+    /// The number of execution passes.
+    pub num_execution_passes: usize,
+
+    /// The number of time windows rolled up.
+    pub num_rolled_up_windows: usize,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+#[derive(Debug)]
+struct Execute {
+    index_uid: IndexUid,
+}
+
+/// An actor for scheduling rollup policy execution on all indexes.
+/// It keeps a list of indexes that have a rollup policy configured
+/// in a cache and periodically update this list.
+pub struct RollupExecutor {
+    metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+    ingest_service: IngestServiceClient,
+    /// A map of index_id to index config that are managed by this executor.
+    index_configs: HashMap<String, IndexConfig>,
+    counters: RollupExecutorCounters,
+}
+
+impl RollupExecutor {
+    pub fn new(
+        metastore: MetastoreServiceClient,
+        search_service: Arc<dyn SearchService>,
+        ingest_service: IngestServiceClient,
+    ) -> Self {
+        Self {
+            metastore,
+            search_service,
+            ingest_service,
+            index_configs: HashMap::new(),
+            counters: RollupExecutorCounters::default(),
+        }
+    }
+
+    /// Indexes refresh Loop handler logic.
+    /// Should not return an error to prevent the actor from crashing.
+    async fn handle_refresh_loop(&mut self, ctx: &ActorContext<Self>) {
+        debug!("loading indexes from the metastore");
+        self.counters.num_refresh_passes += 1;
+
+        let response = match self
+            .metastore
+            .list_indexes_metadata(ListIndexesMetadataRequest::all())
+            .await
+        {
+            Ok(response) => response,
+            Err(error) => {
+                error!(%error, "failed to list indexes from the metastore");
+                return;
+            }
+        };
+        let indexes = match response.deserialize_indexes_metadata().await {
+            Ok(indexes) => indexes,
+            Err(error) => {
+                error!(%error, "failed to deserialize indexes metadata");
+                return;
+            }
+        };
+        let deleted_indexes = compute_deleted_indexes(
+            self.index_configs.keys().map(String::as_str),
+            indexes
+                .iter()
+                .map(|index_metadata| index_metadata.index_id()),
+        );
+        if !deleted_indexes.is_empty() {
+            debug!(index_ids=%deleted_indexes.iter().join(", "), "deleting indexes from cache");
+            for index_id in deleted_indexes {
+                self.index_configs.remove(&index_id);
+            }
+        }
+        for index_metadata in indexes {
+            let index_uid = index_metadata.index_uid.clone();
+            let index_config = index_metadata.into_index_config();
+            // We only care about indexes with a rollup policy configured.
+            let Some(rollup_policy) = &index_config.rollup_policy_opt else {
+                self.index_configs.remove(&index_config.index_id);
+                continue;
+            };
+            // Insert or update the index in the cache.
+            if let Some(value) = self.index_configs.get_mut(&index_config.index_id) {
+                *value = index_config;
+                continue;
+            }
+            if let Ok(next_interval) = rollup_policy.duration_until_next_evaluation() {
+                let message = Execute { index_uid };
+                info!(index_id=?index_config.index_id, scheduled_in=?next_interval, "rollup-policy-schedule-operation");
+                // Inserts & schedule the index's first rollup policy execution.
+                self.index_configs
+                    .insert(index_config.index_id.clone(), index_config);
+                ctx.schedule_self_msg(next_interval, message);
+            } else {
+                error!(index_id=%index_config.index_id, "couldn't extract the index next schedule time");
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Actor for RollupExecutor {
+    type ObservableState = RollupExecutorCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "RollupExecutor".to_string()
+    }
+
+    async fn initialize(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle(Loop, ctx).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for RollupExecutor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Loop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle_refresh_loop(ctx).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Execute> for RollupExecutor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: Execute,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        info!(index_id=%message.index_uid.index_id, "rollup-policy-execute-operation");
+        self.counters.num_execution_passes += 1;
+
+        let Some(index_config) = self.index_configs.get(&message.index_uid.index_id) else {
+            debug!(index_id=%message.index_uid.index_id, "the index might have been deleted");
+            return Ok(());
+        };
+        let rollup_policy = index_config
+            .rollup_policy_opt
+            .as_ref()
+            .expect("index should have a rollup policy configured");
+
+        let execution_result = run_execute_rollup_policy(
+            message.index_uid.clone(),
+            self.metastore.clone(),
+            &*self.search_service,
+            self.ingest_service.clone(),
+            rollup_policy,
+            ctx,
+        )
+        .await;
+        match execution_result {
+            Ok(num_windows) => self.counters.num_rolled_up_windows += num_windows,
+            Err(error) => {
+                error!(index_id=%message.index_uid.index_id, error=?error, "failed to execute the rollup policy on the index");
+            }
+        }
+        if let Ok(next_interval) = rollup_policy.duration_until_next_evaluation() {
+            info!(index_id=?index_config.index_id, scheduled_in=?next_interval, "rollup-policy-schedule-operation");
+            ctx.schedule_self_msg(next_interval, message);
+        } else {
+            // The index is added back and rescheduled by the next cache refresh loop.
+            self.index_configs.remove(&message.index_uid.index_id);
+            error!(index_id=%message.index_uid.index_id, "couldn't extract the index next schedule interval");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_common::ServiceStream;
+    use quickwit_config::{RollupPolicy, ROLLUP_SOURCE_ID};
+    use quickwit_ingest::{CommitType, IngestResponse, IngestService};
+    use quickwit_metastore::checkpoint::IndexCheckpointDelta;
+    use quickwit_metastore::{
+        IndexMetadata, IndexMetadataResponseExt, ListSplitsResponseExt, PublishSplitsRequestExt,
+        Split, SplitMetadata, SplitState,
+    };
+    use quickwit_proto::metastore::{
+        EmptyResponse, IndexMetadataResponse, ListIndexesMetadataResponse, ListSplitsResponse,
+    };
+    use quickwit_proto::search::{SearchPriority, SearchResponse};
+    use quickwit_proto::types::Position;
+    use quickwit_search::MockSearchService;
+    use serde_json::json;
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    fn rollup_policy_for_test() -> RollupPolicy {
+        RollupPolicy {
+            target_index_id: "index-1-rollup".to_string(),
+            window: "1 hour".to_string(),
+            delay: "0s".to_string(),
+            query: "*".to_string(),
+            aggregation: json!({"per_host": {"terms": {"field": "host"}}}),
+            evaluation_schedule: "hourly".to_string(),
+        }
+    }
+
+    fn make_index_metadata() -> IndexMetadata {
+        let mut index_config = IndexConfig::for_test("index-1", "ram:///indexes/index-1");
+        index_config.rollup_policy_opt = Some(rollup_policy_for_test());
+        IndexMetadata::new(index_config)
+    }
+
+    #[tokio::test]
+    async fn test_rollup_executor_rolls_up_complete_windows() -> anyhow::Result<()> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        let mut mock_metastore = MetastoreServiceClient::mock();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .times(..)
+            .returning(|_list_indexes_request| {
+                let indexes_metadata = vec![make_index_metadata()];
+                Ok(ListIndexesMetadataResponse::for_test(indexes_metadata))
+            });
+        mock_metastore
+            .expect_index_metadata()
+            .times(1)
+            .returning(|_index_metadata_request| {
+                let index_metadata = make_index_metadata();
+                Ok(IndexMetadataResponse::try_from_index_metadata(&index_metadata).unwrap())
+            });
+        mock_metastore
+            .expect_list_splits()
+            .times(1)
+            .returning(move |_list_splits_request| {
+                let split = Split {
+                    split_metadata: SplitMetadata {
+                        split_id: "split-1".to_string(),
+                        time_range: Some(now - 2 * 3_600..=now),
+                        ..Default::default()
+                    },
+                    split_state: SplitState::Published,
+                    update_timestamp: 0,
+                    publish_timestamp: Some(0),
+                };
+                let splits_response = ListSplitsResponse::try_from_splits(vec![split]).unwrap();
+                Ok(ServiceStream::from(vec![Ok(splits_response)]))
+            });
+        mock_metastore
+            .expect_publish_splits()
+            .times(1)
+            .returning(move |publish_splits_request| {
+                assert_eq!(publish_splits_request.index_uid().index_id, "index-1");
+                assert!(publish_splits_request.staged_split_ids.is_empty());
+                let checkpoint_delta: IndexCheckpointDelta = publish_splits_request
+                    .deserialize_index_checkpoint()
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(checkpoint_delta.source_id, ROLLUP_SOURCE_ID);
+
+                // The two complete windows are checkpointed together.
+                let (_, partition_delta) = checkpoint_delta.source_delta.iter().next().unwrap();
+                assert_eq!(partition_delta.from, Position::Beginning);
+                let window_end = partition_delta.to.as_u64().unwrap() as i64;
+                assert_eq!(window_end % 3_600, 0);
+                assert!(window_end <= now);
+                Ok(EmptyResponse {})
+            });
+
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(2)
+            .returning(|search_request| {
+                assert_eq!(search_request.index_id_patterns, ["index-1"]);
+                assert_eq!(search_request.max_hits, 0);
+                assert_eq!(search_request.priority, SearchPriority::Batch as i32);
+                let window_start = search_request.start_timestamp.unwrap();
+                assert_eq!(search_request.end_timestamp, Some(window_start + 3_600));
+                assert_eq!(window_start % 3_600, 0);
+
+                let aggregation = json!({
+                    "per_host": {
+                        "buckets": [
+                            {"key": "web-1", "doc_count": 2},
+                            {"key": "web-2", "doc_count": 1}
+                        ]
+                    }
+                });
+                Ok(SearchResponse {
+                    num_hits: 3,
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+
+        let mut mock_ingest_service = IngestServiceClient::mock();
+        mock_ingest_service
+            .expect_ingest()
+            .times(1)
+            .returning(|ingest_request| {
+                assert_eq!(ingest_request.commit(), CommitType::WaitFor);
+                assert_eq!(ingest_request.doc_batches.len(), 1);
+                let doc_batch = &ingest_request.doc_batches[0];
+                assert_eq!(doc_batch.index_id, "index-1-rollup");
+                assert_eq!(doc_batch.num_docs(), 4);
+                Ok(IngestResponse {
+                    num_docs_for_processing: 4,
+                })
+            });
+
+        let rollup_executor = RollupExecutor::new(
+            MetastoreServiceClient::from(mock_metastore),
+            Arc::new(mock_search_service),
+            IngestServiceClient::from(mock_ingest_service),
+        );
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(rollup_executor);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_execution_passes, 0);
+
+        let shift_time_by = rollup_policy_for_test()
+            .duration_until_next_evaluation()
+            .unwrap()
+            + Duration::from_secs(1);
+        universe.sleep(shift_time_by).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_execution_passes, 1);
+        assert_eq!(counters.num_rolled_up_windows, 2);
+
+        universe.assert_quit().await;
+        Ok(())
+    }
+}
@@ -23,12 +23,17 @@ use quickwit_actors::{
 };
 use serde_json::{json, Value as JsonValue};
 
-use crate::actors::{DeleteTaskService, GarbageCollector, RetentionPolicyExecutor};
+use crate::actors::{
+    DeleteTaskService, GarbageCollector, LifecyclePolicyExecutor, RetentionPolicyExecutor,
+    RollupExecutor,
+};
 
 pub struct JanitorService {
     delete_task_service_handle: Option<ActorHandle<DeleteTaskService>>,
     garbage_collector_handle: ActorHandle<GarbageCollector>,
     retention_policy_executor_handle: ActorHandle<RetentionPolicyExecutor>,
+    lifecycle_policy_executor_handle: ActorHandle<LifecyclePolicyExecutor>,
+    rollup_executor_handle_opt: Option<ActorHandle<RollupExecutor>>,
 }
 
 impl JanitorService {
@@ -36,11 +41,15 @@ impl JanitorService {
         delete_task_service_handle: Option<ActorHandle<DeleteTaskService>>,
         garbage_collector_handle: ActorHandle<GarbageCollector>,
         retention_policy_executor_handle: ActorHandle<RetentionPolicyExecutor>,
+        lifecycle_policy_executor_handle: ActorHandle<LifecyclePolicyExecutor>,
+        rollup_executor_handle_opt: Option<ActorHandle<RollupExecutor>>,
     ) -> Self {
         Self {
             delete_task_service_handle,
             garbage_collector_handle,
             retention_policy_executor_handle,
+            lifecycle_policy_executor_handle,
+            rollup_executor_handle_opt,
         }
     }
 
@@ -52,6 +61,13 @@ impl JanitorService {
             })
             && self.garbage_collector_handle.state() != ActorState::Failure
             && self.retention_policy_executor_handle.state() != ActorState::Failure
+            && self.lifecycle_policy_executor_handle.state() != ActorState::Failure
+            && self
+                .rollup_executor_handle_opt
+                .as_ref()
+                .map_or(true, |rollup_executor_handle| {
+                    rollup_executor_handle.state() != ActorState::Failure
+                })
     }
 }
 
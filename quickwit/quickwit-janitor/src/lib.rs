@@ -19,31 +19,40 @@
 
 #![deny(clippy::disallowed_methods)]
 
+use std::sync::Arc;
+
 use quickwit_actors::{Mailbox, Universe};
 use quickwit_common::pubsub::EventBroker;
 use quickwit_config::NodeConfig;
 use quickwit_indexing::actors::MergeSchedulerService;
+use quickwit_ingest::IngestServiceClient;
 use quickwit_metastore::SplitInfo;
 use quickwit_proto::metastore::MetastoreServiceClient;
-use quickwit_search::SearchJobPlacer;
+use quickwit_search::{SearchJobPlacer, SearchService};
 use quickwit_storage::StorageResolver;
 use tracing::info;
 
 pub mod actors;
 pub mod error;
 mod janitor_service;
+mod lifecycle_policy_execution;
 mod metrics;
 mod retention_policy_execution;
+mod rollup_execution;
 
 pub use janitor_service::JanitorService;
 
-use crate::actors::{DeleteTaskService, GarbageCollector, RetentionPolicyExecutor};
+use crate::actors::{
+    DeleteTaskService, GarbageCollector, LifecyclePolicyExecutor, RetentionPolicyExecutor,
+    RollupExecutor,
+};
 
 #[derive(utoipa::OpenApi)]
 #[openapi(components(schemas(SplitInfo)))]
 /// Schema used for the OpenAPI generation which are apart of this crate.
 pub struct JanitorApiSchemas;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn start_janitor_service(
     universe: &Universe,
     config: &NodeConfig,
@@ -52,6 +61,8 @@ pub async fn start_janitor_service(
     storage_resolver: StorageResolver,
     event_broker: EventBroker,
     run_delete_task_service: bool,
+    search_service_opt: Option<Arc<dyn SearchService>>,
+    ingest_service_opt: Option<IngestServiceClient>,
 ) -> anyhow::Result<Mailbox<JanitorService>> {
     info!("starting janitor service");
     let garbage_collector = GarbageCollector::new(metastore.clone(), storage_resolver.clone());
@@ -60,6 +71,26 @@ pub async fn start_janitor_service(
     let retention_policy_executor = RetentionPolicyExecutor::new(metastore.clone());
     let (_, retention_policy_executor_handle) =
         universe.spawn_builder().spawn(retention_policy_executor);
+
+    let lifecycle_policy_executor = LifecyclePolicyExecutor::new(
+        metastore.clone(),
+        storage_resolver.clone(),
+        &config.data_dir_path,
+    )
+    .await?;
+    let (_, lifecycle_policy_executor_handle) =
+        universe.spawn_builder().spawn(lifecycle_policy_executor);
+    let rollup_executor_handle_opt = if let (Some(search_service), Some(ingest_service)) =
+        (search_service_opt, ingest_service_opt)
+    {
+        let rollup_executor =
+            RollupExecutor::new(metastore.clone(), search_service, ingest_service);
+        let (_, rollup_executor_handle) = universe.spawn_builder().spawn(rollup_executor);
+        Some(rollup_executor_handle)
+    } else {
+        info!("rollup executor is disabled: rollup policies will not be executed");
+        None
+    };
     let delete_task_service_handle = if run_delete_task_service {
         let delete_task_service = DeleteTaskService::new(
             metastore,
@@ -82,6 +113,8 @@ pub async fn start_janitor_service(
         delete_task_service_handle,
         garbage_collector_handle,
         retention_policy_executor_handle,
+        lifecycle_policy_executor_handle,
+        rollup_executor_handle_opt,
     );
     let (janitor_service_mailbox, _janitor_service_handle) =
         universe.spawn_builder().spawn(janitor_service);
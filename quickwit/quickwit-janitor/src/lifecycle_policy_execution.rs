@@ -0,0 +1,174 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::{Path, PathBuf};
+
+use quickwit_actors::ActorContext;
+use quickwit_common::pretty::PrettySample;
+use quickwit_common::split_file;
+use quickwit_common::uri::Uri;
+use quickwit_config::LifecyclePolicy;
+use quickwit_indexing::new_split_id;
+use quickwit_metastore::{
+    ListSplitsQuery, ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, SplitMaturity,
+    SplitMetadata, SplitState, StageSplitsRequestExt,
+};
+use quickwit_proto::metastore::{
+    ListSplitsRequest, MetastoreService, MetastoreServiceClient, PublishSplitsRequest,
+    StageSplitsRequest,
+};
+use quickwit_proto::types::{IndexUid, SplitId};
+use quickwit_storage::{FilePayload, Storage, StorageResolver};
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::actors::LifecyclePolicyExecutor;
+
+/// Detect all the splits that aged past the `cold_after` period of a lifecycle policy and move
+/// them to the cold storage.
+///
+/// Each split file is copied to the cold storage under a new split ID, then the new split, which
+/// records the cold storage URI, replaces the original one in the metastore. Deleting the
+/// original split file from the index storage is taken care of by the garbage collector.
+///
+/// * `index_uid` - The target index uid.
+/// * `index_uri` - The URI of the target index.
+/// * `metastore` - The metastore managing the target index.
+/// * `storage_resolver` - Resolves the index storage and the cold storage.
+/// * `scratch_directory` - Directory where the split files are downloaded before being uploaded.
+/// * `lifecycle_policy` - The lifecycle policy used to evaluate the splits.
+/// * `ctx` - A context for reporting progress.
+pub async fn run_execute_lifecycle_policy(
+    index_uid: IndexUid,
+    index_uri: &Uri,
+    mut metastore: MetastoreServiceClient,
+    storage_resolver: &StorageResolver,
+    scratch_directory: &Path,
+    lifecycle_policy: &LifecyclePolicy,
+    ctx: &ActorContext<LifecyclePolicyExecutor>,
+) -> anyhow::Result<Vec<SplitMetadata>> {
+    // Select splits that are published, still in the index storage, and older than the
+    // `cold_after` period. Splits without a timestamp range are ignored.
+    let cold_after = lifecycle_policy.cold_after()?;
+    let current_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+    let max_cold_timestamp = current_timestamp - cold_after.as_secs() as i64;
+    let query = ListSplitsQuery::for_index(index_uid.clone())
+        .with_split_state(SplitState::Published)
+        .with_time_range_end_lte(max_cold_timestamp);
+
+    let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+    let aged_splits: Vec<SplitMetadata> = ctx
+        .protect_future(metastore.list_splits(list_splits_request))
+        .await?
+        .collect_splits_metadata()
+        .await?
+        .into_iter()
+        .filter(|split_metadata| {
+            split_metadata.time_range.is_some() && split_metadata.storage_uri.is_none()
+        })
+        .collect();
+
+    if aged_splits.is_empty() {
+        return Ok(Vec::new());
+    }
+    let aged_split_ids: Vec<SplitId> = aged_splits
+        .iter()
+        .map(|split_metadata| split_metadata.split_id.to_string())
+        .collect();
+    info!(
+        index_id=%index_uid.index_id,
+        split_ids=?PrettySample::new(&aged_split_ids, 5),
+        "Moving {} splits to cold storage based on lifecycle policy.",
+        aged_split_ids.len()
+    );
+    let index_storage = storage_resolver.resolve(index_uri).await?;
+    let cold_storage = storage_resolver
+        .resolve(&lifecycle_policy.cold_storage_uri)
+        .await?;
+
+    let mut cold_splits = Vec::with_capacity(aged_splits.len());
+
+    for split_metadata in aged_splits {
+        let cold_split_metadata = move_split_to_cold_storage(
+            index_uid.clone(),
+            split_metadata,
+            &*index_storage,
+            &*cold_storage,
+            &mut metastore,
+            scratch_directory,
+            ctx,
+        )
+        .await?;
+        cold_splits.push(cold_split_metadata);
+    }
+    Ok(cold_splits)
+}
+
+async fn move_split_to_cold_storage(
+    index_uid: IndexUid,
+    split_metadata: SplitMetadata,
+    index_storage: &dyn Storage,
+    cold_storage: &dyn Storage,
+    metastore: &mut MetastoreServiceClient,
+    scratch_directory: &Path,
+    ctx: &ActorContext<LifecyclePolicyExecutor>,
+) -> anyhow::Result<SplitMetadata> {
+    let split_path = PathBuf::from(split_file(split_metadata.split_id()));
+    let cold_split_id = new_split_id();
+    let cold_split_path = PathBuf::from(split_file(&cold_split_id));
+    let local_split_path = scratch_directory.join(&cold_split_path);
+
+    ctx.protect_future(index_storage.copy_to_file(&split_path, &local_split_path))
+        .await?;
+    let upload_result = async {
+        let payload = FilePayload::from_path(local_split_path.clone()).await?;
+        ctx.protect_future(cold_storage.put(&cold_split_path, Box::new(payload)))
+            .await?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    if let Err(io_error) = tokio::fs::remove_file(&local_split_path).await {
+        warn!(error=%io_error, path=%local_split_path.display(), "failed to remove local split file");
+    }
+    upload_result?;
+
+    // Splits in cold storage are never merged again.
+    let cold_split_metadata = SplitMetadata {
+        split_id: cold_split_id,
+        storage_uri: Some(cold_storage.uri().clone()),
+        maturity: SplitMaturity::Mature,
+        ..split_metadata.clone()
+    };
+    let stage_splits_request =
+        StageSplitsRequest::try_from_split_metadata(index_uid.clone(), &cold_split_metadata)?;
+    ctx.protect_future(metastore.stage_splits(stage_splits_request))
+        .await?;
+
+    let publish_splits_request = PublishSplitsRequest {
+        index_uid: Some(index_uid),
+        staged_split_ids: vec![cold_split_metadata.split_id.clone()],
+        replaced_split_ids: vec![split_metadata.split_id],
+        index_checkpoint_delta_json_opt: None,
+        publish_token_opt: None,
+    };
+    ctx.protect_future(metastore.publish_splits(publish_splits_request))
+        .await?;
+    Ok(cold_split_metadata)
+}
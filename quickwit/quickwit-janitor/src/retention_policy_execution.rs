@@ -19,15 +19,20 @@
 
 use quickwit_actors::ActorContext;
 use quickwit_common::pretty::PrettySample;
-use quickwit_config::RetentionPolicy;
+use quickwit_config::{RetentionPolicy, RetentionRule};
+use quickwit_doc_mapper::tag_pruning::{
+    field_tag, match_tag_field_name, no_tag, tag, TagFilterAst,
+};
 use quickwit_metastore::{
     ListSplitsQuery, ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, SplitMetadata,
     SplitState,
 };
 use quickwit_proto::metastore::{
-    ListSplitsRequest, MarkSplitsForDeletionRequest, MetastoreService, MetastoreServiceClient,
+    DeleteQuery, DeleteTask, ListDeleteTasksRequest, ListSplitsRequest,
+    MarkSplitsForDeletionRequest, MetastoreService, MetastoreServiceClient,
 };
 use quickwit_proto::types::{IndexUid, SplitId};
+use quickwit_query::query_ast::{QueryAst, TermQuery};
 use time::OffsetDateTime;
 use tracing::{info, warn};
 
@@ -40,17 +45,20 @@ use crate::actors::RetentionPolicyExecutor;
 /// * `index_id` - The target index id.
 /// * `metastore` - The metastore managing the target index.
 /// * `retention_policy` - The retention policy to used to evaluate the splits.
+/// * `rolled_up_until_opt` - If the index has a rollup policy, the end of the last time window
+///   rolled up. More recent documents are retained.
 /// * `ctx_opt` - A context for reporting progress (only useful within quickwit actor).
 pub async fn run_execute_retention_policy(
     index_uid: IndexUid,
     mut metastore: MetastoreServiceClient,
     retention_policy: &RetentionPolicy,
+    rolled_up_until_opt: Option<i64>,
     ctx: &ActorContext<RetentionPolicyExecutor>,
 ) -> anyhow::Result<Vec<SplitMetadata>> {
     // Select splits that are published and older than the retention period.
     let retention_period = retention_policy.retention_period()?;
-    let current_timestamp = OffsetDateTime::now_utc().unix_timestamp();
-    let max_retention_timestamp = current_timestamp - retention_period.as_secs() as i64;
+    let max_retention_timestamp =
+        max_retention_timestamp(retention_period.as_secs(), rolled_up_until_opt);
     let query = ListSplitsQuery::for_index(index_uid.clone())
         .with_split_state(SplitState::Published)
         .with_time_range_end_lte(max_retention_timestamp);
@@ -76,10 +84,179 @@ pub async fn run_execute_retention_policy(
             ignored_split_ids.len()
         );
     }
+    mark_splits_for_deletion(
+        index_uid,
+        &mut metastore,
+        &expired_splits,
+        "retention policy",
+        ctx,
+    )
+    .await?;
+    Ok(expired_splits)
+}
+
+/// Applies a retention rule to the documents holding the rule tag.
+///
+/// Splits whose documents all hold the rule tag are marked for deletion once they are older than
+/// the rule period. The expired documents of splits mixing several values of the tag field, or
+/// only partially older than the rule period, are deleted through a delete task executed by the
+/// delete task pipeline. The delete tasks created by previous evaluations of the rule cover the
+/// documents older than their end timestamp, so a new delete task is only created for the
+/// documents that expired since then.
+///
+/// Returns the splits marked for deletion and the delete task created, if any.
+///
+/// * `index_uid` - The target index uid.
+/// * `metastore` - The metastore managing the target index.
+/// * `retention_rule` - The retention rule used to evaluate the splits.
+/// * `rolled_up_until_opt` - If the index has a rollup policy, the end of the last time window
+///   rolled up. More recent documents are retained.
+/// * `ctx` - A context for reporting progress.
+pub async fn run_execute_retention_rule(
+    index_uid: IndexUid,
+    mut metastore: MetastoreServiceClient,
+    retention_rule: &RetentionRule,
+    rolled_up_until_opt: Option<i64>,
+    ctx: &ActorContext<RetentionPolicyExecutor>,
+) -> anyhow::Result<(Vec<SplitMetadata>, Option<DeleteTask>)> {
+    let (field_name, field_value) = retention_rule.tag_field_and_value()?;
+    let retention_period = retention_rule.retention_period()?;
+    let max_retention_timestamp =
+        max_retention_timestamp(retention_period.as_secs(), rolled_up_until_opt);
+
+    let query_ast: QueryAst = TermQuery {
+        field: field_name.to_string(),
+        value: field_value.to_string(),
+    }
+    .into();
+    let query_ast_json = serde_json::to_string(&query_ast)?;
+    let covered_until_opt =
+        delete_tasks_covered_until(index_uid.clone(), &mut metastore, &query_ast_json, ctx).await?;
+
+    // Select the published splits that may contain documents holding the rule tag: the splits
+    // holding the tag and the splits for which the values of the tag field are unknown.
+    let tags_filter = TagFilterAst::Or(vec![
+        no_tag(field_tag(field_name)),
+        tag(&retention_rule.tag),
+    ]);
+    let query = ListSplitsQuery::for_index(index_uid.clone())
+        .with_split_state(SplitState::Published)
+        .with_tags_filter(tags_filter);
+
+    let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+    let splits: Vec<SplitMetadata> = ctx
+        .protect_future(metastore.list_splits(list_splits_request))
+        .await?
+        .collect_splits_metadata()
+        .await?;
+
+    // Splits without a timestamp range are ignored, as for the index retention period. Splits
+    // that only partially expired need a delete task, unless their expired documents are already
+    // covered by a previous delete task.
+    let mut expired_splits = Vec::new();
+    let mut requires_delete_task = false;
+
+    for split_metadata in splits {
+        let Some(time_range) = &split_metadata.time_range else {
+            continue;
+        };
+        if *time_range.start() >= max_retention_timestamp {
+            continue;
+        }
+        if *time_range.end() <= max_retention_timestamp
+            && is_exclusive_to_tag(&split_metadata, field_name, &retention_rule.tag)
+        {
+            expired_splits.push(split_metadata);
+        } else if covered_until_opt.map_or(true, |covered_until| {
+            covered_until < max_retention_timestamp && *time_range.end() >= covered_until
+        }) {
+            requires_delete_task = true;
+        }
+    }
+    mark_splits_for_deletion(
+        index_uid.clone(),
+        &mut metastore,
+        &expired_splits,
+        "retention rule",
+        ctx,
+    )
+    .await?;
+
+    if !requires_delete_task {
+        return Ok((expired_splits, None));
+    }
+    let delete_query = DeleteQuery {
+        index_uid: Some(index_uid.clone()),
+        start_timestamp: covered_until_opt,
+        end_timestamp: Some(max_retention_timestamp),
+        query_ast: query_ast_json,
+    };
+    info!(
+        index_id=%index_uid.index_id,
+        tag=%retention_rule.tag,
+        start_timestamp=?covered_until_opt,
+        end_timestamp=max_retention_timestamp,
+        "Creating delete task based on retention rule."
+    );
+    let delete_task = ctx
+        .protect_future(metastore.create_delete_task(delete_query))
+        .await?;
+    Ok((expired_splits, Some(delete_task)))
+}
+
+/// Returns the end timestamp of the most recent delete task with the given query, which deleted, or
+/// is deleting, the matching documents older than this timestamp.
+async fn delete_tasks_covered_until(
+    index_uid: IndexUid,
+    metastore: &mut MetastoreServiceClient,
+    query_ast_json: &str,
+    ctx: &ActorContext<RetentionPolicyExecutor>,
+) -> anyhow::Result<Option<i64>> {
+    let list_delete_tasks_request = ListDeleteTasksRequest::new(index_uid, 0);
+    let covered_until_opt = ctx
+        .protect_future(metastore.list_delete_tasks(list_delete_tasks_request))
+        .await?
+        .delete_tasks
+        .into_iter()
+        .filter_map(|delete_task| delete_task.delete_query)
+        .filter(|delete_query| delete_query.query_ast == query_ast_json)
+        .filter_map(|delete_query| delete_query.end_timestamp)
+        .max();
+    Ok(covered_until_opt)
+}
+
+fn max_retention_timestamp(retention_period_secs: u64, rolled_up_until_opt: Option<i64>) -> i64 {
+    let current_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+    let max_retention_timestamp = current_timestamp - retention_period_secs as i64;
+    rolled_up_until_opt.map_or(max_retention_timestamp, |rolled_up_until| {
+        max_retention_timestamp.min(rolled_up_until)
+    })
+}
+
+/// Returns true if the tag is the only value of the tag field in the split. If extracting the
+/// values of the field failed when the split was created, the split carries no tag for the field,
+/// not even the field tag, and its documents may hold any value.
+fn is_exclusive_to_tag(split_metadata: &SplitMetadata, field_name: &str, tag: &str) -> bool {
+    if !split_metadata.tags.contains(&field_tag(field_name)) {
+        return false;
+    }
+    split_metadata
+        .tags
+        .iter()
+        .filter(|split_tag| match_tag_field_name(field_name, split_tag))
+        .all(|split_tag| split_tag == tag)
+}
+
+async fn mark_splits_for_deletion(
+    index_uid: IndexUid,
+    metastore: &mut MetastoreServiceClient,
+    expired_splits: &[SplitMetadata],
+    reason: &str,
+    ctx: &ActorContext<RetentionPolicyExecutor>,
+) -> anyhow::Result<()> {
     if expired_splits.is_empty() {
-        return Ok(expired_splits);
+        return Ok(());
     }
-    // Mark the expired splits for deletion.
     let expired_split_ids: Vec<SplitId> = expired_splits
         .iter()
         .map(|split_metadata| split_metadata.split_id.to_string())
@@ -87,12 +264,12 @@ pub async fn run_execute_retention_policy(
     info!(
         index_id=%index_uid.index_id,
         split_ids=?PrettySample::new(&expired_split_ids, 5),
-        "Marking {} splits for deletion based on retention policy.",
+        "Marking {} splits for deletion based on {reason}.",
         expired_split_ids.len()
     );
     let mark_splits_for_deletion_request =
         MarkSplitsForDeletionRequest::new(index_uid, expired_split_ids);
     ctx.protect_future(metastore.mark_splits_for_deletion(mark_splits_for_deletion_request))
         .await?;
-    Ok(expired_splits)
+    Ok(())
 }
@@ -0,0 +1,416 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::mem;
+
+use anyhow::{bail, Context};
+use quickwit_actors::ActorContext;
+use quickwit_config::{RollupPolicy, ROLLUP_SOURCE_ID};
+use quickwit_ingest::{
+    CommitType, DocBatch, DocBatchBuilder, IngestRequest, IngestService, IngestServiceClient,
+    JsonDocBatchBuilder,
+};
+use quickwit_metastore::checkpoint::{IndexCheckpointDelta, PartitionId, SourceCheckpointDelta};
+use quickwit_metastore::{
+    IndexMetadataResponseExt, ListSplitsQuery, ListSplitsRequestExt,
+    MetastoreServiceStreamSplitsExt, SplitState,
+};
+use quickwit_proto::metastore::{
+    IndexMetadataRequest, ListSplitsRequest, MetastoreService, MetastoreServiceClient,
+    PublishSplitsRequest,
+};
+use quickwit_proto::search::{SearchPriority, SearchRequest};
+use quickwit_proto::types::{IndexUid, Position};
+use quickwit_query::query_ast::query_ast_from_user_text;
+use quickwit_search::SearchService;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::actors::RollupExecutor;
+
+/// Maximum number of time windows rolled up by a single execution of a rollup policy, so that
+/// catching up on a large backlog does not hold the executor for too long.
+const MAX_WINDOWS_PER_EXECUTION: usize = 100;
+
+/// The documents of consecutive windows are ingested and checkpointed together until their batch
+/// reaches this size.
+const TARGET_BATCH_NUM_BYTES: usize = 5 * 1024 * 1024; // 5 MiB
+
+/// Returns the end of the last time window rolled up by the rollup policy of an index, or `None`
+/// if no window has been rolled up yet.
+pub async fn fetch_rolled_up_until(
+    metastore: &mut MetastoreServiceClient,
+    index_uid: IndexUid,
+    rollup_policy: &RollupPolicy,
+) -> anyhow::Result<Option<i64>> {
+    let index_metadata = metastore
+        .index_metadata(IndexMetadataRequest::for_index_uid(index_uid))
+        .await?
+        .deserialize_index_metadata()?;
+    let partition_id = PartitionId::from(rollup_policy.target_index_id.as_str());
+    let rolled_up_until_opt = index_metadata
+        .checkpoint
+        .source_checkpoint(ROLLUP_SOURCE_ID)
+        .and_then(|source_checkpoint| source_checkpoint.position_for_partition(&partition_id))
+        .and_then(|position| position.as_u64())
+        .map(|timestamp| timestamp as i64);
+    Ok(rolled_up_until_opt)
+}
+
+/// Rolls up the complete time windows of an index that have not been rolled up yet.
+///
+/// Each window is aggregated with a search request and the resulting buckets are turned into
+/// documents. The documents of consecutive windows are batched, then each batch is ingested into
+/// the target index and the end of its last window is recorded in the index checkpoint. The
+/// windows of a batch are rolled up again if the executor fails after ingesting the batch and
+/// before recording the checkpoint.
+///
+/// Returns the number of windows rolled up.
+///
+/// * `index_uid` - The target index uid.
+/// * `metastore` - The metastore managing the target index.
+/// * `search_service` - The search service computing the aggregations.
+/// * `ingest_service` - The ingest service receiving the rollup documents.
+/// * `rollup_policy` - The rollup policy of the index.
+/// * `ctx` - A context for reporting progress.
+pub async fn run_execute_rollup_policy(
+    index_uid: IndexUid,
+    mut metastore: MetastoreServiceClient,
+    search_service: &dyn SearchService,
+    mut ingest_service: IngestServiceClient,
+    rollup_policy: &RollupPolicy,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<usize> {
+    let window_secs = rollup_policy.window()?.as_secs() as i64;
+    let delay_secs = rollup_policy.delay()?.as_secs() as i64;
+    let max_window_end = OffsetDateTime::now_utc().unix_timestamp() - delay_secs;
+
+    let rolled_up_until_opt = ctx
+        .protect_future(fetch_rolled_up_until(
+            &mut metastore,
+            index_uid.clone(),
+            rollup_policy,
+        ))
+        .await?;
+    let mut window_start = if let Some(rolled_up_until) = rolled_up_until_opt {
+        rolled_up_until
+    } else {
+        // The first window is the one holding the oldest document of the index.
+        let query =
+            ListSplitsQuery::for_index(index_uid.clone()).with_split_state(SplitState::Published);
+        let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+        let min_timestamp_opt = ctx
+            .protect_future(metastore.list_splits(list_splits_request))
+            .await?
+            .collect_splits_metadata()
+            .await?
+            .into_iter()
+            .filter_map(|split_metadata| split_metadata.time_range)
+            .map(|time_range| *time_range.start())
+            .min();
+        let Some(min_timestamp) = min_timestamp_opt else {
+            return Ok(0);
+        };
+        // Windows are recorded in the checkpoint as offsets, which cannot be negative.
+        min_timestamp.max(0) / window_secs * window_secs
+    };
+    let mut from_position = rolled_up_until_opt
+        .map(|rolled_up_until| Position::offset(rolled_up_until as u64))
+        .unwrap_or(Position::Beginning);
+    let mut num_windows = 0;
+
+    let mut doc_batch_builder = new_doc_batch_builder(rollup_policy);
+    let mut batch_num_bytes = 0;
+    let mut batch_num_windows = 0;
+    let mut batch_start = window_start;
+
+    while num_windows < MAX_WINDOWS_PER_EXECUTION && window_start + window_secs <= max_window_end {
+        let window_end = window_start + window_secs;
+        let docs = aggregate_window(
+            &index_uid,
+            search_service,
+            rollup_policy,
+            window_start,
+            window_end,
+            ctx,
+        )
+        .await?;
+        for doc in docs {
+            batch_num_bytes += doc_batch_builder.ingest_doc(JsonValue::Object(doc))?;
+        }
+        window_start = window_end;
+        num_windows += 1;
+        batch_num_windows += 1;
+
+        let is_last_window =
+            num_windows == MAX_WINDOWS_PER_EXECUTION || window_start + window_secs > max_window_end;
+        if batch_num_bytes < TARGET_BATCH_NUM_BYTES && !is_last_window {
+            continue;
+        }
+        let doc_batch =
+            mem::replace(&mut doc_batch_builder, new_doc_batch_builder(rollup_policy)).build();
+        let num_docs = doc_batch.num_docs();
+        let to_position = Position::offset(window_end as u64);
+
+        commit_batch(
+            &index_uid,
+            &mut metastore,
+            &mut ingest_service,
+            rollup_policy,
+            doc_batch,
+            from_position,
+            to_position.clone(),
+            ctx,
+        )
+        .await?;
+        info!(
+            index_id=%index_uid.index_id,
+            target_index_id=%rollup_policy.target_index_id,
+            batch_start,
+            batch_end=window_end,
+            num_windows=batch_num_windows,
+            num_docs,
+            "rolled up time windows"
+        );
+        from_position = to_position;
+        batch_num_bytes = 0;
+        batch_num_windows = 0;
+        batch_start = window_end;
+    }
+    Ok(num_windows)
+}
+
+fn new_doc_batch_builder(rollup_policy: &RollupPolicy) -> JsonDocBatchBuilder {
+    DocBatchBuilder::new(rollup_policy.target_index_id.clone()).json_writer()
+}
+
+/// Ingests the documents of a batch of windows into the target index, waiting for them to be
+/// committed, then records the end of the batch in the index checkpoint.
+#[allow(clippy::too_many_arguments)]
+async fn commit_batch(
+    index_uid: &IndexUid,
+    metastore: &mut MetastoreServiceClient,
+    ingest_service: &mut IngestServiceClient,
+    rollup_policy: &RollupPolicy,
+    doc_batch: DocBatch,
+    from_position: Position,
+    to_position: Position,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<()> {
+    if !doc_batch.is_empty() {
+        let ingest_request = IngestRequest {
+            doc_batches: vec![doc_batch],
+            commit: CommitType::WaitFor.into(),
+        };
+        ctx.protect_future(ingest_service.ingest(ingest_request))
+            .await?;
+    }
+    let checkpoint_delta = IndexCheckpointDelta {
+        source_id: ROLLUP_SOURCE_ID.to_string(),
+        source_delta: SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from(rollup_policy.target_index_id.as_str()),
+            from_position,
+            to_position,
+        )?,
+    };
+    let publish_splits_request = PublishSplitsRequest {
+        index_uid: Some(index_uid.clone()),
+        staged_split_ids: Vec::new(),
+        replaced_split_ids: Vec::new(),
+        index_checkpoint_delta_json_opt: Some(serde_json::to_string(&checkpoint_delta)?),
+        publish_token_opt: None,
+    };
+    ctx.protect_future(metastore.publish_splits(publish_splits_request))
+        .await?;
+    Ok(())
+}
+
+/// Aggregates a time window and returns the resulting rollup documents.
+async fn aggregate_window(
+    index_uid: &IndexUid,
+    search_service: &dyn SearchService,
+    rollup_policy: &RollupPolicy,
+    window_start: i64,
+    window_end: i64,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<Vec<JsonMap<String, JsonValue>>> {
+    let query_ast = query_ast_from_user_text(&rollup_policy.query, None);
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_uid.index_id.to_string()],
+        query_ast: serde_json::to_string(&query_ast)?,
+        start_timestamp: Some(window_start),
+        end_timestamp: Some(window_end),
+        max_hits: 0,
+        aggregation_request: Some(rollup_policy.aggregation.to_string()),
+        priority: SearchPriority::Batch as i32,
+        ..Default::default()
+    };
+    let search_response = ctx
+        .protect_future(search_service.root_search(search_request))
+        .await?;
+
+    if !search_response.errors.is_empty() {
+        bail!(
+            "failed to aggregate time window [{window_start}, {window_end}): {}",
+            search_response.errors.join(", ")
+        );
+    }
+    if search_response.num_hits == 0 {
+        return Ok(Vec::new());
+    }
+    let aggregation_results: JsonValue = match &search_response.aggregation {
+        Some(aggregation_json) => serde_json::from_str(aggregation_json)
+            .context("failed to deserialize aggregation results")?,
+        None => JsonValue::Object(JsonMap::new()),
+    };
+    let mut window_doc = JsonMap::new();
+    window_doc.insert("window_start".to_string(), JsonValue::from(window_start));
+    window_doc.insert("window_end".to_string(), JsonValue::from(window_end));
+
+    let mut docs = Vec::new();
+    collect_rollup_docs(
+        &aggregation_results,
+        search_response.num_hits,
+        window_doc,
+        &mut docs,
+    )?;
+    Ok(docs)
+}
+
+/// Flattens the aggregation results of a bucket into rollup documents: one document per leaf
+/// bucket, holding the keys of the enclosing buckets, the metrics computed at each level and the
+/// number of documents of the leaf bucket.
+fn collect_rollup_docs(
+    bucket: &JsonValue,
+    doc_count: u64,
+    mut doc: JsonMap<String, JsonValue>,
+    docs: &mut Vec<JsonMap<String, JsonValue>>,
+) -> anyhow::Result<()> {
+    let Some(aggregations) = bucket.as_object() else {
+        return Ok(());
+    };
+    let mut bucket_aggregation_opt: Option<(&String, &Vec<JsonValue>)> = None;
+
+    for (name, result) in aggregations {
+        if let Some(buckets) = result.get("buckets") {
+            if bucket_aggregation_opt.is_some() {
+                bail!("rollup aggregation must hold at most one bucket aggregation per level");
+            }
+            let buckets = buckets
+                .as_array()
+                .with_context(|| format!("buckets of aggregation `{name}` must be an array"))?;
+            bucket_aggregation_opt = Some((name, buckets));
+        } else if let Some(value) = result.get("value") {
+            doc.insert(name.clone(), value.clone());
+        } else if let Some(values) = result.get("values") {
+            doc.insert(name.clone(), values.clone());
+        } else if result.is_object() {
+            doc.insert(name.clone(), result.clone());
+        }
+    }
+    let Some((name, buckets)) = bucket_aggregation_opt else {
+        doc.insert("doc_count".to_string(), JsonValue::from(doc_count));
+        docs.push(doc);
+        return Ok(());
+    };
+    for bucket in buckets {
+        let key = bucket
+            .get("key_as_string")
+            .or_else(|| bucket.get("key"))
+            .cloned()
+            .unwrap_or(JsonValue::Null);
+        let bucket_doc_count = bucket
+            .get("doc_count")
+            .and_then(JsonValue::as_u64)
+            .unwrap_or(0);
+        let mut bucket_doc = doc.clone();
+        bucket_doc.insert(name.clone(), key);
+        collect_rollup_docs(bucket, bucket_doc_count, bucket_doc, docs)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_collect_rollup_docs() {
+        let aggregation_results = json!({
+            "per_host": {
+                "doc_count_error_upper_bound": 0,
+                "sum_other_doc_count": 0,
+                "buckets": [
+                    {
+                        "key": "web-1",
+                        "doc_count": 3,
+                        "per_hour": {
+                            "buckets": [
+                                {
+                                    "key": 3600000.0,
+                                    "key_as_string": "1970-01-01T01:00:00Z",
+                                    "doc_count": 3,
+                                    "avg_latency": {"value": 12.5},
+                                    "latency_stats": {"count": 3, "min": 5.0, "max": 20.0}
+                                }
+                            ]
+                        }
+                    },
+                    {
+                        "key": "web-2",
+                        "doc_count": 1,
+                        "per_hour": {"buckets": []}
+                    }
+                ]
+            },
+            "max_latency": {"value": 20.0}
+        });
+        let mut window_doc = JsonMap::new();
+        window_doc.insert("window_start".to_string(), json!(3600));
+
+        let mut docs = Vec::new();
+        collect_rollup_docs(&aggregation_results, 4, window_doc, &mut docs).unwrap();
+        let docs: Vec<JsonValue> = docs.into_iter().map(JsonValue::Object).collect();
+        assert_eq!(
+            docs,
+            vec![json!({
+                "window_start": 3600,
+                "max_latency": 20.0,
+                "per_host": "web-1",
+                "per_hour": "1970-01-01T01:00:00Z",
+                "avg_latency": 12.5,
+                "latency_stats": {"count": 3, "min": 5.0, "max": 20.0},
+                "doc_count": 3
+            })]
+        );
+    }
+
+    #[test]
+    fn test_collect_rollup_docs_rejects_sibling_bucket_aggregations() {
+        let aggregation_results = json!({
+            "per_host": {"buckets": []},
+            "per_status": {"buckets": []}
+        });
+        let mut docs = Vec::new();
+        collect_rollup_docs(&aggregation_results, 0, JsonMap::new(), &mut docs).unwrap_err();
+    }
+}
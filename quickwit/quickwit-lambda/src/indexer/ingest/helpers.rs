@@ -74,6 +74,7 @@ pub(super) async fn create_empty_cluster(
         grpc_advertise_addr: config.grpc_advertise_addr,
         indexing_tasks: Vec::new(),
         indexing_cpu_capacity: CpuCapacity::zero(),
+        availability_zone: None,
     };
     let cluster = Cluster::join(
         config.cluster_id.clone(),
@@ -242,6 +243,8 @@ pub(super) async fn spawn_services(
                 storage_resolver,
                 event_broker,
                 false,
+                None,
+                None,
             )
             .await?,
         )
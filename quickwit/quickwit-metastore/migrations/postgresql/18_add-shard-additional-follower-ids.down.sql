@@ -0,0 +1 @@
+ALTER TABLE shards DROP COLUMN IF EXISTS additional_follower_ids;
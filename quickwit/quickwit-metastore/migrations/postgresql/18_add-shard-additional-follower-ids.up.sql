@@ -0,0 +1 @@
+ALTER TABLE shards ADD COLUMN IF NOT EXISTS additional_follower_ids VARCHAR(255)[] NOT NULL DEFAULT ARRAY[]::VARCHAR(255)[];
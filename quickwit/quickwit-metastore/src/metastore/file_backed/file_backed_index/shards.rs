@@ -119,6 +119,24 @@ impl Shards {
         let shard_id = subrequest.shard_id();
         let entry = self.shards.entry(shard_id.clone());
         let shard = match entry {
+            Entry::Occupied(mut entry) if subrequest.reassign => {
+                let shard = entry.get_mut();
+                shard.leader_id = subrequest.leader_id.clone();
+                shard.follower_id = subrequest.follower_id.clone();
+                shard.additional_follower_ids = subrequest.additional_follower_ids.clone();
+                mutation_occurred = true;
+
+                info!(
+                    index_id=%self.index_uid.index_id,
+                    source_id=%self.source_id,
+                    shard_id=%shard_id,
+                    leader_id=%shard.leader_id,
+                    follower_id=?shard.follower_id,
+                    additional_follower_ids=?shard.additional_follower_ids,
+                    "reassigned shard"
+                );
+                shard.clone()
+            }
             Entry::Occupied(entry) => entry.get().clone(),
             Entry::Vacant(entry) => {
                 let shard = Shard {
@@ -128,6 +146,7 @@ impl Shards {
                     shard_state: ShardState::Open as i32,
                     leader_id: subrequest.leader_id.clone(),
                     follower_id: subrequest.follower_id.clone(),
+                    additional_follower_ids: subrequest.additional_follower_ids.clone(),
                     publish_position_inclusive: Some(Position::Beginning),
                     publish_token: None,
                 };
@@ -140,6 +159,7 @@ impl Shards {
                     shard_id=%shard_id,
                     leader_id=%shard.leader_id,
                     follower_id=?shard.follower_id,
+                    additional_follower_ids=?shard.additional_follower_ids,
                     "opened shard"
                 );
                 shard
@@ -311,6 +331,8 @@ mod tests {
             shard_id: Some(ShardId::from(1)),
             leader_id: "leader_id".to_string(),
             follower_id: None,
+            additional_follower_ids: Vec::new(),
+            reassign: false,
         };
         let MutationOccurred::Yes(subresponse) = shards.open_shards(subrequest.clone()).unwrap()
         else {
@@ -346,6 +368,8 @@ mod tests {
             shard_id: Some(ShardId::from(2)),
             leader_id: "leader_id".to_string(),
             follower_id: Some("follower_id".to_string()),
+            additional_follower_ids: vec!["additional_follower_id".to_string()],
+            reassign: false,
         };
         let MutationOccurred::Yes(subresponse) = shards.open_shards(subrequest).unwrap() else {
             panic!("Expected `MutationOccured::No`");
@@ -361,9 +385,34 @@ mod tests {
         assert_eq!(shard.shard_state(), ShardState::Open);
         assert_eq!(shard.leader_id, "leader_id");
         assert_eq!(shard.follower_id.as_ref().unwrap(), "follower_id");
+        assert_eq!(shard.additional_follower_ids, ["additional_follower_id"]);
         assert_eq!(shard.publish_position_inclusive(), Position::Beginning);
 
         assert_eq!(shards.shards.get(&ShardId::from(2)).unwrap(), shard);
+
+        let subrequest = OpenShardsSubrequest {
+            subrequest_id: 0,
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(ShardId::from(2)),
+            leader_id: "new_leader_id".to_string(),
+            follower_id: None,
+            additional_follower_ids: Vec::new(),
+            reassign: true,
+        };
+        let MutationOccurred::Yes(subresponse) = shards.open_shards(subrequest).unwrap() else {
+            panic!("Expected `MutationOccured::Yes`");
+        };
+        assert_eq!(subresponse.opened_shards.len(), 1);
+
+        let shard = &subresponse.opened_shards[0];
+        assert_eq!(shard.shard_id(), ShardId::from(2));
+        assert_eq!(shard.shard_state(), ShardState::Open);
+        assert_eq!(shard.leader_id, "new_leader_id");
+        assert_eq!(shard.follower_id, None);
+        assert!(shard.additional_follower_ids.is_empty());
+
+        assert_eq!(shards.shards.get(&ShardId::from(2)).unwrap(), shard);
     }
 
     #[test]
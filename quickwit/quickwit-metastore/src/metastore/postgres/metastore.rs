@@ -1453,6 +1453,8 @@ async fn open_or_fetch_shard<'e>(
         .bind(subrequest.shard_id().as_str())
         .bind(&subrequest.leader_id)
         .bind(&subrequest.follower_id)
+        .bind(&subrequest.additional_follower_ids)
+        .bind(subrequest.reassign)
         .fetch_optional(executor.clone())
         .await?;
 
@@ -1464,6 +1466,7 @@ async fn open_or_fetch_shard<'e>(
             shard_id=%shard.shard_id(),
             leader_id=%shard.leader_id,
             follower_id=?shard.follower_id,
+            additional_follower_ids=?shard.additional_follower_ids,
             "opened shard"
         );
         return Ok(shard);
@@ -1616,6 +1619,7 @@ mod tests {
                     .bind(shard.shard_state().as_json_str_name())
                     .bind(&shard.leader_id)
                     .bind(&shard.follower_id)
+                    .bind(&shard.additional_follower_ids)
                     .bind(&shard.publish_position_inclusive().to_string())
                     .bind(&shard.publish_token)
                     .execute(&self.connection_pool)
@@ -1,2 +1,2 @@
-INSERT INTO shards(index_uid, source_id, shard_id, shard_state, leader_id, follower_id, publish_position_inclusive, publish_token)
-    VALUES ($1, $2, $3, CAST($4 AS SHARD_STATE), $5, $6, $7, $8)
+INSERT INTO shards(index_uid, source_id, shard_id, shard_state, leader_id, follower_id, additional_follower_ids, publish_position_inclusive, publish_token)
+    VALUES ($1, $2, $3, CAST($4 AS SHARD_STATE), $5, $6, $7, $8, $9)
@@ -1,6 +1,11 @@
-INSERT INTO shards(index_uid, source_id, shard_id, leader_id, follower_id)
-    VALUES ($1, $2, $3, $4, $5)
-ON CONFLICT
-    DO NOTHING
+INSERT INTO shards(index_uid, source_id, shard_id, leader_id, follower_id, additional_follower_ids)
+    VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (index_uid, source_id, shard_id)
+    DO UPDATE SET
+        leader_id = EXCLUDED.leader_id,
+        follower_id = EXCLUDED.follower_id,
+        additional_follower_ids = EXCLUDED.additional_follower_ids
+    WHERE
+        $7
 RETURNING
     *
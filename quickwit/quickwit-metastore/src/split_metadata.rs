@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::ops::{Range, RangeInclusive};
 use std::path::PathBuf;
@@ -25,6 +25,7 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use bytesize::ByteSize;
+use quickwit_common::uri::Uri;
 use quickwit_proto::types::IndexUid;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DurationMilliSeconds};
@@ -132,6 +133,16 @@ pub struct SplitMetadata {
     /// Number of merge operations that was involved to create
     /// this split.
     pub num_merge_ops: usize,
+
+    /// URI of the storage holding the split file, if it differs from the index URI. This is the
+    /// case of splits moved to a colder storage tier by the index lifecycle policy.
+    pub storage_uri: Option<Uri>,
+
+    /// Statistics on the values of the fields registered in the
+    /// [`DocMapping`](quickwit_config::DocMapping) `min_max_fields` attribute, keyed by field
+    /// name. They are used to prune splits at search time. The bloom filters of the
+    /// `bloom_filter_fields` are stored in the split file instead.
+    pub field_stats: BTreeMap<String, FieldStats>,
 }
 
 impl fmt::Debug for SplitMetadata {
@@ -176,6 +187,12 @@ impl fmt::Debug for SplitMetadata {
         debug_struct.field("footer_offsets", &self.footer_offsets);
         debug_struct.field("delete_opstamp", &self.delete_opstamp);
         debug_struct.field("num_merge_ops", &self.num_merge_ops);
+        if let Some(storage_uri) = &self.storage_uri {
+            debug_struct.field("storage_uri", storage_uri);
+        }
+        if !self.field_stats.is_empty() {
+            debug_struct.field("field_stats", &self.field_stats.keys());
+        }
         debug_struct.finish()
     }
 }
@@ -281,6 +298,8 @@ impl quickwit_config::TestableForRegression for SplitMetadata {
             tags: ["234".to_string(), "aaa".to_string()].into_iter().collect(),
             footer_offsets: 1000..2000,
             num_merge_ops: 3,
+            storage_uri: None,
+            field_stats: BTreeMap::new(),
         }
     }
 
@@ -356,6 +375,48 @@ pub enum SplitMaturity {
     },
 }
 
+/// Statistics on the values of a field within a split.
+///
+/// Statistics are conservative: a split whose statistics match a query may not contain any
+/// matching document, but a split whose statistics do not match a query is guaranteed not to
+/// contain any.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum FieldStats {
+    /// Min and max values of an `i64` field.
+    I64Range {
+        /// Min value.
+        min: i64,
+        /// Max value.
+        max: i64,
+    },
+    /// Min and max values of a `u64` field.
+    U64Range {
+        /// Min value.
+        min: u64,
+        /// Max value.
+        max: u64,
+    },
+    /// Min and max values of a `f64` field.
+    F64Range {
+        /// Min value.
+        min: f64,
+        /// Max value.
+        max: f64,
+    },
+    /// Min and max values of a `datetime` field, expressed in nanoseconds.
+    DateRange {
+        /// Min value.
+        min: i64,
+        /// Max value.
+        max: i64,
+    },
+}
+
+// The min and max values of a fast field are never NaN.
+impl Eq for FieldStats {}
+
 /// Helper function to provide a UTC now timestamp to use
 /// as a default in deserialization.
 ///
@@ -372,6 +433,17 @@ pub fn utc_now_timestamp() -> i64 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_field_stats_serialization() {
+        let field_stats = FieldStats::I64Range { min: -1, max: 10 };
+        let serialized = serde_json::to_string(&field_stats).unwrap();
+        assert_eq!(serialized, r#"{"type":"i64_range","min":-1,"max":10}"#);
+        assert_eq!(
+            serde_json::from_str::<FieldStats>(&serialized).unwrap(),
+            field_stats
+        );
+    }
+
     #[test]
     fn test_split_maturity_serialization() {
         {
@@ -423,6 +495,7 @@ mod tests {
             footer_offsets: 0..1024,
             delete_opstamp: 0,
             num_merge_ops: 0,
+            storage_uri: None,
         };
 
         let expected_output = "SplitMetadata { split_id: \"split-1\", index_uid: IndexUid { \
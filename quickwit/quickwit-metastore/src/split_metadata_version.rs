@@ -17,13 +17,14 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Range, RangeInclusive};
 
+use quickwit_common::uri::Uri;
 use quickwit_proto::types::IndexUid;
 use serde::{Deserialize, Serialize};
 
-use crate::split_metadata::{utc_now_timestamp, SplitMaturity};
+use crate::split_metadata::{utc_now_timestamp, FieldStats, SplitMaturity};
 use crate::SplitMetadata;
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
@@ -91,6 +92,18 @@ pub(crate) struct SplitMetadataV0_8 {
 
     #[serde(default)]
     num_merge_ops: usize,
+
+    /// URI of the storage holding the split file, if it differs from the index URI.
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    storage_uri: Option<Uri>,
+
+    /// Statistics on the values of some fields, used to prune splits at search time.
+    #[schema(value_type = Object)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    field_stats: BTreeMap<String, FieldStats>,
 }
 
 impl From<SplitMetadataV0_8> for SplitMetadata {
@@ -126,6 +139,8 @@ impl From<SplitMetadataV0_8> for SplitMetadata {
             tags: v8.tags,
             footer_offsets: v8.footer_offsets,
             num_merge_ops: v8.num_merge_ops,
+            storage_uri: v8.storage_uri,
+            field_stats: v8.field_stats,
         }
     }
 }
@@ -147,6 +162,8 @@ impl From<SplitMetadata> for SplitMetadataV0_8 {
             tags: split.tags,
             footer_offsets: split.footer_offsets,
             num_merge_ops: split.num_merge_ops,
+            storage_uri: split.storage_uri,
+            field_stats: split.field_stats,
         }
     }
 }
@@ -138,6 +138,8 @@ pub async fn test_metastore_open_shards<
             shard_id: Some(ShardId::from(1)),
             leader_id: "test-ingester-foo".to_string(),
             follower_id: Some("test-ingester-bar".to_string()),
+            additional_follower_ids: vec!["test-ingester-baz".to_string()],
+            reassign: false,
         }],
     };
     let open_shards_response = metastore.open_shards(open_shards_request).await.unwrap();
@@ -155,6 +157,7 @@ pub async fn test_metastore_open_shards<
     assert_eq!(shard.shard_state(), ShardState::Open);
     assert_eq!(shard.leader_id, "test-ingester-foo");
     assert_eq!(shard.follower_id(), "test-ingester-bar");
+    assert_eq!(shard.additional_follower_ids, ["test-ingester-baz"]);
     assert_eq!(shard.publish_position_inclusive(), Position::Beginning);
     assert!(shard.publish_token.is_none());
 
@@ -167,6 +170,8 @@ pub async fn test_metastore_open_shards<
             shard_id: Some(ShardId::from(1)),
             leader_id: "test-ingester-foo".to_string(),
             follower_id: Some("test-ingester-bar".to_string()),
+            additional_follower_ids: vec!["test-ingester-baz".to_string()],
+            reassign: false,
         }],
     };
     let open_shards_response = metastore.open_shards(open_shards_request).await.unwrap();
@@ -184,9 +189,37 @@ pub async fn test_metastore_open_shards<
     assert_eq!(shard.shard_state(), ShardState::Open);
     assert_eq!(shard.leader_id, "test-ingester-foo");
     assert_eq!(shard.follower_id(), "test-ingester-bar");
+    assert_eq!(shard.additional_follower_ids, ["test-ingester-baz"]);
     assert_eq!(shard.publish_position_inclusive(), Position::Beginning);
     assert!(shard.publish_token.is_none());
 
+    // Test reassign shard #1.
+    let open_shards_request = OpenShardsRequest {
+        subrequests: vec![OpenShardsSubrequest {
+            subrequest_id: 0,
+            index_uid: test_index.index_uid.clone().into(),
+            source_id: test_index.source_id.clone(),
+            shard_id: Some(ShardId::from(1)),
+            leader_id: "test-ingester-qux".to_string(),
+            follower_id: None,
+            additional_follower_ids: Vec::new(),
+            reassign: true,
+        }],
+    };
+    let open_shards_response = metastore.open_shards(open_shards_request).await.unwrap();
+    assert_eq!(open_shards_response.subresponses.len(), 1);
+
+    let subresponse = &open_shards_response.subresponses[0];
+    assert_eq!(subresponse.opened_shards.len(), 1);
+
+    let shard = &subresponse.opened_shards[0];
+    assert_eq!(shard.shard_id(), ShardId::from(1));
+    assert_eq!(shard.shard_state(), ShardState::Open);
+    assert_eq!(shard.leader_id, "test-ingester-qux");
+    assert!(shard.follower_id.is_none());
+    assert!(shard.additional_follower_ids.is_empty());
+    assert_eq!(shard.publish_position_inclusive(), Position::Beginning);
+
     cleanup_index(&mut metastore, test_index.index_uid).await;
 }
 
@@ -210,6 +243,7 @@ pub async fn test_metastore_acquire_shards<
             shard_state: ShardState::Closed as i32,
             leader_id: "test-ingester-foo".to_string(),
             follower_id: Some("test-ingester-bar".to_string()),
+            additional_follower_ids: Vec::new(),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: Some("test-publish-token-foo".to_string()),
         },
@@ -220,6 +254,7 @@ pub async fn test_metastore_acquire_shards<
             shard_state: ShardState::Open as i32,
             leader_id: "test-ingester-bar".to_string(),
             follower_id: Some("test-ingester-qux".to_string()),
+            additional_follower_ids: Vec::new(),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: Some("test-publish-token-bar".to_string()),
         },
@@ -230,6 +265,7 @@ pub async fn test_metastore_acquire_shards<
             shard_state: ShardState::Open as i32,
             leader_id: "test-ingester-qux".to_string(),
             follower_id: Some("test-ingester-baz".to_string()),
+            additional_follower_ids: Vec::new(),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: None,
         },
@@ -240,6 +276,7 @@ pub async fn test_metastore_acquire_shards<
             shard_state: ShardState::Open as i32,
             leader_id: "test-ingester-baz".to_string(),
             follower_id: Some("test-ingester-tux".to_string()),
+            additional_follower_ids: Vec::new(),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: None,
         },
@@ -313,6 +350,7 @@ pub async fn test_metastore_list_shards<
             shard_state: ShardState::Open as i32,
             leader_id: "test-ingester-foo".to_string(),
             follower_id: Some("test-ingester-bar".to_string()),
+            additional_follower_ids: Vec::new(),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: Some("test-publish-token-foo".to_string()),
         },
@@ -323,6 +361,7 @@ pub async fn test_metastore_list_shards<
             shard_state: ShardState::Closed as i32,
             leader_id: "test-ingester-bar".to_string(),
             follower_id: Some("test-ingester-qux".to_string()),
+            additional_follower_ids: Vec::new(),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: Some("test-publish-token-bar".to_string()),
         },
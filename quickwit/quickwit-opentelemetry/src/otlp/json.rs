@@ -0,0 +1,640 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of OTLP/JSON export requests and encoding of the corresponding responses.
+//!
+//! OTLP/JSON is the JSON mapping of the OTLP protobuf messages with a few deviations from the
+//! canonical proto3 JSON mapping: trace and span IDs are hex-encoded instead of base64-encoded,
+//! field names are camel-cased, and enums are encoded as integers. See
+//! <https://opentelemetry.io/docs/specs/otlp/#json-protobuf-encoding>.
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use quickwit_proto::opentelemetry::proto::collector::logs::v1::{
+    ExportLogsServiceRequest, ExportLogsServiceResponse,
+};
+use quickwit_proto::opentelemetry::proto::collector::trace::v1::{
+    ExportTraceServiceRequest, ExportTraceServiceResponse,
+};
+use quickwit_proto::opentelemetry::proto::common::v1::any_value::Value as OtlpValue;
+use quickwit_proto::opentelemetry::proto::common::v1::{
+    AnyValue as OtlpAnyValue, ArrayValue as OtlpArrayValue, InstrumentationScope,
+    KeyValue as OtlpKeyValue, KeyValueList as OtlpKeyValueList,
+};
+use quickwit_proto::opentelemetry::proto::logs::v1::{
+    LogRecord as OtlpLogRecord, ResourceLogs, ScopeLogs,
+};
+use quickwit_proto::opentelemetry::proto::resource::v1::Resource as OtlpResource;
+use quickwit_proto::opentelemetry::proto::trace::v1::span::{Event as OtlpEvent, Link as OtlpLink};
+use quickwit_proto::opentelemetry::proto::trace::v1::{
+    ResourceSpans, ScopeSpans, Span as OtlpSpan, Status as OtlpStatus,
+};
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Value as JsonValue};
+use serde_with::{serde_as, DisplayFromStr, PickFirst};
+
+/// Parses an OTLP/JSON encoded `ExportLogsServiceRequest`.
+pub fn parse_otlp_logs_request_json(
+    payload_json: &[u8],
+) -> serde_json::Result<ExportLogsServiceRequest> {
+    let request: JsonExportLogsServiceRequest = serde_json::from_slice(payload_json)?;
+    Ok(request.into())
+}
+
+/// Parses an OTLP/JSON encoded `ExportTraceServiceRequest`.
+pub fn parse_otlp_traces_request_json(
+    payload_json: &[u8],
+) -> serde_json::Result<ExportTraceServiceRequest> {
+    let request: JsonExportTraceServiceRequest = serde_json::from_slice(payload_json)?;
+    Ok(request.into())
+}
+
+/// Encodes an `ExportLogsServiceResponse` in OTLP/JSON.
+pub fn otlp_logs_response_to_json(response: &ExportLogsServiceResponse) -> JsonValue {
+    match &response.partial_success {
+        Some(partial_success) => json!({
+            "partialSuccess": {
+                "rejectedLogRecords": partial_success.rejected_log_records.to_string(),
+                "errorMessage": partial_success.error_message,
+            }
+        }),
+        None => json!({}),
+    }
+}
+
+/// Encodes an `ExportTraceServiceResponse` in OTLP/JSON.
+pub fn otlp_traces_response_to_json(response: &ExportTraceServiceResponse) -> JsonValue {
+    match &response.partial_success {
+        Some(partial_success) => json!({
+            "partialSuccess": {
+                "rejectedSpans": partial_success.rejected_spans.to_string(),
+                "errorMessage": partial_success.error_message,
+            }
+        }),
+        None => json!({}),
+    }
+}
+
+/// Deserializes a hex-encoded trace or span ID. Root spans have no parent span ID, which is
+/// encoded as an empty string and deserializes into an empty vector.
+fn deserialize_hex_id<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where D: Deserializer<'de> {
+    let hex_id = String::deserialize(deserializer)?;
+    hex::decode(hex_id).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_base64<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where D: Deserializer<'de> {
+    let base64_value = String::deserialize(deserializer)?;
+    BASE64_STANDARD
+        .decode(base64_value)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonExportLogsServiceRequest {
+    resource_logs: Vec<JsonResourceLogs>,
+}
+
+impl From<JsonExportLogsServiceRequest> for ExportLogsServiceRequest {
+    fn from(request: JsonExportLogsServiceRequest) -> Self {
+        ExportLogsServiceRequest {
+            resource_logs: request.resource_logs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonResourceLogs {
+    resource: Option<JsonResource>,
+    scope_logs: Vec<JsonScopeLogs>,
+    schema_url: String,
+}
+
+impl From<JsonResourceLogs> for ResourceLogs {
+    fn from(resource_logs: JsonResourceLogs) -> Self {
+        ResourceLogs {
+            resource: resource_logs.resource.map(Into::into),
+            scope_logs: resource_logs
+                .scope_logs
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            schema_url: resource_logs.schema_url,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonScopeLogs {
+    scope: Option<JsonInstrumentationScope>,
+    log_records: Vec<JsonLogRecord>,
+    schema_url: String,
+}
+
+impl From<JsonScopeLogs> for ScopeLogs {
+    fn from(scope_logs: JsonScopeLogs) -> Self {
+        ScopeLogs {
+            scope: scope_logs.scope.map(Into::into),
+            log_records: scope_logs.log_records.into_iter().map(Into::into).collect(),
+            schema_url: scope_logs.schema_url,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonLogRecord {
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    time_unix_nano: u64,
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    observed_time_unix_nano: u64,
+    severity_number: i32,
+    severity_text: String,
+    body: Option<JsonAnyValue>,
+    attributes: Vec<JsonKeyValue>,
+    dropped_attributes_count: u32,
+    flags: u32,
+    #[serde(deserialize_with = "deserialize_hex_id")]
+    trace_id: Vec<u8>,
+    #[serde(deserialize_with = "deserialize_hex_id")]
+    span_id: Vec<u8>,
+}
+
+impl From<JsonLogRecord> for OtlpLogRecord {
+    fn from(log_record: JsonLogRecord) -> Self {
+        OtlpLogRecord {
+            time_unix_nano: log_record.time_unix_nano,
+            observed_time_unix_nano: log_record.observed_time_unix_nano,
+            severity_number: log_record.severity_number,
+            severity_text: log_record.severity_text,
+            body: log_record.body.map(Into::into),
+            attributes: into_otlp_key_values(log_record.attributes),
+            dropped_attributes_count: log_record.dropped_attributes_count,
+            flags: log_record.flags,
+            trace_id: log_record.trace_id,
+            span_id: log_record.span_id,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonExportTraceServiceRequest {
+    resource_spans: Vec<JsonResourceSpans>,
+}
+
+impl From<JsonExportTraceServiceRequest> for ExportTraceServiceRequest {
+    fn from(request: JsonExportTraceServiceRequest) -> Self {
+        ExportTraceServiceRequest {
+            resource_spans: request.resource_spans.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonResourceSpans {
+    resource: Option<JsonResource>,
+    scope_spans: Vec<JsonScopeSpans>,
+    schema_url: String,
+}
+
+impl From<JsonResourceSpans> for ResourceSpans {
+    fn from(resource_spans: JsonResourceSpans) -> Self {
+        ResourceSpans {
+            resource: resource_spans.resource.map(Into::into),
+            scope_spans: resource_spans
+                .scope_spans
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            schema_url: resource_spans.schema_url,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonScopeSpans {
+    scope: Option<JsonInstrumentationScope>,
+    spans: Vec<JsonSpan>,
+    schema_url: String,
+}
+
+impl From<JsonScopeSpans> for ScopeSpans {
+    fn from(scope_spans: JsonScopeSpans) -> Self {
+        ScopeSpans {
+            scope: scope_spans.scope.map(Into::into),
+            spans: scope_spans.spans.into_iter().map(Into::into).collect(),
+            schema_url: scope_spans.schema_url,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonSpan {
+    #[serde(deserialize_with = "deserialize_hex_id")]
+    trace_id: Vec<u8>,
+    #[serde(deserialize_with = "deserialize_hex_id")]
+    span_id: Vec<u8>,
+    trace_state: String,
+    #[serde(deserialize_with = "deserialize_hex_id")]
+    parent_span_id: Vec<u8>,
+    name: String,
+    kind: i32,
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    start_time_unix_nano: u64,
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    end_time_unix_nano: u64,
+    attributes: Vec<JsonKeyValue>,
+    dropped_attributes_count: u32,
+    events: Vec<JsonEvent>,
+    dropped_events_count: u32,
+    links: Vec<JsonLink>,
+    dropped_links_count: u32,
+    status: Option<JsonStatus>,
+}
+
+impl From<JsonSpan> for OtlpSpan {
+    fn from(span: JsonSpan) -> Self {
+        OtlpSpan {
+            trace_id: span.trace_id,
+            span_id: span.span_id,
+            trace_state: span.trace_state,
+            parent_span_id: span.parent_span_id,
+            name: span.name,
+            kind: span.kind,
+            start_time_unix_nano: span.start_time_unix_nano,
+            end_time_unix_nano: span.end_time_unix_nano,
+            attributes: into_otlp_key_values(span.attributes),
+            dropped_attributes_count: span.dropped_attributes_count,
+            events: span.events.into_iter().map(Into::into).collect(),
+            dropped_events_count: span.dropped_events_count,
+            links: span.links.into_iter().map(Into::into).collect(),
+            dropped_links_count: span.dropped_links_count,
+            status: span.status.map(Into::into),
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonEvent {
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    time_unix_nano: u64,
+    name: String,
+    attributes: Vec<JsonKeyValue>,
+    dropped_attributes_count: u32,
+}
+
+impl From<JsonEvent> for OtlpEvent {
+    fn from(event: JsonEvent) -> Self {
+        OtlpEvent {
+            time_unix_nano: event.time_unix_nano,
+            name: event.name,
+            attributes: into_otlp_key_values(event.attributes),
+            dropped_attributes_count: event.dropped_attributes_count,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonLink {
+    #[serde(deserialize_with = "deserialize_hex_id")]
+    trace_id: Vec<u8>,
+    #[serde(deserialize_with = "deserialize_hex_id")]
+    span_id: Vec<u8>,
+    trace_state: String,
+    attributes: Vec<JsonKeyValue>,
+    dropped_attributes_count: u32,
+}
+
+impl From<JsonLink> for OtlpLink {
+    fn from(link: JsonLink) -> Self {
+        OtlpLink {
+            trace_id: link.trace_id,
+            span_id: link.span_id,
+            trace_state: link.trace_state,
+            attributes: into_otlp_key_values(link.attributes),
+            dropped_attributes_count: link.dropped_attributes_count,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonStatus {
+    message: String,
+    code: i32,
+}
+
+impl From<JsonStatus> for OtlpStatus {
+    fn from(status: JsonStatus) -> Self {
+        OtlpStatus {
+            message: status.message,
+            code: status.code,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonResource {
+    attributes: Vec<JsonKeyValue>,
+    dropped_attributes_count: u32,
+}
+
+impl From<JsonResource> for OtlpResource {
+    fn from(resource: JsonResource) -> Self {
+        OtlpResource {
+            attributes: into_otlp_key_values(resource.attributes),
+            dropped_attributes_count: resource.dropped_attributes_count,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonInstrumentationScope {
+    name: String,
+    version: String,
+    attributes: Vec<JsonKeyValue>,
+    dropped_attributes_count: u32,
+}
+
+impl From<JsonInstrumentationScope> for InstrumentationScope {
+    fn from(scope: JsonInstrumentationScope) -> Self {
+        InstrumentationScope {
+            name: scope.name,
+            version: scope.version,
+            attributes: into_otlp_key_values(scope.attributes),
+            dropped_attributes_count: scope.dropped_attributes_count,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct JsonKeyValue {
+    key: String,
+    value: Option<JsonAnyValue>,
+}
+
+fn into_otlp_key_values(key_values: Vec<JsonKeyValue>) -> Vec<OtlpKeyValue> {
+    key_values
+        .into_iter()
+        .map(|key_value| OtlpKeyValue {
+            key: key_value.key,
+            value: key_value.value.map(Into::into),
+        })
+        .collect()
+}
+
+/// `AnyValue` is a `oneof`, which is encoded as an object holding at most one of the fields
+/// below.
+#[serde_as]
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonAnyValue {
+    string_value: Option<String>,
+    bool_value: Option<bool>,
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    int_value: Option<i64>,
+    double_value: Option<f64>,
+    array_value: Option<JsonArrayValue>,
+    kvlist_value: Option<JsonKeyValueList>,
+    #[serde(deserialize_with = "deserialize_base64")]
+    bytes_value: Option<Vec<u8>>,
+}
+
+impl From<JsonAnyValue> for OtlpAnyValue {
+    fn from(any_value: JsonAnyValue) -> Self {
+        let value = if let Some(value) = any_value.string_value {
+            Some(OtlpValue::StringValue(value))
+        } else if let Some(value) = any_value.bool_value {
+            Some(OtlpValue::BoolValue(value))
+        } else if let Some(value) = any_value.int_value {
+            Some(OtlpValue::IntValue(value))
+        } else if let Some(value) = any_value.double_value {
+            Some(OtlpValue::DoubleValue(value))
+        } else if let Some(value) = any_value.array_value {
+            Some(OtlpValue::ArrayValue(OtlpArrayValue {
+                values: value.values.into_iter().map(Into::into).collect(),
+            }))
+        } else if let Some(value) = any_value.kvlist_value {
+            Some(OtlpValue::KvlistValue(OtlpKeyValueList {
+                values: into_otlp_key_values(value.values),
+            }))
+        } else {
+            any_value.bytes_value.map(OtlpValue::BytesValue)
+        };
+        OtlpAnyValue { value }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct JsonArrayValue {
+    values: Vec<JsonAnyValue>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct JsonKeyValueList {
+    values: Vec<JsonKeyValue>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_otlp_logs_request_json() {
+        let payload_json = r#"{
+            "resourceLogs": [{
+                "resource": {
+                    "attributes": [{"key": "service.name", "value": {"stringValue": "api"}}]
+                },
+                "scopeLogs": [{
+                    "scope": {"name": "my-scope", "version": "1.0.0"},
+                    "logRecords": [{
+                        "timeUnixNano": "1704036033047000000",
+                        "observedTimeUnixNano": 1704036033048000000,
+                        "severityNumber": 17,
+                        "severityText": "ERROR",
+                        "body": {"stringValue": "connection refused"},
+                        "attributes": [
+                            {"key": "retries", "value": {"intValue": "3"}},
+                            {"key": "ratio", "value": {"doubleValue": 0.5}},
+                            {"key": "payload", "value": {"bytesValue": "AQI="}},
+                            {"key": "tags", "value": {"arrayValue": {"values": [{"stringValue": "a"}, {"boolValue": true}]}}},
+                            {"key": "http", "value": {"kvlistValue": {"values": [{"key": "method", "value": {"stringValue": "GET"}}]}}}
+                        ],
+                        "traceId": "5b8efff798038103d269b633813fc60c",
+                        "spanId": "eee19b7ec3c1b174"
+                    }]
+                }]
+            }]
+        }"#;
+        let request = parse_otlp_logs_request_json(payload_json.as_bytes()).unwrap();
+        assert_eq!(request.resource_logs.len(), 1);
+
+        let resource_logs = &request.resource_logs[0];
+        let resource = resource_logs.resource.as_ref().unwrap();
+        assert_eq!(resource.attributes[0].key, "service.name");
+
+        let scope_logs = &resource_logs.scope_logs[0];
+        assert_eq!(scope_logs.scope.as_ref().unwrap().name, "my-scope");
+
+        let log_record = &scope_logs.log_records[0];
+        assert_eq!(log_record.time_unix_nano, 1704036033047000000);
+        assert_eq!(log_record.observed_time_unix_nano, 1704036033048000000);
+        assert_eq!(log_record.severity_number, 17);
+        assert_eq!(log_record.severity_text, "ERROR");
+        assert_eq!(
+            log_record.body.as_ref().unwrap().value,
+            Some(OtlpValue::StringValue("connection refused".to_string()))
+        );
+        assert_eq!(
+            log_record.trace_id,
+            hex::decode("5b8efff798038103d269b633813fc60c").unwrap()
+        );
+        assert_eq!(log_record.span_id, hex::decode("eee19b7ec3c1b174").unwrap());
+
+        let attribute_values: Vec<Option<OtlpValue>> = log_record
+            .attributes
+            .iter()
+            .map(|key_value| key_value.value.clone().unwrap().value)
+            .collect();
+        assert_eq!(
+            attribute_values,
+            [
+                Some(OtlpValue::IntValue(3)),
+                Some(OtlpValue::DoubleValue(0.5)),
+                Some(OtlpValue::BytesValue(vec![1, 2])),
+                Some(OtlpValue::ArrayValue(OtlpArrayValue {
+                    values: vec![
+                        OtlpAnyValue {
+                            value: Some(OtlpValue::StringValue("a".to_string()))
+                        },
+                        OtlpAnyValue {
+                            value: Some(OtlpValue::BoolValue(true))
+                        },
+                    ]
+                })),
+                Some(OtlpValue::KvlistValue(OtlpKeyValueList {
+                    values: vec![OtlpKeyValue {
+                        key: "method".to_string(),
+                        value: Some(OtlpAnyValue {
+                            value: Some(OtlpValue::StringValue("GET".to_string()))
+                        }),
+                    }]
+                })),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_otlp_traces_request_json() {
+        let payload_json = r#"{
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [{"key": "service.name", "value": {"stringValue": "api"}}]
+                },
+                "scopeSpans": [{
+                    "spans": [{
+                        "traceId": "5b8efff798038103d269b633813fc60c",
+                        "spanId": "eee19b7ec3c1b174",
+                        "parentSpanId": "",
+                        "name": "GET /",
+                        "kind": 2,
+                        "startTimeUnixNano": "1544712660000000000",
+                        "endTimeUnixNano": "1544712661000000000",
+                        "events": [{"timeUnixNano": "1544712660500000000", "name": "retry"}],
+                        "links": [{"traceId": "5b8efff798038103d269b633813fc60c", "spanId": "eee19b7ec3c1b173"}],
+                        "status": {"code": 2, "message": "boom"}
+                    }]
+                }]
+            }]
+        }"#;
+        let request = parse_otlp_traces_request_json(payload_json.as_bytes()).unwrap();
+        let span = &request.resource_spans[0].scope_spans[0].spans[0];
+        assert_eq!(
+            span.trace_id,
+            hex::decode("5b8efff798038103d269b633813fc60c").unwrap()
+        );
+        assert_eq!(span.span_id, hex::decode("eee19b7ec3c1b174").unwrap());
+        assert!(span.parent_span_id.is_empty());
+        assert_eq!(span.name, "GET /");
+        assert_eq!(span.kind, 2);
+        assert_eq!(span.start_time_unix_nano, 1544712660000000000);
+        assert_eq!(span.end_time_unix_nano, 1544712661000000000);
+        assert_eq!(span.events[0].time_unix_nano, 1544712660500000000);
+        assert_eq!(
+            span.links[0].span_id,
+            hex::decode("eee19b7ec3c1b173").unwrap()
+        );
+        assert_eq!(span.status.as_ref().unwrap().code, 2);
+        assert_eq!(span.status.as_ref().unwrap().message, "boom");
+    }
+
+    #[test]
+    fn test_parse_otlp_traces_request_json_invalid_id() {
+        let payload_json = r#"{
+            "resourceSpans": [{"scopeSpans": [{"spans": [{"traceId": "not-hex"}]}]}]
+        }"#;
+        parse_otlp_traces_request_json(payload_json.as_bytes()).unwrap_err();
+    }
+
+    #[test]
+    fn test_otlp_responses_to_json() {
+        use quickwit_proto::opentelemetry::proto::collector::logs::v1::ExportLogsPartialSuccess;
+        use quickwit_proto::opentelemetry::proto::collector::trace::v1::ExportTracePartialSuccess;
+
+        let logs_response = ExportLogsServiceResponse {
+            partial_success: Some(ExportLogsPartialSuccess {
+                rejected_log_records: 2,
+                error_message: "invalid trace ID".to_string(),
+            }),
+        };
+        assert_eq!(
+            otlp_logs_response_to_json(&logs_response),
+            json!({"partialSuccess": {"rejectedLogRecords": "2", "errorMessage": "invalid trace ID"}})
+        );
+        let traces_response = ExportTraceServiceResponse {
+            partial_success: Some(ExportTracePartialSuccess {
+                rejected_spans: 0,
+                error_message: String::new(),
+            }),
+        };
+        assert_eq!(
+            otlp_traces_response_to_json(&traces_response),
+            json!({"partialSuccess": {"rejectedSpans": "0", "errorMessage": ""}})
+        );
+    }
+}
@@ -258,7 +258,7 @@ impl OtlpGrpcLogsService {
             Status::internal("failed to parse log records")
         })??;
         if num_log_records == num_parse_errors {
-            return Err(tonic::Status::internal(error_message));
+            return Err(tonic::Status::invalid_argument(error_message));
         }
         let num_bytes = doc_batch.num_bytes() as u64;
         self.store_logs(doc_batch).await?;
@@ -266,7 +266,7 @@ impl OtlpGrpcLogsService {
         OTLP_SERVICE_METRICS
             .ingested_log_records_total
             .with_label_values(labels)
-            .inc_by(num_log_records);
+            .inc_by(num_log_records - num_parse_errors);
         OTLP_SERVICE_METRICS
             .ingested_bytes_total
             .with_label_values(labels)
@@ -338,6 +338,8 @@ impl OtlpGrpcLogsService {
                     num_log_records += 1;
 
                     if log_record.time_unix_nano == 0 {
+                        error_message =
+                            "failed to parse log record: timestamp is missing".to_string();
                         num_parse_errors += 1;
                         continue;
                     }
@@ -346,15 +348,29 @@ impl OtlpGrpcLogsService {
                     } else {
                         None
                     };
+                    // Log records with invalid trace or span IDs are rejected individually and
+                    // reported in the partial success response.
                     let trace_id = if log_record.trace_id.iter().any(|&byte| byte != 0) {
-                        let trace_id = TraceId::try_from(log_record.trace_id)?;
-                        Some(trace_id)
+                        match TraceId::try_from(log_record.trace_id) {
+                            Ok(trace_id) => Some(trace_id),
+                            Err(error) => {
+                                error_message = format!("failed to parse log record: {error}");
+                                num_parse_errors += 1;
+                                continue;
+                            }
+                        }
                     } else {
                         None
                     };
                     let span_id = if log_record.span_id.iter().any(|&byte| byte != 0) {
-                        let span_id = SpanId::try_from(log_record.span_id)?;
-                        Some(span_id)
+                        match SpanId::try_from(log_record.span_id) {
+                            Ok(span_id) => Some(span_id),
+                            Err(error) => {
+                                error_message = format!("failed to parse log record: {error}");
+                                num_parse_errors += 1;
+                                continue;
+                            }
+                        }
                     } else {
                         None
                     };
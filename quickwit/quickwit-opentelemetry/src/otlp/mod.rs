@@ -26,6 +26,7 @@ use quickwit_proto::opentelemetry::proto::common::v1::{
 };
 use serde_json::{Number as JsonNumber, Value as JsonValue};
 
+mod json;
 mod logs;
 mod metrics;
 mod span_id;
@@ -34,6 +35,10 @@ mod test_utils;
 mod trace_id;
 mod traces;
 
+pub use json::{
+    otlp_logs_response_to_json, otlp_traces_response_to_json, parse_otlp_logs_request_json,
+    parse_otlp_traces_request_json,
+};
 pub use logs::{OtlpGrpcLogsService, OTEL_LOGS_INDEX_ID};
 pub use span_id::{SpanId, TryFromSpanIdError};
 #[cfg(any(test, feature = "testsuite"))]
@@ -158,6 +158,9 @@ doc_mapping:
 
   timestamp_field: span_start_timestamp_nanos
 
+  # Lets trace lookups skip the splits that do not contain the trace.
+  bloom_filter_fields: [trace_id]
+
   # partition_key: hash_mod(service_name, 100)
   # tag_fields: [service_name]
 
@@ -724,7 +727,7 @@ impl OtlpGrpcTracesService {
             return Err(tonic::Status::invalid_argument("request is empty"));
         }
         if num_spans == num_parse_errors {
-            return Err(tonic::Status::internal(error_message));
+            return Err(tonic::Status::invalid_argument(error_message));
         }
         let num_bytes = doc_batch.num_bytes() as u64;
         self.store_spans(doc_batch).await?;
@@ -732,7 +735,7 @@ impl OtlpGrpcTracesService {
         OTLP_SERVICE_METRICS
             .ingested_spans_total
             .with_label_values(labels)
-            .inc_by(num_spans);
+            .inc_by(num_spans - num_parse_errors);
         OTLP_SERVICE_METRICS
             .ingested_bytes_total
             .with_label_values(labels)
@@ -754,11 +757,35 @@ impl OtlpGrpcTracesService {
         parent_span: RuntimeSpan,
         index_id: IndexId,
     ) -> tonic::Result<ParsedSpans> {
-        let spans = parse_otlp_spans(request)?;
-        let num_spans = spans.len() as u64;
+        let mut spans = BTreeSet::new();
+        let mut num_spans = 0;
         let mut num_parse_errors = 0;
         let mut error_message = String::new();
 
+        // Invalid spans are rejected individually and reported in the partial success response
+        // instead of failing the whole request.
+        for resource_spans in request.resource_spans {
+            let resource = resource_spans
+                .resource
+                .map(Resource::from_otlp)
+                .unwrap_or_default();
+            for scope_spans in resource_spans.scope_spans {
+                let scope = scope_spans.scope.map(Scope::from_otlp).unwrap_or_default();
+                for span in scope_spans.spans {
+                    num_spans += 1;
+
+                    match Span::from_otlp(span, &resource, &scope) {
+                        Ok(span) => {
+                            spans.insert(OrdSpan(span));
+                        }
+                        Err(error) => {
+                            error_message = error.to_string();
+                            num_parse_errors += 1;
+                        }
+                    }
+                }
+            }
+        }
         let mut doc_batch_builder = DocBatchBuilder::new(index_id).json_writer();
         for span in spans {
             if let Err(error) = doc_batch_builder.ingest_doc(&span.0) {
@@ -918,6 +945,10 @@ mod tests {
         let index_config =
             OtlpGrpcTracesService::index_config(&Uri::for_test("ram:///indexes")).unwrap();
         assert_eq!(index_config.index_id, OTEL_TRACES_INDEX_ID);
+        assert!(index_config
+            .doc_mapping
+            .bloom_filter_fields
+            .contains("trace_id"));
     }
 
     #[tokio::test]
@@ -930,6 +961,27 @@ mod tests {
         metastore.create_index(create_index_request).await.unwrap();
     }
 
+    #[test]
+    fn test_parse_spans_rejects_invalid_spans() {
+        let mut resource_spans = crate::otlp::make_resource_spans_for_test();
+        resource_spans[0].scope_spans[0].spans[0].trace_id = vec![1; 3];
+        let request = ExportTraceServiceRequest { resource_spans };
+
+        let parsed_spans = OtlpGrpcTracesService::parse_spans(
+            request,
+            RuntimeSpan::current(),
+            OTEL_TRACES_INDEX_ID.to_string(),
+        )
+        .unwrap();
+        assert_eq!(parsed_spans.num_spans, 5);
+        assert_eq!(parsed_spans.num_parse_errors, 1);
+        assert_eq!(
+            parsed_spans.error_message,
+            "failed to parse span: `trace ID must be 16 bytes long, got 3`"
+        );
+        assert_eq!(parsed_spans.doc_batch.num_docs(), 4);
+    }
+
     #[test]
     fn test_resource_from_otlp() {
         let otlp_resource = OtlpResource {
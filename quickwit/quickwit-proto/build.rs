@@ -116,6 +116,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "Shard.follower_id",
             "#[serde(default, skip_serializing_if = \"Option::is_none\")]",
         )
+        .field_attribute(
+            "Shard.additional_follower_ids",
+            "#[serde(default, skip_serializing_if = \"Vec::is_empty\")]",
+        )
         .field_attribute(
             "Shard.publish_position_inclusive",
             "#[serde(default, skip_serializing_if = \"Option::is_none\")]",
@@ -154,6 +158,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .type_attribute("PartialHit.sort_value", "#[derive(Copy)]")
         .type_attribute("SearchRequest", "#[derive(Eq, Hash)]")
         .type_attribute("ListFieldSerialized", "#[derive(Eq)]")
+        .type_attribute("RuntimeMapping", "#[derive(Eq, Hash)]")
         .type_attribute("SortByValue", "#[derive(Ord, PartialOrd)]")
         .type_attribute("SortField", "#[derive(Eq, Hash)]")
         .out_dir("src/codegen/quickwit")
@@ -45,6 +45,15 @@ pub struct GetOrCreateOpenShardsSuccess {
     pub source_id: ::prost::alloc::string::String,
     #[prost(message, repeated, tag = "4")]
     pub open_shards: ::prost::alloc::vec::Vec<super::ingest::Shard>,
+    /// JSON-serialized doc mapping of the index, used by routers to validate documents. Empty when
+    /// the source applies a transform to the documents before indexing.
+    #[prost(string, tag = "5")]
+    pub doc_mapping_json: ::prost::alloc::string::String,
+    /// Maximum ingest throughput of the index in bytes per second, if the index has a throughput quota.
+    /// Routers reject the ingest requests targeting the index while the ingestion rate of its shards
+    /// across the cluster reaches it.
+    #[prost(uint64, optional, tag = "6")]
+    pub max_ingest_throughput: ::core::option::Option<u64>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -71,6 +80,8 @@ pub struct GetDebugStateResponse {
     pub shard_table: ::prost::alloc::vec::Vec<ShardTableEntry>,
     #[prost(message, repeated, tag = "2")]
     pub physical_index_plan: ::prost::alloc::vec::Vec<PhysicalIndexingPlanEntry>,
+    #[prost(message, repeated, tag = "3")]
+    pub ingest_quotas: ::prost::alloc::vec::Vec<IngestQuotaEntry>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -84,6 +95,24 @@ pub struct ShardTableEntry {
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IngestQuotaEntry {
+    #[prost(string, tag = "1")]
+    pub index_uid: ::prost::alloc::string::String,
+    /// Volume of data ingested today (UTC) across all the ingesters.
+    #[prost(uint64, tag = "2")]
+    pub daily_ingest_volume_bytes: u64,
+    #[prost(uint64, optional, tag = "3")]
+    pub max_daily_ingest_volume_bytes: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "4")]
+    pub max_ingest_throughput_bytes: ::core::option::Option<u64>,
+    #[prost(uint32, tag = "5")]
+    pub num_open_shards: u32,
+    #[prost(bool, tag = "6")]
+    pub quota_exceeded: bool,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PhysicalIndexingPlanEntry {
     #[prost(string, tag = "1")]
     pub node_id: ::prost::alloc::string::String,
@@ -107,6 +136,21 @@ pub struct AdviseResetShardsResponse {
     pub shards_to_truncate: ::prost::alloc::vec::Vec<super::ingest::ShardIdPositions>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HandoffShardsRequest {
+    /// The node ID of the decommissioning ingester.
+    #[prost(string, tag = "1")]
+    pub former_leader_id: ::prost::alloc::string::String,
+    /// The shards handed off, with their new leader and followers.
+    #[prost(message, repeated, tag = "2")]
+    pub shards: ::prost::alloc::vec::Vec<super::ingest::Shard>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HandoffShardsResponse {}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[serde(rename_all = "snake_case")]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
@@ -115,6 +159,7 @@ pub enum GetOrCreateOpenShardsFailureReason {
     IndexNotFound = 1,
     SourceNotFound = 2,
     NoIngestersAvailable = 3,
+    QuotaExceeded = 4,
 }
 impl GetOrCreateOpenShardsFailureReason {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -135,6 +180,9 @@ impl GetOrCreateOpenShardsFailureReason {
             GetOrCreateOpenShardsFailureReason::NoIngestersAvailable => {
                 "GET_OR_CREATE_OPEN_SHARDS_FAILURE_REASON_NO_INGESTERS_AVAILABLE"
             }
+            GetOrCreateOpenShardsFailureReason::QuotaExceeded => {
+                "GET_OR_CREATE_OPEN_SHARDS_FAILURE_REASON_QUOTA_EXCEEDED"
+            }
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -152,6 +200,9 @@ impl GetOrCreateOpenShardsFailureReason {
             "GET_OR_CREATE_OPEN_SHARDS_FAILURE_REASON_NO_INGESTERS_AVAILABLE" => {
                 Some(Self::NoIngestersAvailable)
             }
+            "GET_OR_CREATE_OPEN_SHARDS_FAILURE_REASON_QUOTA_EXCEEDED" => {
+                Some(Self::QuotaExceeded)
+            }
             _ => None,
         }
     }
@@ -199,6 +250,11 @@ pub trait ControlPlaneService: std::fmt::Debug + dyn_clone::DynClone + Send + Sy
         &mut self,
         request: AdviseResetShardsRequest,
     ) -> crate::control_plane::ControlPlaneResult<AdviseResetShardsResponse>;
+    /// Notifies the control plane that a decommissioning ingester handed off some of its shards to other ingesters.
+    async fn handoff_shards(
+        &mut self,
+        request: HandoffShardsRequest,
+    ) -> crate::control_plane::ControlPlaneResult<HandoffShardsResponse>;
     /// Return some innerstate of the control plane meant to assist debugging.
     async fn get_debug_state(
         &mut self,
@@ -336,6 +392,12 @@ impl ControlPlaneService for ControlPlaneServiceClient {
     ) -> crate::control_plane::ControlPlaneResult<AdviseResetShardsResponse> {
         self.inner.advise_reset_shards(request).await
     }
+    async fn handoff_shards(
+        &mut self,
+        request: HandoffShardsRequest,
+    ) -> crate::control_plane::ControlPlaneResult<HandoffShardsResponse> {
+        self.inner.handoff_shards(request).await
+    }
     async fn get_debug_state(
         &mut self,
         request: GetDebugStateRequest,
@@ -406,6 +468,12 @@ pub mod control_plane_service_mock {
         ) -> crate::control_plane::ControlPlaneResult<super::AdviseResetShardsResponse> {
             self.inner.lock().await.advise_reset_shards(request).await
         }
+        async fn handoff_shards(
+            &mut self,
+            request: super::HandoffShardsRequest,
+        ) -> crate::control_plane::ControlPlaneResult<super::HandoffShardsResponse> {
+            self.inner.lock().await.handoff_shards(request).await
+        }
         async fn get_debug_state(
             &mut self,
             request: super::GetDebugStateRequest,
@@ -542,6 +610,22 @@ impl tower::Service<AdviseResetShardsRequest> for Box<dyn ControlPlaneService> {
         Box::pin(fut)
     }
 }
+impl tower::Service<HandoffShardsRequest> for Box<dyn ControlPlaneService> {
+    type Response = HandoffShardsResponse;
+    type Error = crate::control_plane::ControlPlaneError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: HandoffShardsRequest) -> Self::Future {
+        let mut svc = self.clone();
+        let fut = async move { svc.handoff_shards(request).await };
+        Box::pin(fut)
+    }
+}
 impl tower::Service<GetDebugStateRequest> for Box<dyn ControlPlaneService> {
     type Response = GetDebugStateResponse;
     type Error = crate::control_plane::ControlPlaneError;
@@ -597,6 +681,11 @@ struct ControlPlaneServiceTowerServiceStack {
         AdviseResetShardsResponse,
         crate::control_plane::ControlPlaneError,
     >,
+    handoff_shards_svc: quickwit_common::tower::BoxService<
+        HandoffShardsRequest,
+        HandoffShardsResponse,
+        crate::control_plane::ControlPlaneError,
+    >,
     get_debug_state_svc: quickwit_common::tower::BoxService<
         GetDebugStateRequest,
         GetDebugStateResponse,
@@ -614,6 +703,7 @@ impl Clone for ControlPlaneServiceTowerServiceStack {
             delete_source_svc: self.delete_source_svc.clone(),
             get_or_create_open_shards_svc: self.get_or_create_open_shards_svc.clone(),
             advise_reset_shards_svc: self.advise_reset_shards_svc.clone(),
+            handoff_shards_svc: self.handoff_shards_svc.clone(),
             get_debug_state_svc: self.get_debug_state_svc.clone(),
         }
     }
@@ -664,6 +754,12 @@ impl ControlPlaneService for ControlPlaneServiceTowerServiceStack {
     ) -> crate::control_plane::ControlPlaneResult<AdviseResetShardsResponse> {
         self.advise_reset_shards_svc.ready().await?.call(request).await
     }
+    async fn handoff_shards(
+        &mut self,
+        request: HandoffShardsRequest,
+    ) -> crate::control_plane::ControlPlaneResult<HandoffShardsResponse> {
+        self.handoff_shards_svc.ready().await?.call(request).await
+    }
     async fn get_debug_state(
         &mut self,
         request: GetDebugStateRequest,
@@ -741,6 +837,16 @@ type AdviseResetShardsLayer = quickwit_common::tower::BoxLayer<
     AdviseResetShardsResponse,
     crate::control_plane::ControlPlaneError,
 >;
+type HandoffShardsLayer = quickwit_common::tower::BoxLayer<
+    quickwit_common::tower::BoxService<
+        HandoffShardsRequest,
+        HandoffShardsResponse,
+        crate::control_plane::ControlPlaneError,
+    >,
+    HandoffShardsRequest,
+    HandoffShardsResponse,
+    crate::control_plane::ControlPlaneError,
+>;
 type GetDebugStateLayer = quickwit_common::tower::BoxLayer<
     quickwit_common::tower::BoxService<
         GetDebugStateRequest,
@@ -760,6 +866,7 @@ pub struct ControlPlaneServiceTowerLayerStack {
     delete_source_layers: Vec<DeleteSourceLayer>,
     get_or_create_open_shards_layers: Vec<GetOrCreateOpenShardsLayer>,
     advise_reset_shards_layers: Vec<AdviseResetShardsLayer>,
+    handoff_shards_layers: Vec<HandoffShardsLayer>,
     get_debug_state_layers: Vec<GetDebugStateLayer>,
 }
 impl ControlPlaneServiceTowerLayerStack {
@@ -952,6 +1059,31 @@ impl ControlPlaneServiceTowerLayerStack {
                 crate::control_plane::ControlPlaneError,
             >,
         >>::Service as tower::Service<AdviseResetShardsRequest>>::Future: Send + 'static,
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    HandoffShardsRequest,
+                    HandoffShardsResponse,
+                    crate::control_plane::ControlPlaneError,
+                >,
+            > + Clone + Send + Sync + 'static,
+        <L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                HandoffShardsRequest,
+                HandoffShardsResponse,
+                crate::control_plane::ControlPlaneError,
+            >,
+        >>::Service: tower::Service<
+                HandoffShardsRequest,
+                Response = HandoffShardsResponse,
+                Error = crate::control_plane::ControlPlaneError,
+            > + Clone + Send + Sync + 'static,
+        <<L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                HandoffShardsRequest,
+                HandoffShardsResponse,
+                crate::control_plane::ControlPlaneError,
+            >,
+        >>::Service as tower::Service<HandoffShardsRequest>>::Future: Send + 'static,
         L: tower::Layer<
                 quickwit_common::tower::BoxService<
                     GetDebugStateRequest,
@@ -992,6 +1124,8 @@ impl ControlPlaneServiceTowerLayerStack {
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self.advise_reset_shards_layers
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
+        self.handoff_shards_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self.get_debug_state_layers
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self
@@ -1143,6 +1277,26 @@ impl ControlPlaneServiceTowerLayerStack {
             .push(quickwit_common::tower::BoxLayer::new(layer));
         self
     }
+    pub fn stack_handoff_shards_layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    HandoffShardsRequest,
+                    HandoffShardsResponse,
+                    crate::control_plane::ControlPlaneError,
+                >,
+            > + Send + Sync + 'static,
+        L::Service: tower::Service<
+                HandoffShardsRequest,
+                Response = HandoffShardsResponse,
+                Error = crate::control_plane::ControlPlaneError,
+            > + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<HandoffShardsRequest>>::Future: Send + 'static,
+    {
+        self.handoff_shards_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer));
+        self
+    }
     pub fn stack_get_debug_state_layer<L>(mut self, layer: L) -> Self
     where
         L: tower::Layer<
@@ -1264,6 +1418,14 @@ impl ControlPlaneServiceTowerLayerStack {
                 quickwit_common::tower::BoxService::new(boxed_instance.clone()),
                 |svc, layer| layer.layer(svc),
             );
+        let handoff_shards_svc = self
+            .handoff_shards_layers
+            .into_iter()
+            .rev()
+            .fold(
+                quickwit_common::tower::BoxService::new(boxed_instance.clone()),
+                |svc, layer| layer.layer(svc),
+            );
         let get_debug_state_svc = self
             .get_debug_state_layers
             .into_iter()
@@ -1281,6 +1443,7 @@ impl ControlPlaneServiceTowerLayerStack {
             delete_source_svc,
             get_or_create_open_shards_svc,
             advise_reset_shards_svc,
+            handoff_shards_svc,
             get_debug_state_svc,
         };
         ControlPlaneServiceClient::new(tower_svc_stack)
@@ -1421,6 +1584,15 @@ where
                 crate::control_plane::ControlPlaneError,
             >,
         >
+        + tower::Service<
+            HandoffShardsRequest,
+            Response = HandoffShardsResponse,
+            Error = crate::control_plane::ControlPlaneError,
+            Future = BoxFuture<
+                HandoffShardsResponse,
+                crate::control_plane::ControlPlaneError,
+            >,
+        >
         + tower::Service<
             GetDebugStateRequest,
             Response = GetDebugStateResponse,
@@ -1475,6 +1647,12 @@ where
     ) -> crate::control_plane::ControlPlaneResult<AdviseResetShardsResponse> {
         self.call(request).await
     }
+    async fn handoff_shards(
+        &mut self,
+        request: HandoffShardsRequest,
+    ) -> crate::control_plane::ControlPlaneResult<HandoffShardsResponse> {
+        self.call(request).await
+    }
     async fn get_debug_state(
         &mut self,
         request: GetDebugStateRequest,
@@ -1609,6 +1787,19 @@ where
                 AdviseResetShardsRequest::rpc_name(),
             ))
     }
+    async fn handoff_shards(
+        &mut self,
+        request: HandoffShardsRequest,
+    ) -> crate::control_plane::ControlPlaneResult<HandoffShardsResponse> {
+        self.inner
+            .handoff_shards(request)
+            .await
+            .map(|response| response.into_inner())
+            .map_err(|status| crate::error::grpc_status_to_service_error(
+                status,
+                HandoffShardsRequest::rpc_name(),
+            ))
+    }
     async fn get_debug_state(
         &mut self,
         request: GetDebugStateRequest,
@@ -1715,6 +1906,17 @@ for ControlPlaneServiceGrpcServerAdapter {
             .map(tonic::Response::new)
             .map_err(crate::error::grpc_error_to_grpc_status)
     }
+    async fn handoff_shards(
+        &self,
+        request: tonic::Request<HandoffShardsRequest>,
+    ) -> Result<tonic::Response<HandoffShardsResponse>, tonic::Status> {
+        self.inner
+            .clone()
+            .handoff_shards(request.into_inner())
+            .await
+            .map(tonic::Response::new)
+            .map_err(crate::error::grpc_error_to_grpc_status)
+    }
     async fn get_debug_state(
         &self,
         request: tonic::Request<GetDebugStateRequest>,
@@ -2036,6 +2238,37 @@ pub mod control_plane_service_grpc_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Notifies the control plane that a decommissioning ingester handed off some of its shards to other ingesters.
+        pub async fn handoff_shards(
+            &mut self,
+            request: impl tonic::IntoRequest<super::HandoffShardsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::HandoffShardsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.control_plane.ControlPlaneService/HandoffShards",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "quickwit.control_plane.ControlPlaneService",
+                        "HandoffShards",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
         /// Return some innerstate of the control plane meant to assist debugging.
         pub async fn get_debug_state(
             &mut self,
@@ -2133,6 +2366,14 @@ pub mod control_plane_service_grpc_server {
             tonic::Response<super::AdviseResetShardsResponse>,
             tonic::Status,
         >;
+        /// Notifies the control plane that a decommissioning ingester handed off some of its shards to other ingesters.
+        async fn handoff_shards(
+            &self,
+            request: tonic::Request<super::HandoffShardsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::HandoffShardsResponse>,
+            tonic::Status,
+        >;
         /// Return some innerstate of the control plane meant to assist debugging.
         async fn get_debug_state(
             &self,
@@ -2559,6 +2800,52 @@ pub mod control_plane_service_grpc_server {
                     };
                     Box::pin(fut)
                 }
+                "/quickwit.control_plane.ControlPlaneService/HandoffShards" => {
+                    #[allow(non_camel_case_types)]
+                    struct HandoffShardsSvc<T: ControlPlaneServiceGrpc>(pub Arc<T>);
+                    impl<
+                        T: ControlPlaneServiceGrpc,
+                    > tonic::server::UnaryService<super::HandoffShardsRequest>
+                    for HandoffShardsSvc<T> {
+                        type Response = super::HandoffShardsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::HandoffShardsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).handoff_shards(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HandoffShardsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/quickwit.control_plane.ControlPlaneService/GetDebugState" => {
                     #[allow(non_camel_case_types)]
                     struct GetDebugStateSvc<T: ControlPlaneServiceGrpc>(pub Arc<T>);
@@ -45,6 +45,8 @@ pub struct PersistSubrequest {
     pub shard_id: ::core::option::Option<crate::types::ShardId>,
     #[prost(message, optional, tag = "5")]
     pub doc_batch: ::core::option::Option<super::DocBatchV2>,
+    #[prost(string, optional, tag = "6")]
+    pub dedup_id: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -159,6 +161,10 @@ pub struct InitReplicaRequest {
     pub replica_shard: ::core::option::Option<super::Shard>,
     #[prost(uint64, tag = "2")]
     pub replication_seqno: u64,
+    /// Position of the last record written in the primary shard. Followers that already host a replica of the shard
+    /// (i.e. when a follower is promoted) refuse to follow the new leader if their replica is not at this position.
+    #[prost(message, optional, tag = "3")]
+    pub replication_position_inclusive: ::core::option::Option<crate::types::Position>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -199,6 +205,15 @@ pub struct ReplicateSubrequest {
     pub from_position_exclusive: ::core::option::Option<crate::types::Position>,
     #[prost(message, optional, tag = "6")]
     pub doc_batch: ::core::option::Option<super::DocBatchV2>,
+    #[prost(string, optional, tag = "7")]
+    pub dedup_id: ::core::option::Option<::prost::alloc::string::String>,
+    /// Set instead of `doc_batch` when the leader compresses the records of its WAL.
+    #[prost(message, optional, tag = "8")]
+    pub compressed_doc_batch: ::core::option::Option<super::CompressedDocBatch>,
+    /// Set instead of `doc_batch` when a decommissioning leader hands off the tail of its WAL. The records are
+    /// appended as is, starting at position `from_position_exclusive + 1`.
+    #[prost(message, optional, tag = "9")]
+    pub mrecord_batch: ::core::option::Option<super::MRecordBatch>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -345,6 +360,16 @@ pub struct FetchEof {
 pub struct InitShardsRequest {
     #[prost(message, repeated, tag = "1")]
     pub shards: ::prost::alloc::vec::Vec<super::Shard>,
+    /// Shards whose local replica should be promoted to a primary shard because their leader left the cluster.
+    #[prost(message, repeated, tag = "2")]
+    pub promoted_shards: ::prost::alloc::vec::Vec<super::Shard>,
+    /// Maximum throughput in bytes per second of the new shards of the indexes whose throughput quota is
+    /// lower than the throughput of a single shard, keyed by index UID.
+    #[prost(map = "string, uint64", tag = "3")]
+    pub max_shard_throughputs: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        u64,
+    >,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -364,7 +389,10 @@ pub struct CloseShardsResponse {}
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct DecommissionRequest {}
+pub struct DecommissionRequest {
+    #[prost(enumeration = "DecommissionMode", tag = "1")]
+    pub mode: i32,
+}
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -372,6 +400,34 @@ pub struct DecommissionResponse {}
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetReplicaPositionsRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub shards: ::prost::alloc::vec::Vec<super::ShardIds>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetReplicaPositionsResponse {
+    /// Replication positions of the replicas hosted by the ingester. Shards without a local replica are omitted.
+    #[prost(message, repeated, tag = "1")]
+    pub replica_positions: ::prost::alloc::vec::Vec<ReplicaPosition>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReplicaPosition {
+    #[prost(message, optional, tag = "1")]
+    pub index_uid: ::core::option::Option<crate::types::IndexUid>,
+    #[prost(string, tag = "2")]
+    pub source_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub shard_id: ::core::option::Option<crate::types::ShardId>,
+    #[prost(message, optional, tag = "4")]
+    pub replication_position_inclusive: ::core::option::Option<crate::types::Position>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct OpenObservationStreamRequest {}
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -469,6 +525,37 @@ impl ReplicateFailureReason {
 #[serde(rename_all = "snake_case")]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
+pub enum DecommissionMode {
+    /// The ingester closes its shards and waits for them to be fully indexed.
+    Drain = 0,
+    /// The ingester closes its shards and hands off the records not indexed yet to other ingesters, which become
+    /// the new leaders of the shards.
+    Handoff = 1,
+}
+impl DecommissionMode {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            DecommissionMode::Drain => "DECOMMISSION_MODE_DRAIN",
+            DecommissionMode::Handoff => "DECOMMISSION_MODE_HANDOFF",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "DECOMMISSION_MODE_DRAIN" => Some(Self::Drain),
+            "DECOMMISSION_MODE_HANDOFF" => Some(Self::Handoff),
+            _ => None,
+        }
+    }
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
 pub enum IngesterStatus {
     Unspecified = 0,
     /// The ingester is live but not ready yet to accept requests.
@@ -477,7 +564,7 @@ pub enum IngesterStatus {
     Ready = 2,
     /// The ingester is being decommissioned. It accepts read requests but rejects write requests
     /// (open shards, persist, and replicate requests). It will transition to `Decommissioned` once
-    /// all shards are fully indexed.
+    /// all shards are fully indexed or handed off to other ingesters.
     Decommissioning = 3,
     /// The ingester no longer accepts read and write requests. It does not hold any data and can
     /// be safely removed from the cluster.
@@ -563,6 +650,11 @@ impl RpcName for DecommissionRequest {
         "decommission"
     }
 }
+impl RpcName for GetReplicaPositionsRequest {
+    fn rpc_name() -> &'static str {
+        "get_replica_positions"
+    }
+}
 pub type IngesterServiceStream<T> = quickwit_common::ServiceStream<
     crate::ingest::IngestV2Result<T>,
 >;
@@ -616,6 +708,11 @@ pub trait IngesterService: std::fmt::Debug + dyn_clone::DynClone + Send + Sync +
         &mut self,
         request: DecommissionRequest,
     ) -> crate::ingest::IngestV2Result<DecommissionResponse>;
+    /// Returns the replication positions of the replicas of a set of shards hosted by the ingester. This RPC is called by the control plane on followers to pick the most advanced follower to promote when the leader of a shard leaves the cluster.
+    async fn get_replica_positions(
+        &mut self,
+        request: GetReplicaPositionsRequest,
+    ) -> crate::ingest::IngestV2Result<GetReplicaPositionsResponse>;
 }
 dyn_clone::clone_trait_object!(IngesterService);
 #[cfg(any(test, feature = "testsuite"))]
@@ -758,6 +855,12 @@ impl IngesterService for IngesterServiceClient {
     ) -> crate::ingest::IngestV2Result<DecommissionResponse> {
         self.inner.decommission(request).await
     }
+    async fn get_replica_positions(
+        &mut self,
+        request: GetReplicaPositionsRequest,
+    ) -> crate::ingest::IngestV2Result<GetReplicaPositionsResponse> {
+        self.inner.get_replica_positions(request).await
+    }
 }
 #[cfg(any(test, feature = "testsuite"))]
 pub mod ingester_service_mock {
@@ -826,6 +929,12 @@ pub mod ingester_service_mock {
         ) -> crate::ingest::IngestV2Result<super::DecommissionResponse> {
             self.inner.lock().await.decommission(request).await
         }
+        async fn get_replica_positions(
+            &mut self,
+            request: super::GetReplicaPositionsRequest,
+        ) -> crate::ingest::IngestV2Result<super::GetReplicaPositionsResponse> {
+            self.inner.lock().await.get_replica_positions(request).await
+        }
     }
     impl From<MockIngesterService> for IngesterServiceClient {
         fn from(mock: MockIngesterService) -> Self {
@@ -987,6 +1096,22 @@ impl tower::Service<DecommissionRequest> for Box<dyn IngesterService> {
         Box::pin(fut)
     }
 }
+impl tower::Service<GetReplicaPositionsRequest> for Box<dyn IngesterService> {
+    type Response = GetReplicaPositionsResponse;
+    type Error = crate::ingest::IngestV2Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: GetReplicaPositionsRequest) -> Self::Future {
+        let mut svc = self.clone();
+        let fut = async move { svc.get_replica_positions(request).await };
+        Box::pin(fut)
+    }
+}
 /// A tower service stack is a set of tower services.
 #[derive(Debug)]
 struct IngesterServiceTowerServiceStack {
@@ -1036,6 +1161,11 @@ struct IngesterServiceTowerServiceStack {
         DecommissionResponse,
         crate::ingest::IngestV2Error,
     >,
+    get_replica_positions_svc: quickwit_common::tower::BoxService<
+        GetReplicaPositionsRequest,
+        GetReplicaPositionsResponse,
+        crate::ingest::IngestV2Error,
+    >,
 }
 impl Clone for IngesterServiceTowerServiceStack {
     fn clone(&self) -> Self {
@@ -1050,6 +1180,7 @@ impl Clone for IngesterServiceTowerServiceStack {
             truncate_shards_svc: self.truncate_shards_svc.clone(),
             close_shards_svc: self.close_shards_svc.clone(),
             decommission_svc: self.decommission_svc.clone(),
+            get_replica_positions_svc: self.get_replica_positions_svc.clone(),
         }
     }
 }
@@ -1109,6 +1240,12 @@ impl IngesterService for IngesterServiceTowerServiceStack {
     ) -> crate::ingest::IngestV2Result<DecommissionResponse> {
         self.decommission_svc.ready().await?.call(request).await
     }
+    async fn get_replica_positions(
+        &mut self,
+        request: GetReplicaPositionsRequest,
+    ) -> crate::ingest::IngestV2Result<GetReplicaPositionsResponse> {
+        self.get_replica_positions_svc.ready().await?.call(request).await
+    }
 }
 type PersistLayer = quickwit_common::tower::BoxLayer<
     quickwit_common::tower::BoxService<
@@ -1200,6 +1337,16 @@ type DecommissionLayer = quickwit_common::tower::BoxLayer<
     DecommissionResponse,
     crate::ingest::IngestV2Error,
 >;
+type GetReplicaPositionsLayer = quickwit_common::tower::BoxLayer<
+    quickwit_common::tower::BoxService<
+        GetReplicaPositionsRequest,
+        GetReplicaPositionsResponse,
+        crate::ingest::IngestV2Error,
+    >,
+    GetReplicaPositionsRequest,
+    GetReplicaPositionsResponse,
+    crate::ingest::IngestV2Error,
+>;
 #[derive(Debug, Default)]
 pub struct IngesterServiceTowerLayerStack {
     persist_layers: Vec<PersistLayer>,
@@ -1211,6 +1358,7 @@ pub struct IngesterServiceTowerLayerStack {
     truncate_shards_layers: Vec<TruncateShardsLayer>,
     close_shards_layers: Vec<CloseShardsLayer>,
     decommission_layers: Vec<DecommissionLayer>,
+    get_replica_positions_layers: Vec<GetReplicaPositionsLayer>,
 }
 impl IngesterServiceTowerLayerStack {
     pub fn stack_layer<L>(mut self, layer: L) -> Self
@@ -1444,6 +1592,31 @@ impl IngesterServiceTowerLayerStack {
                 crate::ingest::IngestV2Error,
             >,
         >>::Service as tower::Service<DecommissionRequest>>::Future: Send + 'static,
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    GetReplicaPositionsRequest,
+                    GetReplicaPositionsResponse,
+                    crate::ingest::IngestV2Error,
+                >,
+            > + Clone + Send + Sync + 'static,
+        <L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                GetReplicaPositionsRequest,
+                GetReplicaPositionsResponse,
+                crate::ingest::IngestV2Error,
+            >,
+        >>::Service: tower::Service<
+                GetReplicaPositionsRequest,
+                Response = GetReplicaPositionsResponse,
+                Error = crate::ingest::IngestV2Error,
+            > + Clone + Send + Sync + 'static,
+        <<L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                GetReplicaPositionsRequest,
+                GetReplicaPositionsResponse,
+                crate::ingest::IngestV2Error,
+            >,
+        >>::Service as tower::Service<GetReplicaPositionsRequest>>::Future: Send + 'static,
     {
         self.persist_layers.push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self.open_replication_stream_layers
@@ -1462,6 +1635,8 @@ impl IngesterServiceTowerLayerStack {
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self.decommission_layers
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
+        self.get_replica_positions_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self
     }
     pub fn stack_persist_layer<L>(mut self, layer: L) -> Self
@@ -1641,6 +1816,25 @@ impl IngesterServiceTowerLayerStack {
         self.decommission_layers.push(quickwit_common::tower::BoxLayer::new(layer));
         self
     }
+    pub fn stack_get_replica_positions_layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    GetReplicaPositionsRequest,
+                    GetReplicaPositionsResponse,
+                    crate::ingest::IngestV2Error,
+                >,
+            > + Send + Sync + 'static,
+        L::Service: tower::Service<
+                GetReplicaPositionsRequest,
+                Response = GetReplicaPositionsResponse,
+                Error = crate::ingest::IngestV2Error,
+            > + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<GetReplicaPositionsRequest>>::Future: Send + 'static,
+    {
+        self.get_replica_positions_layers.push(quickwit_common::tower::BoxLayer::new(layer));
+        self
+    }
     pub fn build<T>(self, instance: T) -> IngesterServiceClient
     where
         T: IngesterService,
@@ -1759,6 +1953,14 @@ impl IngesterServiceTowerLayerStack {
                 quickwit_common::tower::BoxService::new(boxed_instance.clone()),
                 |svc, layer| layer.layer(svc),
             );
+        let get_replica_positions_svc = self
+            .get_replica_positions_layers
+            .into_iter()
+            .rev()
+            .fold(
+                quickwit_common::tower::BoxService::new(boxed_instance.clone()),
+                |svc, layer| layer.layer(svc),
+            );
         let tower_svc_stack = IngesterServiceTowerServiceStack {
             inner: boxed_instance.clone(),
             persist_svc,
@@ -1770,6 +1972,7 @@ impl IngesterServiceTowerLayerStack {
             truncate_shards_svc,
             close_shards_svc,
             decommission_svc,
+            get_replica_positions_svc,
         };
         IngesterServiceClient::new(tower_svc_stack)
     }
@@ -1908,6 +2111,12 @@ where
             Response = DecommissionResponse,
             Error = crate::ingest::IngestV2Error,
             Future = BoxFuture<DecommissionResponse, crate::ingest::IngestV2Error>,
+        >
+        + tower::Service<
+            GetReplicaPositionsRequest,
+            Response = GetReplicaPositionsResponse,
+            Error = crate::ingest::IngestV2Error,
+            Future = BoxFuture<GetReplicaPositionsResponse, crate::ingest::IngestV2Error>,
         >,
 {
     async fn persist(
@@ -1964,6 +2173,12 @@ where
     ) -> crate::ingest::IngestV2Result<DecommissionResponse> {
         self.call(request).await
     }
+    async fn get_replica_positions(
+        &mut self,
+        request: GetReplicaPositionsRequest,
+    ) -> crate::ingest::IngestV2Result<GetReplicaPositionsResponse> {
+        self.call(request).await
+    }
 }
 #[derive(Debug, Clone)]
 pub struct IngesterServiceGrpcClientAdapter<T> {
@@ -2140,6 +2355,19 @@ where
                 DecommissionRequest::rpc_name(),
             ))
     }
+    async fn get_replica_positions(
+        &mut self,
+        request: GetReplicaPositionsRequest,
+    ) -> crate::ingest::IngestV2Result<GetReplicaPositionsResponse> {
+        self.inner
+            .get_replica_positions(request)
+            .await
+            .map(|response| response.into_inner())
+            .map_err(|status| crate::error::grpc_status_to_service_error(
+                status,
+                GetReplicaPositionsRequest::rpc_name(),
+            ))
+    }
 }
 #[derive(Debug)]
 pub struct IngesterServiceGrpcServerAdapter {
@@ -2273,6 +2501,17 @@ for IngesterServiceGrpcServerAdapter {
             .map(tonic::Response::new)
             .map_err(crate::error::grpc_error_to_grpc_status)
     }
+    async fn get_replica_positions(
+        &self,
+        request: tonic::Request<GetReplicaPositionsRequest>,
+    ) -> Result<tonic::Response<GetReplicaPositionsResponse>, tonic::Status> {
+        self.inner
+            .clone()
+            .get_replica_positions(request.into_inner())
+            .await
+            .map(tonic::Response::new)
+            .map_err(crate::error::grpc_error_to_grpc_status)
+    }
 }
 /// Generated client implementations.
 pub mod ingester_service_grpc_client {
@@ -2642,6 +2881,37 @@ pub mod ingester_service_grpc_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Returns the replication positions of the replicas of a set of shards hosted by the ingester. This RPC is called by the control plane on followers to pick the most advanced follower to promote when the leader of a shard leaves the cluster.
+        pub async fn get_replica_positions(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetReplicaPositionsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetReplicaPositionsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.ingest.ingester.IngesterService/GetReplicaPositions",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "quickwit.ingest.ingester.IngesterService",
+                        "GetReplicaPositions",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -2740,6 +3010,14 @@ pub mod ingester_service_grpc_server {
             tonic::Response<super::DecommissionResponse>,
             tonic::Status,
         >;
+        /// Returns the replication positions of the replicas of a set of shards hosted by the ingester. This RPC is called by the control plane on followers to pick the most advanced follower to promote when the leader of a shard leaves the cluster.
+        async fn get_replica_positions(
+            &self,
+            request: tonic::Request<super::GetReplicaPositionsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetReplicaPositionsResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct IngesterServiceGrpcServer<T: IngesterServiceGrpc> {
@@ -3237,6 +3515,52 @@ pub mod ingester_service_grpc_server {
                     };
                     Box::pin(fut)
                 }
+                "/quickwit.ingest.ingester.IngesterService/GetReplicaPositions" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetReplicaPositionsSvc<T: IngesterServiceGrpc>(pub Arc<T>);
+                    impl<
+                        T: IngesterServiceGrpc,
+                    > tonic::server::UnaryService<super::GetReplicaPositionsRequest>
+                    for GetReplicaPositionsSvc<T> {
+                        type Response = super::GetReplicaPositionsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetReplicaPositionsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).get_replica_positions(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetReplicaPositionsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
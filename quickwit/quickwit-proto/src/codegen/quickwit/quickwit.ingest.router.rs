@@ -22,6 +22,11 @@ pub struct IngestSubrequest {
     pub source_id: ::prost::alloc::string::String,
     #[prost(message, optional, tag = "4")]
     pub doc_batch: ::core::option::Option<super::DocBatchV2>,
+    /// Optional client-supplied idempotency key. Subrequests retried with the same dedup ID are
+    /// acknowledged without being persisted twice, as long as the retry lands within the
+    /// deduplication window configured on the ingesters.
+    #[prost(string, optional, tag = "5")]
+    pub dedup_id: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -47,6 +52,32 @@ pub struct IngestSuccess {
     /// Replication position inclusive.
     #[prost(message, optional, tag = "5")]
     pub replication_position_inclusive: ::core::option::Option<crate::types::Position>,
+    /// Documents of the subrequest that were rejected by the router because they could not be parsed
+    /// against the doc mapping of the index. These documents were not persisted.
+    #[prost(message, repeated, tag = "6")]
+    pub parse_failures: ::prost::alloc::vec::Vec<ParseFailure>,
+    /// For `wait_for` and `force` commits, IDs of the splits published by the indexers since the
+    /// subrequest was persisted, up to the split whose publication made its documents searchable. If
+    /// the documents were already published when the router started waiting, IDs of the splits of
+    /// that publication only. Empty if the router timed out waiting for the publication.
+    #[prost(string, repeated, tag = "7")]
+    pub published_split_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// For `wait_for` and `force` commits, publish position of the shard once the documents of the
+    /// subrequest were published.
+    #[prost(message, optional, tag = "8")]
+    pub published_position_inclusive: ::core::option::Option<crate::types::Position>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ParseFailure {
+    /// Position of the document in the doc batch of the subrequest.
+    #[prost(uint32, tag = "1")]
+    pub doc_ordinal: u32,
+    #[prost(enumeration = "ParseFailureReason", tag = "2")]
+    pub reason: i32,
+    #[prost(string, tag = "3")]
+    pub message: ::prost::alloc::string::String,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -65,6 +96,37 @@ pub struct IngestFailure {
 #[serde(rename_all = "snake_case")]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
+pub enum ParseFailureReason {
+    Unspecified = 0,
+    InvalidJson = 1,
+    InvalidSchema = 2,
+}
+impl ParseFailureReason {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ParseFailureReason::Unspecified => "PARSE_FAILURE_REASON_UNSPECIFIED",
+            ParseFailureReason::InvalidJson => "PARSE_FAILURE_REASON_INVALID_JSON",
+            ParseFailureReason::InvalidSchema => "PARSE_FAILURE_REASON_INVALID_SCHEMA",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "PARSE_FAILURE_REASON_UNSPECIFIED" => Some(Self::Unspecified),
+            "PARSE_FAILURE_REASON_INVALID_JSON" => Some(Self::InvalidJson),
+            "PARSE_FAILURE_REASON_INVALID_SCHEMA" => Some(Self::InvalidSchema),
+            _ => None,
+        }
+    }
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
 pub enum IngestFailureReason {
     Unspecified = 0,
     IndexNotFound = 1,
@@ -73,6 +135,7 @@ pub enum IngestFailureReason {
     NoShardsAvailable = 4,
     RateLimited = 5,
     ResourceExhausted = 6,
+    QuotaExceeded = 7,
 }
 impl IngestFailureReason {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -94,6 +157,7 @@ impl IngestFailureReason {
             IngestFailureReason::ResourceExhausted => {
                 "INGEST_FAILURE_REASON_RESOURCE_EXHAUSTED"
             }
+            IngestFailureReason::QuotaExceeded => "INGEST_FAILURE_REASON_QUOTA_EXCEEDED",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -106,6 +170,7 @@ impl IngestFailureReason {
             "INGEST_FAILURE_REASON_NO_SHARDS_AVAILABLE" => Some(Self::NoShardsAvailable),
             "INGEST_FAILURE_REASON_RATE_LIMITED" => Some(Self::RateLimited),
             "INGEST_FAILURE_REASON_RESOURCE_EXHAUSTED" => Some(Self::ResourceExhausted),
+            "INGEST_FAILURE_REASON_QUOTA_EXCEEDED" => Some(Self::QuotaExceeded),
             _ => None,
         }
     }
@@ -7,6 +7,20 @@ pub struct DocBatchV2 {
     #[prost(uint32, repeated, tag = "2")]
     pub doc_lengths: ::prost::alloc::vec::Vec<u32>,
 }
+/// A `DocBatchV2` encoded with protobuf and compressed with zstd.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompressedDocBatch {
+    #[prost(bytes = "bytes", tag = "1")]
+    pub payload: ::prost::bytes::Bytes,
+    /// Number of documents in the batch.
+    #[prost(uint32, tag = "2")]
+    pub num_docs: u32,
+    /// Uncompressed size of the documents in bytes.
+    #[prost(uint64, tag = "3")]
+    pub num_bytes: u64,
+}
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -37,6 +51,10 @@ pub struct Shard {
     #[prost(string, optional, tag = "5")]
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub follower_id: ::core::option::Option<::prost::alloc::string::String>,
+    /// The node IDs of the ingesters holding an additional copy of the data when the replication factor is greater than 2.
+    #[prost(string, repeated, tag = "6")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_follower_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
     /// Mutable fields
     #[prost(enumeration = "ShardState", tag = "8")]
     pub shard_state: i32,
@@ -283,6 +283,12 @@ pub struct OpenShardsSubrequest {
     pub leader_id: ::prost::alloc::string::String,
     #[prost(string, optional, tag = "6")]
     pub follower_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "7")]
+    pub additional_follower_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// When set and the shard already exists, replaces its leader and followers instead of returning it unchanged.
+    /// This happens when a decommissioning ingester hands off its shards to other ingesters.
+    #[prost(bool, tag = "8")]
+    pub reassign: bool,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
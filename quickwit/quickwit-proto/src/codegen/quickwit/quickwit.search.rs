@@ -191,6 +191,35 @@ pub struct SearchRequest {
     pub search_after: ::core::option::Option<PartialHit>,
     #[prost(enumeration = "CountHits", tag = "17")]
     pub count_hits: i32,
+    /// Priority class of the search, used by the searcher admission control.
+    #[prost(enumeration = "SearchPriority", tag = "18")]
+    pub priority: i32,
+    /// If set, the search response will include a profile of the search
+    /// with per-split timings and resource usage.
+    #[prost(bool, tag = "19")]
+    pub profile: bool,
+    /// If set, the search is not executed. The search response only
+    /// contains the list of splits that would have been searched.
+    #[prost(bool, tag = "20")]
+    pub dry_run: bool,
+    /// Fields computed at query time from the fields of the documents.
+    #[prost(message, repeated, tag = "21")]
+    pub runtime_mappings: ::prost::alloc::vec::Vec<RuntimeMapping>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[derive(Eq, Hash)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RuntimeMapping {
+    /// Name of the runtime field.
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// Type of the runtime field: `long`, `double`, `keyword` or `boolean`.
+    #[prost(string, tag = "2")]
+    pub field_type: ::prost::alloc::string::String,
+    /// Expression computing the value of the runtime field.
+    #[prost(string, tag = "3")]
+    pub script: ::prost::alloc::string::String,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[derive(Eq, Hash)]
@@ -230,6 +259,115 @@ pub struct SearchResponse {
     /// Scroll Id (only set if scroll_secs was set in the request)
     #[prost(string, optional, tag = "6")]
     pub scroll_id: ::core::option::Option<::prost::alloc::string::String>,
+    /// Search profile (only set if profile or dry_run was set in the request)
+    #[prost(message, optional, tag = "7")]
+    pub profile: ::core::option::Option<SearchProfile>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchProfile {
+    /// Number of published splits of the targeted indexes.
+    #[prost(uint64, tag = "1")]
+    pub num_splits: u64,
+    /// Number of splits pruned because their time range does not
+    /// intersect the time range of the query.
+    #[prost(uint64, tag = "2")]
+    pub num_splits_pruned_by_time_range: u64,
+    /// Number of splits pruned because their tags do not match the query.
+    #[prost(uint64, tag = "3")]
+    pub num_splits_pruned_by_tags: u64,
+    /// Number of splits pruned because the min and max values of their
+    /// `min_max_fields` do not match the query.
+    #[prost(uint64, tag = "8")]
+    pub num_splits_pruned_by_field_stats: u64,
+    /// Splits targeted by the search after pruning.
+    #[prost(message, repeated, tag = "4")]
+    pub split_plans: ::prost::alloc::vec::Vec<SplitPlan>,
+    /// Estimated lower bound of the number of bytes downloaded by the search,
+    /// i.e. the size of the footers (including the hotcache) of the splits.
+    #[prost(uint64, tag = "5")]
+    pub estimated_min_num_bytes: u64,
+    /// Estimated upper bound of the number of bytes downloaded by the search,
+    /// i.e. the size of the splits.
+    #[prost(uint64, tag = "6")]
+    pub estimated_max_num_bytes: u64,
+    /// Timings and resource usage of the search of each split (only set if
+    /// profile was set in the request).
+    #[prost(message, repeated, tag = "7")]
+    pub split_profiles: ::prost::alloc::vec::Vec<SplitSearchProfile>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SplitPlan {
+    #[prost(string, tag = "1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub split_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub num_docs: u64,
+    /// Size of the split footer, including the hotcache, in bytes.
+    #[prost(uint64, tag = "4")]
+    pub footer_num_bytes: u64,
+    /// Size of the split, in bytes.
+    #[prost(uint64, tag = "5")]
+    pub split_num_bytes: u64,
+    /// The lowest timestamp appearing in the split
+    #[prost(int64, optional, tag = "6")]
+    pub timestamp_start: ::core::option::Option<i64>,
+    /// The highest timestamp appearing in the split
+    #[prost(int64, optional, tag = "7")]
+    pub timestamp_end: ::core::option::Option<i64>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SplitSearchProfile {
+    #[prost(string, tag = "1")]
+    pub split_id: ::prost::alloc::string::String,
+    /// Whether the leaf search response was served from the leaf search cache.
+    /// If so, all the other fields but `fetch_docs_micros` are left to 0.
+    #[prost(bool, tag = "2")]
+    pub leaf_search_cache_hit: bool,
+    /// Time spent fetching the split footer and the hotcache.
+    #[prost(uint64, tag = "3")]
+    pub footer_fetch_micros: u64,
+    /// Time spent warming up each kind of data. These warmups run concurrently.
+    #[prost(uint64, tag = "4")]
+    pub warmup_terms_micros: u64,
+    #[prost(uint64, tag = "5")]
+    pub warmup_term_ranges_micros: u64,
+    #[prost(uint64, tag = "6")]
+    pub warmup_term_dicts_micros: u64,
+    #[prost(uint64, tag = "7")]
+    pub warmup_fast_fields_micros: u64,
+    #[prost(uint64, tag = "8")]
+    pub warmup_fieldnorms_micros: u64,
+    #[prost(uint64, tag = "9")]
+    pub warmup_postings_micros: u64,
+    /// Total time spent warming up.
+    #[prost(uint64, tag = "10")]
+    pub warmup_micros: u64,
+    /// Time spent executing the query on the warmed up split.
+    #[prost(uint64, tag = "11")]
+    pub query_execution_micros: u64,
+    /// Time spent fetching the documents of the split. This time is measured by the root
+    /// for each fetch docs request, which can cover several splits.
+    #[prost(uint64, tag = "12")]
+    pub fetch_docs_micros: u64,
+    /// Number of bytes downloaded from the storage.
+    #[prost(uint64, tag = "13")]
+    pub num_bytes_downloaded: u64,
+    /// Number of hits and misses of the split footer cache and of the fast fields cache.
+    #[prost(uint64, tag = "14")]
+    pub num_cache_hits: u64,
+    #[prost(uint64, tag = "15")]
+    pub num_cache_misses: u64,
+    /// Whether the split was skipped because its bloom filters do not contain the
+    /// terms of the query.
+    #[prost(bool, tag = "16")]
+    pub pruned_by_bloom_filters: bool,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -285,6 +423,10 @@ pub struct SplitIdAndFooterOffsets {
     /// The highest timestamp appearing in the split
     #[prost(int64, optional, tag = "5")]
     pub timestamp_end: ::core::option::Option<i64>,
+    /// URI of the storage holding the split file, if the split has been moved out of the index
+    /// storage, for instance to a colder storage tier.
+    #[prost(string, optional, tag = "6")]
+    pub storage_uri: ::core::option::Option<::prost::alloc::string::String>,
 }
 /// Hits returned by a FetchDocRequest.
 ///
@@ -411,6 +553,10 @@ pub struct LeafSearchResponse {
     pub intermediate_aggregation_result: ::core::option::Option<
         ::prost::alloc::vec::Vec<u8>,
     >,
+    /// Timings and resource usage of the search of each split (only set if
+    /// profile was set in the request).
+    #[prost(message, repeated, tag = "7")]
+    pub split_profiles: ::prost::alloc::vec::Vec<SplitSearchProfile>,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -442,6 +588,9 @@ pub struct FetchDocsRequest {
     /// `DocMapper` as json serialized trait.
     #[prost(string, tag = "6")]
     pub doc_mapper: ::prost::alloc::string::String,
+    /// Runtime fields to compute and add to the fetched documents.
+    #[prost(message, repeated, tag = "8")]
+    pub runtime_mappings: ::prost::alloc::vec::Vec<RuntimeMapping>,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -673,6 +822,38 @@ impl CountHits {
 #[serde(rename_all = "snake_case")]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
+pub enum SearchPriority {
+    /// Latency sensitive search, typically issued by a user or a dashboard.
+    Interactive = 0,
+    /// Throughput oriented search, such as a report or an export. The number of
+    /// concurrent batch searches can be capped so that they do not starve
+    /// interactive searches.
+    Batch = 1,
+}
+impl SearchPriority {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            SearchPriority::Interactive => "INTERACTIVE",
+            SearchPriority::Batch => "BATCH",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "INTERACTIVE" => Some(Self::Interactive),
+            "BATCH" => Some(Self::Batch),
+            _ => None,
+        }
+    }
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
 pub enum SortOrder {
     /// Ascending order.
     Asc = 0,
@@ -106,6 +106,12 @@ impl RpcName for AdviseResetShardsRequest {
     }
 }
 
+impl RpcName for HandoffShardsRequest {
+    fn rpc_name() -> &'static str {
+        "handoff_shards"
+    }
+}
+
 impl RpcName for GetDebugStateRequest {
     fn rpc_name() -> &'static str {
         "get_debug_state"
@@ -39,6 +39,7 @@ generate_getters! {
     PersistFailure,
     PersistSubrequest,
     PersistSuccess,
+    ReplicaPosition,
     ReplicateFailure,
     ReplicateSubrequest,
     ReplicateSuccess,
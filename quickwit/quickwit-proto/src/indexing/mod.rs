@@ -29,7 +29,7 @@ use serde::{Deserialize, Serialize};
 use thiserror;
 
 use crate::metastore::MetastoreError;
-use crate::types::{IndexUid, PipelineUid, Position, ShardId, SourceId, SourceUid};
+use crate::types::{IndexUid, PipelineUid, Position, ShardId, SourceId, SourceUid, SplitId};
 use crate::{GrpcServiceError, ServiceError, ServiceErrorCode};
 
 include!("../codegen/quickwit/quickwit.indexing.rs");
@@ -272,6 +272,9 @@ pub struct ShardPositionsUpdate {
     pub source_uid: SourceUid,
     // Only shards that received an update are listed here.
     pub updated_shard_positions: Vec<(ShardId, Position)>,
+    // IDs of the splits whose publication advanced the positions of the updated shards, when they
+    // are known.
+    pub published_split_ids: Vec<SplitId>,
 }
 
 impl Event for ShardPositionsUpdate {}
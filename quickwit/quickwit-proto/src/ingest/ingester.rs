@@ -261,6 +261,24 @@ impl ReplicateFailure {
     }
 }
 
+impl ReplicaPosition {
+    pub fn shard_id(&self) -> &ShardId {
+        self.shard_id
+            .as_ref()
+            .expect("`shard_id` should be a required field")
+    }
+
+    pub fn queue_id(&self) -> QueueId {
+        queue_id(self.index_uid(), &self.source_id, self.shard_id())
+    }
+
+    pub fn replication_position_inclusive(&self) -> &Position {
+        self.replication_position_inclusive
+            .as_ref()
+            .expect("`replication_position_inclusive` should be a required field")
+    }
+}
+
 impl TruncateShardsSubrequest {
     pub fn shard_id(&self) -> &ShardId {
         self.shard_id
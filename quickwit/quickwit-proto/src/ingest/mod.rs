@@ -17,8 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::io;
+use std::io::Read;
+
 use bytes::Bytes;
 use bytesize::ByteSize;
+use prost::Message;
 
 use self::ingester::{PersistFailureReason, ReplicateFailureReason};
 use self::router::IngestFailureReason;
@@ -76,11 +80,17 @@ impl GrpcServiceError for IngestV2Error {
 }
 
 impl Shard {
-    /// List of nodes that are storing the shard (the leader, and optionally the follower).
+    /// List of nodes that are storing the shard (the leader, and optionally the followers).
     pub fn ingesters(&self) -> impl Iterator<Item = NodeId> + '_ {
-        [Some(&self.leader_id), self.follower_id.as_ref()]
-            .into_iter()
-            .flatten()
+        std::iter::once(NodeId::new(self.leader_id.clone())).chain(self.follower_ids())
+    }
+
+    /// List of nodes that are storing a copy of the shard (the follower and the additional
+    /// followers).
+    pub fn follower_ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.follower_id
+            .iter()
+            .chain(&self.additional_follower_ids)
             .map(|node_id| NodeId::new(node_id.clone()))
     }
 }
@@ -101,6 +111,18 @@ impl DocBatchV2 {
             })
     }
 
+    /// Same as [`DocBatchV2::docs`] without consuming the batch.
+    pub fn iter_docs(&self) -> impl Iterator<Item = Bytes> + '_ {
+        self.doc_lengths
+            .iter()
+            .scan(0, move |start_offset, doc_length| {
+                let start = *start_offset;
+                let end = start + *doc_length as usize;
+                *start_offset = end;
+                Some(self.doc_buffer.slice(start..end))
+            })
+    }
+
     pub fn is_empty(&self) -> bool {
         self.doc_lengths.is_empty()
     }
@@ -113,6 +135,17 @@ impl DocBatchV2 {
         self.doc_lengths.len()
     }
 
+    /// Encodes the batch with protobuf and compresses it with zstd.
+    pub fn compress(&self, compression_level: i32) -> io::Result<CompressedDocBatch> {
+        let payload = zstd::encode_all(self.encode_to_vec().as_slice(), compression_level)?;
+        let compressed_doc_batch = CompressedDocBatch {
+            payload: Bytes::from(payload),
+            num_docs: self.num_docs() as u32,
+            num_bytes: self.num_bytes() as u64,
+        };
+        Ok(compressed_doc_batch)
+    }
+
     #[cfg(any(test, feature = "testsuite"))]
     pub fn for_test(docs: impl IntoIterator<Item = &'static str>) -> Self {
         let mut doc_buffer = Vec::new();
@@ -129,6 +162,32 @@ impl DocBatchV2 {
     }
 }
 
+impl CompressedDocBatch {
+    pub fn decompress(&self, max_num_bytes: usize) -> io::Result<DocBatchV2> {
+        Self::decompress_payload(&self.payload, max_num_bytes)
+    }
+
+    /// Decompresses the payload of a [`CompressedDocBatch`]. Fails if the payload expands beyond
+    /// `max_num_bytes`, so that a corrupted payload cannot exhaust the memory.
+    pub fn decompress_payload(payload: &[u8], max_num_bytes: usize) -> io::Result<DocBatchV2> {
+        let mut encoded_doc_batch = Vec::new();
+        zstd::stream::read::Decoder::new(payload)?
+            .take(max_num_bytes as u64 + 1)
+            .read_to_end(&mut encoded_doc_batch)?;
+
+        if encoded_doc_batch.len() > max_num_bytes {
+            let message = format!("decompressed doc batch exceeds {max_num_bytes} bytes");
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        DocBatchV2::decode(encoded_doc_batch.as_slice())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_docs == 0
+    }
+}
+
 impl MRecordBatch {
     pub fn encoded_mrecords(&self) -> impl Iterator<Item = Bytes> + '_ {
         self.mrecord_lengths
@@ -316,4 +375,50 @@ mod tests {
 
         assert!(ShardState::from_json_str_name("unknown").is_none());
     }
+
+    #[test]
+    fn test_shard_ingesters() {
+        let mut shard = Shard {
+            leader_id: "test-leader".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(shard.ingesters().collect::<Vec<_>>(), ["test-leader"]);
+        assert_eq!(shard.follower_ids().count(), 0);
+
+        shard.follower_id = Some("test-follower-1".to_string());
+        shard.additional_follower_ids = vec!["test-follower-2".to_string()];
+        assert_eq!(
+            shard.ingesters().collect::<Vec<_>>(),
+            ["test-leader", "test-follower-1", "test-follower-2"]
+        );
+        assert_eq!(
+            shard.follower_ids().collect::<Vec<_>>(),
+            ["test-follower-1", "test-follower-2"]
+        );
+    }
+
+    #[test]
+    fn test_doc_batch_iter_docs() {
+        let doc_batch = DocBatchV2::for_test(["test-doc-foo", "", "test-doc-barbaz"]);
+        let docs: Vec<Bytes> = doc_batch.iter_docs().collect();
+        assert_eq!(docs, ["test-doc-foo", "", "test-doc-barbaz"]);
+        assert_eq!(docs, doc_batch.docs().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_doc_batch_compress_decompress() {
+        let doc_batch = DocBatchV2::for_test(["test-doc-foo", "test-doc-bar"]);
+        let compressed_doc_batch = doc_batch.compress(3).unwrap();
+        assert_eq!(compressed_doc_batch.num_docs, 2);
+        assert_eq!(compressed_doc_batch.num_bytes, 24);
+        assert!(!compressed_doc_batch.is_empty());
+
+        let decompressed_doc_batch = compressed_doc_batch.decompress(1024).unwrap();
+        assert_eq!(decompressed_doc_batch, doc_batch);
+
+        let error = compressed_doc_batch.decompress(16).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        CompressedDocBatch::decompress_payload(b"not-zstd", 1024).unwrap_err();
+    }
 }
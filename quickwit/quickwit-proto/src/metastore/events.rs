@@ -22,8 +22,8 @@
 use quickwit_common::pubsub::Event;
 
 use super::{
-    AddSourceRequest, CreateIndexRequest, DeleteIndexRequest, DeleteSourceRequest, SourceType,
-    ToggleSourceRequest,
+    AddSourceRequest, CreateIndexRequest, DeleteIndexRequest, DeleteShardsRequest,
+    DeleteSourceRequest, SourceType, ToggleSourceRequest,
 };
 use crate::types::{IndexUid, SourceId};
 
@@ -68,5 +68,6 @@ pub struct DeleteSourceEvent {
 impl Event for AddSourceRequest {}
 impl Event for CreateIndexRequest {}
 impl Event for DeleteIndexRequest {}
+impl Event for DeleteShardsRequest {}
 impl Event for DeleteSourceRequest {}
 impl Event for ToggleSourceRequest {}
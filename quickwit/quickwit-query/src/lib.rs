@@ -32,6 +32,7 @@ mod elastic_query_dsl;
 mod error;
 mod json_literal;
 mod not_nan_f32;
+mod pipe_query;
 pub mod query_ast;
 pub mod tokenizers;
 
@@ -39,6 +40,7 @@ pub use elastic_query_dsl::{ElasticQueryDsl, OneFieldMap};
 pub use error::InvalidQuery;
 pub use json_literal::{InterpretUserInput, JsonLiteral};
 pub(crate) use not_nan_f32::NotNaNf32;
+pub use pipe_query::{parse_pipe_query, PipeQuery, PipeQueryRows, PipeSortField, PipeSortOrder};
 pub use query_ast::utils::find_field_or_hit_dynamic;
 use serde::{Deserialize, Serialize};
 pub use tantivy::query::Query as TantivyQuery;
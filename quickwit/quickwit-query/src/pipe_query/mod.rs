@@ -0,0 +1,579 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Pipe-based query language, in the spirit of PPL and LogQL:
+//!
+//! ```text
+//! index=logs service:api | where status >= 500 | stats count() by host | sort -count | head 10
+//! ```
+//!
+//! The first segment selects the indexes with `index=` (or `source=`) clauses, the rest of it
+//! being a regular user query. It is followed by commands:
+//! - `where <field> <op> <value> [and ...]` with `=`, `!=`, `<`, `<=`, `>` and `>=`;
+//! - `stats <function>(<field>) [as <name>], ... [by <field>, ...]` with `count`, `avg`, `sum`,
+//!   `min` and `max`;
+//! - `sort [-|+]<field>, ...` where `-` sorts in descending order;
+//! - `head [<count>]` (or `limit`);
+//! - `fields <field>, ...`.
+//!
+//! The commands are pushed down to the search request as long as possible: filters become part
+//! of the query AST, `sort` and `head` become the sort fields and the number of hits, and `stats`
+//! becomes an aggregation request. The remaining commands are applied to the returned rows.
+
+mod parser;
+mod stats;
+
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+use anyhow::bail;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use self::parser::{parse_command, parse_search_segment, split_pipes, PipeCommand};
+use self::stats::{StatsCommand, MAX_GROUPS_PER_FIELD};
+use crate::query_ast::{
+    query_ast_from_user_text, BoolQuery, FullTextMode, FullTextParams, FullTextQuery, QueryAst,
+    RangeQuery,
+};
+use crate::{JsonLiteral, MatchAllOrNone};
+
+/// Number of hits returned when the query does not end with `head`.
+const DEFAULT_MAX_HITS: u64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+impl CompareOp {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Lte => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Gte => ">=",
+        }
+    }
+
+    fn matches(&self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering.is_eq(),
+            CompareOp::Ne => ordering.is_ne(),
+            CompareOp::Lt => ordering.is_lt(),
+            CompareOp::Lte => ordering.is_le(),
+            CompareOp::Gt => ordering.is_gt(),
+            CompareOp::Gte => ordering.is_ge(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Condition {
+    field: String,
+    compare_op: CompareOp,
+    value: JsonValue,
+}
+
+impl Condition {
+    fn json_literal(&self) -> JsonLiteral {
+        match &self.value {
+            JsonValue::Number(number) => JsonLiteral::Number(number.clone()),
+            JsonValue::Bool(value) => JsonLiteral::Bool(*value),
+            value => JsonLiteral::String(value_to_string(value)),
+        }
+    }
+
+    /// Returns the query matching the documents that satisfy the condition, and whether that
+    /// query should be negated.
+    fn to_query_ast(&self) -> (QueryAst, bool) {
+        let range_query = |lower_bound, upper_bound| {
+            RangeQuery {
+                field: self.field.clone(),
+                lower_bound,
+                upper_bound,
+            }
+            .into()
+        };
+        match self.compare_op {
+            CompareOp::Eq | CompareOp::Ne => {
+                let full_text_query = FullTextQuery {
+                    field: self.field.clone(),
+                    text: value_to_string(&self.value),
+                    params: FullTextParams {
+                        tokenizer: None,
+                        mode: FullTextMode::Phrase { slop: 0 },
+                        zero_terms_query: MatchAllOrNone::MatchNone,
+                    },
+                };
+                (full_text_query.into(), self.compare_op == CompareOp::Ne)
+            }
+            CompareOp::Lt => (
+                range_query(Bound::Unbounded, Bound::Excluded(self.json_literal())),
+                false,
+            ),
+            CompareOp::Lte => (
+                range_query(Bound::Unbounded, Bound::Included(self.json_literal())),
+                false,
+            ),
+            CompareOp::Gt => (
+                range_query(Bound::Excluded(self.json_literal()), Bound::Unbounded),
+                false,
+            ),
+            CompareOp::Gte => (
+                range_query(Bound::Included(self.json_literal()), Bound::Unbounded),
+                false,
+            ),
+        }
+    }
+
+    fn matches_row(&self, row: &JsonValue) -> bool {
+        let Some(row_value) = resolve_row_field(row, &self.field) else {
+            return false;
+        };
+        compare_values(row_value, &self.value)
+            .map(|ordering| self.compare_op.matches(ordering))
+            .unwrap_or(false)
+    }
+}
+
+/// Sort order of a `sort` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeSortOrder {
+    Asc,
+    Desc,
+}
+
+/// Field of a `sort` command pushed down to the search request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipeSortField {
+    pub field_name: String,
+    pub sort_order: PipeSortOrder,
+}
+
+/// Command applied to the rows returned by the search, because it could not be pushed down.
+#[derive(Debug, Clone, PartialEq)]
+enum RowCommand {
+    Where(Vec<Condition>),
+    Sort(Vec<PipeSortField>),
+    Head(u64),
+    Fields(Vec<String>),
+}
+
+/// Result rows of a pipe query.
+#[derive(Debug, Default, PartialEq)]
+pub struct PipeQueryRows {
+    pub rows: Vec<JsonValue>,
+    /// Warnings about incomplete results, for instance when `stats` dropped some groups.
+    pub warnings: Vec<String>,
+}
+
+/// A pipe query compiled into a search request and the post-processing of its results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipeQuery {
+    /// Index ID patterns selected with `index=` clauses. Empty if the query does not select any
+    /// index.
+    pub index_id_patterns: Vec<String>,
+    pub query_ast: QueryAst,
+    pub sort_fields: Vec<PipeSortField>,
+    max_hits_opt: Option<u64>,
+    stats_opt: Option<StatsCommand>,
+    row_commands: Vec<RowCommand>,
+}
+
+impl PipeQuery {
+    /// Number of hits to fetch. No hit is fetched if the query computes stats.
+    pub fn max_hits(&self) -> u64 {
+        if self.stats_opt.is_some() {
+            return 0;
+        }
+        self.max_hits_opt.unwrap_or(DEFAULT_MAX_HITS)
+    }
+
+    /// Aggregation request computing the `stats` command, if any.
+    pub fn aggregation_request(&self) -> Option<JsonValue> {
+        self.stats_opt
+            .as_ref()
+            .and_then(|stats| stats.aggregation_request())
+    }
+
+    /// Returns true if the query computes stats, in which case the rows are built from the
+    /// aggregation results rather than from the hits.
+    pub fn has_stats(&self) -> bool {
+        self.stats_opt.is_some()
+    }
+
+    /// Builds the result rows from the search results, then applies the commands that could not
+    /// be pushed down to the search request.
+    pub fn rows(
+        &self,
+        num_hits: u64,
+        hits: Vec<JsonValue>,
+        aggregations_opt: Option<&JsonValue>,
+    ) -> anyhow::Result<PipeQueryRows> {
+        let mut warnings = Vec::new();
+
+        let mut rows: Vec<JsonValue> = if let Some(stats) = &self.stats_opt {
+            let stats_rows = stats.rows(num_hits, aggregations_opt)?;
+
+            if stats_rows.num_docs_in_dropped_groups > 0 {
+                warnings.push(format!(
+                    "`stats` only returns the {MAX_GROUPS_PER_FIELD} groups with the most \
+                     documents for each `by` field, {} documents belong to dropped groups",
+                    stats_rows.num_docs_in_dropped_groups
+                ));
+            }
+            stats_rows.rows.into_iter().map(JsonValue::Object).collect()
+        } else {
+            hits
+        };
+        for row_command in &self.row_commands {
+            match row_command {
+                RowCommand::Where(conditions) => rows.retain(|row| {
+                    conditions
+                        .iter()
+                        .all(|condition| condition.matches_row(row))
+                }),
+                RowCommand::Sort(sort_fields) => {
+                    rows.sort_by(|left_row, right_row| {
+                        compare_rows(left_row, right_row, sort_fields)
+                    });
+                }
+                RowCommand::Head(count) => rows.truncate(*count as usize),
+                RowCommand::Fields(field_names) => {
+                    for row in rows.iter_mut() {
+                        *row = project_row(row, field_names);
+                    }
+                }
+            }
+        }
+        Ok(PipeQueryRows { rows, warnings })
+    }
+}
+
+/// Parses a pipe query and compiles it into a [`PipeQuery`].
+///
+/// The user query of the search segment is left unparsed in the query AST, so that it can be
+/// resolved against the default search fields of the doc mapper.
+pub fn parse_pipe_query(query: &str) -> anyhow::Result<PipeQuery> {
+    let segments = split_pipes(query)?;
+    let search_segment = parse_search_segment(segments[0])?;
+
+    let mut must_not = Vec::new();
+    let mut filter = Vec::new();
+    let mut sort_fields = Vec::new();
+    let mut max_hits_opt: Option<u64> = None;
+    let mut stats_opt: Option<StatsCommand> = None;
+    let mut row_commands = Vec::new();
+
+    for segment in &segments[1..] {
+        let command = parse_command(segment)
+            .map_err(|error| anyhow::anyhow!("failed to parse command `{segment}`: {error}"))?;
+        // Commands are pushed down to the search request until a command that cannot be pushed
+        // down is met. From then on, they are applied to the rows.
+        let push_down = stats_opt.is_none() && row_commands.is_empty();
+
+        match command {
+            PipeCommand::Where(conditions) if push_down && max_hits_opt.is_none() => {
+                for condition in conditions {
+                    match condition.to_query_ast() {
+                        (query_ast, false) => filter.push(query_ast),
+                        (query_ast, true) => must_not.push(query_ast),
+                    }
+                }
+            }
+            PipeCommand::Sort(pipe_sort_fields) if push_down && max_hits_opt.is_none() => {
+                sort_fields = pipe_sort_fields;
+            }
+            PipeCommand::Head(count) if push_down => {
+                let max_hits = max_hits_opt.map_or(count, |max_hits| max_hits.min(count));
+                max_hits_opt = Some(max_hits);
+            }
+            PipeCommand::Stats(stats) => {
+                if stats_opt.is_some() {
+                    bail!("a pipe query cannot have more than one `stats` command");
+                }
+                if max_hits_opt.is_some() || !row_commands.is_empty() {
+                    bail!("`stats` cannot follow `head` or `fields`");
+                }
+                stats_opt = Some(stats);
+            }
+            PipeCommand::Where(conditions) => row_commands.push(RowCommand::Where(conditions)),
+            PipeCommand::Sort(sort_fields) => row_commands.push(RowCommand::Sort(sort_fields)),
+            PipeCommand::Head(count) => row_commands.push(RowCommand::Head(count)),
+            PipeCommand::Fields(field_names) => row_commands.push(RowCommand::Fields(field_names)),
+        }
+    }
+    let search_query_ast = if search_segment.user_text.is_empty() {
+        QueryAst::MatchAll
+    } else {
+        query_ast_from_user_text(&search_segment.user_text, None)
+    };
+    let query_ast = if filter.is_empty() && must_not.is_empty() {
+        search_query_ast
+    } else {
+        BoolQuery {
+            must: vec![search_query_ast],
+            must_not,
+            filter,
+            ..Default::default()
+        }
+        .into()
+    };
+    Ok(PipeQuery {
+        index_id_patterns: search_segment.index_id_patterns,
+        query_ast,
+        sort_fields,
+        max_hits_opt,
+        stats_opt,
+        row_commands,
+    })
+}
+
+fn value_to_string(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Resolves a field of a row. Dotted names are looked up as is first, then as a path into
+/// nested objects.
+fn resolve_row_field<'a>(row: &'a JsonValue, field_name: &str) -> Option<&'a JsonValue> {
+    let row_object = row.as_object()?;
+    if let Some(value) = row_object.get(field_name) {
+        return Some(value);
+    }
+    let mut value = row;
+    for path_segment in field_name.split('.') {
+        value = value.as_object()?.get(path_segment)?;
+    }
+    Some(value)
+}
+
+fn compare_values(left: &JsonValue, right: &JsonValue) -> Option<Ordering> {
+    let as_f64 = |value: &JsonValue| match value {
+        JsonValue::Number(number) => number.as_f64(),
+        JsonValue::String(text) => text.parse::<f64>().ok(),
+        _ => None,
+    };
+    match (left, right) {
+        (JsonValue::String(left), JsonValue::String(right)) => Some(left.cmp(right)),
+        (JsonValue::Bool(left), JsonValue::Bool(right)) => Some(left.cmp(right)),
+        // Bare words such as `true` are parsed as strings.
+        (JsonValue::Bool(left), JsonValue::String(right)) => Some(left.to_string().cmp(right)),
+        (JsonValue::String(left), JsonValue::Bool(right)) => Some(left.cmp(&right.to_string())),
+        (left, right) => as_f64(left)?.partial_cmp(&as_f64(right)?),
+    }
+}
+
+/// Compares two rows on the sort fields. Rows missing a sort field come last.
+fn compare_rows(
+    left_row: &JsonValue,
+    right_row: &JsonValue,
+    sort_fields: &[PipeSortField],
+) -> Ordering {
+    for sort_field in sort_fields {
+        let left_value_opt =
+            resolve_row_field(left_row, &sort_field.field_name).filter(|value| !value.is_null());
+        let right_value_opt =
+            resolve_row_field(right_row, &sort_field.field_name).filter(|value| !value.is_null());
+        let ordering = match (left_value_opt, right_value_opt) {
+            (Some(left_value), Some(right_value)) => {
+                let ordering = compare_values(left_value, right_value).unwrap_or(Ordering::Equal);
+                match sort_field.sort_order {
+                    PipeSortOrder::Asc => ordering,
+                    PipeSortOrder::Desc => ordering.reverse(),
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+fn project_row(row: &JsonValue, field_names: &[String]) -> JsonValue {
+    let mut projected_row = JsonMap::new();
+
+    for field_name in field_names {
+        if let Some(value) = resolve_row_field(row, field_name) {
+            projected_row.insert(field_name.clone(), value.clone());
+        }
+    }
+    JsonValue::Object(projected_row)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_pipe_query_search_only() {
+        let pipe_query = parse_pipe_query("index=logs service:api").unwrap();
+        assert_eq!(pipe_query.index_id_patterns, vec!["logs".to_string()]);
+        assert_eq!(
+            pipe_query.query_ast,
+            query_ast_from_user_text("service:api", None)
+        );
+        assert!(pipe_query.sort_fields.is_empty());
+        assert_eq!(pipe_query.max_hits(), DEFAULT_MAX_HITS);
+        assert!(!pipe_query.has_stats());
+        assert!(pipe_query.aggregation_request().is_none());
+
+        let pipe_query = parse_pipe_query("").unwrap();
+        assert!(pipe_query.index_id_patterns.is_empty());
+        assert_eq!(pipe_query.query_ast, QueryAst::MatchAll);
+    }
+
+    #[test]
+    fn test_parse_pipe_query_pushes_down_where_sort_and_head() {
+        let pipe_query = parse_pipe_query(
+            "service:api | where status >= 500 and host != db | sort -ts | head 5",
+        )
+        .unwrap();
+        let QueryAst::Bool(bool_query) = &pipe_query.query_ast else {
+            panic!("expected a bool query, got {:?}", pipe_query.query_ast);
+        };
+        assert_eq!(
+            bool_query.must,
+            vec![query_ast_from_user_text("service:api", None)]
+        );
+        assert_eq!(
+            bool_query.filter,
+            vec![QueryAst::Range(RangeQuery {
+                field: "status".to_string(),
+                lower_bound: Bound::Included(JsonLiteral::Number(500.into())),
+                upper_bound: Bound::Unbounded,
+            })]
+        );
+        assert_eq!(bool_query.must_not.len(), 1);
+        assert_eq!(
+            pipe_query.sort_fields,
+            vec![PipeSortField {
+                field_name: "ts".to_string(),
+                sort_order: PipeSortOrder::Desc,
+            }]
+        );
+        assert_eq!(pipe_query.max_hits(), 5);
+        assert!(pipe_query.row_commands.is_empty());
+    }
+
+    #[test]
+    fn test_parse_pipe_query_with_stats() {
+        let pipe_query = parse_pipe_query(
+            "index=logs service:api | where status >= 500 | stats count() by host | sort -count | \
+             head 2",
+        )
+        .unwrap();
+        assert!(pipe_query.has_stats());
+        assert_eq!(pipe_query.max_hits(), 0);
+        assert_eq!(
+            pipe_query.aggregation_request().unwrap(),
+            json!({"group_0": {"terms": {"field": "host", "size": 1000}}})
+        );
+        let aggregations = json!({
+            "group_0": {
+                "buckets": [
+                    {"key": "web-1", "doc_count": 1},
+                    {"key": "web-2", "doc_count": 7},
+                    {"key": "web-3", "doc_count": 3},
+                ]
+            }
+        });
+        let pipe_query_rows = pipe_query
+            .rows(11, Vec::new(), Some(&aggregations))
+            .unwrap();
+        assert_eq!(
+            pipe_query_rows.rows,
+            vec![
+                json!({"host": "web-2", "count": 7}),
+                json!({"host": "web-3", "count": 3}),
+            ]
+        );
+        assert!(pipe_query_rows.warnings.is_empty());
+
+        let aggregations = json!({
+            "group_0": {
+                "sum_other_doc_count": 4,
+                "buckets": [{"key": "web-1", "doc_count": 7}]
+            }
+        });
+        let pipe_query_rows = pipe_query
+            .rows(11, Vec::new(), Some(&aggregations))
+            .unwrap();
+        assert_eq!(
+            pipe_query_rows.rows,
+            vec![json!({"host": "web-1", "count": 7})]
+        );
+        assert_eq!(
+            pipe_query_rows.warnings,
+            vec![
+                "`stats` only returns the 1000 groups with the most documents for each `by` \
+                 field, 4 documents belong to dropped groups"
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pipe_query_row_commands_on_hits() {
+        let pipe_query =
+            parse_pipe_query("* | head 4 | where resource.status > 200 | sort latency | fields id")
+                .unwrap();
+        assert_eq!(pipe_query.query_ast, query_ast_from_user_text("*", None));
+        assert_eq!(pipe_query.max_hits(), 4);
+        assert_eq!(pipe_query.row_commands.len(), 3);
+
+        let hits = vec![
+            json!({"id": 1, "latency": 30, "resource": {"status": 500}}),
+            json!({"id": 2, "latency": 10, "resource": {"status": 200}}),
+            json!({"id": 3, "resource": {"status": 404}}),
+            json!({"id": 4, "latency": 20, "resource": {"status": 503}}),
+        ];
+        let pipe_query_rows = pipe_query.rows(4, hits, None).unwrap();
+        assert_eq!(
+            pipe_query_rows.rows,
+            vec![json!({"id": 4}), json!({"id": 1}), json!({"id": 3})]
+        );
+    }
+
+    #[test]
+    fn test_parse_pipe_query_errors() {
+        parse_pipe_query("* | stats count() | stats count()").unwrap_err();
+        parse_pipe_query("* | head 3 | stats count()").unwrap_err();
+        parse_pipe_query("* | fields host | stats count()").unwrap_err();
+        let error = parse_pipe_query("* | unknown").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("failed to parse command `unknown`"));
+    }
+}
@@ -0,0 +1,575 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{bail, Context};
+use serde_json::{Number as JsonNumber, Value as JsonValue};
+
+use super::stats::{StatsCommand, StatsFunction, StatsMetric};
+use super::{CompareOp, Condition, PipeSortField, PipeSortOrder};
+
+/// Number of rows returned by `head` when no count is specified.
+const DEFAULT_HEAD_COUNT: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PipeCommand {
+    Where(Vec<Condition>),
+    Stats(StatsCommand),
+    Sort(Vec<PipeSortField>),
+    Head(u64),
+    Fields(Vec<String>),
+}
+
+/// The leading segment of a pipe query, before the first `|`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct SearchSegment {
+    pub index_id_patterns: Vec<String>,
+    pub user_text: String,
+}
+
+/// Splits a pipe query on the `|` characters that are not enclosed in quotes.
+pub(crate) fn split_pipes(query: &str) -> anyhow::Result<Vec<&str>> {
+    let mut segments = Vec::new();
+    let mut segment_start = 0;
+    let mut quote_opt: Option<char> = None;
+    let mut escaped = false;
+
+    for (idx, ch) in query.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote_opt, ch) {
+            (_, '\\') => escaped = true,
+            (Some(quote), ch) if ch == quote => quote_opt = None,
+            (None, '"') | (None, '\'') => quote_opt = Some(ch),
+            (None, '|') => {
+                segments.push(query[segment_start..idx].trim());
+                segment_start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    if let Some(quote) = quote_opt {
+        bail!("unterminated quote `{quote}` in pipe query");
+    }
+    segments.push(query[segment_start..].trim());
+    Ok(segments)
+}
+
+/// Extracts the `index=` (or `source=`) clauses of the search segment. The remaining text is
+/// kept as is and interpreted as a regular user query.
+pub(crate) fn parse_search_segment(segment: &str) -> anyhow::Result<SearchSegment> {
+    let mut search_segment = SearchSegment::default();
+    let mut user_text_parts = Vec::new();
+
+    for word in split_whitespace_outside_quotes(segment) {
+        let index_id_patterns_opt = word
+            .strip_prefix("index=")
+            .or_else(|| word.strip_prefix("source="));
+
+        if let Some(index_id_patterns) = index_id_patterns_opt {
+            for index_id_pattern in index_id_patterns.split(',') {
+                let index_id_pattern = index_id_pattern.trim_matches(|ch| ch == '"' || ch == '\'');
+                if index_id_pattern.is_empty() {
+                    bail!("empty index ID pattern in `{word}`");
+                }
+                search_segment
+                    .index_id_patterns
+                    .push(index_id_pattern.to_string());
+            }
+        } else {
+            user_text_parts.push(word);
+        }
+    }
+    search_segment.user_text = user_text_parts.join(" ");
+    Ok(search_segment)
+}
+
+fn split_whitespace_outside_quotes(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut word_start_opt: Option<usize> = None;
+    let mut quote_opt: Option<char> = None;
+    let mut escaped = false;
+
+    for (idx, ch) in text.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        if quote_opt.is_none() && ch.is_whitespace() {
+            if let Some(word_start) = word_start_opt.take() {
+                words.push(&text[word_start..idx]);
+            }
+            continue;
+        }
+        if word_start_opt.is_none() {
+            word_start_opt = Some(idx);
+        }
+        match (quote_opt, ch) {
+            (_, '\\') => escaped = true,
+            (Some(quote), ch) if ch == quote => quote_opt = None,
+            (None, '"') | (None, '\'') => quote_opt = Some(ch),
+            _ => {}
+        }
+    }
+    if let Some(word_start) = word_start_opt {
+        words.push(&text[word_start..]);
+    }
+    words
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Compare(CompareOp),
+    Comma,
+    LParen,
+    RParen,
+    Minus,
+    Plus,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(word) => format!("`{word}`"),
+            Token::Str(text) => format!("'{text}'"),
+            Token::Compare(compare_op) => format!("`{}`", compare_op.as_str()),
+            Token::Comma => "`,`".to_string(),
+            Token::LParen => "`(`".to_string(),
+            Token::RParen => "`)`".to_string(),
+            Token::Minus => "`-`".to_string(),
+            Token::Plus => "`+`".to_string(),
+        }
+    }
+}
+
+fn is_word_start(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '@'
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '@' | '.' | '-' | '*')
+}
+
+fn tokenize(segment: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = segment.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            ch if ch.is_whitespace() => continue,
+            ',' => Token::Comma,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '-' => Token::Minus,
+            '+' => Token::Plus,
+            '=' => {
+                // `==` is accepted as an alias of `=`.
+                if chars.peek() == Some(&'=') {
+                    chars.next();
+                }
+                Token::Compare(CompareOp::Eq)
+            }
+            '!' => {
+                if chars.next() != Some('=') {
+                    bail!("expected `!=`");
+                }
+                Token::Compare(CompareOp::Ne)
+            }
+            '<' | '>' => {
+                let or_equal = chars.peek() == Some(&'=');
+                if or_equal {
+                    chars.next();
+                }
+                let compare_op = match (ch, or_equal) {
+                    ('<', false) => CompareOp::Lt,
+                    ('<', true) => CompareOp::Lte,
+                    ('>', false) => CompareOp::Gt,
+                    _ => CompareOp::Gte,
+                };
+                Token::Compare(compare_op)
+            }
+            '"' | '\'' => {
+                let quote = ch;
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            let escaped_ch = chars.next().context("unterminated string")?;
+                            text.push(escaped_ch);
+                        }
+                        Some(ch) if ch == quote => break,
+                        Some(ch) => text.push(ch),
+                        None => bail!("unterminated string"),
+                    }
+                }
+                Token::Str(text)
+            }
+            ch if is_word_start(ch) => {
+                let mut word = ch.to_string();
+                while let Some(&next_ch) = chars.peek() {
+                    if !is_word_char(next_ch) {
+                        break;
+                    }
+                    word.push(next_ch);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+            _ => bail!("unexpected character `{ch}`"),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct CommandParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl CommandParser {
+    fn peek_token(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token_opt = self.tokens.get(self.position).cloned();
+        if token_opt.is_some() {
+            self.position += 1;
+        }
+        token_opt
+    }
+
+    fn consume_if(&mut self, token: &Token) -> bool {
+        if self.peek_token() == Some(token) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if let Some(Token::Word(word)) = self.peek_token() {
+            if word.eq_ignore_ascii_case(keyword) {
+                self.position += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn expect_token(&mut self, expected_token: Token) -> anyhow::Result<()> {
+        match self.next_token() {
+            Some(token) if token == expected_token => Ok(()),
+            Some(token) => bail!(
+                "expected {}, found {}",
+                expected_token.describe(),
+                token.describe()
+            ),
+            None => bail!(
+                "expected {}, found end of command",
+                expected_token.describe()
+            ),
+        }
+    }
+
+    fn expect_word(&mut self) -> anyhow::Result<String> {
+        match self.next_token() {
+            Some(Token::Word(word)) => Ok(word),
+            Some(token) => bail!("expected a field name, found {}", token.describe()),
+            None => bail!("expected a field name, found end of command"),
+        }
+    }
+
+    fn expect_end(&self) -> anyhow::Result<()> {
+        if let Some(token) = self.peek_token() {
+            bail!("unexpected {}", token.describe());
+        }
+        Ok(())
+    }
+
+    /// Parses a field name. Stats outputs such as `avg(latency)` are accepted so that they can be
+    /// referenced by the commands following `stats`.
+    fn parse_field_name(&mut self) -> anyhow::Result<String> {
+        let word = self.expect_word()?;
+        if !self.consume_if(&Token::LParen) {
+            return Ok(word);
+        }
+        if self.consume_if(&Token::RParen) {
+            return Ok(format!("{word}()"));
+        }
+        let argument = self.expect_word()?;
+        self.expect_token(Token::RParen)?;
+        Ok(format!("{word}({argument})"))
+    }
+
+    fn parse_field_names(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut field_names = vec![self.parse_field_name()?];
+        while self.consume_if(&Token::Comma) {
+            field_names.push(self.parse_field_name()?);
+        }
+        Ok(field_names)
+    }
+
+    fn parse_value(&mut self) -> anyhow::Result<JsonValue> {
+        let negative = self.consume_if(&Token::Minus);
+        let value = match self.next_token() {
+            Some(Token::Str(text)) if !negative => JsonValue::String(text),
+            Some(Token::Word(word)) => {
+                let number_text = if negative { format!("-{word}") } else { word };
+                match parse_number(&number_text) {
+                    Some(number) => JsonValue::Number(number),
+                    None if !negative => JsonValue::String(number_text),
+                    None => bail!("expected a number after `-`, found `{number_text}`"),
+                }
+            }
+            Some(token) => bail!("expected a value, found {}", token.describe()),
+            None => bail!("expected a value, found end of command"),
+        };
+        Ok(value)
+    }
+
+    fn parse_where(&mut self) -> anyhow::Result<Vec<Condition>> {
+        let mut conditions = Vec::new();
+        loop {
+            let field = self.parse_field_name()?;
+            let compare_op = match self.next_token() {
+                Some(Token::Compare(compare_op)) => compare_op,
+                Some(token) => bail!("expected a comparison operator, found {}", token.describe()),
+                None => bail!("expected a comparison operator, found end of command"),
+            };
+            let value = self.parse_value()?;
+            conditions.push(Condition {
+                field,
+                compare_op,
+                value,
+            });
+            if !self.consume_keyword("and") {
+                break;
+            }
+        }
+        Ok(conditions)
+    }
+
+    fn parse_stats(&mut self) -> anyhow::Result<StatsCommand> {
+        let mut metrics = Vec::new();
+        loop {
+            let function_name = self.expect_word()?;
+            let function: StatsFunction = function_name.parse()?;
+            self.expect_token(Token::LParen)?;
+            let field_opt = if self.consume_if(&Token::RParen) {
+                None
+            } else {
+                let field = self.expect_word()?;
+                self.expect_token(Token::RParen)?;
+                Some(field)
+            };
+            let output_name = if self.consume_keyword("as") {
+                self.expect_word()?
+            } else {
+                StatsMetric::default_output_name(function, field_opt.as_deref())
+            };
+            metrics.push(StatsMetric::new(function, field_opt, output_name)?);
+
+            if !self.consume_if(&Token::Comma) {
+                break;
+            }
+        }
+        let group_by = if self.consume_keyword("by") {
+            self.parse_field_names()?
+        } else {
+            Vec::new()
+        };
+        StatsCommand::new(metrics, group_by)
+    }
+
+    fn parse_sort(&mut self) -> anyhow::Result<Vec<PipeSortField>> {
+        let mut sort_fields = Vec::new();
+        loop {
+            let sort_order = if self.consume_if(&Token::Minus) {
+                PipeSortOrder::Desc
+            } else {
+                self.consume_if(&Token::Plus);
+                PipeSortOrder::Asc
+            };
+            let field_name = self.parse_field_name()?;
+            let sort_order = if self.consume_keyword("desc") {
+                PipeSortOrder::Desc
+            } else if self.consume_keyword("asc") {
+                PipeSortOrder::Asc
+            } else {
+                sort_order
+            };
+            sort_fields.push(PipeSortField {
+                field_name,
+                sort_order,
+            });
+            if !self.consume_if(&Token::Comma) {
+                break;
+            }
+        }
+        Ok(sort_fields)
+    }
+
+    fn parse_head(&mut self) -> anyhow::Result<u64> {
+        let Some(token) = self.next_token() else {
+            return Ok(DEFAULT_HEAD_COUNT);
+        };
+        match token {
+            Token::Word(word) => word
+                .parse::<u64>()
+                .with_context(|| format!("expected a number of rows, found `{word}`")),
+            token => bail!("expected a number of rows, found {}", token.describe()),
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Option<JsonNumber> {
+    if let Ok(value) = text.parse::<i64>() {
+        return Some(value.into());
+    }
+    if let Ok(value) = text.parse::<u64>() {
+        return Some(value.into());
+    }
+    text.parse::<f64>().ok().and_then(JsonNumber::from_f64)
+}
+
+/// Parses one of the segments following the search segment.
+pub(crate) fn parse_command(segment: &str) -> anyhow::Result<PipeCommand> {
+    let tokens = tokenize(segment)?;
+    let mut parser = CommandParser {
+        tokens,
+        position: 0,
+    };
+    let Some(Token::Word(command_name)) = parser.next_token() else {
+        bail!("expected a command");
+    };
+    let command = match command_name.to_ascii_lowercase().as_str() {
+        "where" => PipeCommand::Where(parser.parse_where()?),
+        "stats" => PipeCommand::Stats(parser.parse_stats()?),
+        "sort" => PipeCommand::Sort(parser.parse_sort()?),
+        "head" | "limit" => PipeCommand::Head(parser.parse_head()?),
+        "fields" => PipeCommand::Fields(parser.parse_field_names()?),
+        _ => bail!(
+            "unknown command `{command_name}`, expected one of `where`, `stats`, `sort`, `head` \
+             or `fields`"
+        ),
+    };
+    parser.expect_end()?;
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_split_pipes() {
+        assert_eq!(
+            split_pipes("service:api | where status >= 500").unwrap(),
+            vec!["service:api", "where status >= 500"]
+        );
+        assert_eq!(
+            split_pipes(r#"body:"a|b" | head"#).unwrap(),
+            vec![r#"body:"a|b""#, "head"]
+        );
+        assert_eq!(split_pipes("").unwrap(), vec![""]);
+        split_pipes(r#"body:"a|b"#).unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_search_segment() {
+        let search_segment =
+            parse_search_segment(r#"index=logs,otel-* service:api body:"index=foo""#).unwrap();
+        assert_eq!(
+            search_segment,
+            SearchSegment {
+                index_id_patterns: vec!["logs".to_string(), "otel-*".to_string()],
+                user_text: r#"service:api body:"index=foo""#.to_string(),
+            }
+        );
+        let search_segment = parse_search_segment("source=logs").unwrap();
+        assert_eq!(search_segment.index_id_patterns, vec!["logs".to_string()]);
+        assert!(search_segment.user_text.is_empty());
+        parse_search_segment("index=").unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_where_command() {
+        let command =
+            parse_command("where status >= 500 and host != 'db-1' and delta > -1.5").unwrap();
+        assert_eq!(
+            command,
+            PipeCommand::Where(vec![
+                Condition {
+                    field: "status".to_string(),
+                    compare_op: CompareOp::Gte,
+                    value: json!(500),
+                },
+                Condition {
+                    field: "host".to_string(),
+                    compare_op: CompareOp::Ne,
+                    value: json!("db-1"),
+                },
+                Condition {
+                    field: "delta".to_string(),
+                    compare_op: CompareOp::Gt,
+                    value: json!(-1.5),
+                },
+            ])
+        );
+        parse_command("where status").unwrap_err();
+        parse_command("where status = ").unwrap_err();
+        parse_command("where status = 5 or status = 6").unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_sort_head_and_fields_commands() {
+        assert_eq!(
+            parse_command("sort -count, host, avg(latency) desc").unwrap(),
+            PipeCommand::Sort(vec![
+                PipeSortField {
+                    field_name: "count".to_string(),
+                    sort_order: PipeSortOrder::Desc,
+                },
+                PipeSortField {
+                    field_name: "host".to_string(),
+                    sort_order: PipeSortOrder::Asc,
+                },
+                PipeSortField {
+                    field_name: "avg(latency)".to_string(),
+                    sort_order: PipeSortOrder::Desc,
+                },
+            ])
+        );
+        assert_eq!(parse_command("head").unwrap(), PipeCommand::Head(10));
+        assert_eq!(parse_command("limit 3").unwrap(), PipeCommand::Head(3));
+        parse_command("head ten").unwrap_err();
+        assert_eq!(
+            parse_command("fields host, status").unwrap(),
+            PipeCommand::Fields(vec!["host".to_string(), "status".to_string()])
+        );
+        parse_command("dedup host").unwrap_err();
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+/// Maximum number of groups returned for each `by` field of a `stats` command.
+pub(crate) const MAX_GROUPS_PER_FIELD: u32 = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StatsFunction {
+    Count,
+    Avg,
+    Sum,
+    Min,
+    Max,
+}
+
+impl StatsFunction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StatsFunction::Count => "count",
+            StatsFunction::Avg => "avg",
+            StatsFunction::Sum => "sum",
+            StatsFunction::Min => "min",
+            StatsFunction::Max => "max",
+        }
+    }
+
+    fn aggregation_name(&self) -> &'static str {
+        match self {
+            StatsFunction::Count => "value_count",
+            StatsFunction::Avg => "avg",
+            StatsFunction::Sum => "sum",
+            StatsFunction::Min => "min",
+            StatsFunction::Max => "max",
+        }
+    }
+}
+
+impl fmt::Display for StatsFunction {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StatsFunction {
+    type Err = anyhow::Error;
+
+    fn from_str(function_name: &str) -> anyhow::Result<Self> {
+        let function = match function_name.to_ascii_lowercase().as_str() {
+            "count" => StatsFunction::Count,
+            "avg" => StatsFunction::Avg,
+            "sum" => StatsFunction::Sum,
+            "min" => StatsFunction::Min,
+            "max" => StatsFunction::Max,
+            _ => bail!(
+                "unknown stats function `{function_name}`, expected one of `count`, `avg`, `sum`, \
+                 `min` or `max`"
+            ),
+        };
+        Ok(function)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StatsMetric {
+    function: StatsFunction,
+    field_opt: Option<String>,
+    output_name: String,
+}
+
+impl StatsMetric {
+    pub fn new(
+        function: StatsFunction,
+        field_opt: Option<String>,
+        output_name: String,
+    ) -> anyhow::Result<Self> {
+        if field_opt.is_none() && function != StatsFunction::Count {
+            bail!("stats function `{function}` requires a field");
+        }
+        Ok(Self {
+            function,
+            field_opt,
+            output_name,
+        })
+    }
+
+    /// `count()` is named `count`, other metrics are named after the function call, for instance
+    /// `avg(latency)`.
+    pub fn default_output_name(function: StatsFunction, field_opt: Option<&str>) -> String {
+        match field_opt {
+            Some(field) => format!("{function}({field})"),
+            None => function.to_string(),
+        }
+    }
+
+    /// Returns the aggregation computing this metric, or `None` if the metric is the document
+    /// count of the enclosing bucket.
+    fn aggregation(&self) -> Option<JsonValue> {
+        let field = self.field_opt.as_ref()?;
+        Some(json!({ self.function.aggregation_name(): { "field": field } }))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StatsCommand {
+    metrics: Vec<StatsMetric>,
+    group_by: Vec<String>,
+}
+
+fn metric_key(metric_ord: usize) -> String {
+    format!("metric_{metric_ord}")
+}
+
+fn group_key(group_ord: usize) -> String {
+    format!("group_{group_ord}")
+}
+
+/// Rows of a `stats` command.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct StatsRows {
+    pub rows: Vec<JsonMap<String, JsonValue>>,
+    /// Number of documents that belong to groups dropped because a `by` field has more than
+    /// `MAX_GROUPS_PER_FIELD` values.
+    pub num_docs_in_dropped_groups: u64,
+}
+
+impl StatsCommand {
+    pub fn new(metrics: Vec<StatsMetric>, group_by: Vec<String>) -> anyhow::Result<Self> {
+        let mut output_names = HashSet::new();
+
+        for output_name in metrics
+            .iter()
+            .map(|metric| &metric.output_name)
+            .chain(group_by.iter())
+        {
+            if !output_names.insert(output_name) {
+                bail!("stats output `{output_name}` is defined more than once");
+            }
+        }
+        Ok(Self { metrics, group_by })
+    }
+
+    /// Builds the aggregation request computing the stats: one nested `terms` aggregation per
+    /// `by` field, with the metrics aggregations at the innermost level. Returns `None` if the
+    /// stats only count documents without grouping them.
+    pub fn aggregation_request(&self) -> Option<JsonValue> {
+        let mut aggregations = JsonMap::new();
+
+        for (metric_ord, metric) in self.metrics.iter().enumerate() {
+            if let Some(aggregation) = metric.aggregation() {
+                aggregations.insert(metric_key(metric_ord), aggregation);
+            }
+        }
+        for (group_ord, field) in self.group_by.iter().enumerate().rev() {
+            let mut terms_aggregation = json!({
+                "terms": {
+                    "field": field,
+                    "size": MAX_GROUPS_PER_FIELD,
+                }
+            });
+            if !aggregations.is_empty() {
+                terms_aggregation["aggs"] = JsonValue::Object(aggregations);
+            }
+            aggregations = JsonMap::new();
+            aggregations.insert(group_key(group_ord), terms_aggregation);
+        }
+        if aggregations.is_empty() {
+            return None;
+        }
+        Some(JsonValue::Object(aggregations))
+    }
+
+    /// Flattens the aggregation results into one row per group.
+    pub fn rows(
+        &self,
+        num_hits: u64,
+        aggregations_opt: Option<&JsonValue>,
+    ) -> anyhow::Result<StatsRows> {
+        let empty_aggregations = JsonValue::Object(JsonMap::new());
+        let aggregations = aggregations_opt.unwrap_or(&empty_aggregations);
+        let mut stats_rows = StatsRows::default();
+        let mut group_values = Vec::with_capacity(self.group_by.len());
+        self.collect_rows(aggregations, num_hits, &mut group_values, &mut stats_rows)?;
+        Ok(stats_rows)
+    }
+
+    fn collect_rows(
+        &self,
+        bucket: &JsonValue,
+        doc_count: u64,
+        group_values: &mut Vec<JsonValue>,
+        stats_rows: &mut StatsRows,
+    ) -> anyhow::Result<()> {
+        let group_ord = group_values.len();
+
+        if group_ord == self.group_by.len() {
+            let mut row = JsonMap::new();
+
+            for (field, group_value) in self.group_by.iter().zip(group_values.iter()) {
+                row.insert(field.clone(), group_value.clone());
+            }
+            for (metric_ord, metric) in self.metrics.iter().enumerate() {
+                let metric_value = if metric.field_opt.is_none() {
+                    JsonValue::from(doc_count)
+                } else {
+                    bucket
+                        .get(metric_key(metric_ord))
+                        .and_then(|metric_result| metric_result.get("value"))
+                        .cloned()
+                        .unwrap_or(JsonValue::Null)
+                };
+                row.insert(metric.output_name.clone(), metric_value);
+            }
+            stats_rows.rows.push(row);
+            return Ok(());
+        }
+        let terms_result = bucket.get(group_key(group_ord));
+        let buckets = terms_result
+            .and_then(|terms_result| terms_result.get("buckets"))
+            .and_then(|buckets| buckets.as_array())
+            .with_context(|| {
+                format!(
+                    "missing `{}` buckets in aggregation results",
+                    self.group_by[group_ord]
+                )
+            })?;
+        stats_rows.num_docs_in_dropped_groups += terms_result
+            .and_then(|terms_result| terms_result.get("sum_other_doc_count"))
+            .and_then(|sum_other_doc_count| sum_other_doc_count.as_u64())
+            .unwrap_or(0);
+
+        for bucket in buckets {
+            let group_value = bucket
+                .get("key_as_string")
+                .or_else(|| bucket.get("key"))
+                .cloned()
+                .unwrap_or(JsonValue::Null);
+            let doc_count = bucket
+                .get("doc_count")
+                .and_then(|doc_count| doc_count.as_u64())
+                .unwrap_or(0);
+            group_values.push(group_value);
+            self.collect_rows(bucket, doc_count, group_values, stats_rows)?;
+            group_values.pop();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats_command(metrics: &[(StatsFunction, Option<&str>)], group_by: &[&str]) -> StatsCommand {
+        let metrics = metrics
+            .iter()
+            .map(|(function, field_opt)| {
+                let output_name = StatsMetric::default_output_name(*function, *field_opt);
+                StatsMetric::new(*function, field_opt.map(str::to_string), output_name).unwrap()
+            })
+            .collect();
+        let group_by = group_by.iter().map(|field| field.to_string()).collect();
+        StatsCommand::new(metrics, group_by).unwrap()
+    }
+
+    #[test]
+    fn test_stats_count_without_group_by() {
+        let stats_command = stats_command(&[(StatsFunction::Count, None)], &[]);
+        assert!(stats_command.aggregation_request().is_none());
+
+        let stats_rows = stats_command.rows(42, None).unwrap();
+        assert_eq!(stats_rows.rows.len(), 1);
+        assert_eq!(stats_rows.rows[0]["count"], json!(42));
+        assert_eq!(stats_rows.num_docs_in_dropped_groups, 0);
+    }
+
+    #[test]
+    fn test_stats_aggregation_request_and_rows() {
+        let stats_command = stats_command(
+            &[
+                (StatsFunction::Count, None),
+                (StatsFunction::Avg, Some("latency")),
+            ],
+            &["host", "status"],
+        );
+        assert_eq!(
+            stats_command.aggregation_request().unwrap(),
+            json!({
+                "group_0": {
+                    "terms": {"field": "host", "size": 1000},
+                    "aggs": {
+                        "group_1": {
+                            "terms": {"field": "status", "size": 1000},
+                            "aggs": {
+                                "metric_1": {"avg": {"field": "latency"}}
+                            }
+                        }
+                    }
+                }
+            })
+        );
+        let aggregations = json!({
+            "group_0": {
+                "sum_other_doc_count": 5,
+                "buckets": [
+                    {
+                        "key": "web-1",
+                        "doc_count": 3,
+                        "group_1": {
+                            "sum_other_doc_count": 2,
+                            "buckets": [
+                                {"key": 200.0, "doc_count": 2, "metric_1": {"value": 12.5}},
+                                {"key": 500.0, "doc_count": 1, "metric_1": {"value": 80.0}}
+                            ]
+                        }
+                    },
+                    {
+                        "key": "web-2",
+                        "doc_count": 1,
+                        "group_1": {
+                            "buckets": [
+                                {"key": 200.0, "doc_count": 1, "metric_1": {"value": null}}
+                            ]
+                        }
+                    }
+                ]
+            }
+        });
+        let stats_rows = stats_command.rows(11, Some(&aggregations)).unwrap();
+        assert_eq!(stats_rows.num_docs_in_dropped_groups, 7);
+
+        let rows: Vec<JsonValue> = stats_rows.rows.into_iter().map(JsonValue::Object).collect();
+        assert_eq!(
+            rows,
+            vec![
+                json!({"host": "web-1", "status": 200.0, "count": 2, "avg(latency)": 12.5}),
+                json!({"host": "web-1", "status": 500.0, "count": 1, "avg(latency)": 80.0}),
+                json!({"host": "web-2", "status": 200.0, "count": 1, "avg(latency)": null}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stats_invalid_definitions() {
+        StatsMetric::new(StatsFunction::Avg, None, "avg".to_string()).unwrap_err();
+        "median".parse::<StatsFunction>().unwrap_err();
+
+        let count_metric =
+            StatsMetric::new(StatsFunction::Count, None, "host".to_string()).unwrap();
+        StatsCommand::new(vec![count_metric], vec!["host".to_string()]).unwrap_err();
+    }
+}
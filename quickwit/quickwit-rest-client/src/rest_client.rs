@@ -702,6 +702,7 @@ mod test {
             aggregations: None,
             elapsed_time_micros: 100,
             errors: Vec::new(),
+            profile: None,
         };
         Mock::given(method("POST"))
             .and(path("/api/v1/my-index/search"))
@@ -27,12 +27,14 @@ use quickwit_proto::search::{
     LeafListTermsResponse, LeafSearchRequest, LeafSearchResponse, LeafSearchStreamRequest,
     LeafSearchStreamResponse, ListFieldsResponse, PutKvRequest,
 };
-use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::{debug, error, info, warn};
 
+use crate::collector::{
+    aggregations_from_request, merge_intermediate_aggregation_result, QuickwitAggregations,
+};
 use crate::retry::search::LeafSearchRetryPolicy;
 use crate::retry::search_stream::{LeafSearchStreamRetryPolicy, SuccessfulSplitIds};
 use crate::retry::{retry_client, DefaultRetryPolicy, RetryPolicy};
@@ -106,8 +108,13 @@ impl ClusterClient {
                 "Leaf search response error: `{:?}`. Retry once to execute {:?} with {:?}",
                 response_res, retry_request, client
             );
+            // The intermediate aggregation results can only be merged knowing the aggregations.
+            let aggregations_opt = match &retry_request.search_request {
+                Some(search_request) => aggregations_from_request(search_request)?,
+                None => None,
+            };
             let retry_result = client.leaf_search(retry_request).await;
-            response_res = merge_leaf_search_results(response_res, retry_result);
+            response_res = merge_leaf_search_results(&aggregations_opt, response_res, retry_result);
         }
         response_res
     }
@@ -262,19 +269,8 @@ fn replicate_kv_to_one_server(
     }
 }
 
-/// Takes two intermediate aggregation results serialized using postcard,
-/// merge them and returns the merged serialized result.
-fn merge_intermediate_aggregation(left: &[u8], right: &[u8]) -> crate::Result<Vec<u8>> {
-    let mut intermediate_aggregation_results_left: IntermediateAggregationResults =
-        postcard::from_bytes(left)?;
-    let intermediate_aggregation_results_right: IntermediateAggregationResults =
-        postcard::from_bytes(right)?;
-    intermediate_aggregation_results_left.merge_fruits(intermediate_aggregation_results_right)?;
-    let serialized = postcard::to_allocvec(&intermediate_aggregation_results_left)?;
-    Ok(serialized)
-}
-
 fn merge_leaf_search_response(
+    aggregations_opt: &Option<QuickwitAggregations>,
     mut left_response: LeafSearchResponse,
     right_response: LeafSearchResponse,
 ) -> crate::Result<LeafSearchResponse> {
@@ -285,11 +281,10 @@ fn merge_leaf_search_response(
         left_response.intermediate_aggregation_result,
         right_response.intermediate_aggregation_result,
     ) {
-        (Some(left_agg_bytes), Some(right_agg_bytes)) => {
-            let intermediate_aggregation_bytes: Vec<u8> =
-                merge_intermediate_aggregation(&left_agg_bytes[..], &right_agg_bytes[..])?;
-            Some(intermediate_aggregation_bytes)
-        }
+        (Some(left_agg_bytes), Some(right_agg_bytes)) => merge_intermediate_aggregation_result(
+            aggregations_opt,
+            [&left_agg_bytes[..], &right_agg_bytes[..]].into_iter(),
+        )?,
         (None, Some(right)) => Some(right),
         (Some(left), None) => Some(left),
         (None, None) => None,
@@ -301,17 +296,19 @@ fn merge_leaf_search_response(
             + right_response.num_attempted_splits,
         failed_splits: right_response.failed_splits,
         partial_hits: left_response.partial_hits,
+        split_profiles: Vec::new(),
     })
 }
 
 // Merge initial leaf search results with results obtained from a retry.
 fn merge_leaf_search_results(
+    aggregations_opt: &Option<QuickwitAggregations>,
     left_search_response_result: crate::Result<LeafSearchResponse>,
     right_search_response_result: crate::Result<LeafSearchResponse>,
 ) -> crate::Result<LeafSearchResponse> {
     match (left_search_response_result, right_search_response_result) {
         (Ok(left_response), Ok(right_response)) => {
-            merge_leaf_search_response(left_response, right_response)
+            merge_leaf_search_response(aggregations_opt, left_response, right_response)
         }
         (Ok(single_valid_response), Err(_)) => Ok(single_valid_response),
         (Err(_), Ok(single_valid_response)) => Ok(single_valid_response),
@@ -380,6 +377,7 @@ mod tests {
                 split_footer_start: 0,
                 timestamp_start: None,
                 timestamp_end: None,
+                storage_uri: None,
             }],
             ..Default::default()
         }
@@ -403,6 +401,7 @@ mod tests {
                     split_footer_end: 100,
                     timestamp_start: None,
                     timestamp_end: None,
+                    storage_uri: None,
                 },
                 SplitIdAndFooterOffsets {
                     split_id: "split_2".to_string(),
@@ -410,6 +409,7 @@ mod tests {
                     split_footer_end: 100,
                     timestamp_start: None,
                     timestamp_end: None,
+                    storage_uri: None,
                 },
             ],
         }
@@ -437,6 +437,7 @@ mod tests {
                     split_footer_end: 100,
                     timestamp_start: None,
                     timestamp_end: None,
+                    storage_uri: None,
                 },
                 SplitIdAndFooterOffsets {
                     split_id: "split_2".to_string(),
@@ -444,6 +445,7 @@ mod tests {
                     split_footer_end: 100,
                     timestamp_start: None,
                     timestamp_end: None,
+                    storage_uri: None,
                 },
             ],
         }
@@ -618,7 +620,7 @@ mod tests {
             ..Default::default()
         };
         let merged_leaf_search_response =
-            merge_leaf_search_results(Ok(leaf_response), Ok(leaf_response_retry)).unwrap();
+            merge_leaf_search_results(&None, Ok(leaf_response), Ok(leaf_response_retry)).unwrap();
         assert_eq!(merged_leaf_search_response.num_attempted_splits, 2);
         assert_eq!(merged_leaf_search_response.num_hits, 2);
         assert_eq!(merged_leaf_search_response.partial_hits.len(), 2);
@@ -641,6 +643,7 @@ mod tests {
             ..Default::default()
         };
         let merged_result = merge_leaf_search_results(
+            &None,
             Err(SearchError::Internal("error".to_string())),
             Ok(leaf_response),
         )
@@ -655,6 +658,7 @@ mod tests {
     #[test]
     fn test_merge_leaf_search_retry_error_on_error() -> anyhow::Result<()> {
         let merge_error = merge_leaf_search_results(
+            &None,
             Err(SearchError::Internal("error".to_string())),
             Err(SearchError::Internal("retry error".to_string())),
         )
@@ -20,13 +20,14 @@
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use quickwit_common::binary_heap::{SortKeyMapper, TopK};
 use quickwit_doc_mapper::{DocMapper, WarmupInfo};
 use quickwit_proto::search::{
-    LeafSearchResponse, PartialHit, SearchRequest, SortByValue, SortOrder, SortValue,
-    SplitSearchError,
+    CountHits, LeafSearchResponse, PartialHit, SearchRequest, SortByValue, SortOrder, SortValue,
+    SplitSearchError, SplitSearchProfile,
 };
 use serde::Deserialize;
 use tantivy::aggregation::agg_req::{get_fast_field_names, Aggregations};
@@ -35,11 +36,22 @@ use tantivy::aggregation::{AggregationLimits, AggregationSegmentCollector};
 use tantivy::collector::{Collector, SegmentCollector};
 use tantivy::columnar::{ColumnType, MonotonicallyMappableToU64};
 use tantivy::fastfield::Column;
-use tantivy::{DateTime, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+use tantivy::query::{Scorer, Weight};
+use tantivy::{
+    DateTime, DocId, DocSet, IndexSortByField, Order, Score, SegmentOrdinal, SegmentReader,
+    TantivyError, TERMINATED,
+};
 
 use crate::filters::{create_timestamp_filter_builder, TimestampFilter, TimestampFilterBuilder};
 use crate::find_trace_ids_collector::{FindTraceIdsCollector, FindTraceIdsSegmentCollector, Span};
-use crate::GlobalDocAddress;
+use crate::runtime_field_aggregations::{
+    IntermediateRuntimeAggregationResults, RuntimeFieldAggregationSegmentCollector,
+    RuntimeFieldAggregations,
+};
+use crate::runtime_fields::{
+    parse_runtime_fields, FastFieldColumns, RuntimeField, RuntimeFieldType, RuntimeValue,
+};
+use crate::{GlobalDocAddress, SearchError};
 
 #[derive(Clone, Debug)]
 pub(crate) enum SortByComponent {
@@ -50,6 +62,11 @@ pub(crate) enum SortByComponent {
         field_name: String,
         order: SortOrder,
     },
+    /// Runtime field computed from fast fields.
+    RuntimeField {
+        runtime_field: Arc<RuntimeField>,
+        order: SortOrder,
+    },
     Score {
         order: SortOrder,
     },
@@ -100,6 +117,19 @@ impl SortByComponent {
                     sort_field_type,
                 })
             }
+            SortByComponent::RuntimeField { runtime_field, .. } => {
+                let columns = FastFieldColumns::open(segment_reader, runtime_field.field_names())?;
+                let sort_field_type = match runtime_field.field_type() {
+                    RuntimeFieldType::Long => SortFieldType::I64,
+                    RuntimeFieldType::Date => SortFieldType::DateTime,
+                    _ => SortFieldType::F64,
+                };
+                Ok(SortingFieldExtractorComponent::RuntimeField {
+                    runtime_field: runtime_field.clone(),
+                    columns,
+                    sort_field_type,
+                })
+            }
             SortByComponent::Score { .. } => Ok(SortingFieldExtractorComponent::Score),
         }
     }
@@ -107,22 +137,26 @@ impl SortByComponent {
         match self {
             SortByComponent::DocId { .. } => false,
             SortByComponent::FastField { .. } => false,
+            SortByComponent::RuntimeField { .. } => false,
             SortByComponent::Score { .. } => true,
         }
     }
     pub fn add_fast_field(&self, set: &mut HashSet<String>) {
-        if let SortByComponent::FastField {
-            field_name,
-            order: _,
-        } = self
-        {
-            set.insert(field_name.clone());
+        match self {
+            SortByComponent::FastField { field_name, .. } => {
+                set.insert(field_name.clone());
+            }
+            SortByComponent::RuntimeField { runtime_field, .. } => {
+                set.extend(runtime_field.field_names());
+            }
+            SortByComponent::DocId { .. } | SortByComponent::Score { .. } => {}
         }
     }
     pub fn sort_order(&self) -> SortOrder {
         match self {
             SortByComponent::DocId { order } => *order,
             SortByComponent::FastField { order, .. } => *order,
+            SortByComponent::RuntimeField { order, .. } => *order,
             SortByComponent::Score { order } => *order,
         }
     }
@@ -146,21 +180,58 @@ enum SortingFieldExtractorComponent {
         sort_column: Column<u64>,
         sort_field_type: SortFieldType,
     },
+    /// Runtime field evaluated on the columns of the fast fields it references.
+    RuntimeField {
+        runtime_field: Arc<RuntimeField>,
+        columns: FastFieldColumns,
+        sort_field_type: SortFieldType,
+    },
     Score,
 }
 
 impl SortingFieldExtractorComponent {
+    /// Returns true if the sort values are loaded from fast fields, which includes runtime fields.
     fn is_fast_field(&self) -> bool {
-        matches!(self, SortingFieldExtractorComponent::FastField { .. })
+        matches!(
+            self,
+            SortingFieldExtractorComponent::FastField { .. }
+                | SortingFieldExtractorComponent::RuntimeField { .. }
+        )
+    }
+
+    /// Evaluates the runtime field for the given doc_id and returns its u64 representation.
+    fn extract_runtime_field_value(
+        runtime_field: &RuntimeField,
+        columns: &FastFieldColumns,
+        doc_id: DocId,
+    ) -> Option<u64> {
+        match runtime_field.eval(&|field_name| columns.value(field_name, doc_id)) {
+            RuntimeValue::I64(value) => Some(value.to_u64()),
+            RuntimeValue::F64(value) => Some(value.to_u64()),
+            RuntimeValue::DateTime(value) => Some(value.to_u64()),
+            _ => None,
+        }
     }
     /// Loads the fast field values for the given doc_ids in its u64 representation. The returned
     /// u64 representation maintains the ordering of the original value.
     #[inline]
     fn extract_typed_sort_values_block(&self, doc_ids: &[DocId], values: &mut [Option<u64>]) {
         // In the collect block case we don't have scores to extract
-        if let SortingFieldExtractorComponent::FastField { sort_column, .. } = self {
-            let values = &mut values[..doc_ids.len()];
-            sort_column.first_vals(doc_ids, values);
+        match self {
+            SortingFieldExtractorComponent::FastField { sort_column, .. } => {
+                let values = &mut values[..doc_ids.len()];
+                sort_column.first_vals(doc_ids, values);
+            }
+            SortingFieldExtractorComponent::RuntimeField {
+                runtime_field,
+                columns,
+                ..
+            } => {
+                for (doc_id, value) in doc_ids.iter().zip(values.iter_mut()) {
+                    *value = Self::extract_runtime_field_value(runtime_field, columns, *doc_id);
+                }
+            }
+            SortingFieldExtractorComponent::DocId | SortingFieldExtractorComponent::Score => {}
         }
     }
 
@@ -177,6 +248,11 @@ impl SortingFieldExtractorComponent {
             SortingFieldExtractorComponent::FastField { sort_column, .. } => {
                 sort_column.first(doc_id)
             }
+            SortingFieldExtractorComponent::RuntimeField {
+                runtime_field,
+                columns,
+                ..
+            } => Self::extract_runtime_field_value(runtime_field, columns, doc_id),
             SortingFieldExtractorComponent::Score { .. } => Some((score as f64).to_u64()),
         }
     }
@@ -199,6 +275,9 @@ impl SortingFieldExtractorComponent {
             SortingFieldExtractorComponent::DocId => SortValue::U64(sort_value),
             SortingFieldExtractorComponent::FastField {
                 sort_field_type, ..
+            }
+            | SortingFieldExtractorComponent::RuntimeField {
+                sort_field_type, ..
             } => map_fast_field_to_value(sort_value, *sort_field_type),
             SortingFieldExtractorComponent::Score => SortValue::F64(f64::from_u64(sort_value)),
         }
@@ -219,6 +298,9 @@ impl SortingFieldExtractorComponent {
             },
             SortingFieldExtractorComponent::FastField {
                 sort_field_type, ..
+            }
+            | SortingFieldExtractorComponent::RuntimeField {
+                sort_field_type, ..
             } => {
                 // We need to convert a (potential user provided) value in the correct u64
                 // representation of the fast field.
@@ -496,6 +578,7 @@ impl Eq for PartialHitHeapItem {}
 enum AggregationSegmentCollectors {
     FindTraceIdsSegmentCollector(Box<FindTraceIdsSegmentCollector>),
     TantivyAggregationSegmentCollector(AggregationSegmentCollector),
+    RuntimeFieldAggregationSegmentCollector(Box<RuntimeFieldAggregationSegmentCollector>),
 }
 
 /// Quickwit collector working at the scale of the segment.
@@ -517,6 +600,14 @@ impl QuickwitSegmentCollector {
         }
         true
     }
+
+    /// Returns true if no document left in the segment can make it into the top K.
+    fn is_complete(&self) -> bool {
+        self.segment_top_k_collector
+            .as_ref()
+            .map(|segment_top_k_collector| segment_top_k_collector.is_complete)
+            .unwrap_or(false)
+    }
 }
 
 /// Quickwit collector working at the scale of the segment.
@@ -531,6 +622,10 @@ struct QuickwitSegmentTopKCollector {
     precomp_search_after_order: Ordering,
     sort_values1: Box<[Option<u64>; 64]>,
     sort_values2: Box<[Option<u64>; 64]>,
+    // The documents of the segment are sorted by the first sort value, in the search sort order.
+    sorted_by_sort_value: bool,
+    // No document left in the segment can make it into the top K.
+    is_complete: bool,
 }
 
 /// Search After, but the sort values are converted to the u64 fast field representation.
@@ -594,7 +689,31 @@ impl SearchAfterSegment {
 }
 
 impl QuickwitSegmentTopKCollector {
+    /// When the segment is sorted by the first sort value, the documents coming after a document
+    /// ranked strictly worse than the worst hit of a full top K are ranked strictly worse too.
+    fn update_is_complete(&mut self, last_sort_value: Option<u64>) {
+        if !self.sorted_by_sort_value || last_sort_value.is_none() || !self.top_k_hits.at_capacity()
+        {
+            return;
+        }
+        let Some(worst_hit) = self.top_k_hits.peek_worst() else {
+            return;
+        };
+        let order = self.top_k_hits.sort_key_mapper.order1;
+        if order.compare_opt(&last_sort_value, &worst_hit.sort_value) == Ordering::Less {
+            self.is_complete = true;
+        }
+    }
+
     fn collect_top_k_block(&mut self, docs: &[DocId]) {
+        if self.is_complete || docs.is_empty() {
+            return;
+        }
+        self.collect_top_k_block_aux(docs);
+        self.update_is_complete(self.sort_values1[docs.len() - 1]);
+    }
+
+    fn collect_top_k_block_aux(&mut self, docs: &[DocId]) {
         self.score_extractor.extract_typed_sort_values(
             docs,
             &mut self.sort_values1[..],
@@ -721,6 +840,9 @@ impl QuickwitSegmentTopKCollector {
 
     #[inline]
     fn collect_top_k(&mut self, doc_id: DocId, score: Score) {
+        if self.is_complete {
+            return;
+        }
         let (sort_value, sort_value2): (Option<u64>, Option<u64>) =
             self.score_extractor.extract_typed_sort_value(doc_id, score);
         Self::collect_top_k_vals(
@@ -731,6 +853,7 @@ impl QuickwitSegmentTopKCollector {
             self.precomp_search_after_order,
             &mut self.top_k_hits,
         );
+        self.update_is_complete(sort_value);
     }
 }
 
@@ -831,6 +954,9 @@ impl SegmentCollector for QuickwitSegmentCollector {
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 collector.collect_block(filtered_docs)
             }
+            Some(AggregationSegmentCollectors::RuntimeFieldAggregationSegmentCollector(
+                collector,
+            )) => collector.collect_block(filtered_docs),
             None => (),
         }
     }
@@ -853,6 +979,9 @@ impl SegmentCollector for QuickwitSegmentCollector {
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 collector.collect(doc_id, score)
             }
+            Some(AggregationSegmentCollectors::RuntimeFieldAggregationSegmentCollector(
+                collector,
+            )) => collector.collect(doc_id, score),
             None => (),
         }
     }
@@ -888,6 +1017,13 @@ impl SegmentCollector for QuickwitSegmentCollector {
                     .expect("Collector fruit should be serializable.");
                 Some(serialized)
             }
+            Some(AggregationSegmentCollectors::RuntimeFieldAggregationSegmentCollector(
+                collector,
+            )) => {
+                let serialized = postcard::to_allocvec(&collector.harvest()?)
+                    .expect("Collector fruit should be serializable.");
+                Some(serialized)
+            }
             None => None,
         };
         Ok(LeafSearchResponse {
@@ -896,6 +1032,7 @@ impl SegmentCollector for QuickwitSegmentCollector {
             partial_hits,
             failed_splits: Vec::new(),
             num_attempted_splits: 1,
+            split_profiles: Vec::new(),
         })
     }
 }
@@ -909,10 +1046,14 @@ pub enum QuickwitAggregations {
     FindTraceIdsAggregation(FindTraceIdsCollector),
     /// Your classic Tantivy aggregation.
     TantivyAggregations(Aggregations),
+    /// Aggregation referencing runtime fields, which Tantivy cannot read from the fast fields.
+    /// It is built by [`aggregations_from_request`] and never deserialized directly.
+    #[serde(skip)]
+    RuntimeFieldAggregations(RuntimeFieldAggregations),
 }
 
 impl QuickwitAggregations {
-    fn fast_field_names(&self) -> HashSet<String> {
+    pub(crate) fn fast_field_names(&self) -> HashSet<String> {
         match self {
             QuickwitAggregations::FindTraceIdsAggregation(collector) => {
                 collector.fast_field_names()
@@ -920,6 +1061,9 @@ impl QuickwitAggregations {
             QuickwitAggregations::TantivyAggregations(aggregations) => {
                 get_fast_field_names(aggregations)
             }
+            QuickwitAggregations::RuntimeFieldAggregations(aggregations) => {
+                aggregations.fast_field_names()
+            }
         }
     }
 
@@ -931,14 +1075,52 @@ impl QuickwitAggregations {
             QuickwitAggregations::TantivyAggregations(aggreg) => {
                 QuickwitIncrementalAggregations::TantivyAggregations(aggreg.clone(), Vec::new())
             }
+            QuickwitAggregations::RuntimeFieldAggregations(aggreg) => {
+                QuickwitIncrementalAggregations::RuntimeFieldAggregations(
+                    aggreg.clone(),
+                    Vec::new(),
+                )
+            }
         }
     }
 }
 
+/// Parses the aggregations of a search request. Aggregations referencing runtime fields are
+/// evaluated by Quickwit rather than Tantivy.
+pub(crate) fn aggregations_from_request(
+    search_request: &SearchRequest,
+) -> crate::Result<Option<QuickwitAggregations>> {
+    let Some(aggregations_json) = &search_request.aggregation_request else {
+        return Ok(None);
+    };
+    let aggregations: QuickwitAggregations = serde_json::from_str(aggregations_json)?;
+
+    if search_request.runtime_mappings.is_empty()
+        || !matches!(aggregations, QuickwitAggregations::TantivyAggregations(_))
+    {
+        return Ok(Some(aggregations));
+    }
+    let runtime_fields = parse_runtime_fields(&search_request.runtime_mappings)?;
+    let fast_field_names = aggregations.fast_field_names();
+
+    if runtime_fields
+        .iter()
+        .any(|runtime_field| fast_field_names.contains(runtime_field.name()))
+    {
+        let runtime_field_aggregations =
+            RuntimeFieldAggregations::try_new(aggregations_json, runtime_fields)?;
+        return Ok(Some(QuickwitAggregations::RuntimeFieldAggregations(
+            runtime_field_aggregations,
+        )));
+    }
+    Ok(Some(aggregations))
+}
+
 #[derive(Clone)]
 enum QuickwitIncrementalAggregations {
     FindTraceIdsAggregation(FindTraceIdsCollector, Vec<Vec<Span>>),
     TantivyAggregations(Aggregations, Vec<Vec<u8>>),
+    RuntimeFieldAggregations(RuntimeFieldAggregations, Vec<Vec<u8>>),
     NoAggregation,
 }
 
@@ -954,7 +1136,8 @@ impl QuickwitIncrementalAggregations {
                     state.push(new_state);
                 }
             }
-            QuickwitIncrementalAggregations::TantivyAggregations(_, state) => {
+            QuickwitIncrementalAggregations::TantivyAggregations(_, state)
+            | QuickwitIncrementalAggregations::RuntimeFieldAggregations(_, state) => {
                 state.push(intermediate_result);
             }
             QuickwitIncrementalAggregations::NoAggregation => (),
@@ -984,6 +1167,7 @@ impl QuickwitIncrementalAggregations {
                 None
             }
             QuickwitIncrementalAggregations::TantivyAggregations(_, _) => None,
+            QuickwitIncrementalAggregations::RuntimeFieldAggregations(_, _) => None,
             QuickwitIncrementalAggregations::NoAggregation => None,
         }
     }
@@ -1005,6 +1189,12 @@ impl QuickwitIncrementalAggregations {
                     state.iter().map(|vec| vec.as_slice()),
                 )
             }
+            QuickwitIncrementalAggregations::RuntimeFieldAggregations(aggregation, state) => {
+                merge_intermediate_aggregation_result(
+                    &Some(QuickwitAggregations::RuntimeFieldAggregations(aggregation)),
+                    state.iter().map(|vec| vec.as_slice()),
+                )
+            }
             QuickwitIncrementalAggregations::NoAggregation => Ok(None),
         }
     }
@@ -1024,9 +1214,37 @@ pub(crate) struct QuickwitCollector {
     pub aggregation: Option<QuickwitAggregations>,
     pub aggregation_limits: AggregationLimits,
     search_after: Option<PartialHit>,
+    // The documents of the split are sorted by the first sort field, in the search sort order.
+    sorted_by_sort_field: bool,
+    // The number of hits must be exact.
+    count_all: bool,
 }
 
 impl QuickwitCollector {
+    /// Informs the collector of the field the documents of the split are sorted by, so that it
+    /// can stop collecting hits early when the search is sorted by the same field and order.
+    pub fn set_index_sort_by_field(&mut self, index_sort_by_field_opt: Option<&IndexSortByField>) {
+        self.sorted_by_sort_field = match (index_sort_by_field_opt, &self.sort_by.first) {
+            (Some(index_sort_by_field), SortByComponent::FastField { field_name, order }) => {
+                index_sort_by_field.field == *field_name
+                    && matches!(
+                        (&index_sort_by_field.order, order),
+                        (Order::Asc, SortOrder::Asc) | (Order::Desc, SortOrder::Desc)
+                    )
+            }
+            _ => false,
+        };
+    }
+
+    /// Returns true if the collection of a segment can stop as soon as no document left can make
+    /// it into the top K. The number of hits is then an underestimate.
+    fn can_terminate_early(&self) -> bool {
+        self.sorted_by_sort_field
+            && !self.count_all
+            && self.aggregation.is_none()
+            && self.max_hits + self.start_offset > 0
+    }
+
     pub fn fast_field_names(&self) -> HashSet<String> {
         let mut fast_field_names = HashSet::default();
         self.sort_by.first.add_fast_field(&mut fast_field_names);
@@ -1084,6 +1302,11 @@ impl Collector for QuickwitCollector {
                     )?,
                 ),
             ),
+            Some(QuickwitAggregations::RuntimeFieldAggregations(aggs)) => Some(
+                AggregationSegmentCollectors::RuntimeFieldAggregationSegmentCollector(Box::new(
+                    aggs.for_segment(segment_reader)?,
+                )),
+            ),
             None => None,
         };
         let score_extractor = get_score_extractor(&self.sort_by, segment_reader)?;
@@ -1113,6 +1336,8 @@ impl Collector for QuickwitCollector {
                 precomp_search_after_order,
                 sort_values1: Box::new([None; 64]),
                 sort_values2: Box::new([None; 64]),
+                sorted_by_sort_value: self.sorted_by_sort_field,
+                is_complete: false,
             })
         };
 
@@ -1126,6 +1351,71 @@ impl Collector for QuickwitCollector {
         })
     }
 
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<tantivy::Result<LeafSearchResponse>> {
+        let requires_scoring = self.requires_scoring();
+        let mut segment_collector = self.for_segment(segment_ord, segment_reader)?;
+        let alive_bitset_opt = segment_reader.alive_bitset();
+
+        if self.can_terminate_early() {
+            // The documents are visited one by one in doc id order, that is in sort order, so
+            // that we can stop as soon as the top K is known.
+            let mut scorer = weight.scorer(segment_reader, 1.0)?;
+            let mut doc = scorer.doc();
+            while doc != TERMINATED {
+                let is_alive = alive_bitset_opt
+                    .map(|alive_bitset| alive_bitset.is_alive(doc))
+                    .unwrap_or(true);
+                if is_alive {
+                    let score = if requires_scoring {
+                        scorer.score()
+                    } else {
+                        0.0
+                    };
+                    segment_collector.collect(doc, score);
+                    if segment_collector.is_complete() {
+                        break;
+                    }
+                }
+                doc = scorer.advance();
+            }
+            return Ok(segment_collector.harvest());
+        }
+        match (alive_bitset_opt, requires_scoring) {
+            (Some(alive_bitset), true) => {
+                weight.for_each(segment_reader, &mut |doc, score| {
+                    if alive_bitset.is_alive(doc) {
+                        segment_collector.collect(doc, score);
+                    }
+                })?;
+            }
+            (Some(alive_bitset), false) => {
+                weight.for_each_no_score(segment_reader, &mut |docs| {
+                    for doc in docs.iter().cloned() {
+                        if alive_bitset.is_alive(doc) {
+                            segment_collector.collect(doc, 0.0);
+                        }
+                    }
+                })?;
+            }
+            (None, true) => {
+                weight.for_each(segment_reader, &mut |doc, score| {
+                    segment_collector.collect(doc, score);
+                })?;
+            }
+            (None, false) => {
+                weight.for_each_no_score(segment_reader, &mut |docs| {
+                    segment_collector.collect_block(docs);
+                })?;
+            }
+        }
+        Ok(segment_collector.harvest())
+    }
+
     fn requires_scoring(&self) -> bool {
         // We do not need BM25 scoring in Quickwit if it is not opted-in.
         // By returning false, we inform tantivy that it does not need to decompress
@@ -1177,7 +1467,7 @@ fn map_error(err: postcard::Error) -> TantivyError {
 }
 
 /// Merges a set of Leaf Results.
-fn merge_intermediate_aggregation_result<'a>(
+pub(crate) fn merge_intermediate_aggregation_result<'a>(
     aggregations_opt: &Option<QuickwitAggregations>,
     intermediate_aggregation_results: impl Iterator<Item = &'a [u8]>,
 ) -> tantivy::Result<Option<Vec<u8>>> {
@@ -1214,6 +1504,21 @@ fn merge_intermediate_aggregation_result<'a>(
                 None
             }
         }
+        Some(QuickwitAggregations::RuntimeFieldAggregations(aggregations)) => {
+            let fruits: Vec<IntermediateRuntimeAggregationResults> =
+                intermediate_aggregation_results
+                    .map(|intermediate_aggregation_result| {
+                        postcard::from_bytes(intermediate_aggregation_result).map_err(map_error)
+                    })
+                    .collect::<Result<_, _>>()?;
+            if fruits.is_empty() {
+                None
+            } else {
+                let merged_fruit = aggregations.merge_fruits(fruits);
+                let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
+                Some(serialized)
+            }
+        }
         None => None,
     };
 
@@ -1253,6 +1558,10 @@ fn merge_leaf_responses(
         .flat_map(|leaf_response| leaf_response.failed_splits.iter())
         .cloned()
         .collect_vec();
+    let split_profiles = leaf_responses
+        .iter_mut()
+        .flat_map(|leaf_response| std::mem::take(&mut leaf_response.split_profiles))
+        .collect_vec();
     let all_partial_hits: Vec<PartialHit> = leaf_responses
         .into_iter()
         .flat_map(|leaf_response| leaf_response.partial_hits)
@@ -1269,6 +1578,7 @@ fn merge_leaf_responses(
         partial_hits: top_k_partial_hits,
         failed_splits,
         num_attempted_splits,
+        split_profiles,
     })
 }
 
@@ -1290,12 +1600,36 @@ fn top_k_partial_hits(
     top_k_hits.finalize()
 }
 
-pub(crate) fn sort_by_from_request(search_request: &SearchRequest) -> SortByPair {
+pub(crate) fn sort_by_from_request(search_request: &SearchRequest) -> crate::Result<SortByPair> {
+    let runtime_fields = parse_runtime_fields(&search_request.runtime_mappings)?;
+
+    for sort_field in &search_request.sort_fields {
+        let runtime_field_opt = runtime_fields
+            .iter()
+            .find(|runtime_field| runtime_field.name() == sort_field.field_name);
+        if let Some(runtime_field) = runtime_field_opt {
+            if !runtime_field.field_type().is_sortable() {
+                return Err(SearchError::InvalidArgument(format!(
+                    "sort by runtime field is only supported for `long`, `double`, and `date` \
+                     runtime fields, `{}` is not",
+                    runtime_field.name()
+                )));
+            }
+        }
+    }
     let to_sort_by_component = |field_name: &str, order| {
         if field_name == "_score" {
             SortByComponent::Score { order }
         } else if field_name == "_shard_doc" || field_name == "_doc" {
             SortByComponent::DocId { order }
+        } else if let Some(runtime_field) = runtime_fields
+            .iter()
+            .find(|runtime_field| runtime_field.name() == field_name)
+        {
+            SortByComponent::RuntimeField {
+                runtime_field: Arc::new(runtime_field.clone()),
+                order,
+            }
         } else {
             SortByComponent::FastField {
                 field_name: field_name.to_string(),
@@ -1305,7 +1639,7 @@ pub(crate) fn sort_by_from_request(search_request: &SearchRequest) -> SortByPair
     };
 
     let num_sort_fields = search_request.sort_fields.len();
-    if num_sort_fields == 0 {
+    let sort_by = if num_sort_fields == 0 {
         SortByComponent::DocId {
             order: SortOrder::Desc,
         }
@@ -1325,7 +1659,8 @@ pub(crate) fn sort_by_from_request(search_request: &SearchRequest) -> SortByPair
         }
     } else {
         panic!("Sort by more than 2 fields is not supported yet.")
-    }
+    };
+    Ok(sort_by)
 }
 
 /// Builds the QuickwitCollector, in function of the information that was requested by the user.
@@ -1335,16 +1670,13 @@ pub(crate) fn make_collector_for_split(
     search_request: &SearchRequest,
     aggregation_limits: AggregationLimits,
 ) -> crate::Result<QuickwitCollector> {
-    let aggregation = match &search_request.aggregation_request {
-        Some(aggregation) => Some(serde_json::from_str(aggregation)?),
-        None => None,
-    };
+    let aggregation = aggregations_from_request(search_request)?;
     let timestamp_filter_builder_opt = create_timestamp_filter_builder(
         doc_mapper.timestamp_field_name(),
         search_request.start_timestamp,
         search_request.end_timestamp,
     );
-    let sort_by = sort_by_from_request(search_request);
+    let sort_by = sort_by_from_request(search_request)?;
     Ok(QuickwitCollector {
         split_id,
         start_offset: search_request.start_offset as usize,
@@ -1354,6 +1686,8 @@ pub(crate) fn make_collector_for_split(
         aggregation,
         aggregation_limits,
         search_after: search_request.search_after.clone(),
+        sorted_by_sort_field: false,
+        count_all: search_request.count_hits() == CountHits::CountAll,
     })
 }
 
@@ -1362,11 +1696,8 @@ pub(crate) fn make_merge_collector(
     search_request: &SearchRequest,
     aggregation_limits: &AggregationLimits,
 ) -> crate::Result<QuickwitCollector> {
-    let aggregation = match &search_request.aggregation_request {
-        Some(aggregation) => Some(serde_json::from_str(aggregation)?),
-        None => None,
-    };
-    let sort_by = sort_by_from_request(search_request);
+    let aggregation = aggregations_from_request(search_request)?;
+    let sort_by = sort_by_from_request(search_request)?;
     Ok(QuickwitCollector {
         split_id: String::default(),
         start_offset: search_request.start_offset as usize,
@@ -1376,6 +1707,8 @@ pub(crate) fn make_merge_collector(
         aggregation,
         aggregation_limits: aggregation_limits.clone(),
         search_after: search_request.search_after.clone(),
+        sorted_by_sort_field: false,
+        count_all: search_request.count_hits() == CountHits::CountAll,
     })
 }
 
@@ -1499,6 +1832,7 @@ pub(crate) struct IncrementalCollector {
     num_hits: u64,
     failed_splits: Vec<SplitSearchError>,
     num_attempted_splits: u64,
+    split_profiles: Vec<SplitSearchProfile>,
 }
 
 impl IncrementalCollector {
@@ -1518,6 +1852,7 @@ impl IncrementalCollector {
             num_hits: 0,
             failed_splits: Vec::new(),
             num_attempted_splits: 0,
+            split_profiles: Vec::new(),
         }
     }
 
@@ -1529,12 +1864,14 @@ impl IncrementalCollector {
             failed_splits,
             num_attempted_splits,
             intermediate_aggregation_result,
+            split_profiles,
         } = leaf_response;
 
         self.num_hits += num_hits;
         self.top_k_hits.add_entries(partial_hits.into_iter());
         self.failed_splits.extend(failed_splits);
         self.num_attempted_splits += num_attempted_splits;
+        self.split_profiles.extend(split_profiles);
         if let Some(intermediate_aggregation_result) = intermediate_aggregation_result {
             self.incremental_aggregation
                 .add(intermediate_aggregation_result)?;
@@ -1578,6 +1915,7 @@ impl IncrementalCollector {
             failed_splits: self.failed_splits,
             num_attempted_splits: self.num_attempted_splits,
             intermediate_aggregation_result,
+            split_profiles: self.split_profiles,
         })
     }
 }
@@ -1587,8 +1925,8 @@ mod tests {
     use std::cmp::Ordering;
 
     use quickwit_proto::search::{
-        LeafSearchResponse, PartialHit, SearchRequest, SortByValue, SortField, SortOrder,
-        SortValue, SplitSearchError,
+        CountHits, LeafSearchResponse, PartialHit, SearchRequest, SortByValue, SortField,
+        SortOrder, SortValue, SplitSearchError,
     };
     use tantivy::collector::Collector;
     use tantivy::TantivyDocument;
@@ -1974,6 +2312,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sort_by_index_sort_field_terminates_early() {
+        use tantivy::schema::{NumericOptions, Schema};
+        use tantivy::{IndexBuilder, IndexSettings, IndexSortByField, Order};
+
+        let mut schema_builder = Schema::builder();
+        let field = schema_builder.add_u64_field("sort1", NumericOptions::default().set_fast());
+        let schema = schema_builder.build();
+        let index_settings = IndexSettings {
+            sort_by_field: Some(IndexSortByField {
+                field: "sort1".to_string(),
+                order: Order::Desc,
+            }),
+            ..Default::default()
+        };
+        let index = IndexBuilder::new()
+            .schema(schema)
+            .settings(index_settings)
+            .create_in_ram()
+            .unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for val in [3, 7, 1, 9, 4, 7, 0, 5] {
+            let mut doc = TantivyDocument::new();
+            doc.add_u64(field, val);
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+
+        // The number of hits counts the documents visited: with an underestimated count, the
+        // collection stops at the first document ranked strictly worse than the top 3.
+        for (sort_str, count_hits, expected_num_hits, expected_sort_values) in [
+            ("sort1", CountHits::Underestimate, 4, [9, 7, 7]),
+            ("sort1", CountHits::CountAll, 8, [9, 7, 7]),
+            ("-sort1", CountHits::Underestimate, 8, [0, 1, 3]),
+        ] {
+            let mut request = make_request(3, sort_str);
+            request.set_count_hits(count_hits);
+            let mut collector = super::make_collector_for_split(
+                "fake_split_id".to_string(),
+                &MockDocMapper,
+                &request,
+                Default::default(),
+            )
+            .unwrap();
+            collector.set_index_sort_by_field(index.settings().sort_by_field.as_ref());
+            assert_eq!(collector.sorted_by_sort_field, sort_str == "sort1");
+
+            let res = searcher
+                .search(&tantivy::query::AllQuery, &collector)
+                .unwrap();
+            assert_eq!(res.num_hits, expected_num_hits);
+            let sort_values: Vec<u64> = res
+                .partial_hits
+                .iter()
+                .map(
+                    |partial_hit| match partial_hit.sort_value.unwrap().sort_value {
+                        Some(SortValue::U64(val)) => val,
+                        _ => panic!("expected a u64 sort value"),
+                    },
+                )
+                .collect();
+            assert_eq!(sort_values, expected_sort_values);
+        }
+    }
+
     #[test]
     fn test_search_after() {
         let index = make_index();
@@ -2158,6 +2562,7 @@ mod tests {
                 failed_splits: Vec::new(),
                 num_attempted_splits: 3,
                 intermediate_aggregation_result: None,
+                split_profiles: Vec::new(),
             }],
         );
 
@@ -2174,7 +2579,8 @@ mod tests {
                 }],
                 failed_splits: Vec::new(),
                 num_attempted_splits: 3,
-                intermediate_aggregation_result: None
+                intermediate_aggregation_result: None,
+                split_profiles: Vec::new(),
             }
         );
 
@@ -2212,6 +2618,7 @@ mod tests {
                     failed_splits: Vec::new(),
                     num_attempted_splits: 3,
                     intermediate_aggregation_result: None,
+                    split_profiles: Vec::new(),
                 },
                 LeafSearchResponse {
                     num_hits: 10,
@@ -2229,6 +2636,7 @@ mod tests {
                     }],
                     num_attempted_splits: 2,
                     intermediate_aggregation_result: None,
+                    split_profiles: Vec::new(),
                 },
             ],
         );
@@ -2259,7 +2667,8 @@ mod tests {
                     retryable_error: true,
                 }],
                 num_attempted_splits: 5,
-                intermediate_aggregation_result: None
+                intermediate_aggregation_result: None,
+                split_profiles: Vec::new(),
             }
         );
 
@@ -2298,6 +2707,7 @@ mod tests {
                     failed_splits: Vec::new(),
                     num_attempted_splits: 3,
                     intermediate_aggregation_result: None,
+                    split_profiles: Vec::new(),
                 },
                 LeafSearchResponse {
                     num_hits: 10,
@@ -2315,6 +2725,7 @@ mod tests {
                     }],
                     num_attempted_splits: 2,
                     intermediate_aggregation_result: None,
+                    split_profiles: Vec::new(),
                 },
             ],
         );
@@ -2345,7 +2756,8 @@ mod tests {
                     retryable_error: true,
                 }],
                 num_attempted_splits: 5,
-                intermediate_aggregation_result: None
+                intermediate_aggregation_result: None,
+                split_profiles: Vec::new(),
             }
         );
         // TODO would be nice to test aggregation too.
@@ -46,6 +46,8 @@ pub enum SearchError {
     StorageResolver(#[from] StorageResolverError),
     #[error("request timed out: {0}")]
     Timeout(String),
+    #[error("too many requests: {0}")]
+    TooManyRequests(String),
     #[error("service unavailable: {0}")]
     Unavailable(String),
 }
@@ -60,6 +62,7 @@ impl ServiceError for SearchError {
             Self::InvalidQuery(_) => ServiceErrorCode::BadRequest,
             Self::StorageResolver(_) => ServiceErrorCode::Internal,
             Self::Timeout(_) => ServiceErrorCode::Timeout,
+            Self::TooManyRequests(_) => ServiceErrorCode::TooManyRequests,
             Self::Unavailable(_) => ServiceErrorCode::Unavailable,
         }
     }
@@ -34,6 +34,7 @@ use tantivy::{ReloadPolicy, Score, Searcher, SnippetGenerator, Term};
 use tracing::{error, Instrument};
 
 use crate::leaf::open_index_with_caches;
+use crate::runtime_fields::{add_runtime_fields_to_doc, extract_date_values, RuntimeField};
 use crate::service::SearcherContext;
 use crate::{convert_document_to_json_string, GlobalDocAddress};
 
@@ -48,6 +49,7 @@ async fn fetch_docs_to_map(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<dyn DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
+    runtime_fields: &[RuntimeField],
 ) -> anyhow::Result<HashMap<GlobalDocAddress, Document>> {
     let mut split_fetch_docs_futures = Vec::new();
 
@@ -75,6 +77,7 @@ async fn fetch_docs_to_map(
             split_and_offset,
             doc_mapper.clone(),
             snippet_request_opt,
+            runtime_fields,
         ));
     }
 
@@ -107,7 +110,8 @@ async fn fetch_docs_to_map(
 ///
 /// This function takes a list of partial hits (possibly from different splits)
 /// and the storage associated to an index, fetches the document from
-/// the split document stores, and returns the full hits.
+/// the split document stores, and returns the full hits. The runtime fields
+/// are computed and added to each document.
 pub async fn fetch_docs(
     searcher_context: Arc<SearcherContext>,
     partial_hits: Vec<PartialHit>,
@@ -115,6 +119,7 @@ pub async fn fetch_docs(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<dyn DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
+    runtime_fields: &[RuntimeField],
 ) -> anyhow::Result<FetchDocsResponse> {
     let global_doc_addrs: Vec<GlobalDocAddress> = partial_hits
         .iter()
@@ -128,6 +133,7 @@ pub async fn fetch_docs(
         splits,
         doc_mapper,
         snippet_request_opt,
+        runtime_fields,
     )
     .await?;
 
@@ -168,6 +174,7 @@ async fn fetch_docs_in_split(
     split: &SplitIdAndFooterOffsets,
     doc_mapper: Arc<dyn DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
+    runtime_fields: &[RuntimeField],
 ) -> anyhow::Result<Vec<(GlobalDocAddress, Document)>> {
     global_doc_addrs.sort_by_key(|doc| doc.doc_addr);
     // Opens the index without the ephemeral unbounded cache, this cache is indeed not useful
@@ -178,6 +185,7 @@ async fn fetch_docs_in_split(
         split,
         Some(doc_mapper.tokenizer_manager()),
         false,
+        None,
     )
     .await
     .context("open-index-for-split")?;
@@ -205,8 +213,14 @@ async fn fetch_docs_in_split(
                 .context("searcher-doc-async")?;
 
             let named_field_doc = doc.to_named_doc(moved_searcher.schema());
-            let content_json =
-                convert_document_to_json_string(named_field_doc, &*moved_doc_mapper)?;
+            let content_json = if runtime_fields.is_empty() {
+                convert_document_to_json_string(named_field_doc, &*moved_doc_mapper)?
+            } else {
+                let date_values = extract_date_values(&named_field_doc);
+                let mut doc_json_map = moved_doc_mapper.doc_to_json(named_field_doc.0)?;
+                add_runtime_fields_to_doc(runtime_fields, &date_values, &mut doc_json_map);
+                serde_json::to_string(&doc_json_map)?
+            };
             if fields_snippet_generator_opt_clone.is_none() {
                 return Ok((
                     global_doc_addr,
@@ -0,0 +1,521 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+
+use quickwit_common::bloom_filter::BloomFilter;
+use quickwit_metastore::FieldStats;
+use quickwit_query::query_ast::{
+    BoolQuery, FullTextMode, FullTextQuery, QueryAst, RangeQuery, TermQuery, TermSetQuery,
+};
+use quickwit_query::tokenizers::DEFAULT_REMOVE_TOKEN_LENGTH;
+use quickwit_query::{InterpretUserInput, JsonLiteral};
+use tantivy::schema::{FieldType, Schema};
+use tantivy::DateTime;
+
+/// Statistics on the values of the fields of a split, against which the term and range queries
+/// are evaluated.
+trait SplitStats {
+    /// Returns `false` if no document of the split holds `value` in `field`.
+    fn term_may_match(&self, field: &str, value: &str) -> bool;
+
+    /// Returns `false` if no document of the split holds a value within the bounds in `field`.
+    fn range_may_match(
+        &self,
+        field: &str,
+        lower_bound: &Bound<JsonLiteral>,
+        upper_bound: &Bound<JsonLiteral>,
+    ) -> bool;
+}
+
+/// Returns `false` if the field statistics of a split guarantee that none of its documents
+/// matches the query.
+///
+/// The check is conservative: the queries that cannot be evaluated against the statistics,
+/// or that target fields without statistics, are assumed to match.
+pub(crate) fn split_may_match(
+    query_ast: &QueryAst,
+    field_stats: &BTreeMap<String, FieldStats>,
+) -> bool {
+    if field_stats.is_empty() {
+        return true;
+    }
+    query_may_match(query_ast, field_stats)
+}
+
+/// Returns `false` if the bloom filters of a split guarantee that none of its documents matches
+/// the query. The values of the `bytes` fields are looked up after decoding their hex or base64
+/// representation.
+pub(crate) fn split_may_match_bloom_filters(
+    query_ast: &QueryAst,
+    bloom_filters: &BTreeMap<String, BloomFilter>,
+    schema: &Schema,
+) -> bool {
+    if bloom_filters.is_empty() {
+        return true;
+    }
+    query_may_match(
+        query_ast,
+        &SplitBloomFilters {
+            bloom_filters,
+            schema,
+        },
+    )
+}
+
+/// Returns `true` if the bloom filters of the given fields can prune splits for this query, that
+/// is to say if a split holding none of the terms of these fields cannot match the query. This
+/// lets searchers skip loading the bloom filters when they are of no use.
+pub(crate) fn bloom_filters_may_prune(
+    query_ast: &QueryAst,
+    bloom_filter_field_names: &BTreeSet<String>,
+) -> bool {
+    if bloom_filter_field_names.is_empty() {
+        return false;
+    }
+    !query_may_match(query_ast, &NoTerms(bloom_filter_field_names))
+}
+
+fn query_may_match(query_ast: &QueryAst, split_stats: &dyn SplitStats) -> bool {
+    match query_ast {
+        QueryAst::Bool(BoolQuery {
+            must,
+            should,
+            filter,
+            ..
+        }) => {
+            if !must
+                .iter()
+                .chain(filter)
+                .all(|clause| query_may_match(clause, split_stats))
+            {
+                return false;
+            }
+            // Should clauses are optional as soon as there is a must or filter clause.
+            if must.is_empty() && filter.is_empty() && !should.is_empty() {
+                return should
+                    .iter()
+                    .any(|clause| query_may_match(clause, split_stats));
+            }
+            true
+        }
+        QueryAst::Term(TermQuery { field, value }) => split_stats.term_may_match(field, value),
+        QueryAst::TermSet(TermSetQuery { terms_per_field }) => {
+            terms_per_field.is_empty()
+                || terms_per_field.iter().any(|(field, terms)| {
+                    terms
+                        .iter()
+                        .any(|term| split_stats.term_may_match(field, term))
+                })
+        }
+        // On the fields supporting statistics, a full text query without a custom tokenizer
+        // boils down to a term query, unless the text is dropped by the tokenizer.
+        QueryAst::FullText(FullTextQuery {
+            field,
+            text,
+            params,
+        }) if params.tokenizer.is_none()
+            && !matches!(params.mode, FullTextMode::BoolPrefix { .. })
+            && !text.is_empty()
+            && text.len() < DEFAULT_REMOVE_TOKEN_LENGTH =>
+        {
+            split_stats.term_may_match(field, text)
+        }
+        QueryAst::Range(RangeQuery {
+            field,
+            lower_bound,
+            upper_bound,
+        }) => split_stats.range_may_match(field, lower_bound, upper_bound),
+        QueryAst::Boost { underlying, .. } => query_may_match(underlying, split_stats),
+        _ => true,
+    }
+}
+
+impl SplitStats for BTreeMap<String, FieldStats> {
+    fn term_may_match(&self, field: &str, value: &str) -> bool {
+        let Some(stats) = self.get(field) else {
+            return true;
+        };
+        let bound = Bound::Included(JsonLiteral::String(value.to_string()));
+        range_may_match(stats, &bound, &bound)
+    }
+
+    fn range_may_match(
+        &self,
+        field: &str,
+        lower_bound: &Bound<JsonLiteral>,
+        upper_bound: &Bound<JsonLiteral>,
+    ) -> bool {
+        let Some(stats) = self.get(field) else {
+            return true;
+        };
+        range_may_match(stats, lower_bound, upper_bound)
+    }
+}
+
+struct SplitBloomFilters<'a> {
+    bloom_filters: &'a BTreeMap<String, BloomFilter>,
+    schema: &'a Schema,
+}
+
+impl SplitStats for SplitBloomFilters<'_> {
+    fn term_may_match(&self, field: &str, value: &str) -> bool {
+        let Some(bloom_filter) = self.bloom_filters.get(field) else {
+            return true;
+        };
+        let is_bytes_field = self.schema.get_field(field).is_ok_and(|field| {
+            matches!(
+                self.schema.get_field_entry(field).field_type(),
+                FieldType::Bytes(_)
+            )
+        });
+        if is_bytes_field {
+            // Bytes values are expressed in hex or base64 in the query.
+            return Vec::<u8>::interpret_str(value)
+                .map(|bytes| bloom_filter.contains(&bytes))
+                .unwrap_or(true);
+        }
+        bloom_filter.contains(value.as_bytes())
+    }
+
+    fn range_may_match(&self, _: &str, _: &Bound<JsonLiteral>, _: &Bound<JsonLiteral>) -> bool {
+        true
+    }
+}
+
+/// Statistics of a split holding none of the terms of the given fields.
+struct NoTerms<'a>(&'a BTreeSet<String>);
+
+impl SplitStats for NoTerms<'_> {
+    fn term_may_match(&self, field: &str, _value: &str) -> bool {
+        !self.0.contains(field)
+    }
+
+    fn range_may_match(&self, _: &str, _: &Bound<JsonLiteral>, _: &Bound<JsonLiteral>) -> bool {
+        true
+    }
+}
+
+fn range_may_match(
+    stats: &FieldStats,
+    lower_bound: &Bound<JsonLiteral>,
+    upper_bound: &Bound<JsonLiteral>,
+) -> bool {
+    match stats {
+        FieldStats::I64Range { min, max } => intersects(
+            *min,
+            *max,
+            interpret_bound::<i64>(lower_bound),
+            interpret_bound::<i64>(upper_bound),
+        ),
+        FieldStats::U64Range { min, max } => intersects(
+            *min,
+            *max,
+            interpret_bound::<u64>(lower_bound),
+            interpret_bound::<u64>(upper_bound),
+        ),
+        FieldStats::F64Range { min, max } => intersects(
+            *min,
+            *max,
+            interpret_bound::<f64>(lower_bound),
+            interpret_bound::<f64>(upper_bound),
+        ),
+        FieldStats::DateRange { min, max } => intersects(
+            *min,
+            *max,
+            date_bound_to_nanos(interpret_bound::<DateTime>(lower_bound)),
+            date_bound_to_nanos(interpret_bound::<DateTime>(upper_bound)),
+        ),
+    }
+}
+
+/// Interprets a bound of the query. A bound that cannot be interpreted as a `T` is considered
+/// unbounded.
+fn interpret_bound<'a, T: InterpretUserInput<'a>>(bound: &'a Bound<JsonLiteral>) -> Bound<T> {
+    match bound {
+        Bound::Included(literal) => T::interpret_json(literal)
+            .map(Bound::Included)
+            .unwrap_or(Bound::Unbounded),
+        Bound::Excluded(literal) => T::interpret_json(literal)
+            .map(Bound::Excluded)
+            .unwrap_or(Bound::Unbounded),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+fn date_bound_to_nanos(bound: Bound<DateTime>) -> Bound<i64> {
+    match bound {
+        Bound::Included(date_time) => Bound::Included(date_time.into_timestamp_nanos()),
+        Bound::Excluded(date_time) => Bound::Excluded(date_time.into_timestamp_nanos()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Returns `true` if some value of the `[min, max]` interval is within the bounds.
+fn intersects<T: PartialOrd>(min: T, max: T, lower_bound: Bound<T>, upper_bound: Bound<T>) -> bool {
+    let is_above_lower_bound = match lower_bound {
+        Bound::Included(lower) => max >= lower,
+        Bound::Excluded(lower) => max > lower,
+        Bound::Unbounded => true,
+    };
+    let is_below_upper_bound = match upper_bound {
+        Bound::Included(upper) => min <= upper,
+        Bound::Excluded(upper) => min < upper,
+        Bound::Unbounded => true,
+    };
+    is_above_lower_bound && is_below_upper_bound
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_query::query_ast::qast_helper;
+    use tantivy::schema::{BytesOptions, STRING};
+
+    use super::*;
+
+    fn field_stats_for_test() -> BTreeMap<String, FieldStats> {
+        let mut field_stats = BTreeMap::new();
+        field_stats.insert(
+            "status".to_string(),
+            FieldStats::U64Range { min: 200, max: 404 },
+        );
+        field_stats.insert(
+            "timestamp".to_string(),
+            FieldStats::DateRange {
+                min: 1_700_000_000_000_000_000,
+                max: 1_700_000_100_000_000_000,
+            },
+        );
+        field_stats
+    }
+
+    fn bloom_filters_for_test() -> (BTreeMap<String, BloomFilter>, Schema) {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("trace_id", STRING);
+        schema_builder.add_bytes_field("span_id", BytesOptions::default().set_indexed());
+        let schema = schema_builder.build();
+
+        let mut trace_id_bloom_filter = BloomFilter::with_capacity(2, 0.01);
+        trace_id_bloom_filter.insert(b"trace-1");
+        trace_id_bloom_filter.insert(b"trace-2");
+        let mut span_id_bloom_filter = BloomFilter::with_capacity(1, 0.01);
+        span_id_bloom_filter.insert(&[0xab, 0xcd]);
+
+        let bloom_filters = BTreeMap::from([
+            ("trace_id".to_string(), trace_id_bloom_filter),
+            ("span_id".to_string(), span_id_bloom_filter),
+        ]);
+        (bloom_filters, schema)
+    }
+
+    fn term(field: &str, value: &str) -> QueryAst {
+        TermQuery {
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+        .into()
+    }
+
+    fn range(
+        field: &str,
+        lower_bound: Bound<JsonLiteral>,
+        upper_bound: Bound<JsonLiteral>,
+    ) -> QueryAst {
+        RangeQuery {
+            field: field.to_string(),
+            lower_bound,
+            upper_bound,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_split_may_match_term_query() {
+        let field_stats = field_stats_for_test();
+        assert!(split_may_match(&term("status", "200"), &field_stats));
+        assert!(split_may_match(&term("status", "404"), &field_stats));
+        assert!(!split_may_match(&term("status", "500"), &field_stats));
+        assert!(split_may_match(
+            &term("status", "not-a-number"),
+            &field_stats
+        ));
+        assert!(split_may_match(&term("unknown", "trace-3"), &field_stats));
+        assert!(split_may_match(
+            &term("timestamp", "2023-11-14T22:14:00Z"),
+            &field_stats
+        ));
+        assert!(!split_may_match(
+            &term("timestamp", "2024-01-01T00:00:00Z"),
+            &field_stats
+        ));
+        assert!(split_may_match(&term("status", "500"), &BTreeMap::new()));
+    }
+
+    #[test]
+    fn test_split_may_match_range_query() {
+        let field_stats = field_stats_for_test();
+        assert!(split_may_match(
+            &range(
+                "status",
+                Bound::Included(JsonLiteral::Number(400.into())),
+                Bound::Unbounded
+            ),
+            &field_stats
+        ));
+        assert!(!split_may_match(
+            &range(
+                "status",
+                Bound::Excluded(JsonLiteral::Number(404.into())),
+                Bound::Unbounded
+            ),
+            &field_stats
+        ));
+        assert!(!split_may_match(
+            &range(
+                "status",
+                Bound::Unbounded,
+                Bound::Excluded(JsonLiteral::String("200".to_string()))
+            ),
+            &field_stats
+        ));
+        assert!(split_may_match(
+            &range(
+                "status",
+                Bound::Unbounded,
+                Bound::Included(JsonLiteral::String("200".to_string()))
+            ),
+            &field_stats
+        ));
+        assert!(!split_may_match(
+            &range(
+                "timestamp",
+                Bound::Included(JsonLiteral::String("2024-01-01T00:00:00Z".to_string())),
+                Bound::Unbounded
+            ),
+            &field_stats
+        ));
+        assert!(split_may_match(
+            &range(
+                "trace_id",
+                Bound::Included(JsonLiteral::String("a".to_string())),
+                Bound::Unbounded
+            ),
+            &field_stats
+        ));
+    }
+
+    #[test]
+    fn test_split_may_match_bool_query() {
+        let field_stats = field_stats_for_test();
+        let query_ast: QueryAst = BoolQuery {
+            must: vec![term("status", "200"), term("status", "500")],
+            ..Default::default()
+        }
+        .into();
+        assert!(!split_may_match(&query_ast, &field_stats));
+
+        let query_ast: QueryAst = BoolQuery {
+            should: vec![term("status", "200"), term("status", "500")],
+            ..Default::default()
+        }
+        .into();
+        assert!(split_may_match(&query_ast, &field_stats));
+
+        let query_ast: QueryAst = BoolQuery {
+            should: vec![term("status", "100"), term("status", "500")],
+            ..Default::default()
+        }
+        .into();
+        assert!(!split_may_match(&query_ast, &field_stats));
+
+        let query_ast: QueryAst = BoolQuery {
+            filter: vec![term("status", "200")],
+            should: vec![term("status", "500")],
+            ..Default::default()
+        }
+        .into();
+        assert!(split_may_match(&query_ast, &field_stats));
+
+        let query_ast: QueryAst = BoolQuery {
+            must_not: vec![term("status", "200")],
+            ..Default::default()
+        }
+        .into();
+        assert!(split_may_match(&query_ast, &field_stats));
+
+        let query_ast = qast_helper("status:500 AND body:error", &["body"]);
+        assert!(!split_may_match(&query_ast, &field_stats));
+    }
+
+    #[test]
+    fn test_split_may_match_bloom_filters() {
+        let (bloom_filters, schema) = bloom_filters_for_test();
+        let may_match = |query_ast: &QueryAst| {
+            split_may_match_bloom_filters(query_ast, &bloom_filters, &schema)
+        };
+
+        assert!(may_match(&term("trace_id", "trace-1")));
+        assert!(may_match(&term("trace_id", "trace-2")));
+        assert!(may_match(&term("span_id", "abcd")));
+        assert!(may_match(&term("span_id", "q80=")));
+        assert!(may_match(&term("span_id", "not base64")));
+        assert!(may_match(&term("unknown", "trace-3")));
+
+        let num_false_positives = (0..1_000)
+            .filter(|i| may_match(&term("trace_id", &format!("other-trace-{i}"))))
+            .count();
+        assert!(num_false_positives < 100, "{num_false_positives}");
+
+        let query_ast = qast_helper("trace_id:trace-1 AND body:error", &["body"]);
+        assert!(may_match(&query_ast));
+
+        assert!(split_may_match_bloom_filters(
+            &term("trace_id", "other-trace"),
+            &BTreeMap::new(),
+            &schema
+        ));
+    }
+
+    #[test]
+    fn test_bloom_filters_may_prune() {
+        let bloom_filter_field_names = BTreeSet::from(["trace_id".to_string()]);
+
+        assert!(bloom_filters_may_prune(
+            &term("trace_id", "trace-1"),
+            &bloom_filter_field_names
+        ));
+        assert!(bloom_filters_may_prune(
+            &qast_helper("trace_id:trace-1 AND body:error", &["body"]),
+            &bloom_filter_field_names
+        ));
+        assert!(!bloom_filters_may_prune(
+            &qast_helper("trace_id:trace-1 OR body:error", &["body"]),
+            &bloom_filter_field_names
+        ));
+        assert!(!bloom_filters_may_prune(
+            &term("body", "error"),
+            &bloom_filter_field_names
+        ));
+        assert!(!bloom_filters_may_prune(
+            &term("trace_id", "trace-1"),
+            &BTreeSet::new()
+        ));
+    }
+}
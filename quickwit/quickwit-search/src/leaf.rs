@@ -18,12 +18,15 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use anyhow::Context;
 use futures::future::try_join_all;
+use quickwit_common::bloom_filter::deserialize_bloom_filters;
 use quickwit_common::pretty::PrettySample;
+use quickwit_common::shared_consts::BLOOM_FILTERS_FILE_NAME;
 use quickwit_directories::{CachingDirectory, HotDirectory, StorageDirectory};
 use quickwit_doc_mapper::{DocMapper, TermRange, WarmupInfo};
 use quickwit_proto::search::{
@@ -37,11 +40,13 @@ use quickwit_storage::{
 };
 use tantivy::directory::FileSlice;
 use tantivy::fastfield::FastFieldReaders;
-use tantivy::schema::Field;
+use tantivy::schema::{Field, Schema};
 use tantivy::{Index, ReloadPolicy, Searcher, Term};
 use tracing::*;
 
 use crate::collector::{make_collector_for_split, make_merge_collector, IncrementalCollector};
+use crate::field_stats_pruning::{bloom_filters_may_prune, split_may_match_bloom_filters};
+use crate::search_profile::{profile_future, SplitSearchProfiler};
 use crate::service::SearcherContext;
 use crate::SearchError;
 
@@ -50,13 +55,20 @@ async fn get_split_footer_from_cache_or_fetch(
     index_storage: Arc<dyn Storage>,
     split_and_footer_offsets: &SplitIdAndFooterOffsets,
     footer_cache: &MemorySizedCache<String>,
+    split_profiler_opt: Option<&Arc<SplitSearchProfiler>>,
 ) -> anyhow::Result<OwnedBytes> {
     {
         let possible_val = footer_cache.get(&split_and_footer_offsets.split_id);
         if let Some(footer_data) = possible_val {
+            if let Some(split_profiler) = split_profiler_opt {
+                split_profiler.record_cache_hit();
+            }
             return Ok(footer_data);
         }
     }
+    if let Some(split_profiler) = split_profiler_opt {
+        split_profiler.record_cache_miss();
+    }
     let split_file = PathBuf::from(format!("{}.split", split_and_footer_offsets.split_id));
     let footer_data_opt = index_storage
         .get_slice(
@@ -83,17 +95,31 @@ async fn get_split_footer_from_cache_or_fetch(
 
 /// Returns hotcache_bytes and the split directory (`BundleStorage`) with cache layer:
 /// - A split footer cache given by `SearcherContext.split_footer_cache`.
+///
+/// If a profiler is provided, the footer fetch and the downloads from `index_storage` are
+/// recorded.
 #[instrument(skip_all, fields(split_footer_start=split_and_footer_offsets.split_footer_start, split_footer_end=split_and_footer_offsets.split_footer_end))]
 pub(crate) async fn open_split_bundle(
     searcher_context: &SearcherContext,
     index_storage: Arc<dyn Storage>,
     split_and_footer_offsets: &SplitIdAndFooterOffsets,
+    split_profiler_opt: Option<&Arc<SplitSearchProfiler>>,
 ) -> anyhow::Result<(FileSlice, BundleStorage)> {
+    let index_storage = if let Some(split_profiler) = split_profiler_opt {
+        split_profiler.wrap_storage(index_storage)
+    } else {
+        index_storage
+    };
     let split_file = PathBuf::from(format!("{}.split", split_and_footer_offsets.split_id));
-    let footer_data = get_split_footer_from_cache_or_fetch(
-        index_storage.clone(),
-        split_and_footer_offsets,
-        &searcher_context.split_footer_cache,
+    let footer_data = profile_future(
+        split_profiler_opt,
+        |split_profile| &mut split_profile.footer_fetch_micros,
+        get_split_footer_from_cache_or_fetch(
+            index_storage.clone(),
+            split_and_footer_offsets,
+            &searcher_context.split_footer_cache,
+            split_profiler_opt,
+        ),
     )
     .await?;
 
@@ -115,10 +141,69 @@ pub(crate) async fn open_split_bundle(
     Ok((hotcache_bytes, bundle_storage))
 }
 
+/// Loads the bloom filters stored in the split file and returns `false` if they guarantee that
+/// none of the documents of the split matches the query.
+///
+/// The bloom filters are kept in the fast fields cache, so that the following searches on the
+/// split do not download them again.
+async fn split_bloom_filters_may_match(
+    searcher_context: &SearcherContext,
+    index_storage: Arc<dyn Storage>,
+    split_and_footer_offsets: &SplitIdAndFooterOffsets,
+    query_ast: &QueryAst,
+    schema: &Schema,
+    split_profiler_opt: Option<&Arc<SplitSearchProfiler>>,
+) -> anyhow::Result<bool> {
+    // The files of the split bundle are cached under their own name, which is not unique for the
+    // bloom filters.
+    let cache_path = PathBuf::from(format!(
+        "{}.{BLOOM_FILTERS_FILE_NAME}",
+        split_and_footer_offsets.split_id
+    ));
+    let serialized_bloom_filters = if let Some(serialized_bloom_filters) = searcher_context
+        .fast_fields_cache
+        .get_all(&cache_path)
+        .await
+    {
+        serialized_bloom_filters
+    } else {
+        let (_, bundle_storage) = open_split_bundle(
+            searcher_context,
+            index_storage,
+            split_and_footer_offsets,
+            split_profiler_opt,
+        )
+        .await?;
+        let bloom_filters_path = Path::new(BLOOM_FILTERS_FILE_NAME);
+
+        // Splits created before the bloom filter fields were configured do not have bloom
+        // filters.
+        if !bundle_storage.exists(bloom_filters_path).await? {
+            return Ok(true);
+        }
+        let serialized_bloom_filters = bundle_storage.get_all(bloom_filters_path).await?;
+        searcher_context
+            .fast_fields_cache
+            .put_all(cache_path, serialized_bloom_filters.clone())
+            .await;
+        serialized_bloom_filters
+    };
+    let bloom_filters = deserialize_bloom_filters(serialized_bloom_filters.as_slice())
+        .context("failed to deserialize bloom filters")?;
+    Ok(split_may_match_bloom_filters(
+        query_ast,
+        &bloom_filters,
+        schema,
+    ))
+}
+
 /// Opens a `tantivy::Index` for the given split with several cache layers:
 /// - A split footer cache given by `SearcherContext.split_footer_cache`.
 /// - A fast fields cache given by `SearcherContext.storage_long_term_cache`.
 /// - An ephemeral unbounded cache directory whose lifetime is tied to the returned `Index`.
+///
+/// If a profiler is provided, the footer fetch, the downloads from `index_storage` and the
+/// lookups in the caches are recorded.
 #[instrument(skip_all, fields(split_footer_start=split_and_footer_offsets.split_footer_start, split_footer_end=split_and_footer_offsets.split_footer_end))]
 pub(crate) async fn open_index_with_caches(
     searcher_context: &SearcherContext,
@@ -126,14 +211,23 @@ pub(crate) async fn open_index_with_caches(
     split_and_footer_offsets: &SplitIdAndFooterOffsets,
     tokenizer_manager: Option<&TokenizerManager>,
     ephemeral_unbounded_cache: bool,
+    split_profiler_opt: Option<&Arc<SplitSearchProfiler>>,
 ) -> anyhow::Result<Index> {
-    let (hotcache_bytes, bundle_storage) =
-        open_split_bundle(searcher_context, index_storage, split_and_footer_offsets).await?;
+    let (hotcache_bytes, bundle_storage) = open_split_bundle(
+        searcher_context,
+        index_storage,
+        split_and_footer_offsets,
+        split_profiler_opt,
+    )
+    .await?;
 
-    let bundle_storage_with_cache = wrap_storage_with_cache(
-        searcher_context.fast_fields_cache.clone(),
-        Arc::new(bundle_storage),
-    );
+    let fast_fields_cache = if let Some(split_profiler) = split_profiler_opt {
+        split_profiler.wrap_cache(searcher_context.fast_fields_cache.clone())
+    } else {
+        searcher_context.fast_fields_cache.clone()
+    };
+    let bundle_storage_with_cache =
+        wrap_storage_with_cache(fast_fields_cache, Arc::new(bundle_storage));
     let directory = StorageDirectory::new(bundle_storage_with_cache);
 
     let hot_directory = if ephemeral_unbounded_cache {
@@ -170,24 +264,52 @@ pub(crate) async fn open_index_with_caches(
 /// * `term_dict_field_names` - A list of fields, where the whole dictionary needs to be loaded.
 /// This is e.g. required for term aggregation, since we don't know in advance which terms are going
 /// to be hit.
+///
+/// * `split_profiler_opt` - If set, the duration of the warmup of each kind of data is recorded.
 #[instrument(skip_all)]
-pub(crate) async fn warmup(searcher: &Searcher, warmup_info: &WarmupInfo) -> anyhow::Result<()> {
+pub(crate) async fn warmup(
+    searcher: &Searcher,
+    warmup_info: &WarmupInfo,
+    split_profiler_opt: Option<&Arc<SplitSearchProfiler>>,
+) -> anyhow::Result<()> {
     debug!(warmup_info=?warmup_info);
-    let warm_up_terms_future = warm_up_terms(searcher, &warmup_info.terms_grouped_by_field)
-        .instrument(debug_span!("warm_up_terms"));
-    let warm_up_term_ranges_future =
-        warm_up_term_ranges(searcher, &warmup_info.term_ranges_grouped_by_field)
-            .instrument(debug_span!("warm_up_term_ranges"));
-    let warm_up_term_dict_future =
-        warm_up_term_dict_fields(searcher, &warmup_info.term_dict_fields)
-            .instrument(debug_span!("warm_up_term_dicts"));
-    let warm_up_fastfields_future = warm_up_fastfields(searcher, &warmup_info.fast_field_names)
-        .instrument(debug_span!("warm_up_fastfields"));
-    let warm_up_fieldnorms_future = warm_up_fieldnorms(searcher, warmup_info.field_norms)
-        .instrument(debug_span!("warm_up_fieldnorms"));
+    let warm_up_terms_future = profile_future(
+        split_profiler_opt,
+        |split_profile| &mut split_profile.warmup_terms_micros,
+        warm_up_terms(searcher, &warmup_info.terms_grouped_by_field),
+    )
+    .instrument(debug_span!("warm_up_terms"));
+    let warm_up_term_ranges_future = profile_future(
+        split_profiler_opt,
+        |split_profile| &mut split_profile.warmup_term_ranges_micros,
+        warm_up_term_ranges(searcher, &warmup_info.term_ranges_grouped_by_field),
+    )
+    .instrument(debug_span!("warm_up_term_ranges"));
+    let warm_up_term_dict_future = profile_future(
+        split_profiler_opt,
+        |split_profile| &mut split_profile.warmup_term_dicts_micros,
+        warm_up_term_dict_fields(searcher, &warmup_info.term_dict_fields),
+    )
+    .instrument(debug_span!("warm_up_term_dicts"));
+    let warm_up_fastfields_future = profile_future(
+        split_profiler_opt,
+        |split_profile| &mut split_profile.warmup_fast_fields_micros,
+        warm_up_fastfields(searcher, &warmup_info.fast_field_names),
+    )
+    .instrument(debug_span!("warm_up_fastfields"));
+    let warm_up_fieldnorms_future = profile_future(
+        split_profiler_opt,
+        |split_profile| &mut split_profile.warmup_fieldnorms_micros,
+        warm_up_fieldnorms(searcher, warmup_info.field_norms),
+    )
+    .instrument(debug_span!("warm_up_fieldnorms"));
     // TODO merge warm_up_postings into warm_up_term_dict_fields
-    let warm_up_postings_future = warm_up_postings(searcher, &warmup_info.term_dict_fields)
-        .instrument(debug_span!("warm_up_postings"));
+    let warm_up_postings_future = profile_future(
+        split_profiler_opt,
+        |split_profile| &mut split_profile.warmup_postings_micros,
+        warm_up_postings(searcher, &warmup_info.term_dict_fields),
+    )
+    .instrument(debug_span!("warm_up_postings"));
 
     tokio::try_join!(
         warm_up_terms_future,
@@ -335,13 +457,50 @@ async fn leaf_search_single_split(
     doc_mapper: Arc<dyn DocMapper>,
 ) -> crate::Result<LeafSearchResponse> {
     rewrite_request(&mut search_request, &split);
-    if let Some(cached_answer) = searcher_context
+    let split_profiler_opt = if search_request.profile {
+        Some(SplitSearchProfiler::new(split.split_id.clone()))
+    } else {
+        None
+    };
+    if let Some(mut cached_answer) = searcher_context
         .leaf_search_cache
         .get(split.clone(), search_request.clone())
     {
+        if let Some(split_profiler) = split_profiler_opt {
+            split_profiler.record_leaf_search_cache_hit();
+            cached_answer
+                .split_profiles
+                .push(split_profiler.split_profile());
+        }
         return Ok(cached_answer);
     }
 
+    let query_ast: QueryAst = serde_json::from_str(search_request.query_ast.as_str())
+        .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+
+    if bloom_filters_may_prune(&query_ast, &doc_mapper.bloom_filter_field_names())
+        && !split_bloom_filters_may_match(
+            searcher_context,
+            storage.clone(),
+            &split,
+            &query_ast,
+            &doc_mapper.schema(),
+            split_profiler_opt.as_ref(),
+        )
+        .await?
+    {
+        let mut leaf_search_response = LeafSearchResponse {
+            num_attempted_splits: 1,
+            ..Default::default()
+        };
+        if let Some(split_profiler) = split_profiler_opt {
+            split_profiler.record_pruned_by_bloom_filters();
+            leaf_search_response
+                .split_profiles
+                .push(split_profiler.split_profile());
+        }
+        return Ok(leaf_search_response);
+    }
     let split_id = split.split_id.to_string();
     let index = open_index_with_caches(
         searcher_context,
@@ -349,18 +508,18 @@ async fn leaf_search_single_split(
         &split,
         Some(doc_mapper.tokenizer_manager()),
         true,
+        split_profiler_opt.as_ref(),
     )
     .await?;
     let split_schema = index.schema();
 
-    let quickwit_collector = make_collector_for_split(
+    let mut quickwit_collector = make_collector_for_split(
         split_id.clone(),
         doc_mapper.as_ref(),
         &search_request,
         searcher_context.get_aggregation_limits(),
     )?;
-    let query_ast: QueryAst = serde_json::from_str(search_request.query_ast.as_str())
-        .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+    quickwit_collector.set_index_sort_by_field(index.settings().sort_by_field.as_ref());
     let (query, mut warmup_info) = doc_mapper.query(split_schema, &query_ast, false)?;
     let reader = index
         .reader_builder()
@@ -372,9 +531,15 @@ async fn leaf_search_single_split(
     warmup_info.merge(collector_warmup_info);
     warmup_info.simplify();
 
-    warmup(&searcher, &warmup_info).await?;
+    profile_future(
+        split_profiler_opt.as_ref(),
+        |split_profile| &mut split_profile.warmup_micros,
+        warmup(&searcher, &warmup_info, split_profiler_opt.as_ref()),
+    )
+    .await?;
+    let query_execution_start = Instant::now();
     let span = info_span!("tantivy_search");
-    let leaf_search_response = crate::run_cpu_intensive(move || {
+    let mut leaf_search_response = crate::run_cpu_intensive(move || {
         let _span_guard = span.enter();
         searcher.search(&query, &quickwit_collector)
     })
@@ -386,6 +551,16 @@ async fn leaf_search_single_split(
     searcher_context
         .leaf_search_cache
         .put(split, search_request, leaf_search_response.clone());
+
+    if let Some(split_profiler) = split_profiler_opt {
+        split_profiler.record_elapsed(
+            |split_profile| &mut split_profile.query_execution_micros,
+            query_execution_start,
+        );
+        leaf_search_response
+            .split_profiles
+            .push(split_profiler.split_profile());
+    }
     Ok(leaf_search_response)
 }
 
@@ -21,7 +21,7 @@ use std::ops::Bound;
 
 use prost::Message;
 use quickwit_proto::search::{
-    CountHits, LeafSearchResponse, SearchRequest, SplitIdAndFooterOffsets,
+    LeafSearchResponse, SearchPriority, SearchRequest, SplitIdAndFooterOffsets,
 };
 use quickwit_storage::{MemorySizedCache, OwnedBytes};
 
@@ -101,9 +101,13 @@ impl CacheKey {
 
         search_request.start_timestamp = None;
         search_request.end_timestamp = None;
-        // it doesn't matter whether or not we count all hits at the scale of a
-        // single split: either we did process it and got everything, or we didn't.
-        search_request.count_hits = CountHits::CountAll.into();
+        // `count_hits` is kept: with `Underestimate`, the collection of a split sorted by the
+        // sort field can stop early, and the number of hits of the response is not exact.
+        // the priority only matters for admission control.
+        search_request.priority = SearchPriority::Interactive.into();
+        // profiling does not change the result, and cached responses are stored without
+        // profile.
+        search_request.profile = false;
 
         CacheKey {
             split_id: split_info.split_id,
@@ -206,6 +210,7 @@ mod tests {
             split_footer_end: 100,
             timestamp_start: None,
             timestamp_end: None,
+            storage_uri: None,
         };
 
         let split_2 = SplitIdAndFooterOffsets {
@@ -214,6 +219,7 @@ mod tests {
             split_footer_end: 100,
             timestamp_start: None,
             timestamp_end: None,
+            storage_uri: None,
         };
 
         let query_1 = SearchRequest {
@@ -248,6 +254,7 @@ mod tests {
                 sort_value2: None,
                 split_id: "split_1".to_string(),
             }],
+            split_profiles: Vec::new(),
         };
 
         assert!(cache.get(split_1.clone(), query_1.clone()).is_none());
@@ -268,6 +275,7 @@ mod tests {
             split_footer_end: 100,
             timestamp_start: Some(100),
             timestamp_end: Some(199),
+            storage_uri: None,
         };
         let split_2 = SplitIdAndFooterOffsets {
             split_id: "split_2".to_string(),
@@ -275,6 +283,7 @@ mod tests {
             split_footer_end: 100,
             timestamp_start: Some(150),
             timestamp_end: Some(249),
+            storage_uri: None,
         };
         let split_3 = SplitIdAndFooterOffsets {
             split_id: "split_3".to_string(),
@@ -282,6 +291,7 @@ mod tests {
             split_footer_end: 100,
             timestamp_start: Some(150),
             timestamp_end: Some(249),
+            storage_uri: None,
         };
 
         let query_1 = SearchRequest {
@@ -334,6 +344,7 @@ mod tests {
                 sort_value2: None,
                 split_id: "split_1".to_string(),
             }],
+            split_profiles: Vec::new(),
         };
 
         // for split_1, 1 and 1bis cover different timestamp ranges
@@ -27,6 +27,7 @@ mod cluster_client;
 mod collector;
 mod error;
 mod fetch_docs;
+mod field_stats_pruning;
 mod filters;
 mod find_trace_ids_collector;
 mod leaf;
@@ -36,12 +37,18 @@ mod list_fields_cache;
 mod list_terms;
 mod retry;
 mod root;
+mod root_search_cache;
+mod runtime_field_aggregations;
+mod runtime_fields;
 mod scroll_context;
+mod search_admission;
 mod search_job_placer;
+mod search_profile;
 mod search_response_rest;
 mod search_stream;
 mod service;
 mod thread_pool;
+mod tiered_storage;
 
 mod metrics;
 
@@ -87,6 +94,7 @@ pub use crate::root::{
     check_all_index_metadata_found, jobs_to_leaf_requests, root_search, IndexMetasForLeafSearch,
     SearchJob,
 };
+pub use crate::runtime_fields::{RuntimeFieldMapping, RuntimeFieldScript};
 pub use crate::search_job_placer::{Job, SearchJobPlacer};
 pub use crate::search_response_rest::SearchResponseRest;
 pub use crate::search_stream::root_search_stream;
@@ -169,6 +177,10 @@ fn extract_split_and_footer_offsets(split_metadata: &SplitMetadata) -> SplitIdAn
             .time_range
             .as_ref()
             .map(|time_range| *time_range.end()),
+        storage_uri: split_metadata
+            .storage_uri
+            .as_ref()
+            .map(|storage_uri| storage_uri.to_string()),
     }
 }
 
@@ -28,6 +28,7 @@ pub struct SearchMetrics {
     pub leaf_searches_splits_total: IntCounter,
     pub leaf_search_split_duration_secs: Histogram,
     pub active_search_threads_count: IntGauge,
+    pub root_searches_rejected_total: IntCounter,
 }
 
 impl Default for SearchMetrics {
@@ -50,6 +51,12 @@ impl Default for SearchMetrics {
                 "search",
                 &[],
             ),
+            root_searches_rejected_total: new_counter(
+                "root_searches_rejected_total",
+                "Number of root searches rejected because the search quotas of an index were \
+                 exceeded.",
+                "search",
+            ),
         }
     }
 }
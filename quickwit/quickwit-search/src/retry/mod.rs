@@ -133,6 +133,7 @@ mod tests {
             split_footer_start: 0,
             timestamp_start: None,
             timestamp_end: None,
+            storage_uri: None,
         };
         let client_for_retry = retry_client(
             &search_job_placer,
@@ -18,7 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{HashMap, HashSet};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
 use futures::future::try_join_all;
@@ -35,8 +35,8 @@ use quickwit_proto::metastore::{
 };
 use quickwit_proto::search::{
     FetchDocsRequest, FetchDocsResponse, Hit, LeafHit, LeafSearchRequest, LeafSearchResponse,
-    PartialHit, SearchRequest, SearchResponse, SnippetRequest, SortDatetimeFormat, SortField,
-    SortValue, SplitIdAndFooterOffsets,
+    PartialHit, RuntimeMapping, SearchProfile, SearchRequest, SearchResponse, SnippetRequest,
+    SortDatetimeFormat, SortField, SortValue, SplitIdAndFooterOffsets, SplitSearchProfile,
 };
 use quickwit_proto::types::{IndexUid, SplitId};
 use quickwit_query::query_ast::{
@@ -51,10 +51,17 @@ use tantivy::TantivyError;
 use tracing::{debug, error, info, info_span, instrument};
 
 use crate::cluster_client::ClusterClient;
-use crate::collector::{make_merge_collector, QuickwitAggregations};
+use crate::collector::{
+    aggregations_from_request, make_merge_collector, sort_by_from_request, QuickwitAggregations,
+};
+use crate::field_stats_pruning::split_may_match;
 use crate::find_trace_ids_collector::Span;
+use crate::root_search_cache::{now_secs, RootSearchCache, RootSearchCacheLookup};
+use crate::runtime_field_aggregations::IntermediateRuntimeAggregationResults;
+use crate::runtime_fields::{parse_runtime_fields, RuntimeFieldType};
 use crate::scroll_context::{ScrollContext, ScrollKeyAndStartOffset};
 use crate::search_job_placer::Job;
+use crate::search_profile::prune_splits_and_build_search_profile;
 use crate::service::SearcherContext;
 use crate::{
     extract_split_and_footer_offsets, list_relevant_splits, SearchError, SearchJobPlacer,
@@ -175,6 +182,7 @@ fn validate_request_and_build_metadata(
         &search_request.sort_fields,
         &search_request.search_after,
     )?;
+    let runtime_field_types = validate_runtime_fields(search_request)?;
     let query_ast: QueryAst = serde_json::from_str(&search_request.query_ast)
         .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
     let mut indexes_meta_for_leaf_search: HashMap<IndexUid, IndexMetasForLeafSearch> =
@@ -233,6 +241,7 @@ fn validate_request_and_build_metadata(
         validate_sort_field_types(
             &schema,
             &search_request.sort_fields,
+            &runtime_field_types,
             &mut sort_fields_is_datetime,
         )?;
 
@@ -265,13 +274,45 @@ fn validate_request_and_build_metadata(
     })
 }
 
+/// Validates the runtime fields of the request and returns their types.
+fn validate_runtime_fields(
+    search_request: &SearchRequest,
+) -> crate::Result<HashMap<String, RuntimeFieldType>> {
+    let runtime_fields = parse_runtime_fields(&search_request.runtime_mappings)?;
+    if runtime_fields.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let runtime_field_types: HashMap<String, RuntimeFieldType> = runtime_fields
+        .iter()
+        .map(|runtime_field| (runtime_field.name().to_string(), runtime_field.field_type()))
+        .collect();
+
+    // Checks that the runtime fields used to sort hits are sortable.
+    sort_by_from_request(search_request)?;
+
+    // Checks that the aggregations referencing runtime fields are supported.
+    if search_request.aggregation_request.is_some() {
+        aggregations_from_request(search_request)?;
+    }
+    Ok(runtime_field_types)
+}
+
 /// Validate sort field types.
 fn validate_sort_field_types(
     schema: &Schema,
     sort_fields: &[SortField],
+    runtime_field_types: &HashMap<String, RuntimeFieldType>,
     sort_field_is_datetime: &mut HashMap<String, bool>,
 ) -> crate::Result<()> {
     for sort_field in sort_fields.iter() {
+        // Runtime fields are not part of the schema.
+        if let Some(runtime_field_type) = runtime_field_types.get(&sort_field.field_name) {
+            sort_field_is_datetime.insert(
+                sort_field.field_name.to_string(),
+                *runtime_field_type == RuntimeFieldType::Date,
+            );
+            continue;
+        }
         if let Some(sort_field_entry) = get_sort_by_field_entry(&sort_field.field_name, schema)? {
             validate_sort_by_field_type(
                 sort_field_entry,
@@ -354,6 +395,11 @@ fn simplify_search_request_for_scroll_api(req: &SearchRequest) -> crate::Result<
         // request is simplified after initial query, and we cache the hit count, so we don't need
         // to recompute it afterward.
         count_hits: quickwit_proto::search::CountHits::Underestimate as i32,
+        priority: req.priority,
+        // Subsequent scroll requests are not profiled.
+        profile: false,
+        dry_run: false,
+        runtime_mappings: req.runtime_mappings.clone(),
     })
 }
 
@@ -623,6 +669,7 @@ fn get_count_from_metadata(split_metadatas: &[SplitMetadata]) -> Vec<LeafSearchR
             failed_splits: Vec::new(),
             num_attempted_splits: 1,
             intermediate_aggregation_result: None,
+            split_profiles: Vec::new(),
         })
         .collect()
 }
@@ -658,7 +705,16 @@ pub(crate) async fn search_partial_hits_phase(
             }
             try_join_all(leaf_request_tasks).await?
         };
+    merge_leaf_search_responses(searcher_context, search_request, leaf_search_responses).await
+}
 
+/// Merges leaf search responses into one, applying the `start_offset` and `max_hits` of the
+/// request.
+async fn merge_leaf_search_responses(
+    searcher_context: &SearcherContext,
+    search_request: &SearchRequest,
+    leaf_search_responses: Vec<LeafSearchResponse>,
+) -> crate::Result<LeafSearchResponse> {
     // Creates a collector which merges responses into one
     let merge_collector =
         make_merge_collector(search_request, &searcher_context.get_aggregation_limits())?;
@@ -702,6 +758,10 @@ pub(crate) fn get_snippet_request(search_request: &SearchRequest) -> Option<Snip
     })
 }
 
+/// Fetches the documents of the given partial hits.
+///
+/// If `split_profiles_opt` is set, the duration of the fetch docs requests is added to the profile
+/// of the splits they cover.
 #[instrument(skip_all, fields(partial_hits_num=partial_hits.len()))]
 pub(crate) async fn fetch_docs_phase(
     indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
@@ -709,6 +769,7 @@ pub(crate) async fn fetch_docs_phase(
     split_metadatas: &[SplitMetadata],
     search_request: &SearchRequest,
     cluster_client: &ClusterClient,
+    split_profiles_opt: Option<&mut Vec<SplitSearchProfile>>,
 ) -> crate::Result<Vec<Hit>> {
     let snippet_request: Option<SnippetRequest> = get_snippet_request(search_request);
     let hit_order: HashMap<(String, u32, u32), usize> = partial_hits
@@ -735,19 +796,49 @@ pub(crate) async fn fetch_docs_phase(
     for (client, client_jobs) in assigned_fetch_docs_jobs {
         let fetch_jobs_requests = jobs_to_fetch_docs_requests(
             snippet_request.clone(),
+            &search_request.runtime_mappings,
             indexes_metas_for_leaf_search,
             client_jobs,
         )?;
         for fetch_docs_request in fetch_jobs_requests {
-            fetch_docs_tasks.push(cluster_client.fetch_docs(fetch_docs_request, client.clone()));
+            let split_ids: Vec<SplitId> = fetch_docs_request
+                .split_offsets
+                .iter()
+                .map(|split_offsets| split_offsets.split_id.clone())
+                .collect();
+            let fetch_docs_fut = cluster_client.fetch_docs(fetch_docs_request, client.clone());
+            fetch_docs_tasks.push(async move {
+                let start = Instant::now();
+                let fetch_docs_response = fetch_docs_fut.await?;
+                crate::Result::Ok((split_ids, start.elapsed(), fetch_docs_response))
+            });
+        }
+    }
+    let fetch_docs_results: Vec<(Vec<SplitId>, Duration, FetchDocsResponse)> =
+        try_join_all(fetch_docs_tasks).await?;
+
+    if let Some(split_profiles) = split_profiles_opt {
+        for (split_ids, elapsed, _) in &fetch_docs_results {
+            for split_id in split_ids {
+                let split_profile_opt = split_profiles
+                    .iter_mut()
+                    .find(|split_profile| split_profile.split_id == *split_id);
+                if let Some(split_profile) = split_profile_opt {
+                    split_profile.fetch_docs_micros += elapsed.as_micros() as u64;
+                } else {
+                    split_profiles.push(SplitSearchProfile {
+                        split_id: split_id.clone(),
+                        fetch_docs_micros: elapsed.as_micros() as u64,
+                        ..Default::default()
+                    });
+                }
+            }
         }
     }
-    let fetch_docs_responses: Vec<FetchDocsResponse> = try_join_all(fetch_docs_tasks).await?;
-
     // Merge the fetched docs.
-    let leaf_hits = fetch_docs_responses
+    let leaf_hits = fetch_docs_results
         .into_iter()
-        .flat_map(|response| response.hits.into_iter());
+        .flat_map(|(_, _, response)| response.hits.into_iter());
 
     // Build map of Split ID > index ID to add the index ID to the hits.
     // Used for ES compatibility.
@@ -865,6 +956,27 @@ async fn root_search_aux(
     cluster_client: &ClusterClient,
 ) -> crate::Result<SearchResponse> {
     debug!(split_metadatas = ?PrettySample::new(&split_metadatas, 5));
+    if let Some(root_search_cache) = &searcher_context.root_search_cache_opt {
+        if RootSearchCache::is_cacheable(&search_request)
+            && RootSearchCache::is_recent(&search_request, now_secs())
+        {
+            if let Some((aligned_search_request, edge_search_requests)) =
+                RootSearchCache::split_time_range(&search_request)
+            {
+                return root_search_aux_with_cache(
+                    root_search_cache,
+                    searcher_context,
+                    indexes_metas_for_leaf_search,
+                    search_request,
+                    aligned_search_request,
+                    edge_search_requests,
+                    split_metadatas,
+                    cluster_client,
+                )
+                .await;
+            }
+        }
+    }
     let (first_phase_result, scroll_key_and_start_offset_opt): (
         LeafSearchResponse,
         Option<ScrollKeyAndStartOffset>,
@@ -877,17 +989,45 @@ async fn root_search_aux(
     )
     .await?;
 
+    let scroll_id_opt = scroll_key_and_start_offset_opt
+        .as_ref()
+        .map(ToString::to_string);
+    fetch_docs_and_build_search_response(
+        searcher_context,
+        indexes_metas_for_leaf_search,
+        &search_request,
+        &split_metadatas,
+        first_phase_result,
+        scroll_id_opt,
+        cluster_client,
+    )
+    .await
+}
+
+/// Fetches the documents of the hits selected by the first phase of the search, finalizes the
+/// aggregations, and builds the search response.
+async fn fetch_docs_and_build_search_response(
+    searcher_context: &SearcherContext,
+    indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
+    search_request: &SearchRequest,
+    split_metadatas: &[SplitMetadata],
+    first_phase_result: LeafSearchResponse,
+    scroll_id_opt: Option<String>,
+    cluster_client: &ClusterClient,
+) -> crate::Result<SearchResponse> {
+    let mut split_profiles = first_phase_result.split_profiles;
     let hits = fetch_docs_phase(
         indexes_metas_for_leaf_search,
         &first_phase_result.partial_hits,
-        &split_metadatas[..],
-        &search_request,
+        split_metadatas,
+        search_request,
         cluster_client,
+        search_request.profile.then_some(&mut split_profiles),
     )
     .await?;
 
     let mut aggregation_result_json_opt = finalize_aggregation_if_any(
-        &search_request,
+        search_request,
         first_phase_result.intermediate_aggregation_result,
         searcher_context,
     )?;
@@ -895,6 +1035,10 @@ async fn root_search_aux(
     if indexes_metas_for_leaf_search.is_empty() {
         aggregation_result_json_opt = None;
     }
+    let search_profile_opt = search_request.profile.then(|| SearchProfile {
+        split_profiles,
+        ..Default::default()
+    });
 
     Ok(SearchResponse {
         aggregation: aggregation_result_json_opt,
@@ -902,12 +1046,183 @@ async fn root_search_aux(
         hits,
         elapsed_time_micros: 0u64,
         errors: Vec::new(),
-        scroll_id: scroll_key_and_start_offset_opt
-            .as_ref()
-            .map(ToString::to_string),
+        scroll_id: scroll_id_opt,
+        profile: search_profile_opt,
     })
 }
 
+/// Same as [`root_search_aux`], but serves the request from the [`RootSearchCache`] whenever
+/// possible. The time range of the request is split into a minute-aligned range, whose results are
+/// cached, and its unaligned edges, which are always searched (see
+/// [`RootSearchCache::split_time_range`]). For the aligned range:
+/// - if the request was already executed on the same set of splits, the cached results are reused
+///   directly;
+/// - if it was executed on a subset of the splits, only the new splits are searched and their
+///   results are merged with the cached ones.
+#[allow(clippy::too_many_arguments)]
+async fn root_search_aux_with_cache(
+    root_search_cache: &RootSearchCache,
+    searcher_context: &SearcherContext,
+    indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
+    search_request: SearchRequest,
+    aligned_search_request: SearchRequest,
+    edge_search_requests: Vec<SearchRequest>,
+    split_metadatas: Vec<SplitMetadata>,
+    cluster_client: &ClusterClient,
+) -> crate::Result<SearchResponse> {
+    let aligned_split_metadatas =
+        splits_overlapping_time_range(&split_metadatas, &aligned_search_request);
+
+    let (cached_leaf_search_response_opt, split_metadatas_to_search) =
+        match root_search_cache.get(&aligned_search_request, &aligned_split_metadatas) {
+            RootSearchCacheLookup::Hit {
+                leaf_search_response,
+                search_response_opt,
+            } => {
+                debug!("root search cache hit");
+
+                if edge_search_requests.is_empty() {
+                    if let Some(search_response) = search_response_opt {
+                        return Ok(search_response);
+                    }
+                }
+                (Some(leaf_search_response), Vec::new())
+            }
+            RootSearchCacheLookup::Partial {
+                leaf_search_response,
+                cached_split_ids,
+            } => {
+                let new_split_metadatas: Vec<SplitMetadata> = aligned_split_metadatas
+                    .iter()
+                    .filter(|split_metadata| !cached_split_ids.contains(&split_metadata.split_id))
+                    .cloned()
+                    .collect();
+                debug!(
+                    num_cached_splits = cached_split_ids.len(),
+                    num_new_splits = new_split_metadatas.len(),
+                    "root search cache partial hit"
+                );
+                (Some(leaf_search_response), new_split_metadatas)
+            }
+            RootSearchCacheLookup::Miss => (None, aligned_split_metadatas.clone()),
+        };
+    // The cached leaf search response must remain mergeable with the responses of future splits
+    // and of the edges of the time range, so we merge the leaf search responses without skipping
+    // the first `start_offset` hits, and only apply the offset in a second step.
+    let aligned_search_request_without_offset = without_start_offset(&aligned_search_request);
+
+    let aligned_leaf_search_response_without_offset = match cached_leaf_search_response_opt {
+        Some(cached_leaf_search_response) if split_metadatas_to_search.is_empty() => {
+            cached_leaf_search_response
+        }
+        Some(cached_leaf_search_response) => {
+            let new_leaf_search_response = search_partial_hits_phase(
+                searcher_context,
+                indexes_metas_for_leaf_search,
+                &aligned_search_request_without_offset,
+                &split_metadatas_to_search,
+                cluster_client,
+            )
+            .await?;
+            merge_leaf_search_responses(
+                searcher_context,
+                &aligned_search_request_without_offset,
+                vec![cached_leaf_search_response, new_leaf_search_response],
+            )
+            .await?
+        }
+        None => {
+            search_partial_hits_phase(
+                searcher_context,
+                indexes_metas_for_leaf_search,
+                &aligned_search_request_without_offset,
+                &split_metadatas_to_search,
+                cluster_client,
+            )
+            .await?
+        }
+    };
+    let mut leaf_search_responses_without_offset =
+        vec![aligned_leaf_search_response_without_offset.clone()];
+
+    for edge_search_request in &edge_search_requests {
+        let edge_split_metadatas =
+            splits_overlapping_time_range(&split_metadatas, edge_search_request);
+
+        if edge_split_metadatas.is_empty() {
+            continue;
+        }
+        let edge_leaf_search_response = search_partial_hits_phase(
+            searcher_context,
+            indexes_metas_for_leaf_search,
+            &without_start_offset(edge_search_request),
+            &edge_split_metadatas,
+            cluster_client,
+        )
+        .await?;
+        leaf_search_responses_without_offset.push(edge_leaf_search_response);
+    }
+    let first_phase_result = merge_leaf_search_responses(
+        searcher_context,
+        &search_request,
+        leaf_search_responses_without_offset,
+    )
+    .await?;
+
+    let search_response = fetch_docs_and_build_search_response(
+        searcher_context,
+        indexes_metas_for_leaf_search,
+        &search_request,
+        &split_metadatas,
+        first_phase_result,
+        None,
+        cluster_client,
+    )
+    .await?;
+    // The search response can only be reused as is by requests on the aligned time range.
+    let search_response_opt = edge_search_requests
+        .is_empty()
+        .then(|| search_response.clone());
+    root_search_cache.put(
+        &aligned_search_request,
+        &aligned_split_metadatas,
+        aligned_leaf_search_response_without_offset,
+        search_response_opt,
+    );
+    Ok(search_response)
+}
+
+/// Returns a copy of the request that returns the first `start_offset + max_hits` hits.
+fn without_start_offset(search_request: &SearchRequest) -> SearchRequest {
+    let mut search_request_without_offset = search_request.clone();
+    search_request_without_offset.start_offset = 0;
+    search_request_without_offset.max_hits += search_request.start_offset;
+    search_request_without_offset
+}
+
+/// Returns the splits whose time range overlaps the time range of the request.
+fn splits_overlapping_time_range(
+    split_metadatas: &[SplitMetadata],
+    search_request: &SearchRequest,
+) -> Vec<SplitMetadata> {
+    split_metadatas
+        .iter()
+        .filter(|split_metadata| {
+            let Some(time_range) = &split_metadata.time_range else {
+                return true;
+            };
+            // The end timestamp of the request is exclusive.
+            search_request
+                .start_timestamp
+                .map_or(true, |start_timestamp| *time_range.end() >= start_timestamp)
+                && search_request
+                    .end_timestamp
+                    .map_or(true, |end_timestamp| *time_range.start() < end_timestamp)
+        })
+        .cloned()
+        .collect()
+}
+
 fn finalize_aggregation(
     intermediate_aggregation_result_bytes_opt: Option<Vec<u8>>,
     aggregations: QuickwitAggregations,
@@ -940,6 +1255,19 @@ fn finalize_aggregation(
                 .into_final_result(aggregations, &searcher_context.get_aggregation_limits())?;
             serde_json::to_string(&final_aggregation_results)?
         }
+        QuickwitAggregations::RuntimeFieldAggregations(aggregations) => {
+            let intermediate_aggregation_results: IntermediateRuntimeAggregationResults =
+                if let Some(intermediate_aggregation_result_bytes) =
+                    intermediate_aggregation_result_bytes_opt
+                {
+                    postcard::from_bytes(&intermediate_aggregation_result_bytes)?
+                } else {
+                    Default::default()
+                };
+            let final_aggregation_results =
+                aggregations.finalize(intermediate_aggregation_results)?;
+            serde_json::to_string(&final_aggregation_results)?
+        }
     };
     Ok(Some(merge_aggregation_result))
 }
@@ -949,10 +1277,9 @@ fn finalize_aggregation_if_any(
     intermediate_aggregation_result_bytes_opt: Option<Vec<u8>>,
     searcher_context: &SearcherContext,
 ) -> crate::Result<Option<String>> {
-    let Some(aggregations_json) = search_request.aggregation_request.as_ref() else {
+    let Some(aggregations) = aggregations_from_request(search_request)? else {
         return Ok(None);
     };
-    let aggregations: QuickwitAggregations = serde_json::from_str(aggregations_json)?;
     let aggregation_result_json = finalize_aggregation(
         intermediate_aggregation_result_bytes_opt,
         aggregations,
@@ -1046,6 +1373,18 @@ pub async fn root_search(
         .iter()
         .map(|index_metadata| index_metadata.index_uid.clone())
         .collect_vec();
+    let index_ids = index_uids
+        .iter()
+        .map(|index_uid| index_uid.index_id.clone())
+        .collect_vec();
+    let _search_admission_permit = searcher_context
+        .search_admission_controller
+        .admit(
+            &index_ids,
+            search_request.priority(),
+            search_request.aggregation_request.is_some(),
+        )
+        .await?;
     let request_metadata = validate_request_and_build_metadata(&indexes_metadata, &search_request)?;
     search_request.query_ast = serde_json::to_string(&request_metadata.query_ast_resolved)?;
 
@@ -1064,19 +1403,48 @@ pub async fn root_search(
             &mut search_request.end_timestamp,
         );
     }
-    let tag_filter_ast = extract_tags_from_query(request_metadata.query_ast_resolved);
+    let tag_filter_ast = extract_tags_from_query(request_metadata.query_ast_resolved.clone());
 
     // TODO if search after is set, we sort by timestamp and we don't want to count all results,
     // we can refine more here. Same if we sort by _shard_doc
-    let split_metadatas: Vec<SplitMetadata> = list_relevant_splits(
-        index_uids,
-        search_request.start_timestamp,
-        search_request.end_timestamp,
-        tag_filter_ast,
-        &mut metastore,
-    )
-    .await?;
+    let (split_metadatas, search_profile_opt): (Vec<SplitMetadata>, Option<SearchProfile>) =
+        if search_request.profile || search_request.dry_run {
+            // We prune the splits ourselves to report the number of pruned splits.
+            let split_metadatas =
+                list_relevant_splits(index_uids, None, None, None, &mut metastore).await?;
+            let (split_metadatas, search_profile) = prune_splits_and_build_search_profile(
+                split_metadatas,
+                search_request.start_timestamp,
+                search_request.end_timestamp,
+                tag_filter_ast.as_ref(),
+                &request_metadata.query_ast_resolved,
+            );
+            (split_metadatas, Some(search_profile))
+        } else {
+            let mut split_metadatas = list_relevant_splits(
+                index_uids,
+                search_request.start_timestamp,
+                search_request.end_timestamp,
+                tag_filter_ast,
+                &mut metastore,
+            )
+            .await?;
+            split_metadatas.retain(|split_metadata| {
+                split_may_match(
+                    &request_metadata.query_ast_resolved,
+                    &split_metadata.field_stats,
+                )
+            });
+            (split_metadatas, None)
+        };
 
+    if search_request.dry_run {
+        return Ok(SearchResponse {
+            elapsed_time_micros: start_instant.elapsed().as_micros() as u64,
+            profile: search_profile_opt,
+            ..Default::default()
+        });
+    }
     let mut search_response = root_search_aux(
         searcher_context,
         &request_metadata.indexes_meta_for_leaf_search,
@@ -1086,6 +1454,14 @@ pub async fn root_search(
     )
     .await?;
 
+    if let Some(mut search_profile) = search_profile_opt {
+        search_profile.split_profiles = search_response
+            .profile
+            .take()
+            .map(|search_profile| search_profile.split_profiles)
+            .unwrap_or_default();
+        search_response.profile = Some(search_profile);
+    }
     search_response.elapsed_time_micros = start_instant.elapsed().as_micros() as u64;
     Ok(search_response)
 }
@@ -1429,6 +1805,7 @@ pub fn jobs_to_leaf_requests(
 /// Builds a list of [`FetchDocsRequest`], one per index, from a list of [`FetchDocsJob`].
 pub fn jobs_to_fetch_docs_requests(
     snippet_request_opt: Option<SnippetRequest>,
+    runtime_mappings: &[RuntimeMapping],
     indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
     jobs: Vec<FetchDocsJob>,
 ) -> crate::Result<Vec<FetchDocsRequest>> {
@@ -1457,6 +1834,7 @@ pub fn jobs_to_fetch_docs_requests(
             index_uri: index_meta.index_uri.to_string(),
             snippet_request: snippet_request_opt.clone(),
             doc_mapper: index_meta.doc_mapper_str.clone(),
+            runtime_mappings: runtime_mappings.to_vec(),
         };
         fetch_docs_requests.push(fetch_docs_req);
     }
@@ -1469,9 +1847,12 @@ mod tests {
     use std::str::FromStr;
     use std::sync::{Arc, RwLock};
 
+    use bytesize::ByteSize;
     use quickwit_common::shared_consts::SCROLL_BATCH_LEN;
     use quickwit_common::ServiceStream;
-    use quickwit_config::{DocMapping, IndexConfig, IndexingSettings, SearchSettings};
+    use quickwit_config::{
+        DocMapping, IndexConfig, IndexingSettings, SearchSettings, SearcherConfig,
+    };
     use quickwit_indexing::MockSplitBuilder;
     use quickwit_metastore::{IndexMetadata, ListSplitsRequestExt, ListSplitsResponseExt};
     use quickwit_proto::metastore::{ListIndexesMetadataResponse, ListSplitsResponse};
@@ -1570,6 +1951,9 @@ mod tests {
             indexing_settings,
             search_settings,
             retention_policy_opt: Default::default(),
+            lifecycle_policy_opt: Default::default(),
+            rollup_policy_opt: Default::default(),
+            ingest_quota_opt: Default::default(),
         })
     }
 
@@ -1742,6 +2126,9 @@ mod tests {
             indexing_settings,
             search_settings,
             retention_policy_opt: Default::default(),
+            lifecycle_policy_opt: Default::default(),
+            rollup_policy_opt: Default::default(),
+            ingest_quota_opt: Default::default(),
         })
     }
 
@@ -1862,7 +2249,13 @@ mod tests {
         schema_builder.add_u64_field("id", FAST);
         let schema = schema_builder.build();
         let mut sort_field_are_datetime = HashMap::new();
-        validate_sort_field_types(&schema, &sort_fields, &mut sort_field_are_datetime).unwrap();
+        validate_sort_field_types(
+            &schema,
+            &sort_fields,
+            &HashMap::new(),
+            &mut sort_field_are_datetime,
+        )
+        .unwrap();
         assert_eq!(sort_field_are_datetime.get("_doc"), Some(&false));
         assert_eq!(sort_field_are_datetime.get("_shard_doc"), Some(&false));
     }
@@ -1886,7 +2279,13 @@ mod tests {
         schema_builder.add_u64_field("id", FAST);
         let schema = schema_builder.build();
         let mut sort_field_are_datetime = HashMap::new();
-        validate_sort_field_types(&schema, &sort_fields, &mut sort_field_are_datetime).unwrap();
+        validate_sort_field_types(
+            &schema,
+            &sort_fields,
+            &HashMap::new(),
+            &mut sort_field_are_datetime,
+        )
+        .unwrap();
         assert_eq!(sort_field_are_datetime.get("timestamp"), Some(&true));
         assert_eq!(sort_field_are_datetime.get("id"), Some(&false));
     }
@@ -1913,9 +2312,13 @@ mod tests {
             let mut sort_field_are_datetime = HashMap::new();
             sort_field_are_datetime.insert("timestamp".to_string(), false);
             sort_field_are_datetime.insert("id".to_string(), false);
-            let error =
-                validate_sort_field_types(&schema, &sort_fields, &mut sort_field_are_datetime)
-                    .unwrap_err();
+            let error = validate_sort_field_types(
+                &schema,
+                &sort_fields,
+                &HashMap::new(),
+                &mut sort_field_are_datetime,
+            )
+            .unwrap_err();
             assert_eq!(
                 error.to_string(),
                 "sort datetime field `timestamp` must be of type datetime on all indexes"
@@ -1924,9 +2327,13 @@ mod tests {
         {
             let mut sort_field_are_datetime = HashMap::new();
             sort_field_are_datetime.insert("id".to_string(), true);
-            let error =
-                validate_sort_field_types(&schema, &sort_fields, &mut sort_field_are_datetime)
-                    .unwrap_err();
+            let error = validate_sort_field_types(
+                &schema,
+                &sort_fields,
+                &HashMap::new(),
+                &mut sort_field_are_datetime,
+            )
+            .unwrap_err();
             assert_eq!(
                 error.to_string(),
                 "sort datetime field `id` must be of type datetime on all indexes"
@@ -2401,6 +2808,118 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_root_search_profile_and_dry_run() -> anyhow::Result<()> {
+        let mut metastore = MetastoreServiceClient::mock();
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
+        let index_uid = index_metadata.index_uid.clone();
+        metastore
+            .expect_list_indexes_metadata()
+            .returning(move |_index_ids_query| {
+                Ok(ListIndexesMetadataResponse::for_test(vec![
+                    index_metadata.clone()
+                ]))
+            });
+        metastore
+            .expect_list_splits()
+            .returning(move |_list_splits_request| {
+                let split1 = MockSplitBuilder::new("split1")
+                    .with_index_uid(&index_uid)
+                    .build();
+                let mut split2 = MockSplitBuilder::new("split2")
+                    .with_index_uid(&index_uid)
+                    .build();
+                split2.split_metadata.time_range = Some(200_000..=210_000);
+                let splits_response =
+                    ListSplitsResponse::try_from_splits(vec![split1, split2]).unwrap();
+                Ok(ServiceStream::from(vec![Ok(splits_response)]))
+            });
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_leaf_search().times(1).returning(
+            |leaf_search_req: quickwit_proto::search::LeafSearchRequest| {
+                let split_ids: Vec<&str> = leaf_search_req
+                    .split_offsets
+                    .iter()
+                    .map(|split_offsets| split_offsets.split_id.as_str())
+                    .collect();
+                assert_eq!(split_ids, ["split2"]);
+                assert!(leaf_search_req.search_request.unwrap().profile);
+                Ok(quickwit_proto::search::LeafSearchResponse {
+                    num_hits: 1,
+                    partial_hits: vec![mock_partial_hit("split2", 1, 1)],
+                    num_attempted_splits: 1,
+                    split_profiles: vec![SplitSearchProfile {
+                        split_id: "split2".to_string(),
+                        num_bytes_downloaded: 1_000,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })
+            },
+        );
+        mock_search_service.expect_fetch_docs().times(1).returning(
+            |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
+                Ok(quickwit_proto::search::FetchDocsResponse {
+                    hits: get_doc_for_fetch_req(fetch_docs_req),
+                })
+            },
+        );
+        let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+        let searcher_context = SearcherContext::for_test();
+        let metastore = MetastoreServiceClient::from(metastore);
+
+        let search_request = quickwit_proto::search::SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: qast_json_helper("test", &["body"]),
+            max_hits: 10,
+            start_timestamp: Some(150_000),
+            dry_run: true,
+            ..Default::default()
+        };
+        let search_response = root_search(
+            &searcher_context,
+            search_request.clone(),
+            metastore.clone(),
+            &cluster_client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(search_response.num_hits, 0);
+        assert!(search_response.hits.is_empty());
+        let search_profile = search_response.profile.unwrap();
+        assert_eq!(search_profile.num_splits, 2);
+        assert_eq!(search_profile.num_splits_pruned_by_time_range, 1);
+        assert_eq!(search_profile.num_splits_pruned_by_tags, 0);
+        assert_eq!(search_profile.split_plans.len(), 1);
+        assert_eq!(search_profile.split_plans[0].split_id, "split2");
+        assert!(search_profile.split_profiles.is_empty());
+
+        let search_request = quickwit_proto::search::SearchRequest {
+            dry_run: false,
+            profile: true,
+            ..search_request
+        };
+        let search_response = root_search(
+            &searcher_context,
+            search_request,
+            metastore,
+            &cluster_client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(search_response.num_hits, 1);
+        assert_eq!(search_response.hits.len(), 1);
+        let search_profile = search_response.profile.unwrap();
+        assert_eq!(search_profile.num_splits_pruned_by_time_range, 1);
+        assert_eq!(search_profile.split_plans.len(), 1);
+        assert_eq!(search_profile.split_profiles.len(), 1);
+        assert_eq!(search_profile.split_profiles[0].split_id, "split2");
+        assert_eq!(search_profile.split_profiles[0].num_bytes_downloaded, 1_000);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_root_search_multiple_splits() -> anyhow::Result<()> {
         let search_request = quickwit_proto::search::SearchRequest {
@@ -2491,6 +3010,239 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_root_search_with_root_search_cache() -> anyhow::Result<()> {
+        let search_request = quickwit_proto::search::SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: qast_json_helper("test", &["body"]),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let mut metastore = MetastoreServiceClient::mock();
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
+        let index_uid = index_metadata.index_uid.clone();
+        metastore
+            .expect_list_indexes_metadata()
+            .returning(move |_index_ids_query| {
+                Ok(ListIndexesMetadataResponse::for_test(vec![
+                    index_metadata.clone()
+                ]))
+            });
+        let mut num_list_splits_calls = 0;
+        metastore.expect_list_splits().returning(move |_filter| {
+            num_list_splits_calls += 1;
+            // The second split is published between the first and the second search.
+            let mut splits = vec![MockSplitBuilder::new("split1")
+                .with_index_uid(&index_uid)
+                .build()];
+            if num_list_splits_calls > 1 {
+                splits.push(
+                    MockSplitBuilder::new("split2")
+                        .with_index_uid(&index_uid)
+                        .build(),
+                );
+            }
+            let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+            Ok(ServiceStream::from(vec![Ok(splits_response)]))
+        });
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_leaf_search()
+            .withf(|leaf_search_req| leaf_search_req.split_offsets[0].split_id == "split1")
+            .times(1)
+            .returning(
+                |_leaf_search_req: quickwit_proto::search::LeafSearchRequest| {
+                    Ok(quickwit_proto::search::LeafSearchResponse {
+                        num_hits: 2,
+                        partial_hits: vec![
+                            mock_partial_hit("split1", 3, 1),
+                            mock_partial_hit("split1", 1, 3),
+                        ],
+                        failed_splits: Vec::new(),
+                        num_attempted_splits: 1,
+                        ..Default::default()
+                    })
+                },
+            );
+        mock_search_service
+            .expect_leaf_search()
+            .withf(|leaf_search_req| leaf_search_req.split_offsets[0].split_id == "split2")
+            .times(1)
+            .returning(
+                |_leaf_search_req: quickwit_proto::search::LeafSearchRequest| {
+                    Ok(quickwit_proto::search::LeafSearchResponse {
+                        num_hits: 1,
+                        partial_hits: vec![mock_partial_hit("split2", 2, 2)],
+                        failed_splits: Vec::new(),
+                        num_attempted_splits: 1,
+                        ..Default::default()
+                    })
+                },
+            );
+        mock_search_service.expect_fetch_docs().returning(
+            |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
+                Ok(quickwit_proto::search::FetchDocsResponse {
+                    hits: get_doc_for_fetch_req(fetch_docs_req),
+                })
+            },
+        );
+        let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+
+        let searcher_config = SearcherConfig {
+            root_search_cache_capacity: ByteSize::mb(1),
+            ..Default::default()
+        };
+        let searcher_context = SearcherContext::new(searcher_config, None);
+        let metastore = MetastoreServiceClient::from(metastore);
+
+        // The first search is executed on `split1` only.
+        let search_response = root_search(
+            &searcher_context,
+            search_request.clone(),
+            metastore.clone(),
+            &cluster_client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(search_response.num_hits, 2);
+        assert_eq!(search_response.hits.len(), 2);
+
+        // The second search only hits `split2` and merges its results with the cached ones.
+        let search_response = root_search(
+            &searcher_context,
+            search_request.clone(),
+            metastore.clone(),
+            &cluster_client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(search_response.num_hits, 3);
+        assert_eq!(search_response.hits.len(), 3);
+        assert!(search_response
+            .hits
+            .iter()
+            .any(|hit| hit.partial_hit.as_ref().unwrap().split_id == "split2"));
+
+        // The third search is served from the cache.
+        let search_response = root_search(
+            &searcher_context,
+            search_request,
+            metastore,
+            &cluster_client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(search_response.num_hits, 3);
+        assert_eq!(search_response.hits.len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_root_search_with_root_search_cache_unaligned_time_range() -> anyhow::Result<()> {
+        let aligned_now_secs = now_secs().div_euclid(60) * 60;
+        let start_timestamp = aligned_now_secs - 590;
+        let end_timestamp = aligned_now_secs - 230;
+        let search_request = quickwit_proto::search::SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: qast_json_helper("test", &["body"]),
+            max_hits: 10,
+            start_timestamp: Some(start_timestamp),
+            end_timestamp: Some(end_timestamp),
+            ..Default::default()
+        };
+        let mut metastore = MetastoreServiceClient::mock();
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
+        let index_uid = index_metadata.index_uid.clone();
+        metastore
+            .expect_list_indexes_metadata()
+            .returning(move |_index_ids_query| {
+                Ok(ListIndexesMetadataResponse::for_test(vec![
+                    index_metadata.clone()
+                ]))
+            });
+        metastore.expect_list_splits().returning(move |_filter| {
+            let splits = vec![MockSplitBuilder::new("split1")
+                .with_index_uid(&index_uid)
+                .build()];
+            let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+            Ok(ServiceStream::from(vec![Ok(splits_response)]))
+        });
+        // The documents of the split, identified by their position, with their timestamp.
+        let doc_timestamps: Vec<i64> = [-600, -595, -590, -570, -300, -235, -230, -200]
+            .into_iter()
+            .map(|offset| aligned_now_secs + offset)
+            .collect();
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_leaf_search().returning(
+            move |leaf_search_req: quickwit_proto::search::LeafSearchRequest| {
+                let search_request = leaf_search_req.search_request.unwrap();
+                let partial_hits: Vec<PartialHit> = doc_timestamps
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, timestamp)| {
+                        search_request
+                            .start_timestamp
+                            .map_or(true, |start_timestamp| **timestamp >= start_timestamp)
+                            && search_request
+                                .end_timestamp
+                                .map_or(true, |end_timestamp| **timestamp < end_timestamp)
+                    })
+                    .map(|(doc_id, timestamp)| {
+                        mock_partial_hit("split1", *timestamp as u64, doc_id as u32)
+                    })
+                    .collect();
+                Ok(quickwit_proto::search::LeafSearchResponse {
+                    num_hits: partial_hits.len() as u64,
+                    partial_hits,
+                    failed_splits: Vec::new(),
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            },
+        );
+        mock_search_service.expect_fetch_docs().returning(
+            |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
+                Ok(quickwit_proto::search::FetchDocsResponse {
+                    hits: get_doc_for_fetch_req(fetch_docs_req),
+                })
+            },
+        );
+        let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+
+        let searcher_config = SearcherConfig {
+            root_search_cache_capacity: ByteSize::mb(1),
+            ..Default::default()
+        };
+        let searcher_context = SearcherContext::new(searcher_config, None);
+        let metastore = MetastoreServiceClient::from(metastore);
+
+        // The second search is served from the cache for the aligned part of the time range.
+        for _ in 0..2 {
+            let search_response = root_search(
+                &searcher_context,
+                search_request.clone(),
+                metastore.clone(),
+                &cluster_client,
+            )
+            .await
+            .unwrap();
+            assert_eq!(search_response.num_hits, 4);
+
+            let mut doc_ids: Vec<u32> = search_response
+                .hits
+                .iter()
+                .map(|hit| hit.partial_hit.as_ref().unwrap().doc_id)
+                .collect();
+            doc_ids.sort_unstable();
+            assert_eq!(doc_ids, [2, 3, 4, 5]);
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_root_search_multiple_splits_sort_heteregeneous_field_ascending(
     ) -> anyhow::Result<()> {
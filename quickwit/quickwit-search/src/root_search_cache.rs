@@ -0,0 +1,460 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use quickwit_metastore::SplitMetadata;
+use quickwit_proto::search::{LeafSearchResponse, SearchPriority, SearchRequest, SearchResponse};
+use quickwit_proto::types::SplitId;
+use quickwit_storage::{MemorySizedCache, OwnedBytes};
+use serde::{Deserialize, Serialize};
+
+/// A cache to memoize `root_search` results.
+///
+/// Entries are keyed on the normalized search request. Each entry remembers the set of splits
+/// (and their delete opstamps) the response was computed on, so that:
+/// - if the set of splits returned by the metastore did not change, the merged response is returned
+///   as is;
+/// - if the set of splits only grew, which is typical of dashboards refreshing a recent time range,
+///   only the new splits need to be searched, and their results are merged with the cached ones.
+///
+/// Any other change (split merged, deleted, or with a new delete opstamp) invalidates the entry.
+///
+/// Only requests on a recent time range are cached: see [`RootSearchCache::split_time_range`].
+pub struct RootSearchCache {
+    content: MemorySizedCache<SearchRequest>,
+}
+
+/// Requests whose time range ends less than an hour ago, or is open-ended, are considered recent.
+const RECENT_TIME_RANGE_HORIZON_SECS: i64 = 3_600;
+
+/// Granularity to which the cached part of the time range of recent requests is aligned.
+const TIME_RANGE_ALIGNMENT_SECS: i64 = 60;
+
+/// The outcome of a [`RootSearchCache`] lookup.
+#[derive(Debug)]
+pub(crate) enum RootSearchCacheLookup {
+    /// The request was already executed on the exact same set of splits. `search_response_opt` is
+    /// `None` if the response of the request itself was not cached, because it was merged with
+    /// the results of the edges of the time range of the original request.
+    Hit {
+        leaf_search_response: LeafSearchResponse,
+        search_response_opt: Option<SearchResponse>,
+    },
+    /// The request was already executed on a subset of the splits. `leaf_search_response` is the
+    /// merged, untruncated by `start_offset`, result of the leaf searches on those splits.
+    Partial {
+        leaf_search_response: LeafSearchResponse,
+        cached_split_ids: HashSet<SplitId>,
+    },
+    Miss,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// The splits on which the request was executed, with their delete opstamp, sorted by split
+    /// ID.
+    splits: Vec<(SplitId, u64)>,
+    /// The merged leaf search responses, computed with a `start_offset` of 0.
+    leaf_search_response: LeafSearchResponse,
+    search_response_opt: Option<SearchResponse>,
+}
+
+impl RootSearchCache {
+    pub fn new(capacity: usize) -> RootSearchCache {
+        RootSearchCache {
+            content: MemorySizedCache::with_capacity_in_bytes(
+                capacity,
+                &quickwit_storage::STORAGE_METRICS.root_search_cache,
+            ),
+        }
+    }
+
+    /// Returns whether the results of a request can be cached. Scroll requests are not cached
+    /// because they have side effects: they populate the scroll context. Profiled requests are not
+    /// cached either, since their response describes how the search was actually executed.
+    pub fn is_cacheable(search_request: &SearchRequest) -> bool {
+        search_request.scroll_ttl_secs.is_none() && !search_request.profile
+    }
+
+    /// Returns whether the time range of a request is recent, i.e. ends less than an hour before
+    /// `now_secs` or is open-ended. Older time ranges are rarely queried twice.
+    pub fn is_recent(search_request: &SearchRequest, now_secs: i64) -> bool {
+        search_request.end_timestamp.map_or(true, |end_timestamp| {
+            end_timestamp >= now_secs - RECENT_TIME_RANGE_HORIZON_SECS
+        })
+    }
+
+    /// Splits the time range of a request into a range aligned on minute boundaries, whose results
+    /// are cached, and the at most two unaligned edges of the time range, which are searched on
+    /// every request. Dashboards refreshing a rolling time range, e.g. the last 15 minutes, then
+    /// keep hitting the same cache entry for a minute, and only the splits published in the
+    /// meantime and the edges are searched. Merging the results of the aligned range and of the
+    /// edges yields exactly the results of the original time range.
+    ///
+    /// Returns `None` if the time range does not contain a whole aligned minute.
+    pub fn split_time_range(
+        search_request: &SearchRequest,
+    ) -> Option<(SearchRequest, Vec<SearchRequest>)> {
+        let aligned_start_timestamp_opt = search_request.start_timestamp.map(|start_timestamp| {
+            (start_timestamp + TIME_RANGE_ALIGNMENT_SECS - 1).div_euclid(TIME_RANGE_ALIGNMENT_SECS)
+                * TIME_RANGE_ALIGNMENT_SECS
+        });
+        // The end timestamp is exclusive.
+        let aligned_end_timestamp_opt = search_request.end_timestamp.map(|end_timestamp| {
+            end_timestamp.div_euclid(TIME_RANGE_ALIGNMENT_SECS) * TIME_RANGE_ALIGNMENT_SECS
+        });
+        if let (Some(aligned_start_timestamp), Some(aligned_end_timestamp)) =
+            (aligned_start_timestamp_opt, aligned_end_timestamp_opt)
+        {
+            if aligned_start_timestamp >= aligned_end_timestamp {
+                return None;
+            }
+        }
+        let mut edge_search_requests = Vec::new();
+
+        if let (Some(start_timestamp), Some(aligned_start_timestamp)) =
+            (search_request.start_timestamp, aligned_start_timestamp_opt)
+        {
+            if start_timestamp != aligned_start_timestamp {
+                edge_search_requests.push(SearchRequest {
+                    start_timestamp: Some(start_timestamp),
+                    end_timestamp: Some(aligned_start_timestamp),
+                    ..search_request.clone()
+                });
+            }
+        }
+        if let (Some(end_timestamp), Some(aligned_end_timestamp)) =
+            (search_request.end_timestamp, aligned_end_timestamp_opt)
+        {
+            if end_timestamp != aligned_end_timestamp {
+                edge_search_requests.push(SearchRequest {
+                    start_timestamp: Some(aligned_end_timestamp),
+                    end_timestamp: Some(end_timestamp),
+                    ..search_request.clone()
+                });
+            }
+        }
+        let aligned_search_request = SearchRequest {
+            start_timestamp: aligned_start_timestamp_opt,
+            end_timestamp: aligned_end_timestamp_opt,
+            ..search_request.clone()
+        };
+        Some((aligned_search_request, edge_search_requests))
+    }
+
+    pub(crate) fn get(
+        &self,
+        search_request: &SearchRequest,
+        split_metadatas: &[SplitMetadata],
+    ) -> RootSearchCacheLookup {
+        if !Self::is_recent(search_request, now_secs()) {
+            return RootSearchCacheLookup::Miss;
+        }
+        let key = normalize_search_request(search_request);
+        let Some(encoded_entry) = self.content.get(&key) else {
+            return RootSearchCacheLookup::Miss;
+        };
+        // this should never fail
+        let Ok(entry) = postcard::from_bytes::<CacheEntry>(&encoded_entry) else {
+            return RootSearchCacheLookup::Miss;
+        };
+        let delete_opstamps: HashMap<&str, u64> = split_metadatas
+            .iter()
+            .map(|split_metadata| {
+                (
+                    split_metadata.split_id.as_str(),
+                    split_metadata.delete_opstamp,
+                )
+            })
+            .collect();
+        let all_cached_splits_unchanged = entry.splits.iter().all(|(split_id, delete_opstamp)| {
+            delete_opstamps.get(split_id.as_str()) == Some(delete_opstamp)
+        });
+        if !all_cached_splits_unchanged {
+            return RootSearchCacheLookup::Miss;
+        }
+        if entry.splits.len() == split_metadatas.len() {
+            return RootSearchCacheLookup::Hit {
+                leaf_search_response: entry.leaf_search_response,
+                search_response_opt: entry.search_response_opt,
+            };
+        }
+        let cached_split_ids: HashSet<SplitId> = entry
+            .splits
+            .into_iter()
+            .map(|(split_id, _)| split_id)
+            .collect();
+        RootSearchCacheLookup::Partial {
+            leaf_search_response: entry.leaf_search_response,
+            cached_split_ids,
+        }
+    }
+
+    pub(crate) fn put(
+        &self,
+        search_request: &SearchRequest,
+        split_metadatas: &[SplitMetadata],
+        leaf_search_response: LeafSearchResponse,
+        search_response_opt: Option<SearchResponse>,
+    ) {
+        if !Self::is_recent(search_request, now_secs()) {
+            return;
+        }
+        let key = normalize_search_request(search_request);
+        let mut splits: Vec<(SplitId, u64)> = split_metadatas
+            .iter()
+            .map(|split_metadata| {
+                (
+                    split_metadata.split_id.clone(),
+                    split_metadata.delete_opstamp,
+                )
+            })
+            .collect();
+        splits.sort_unstable();
+        let entry = CacheEntry {
+            splits,
+            leaf_search_response,
+            search_response_opt,
+        };
+        // this should never fail
+        let Ok(encoded_entry) = postcard::to_allocvec(&entry) else {
+            return;
+        };
+        self.content.put(key, OwnedBytes::new(encoded_entry));
+    }
+}
+
+pub(crate) fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Removes the parts of the request that do not influence its result.
+fn normalize_search_request(search_request: &SearchRequest) -> SearchRequest {
+    let mut normalized_search_request = search_request.clone();
+    normalized_search_request.index_id_patterns.sort_unstable();
+    normalized_search_request.index_id_patterns.dedup();
+    normalized_search_request.priority = SearchPriority::Interactive.into();
+    normalized_search_request
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::SplitMetadata;
+    use quickwit_proto::search::{LeafSearchResponse, SearchRequest, SearchResponse};
+
+    use super::{RootSearchCache, RootSearchCacheLookup};
+
+    fn mock_split(split_id: &str, delete_opstamp: u64) -> SplitMetadata {
+        SplitMetadata {
+            split_id: split_id.to_string(),
+            delete_opstamp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_root_search_cache() {
+        let cache = RootSearchCache::new(64_000_000);
+
+        let search_request = SearchRequest {
+            index_id_patterns: vec!["test-idx-2".to_string(), "test-idx-1".to_string()],
+            query_ast: "test".to_string(),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let search_request_reordered = SearchRequest {
+            index_id_patterns: vec!["test-idx-1".to_string(), "test-idx-2".to_string()],
+            ..search_request.clone()
+        };
+        let other_search_request = SearchRequest {
+            query_ast: "test2".to_string(),
+            ..search_request.clone()
+        };
+        let leaf_search_response = LeafSearchResponse {
+            num_hits: 1234,
+            num_attempted_splits: 2,
+            ..Default::default()
+        };
+        let search_response = SearchResponse {
+            num_hits: 1234,
+            ..Default::default()
+        };
+        let splits = vec![mock_split("split_1", 0), mock_split("split_2", 0)];
+
+        assert!(matches!(
+            cache.get(&search_request, &splits),
+            RootSearchCacheLookup::Miss
+        ));
+        cache.put(
+            &search_request,
+            &splits,
+            leaf_search_response.clone(),
+            Some(search_response.clone()),
+        );
+
+        let RootSearchCacheLookup::Hit {
+            leaf_search_response: cached_leaf_search_response,
+            search_response_opt: cached_search_response_opt,
+        } = cache.get(&search_request_reordered, &splits)
+        else {
+            panic!("expected a cache hit");
+        };
+        assert_eq!(cached_leaf_search_response, leaf_search_response);
+        assert_eq!(cached_search_response_opt, Some(search_response));
+
+        assert!(matches!(
+            cache.get(&other_search_request, &splits),
+            RootSearchCacheLookup::Miss
+        ));
+
+        // A new split was published.
+        let splits_with_new_split = vec![
+            mock_split("split_1", 0),
+            mock_split("split_2", 0),
+            mock_split("split_3", 0),
+        ];
+        let RootSearchCacheLookup::Partial {
+            leaf_search_response: cached_leaf_search_response,
+            cached_split_ids,
+        } = cache.get(&search_request, &splits_with_new_split)
+        else {
+            panic!("expected a partial cache hit");
+        };
+        assert_eq!(cached_leaf_search_response, leaf_search_response);
+        assert_eq!(cached_split_ids.len(), 2);
+        assert!(cached_split_ids.contains("split_1"));
+        assert!(cached_split_ids.contains("split_2"));
+
+        // The splits were merged.
+        let merged_splits = vec![mock_split("split_4", 0)];
+        assert!(matches!(
+            cache.get(&search_request, &merged_splits),
+            RootSearchCacheLookup::Miss
+        ));
+
+        // A delete task was applied to one of the splits.
+        let splits_with_delete = vec![mock_split("split_1", 0), mock_split("split_2", 1)];
+        assert!(matches!(
+            cache.get(&search_request, &splits_with_delete),
+            RootSearchCacheLookup::Miss
+        ));
+    }
+
+    #[test]
+    fn test_root_search_cache_is_cacheable() {
+        let search_request = SearchRequest::default();
+        assert!(RootSearchCache::is_cacheable(&search_request));
+
+        let scroll_search_request = SearchRequest {
+            scroll_ttl_secs: Some(30),
+            ..Default::default()
+        };
+        assert!(!RootSearchCache::is_cacheable(&scroll_search_request));
+
+        let profile_search_request = SearchRequest {
+            profile: true,
+            ..Default::default()
+        };
+        assert!(!RootSearchCache::is_cacheable(&profile_search_request));
+    }
+
+    #[test]
+    fn test_root_search_cache_is_recent() {
+        let now_secs = 10_000;
+        let search_request = SearchRequest::default();
+        assert!(RootSearchCache::is_recent(&search_request, now_secs));
+
+        let recent_search_request = SearchRequest {
+            start_timestamp: Some(now_secs - 900),
+            end_timestamp: Some(now_secs),
+            ..Default::default()
+        };
+        assert!(RootSearchCache::is_recent(&recent_search_request, now_secs));
+
+        let old_search_request = SearchRequest {
+            end_timestamp: Some(now_secs - 7_200),
+            ..Default::default()
+        };
+        assert!(!RootSearchCache::is_recent(&old_search_request, now_secs));
+    }
+
+    #[test]
+    fn test_root_search_cache_ignores_old_time_ranges() {
+        let cache = RootSearchCache::new(64_000_000);
+        let search_request = SearchRequest {
+            end_timestamp: Some(1_000),
+            ..Default::default()
+        };
+        let splits = vec![mock_split("split_1", 0)];
+        cache.put(
+            &search_request,
+            &splits,
+            LeafSearchResponse::default(),
+            Some(SearchResponse::default()),
+        );
+        assert!(matches!(
+            cache.get(&search_request, &splits),
+            RootSearchCacheLookup::Miss
+        ));
+    }
+
+    #[test]
+    fn test_root_search_cache_split_time_range() {
+        let search_request = SearchRequest {
+            start_timestamp: Some(1_000_030),
+            end_timestamp: Some(1_000_930),
+            ..Default::default()
+        };
+        let (aligned_search_request, edge_search_requests) =
+            RootSearchCache::split_time_range(&search_request).unwrap();
+        assert_eq!(aligned_search_request.start_timestamp, Some(1_000_080));
+        assert_eq!(aligned_search_request.end_timestamp, Some(1_000_920));
+        assert_eq!(edge_search_requests.len(), 2);
+        assert_eq!(edge_search_requests[0].start_timestamp, Some(1_000_030));
+        assert_eq!(edge_search_requests[0].end_timestamp, Some(1_000_080));
+        assert_eq!(edge_search_requests[1].start_timestamp, Some(1_000_920));
+        assert_eq!(edge_search_requests[1].end_timestamp, Some(1_000_930));
+
+        // Aligned time ranges have no edges.
+        let (aligned_search_request, edge_search_requests) =
+            RootSearchCache::split_time_range(&aligned_search_request).unwrap();
+        assert_eq!(aligned_search_request.start_timestamp, Some(1_000_080));
+        assert_eq!(aligned_search_request.end_timestamp, Some(1_000_920));
+        assert!(edge_search_requests.is_empty());
+
+        let (aligned_search_request, edge_search_requests) =
+            RootSearchCache::split_time_range(&SearchRequest::default()).unwrap();
+        assert!(aligned_search_request.start_timestamp.is_none());
+        assert!(aligned_search_request.end_timestamp.is_none());
+        assert!(edge_search_requests.is_empty());
+
+        // The time range does not contain a whole aligned minute.
+        let search_request = SearchRequest {
+            start_timestamp: Some(1_000_030),
+            end_timestamp: Some(1_000_100),
+            ..Default::default()
+        };
+        assert!(RootSearchCache::split_time_range(&search_request).is_none());
+    }
+}
@@ -0,0 +1,1141 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Aggregations over runtime fields.
+//!
+//! Tantivy aggregations read the values of the documents from the fast field columns of the
+//! segments, which runtime fields do not have. When an aggregation request references a runtime
+//! field, the whole request is evaluated by the collector of this module instead, which computes
+//! the values of the runtime fields, and reads the values of the other fast fields, document per
+//! document.
+//!
+//! The `terms`, `histogram`, and `date_histogram` bucket aggregations, with nested
+//! aggregations, and the `avg`, `min`, `max`, `sum`, `stats`, and `value_count` metric
+//! aggregations are supported. Their results have the same format as the results of the
+//! equivalent Tantivy aggregations.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use quickwit_datetime::TantivyDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tantivy::collector::SegmentCollector;
+use tantivy::columnar::MonotonicallyMappableToU64;
+use tantivy::{DocId, Score, SegmentReader, TantivyError};
+
+use crate::runtime_fields::{format_date_time, FastFieldColumns, RuntimeField, RuntimeValue};
+use crate::SearchError;
+
+/// Maximum number of buckets of a response, same as the default bucket limit of Tantivy
+/// aggregations.
+const MAX_NUM_BUCKETS: usize = 65_000;
+
+const DEFAULT_TERMS_SIZE: u32 = 10;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MetricKind {
+    Avg,
+    Min,
+    Max,
+    Sum,
+    Stats,
+    ValueCount,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum AggregationKind {
+    Metric(MetricKind),
+    Terms {
+        size: usize,
+        // Number of buckets kept per segment and per leaf.
+        shard_size: usize,
+        min_doc_count: u64,
+    },
+    Histogram {
+        interval: f64,
+        offset: f64,
+        min_doc_count: u64,
+    },
+    DateHistogram {
+        interval_millis: i64,
+        offset_millis: i64,
+        min_doc_count: u64,
+    },
+}
+
+impl AggregationKind {
+    fn empty_result(&self) -> IntermediateRuntimeAggregationResult {
+        match self {
+            AggregationKind::Metric(_) => {
+                IntermediateRuntimeAggregationResult::Metric(IntermediateStats::default())
+            }
+            _ => IntermediateRuntimeAggregationResult::Buckets(IntermediateBuckets::default()),
+        }
+    }
+
+    fn bucket_key(&self, value: RuntimeValue) -> Option<BucketKey> {
+        match self {
+            AggregationKind::Metric(_) => None,
+            AggregationKind::Terms { .. } => BucketKey::from_value(value),
+            AggregationKind::Histogram {
+                interval, offset, ..
+            } => {
+                let bucket_index = ((value.as_f64()? - offset) / interval).floor();
+                Some(BucketKey::I64(bucket_index as i64))
+            }
+            AggregationKind::DateHistogram {
+                interval_millis,
+                offset_millis,
+                ..
+            } => {
+                let timestamp_millis = value.as_date_time()?.into_timestamp_millis();
+                let bucket_index = timestamp_millis
+                    .saturating_sub(*offset_millis)
+                    .div_euclid(*interval_millis);
+                Some(BucketKey::I64(bucket_index))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct RuntimeFieldAggregation {
+    field: String,
+    kind: AggregationKind,
+    sub_aggregations: Vec<(String, RuntimeFieldAggregation)>,
+}
+
+/// Aggregation request referencing runtime fields.
+#[derive(Clone, Debug)]
+pub struct RuntimeFieldAggregations {
+    aggregations: Arc<Vec<(String, RuntimeFieldAggregation)>>,
+    runtime_fields: Arc<Vec<RuntimeField>>,
+}
+
+impl RuntimeFieldAggregations {
+    /// Parses an aggregation request, in the Elasticsearch format, referencing the given runtime
+    /// fields.
+    pub(crate) fn try_new(
+        aggregations_json: &str,
+        runtime_fields: Vec<RuntimeField>,
+    ) -> crate::Result<Self> {
+        let aggregations_json: JsonMap<String, JsonValue> =
+            serde_json::from_str(aggregations_json)?;
+        let aggregations = parse_aggregations(&aggregations_json)
+            .map_err(SearchError::InvalidAggregationRequest)?;
+        Ok(RuntimeFieldAggregations {
+            aggregations: Arc::new(aggregations),
+            runtime_fields: Arc::new(runtime_fields),
+        })
+    }
+
+    /// Returns the names of the fast fields read by the aggregations, including the fields
+    /// referenced by the runtime fields.
+    pub(crate) fn fast_field_names(&self) -> HashSet<String> {
+        let mut field_names = HashSet::new();
+        collect_field_names(&self.aggregations, &mut field_names);
+
+        let mut fast_field_names = HashSet::with_capacity(field_names.len());
+        for field_name in field_names {
+            if let Some(runtime_field) = self.find_runtime_field(&field_name) {
+                fast_field_names.extend(runtime_field.field_names());
+            } else {
+                fast_field_names.insert(field_name);
+            }
+        }
+        fast_field_names
+    }
+
+    pub(crate) fn for_segment(
+        &self,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<RuntimeFieldAggregationSegmentCollector> {
+        let columns = FastFieldColumns::open(segment_reader, self.fast_field_names())?;
+        Ok(RuntimeFieldAggregationSegmentCollector {
+            aggregations: self.clone(),
+            columns,
+            results: IntermediateRuntimeAggregationResults::default(),
+            num_buckets: 0,
+            error_opt: None,
+        })
+    }
+
+    /// Merges the intermediate results of several segments or splits.
+    pub(crate) fn merge_fruits(
+        &self,
+        fruits: Vec<IntermediateRuntimeAggregationResults>,
+    ) -> IntermediateRuntimeAggregationResults {
+        let mut merged_results = IntermediateRuntimeAggregationResults::default();
+
+        for fruit in fruits {
+            merged_results.merge(fruit);
+        }
+        truncate_terms(&self.aggregations, &mut merged_results);
+        merged_results
+    }
+
+    /// Builds the final results of the aggregations.
+    pub(crate) fn finalize(
+        &self,
+        results: IntermediateRuntimeAggregationResults,
+    ) -> crate::Result<JsonValue> {
+        finalize_aggregations(&self.aggregations, results)
+            .map(JsonValue::Object)
+            .map_err(SearchError::InvalidAggregationRequest)
+    }
+
+    fn find_runtime_field(&self, field_name: &str) -> Option<&RuntimeField> {
+        self.runtime_fields
+            .iter()
+            .find(|runtime_field| runtime_field.name() == field_name)
+    }
+
+    fn field_value(
+        &self,
+        columns: &FastFieldColumns,
+        field_name: &str,
+        doc_id: DocId,
+    ) -> RuntimeValue {
+        match self.find_runtime_field(field_name) {
+            Some(runtime_field) => {
+                runtime_field.eval(&|field_name| columns.value(field_name, doc_id))
+            }
+            None => columns.value(field_name, doc_id),
+        }
+    }
+}
+
+fn collect_field_names(
+    aggregations: &[(String, RuntimeFieldAggregation)],
+    field_names: &mut HashSet<String>,
+) {
+    for (_, aggregation) in aggregations {
+        field_names.insert(aggregation.field.clone());
+        collect_field_names(&aggregation.sub_aggregations, field_names);
+    }
+}
+
+fn parse_aggregations(
+    aggregations_json: &JsonMap<String, JsonValue>,
+) -> Result<Vec<(String, RuntimeFieldAggregation)>, String> {
+    let mut aggregations = Vec::with_capacity(aggregations_json.len());
+
+    for (name, aggregation_json) in aggregations_json {
+        let JsonValue::Object(aggregation_json) = aggregation_json else {
+            return Err(format!("aggregation `{name}` must be an object"));
+        };
+        let mut field_and_kind_opt = None;
+        let mut sub_aggregations = Vec::new();
+
+        for (key, value) in aggregation_json {
+            if key == "aggs" || key == "aggregations" {
+                let JsonValue::Object(sub_aggregations_json) = value else {
+                    return Err(format!(
+                        "the sub-aggregations of aggregation `{name}` must be an object"
+                    ));
+                };
+                sub_aggregations = parse_aggregations(sub_aggregations_json)?;
+            } else if field_and_kind_opt.is_some() {
+                return Err(format!("aggregation `{name}` must have a single type"));
+            } else {
+                let field_and_kind = parse_aggregation_kind(key, value.clone())
+                    .map_err(|error| format!("invalid aggregation `{name}`: {error}"))?;
+                field_and_kind_opt = Some(field_and_kind);
+            }
+        }
+        let Some((field, kind)) = field_and_kind_opt else {
+            return Err(format!("aggregation `{name}` must have a type"));
+        };
+        if matches!(kind, AggregationKind::Metric(_)) && !sub_aggregations.is_empty() {
+            return Err(format!(
+                "metric aggregation `{name}` cannot have sub-aggregations"
+            ));
+        }
+        let aggregation = RuntimeFieldAggregation {
+            field,
+            kind,
+            sub_aggregations,
+        };
+        aggregations.push((name.clone(), aggregation));
+    }
+    Ok(aggregations)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MetricParams {
+    field: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TermsParams {
+    field: String,
+    #[serde(default)]
+    size: Option<u32>,
+    #[serde(default, alias = "split_size")]
+    shard_size: Option<u32>,
+    #[serde(default)]
+    min_doc_count: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HistogramParams {
+    field: String,
+    interval: f64,
+    #[serde(default)]
+    offset: Option<f64>,
+    #[serde(default)]
+    min_doc_count: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DateHistogramParams {
+    field: String,
+    fixed_interval: String,
+    #[serde(default)]
+    offset: Option<String>,
+    #[serde(default)]
+    min_doc_count: Option<u64>,
+}
+
+fn parse_aggregation_kind(
+    aggregation_type: &str,
+    params_json: JsonValue,
+) -> Result<(String, AggregationKind), String> {
+    let field_and_kind = match aggregation_type {
+        "avg" | "min" | "max" | "sum" | "stats" | "value_count" => {
+            let params: MetricParams =
+                serde_json::from_value(params_json).map_err(|error| error.to_string())?;
+            let metric_kind = match aggregation_type {
+                "avg" => MetricKind::Avg,
+                "min" => MetricKind::Min,
+                "max" => MetricKind::Max,
+                "sum" => MetricKind::Sum,
+                "stats" => MetricKind::Stats,
+                _ => MetricKind::ValueCount,
+            };
+            (params.field, AggregationKind::Metric(metric_kind))
+        }
+        "terms" => {
+            let params: TermsParams =
+                serde_json::from_value(params_json).map_err(|error| error.to_string())?;
+            let size = params.size.unwrap_or(DEFAULT_TERMS_SIZE) as usize;
+            let shard_size = params
+                .shard_size
+                .map(|shard_size| shard_size as usize)
+                .unwrap_or(size.saturating_mul(10))
+                .max(size);
+            let kind = AggregationKind::Terms {
+                size,
+                shard_size,
+                min_doc_count: params.min_doc_count.unwrap_or(1),
+            };
+            (params.field, kind)
+        }
+        "histogram" => {
+            let params: HistogramParams =
+                serde_json::from_value(params_json).map_err(|error| error.to_string())?;
+            if !params.interval.is_finite() || params.interval <= 0.0 {
+                return Err(format!(
+                    "the interval must be a positive number, got `{}`",
+                    params.interval
+                ));
+            }
+            let kind = AggregationKind::Histogram {
+                interval: params.interval,
+                offset: params.offset.unwrap_or(0.0),
+                min_doc_count: params.min_doc_count.unwrap_or(0),
+            };
+            (params.field, kind)
+        }
+        "date_histogram" => {
+            let params: DateHistogramParams =
+                serde_json::from_value(params_json).map_err(|error| error.to_string())?;
+            let interval_millis = parse_duration_millis(&params.fixed_interval)?;
+            if interval_millis <= 0 {
+                return Err(format!(
+                    "the fixed interval must be positive, got `{}`",
+                    params.fixed_interval
+                ));
+            }
+            let offset_millis = match &params.offset {
+                Some(offset) => parse_duration_millis(offset)?,
+                None => 0,
+            };
+            let kind = AggregationKind::DateHistogram {
+                interval_millis,
+                offset_millis,
+                min_doc_count: params.min_doc_count.unwrap_or(0),
+            };
+            (params.field, kind)
+        }
+        _ => {
+            return Err(format!(
+                "`{aggregation_type}` aggregations are not supported in requests referencing \
+                 runtime fields"
+            ))
+        }
+    };
+    Ok(field_and_kind)
+}
+
+/// Parses durations such as `30s`, `1h`, or `-1d` into milliseconds.
+fn parse_duration_millis(duration_str: &str) -> Result<i64, String> {
+    let (sign, unsigned_duration_str) = match duration_str.strip_prefix('-') {
+        Some(unsigned_duration_str) => (-1, unsigned_duration_str),
+        None => (1, duration_str.strip_prefix('+').unwrap_or(duration_str)),
+    };
+    let num_digits = unsigned_duration_str
+        .chars()
+        .take_while(|character| character.is_ascii_digit())
+        .count();
+    let (value_str, unit) = unsigned_duration_str.split_at(num_digits);
+    let value: i64 = value_str
+        .parse()
+        .map_err(|_| format!("invalid duration `{duration_str}`"))?;
+    let unit_millis = match unit {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => {
+            return Err(format!(
+                "invalid duration `{duration_str}`, the unit must be one of `ms`, `s`, `m`, `h`, \
+                 or `d`"
+            ))
+        }
+    };
+    value
+        .checked_mul(unit_millis)
+        .map(|millis| sign * millis)
+        .ok_or_else(|| format!("duration `{duration_str}` is too large"))
+}
+
+/// Key of a bucket. Floats are stored in their order-preserving `u64` representation and dates as
+/// nanoseconds since the Unix epoch.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+enum BucketKey {
+    Bool(bool),
+    I64(i64),
+    F64(u64),
+    Str(String),
+    DateTime(i64),
+}
+
+impl BucketKey {
+    fn from_value(value: RuntimeValue) -> Option<Self> {
+        match value {
+            RuntimeValue::Null => None,
+            RuntimeValue::Bool(value) => Some(BucketKey::Bool(value)),
+            RuntimeValue::I64(value) => Some(BucketKey::I64(value)),
+            RuntimeValue::F64(value) => Some(BucketKey::F64(value.to_u64())),
+            RuntimeValue::Str(value) => Some(BucketKey::Str(value)),
+            RuntimeValue::DateTime(value) => {
+                Some(BucketKey::DateTime(value.into_timestamp_nanos()))
+            }
+        }
+    }
+
+    /// Adds the `key` and, for booleans and dates, the `key_as_string` of a terms bucket.
+    fn add_to_bucket_json(self, bucket_json: &mut JsonMap<String, JsonValue>) {
+        let (key, key_as_string_opt) = match self {
+            BucketKey::Bool(value) => (json!(value as i64), Some(value.to_string())),
+            BucketKey::I64(value) => (json!(value), None),
+            BucketKey::F64(value) => (json!(f64::from_u64(value)), None),
+            BucketKey::Str(value) => (json!(value), None),
+            BucketKey::DateTime(timestamp_nanos) => {
+                let date_time = TantivyDateTime::from_timestamp_nanos(timestamp_nanos);
+                (
+                    json!(date_time.into_timestamp_millis()),
+                    format_date_time(date_time),
+                )
+            }
+        };
+        bucket_json.insert("key".to_string(), key);
+
+        if let Some(key_as_string) = key_as_string_opt {
+            bucket_json.insert("key_as_string".to_string(), json!(key_as_string));
+        }
+    }
+}
+
+/// Intermediate results of runtime field aggregations, merged across segments, splits, and
+/// leaves.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateRuntimeAggregationResults(
+    BTreeMap<String, IntermediateRuntimeAggregationResult>,
+);
+
+impl IntermediateRuntimeAggregationResults {
+    fn merge(&mut self, other: IntermediateRuntimeAggregationResults) {
+        for (name, other_result) in other.0 {
+            match self.0.entry(name) {
+                Entry::Vacant(entry) => {
+                    entry.insert(other_result);
+                }
+                Entry::Occupied(mut entry) => entry.get_mut().merge(other_result),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum IntermediateRuntimeAggregationResult {
+    Metric(IntermediateStats),
+    Buckets(IntermediateBuckets),
+}
+
+impl IntermediateRuntimeAggregationResult {
+    fn merge(&mut self, other: IntermediateRuntimeAggregationResult) {
+        // Results with the same name come from the same aggregation and have the same variant.
+        match (self, other) {
+            (
+                IntermediateRuntimeAggregationResult::Metric(stats),
+                IntermediateRuntimeAggregationResult::Metric(other_stats),
+            ) => stats.merge(other_stats),
+            (
+                IntermediateRuntimeAggregationResult::Buckets(buckets),
+                IntermediateRuntimeAggregationResult::Buckets(other_buckets),
+            ) => buckets.merge(other_buckets),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct IntermediateStats {
+    // Number of non-null values.
+    value_count: u64,
+    // Number of numeric values.
+    count: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl IntermediateStats {
+    fn collect(&mut self, value: &RuntimeValue) {
+        self.value_count += 1;
+
+        let Some(value) = value.as_f64() else {
+            return;
+        };
+        self.count += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    fn merge(&mut self, other: IntermediateStats) {
+        self.value_count += other.value_count;
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = match (self.min, other.min) {
+            (Some(min), Some(other_min)) => Some(min.min(other_min)),
+            (min_opt, other_min_opt) => min_opt.or(other_min_opt),
+        };
+        self.max = match (self.max, other.max) {
+            (Some(max), Some(other_max)) => Some(max.max(other_max)),
+            (max_opt, other_max_opt) => max_opt.or(other_max_opt),
+        };
+    }
+
+    fn finalize(self, metric_kind: MetricKind) -> JsonValue {
+        let avg_opt = (self.count > 0).then(|| self.sum / self.count as f64);
+        match metric_kind {
+            MetricKind::Avg => json!({"value": avg_opt}),
+            MetricKind::Min => json!({"value": self.min}),
+            MetricKind::Max => json!({"value": self.max}),
+            MetricKind::Sum => json!({"value": self.sum}),
+            MetricKind::ValueCount => json!({"value": self.value_count as f64}),
+            MetricKind::Stats => json!({
+                "count": self.count,
+                "min": self.min,
+                "max": self.max,
+                "sum": self.sum,
+                "avg": avg_opt,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct IntermediateBuckets {
+    buckets: BTreeMap<BucketKey, IntermediateBucket>,
+    // Number of documents in the buckets dropped by `truncate`.
+    sum_other_doc_count: u64,
+    doc_count_error_upper_bound: u64,
+}
+
+impl IntermediateBuckets {
+    fn merge(&mut self, other: IntermediateBuckets) {
+        self.sum_other_doc_count += other.sum_other_doc_count;
+        self.doc_count_error_upper_bound += other.doc_count_error_upper_bound;
+
+        for (bucket_key, other_bucket) in other.buckets {
+            match self.buckets.entry(bucket_key) {
+                Entry::Vacant(entry) => {
+                    entry.insert(other_bucket);
+                }
+                Entry::Occupied(mut entry) => {
+                    let bucket = entry.get_mut();
+                    bucket.doc_count += other_bucket.doc_count;
+                    bucket.sub_aggregations.merge(other_bucket.sub_aggregations);
+                }
+            }
+        }
+    }
+
+    /// Keeps the `num_buckets` buckets with the most documents.
+    fn truncate(&mut self, num_buckets: usize) {
+        if self.buckets.len() <= num_buckets {
+            return;
+        }
+        let mut buckets = sort_buckets_by_doc_count(std::mem::take(&mut self.buckets));
+        let dropped_buckets = buckets.split_off(num_buckets);
+
+        // The count of a term that was dropped here but returned in the end is underestimated by
+        // at most the count of the largest dropped bucket.
+        if let Some((_, largest_dropped_bucket)) = dropped_buckets.first() {
+            self.doc_count_error_upper_bound += largest_dropped_bucket.doc_count;
+        }
+        self.sum_other_doc_count += dropped_buckets
+            .iter()
+            .map(|(_, bucket)| bucket.doc_count)
+            .sum::<u64>();
+        self.buckets = buckets.into_iter().collect();
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct IntermediateBucket {
+    doc_count: u64,
+    sub_aggregations: IntermediateRuntimeAggregationResults,
+}
+
+fn sort_buckets_by_doc_count(
+    buckets: BTreeMap<BucketKey, IntermediateBucket>,
+) -> Vec<(BucketKey, IntermediateBucket)> {
+    let mut buckets: Vec<(BucketKey, IntermediateBucket)> = buckets.into_iter().collect();
+    // The sort is stable, so buckets with the same number of documents remain sorted by key.
+    buckets.sort_by(|(_, left_bucket), (_, right_bucket)| {
+        right_bucket.doc_count.cmp(&left_bucket.doc_count)
+    });
+    buckets
+}
+
+/// Truncates the buckets of the terms aggregations to their shard size.
+fn truncate_terms(
+    aggregations: &[(String, RuntimeFieldAggregation)],
+    results: &mut IntermediateRuntimeAggregationResults,
+) {
+    for (name, aggregation) in aggregations {
+        let Some(IntermediateRuntimeAggregationResult::Buckets(buckets)) = results.0.get_mut(name)
+        else {
+            continue;
+        };
+        if let AggregationKind::Terms { shard_size, .. } = aggregation.kind {
+            buckets.truncate(shard_size);
+        }
+        for bucket in buckets.buckets.values_mut() {
+            truncate_terms(&aggregation.sub_aggregations, &mut bucket.sub_aggregations);
+        }
+    }
+}
+
+/// Collects the values of the aggregated fields of the documents of a segment.
+pub struct RuntimeFieldAggregationSegmentCollector {
+    aggregations: RuntimeFieldAggregations,
+    columns: FastFieldColumns,
+    results: IntermediateRuntimeAggregationResults,
+    num_buckets: usize,
+    error_opt: Option<String>,
+}
+
+impl SegmentCollector for RuntimeFieldAggregationSegmentCollector {
+    type Fruit = tantivy::Result<IntermediateRuntimeAggregationResults>;
+
+    fn collect(&mut self, doc_id: DocId, _score: Score) {
+        if self.error_opt.is_some() {
+            return;
+        }
+        let field_value = |field_name: &str| {
+            self.aggregations
+                .field_value(&self.columns, field_name, doc_id)
+        };
+
+        if let Err(error) = collect_aggregations(
+            &self.aggregations.aggregations,
+            &field_value,
+            &mut self.results,
+            &mut self.num_buckets,
+        ) {
+            self.error_opt = Some(error);
+        }
+    }
+
+    fn harvest(mut self) -> Self::Fruit {
+        if let Some(error) = self.error_opt {
+            return Err(TantivyError::InvalidArgument(error));
+        }
+        truncate_terms(&self.aggregations.aggregations, &mut self.results);
+        Ok(self.results)
+    }
+}
+
+fn collect_aggregations(
+    aggregations: &[(String, RuntimeFieldAggregation)],
+    field_value: &dyn Fn(&str) -> RuntimeValue,
+    results: &mut IntermediateRuntimeAggregationResults,
+    num_buckets: &mut usize,
+) -> Result<(), String> {
+    for (name, aggregation) in aggregations {
+        let value = field_value(&aggregation.field);
+
+        if value.is_null() {
+            continue;
+        }
+        if !results.0.contains_key(name) {
+            results
+                .0
+                .insert(name.clone(), aggregation.kind.empty_result());
+        }
+        let result = results.0.get_mut(name).expect("result should be present");
+
+        match result {
+            IntermediateRuntimeAggregationResult::Metric(stats) => stats.collect(&value),
+            IntermediateRuntimeAggregationResult::Buckets(buckets) => {
+                let Some(bucket_key) = aggregation.kind.bucket_key(value) else {
+                    continue;
+                };
+                let bucket = match buckets.buckets.entry(bucket_key) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        *num_buckets += 1;
+
+                        if *num_buckets > MAX_NUM_BUCKETS {
+                            return Err(format!(
+                                "aggregation `{name}` exceeds the limit of {MAX_NUM_BUCKETS} \
+                                 buckets"
+                            ));
+                        }
+                        entry.insert(IntermediateBucket::default())
+                    }
+                };
+                bucket.doc_count += 1;
+                collect_aggregations(
+                    &aggregation.sub_aggregations,
+                    field_value,
+                    &mut bucket.sub_aggregations,
+                    num_buckets,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn finalize_aggregations(
+    aggregations: &[(String, RuntimeFieldAggregation)],
+    mut results: IntermediateRuntimeAggregationResults,
+) -> Result<JsonMap<String, JsonValue>, String> {
+    let mut aggregations_json = JsonMap::with_capacity(aggregations.len());
+
+    for (name, aggregation) in aggregations {
+        let result_opt = results.0.remove(name);
+
+        let aggregation_json = match (&aggregation.kind, result_opt) {
+            (
+                AggregationKind::Metric(metric_kind),
+                Some(IntermediateRuntimeAggregationResult::Metric(stats)),
+            ) => stats.finalize(*metric_kind),
+            (AggregationKind::Metric(metric_kind), _) => {
+                IntermediateStats::default().finalize(*metric_kind)
+            }
+            (_, Some(IntermediateRuntimeAggregationResult::Buckets(buckets))) => {
+                finalize_buckets(aggregation, buckets)?
+            }
+            (_, _) => finalize_buckets(aggregation, IntermediateBuckets::default())?,
+        };
+        aggregations_json.insert(name.clone(), aggregation_json);
+    }
+    Ok(aggregations_json)
+}
+
+fn finalize_buckets(
+    aggregation: &RuntimeFieldAggregation,
+    mut buckets: IntermediateBuckets,
+) -> Result<JsonValue, String> {
+    let mut buckets_json = Vec::new();
+
+    match aggregation.kind {
+        AggregationKind::Metric(_) => {}
+        AggregationKind::Terms {
+            size,
+            min_doc_count,
+            ..
+        } => {
+            buckets
+                .buckets
+                .retain(|_, bucket| bucket.doc_count >= min_doc_count);
+            // Only the buckets dropped by the leaves add to the error.
+            let doc_count_error_upper_bound = buckets.doc_count_error_upper_bound;
+            buckets.truncate(size);
+
+            for (bucket_key, bucket) in sort_buckets_by_doc_count(buckets.buckets) {
+                let mut bucket_json = JsonMap::new();
+                bucket_key.add_to_bucket_json(&mut bucket_json);
+                buckets_json.push(finalize_bucket(aggregation, bucket, bucket_json)?);
+            }
+            return Ok(json!({
+                "doc_count_error_upper_bound": doc_count_error_upper_bound,
+                "sum_other_doc_count": buckets.sum_other_doc_count,
+                "buckets": buckets_json,
+            }));
+        }
+        AggregationKind::Histogram {
+            interval,
+            offset,
+            min_doc_count,
+        } => {
+            for (bucket_index, bucket) in histogram_buckets(buckets, min_doc_count)? {
+                let mut bucket_json = JsonMap::new();
+                let key = bucket_index as f64 * interval + offset;
+                bucket_json.insert("key".to_string(), json!(key));
+                buckets_json.push(finalize_bucket(aggregation, bucket, bucket_json)?);
+            }
+        }
+        AggregationKind::DateHistogram {
+            interval_millis,
+            offset_millis,
+            min_doc_count,
+        } => {
+            for (bucket_index, bucket) in histogram_buckets(buckets, min_doc_count)? {
+                let mut bucket_json = JsonMap::new();
+                let timestamp_millis = bucket_index
+                    .saturating_mul(interval_millis)
+                    .saturating_add(offset_millis);
+                let date_time = TantivyDateTime::from_timestamp_millis(timestamp_millis);
+                bucket_json.insert("key".to_string(), json!(timestamp_millis as f64));
+                bucket_json.insert(
+                    "key_as_string".to_string(),
+                    json!(format_date_time(date_time)),
+                );
+                buckets_json.push(finalize_bucket(aggregation, bucket, bucket_json)?);
+            }
+        }
+    }
+    Ok(json!({ "buckets": buckets_json }))
+}
+
+/// Returns the buckets of a histogram sorted by index. When `min_doc_count` is 0, the empty
+/// buckets between the first and last buckets are added.
+fn histogram_buckets(
+    buckets: IntermediateBuckets,
+    min_doc_count: u64,
+) -> Result<Vec<(i64, IntermediateBucket)>, String> {
+    let buckets: Vec<(i64, IntermediateBucket)> = buckets
+        .buckets
+        .into_iter()
+        .filter_map(|(bucket_key, bucket)| match bucket_key {
+            BucketKey::I64(bucket_index) if bucket.doc_count >= min_doc_count => {
+                Some((bucket_index, bucket))
+            }
+            _ => None,
+        })
+        .collect();
+
+    if min_doc_count > 0 {
+        return Ok(buckets);
+    }
+    let (Some((first_bucket_index, _)), Some((last_bucket_index, _))) =
+        (buckets.first(), buckets.last())
+    else {
+        return Ok(buckets);
+    };
+    let num_buckets = (*last_bucket_index as i128 - *first_bucket_index as i128 + 1) as u128;
+
+    if num_buckets > MAX_NUM_BUCKETS as u128 {
+        return Err(format!(
+            "histogram returns {num_buckets} buckets, which exceeds the limit of \
+             {MAX_NUM_BUCKETS} buckets"
+        ));
+    }
+    let first_bucket_index = *first_bucket_index;
+    let mut filled_buckets = Vec::with_capacity(num_buckets as usize);
+    let mut buckets_iter = buckets.into_iter().peekable();
+
+    for offset in 0..num_buckets as i64 {
+        let bucket_index = first_bucket_index + offset;
+
+        match buckets_iter.next_if(|(index, _)| *index == bucket_index) {
+            Some(bucket) => filled_buckets.push(bucket),
+            None => filled_buckets.push((bucket_index, IntermediateBucket::default())),
+        }
+    }
+    Ok(filled_buckets)
+}
+
+fn finalize_bucket(
+    aggregation: &RuntimeFieldAggregation,
+    bucket: IntermediateBucket,
+    mut bucket_json: JsonMap<String, JsonValue>,
+) -> Result<JsonValue, String> {
+    bucket_json.insert("doc_count".to_string(), json!(bucket.doc_count));
+    let sub_aggregations_json =
+        finalize_aggregations(&aggregation.sub_aggregations, bucket.sub_aggregations)?;
+    bucket_json.extend(sub_aggregations_json);
+    Ok(JsonValue::Object(bucket_json))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse(
+        aggregations_json: JsonValue,
+    ) -> Result<Vec<(String, RuntimeFieldAggregation)>, String> {
+        let JsonValue::Object(aggregations_json) = aggregations_json else {
+            panic!("aggregations should be an object");
+        };
+        parse_aggregations(&aggregations_json)
+    }
+
+    fn collect_docs(
+        aggregations: &[(String, RuntimeFieldAggregation)],
+        docs: &[HashMap<&str, RuntimeValue>],
+    ) -> IntermediateRuntimeAggregationResults {
+        let mut results = IntermediateRuntimeAggregationResults::default();
+        let mut num_buckets = 0;
+
+        for doc in docs {
+            let field_value =
+                |field_name: &str| doc.get(field_name).cloned().unwrap_or(RuntimeValue::Null);
+            collect_aggregations(aggregations, &field_value, &mut results, &mut num_buckets)
+                .unwrap();
+        }
+        truncate_terms(aggregations, &mut results);
+        results
+    }
+
+    #[test]
+    fn test_parse_runtime_field_aggregations() {
+        let aggregations = parse(json!({
+            "services": {
+                "terms": {"field": "service", "size": 5},
+                "aggs": {"avg_duration": {"avg": {"field": "duration"}}}
+            },
+            "per_minute": {"date_histogram": {"field": "end", "fixed_interval": "1m"}},
+        }))
+        .unwrap();
+        assert_eq!(aggregations.len(), 2);
+
+        let find_aggregation = |name: &str| {
+            aggregations
+                .iter()
+                .find(|(aggregation_name, _)| aggregation_name == name)
+                .map(|(_, aggregation)| aggregation)
+                .unwrap()
+        };
+        let per_minute = find_aggregation("per_minute");
+        assert_eq!(per_minute.field, "end");
+        assert_eq!(
+            per_minute.kind,
+            AggregationKind::DateHistogram {
+                interval_millis: 60_000,
+                offset_millis: 0,
+                min_doc_count: 0,
+            }
+        );
+        let services = find_aggregation("services");
+        assert_eq!(services.field, "service");
+        assert_eq!(
+            services.kind,
+            AggregationKind::Terms {
+                size: 5,
+                shard_size: 50,
+                min_doc_count: 1,
+            }
+        );
+        assert_eq!(services.sub_aggregations.len(), 1);
+        assert_eq!(services.sub_aggregations[0].1.field, "duration");
+
+        let error = parse(json!({"p": {"percentiles": {"field": "duration"}}})).unwrap_err();
+        assert!(error.contains("`percentiles` aggregations are not supported"));
+
+        let error = parse(json!({"a": {"avg": {"field": "duration", "missing": 0}}})).unwrap_err();
+        assert!(error.contains("unknown field `missing`"));
+
+        let error = parse(json!({
+            "a": {"avg": {"field": "duration"}, "aggs": {"b": {"max": {"field": "duration"}}}}
+        }))
+        .unwrap_err();
+        assert!(error.contains("cannot have sub-aggregations"));
+
+        let error =
+            parse(json!({"h": {"histogram": {"field": "duration", "interval": 0}}})).unwrap_err();
+        assert!(error.contains("the interval must be a positive number"));
+    }
+
+    #[test]
+    fn test_parse_duration_millis() {
+        assert_eq!(parse_duration_millis("10ms").unwrap(), 10);
+        assert_eq!(parse_duration_millis("30s").unwrap(), 30_000);
+        assert_eq!(parse_duration_millis("+2h").unwrap(), 7_200_000);
+        assert_eq!(parse_duration_millis("-1d").unwrap(), -86_400_000);
+        assert!(parse_duration_millis("1w").is_err());
+        assert!(parse_duration_millis("m").is_err());
+    }
+
+    #[test]
+    fn test_runtime_field_aggregations_terms() {
+        let aggregations = parse(json!({
+            "services": {
+                "terms": {"field": "service", "size": 1, "shard_size": 2},
+                "aggs": {"duration_stats": {"stats": {"field": "duration"}}}
+            },
+            "num_durations": {"value_count": {"field": "duration"}},
+        }))
+        .unwrap();
+        let doc = |service: &str, duration: i64| {
+            HashMap::from_iter([
+                ("service", RuntimeValue::Str(service.to_string())),
+                ("duration", RuntimeValue::I64(duration)),
+            ])
+        };
+        let left_results = collect_docs(
+            &aggregations,
+            &[
+                doc("frontend", 10),
+                doc("frontend", 30),
+                doc("backend", 5),
+                doc("db", 1),
+            ],
+        );
+        let right_results = collect_docs(
+            &aggregations,
+            &[doc("backend", 15), doc("backend", 25), doc("db", 2)],
+        );
+        let runtime_field_aggregations = RuntimeFieldAggregations {
+            aggregations: Arc::new(aggregations),
+            runtime_fields: Arc::new(Vec::new()),
+        };
+        let merged_results =
+            runtime_field_aggregations.merge_fruits(vec![left_results, right_results]);
+        let final_results = runtime_field_aggregations.finalize(merged_results).unwrap();
+        assert_eq!(
+            final_results,
+            json!({
+                "services": {
+                    "doc_count_error_upper_bound": 2,
+                    "sum_other_doc_count": 4,
+                    "buckets": [{
+                        "key": "backend",
+                        "doc_count": 3,
+                        "duration_stats": {
+                            "count": 3,
+                            "min": 5.0,
+                            "max": 25.0,
+                            "sum": 45.0,
+                            "avg": 15.0,
+                        }
+                    }]
+                },
+                "num_durations": {"value": 7.0},
+            })
+        );
+    }
+
+    #[test]
+    fn test_runtime_field_aggregations_histograms() {
+        let aggregations = parse(json!({
+            "durations": {"histogram": {"field": "duration", "interval": 10}},
+            "per_minute": {
+                "date_histogram": {"field": "end", "fixed_interval": "1m"},
+                "aggs": {"max_duration": {"max": {"field": "duration"}}}
+            },
+            "avg_missing": {"avg": {"field": "missing"}},
+        }))
+        .unwrap();
+        let doc = |end_secs: i64, duration: f64| {
+            HashMap::from_iter([
+                (
+                    "end",
+                    RuntimeValue::DateTime(TantivyDateTime::from_timestamp_secs(end_secs)),
+                ),
+                ("duration", RuntimeValue::F64(duration)),
+            ])
+        };
+        let results = collect_docs(
+            &aggregations,
+            &[
+                doc(1_704_067_200, 2.5),
+                doc(1_704_067_210, 7.5),
+                doc(1_704_067_330, 31.0),
+            ],
+        );
+        let final_results = finalize_aggregations(&aggregations, results).unwrap();
+        assert_eq!(
+            JsonValue::Object(final_results),
+            json!({
+                "durations": {
+                    "buckets": [
+                        {"key": 0.0, "doc_count": 2},
+                        {"key": 10.0, "doc_count": 0},
+                        {"key": 20.0, "doc_count": 0},
+                        {"key": 30.0, "doc_count": 1},
+                    ]
+                },
+                "per_minute": {
+                    "buckets": [
+                        {
+                            "key": 1_704_067_200_000.0,
+                            "key_as_string": "2024-01-01T00:00:00Z",
+                            "doc_count": 2,
+                            "max_duration": {"value": 7.5},
+                        },
+                        {
+                            "key": 1_704_067_260_000.0,
+                            "key_as_string": "2024-01-01T00:01:00Z",
+                            "doc_count": 0,
+                            "max_duration": {"value": null},
+                        },
+                        {
+                            "key": 1_704_067_320_000.0,
+                            "key_as_string": "2024-01-01T00:02:00Z",
+                            "doc_count": 1,
+                            "max_duration": {"value": 31.0},
+                        },
+                    ]
+                },
+                "avg_missing": {"value": null},
+            })
+        );
+    }
+}
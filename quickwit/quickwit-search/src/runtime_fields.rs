@@ -0,0 +1,1339 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime fields are fields computed at query time from the fields of the documents.
+//!
+//! Their value is defined by a small expression language:
+//! - field references: `end`, `http.status`, or Elasticsearch style `doc['end'].value`;
+//! - string, number, and boolean literals and `null`;
+//! - the arithmetic operators `+`, `-`, `*`, `/`, and `%`. `+` concatenates strings;
+//! - the functions `coalesce`, `length`, `lowercase`, `uppercase`, `regex_extract`, `to_date`,
+//!   `to_double`, `to_long`, and `to_string`.
+//!
+//! Datetime fields evaluate to dates. Subtracting two dates returns the number of milliseconds
+//! between them, and adding or subtracting a number of milliseconds to a date returns a date.
+//! `to_date` parses RFC 3339 and ISO 8601 strings and Unix timestamps, and converts numbers from
+//! milliseconds since the Unix epoch. Conversely, dates convert to milliseconds since the Unix
+//! epoch when a number is expected.
+//!
+//! For compatibility with Elasticsearch scripts, the expression may be wrapped in `emit(...)` and
+//! followed by a `;`.
+//!
+//! Any operation on a missing value or on values of incompatible types evaluates to `null`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+use quickwit_datetime::{
+    parse_date_time_str, DateTimeInputFormat, DateTimeOutputFormat, TantivyDateTime,
+};
+use quickwit_proto::search::RuntimeMapping;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+use tantivy::columnar::{ColumnType, MonotonicallyMappableToU64, StrColumn};
+use tantivy::fastfield::Column;
+use tantivy::schema::{NamedFieldDocument, OwnedValue};
+use tantivy::{DocId, SegmentReader};
+
+use crate::SearchError;
+
+/// Maximum nesting depth of the expressions of a script, which bounds the recursion of the parser
+/// and of the evaluation. Each parenthesized expression, function call, negation, or binary
+/// operation of a chain such as `a + b + c` counts as one level.
+const MAX_NESTING_DEPTH: usize = 32;
+
+/// Runtime field mapping, as accepted by the search REST APIs.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeFieldMapping {
+    /// Type of the runtime field: `long`, `double`, `date`, `keyword` or `boolean`.
+    #[serde(rename = "type")]
+    pub field_type: String,
+    /// Expression computing the value of the runtime field.
+    pub script: RuntimeFieldScript,
+}
+
+impl RuntimeFieldMapping {
+    /// Converts the mapping into its protobuf representation.
+    pub fn into_runtime_mapping(self, name: String) -> RuntimeMapping {
+        let script = match self.script {
+            RuntimeFieldScript::Source(source) => source,
+            RuntimeFieldScript::Object { source } => source,
+        };
+        RuntimeMapping {
+            name,
+            field_type: self.field_type,
+            script,
+        }
+    }
+}
+
+/// The script of a runtime field can be passed either as a string or, as in Elasticsearch, as an
+/// object with a `source` property.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RuntimeFieldScript {
+    /// Source of the script.
+    Source(String),
+    /// Elasticsearch style script object.
+    Object {
+        /// Source of the script.
+        source: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum RuntimeFieldType {
+    Long,
+    Double,
+    Date,
+    Keyword,
+    Boolean,
+}
+
+impl RuntimeFieldType {
+    /// Returns true if hits can be sorted by the runtime fields of this type.
+    pub fn is_sortable(&self) -> bool {
+        matches!(
+            self,
+            RuntimeFieldType::Long | RuntimeFieldType::Double | RuntimeFieldType::Date
+        )
+    }
+}
+
+impl FromStr for RuntimeFieldType {
+    type Err = String;
+
+    fn from_str(field_type: &str) -> Result<Self, Self::Err> {
+        match field_type {
+            "long" => Ok(RuntimeFieldType::Long),
+            "double" => Ok(RuntimeFieldType::Double),
+            "date" => Ok(RuntimeFieldType::Date),
+            "keyword" => Ok(RuntimeFieldType::Keyword),
+            "boolean" => Ok(RuntimeFieldType::Boolean),
+            _ => Err(format!(
+                "unsupported runtime field type `{field_type}`, expected one of `long`, `double`, \
+                 `date`, `keyword`, or `boolean`"
+            )),
+        }
+    }
+}
+
+/// Value manipulated by runtime field expressions.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum RuntimeValue {
+    Null,
+    Bool(bool),
+    I64(i64),
+    F64(f64),
+    Str(String),
+    DateTime(TantivyDateTime),
+}
+
+impl RuntimeValue {
+    fn from_json(json_value: &JsonValue) -> Self {
+        match json_value {
+            JsonValue::Bool(value) => RuntimeValue::Bool(*value),
+            JsonValue::Number(number) => {
+                if let Some(value) = number.as_i64() {
+                    RuntimeValue::I64(value)
+                } else if let Some(value) = number.as_f64() {
+                    RuntimeValue::F64(value)
+                } else {
+                    RuntimeValue::Null
+                }
+            }
+            JsonValue::String(value) => RuntimeValue::Str(value.clone()),
+            // Multivalued fields evaluate to their first value.
+            JsonValue::Array(values) => values
+                .first()
+                .map(RuntimeValue::from_json)
+                .unwrap_or(RuntimeValue::Null),
+            JsonValue::Null | JsonValue::Object(_) => RuntimeValue::Null,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, RuntimeValue::Null)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        let value_opt = match self {
+            RuntimeValue::Null => None,
+            RuntimeValue::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+            RuntimeValue::I64(value) => Some(*value as f64),
+            RuntimeValue::F64(value) => Some(*value),
+            RuntimeValue::Str(value) => value.trim().parse::<f64>().ok(),
+            RuntimeValue::DateTime(value) => Some(value.into_timestamp_millis() as f64),
+        };
+        value_opt.filter(|value| value.is_finite())
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            RuntimeValue::I64(value) => Some(*value),
+            RuntimeValue::DateTime(value) => Some(value.into_timestamp_millis()),
+            RuntimeValue::Str(value) => value
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .or_else(|| self.as_f64().map(|value| value as i64)),
+            // Float to integer casts saturate.
+            _ => self.as_f64().map(|value| value as i64),
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            RuntimeValue::Null => None,
+            RuntimeValue::Bool(value) => Some(*value),
+            RuntimeValue::I64(value) => Some(*value != 0),
+            RuntimeValue::F64(value) => Some(*value != 0.0),
+            RuntimeValue::Str(value) => value.trim().parse::<bool>().ok(),
+            RuntimeValue::DateTime(_) => None,
+        }
+    }
+
+    pub fn as_date_time(&self) -> Option<TantivyDateTime> {
+        match self {
+            RuntimeValue::Null | RuntimeValue::Bool(_) => None,
+            RuntimeValue::I64(value) => Some(TantivyDateTime::from_timestamp_millis(*value)),
+            RuntimeValue::F64(value) => {
+                let timestamp_nanos = *value * 1_000_000.0;
+                if timestamp_nanos.is_finite() {
+                    Some(TantivyDateTime::from_timestamp_nanos(
+                        timestamp_nanos as i64,
+                    ))
+                } else {
+                    None
+                }
+            }
+            RuntimeValue::Str(value) => parse_date_time_str(
+                value.trim(),
+                &[
+                    DateTimeInputFormat::Rfc3339,
+                    DateTimeInputFormat::Iso8601,
+                    DateTimeInputFormat::Timestamp,
+                ],
+            )
+            .ok(),
+            RuntimeValue::DateTime(value) => Some(*value),
+        }
+    }
+
+    fn into_string(self) -> Option<String> {
+        match self {
+            RuntimeValue::Null => None,
+            RuntimeValue::Bool(value) => Some(value.to_string()),
+            RuntimeValue::I64(value) => Some(value.to_string()),
+            RuntimeValue::F64(value) => Some(value.to_string()),
+            RuntimeValue::Str(value) => Some(value),
+            RuntimeValue::DateTime(value) => format_date_time(value),
+        }
+    }
+
+    fn coerce(self, field_type: RuntimeFieldType) -> RuntimeValue {
+        let coerced_value_opt = match field_type {
+            RuntimeFieldType::Long => self.as_i64().map(RuntimeValue::I64),
+            RuntimeFieldType::Double => self.as_f64().map(RuntimeValue::F64),
+            RuntimeFieldType::Date => self.as_date_time().map(RuntimeValue::DateTime),
+            RuntimeFieldType::Keyword => self.into_string().map(RuntimeValue::Str),
+            RuntimeFieldType::Boolean => self.as_bool().map(RuntimeValue::Bool),
+        };
+        coerced_value_opt.unwrap_or(RuntimeValue::Null)
+    }
+
+    fn into_json(self) -> JsonValue {
+        match self {
+            RuntimeValue::Null => JsonValue::Null,
+            RuntimeValue::Bool(value) => JsonValue::Bool(value),
+            RuntimeValue::I64(value) => JsonValue::Number(value.into()),
+            RuntimeValue::F64(value) => JsonNumber::from_f64(value)
+                .map(JsonValue::Number)
+                .unwrap_or(JsonValue::Null),
+            RuntimeValue::Str(value) => JsonValue::String(value),
+            RuntimeValue::DateTime(value) => format_date_time(value)
+                .map(JsonValue::String)
+                .unwrap_or(JsonValue::Null),
+        }
+    }
+}
+
+/// Formats a date as an RFC 3339 string.
+pub(crate) fn format_date_time(date_time: TantivyDateTime) -> Option<String> {
+    match DateTimeOutputFormat::Rfc3339.format_to_json(date_time) {
+        Ok(JsonValue::String(date_time_str)) => Some(date_time_str),
+        _ => None,
+    }
+}
+
+/// A runtime field parsed from a [`RuntimeMapping`].
+#[derive(Clone, Debug)]
+pub(crate) struct RuntimeField {
+    name: String,
+    field_type: RuntimeFieldType,
+    expr: Expr,
+}
+
+impl RuntimeField {
+    pub fn try_from_mapping(runtime_mapping: &RuntimeMapping) -> crate::Result<Self> {
+        let name = &runtime_mapping.name;
+        if name.is_empty() {
+            return Err(SearchError::InvalidArgument(
+                "runtime field name must not be empty".to_string(),
+            ));
+        }
+        let field_type =
+            RuntimeFieldType::from_str(&runtime_mapping.field_type).map_err(|error| {
+                SearchError::InvalidArgument(format!("invalid runtime field `{name}`: {error}"))
+            })?;
+        let expr = parse_expr(&runtime_mapping.script).map_err(|error| {
+            SearchError::InvalidArgument(format!(
+                "failed to parse the script of runtime field `{name}`: {error}"
+            ))
+        })?;
+        Ok(RuntimeField {
+            name: name.clone(),
+            field_type,
+            expr,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn field_type(&self) -> RuntimeFieldType {
+        self.field_type
+    }
+
+    /// Returns the names of the document fields referenced by the runtime field.
+    pub fn field_names(&self) -> HashSet<String> {
+        let mut field_names = HashSet::new();
+        self.expr.collect_field_names(&mut field_names);
+        field_names
+    }
+
+    /// Evaluates the runtime field, resolving the referenced document fields with
+    /// `resolve_field`. The value is coerced to the type of the runtime field.
+    pub fn eval(&self, resolve_field: &dyn Fn(&str) -> RuntimeValue) -> RuntimeValue {
+        self.expr.eval(resolve_field).coerce(self.field_type)
+    }
+
+    /// Evaluates the runtime field on a JSON document. The values of the datetime fields of the
+    /// document are looked up in `date_values` first, as the JSON document only contains their
+    /// formatted representation.
+    pub fn eval_json(
+        &self,
+        date_values: &HashMap<String, TantivyDateTime>,
+        doc: &JsonMap<String, JsonValue>,
+    ) -> JsonValue {
+        self.eval(&|field_name| {
+            if let Some(date_value) = date_values.get(field_name) {
+                return RuntimeValue::DateTime(*date_value);
+            }
+            resolve_json_field(doc, field_name)
+        })
+        .into_json()
+    }
+}
+
+/// Parses the runtime mappings of a search request.
+pub(crate) fn parse_runtime_fields(
+    runtime_mappings: &[RuntimeMapping],
+) -> crate::Result<Vec<RuntimeField>> {
+    let mut runtime_fields: Vec<RuntimeField> = Vec::with_capacity(runtime_mappings.len());
+
+    for runtime_mapping in runtime_mappings {
+        let runtime_field = RuntimeField::try_from_mapping(runtime_mapping)?;
+
+        if runtime_fields
+            .iter()
+            .any(|other| other.name() == runtime_field.name())
+        {
+            return Err(SearchError::InvalidArgument(format!(
+                "runtime field `{}` is defined more than once",
+                runtime_field.name()
+            )));
+        }
+        runtime_fields.push(runtime_field);
+    }
+    Ok(runtime_fields)
+}
+
+/// Returns the values of the datetime fields of a document. Multivalued fields evaluate to their
+/// first value.
+pub(crate) fn extract_date_values(
+    named_field_doc: &NamedFieldDocument,
+) -> HashMap<String, TantivyDateTime> {
+    named_field_doc
+        .0
+        .iter()
+        .filter_map(|(field_name, values)| match values.first() {
+            Some(OwnedValue::Date(date_value)) => Some((field_name.clone(), *date_value)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Adds the values of the runtime fields to a JSON document. The runtime fields are all evaluated
+/// on the original document and shadow the document fields with the same name.
+pub(crate) fn add_runtime_fields_to_doc(
+    runtime_fields: &[RuntimeField],
+    date_values: &HashMap<String, TantivyDateTime>,
+    doc: &mut JsonMap<String, JsonValue>,
+) {
+    let runtime_values: Vec<JsonValue> = runtime_fields
+        .iter()
+        .map(|runtime_field| runtime_field.eval_json(date_values, doc))
+        .collect();
+
+    for (runtime_field, runtime_value) in runtime_fields.iter().zip(runtime_values) {
+        doc.insert(runtime_field.name().to_string(), runtime_value);
+    }
+}
+
+/// Looks up a field in a JSON document. Dots in the field name are interpreted as object
+/// boundaries unless the document contains a key with the full field name.
+fn resolve_json_field(doc: &JsonMap<String, JsonValue>, field_name: &str) -> RuntimeValue {
+    if let Some(value) = doc.get(field_name) {
+        return RuntimeValue::from_json(value);
+    }
+    let mut path = field_name.split('.');
+    let Some(mut value) = path.next().and_then(|key| doc.get(key)) else {
+        return RuntimeValue::Null;
+    };
+    for key in path {
+        let Some(child_value) = value.as_object().and_then(|object| object.get(key)) else {
+            return RuntimeValue::Null;
+        };
+        value = child_value;
+    }
+    RuntimeValue::from_json(value)
+}
+
+enum FastFieldColumn {
+    Numeric(Column<u64>, ColumnType),
+    Str(StrColumn),
+}
+
+/// Fast field columns of a segment, used to evaluate runtime fields at collection time.
+pub(crate) struct FastFieldColumns {
+    columns: Vec<(String, FastFieldColumn)>,
+}
+
+impl FastFieldColumns {
+    /// Opens the columns of the given fast fields. Fields that are not fast fields of the segment
+    /// evaluate to `null`.
+    pub fn open(
+        segment_reader: &SegmentReader,
+        field_names: impl IntoIterator<Item = String>,
+    ) -> tantivy::Result<Self> {
+        let fast_fields = segment_reader.fast_fields();
+        let mut columns = Vec::new();
+
+        for field_name in field_names {
+            if let Some((column, column_type)) = fast_fields.u64_lenient(&field_name)? {
+                columns.push((field_name, FastFieldColumn::Numeric(column, column_type)));
+            } else if let Some(str_column) = fast_fields.str(&field_name)? {
+                columns.push((field_name, FastFieldColumn::Str(str_column)));
+            }
+        }
+        Ok(FastFieldColumns { columns })
+    }
+
+    /// Returns the first value of a fast field for the given doc.
+    pub fn value(&self, field_name: &str, doc_id: DocId) -> RuntimeValue {
+        let Some((_, column)) = self.columns.iter().find(|(name, _)| name == field_name) else {
+            return RuntimeValue::Null;
+        };
+        match column {
+            FastFieldColumn::Numeric(column, column_type) => {
+                let Some(value) = column.first(doc_id) else {
+                    return RuntimeValue::Null;
+                };
+                match column_type {
+                    ColumnType::U64 => i64::try_from(value)
+                        .map(RuntimeValue::I64)
+                        .unwrap_or(RuntimeValue::F64(value as f64)),
+                    ColumnType::I64 => RuntimeValue::I64(i64::from_u64(value)),
+                    ColumnType::F64 => RuntimeValue::F64(f64::from_u64(value)),
+                    ColumnType::Bool => RuntimeValue::Bool(value != 0),
+                    ColumnType::DateTime => {
+                        RuntimeValue::DateTime(TantivyDateTime::from_u64(value))
+                    }
+                    _ => RuntimeValue::Null,
+                }
+            }
+            FastFieldColumn::Str(str_column) => {
+                let Some(term_ord) = str_column.term_ords(doc_id).next() else {
+                    return RuntimeValue::Null;
+                };
+                let mut value = String::new();
+                match str_column.ord_to_str(term_ord, &mut value) {
+                    Ok(true) => RuntimeValue::Str(value),
+                    _ => RuntimeValue::Null,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Clone, Debug)]
+enum Function {
+    Coalesce,
+    Length,
+    Lowercase,
+    RegexExtract { regex: Regex, group: usize },
+    ToDate,
+    ToDouble,
+    ToLong,
+    ToString,
+    Uppercase,
+}
+
+impl Function {
+    fn eval(&self, mut args: Vec<RuntimeValue>) -> RuntimeValue {
+        let result_opt = match self {
+            Function::Coalesce => args.into_iter().find(|arg| !arg.is_null()),
+            Function::Length => args
+                .pop()
+                .and_then(RuntimeValue::into_string)
+                .map(|value| RuntimeValue::I64(value.chars().count() as i64)),
+            Function::Lowercase => args
+                .pop()
+                .and_then(RuntimeValue::into_string)
+                .map(|value| RuntimeValue::Str(value.to_lowercase())),
+            Function::RegexExtract { regex, group } => args
+                .pop()
+                .and_then(RuntimeValue::into_string)
+                .and_then(|text| {
+                    regex
+                        .captures(&text)
+                        .and_then(|captures| captures.get(*group))
+                        .map(|capture| RuntimeValue::Str(capture.as_str().to_string()))
+                }),
+            Function::ToDate => args
+                .pop()
+                .and_then(|arg| arg.as_date_time())
+                .map(RuntimeValue::DateTime),
+            Function::ToDouble => args
+                .pop()
+                .and_then(|arg| arg.as_f64())
+                .map(RuntimeValue::F64),
+            Function::ToLong => args
+                .pop()
+                .and_then(|arg| arg.as_i64())
+                .map(RuntimeValue::I64),
+            Function::ToString => args
+                .pop()
+                .and_then(RuntimeValue::into_string)
+                .map(RuntimeValue::Str),
+            Function::Uppercase => args
+                .pop()
+                .and_then(RuntimeValue::into_string)
+                .map(|value| RuntimeValue::Str(value.to_uppercase())),
+        };
+        result_opt.unwrap_or(RuntimeValue::Null)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Literal(RuntimeValue),
+    Field(String),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, resolve_field: &dyn Fn(&str) -> RuntimeValue) -> RuntimeValue {
+        match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Field(field_name) => resolve_field(field_name),
+            Expr::Neg(expr) => match expr.eval(resolve_field) {
+                RuntimeValue::I64(value) => value
+                    .checked_neg()
+                    .map(RuntimeValue::I64)
+                    .unwrap_or(RuntimeValue::Null),
+                RuntimeValue::DateTime(_) => RuntimeValue::Null,
+                value => value
+                    .as_f64()
+                    .map(|value| RuntimeValue::F64(-value))
+                    .unwrap_or(RuntimeValue::Null),
+            },
+            Expr::Binary(op, left, right) => {
+                eval_binary_op(*op, left.eval(resolve_field), right.eval(resolve_field))
+            }
+            Expr::Call(function, args) => {
+                let args = args.iter().map(|arg| arg.eval(resolve_field)).collect();
+                function.eval(args)
+            }
+        }
+    }
+
+    fn collect_field_names(&self, field_names: &mut HashSet<String>) {
+        match self {
+            Expr::Literal(_) => {}
+            Expr::Field(field_name) => {
+                field_names.insert(field_name.clone());
+            }
+            Expr::Neg(expr) => expr.collect_field_names(field_names),
+            Expr::Binary(_, left, right) => {
+                left.collect_field_names(field_names);
+                right.collect_field_names(field_names);
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    arg.collect_field_names(field_names);
+                }
+            }
+        }
+    }
+}
+
+fn eval_binary_op(op: BinaryOp, left: RuntimeValue, right: RuntimeValue) -> RuntimeValue {
+    if left.is_null() || right.is_null() {
+        return RuntimeValue::Null;
+    }
+    match (op, left, right) {
+        (BinaryOp::Sub, RuntimeValue::DateTime(left), RuntimeValue::DateTime(right)) => left
+            .into_timestamp_nanos()
+            .checked_sub(right.into_timestamp_nanos())
+            .map(|diff_nanos| RuntimeValue::I64(diff_nanos / 1_000_000))
+            .unwrap_or(RuntimeValue::Null),
+        (
+            op @ (BinaryOp::Add | BinaryOp::Sub),
+            RuntimeValue::DateTime(date_time),
+            offset_millis @ (RuntimeValue::I64(_) | RuntimeValue::F64(_)),
+        )
+        | (
+            op @ BinaryOp::Add,
+            offset_millis @ (RuntimeValue::I64(_) | RuntimeValue::F64(_)),
+            RuntimeValue::DateTime(date_time),
+        ) => shift_date_time(date_time, op, offset_millis),
+        (BinaryOp::Add, RuntimeValue::Str(mut left), right) => {
+            left.push_str(&right.into_string().unwrap_or_default());
+            RuntimeValue::Str(left)
+        }
+        (BinaryOp::Add, left, RuntimeValue::Str(right)) => {
+            let mut left = left.into_string().unwrap_or_default();
+            left.push_str(&right);
+            RuntimeValue::Str(left)
+        }
+        (_, RuntimeValue::DateTime(_), _) | (_, _, RuntimeValue::DateTime(_)) => RuntimeValue::Null,
+        (op, RuntimeValue::I64(left), RuntimeValue::I64(right)) => {
+            let result_opt = match op {
+                BinaryOp::Add => left.checked_add(right),
+                BinaryOp::Sub => left.checked_sub(right),
+                BinaryOp::Mul => left.checked_mul(right),
+                BinaryOp::Div => left.checked_div(right),
+                BinaryOp::Rem => left.checked_rem(right),
+            };
+            result_opt
+                .map(RuntimeValue::I64)
+                .unwrap_or(RuntimeValue::Null)
+        }
+        (op, left, right) => {
+            let (Some(left), Some(right)) = (left.as_f64(), right.as_f64()) else {
+                return RuntimeValue::Null;
+            };
+            let result = match op {
+                BinaryOp::Add => left + right,
+                BinaryOp::Sub => left - right,
+                BinaryOp::Mul => left * right,
+                BinaryOp::Div => left / right,
+                BinaryOp::Rem => left % right,
+            };
+            if result.is_finite() {
+                RuntimeValue::F64(result)
+            } else {
+                RuntimeValue::Null
+            }
+        }
+    }
+}
+
+/// Adds or subtracts a number of milliseconds to a date.
+fn shift_date_time(
+    date_time: TantivyDateTime,
+    op: BinaryOp,
+    offset_millis: RuntimeValue,
+) -> RuntimeValue {
+    let Some(offset_nanos) = offset_millis
+        .as_f64()
+        .map(|offset_millis| offset_millis * 1_000_000.0)
+        .filter(|offset_nanos| offset_nanos.abs() < i64::MAX as f64)
+    else {
+        return RuntimeValue::Null;
+    };
+    let timestamp_nanos = date_time.into_timestamp_nanos();
+    let shifted_timestamp_nanos_opt = if op == BinaryOp::Sub {
+        timestamp_nanos.checked_sub(offset_nanos as i64)
+    } else {
+        timestamp_nanos.checked_add(offset_nanos as i64)
+    };
+    shifted_timestamp_nanos_opt
+        .map(|timestamp_nanos| {
+            RuntimeValue::DateTime(TantivyDateTime::from_timestamp_nanos(timestamp_nanos))
+        })
+        .unwrap_or(RuntimeValue::Null)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(RuntimeValue),
+    Str(String),
+    Ident(String),
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Dot,
+    Semicolon,
+    Op(BinaryOp),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(RuntimeValue::I64(value)) => write!(formatter, "{value}"),
+            Token::Number(RuntimeValue::F64(value)) => write!(formatter, "{value}"),
+            Token::Number(_) => write!(formatter, "number"),
+            Token::Str(value) => write!(formatter, "'{value}'"),
+            Token::Ident(ident) => write!(formatter, "{ident}"),
+            Token::LeftParen => write!(formatter, "("),
+            Token::RightParen => write!(formatter, ")"),
+            Token::LeftBracket => write!(formatter, "["),
+            Token::RightBracket => write!(formatter, "]"),
+            Token::Comma => write!(formatter, ","),
+            Token::Dot => write!(formatter, "."),
+            Token::Semicolon => write!(formatter, ";"),
+            Token::Op(BinaryOp::Add) => write!(formatter, "+"),
+            Token::Op(BinaryOp::Sub) => write!(formatter, "-"),
+            Token::Op(BinaryOp::Mul) => write!(formatter, "*"),
+            Token::Op(BinaryOp::Div) => write!(formatter, "/"),
+            Token::Op(BinaryOp::Rem) => write!(formatter, "%"),
+        }
+    }
+}
+
+fn tokenize(script: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = script.chars().peekable();
+
+    while let Some(&next_char) = chars.peek() {
+        let token = match next_char {
+            _ if next_char.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '0'..='9' => tokenize_number(&mut chars)?,
+            '\'' | '"' => tokenize_string(&mut chars)?,
+            _ if next_char.is_alphabetic() || next_char == '_' => {
+                let mut ident = String::new();
+                while let Some(&next_char) = chars.peek() {
+                    if !next_char.is_alphanumeric() && next_char != '_' {
+                        break;
+                    }
+                    ident.push(next_char);
+                    chars.next();
+                }
+                Token::Ident(ident)
+            }
+            _ => {
+                chars.next();
+                match next_char {
+                    '(' => Token::LeftParen,
+                    ')' => Token::RightParen,
+                    '[' => Token::LeftBracket,
+                    ']' => Token::RightBracket,
+                    ',' => Token::Comma,
+                    '.' => Token::Dot,
+                    ';' => Token::Semicolon,
+                    '+' => Token::Op(BinaryOp::Add),
+                    '-' => Token::Op(BinaryOp::Sub),
+                    '*' => Token::Op(BinaryOp::Mul),
+                    '/' => Token::Op(BinaryOp::Div),
+                    '%' => Token::Op(BinaryOp::Rem),
+                    _ => return Err(format!("unexpected character `{next_char}`")),
+                }
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn tokenize_number(chars: &mut Peekable<Chars>) -> Result<Token, String> {
+    let mut number = String::new();
+    let mut is_float = false;
+
+    while let Some(&next_char) = chars.peek() {
+        match next_char {
+            '0'..='9' => {}
+            '.' | 'e' | 'E' => is_float = true,
+            '+' | '-' if number.ends_with(['e', 'E']) => {}
+            _ => break,
+        }
+        number.push(next_char);
+        chars.next();
+    }
+    let value = if is_float {
+        number.parse::<f64>().ok().map(RuntimeValue::F64)
+    } else {
+        number.parse::<i64>().ok().map(RuntimeValue::I64)
+    };
+    value
+        .map(Token::Number)
+        .ok_or_else(|| format!("invalid number `{number}`"))
+}
+
+fn tokenize_string(chars: &mut Peekable<Chars>) -> Result<Token, String> {
+    let quote = chars.next().expect("string should start with a quote");
+    let mut value = String::new();
+
+    loop {
+        match chars.next() {
+            Some('\\') => match chars.next() {
+                Some(escaped_char) if escaped_char == quote || escaped_char == '\\' => {
+                    value.push(escaped_char)
+                }
+                // Other escape sequences are kept as is, so that regular expressions do not need
+                // to be escaped twice.
+                Some(escaped_char) => {
+                    value.push('\\');
+                    value.push(escaped_char);
+                }
+                None => break,
+            },
+            Some(next_char) if next_char == quote => return Ok(Token::Str(value)),
+            Some(next_char) => value.push(next_char),
+            None => break,
+        }
+    }
+    Err("unterminated string literal".to_string())
+}
+
+fn parse_expr(script: &str) -> Result<Expr, String> {
+    let tokens = tokenize(script)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+    };
+    let expr = parser.parse_additive()?;
+    parser.consume_if(&Token::Semicolon);
+
+    if let Some(token) = parser.peek_token() {
+        return Err(format!("unexpected token `{token}`"));
+    }
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek_token(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token_opt = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token_opt
+    }
+
+    fn consume_if(&mut self, token: &Token) -> bool {
+        if self.peek_token() == Some(token) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected_token: Token) -> Result<(), String> {
+        match self.next_token() {
+            Some(token) if token == expected_token => Ok(()),
+            Some(token) => Err(format!("expected `{expected_token}`, got `{token}`")),
+            None => Err(format!("expected `{expected_token}`, got end of script")),
+        }
+    }
+
+    fn increment_depth(&mut self) -> Result<(), String> {
+        if self.depth == MAX_NESTING_DEPTH {
+            return Err(format!(
+                "script cannot be nested more than {MAX_NESTING_DEPTH} levels deep"
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, String> {
+        let depth = self.depth;
+        let mut expr = self.parse_multiplicative()?;
+
+        while let Some(Token::Op(op @ (BinaryOp::Add | BinaryOp::Sub))) = self.peek_token().cloned()
+        {
+            self.pos += 1;
+            self.increment_depth()?;
+            let right = self.parse_multiplicative()?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(right));
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, String> {
+        let depth = self.depth;
+        let mut expr = self.parse_unary()?;
+
+        while let Some(Token::Op(op @ (BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem))) =
+            self.peek_token().cloned()
+        {
+            self.pos += 1;
+            self.increment_depth()?;
+            let right = self.parse_unary()?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(right));
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.consume_if(&Token::Op(BinaryOp::Sub)) {
+            self.increment_depth()?;
+            let expr = self.parse_unary()?;
+            self.depth -= 1;
+            return Ok(Expr::Neg(Box::new(expr)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next_token() {
+            Some(Token::Number(value)) => Ok(Expr::Literal(value)),
+            Some(Token::Str(value)) => Ok(Expr::Literal(RuntimeValue::Str(value))),
+            Some(Token::LeftParen) => {
+                self.increment_depth()?;
+                let expr = self.parse_additive()?;
+                self.expect(Token::RightParen)?;
+                self.depth -= 1;
+                Ok(expr)
+            }
+            Some(Token::Ident(ident)) => self.parse_ident(ident),
+            Some(token) => Err(format!("unexpected token `{token}`")),
+            None => Err("unexpected end of script".to_string()),
+        }
+    }
+
+    fn parse_ident(&mut self, ident: String) -> Result<Expr, String> {
+        match ident.as_str() {
+            "true" => return Ok(Expr::Literal(RuntimeValue::Bool(true))),
+            "false" => return Ok(Expr::Literal(RuntimeValue::Bool(false))),
+            "null" => return Ok(Expr::Literal(RuntimeValue::Null)),
+            "doc" if self.peek_token() == Some(&Token::LeftBracket) => {
+                return self.parse_doc_field()
+            }
+            _ => {}
+        }
+        if self.consume_if(&Token::LeftParen) {
+            self.increment_depth()?;
+            let expr = self.parse_call(ident)?;
+            self.depth -= 1;
+            return Ok(expr);
+        }
+        let mut field_name = ident;
+
+        while self.consume_if(&Token::Dot) {
+            match self.next_token() {
+                Some(Token::Ident(ident)) => {
+                    field_name.push('.');
+                    field_name.push_str(&ident);
+                }
+                _ => return Err(format!("invalid field name after `{field_name}.`")),
+            }
+        }
+        Ok(Expr::Field(field_name))
+    }
+
+    /// Parses Elasticsearch style field references: `doc['field']` or `doc['field'].value`.
+    fn parse_doc_field(&mut self) -> Result<Expr, String> {
+        self.expect(Token::LeftBracket)?;
+        let Some(Token::Str(field_name)) = self.next_token() else {
+            return Err("expected a quoted field name after `doc[`".to_string());
+        };
+        self.expect(Token::RightBracket)?;
+
+        if self.consume_if(&Token::Dot) {
+            self.expect(Token::Ident("value".to_string()))?;
+        }
+        Ok(Expr::Field(field_name))
+    }
+
+    fn parse_call(&mut self, function_name: String) -> Result<Expr, String> {
+        let mut args = Vec::new();
+
+        if !self.consume_if(&Token::RightParen) {
+            loop {
+                args.push(self.parse_additive()?);
+
+                if self.consume_if(&Token::RightParen) {
+                    break;
+                }
+                self.expect(Token::Comma)?;
+            }
+        }
+        let function = match (function_name.as_str(), args.len()) {
+            ("emit", 1) => return Ok(args.pop().expect("args should have one element")),
+            ("coalesce", num_args) if num_args > 0 => Function::Coalesce,
+            ("length", 1) => Function::Length,
+            ("lowercase", 1) => Function::Lowercase,
+            ("regex_extract", 2 | 3) => {
+                let group = match args.get(2) {
+                    Some(Expr::Literal(RuntimeValue::I64(group))) if *group >= 0 => *group as usize,
+                    Some(_) => {
+                        return Err(
+                            "the group of `regex_extract` must be a positive integer".to_string()
+                        )
+                    }
+                    None => 1,
+                };
+                let Some(Expr::Literal(RuntimeValue::Str(pattern))) = args.get(1) else {
+                    return Err("the pattern of `regex_extract` must be a string".to_string());
+                };
+                let regex = Regex::new(pattern)
+                    .map_err(|error| format!("invalid regular expression: {error}"))?;
+
+                if group > regex.captures_len() - 1 {
+                    return Err(format!(
+                        "the regular expression `{pattern}` does not have a group {group}"
+                    ));
+                }
+                args.truncate(1);
+                Function::RegexExtract { regex, group }
+            }
+            ("to_date", 1) => Function::ToDate,
+            ("to_double", 1) => Function::ToDouble,
+            ("to_long", 1) => Function::ToLong,
+            ("to_string", 1) => Function::ToString,
+            ("uppercase", 1) => Function::Uppercase,
+            (
+                "emit" | "coalesce" | "length" | "lowercase" | "regex_extract" | "to_date"
+                | "to_double" | "to_long" | "to_string" | "uppercase",
+                num_args,
+            ) => {
+                return Err(format!(
+                    "invalid number of arguments for function `{function_name}`: {num_args}"
+                ))
+            }
+            _ => return Err(format!("unknown function `{function_name}`")),
+        };
+        Ok(Expr::Call(function, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn runtime_field(field_type: &str, script: &str) -> RuntimeField {
+        RuntimeField::try_from_mapping(&RuntimeMapping {
+            name: "runtime".to_string(),
+            field_type: field_type.to_string(),
+            script: script.to_string(),
+        })
+        .unwrap()
+    }
+
+    fn eval_json(field_type: &str, script: &str, doc: JsonValue) -> JsonValue {
+        let JsonValue::Object(doc) = doc else {
+            panic!("doc should be an object");
+        };
+        runtime_field(field_type, script).eval_json(&HashMap::new(), &doc)
+    }
+
+    #[test]
+    fn test_runtime_field_arithmetic() {
+        let doc = json!({"start": 100, "end": 250, "ratio": 0.5, "text": "12"});
+        assert_eq!(eval_json("long", "end - start", doc.clone()), json!(150));
+        assert_eq!(
+            eval_json(
+                "long",
+                "emit(doc['end'].value - doc['start'].value);",
+                doc.clone()
+            ),
+            json!(150)
+        );
+        assert_eq!(
+            eval_json("long", "-(end - start) * 2", doc.clone()),
+            json!(-300)
+        );
+        assert_eq!(eval_json("long", "1 + 2 * 3 % 4", doc.clone()), json!(3));
+        assert_eq!(
+            eval_json("double", "end * ratio", doc.clone()),
+            json!(125.0)
+        );
+        assert_eq!(eval_json("double", "end / 0", doc.clone()), json!(null));
+        assert_eq!(eval_json("long", "end / 0", doc.clone()), json!(null));
+        assert_eq!(eval_json("long", "text + 1", doc.clone()), json!(121));
+        assert_eq!(
+            eval_json("long", "to_long(text) + 1", doc.clone()),
+            json!(13)
+        );
+        assert_eq!(
+            eval_json("long", "missing - start", doc.clone()),
+            json!(null)
+        );
+        assert_eq!(
+            eval_json("long", "coalesce(missing, start) + 1", doc),
+            json!(101)
+        );
+    }
+
+    #[test]
+    fn test_runtime_field_strings() {
+        let doc = json!({
+            "message": "GET /index.html 404 12ms",
+            "service": {"name": "Frontend"},
+            "attributes.http.method": "GET",
+            "tags": ["first", "second"],
+        });
+        assert_eq!(
+            eval_json(
+                "long",
+                r#"regex_extract(message, "\\s(\\d{3})\\s")"#,
+                doc.clone()
+            ),
+            json!(404)
+        );
+        assert_eq!(
+            eval_json(
+                "keyword",
+                r#"regex_extract(message, "^(\\w+) (\\S+)", 2)"#,
+                doc.clone()
+            ),
+            json!("/index.html")
+        );
+        assert_eq!(
+            eval_json("keyword", "regex_extract(message, 'POST', 0)", doc.clone()),
+            json!(null)
+        );
+        assert_eq!(
+            eval_json(
+                "keyword",
+                "lowercase(service.name) + '-' + tags",
+                doc.clone()
+            ),
+            json!("frontend-first")
+        );
+        assert_eq!(
+            eval_json("keyword", "attributes.http.method", doc.clone()),
+            json!("GET")
+        );
+        assert_eq!(
+            eval_json("long", "length(service.name)", doc.clone()),
+            json!(8)
+        );
+        assert_eq!(eval_json("boolean", "length(tags) - 5", doc), json!(false));
+    }
+
+    #[test]
+    fn test_runtime_field_dates() {
+        let JsonValue::Object(doc) = json!({
+            "start": "2024-01-01T00:00:00Z",
+            "end": "2024-01-01T00:00:01.5Z",
+            "created_at": "2024-01-01T00:00:00Z",
+        }) else {
+            panic!("doc should be an object");
+        };
+        let date_values = HashMap::from_iter([
+            (
+                "start".to_string(),
+                TantivyDateTime::from_timestamp_secs(1_704_067_200),
+            ),
+            (
+                "end".to_string(),
+                TantivyDateTime::from_timestamp_millis(1_704_067_201_500),
+            ),
+        ]);
+        let eval_json = |field_type: &str, script: &str| {
+            runtime_field(field_type, script).eval_json(&date_values, &doc)
+        };
+        assert_eq!(eval_json("long", "end - start"), json!(1_500));
+        assert_eq!(eval_json("double", "(end - start) / 1000.0"), json!(1.5));
+        assert_eq!(
+            eval_json("date", "start + 60 * 1000"),
+            json!("2024-01-01T00:01:00Z")
+        );
+        assert_eq!(
+            eval_json("date", "end - 1500"),
+            json!("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(eval_json("long", "start"), json!(1_704_067_200_000i64));
+        assert_eq!(eval_json("keyword", "start"), json!("2024-01-01T00:00:00Z"));
+        // `created_at` is not a datetime field of the document and must be parsed explicitly.
+        assert_eq!(eval_json("long", "end - created_at"), json!(null));
+        assert_eq!(eval_json("long", "end - to_date(created_at)"), json!(1_500));
+        assert_eq!(
+            eval_json("date", "to_date(1704067200000)"),
+            json!("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(eval_json("date", "start * 2"), json!(null));
+        assert_eq!(eval_json("date", "-start"), json!(null));
+        assert_eq!(eval_json("date", "to_date('yesterday')"), json!(null));
+    }
+
+    #[test]
+    fn test_runtime_field_invalid() {
+        let parse_error = |field_type: &str, script: &str| {
+            RuntimeField::try_from_mapping(&RuntimeMapping {
+                name: "runtime".to_string(),
+                field_type: field_type.to_string(),
+                script: script.to_string(),
+            })
+            .unwrap_err()
+            .to_string()
+        };
+        assert!(parse_error("ip", "end").contains("unsupported runtime field type `ip`"));
+        assert!(parse_error("long", "end -").contains("unexpected end of script"));
+        assert!(parse_error("long", "(end").contains("expected `)`"));
+        assert!(parse_error("long", "end start").contains("unexpected token `start`"));
+        assert!(parse_error("long", "foo(end)").contains("unknown function `foo`"));
+        assert!(parse_error("long", "lowercase()").contains("invalid number of arguments"));
+        assert!(parse_error("long", "regex_extract(end, end)").contains("must be a string"));
+        assert!(parse_error("long", "regex_extract(end, '(')").contains("invalid regular"));
+        assert!(parse_error("long", "regex_extract(end, 'a')").contains("does not have a group"));
+        assert!(parse_error("long", "'end").contains("unterminated string"));
+        assert!(parse_error("long", "end # 1").contains("unexpected character `#`"));
+    }
+
+    #[test]
+    fn test_runtime_field_nesting_depth() {
+        let parse_result = |script: &str| {
+            RuntimeField::try_from_mapping(&RuntimeMapping {
+                name: "runtime".to_string(),
+                field_type: "long".to_string(),
+                script: script.to_string(),
+            })
+        };
+        let nested_script = |prefix: &str, suffix: &str, depth: usize| {
+            format!("{}end{}", prefix.repeat(depth), suffix.repeat(depth))
+        };
+        parse_result(&nested_script("(", ")", MAX_NESTING_DEPTH)).unwrap();
+        parse_result(&nested_script("-", "", MAX_NESTING_DEPTH)).unwrap();
+        parse_result(&nested_script("", " + 1", MAX_NESTING_DEPTH)).unwrap();
+        parse_result(&nested_script("length(", ")", MAX_NESTING_DEPTH)).unwrap();
+
+        for (prefix, suffix) in [
+            ("(", ")"),
+            ("-", ""),
+            ("", " + 1"),
+            ("", " * 2"),
+            ("length(", ")"),
+        ] {
+            let error = parse_result(&nested_script(prefix, suffix, 100_000)).unwrap_err();
+            assert!(error
+                .to_string()
+                .contains("cannot be nested more than 32 levels deep"));
+        }
+    }
+
+    #[test]
+    fn test_parse_runtime_fields() {
+        let runtime_mappings = vec![
+            RuntimeMapping {
+                name: "duration".to_string(),
+                field_type: "long".to_string(),
+                script: "end - start".to_string(),
+            },
+            RuntimeMapping {
+                name: "status".to_string(),
+                field_type: "long".to_string(),
+                script: "regex_extract(message, 'status=(\\d+)')".to_string(),
+            },
+        ];
+        let runtime_fields = parse_runtime_fields(&runtime_mappings).unwrap();
+        assert_eq!(runtime_fields.len(), 2);
+        assert_eq!(
+            runtime_fields[0].field_names(),
+            HashSet::from_iter(["end".to_string(), "start".to_string()])
+        );
+        let JsonValue::Object(mut doc) =
+            json!({"start": 1, "end": 3, "message": "status=200", "duration": "shadowed"})
+        else {
+            panic!("doc should be an object");
+        };
+        add_runtime_fields_to_doc(&runtime_fields, &HashMap::new(), &mut doc);
+        assert_eq!(
+            JsonValue::Object(doc),
+            json!({"start": 1, "end": 3, "message": "status=200", "duration": 2, "status": 200})
+        );
+
+        let duplicate_runtime_mappings =
+            vec![runtime_mappings[0].clone(), runtime_mappings[0].clone()];
+        let error = parse_runtime_fields(&duplicate_runtime_mappings).unwrap_err();
+        assert!(error.to_string().contains("defined more than once"));
+    }
+
+    #[test]
+    fn test_runtime_field_mapping_deserialization() {
+        let runtime_mapping: RuntimeFieldMapping =
+            serde_json::from_value(json!({"type": "long", "script": "end - start"})).unwrap();
+        assert_eq!(
+            runtime_mapping.into_runtime_mapping("duration".to_string()),
+            RuntimeMapping {
+                name: "duration".to_string(),
+                field_type: "long".to_string(),
+                script: "end - start".to_string(),
+            }
+        );
+        let runtime_mapping: RuntimeFieldMapping = serde_json::from_value(
+            json!({"type": "long", "script": {"source": "emit(doc['end'].value)"}}),
+        )
+        .unwrap();
+        assert_eq!(
+            runtime_mapping
+                .into_runtime_mapping("end".to_string())
+                .script,
+            "emit(doc['end'].value)"
+        );
+    }
+}
@@ -0,0 +1,456 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytesize::ByteSize;
+use quickwit_config::SearchQuotasConfig;
+use quickwit_proto::search::SearchPriority;
+use quickwit_proto::types::IndexId;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::metrics::SEARCH_METRICS;
+use crate::SearchError;
+
+/// Enforces the per-index [`SearchQuotasConfig`] on root searches.
+///
+/// Before running, a root search must obtain a [`SearchAdmissionPermit`] for each of the indexes
+/// it targets. Searches that cannot obtain their permits wait in a queue. They are rejected with
+/// a [`SearchError::TooManyRequests`] error if the queue is full or if they waited for too long.
+///
+/// The quotas of the indexes that are not searched anymore are evicted periodically.
+pub struct SearchAdmissionController {
+    search_quotas_opt: Option<SearchQuotasConfig>,
+    aggregation_memory_limit: ByteSize,
+    index_quotas: Mutex<IndexQuotasMap>,
+}
+
+/// Interval at which the quotas of idle indexes are evicted.
+const IDLE_INDEX_QUOTAS_EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+struct IndexQuotasMap {
+    index_quotas: HashMap<IndexId, Arc<IndexQuotas>>,
+    last_eviction: Instant,
+}
+
+impl IndexQuotasMap {
+    /// Removes the quotas of the indexes that have no running or queued searches. Their
+    /// semaphores are full, so recreating them later is equivalent to keeping them.
+    fn evict_idle_index_quotas(&mut self) {
+        self.index_quotas
+            .retain(|_index_id, index_quotas| !index_quotas.is_idle());
+        self.last_eviction = Instant::now();
+    }
+}
+
+/// Releases the resources reserved by a root search when dropped.
+#[derive(Default)]
+pub struct SearchAdmissionPermit {
+    _semaphore_permits: Vec<OwnedSemaphorePermit>,
+}
+
+struct IndexQuotas {
+    search_semaphore: Arc<Semaphore>,
+    batch_search_semaphore_opt: Option<Arc<Semaphore>>,
+    // The permits of this semaphore are expressed in MiB.
+    aggregation_memory_semaphore_opt: Option<Arc<Semaphore>>,
+    num_queued_searches: AtomicUsize,
+}
+
+impl IndexQuotas {
+    /// Returns whether no search holds or waits for the quotas. Must be called on the reference
+    /// held by the [`IndexQuotasMap`].
+    fn is_idle(self: &Arc<Self>) -> bool {
+        // Searches waiting for their permits hold a reference to the quotas, and permits hold a
+        // reference to their semaphore.
+        Arc::strong_count(self) == 1
+            && Arc::strong_count(&self.search_semaphore) == 1
+            && self
+                .batch_search_semaphore_opt
+                .as_ref()
+                .map_or(true, |semaphore| Arc::strong_count(semaphore) == 1)
+            && self
+                .aggregation_memory_semaphore_opt
+                .as_ref()
+                .map_or(true, |semaphore| Arc::strong_count(semaphore) == 1)
+    }
+
+    fn new(search_quotas: &SearchQuotasConfig) -> Self {
+        let search_semaphore = Arc::new(Semaphore::new(
+            search_quotas.max_num_concurrent_searches_per_index.get() as usize,
+        ));
+        let batch_search_semaphore_opt = search_quotas
+            .max_num_concurrent_batch_searches_per_index
+            .map(|max_num_concurrent_batch_searches| {
+                Arc::new(Semaphore::new(
+                    max_num_concurrent_batch_searches.get() as usize
+                ))
+            });
+        let aggregation_memory_semaphore_opt =
+            search_quotas
+                .max_aggregation_memory_per_index
+                .map(|max_aggregation_memory| {
+                    Arc::new(Semaphore::new(num_mibs(max_aggregation_memory) as usize))
+                });
+        IndexQuotas {
+            search_semaphore,
+            batch_search_semaphore_opt,
+            aggregation_memory_semaphore_opt,
+            num_queued_searches: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// Decrements the number of queued searches of an index when dropped.
+struct QueuedSearchGuard<'a> {
+    num_queued_searches: &'a AtomicUsize,
+}
+
+impl Drop for QueuedSearchGuard<'_> {
+    fn drop(&mut self) {
+        self.num_queued_searches.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn num_mibs(num_bytes: ByteSize) -> u32 {
+    num_bytes
+        .as_u64()
+        .div_ceil(ByteSize::mib(1).as_u64())
+        .min(u32::MAX as u64) as u32
+}
+
+impl SearchAdmissionController {
+    /// Creates a new admission controller. If `search_quotas_opt` is `None`, all the searches are
+    /// admitted right away.
+    pub fn new(
+        search_quotas_opt: Option<SearchQuotasConfig>,
+        aggregation_memory_limit: ByteSize,
+    ) -> Self {
+        SearchAdmissionController {
+            search_quotas_opt,
+            aggregation_memory_limit,
+            index_quotas: Mutex::new(IndexQuotasMap {
+                index_quotas: HashMap::new(),
+                last_eviction: Instant::now(),
+            }),
+        }
+    }
+
+    fn index_quotas(&self, index_id: &str, search_quotas: &SearchQuotasConfig) -> Arc<IndexQuotas> {
+        let mut index_quotas_guard = self.index_quotas.lock().unwrap();
+
+        if index_quotas_guard.last_eviction.elapsed() >= IDLE_INDEX_QUOTAS_EVICTION_INTERVAL {
+            index_quotas_guard.evict_idle_index_quotas();
+        }
+        if let Some(index_quotas) = index_quotas_guard.index_quotas.get(index_id) {
+            return index_quotas.clone();
+        }
+        let index_quotas = Arc::new(IndexQuotas::new(search_quotas));
+        index_quotas_guard
+            .index_quotas
+            .insert(index_id.to_string(), index_quotas.clone());
+        index_quotas
+    }
+
+    /// Waits until a search with the given priority can run on all the given indexes.
+    ///
+    /// `has_aggregations` indicates whether the search should reserve aggregation memory.
+    pub async fn admit(
+        &self,
+        index_ids: &[IndexId],
+        priority: SearchPriority,
+        has_aggregations: bool,
+    ) -> crate::Result<SearchAdmissionPermit> {
+        let Some(search_quotas) = &self.search_quotas_opt else {
+            return Ok(SearchAdmissionPermit::default());
+        };
+        // Acquiring the permits in a consistent order prevents two searches targeting the same
+        // indexes from deadlocking each other.
+        let mut index_ids: Vec<&IndexId> = index_ids.iter().collect();
+        index_ids.sort_unstable();
+        index_ids.dedup();
+
+        let mut index_quotas_list = Vec::with_capacity(index_ids.len());
+
+        for index_id in index_ids {
+            let index_quotas = self.index_quotas(index_id, search_quotas);
+            let num_queued_searches = index_quotas.num_queued_searches.load(Ordering::Relaxed);
+
+            if num_queued_searches >= search_quotas.max_num_queued_searches_per_index as usize
+                && index_quotas.search_semaphore.available_permits() == 0
+            {
+                SEARCH_METRICS.root_searches_rejected_total.inc();
+                return Err(SearchError::TooManyRequests(format!(
+                    "too many searches are queued for index `{index_id}`"
+                )));
+            }
+            index_quotas_list.push((index_id.clone(), index_quotas));
+        }
+        let aggregation_memory_mibs = if has_aggregations {
+            num_mibs(self.aggregation_memory_limit)
+        } else {
+            0
+        };
+        let acquire_permits_fut = async {
+            let mut semaphore_permits = Vec::new();
+
+            for (_index_id, index_quotas) in &index_quotas_list {
+                index_quotas
+                    .num_queued_searches
+                    .fetch_add(1, Ordering::Relaxed);
+                let _queued_search_guard = QueuedSearchGuard {
+                    num_queued_searches: &index_quotas.num_queued_searches,
+                };
+                if priority == SearchPriority::Batch {
+                    if let Some(batch_search_semaphore) = &index_quotas.batch_search_semaphore_opt {
+                        let permit = batch_search_semaphore
+                            .clone()
+                            .acquire_owned()
+                            .await
+                            .expect("semaphore should not be closed");
+                        semaphore_permits.push(permit);
+                    }
+                }
+                let permit = index_quotas
+                    .search_semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("semaphore should not be closed");
+                semaphore_permits.push(permit);
+
+                if aggregation_memory_mibs > 0 {
+                    if let Some(aggregation_memory_semaphore) =
+                        &index_quotas.aggregation_memory_semaphore_opt
+                    {
+                        let permit = aggregation_memory_semaphore
+                            .clone()
+                            .acquire_many_owned(aggregation_memory_mibs)
+                            .await
+                            .expect("semaphore should not be closed");
+                        semaphore_permits.push(permit);
+                    }
+                }
+            }
+            semaphore_permits
+        };
+        let semaphore_permits =
+            tokio::time::timeout(search_quotas.queue_timeout(), acquire_permits_fut)
+                .await
+                .map_err(|_| {
+                    SEARCH_METRICS.root_searches_rejected_total.inc();
+                    let index_ids = index_quotas_list
+                        .iter()
+                        .map(|(index_id, _)| index_id.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    SearchError::TooManyRequests(format!(
+                        "search timed out waiting for its turn on indexes `{index_ids}`"
+                    ))
+                })?;
+        Ok(SearchAdmissionPermit {
+            _semaphore_permits: semaphore_permits,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::{NonZeroU32, NonZeroU64};
+
+    use super::*;
+
+    fn search_quotas_for_test() -> SearchQuotasConfig {
+        SearchQuotasConfig {
+            max_num_concurrent_searches_per_index: NonZeroU32::new(2).unwrap(),
+            max_num_concurrent_batch_searches_per_index: Some(NonZeroU32::new(1).unwrap()),
+            max_aggregation_memory_per_index: Some(ByteSize::mib(10)),
+            max_num_queued_searches_per_index: 1,
+            queue_timeout_secs: NonZeroU64::new(1).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_admission_controller_disabled() {
+        let admission_controller = SearchAdmissionController::new(None, ByteSize::mib(10));
+        let index_ids = vec!["test-index".to_string()];
+        let mut permits = Vec::new();
+
+        for _ in 0..10 {
+            let permit = admission_controller
+                .admit(&index_ids, SearchPriority::Batch, true)
+                .await
+                .unwrap();
+            permits.push(permit);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_admission_controller_batch_searches() {
+        tokio::time::pause();
+        let admission_controller =
+            SearchAdmissionController::new(Some(search_quotas_for_test()), ByteSize::mib(1));
+        let index_ids = vec!["test-index".to_string()];
+
+        let batch_permit = admission_controller
+            .admit(&index_ids, SearchPriority::Batch, false)
+            .await
+            .unwrap();
+
+        // The only batch slot is taken.
+        let error = admission_controller
+            .admit(&index_ids, SearchPriority::Batch, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, SearchError::TooManyRequests(_)));
+
+        // Interactive searches can still run.
+        let interactive_permit = admission_controller
+            .admit(&index_ids, SearchPriority::Interactive, false)
+            .await
+            .unwrap();
+
+        // Other indexes are not affected.
+        let other_index_ids = vec!["other-index".to_string()];
+        admission_controller
+            .admit(&other_index_ids, SearchPriority::Batch, false)
+            .await
+            .unwrap();
+
+        drop(batch_permit);
+        drop(interactive_permit);
+
+        admission_controller
+            .admit(&index_ids, SearchPriority::Batch, false)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_admission_controller_aggregation_memory() {
+        tokio::time::pause();
+        let admission_controller =
+            SearchAdmissionController::new(Some(search_quotas_for_test()), ByteSize::mib(6));
+        let index_ids = vec!["test-index".to_string()];
+
+        let aggregation_permit = admission_controller
+            .admit(&index_ids, SearchPriority::Interactive, true)
+            .await
+            .unwrap();
+
+        // The aggregation memory budget of the index is exhausted.
+        let error = admission_controller
+            .admit(&index_ids, SearchPriority::Interactive, true)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, SearchError::TooManyRequests(_)));
+
+        // Searches without aggregations are not affected.
+        admission_controller
+            .admit(&index_ids, SearchPriority::Interactive, false)
+            .await
+            .unwrap();
+
+        drop(aggregation_permit);
+
+        admission_controller
+            .admit(&index_ids, SearchPriority::Interactive, true)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_admission_controller_queue_full() {
+        let admission_controller = Arc::new(SearchAdmissionController::new(
+            Some(search_quotas_for_test()),
+            ByteSize::mib(1),
+        ));
+        let index_ids = vec!["test-index".to_string()];
+
+        let _permit_0 = admission_controller
+            .admit(&index_ids, SearchPriority::Interactive, false)
+            .await
+            .unwrap();
+        let _permit_1 = admission_controller
+            .admit(&index_ids, SearchPriority::Interactive, false)
+            .await
+            .unwrap();
+
+        let admission_controller_clone = admission_controller.clone();
+        let index_ids_clone = index_ids.clone();
+        let queued_search_handle = tokio::spawn(async move {
+            admission_controller_clone
+                .admit(&index_ids_clone, SearchPriority::Interactive, false)
+                .await
+        });
+        while admission_controller
+            .index_quotas("test-index", &search_quotas_for_test())
+            .num_queued_searches
+            .load(Ordering::Relaxed)
+            == 0
+        {
+            tokio::task::yield_now().await;
+        }
+        // The queue can hold a single search.
+        let error = admission_controller
+            .admit(&index_ids, SearchPriority::Interactive, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, SearchError::TooManyRequests(_)));
+
+        queued_search_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_search_admission_controller_evicts_idle_index_quotas() {
+        tokio::time::pause();
+        let admission_controller =
+            SearchAdmissionController::new(Some(search_quotas_for_test()), ByteSize::mib(1));
+
+        let idle_index_ids = vec!["test-index-idle".to_string()];
+        let permit = admission_controller
+            .admit(&idle_index_ids, SearchPriority::Interactive, true)
+            .await
+            .unwrap();
+        drop(permit);
+
+        let busy_index_ids = vec!["test-index-busy".to_string()];
+        let _permit = admission_controller
+            .admit(&busy_index_ids, SearchPriority::Batch, true)
+            .await
+            .unwrap();
+
+        tokio::time::advance(IDLE_INDEX_QUOTAS_EVICTION_INTERVAL).await;
+
+        admission_controller
+            .admit(&busy_index_ids, SearchPriority::Interactive, false)
+            .await
+            .unwrap();
+
+        let index_quotas_guard = admission_controller.index_quotas.lock().unwrap();
+        assert_eq!(index_quotas_guard.index_quotas.len(), 1);
+        assert!(index_quotas_guard
+            .index_quotas
+            .contains_key("test-index-busy"));
+    }
+}
@@ -0,0 +1,340 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::future::Future;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+use quickwit_metastore::{split_tag_filter, SplitMetadata};
+use quickwit_proto::search::{SearchProfile, SplitPlan, SplitSearchProfile};
+use quickwit_query::query_ast::QueryAst;
+use quickwit_storage::{wrap_storage_with_download_counter, OwnedBytes, Storage, StorageCache};
+
+use crate::field_stats_pruning::split_may_match;
+
+/// Collects the timings and resource usage of the search of a single split when the search
+/// request has `profile` set.
+pub(crate) struct SplitSearchProfiler {
+    split_profile: Mutex<SplitSearchProfile>,
+    num_bytes_downloaded: Arc<AtomicU64>,
+    num_cache_hits: AtomicU64,
+    num_cache_misses: AtomicU64,
+}
+
+impl SplitSearchProfiler {
+    pub fn new(split_id: String) -> Arc<Self> {
+        let split_profile = SplitSearchProfile {
+            split_id,
+            ..Default::default()
+        };
+        Arc::new(SplitSearchProfiler {
+            split_profile: Mutex::new(split_profile),
+            num_bytes_downloaded: Arc::default(),
+            num_cache_hits: AtomicU64::default(),
+            num_cache_misses: AtomicU64::default(),
+        })
+    }
+
+    /// Adds the time elapsed since `start` to the timing selected by `timing_micros`.
+    pub fn record_elapsed(
+        &self,
+        timing_micros: fn(&mut SplitSearchProfile) -> &mut u64,
+        start: Instant,
+    ) {
+        let elapsed_micros = start.elapsed().as_micros() as u64;
+        *timing_micros(&mut self.split_profile.lock().unwrap()) += elapsed_micros;
+    }
+
+    pub fn record_leaf_search_cache_hit(&self) {
+        self.split_profile.lock().unwrap().leaf_search_cache_hit = true;
+    }
+
+    pub fn record_pruned_by_bloom_filters(&self) {
+        self.split_profile.lock().unwrap().pruned_by_bloom_filters = true;
+    }
+
+    pub fn record_cache_hit(&self) {
+        self.num_cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_cache_miss(&self) {
+        self.num_cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Wraps the storage of the index so that the bytes downloaded from it are accounted for.
+    pub fn wrap_storage(&self, storage: Arc<dyn Storage>) -> Arc<dyn Storage> {
+        wrap_storage_with_download_counter(storage, self.num_bytes_downloaded.clone())
+    }
+
+    /// Wraps the fast fields cache so that its hits and misses are accounted for.
+    pub fn wrap_cache(self: &Arc<Self>, cache: Arc<dyn StorageCache>) -> Arc<dyn StorageCache> {
+        Arc::new(StorageCacheWithProfiler {
+            cache,
+            profiler: self.clone(),
+        })
+    }
+
+    pub fn split_profile(&self) -> SplitSearchProfile {
+        let mut split_profile = self.split_profile.lock().unwrap().clone();
+        split_profile.num_bytes_downloaded = self.num_bytes_downloaded.load(Ordering::Relaxed);
+        split_profile.num_cache_hits = self.num_cache_hits.load(Ordering::Relaxed);
+        split_profile.num_cache_misses = self.num_cache_misses.load(Ordering::Relaxed);
+        split_profile
+    }
+}
+
+/// Awaits `future`, adding its duration to the timing selected by `timing_micros` if a profiler
+/// is provided.
+pub(crate) async fn profile_future<F: Future>(
+    profiler_opt: Option<&Arc<SplitSearchProfiler>>,
+    timing_micros: fn(&mut SplitSearchProfile) -> &mut u64,
+    future: F,
+) -> F::Output {
+    let start = Instant::now();
+    let output = future.await;
+
+    if let Some(profiler) = profiler_opt {
+        profiler.record_elapsed(timing_micros, start);
+    }
+    output
+}
+
+struct StorageCacheWithProfiler {
+    cache: Arc<dyn StorageCache>,
+    profiler: Arc<SplitSearchProfiler>,
+}
+
+impl StorageCacheWithProfiler {
+    fn record_lookup(&self, path: &Path, bytes_opt: &Option<OwnedBytes>) {
+        // The fast fields cache is looked up for every file of the split, but only stores fast
+        // fields: the lookups of other files are not relevant.
+        if !path.to_string_lossy().ends_with(".fast") {
+            return;
+        }
+        if bytes_opt.is_some() {
+            self.profiler.record_cache_hit();
+        } else {
+            self.profiler.record_cache_miss();
+        }
+    }
+}
+
+#[async_trait]
+impl StorageCache for StorageCacheWithProfiler {
+    async fn get(&self, path: &Path, byte_range: Range<usize>) -> Option<OwnedBytes> {
+        let bytes_opt = self.cache.get(path, byte_range).await;
+        self.record_lookup(path, &bytes_opt);
+        bytes_opt
+    }
+
+    async fn get_all(&self, path: &Path) -> Option<OwnedBytes> {
+        let bytes_opt = self.cache.get_all(path).await;
+        self.record_lookup(path, &bytes_opt);
+        bytes_opt
+    }
+
+    async fn put(&self, path: PathBuf, byte_range: Range<usize>, bytes: OwnedBytes) {
+        self.cache.put(path, byte_range, bytes).await
+    }
+
+    async fn put_all(&self, path: PathBuf, bytes: OwnedBytes) {
+        self.cache.put_all(path, bytes).await
+    }
+}
+
+/// Applies the time range and tags pruning usually performed by the metastore, followed by the
+/// field statistics pruning, and builds the search profile describing the splits that remain to
+/// be searched.
+pub(crate) fn prune_splits_and_build_search_profile(
+    split_metadatas: Vec<SplitMetadata>,
+    start_timestamp_opt: Option<i64>,
+    end_timestamp_opt: Option<i64>,
+    tag_filter_ast_opt: Option<&TagFilterAst>,
+    query_ast: &QueryAst,
+) -> (Vec<SplitMetadata>, SearchProfile) {
+    let mut search_profile = SearchProfile {
+        num_splits: split_metadatas.len() as u64,
+        ..Default::default()
+    };
+    let mut relevant_split_metadatas = Vec::with_capacity(split_metadatas.len());
+
+    for split_metadata in split_metadatas {
+        if let Some(time_range) = &split_metadata.time_range {
+            let is_after_start = start_timestamp_opt
+                .map(|start_timestamp| *time_range.end() >= start_timestamp)
+                .unwrap_or(true);
+            let is_before_end = end_timestamp_opt
+                .map(|end_timestamp| *time_range.start() < end_timestamp)
+                .unwrap_or(true);
+
+            if !is_after_start || !is_before_end {
+                search_profile.num_splits_pruned_by_time_range += 1;
+                continue;
+            }
+        }
+        if !split_tag_filter(&split_metadata, tag_filter_ast_opt) {
+            search_profile.num_splits_pruned_by_tags += 1;
+            continue;
+        }
+        if !split_may_match(query_ast, &split_metadata.field_stats) {
+            search_profile.num_splits_pruned_by_field_stats += 1;
+            continue;
+        }
+        let split_plan = SplitPlan {
+            index_id: split_metadata.index_uid.index_id.clone(),
+            split_id: split_metadata.split_id.clone(),
+            num_docs: split_metadata.num_docs as u64,
+            footer_num_bytes: split_metadata.footer_offsets.end
+                - split_metadata.footer_offsets.start,
+            split_num_bytes: split_metadata.footer_offsets.end,
+            timestamp_start: split_metadata
+                .time_range
+                .as_ref()
+                .map(|time_range| *time_range.start()),
+            timestamp_end: split_metadata
+                .time_range
+                .as_ref()
+                .map(|time_range| *time_range.end()),
+        };
+        search_profile.estimated_min_num_bytes += split_plan.footer_num_bytes;
+        search_profile.estimated_max_num_bytes += split_plan.split_num_bytes;
+        search_profile.split_plans.push(split_plan);
+        relevant_split_metadatas.push(split_metadata);
+    }
+    (relevant_split_metadatas, search_profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use quickwit_doc_mapper::tag_pruning::tag;
+    use quickwit_metastore::FieldStats;
+    use quickwit_proto::types::IndexUid;
+    use quickwit_query::query_ast::TermQuery;
+    use quickwit_storage::QuickwitCache;
+
+    use super::*;
+
+    fn mock_split(split_id: &str, time_range: Option<(i64, i64)>, tags: &[&str]) -> SplitMetadata {
+        SplitMetadata {
+            split_id: split_id.to_string(),
+            index_uid: IndexUid::new_with_random_ulid("test-index"),
+            num_docs: 10,
+            time_range: time_range.map(|(start, end)| start..=end),
+            tags: tags
+                .iter()
+                .map(|tag| tag.to_string())
+                .collect::<BTreeSet<_>>(),
+            footer_offsets: 900..1000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_prune_splits_and_build_search_profile() {
+        let mut split_metadatas = vec![
+            mock_split("split-1", Some((0, 99)), &["tenant:a"]),
+            mock_split("split-2", Some((100, 199)), &["tenant:a"]),
+            mock_split("split-3", Some((200, 299)), &["tenant:b"]),
+            mock_split("split-4", None, &["tenant:a"]),
+            mock_split("split-5", Some((150, 250)), &["tenant:b"]),
+            mock_split("split-6", Some((100, 199)), &["tenant:a"]),
+        ];
+        split_metadatas[5].field_stats.insert(
+            "status".to_string(),
+            FieldStats::U64Range { min: 500, max: 599 },
+        );
+        let tag_filter_ast = tag("tenant:a");
+        let query_ast: QueryAst = TermQuery {
+            field: "status".to_string(),
+            value: "200".to_string(),
+        }
+        .into();
+        let (relevant_split_metadatas, search_profile) = prune_splits_and_build_search_profile(
+            split_metadatas,
+            Some(100),
+            Some(200),
+            Some(&tag_filter_ast),
+            &query_ast,
+        );
+        let relevant_split_ids: Vec<&str> = relevant_split_metadatas
+            .iter()
+            .map(|split_metadata| split_metadata.split_id.as_str())
+            .collect();
+        assert_eq!(relevant_split_ids, ["split-2", "split-4"]);
+
+        assert_eq!(search_profile.num_splits, 6);
+        assert_eq!(search_profile.num_splits_pruned_by_time_range, 2);
+        assert_eq!(search_profile.num_splits_pruned_by_tags, 1);
+        assert_eq!(search_profile.num_splits_pruned_by_field_stats, 1);
+        assert_eq!(search_profile.split_plans.len(), 2);
+        assert_eq!(search_profile.split_plans[0].split_id, "split-2");
+        assert_eq!(search_profile.split_plans[0].index_id, "test-index");
+        assert_eq!(search_profile.split_plans[0].timestamp_start, Some(100));
+        assert_eq!(search_profile.split_plans[0].timestamp_end, Some(199));
+        assert_eq!(search_profile.split_plans[1].timestamp_start, None);
+        assert_eq!(search_profile.estimated_min_num_bytes, 200);
+        assert_eq!(search_profile.estimated_max_num_bytes, 2000);
+        assert!(search_profile.split_profiles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_split_search_profiler() {
+        let profiler = SplitSearchProfiler::new("split-1".to_string());
+
+        let cache: Arc<dyn StorageCache> = Arc::new(QuickwitCache::new(1_000));
+        let cache_with_profiler = profiler.wrap_cache(cache);
+        let path = Path::new("segment.fast");
+        assert!(cache_with_profiler.get(path, 0..3).await.is_none());
+        cache_with_profiler
+            .put(path.to_path_buf(), 0..3, OwnedBytes::new(&b"abc"[..]))
+            .await;
+        assert!(cache_with_profiler.get(path, 0..3).await.is_some());
+        assert!(cache_with_profiler
+            .get(Path::new("segment.idx"), 0..3)
+            .await
+            .is_none());
+
+        let start = Instant::now();
+        profile_future(
+            Some(&profiler),
+            |split_profile| &mut split_profile.warmup_micros,
+            tokio::time::sleep(std::time::Duration::from_millis(1)),
+        )
+        .await;
+        profiler.record_elapsed(
+            |split_profile| &mut split_profile.footer_fetch_micros,
+            start,
+        );
+
+        let split_profile = profiler.split_profile();
+        assert_eq!(split_profile.split_id, "split-1");
+        assert!(!split_profile.leaf_search_cache_hit);
+        assert_eq!(split_profile.num_cache_hits, 1);
+        assert_eq!(split_profile.num_cache_misses, 1);
+        assert!(split_profile.warmup_micros >= 1_000);
+        assert!(split_profile.footer_fetch_micros >= split_profile.warmup_micros);
+    }
+}
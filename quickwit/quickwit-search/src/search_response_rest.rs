@@ -20,7 +20,7 @@
 use std::convert::TryFrom;
 
 use quickwit_common::truncate_str;
-use quickwit_proto::search::SearchResponse;
+use quickwit_proto::search::{SearchProfile, SearchResponse};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 
@@ -47,6 +47,9 @@ pub struct SearchResponseRest {
     #[schema(value_type = Object)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aggregations: Option<JsonValue>,
+    /// Search profile, only present if `profile` or `dry_run` was set in the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<SearchProfile>,
 }
 
 impl TryFrom<SearchResponse> for SearchResponseRest {
@@ -97,6 +100,7 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
             elapsed_time_micros: search_response.elapsed_time_micros,
             errors: search_response.errors,
             aggregations: aggregations_opt,
+            profile: search_response.profile,
         })
     }
 }
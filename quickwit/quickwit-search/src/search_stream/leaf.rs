@@ -133,6 +133,7 @@ async fn leaf_search_stream_single_split(
         &split,
         Some(doc_mapper.tokenizer_manager()),
         true,
+        None,
     )
     .await?;
     let split_schema = index.schema();
@@ -186,7 +187,7 @@ async fn leaf_search_stream_single_split(
     warmup_info.fast_field_names.extend(fast_field_names);
     warmup_info.simplify();
 
-    warmup(&searcher, &warmup_info).await?;
+    warmup(&searcher, &warmup_info, None).await?;
 
     let span = info_span!(
         "collect_fast_field",
@@ -47,8 +47,12 @@ use crate::list_fields::{leaf_list_fields, root_list_fields};
 use crate::list_fields_cache::ListFieldsCache;
 use crate::list_terms::{leaf_list_terms, root_list_terms};
 use crate::root::fetch_docs_phase;
+use crate::root_search_cache::RootSearchCache;
+use crate::runtime_fields::parse_runtime_fields;
 use crate::scroll_context::{MiniKV, ScrollContext, ScrollKeyAndStartOffset};
+use crate::search_admission::SearchAdmissionController;
 use crate::search_stream::{leaf_search_stream, root_search_stream};
+use crate::tiered_storage::resolve_index_storage;
 use crate::{fetch_docs, leaf_search, root_search, ClusterClient, SearchError};
 
 #[derive(Clone)]
@@ -197,7 +201,12 @@ impl SearchService for SearchServiceImpl {
             .ok_or_else(|| SearchError::Internal("no search request".to_string()))?
             .into();
         let index_uri = Uri::from_str(&leaf_search_request.index_uri)?;
-        let storage = self.storage_resolver.resolve(&index_uri).await?;
+        let storage = resolve_index_storage(
+            &self.storage_resolver,
+            &index_uri,
+            &leaf_search_request.split_offsets,
+        )
+        .await?;
         let doc_mapper = deserialize_doc_mapper(&leaf_search_request.doc_mapper)?;
 
         let leaf_search_response = leaf_search(
@@ -217,10 +226,16 @@ impl SearchService for SearchServiceImpl {
         fetch_docs_request: FetchDocsRequest,
     ) -> crate::Result<FetchDocsResponse> {
         let index_uri = Uri::from_str(&fetch_docs_request.index_uri)?;
-        let storage = self.storage_resolver.resolve(&index_uri).await?;
+        let storage = resolve_index_storage(
+            &self.storage_resolver,
+            &index_uri,
+            &fetch_docs_request.split_offsets,
+        )
+        .await?;
         let snippet_request_opt: Option<&SnippetRequest> =
             fetch_docs_request.snippet_request.as_ref();
         let doc_mapper = deserialize_doc_mapper(&fetch_docs_request.doc_mapper)?;
+        let runtime_fields = parse_runtime_fields(&fetch_docs_request.runtime_mappings)?;
         let fetch_docs_response = fetch_docs(
             self.searcher_context.clone(),
             fetch_docs_request.partial_hits,
@@ -228,6 +243,7 @@ impl SearchService for SearchServiceImpl {
             &fetch_docs_request.split_offsets,
             doc_mapper,
             snippet_request_opt,
+            &runtime_fields,
         )
         .await?;
 
@@ -255,7 +271,12 @@ impl SearchService for SearchServiceImpl {
             .request
             .ok_or_else(|| SearchError::Internal("no search request".to_string()))?;
         let index_uri = Uri::from_str(&leaf_stream_request.index_uri)?;
-        let storage = self.storage_resolver.resolve(&index_uri).await?;
+        let storage = resolve_index_storage(
+            &self.storage_resolver,
+            &index_uri,
+            &leaf_stream_request.split_offsets,
+        )
+        .await?;
         let doc_mapper = deserialize_doc_mapper(&leaf_stream_request.doc_mapper)?;
         let leaf_receiver = leaf_search_stream(
             self.searcher_context.clone(),
@@ -290,7 +311,12 @@ impl SearchService for SearchServiceImpl {
             .list_terms_request
             .ok_or_else(|| SearchError::Internal("no search request".to_string()))?;
         let index_uri = Uri::from_str(&leaf_search_request.index_uri)?;
-        let storage = self.storage_resolver.resolve(&index_uri).await?;
+        let storage = resolve_index_storage(
+            &self.storage_resolver,
+            &index_uri,
+            &leaf_search_request.split_offsets,
+        )
+        .await?;
         let split_ids = leaf_search_request.split_offsets;
 
         let leaf_search_response = leaf_list_terms(
@@ -344,7 +370,12 @@ impl SearchService for SearchServiceImpl {
         list_fields_req: LeafListFieldsRequest,
     ) -> crate::Result<ListFieldsResponse> {
         let index_uri = Uri::from_str(&list_fields_req.index_uri)?;
-        let storage = self.storage_resolver.resolve(&index_uri).await?;
+        let storage = resolve_index_storage(
+            &self.storage_resolver,
+            &index_uri,
+            &list_fields_req.split_offsets,
+        )
+        .await?;
         let index_id = list_fields_req.index_id;
         let split_ids = list_fields_req.split_offsets;
         leaf_list_fields(
@@ -402,6 +433,7 @@ pub(crate) async fn scroll(
         &scroll_context.split_metadatas[..],
         &scroll_context.search_request,
         cluster_client,
+        None,
     )
     .await?;
 
@@ -428,6 +460,7 @@ pub(crate) async fn scroll(
         scroll_id: Some(next_scroll_id.to_string()),
         errors: Vec::new(),
         aggregation: None,
+        profile: None,
     })
 }
 /// [`SearcherContext`] provides a common set of variables
@@ -446,10 +479,14 @@ pub struct SearcherContext {
     pub split_stream_semaphore: Semaphore,
     /// Recent sub-query cache.
     pub leaf_search_cache: LeafSearchCache,
+    /// Recent root search results cache. `None` if the root search cache is disabled.
+    pub root_search_cache_opt: Option<RootSearchCache>,
     /// Search split cache. `None` if no split cache is configured.
     pub split_cache_opt: Option<Arc<SplitCache>>,
     /// List fields cache. Caches the list fields response for a given split.
     pub list_fields_cache: ListFieldsCache,
+    /// Enforces the per-index search quotas on root searches.
+    pub search_admission_controller: SearchAdmissionController,
 }
 
 impl std::fmt::Debug for SearcherContext {
@@ -490,6 +527,17 @@ impl SearcherContext {
             LeafSearchCache::new(searcher_config.partial_request_cache_capacity.as_u64() as usize);
         let list_fields_cache =
             ListFieldsCache::new(searcher_config.partial_request_cache_capacity.as_u64() as usize);
+        let root_search_cache_capacity =
+            searcher_config.root_search_cache_capacity.as_u64() as usize;
+        let root_search_cache_opt = if root_search_cache_capacity > 0 {
+            Some(RootSearchCache::new(root_search_cache_capacity))
+        } else {
+            None
+        };
+        let search_admission_controller = SearchAdmissionController::new(
+            searcher_config.search_quotas,
+            searcher_config.aggregation_memory_limit,
+        );
 
         Self {
             searcher_config,
@@ -498,8 +546,10 @@ impl SearcherContext {
             split_footer_cache: global_split_footer_cache,
             split_stream_semaphore,
             leaf_search_cache,
+            root_search_cache_opt,
             list_fields_cache,
             split_cache_opt,
+            search_admission_controller,
         }
     }
 
@@ -26,8 +26,8 @@ use quickwit_doc_mapper::DefaultDocMapper;
 use quickwit_indexing::TestSandbox;
 use quickwit_opentelemetry::otlp::TraceId;
 use quickwit_proto::search::{
-    LeafListTermsResponse, ListTermsRequest, SearchRequest, SortByValue, SortField, SortOrder,
-    SortValue,
+    LeafListTermsResponse, ListTermsRequest, RuntimeMapping, SearchRequest, SortByValue, SortField,
+    SortOrder, SortValue,
 };
 use quickwit_query::query_ast::{
     qast_helper, qast_json_helper, query_ast_from_user_text, QueryAst,
@@ -912,6 +912,211 @@ async fn test_sort_by_2_field() {
     test_sandbox.assert_quit().await;
 }
 
+#[tokio::test]
+async fn test_sort_by_runtime_field() {
+    let index_id = "sort_by_runtime_field".to_string();
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: start
+                type: i64
+                fast: true
+              - name: end
+                type: i64
+                fast: true
+              - name: message
+                type: text
+            "#;
+    let test_sandbox = TestSandbox::create(&index_id, doc_mapping_yaml, "{}", &[])
+        .await
+        .unwrap();
+    let docs = vec![
+        json!({"start": 10, "end": 15, "message": "status=200"}),
+        json!({"start": 10, "end": 40, "message": "status=500"}),
+        json!({"start": 20, "end": 30, "message": "status=404"}),
+        json!({"message": "no timings"}),
+    ];
+    test_sandbox.add_documents(docs).await.unwrap();
+
+    let query_ast_json = serde_json::to_string(&QueryAst::MatchAll).unwrap();
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: query_ast_json,
+        max_hits: 1_000,
+        sort_fields: vec![SortField {
+            field_name: "duration".to_string(),
+            sort_order: SortOrder::Desc as i32,
+            sort_datetime_format: None,
+        }],
+        runtime_mappings: vec![
+            RuntimeMapping {
+                name: "duration".to_string(),
+                field_type: "long".to_string(),
+                script: "end - start".to_string(),
+            },
+            RuntimeMapping {
+                name: "status".to_string(),
+                field_type: "long".to_string(),
+                script: r"regex_extract(message, 'status=(\d+)')".to_string(),
+            },
+        ],
+        ..Default::default()
+    };
+    let search_resp = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(search_resp.num_hits, 4);
+    let ordered_docs: Vec<u32> = search_resp
+        .hits
+        .iter()
+        .map(|hit| hit.partial_hit.as_ref().unwrap().doc_id)
+        .collect();
+    assert_eq!(&ordered_docs[..], &[1, 2, 0, 3]);
+
+    let hit_json: JsonValue = serde_json::from_str(&search_resp.hits[0].json).unwrap();
+    assert_json_include!(
+        actual: hit_json,
+        expected: json!({"duration": 30, "status": 500})
+    );
+    let hit_json: JsonValue = serde_json::from_str(&search_resp.hits[3].json).unwrap();
+    assert_json_include!(
+        actual: hit_json,
+        expected: json!({"duration": null, "status": null})
+    );
+    test_sandbox.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_aggregation_on_runtime_fields() {
+    let index_id = "aggregation_on_runtime_fields".to_string();
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: start
+                type: i64
+                fast: true
+              - name: end
+                type: i64
+                fast: true
+              - name: service
+                type: text
+                tokenizer: raw
+                fast: true
+            "#;
+    let test_sandbox = TestSandbox::create(&index_id, doc_mapping_yaml, "{}", &[])
+        .await
+        .unwrap();
+    let docs = vec![
+        json!({"start": 10, "end": 15, "service": "Frontend"}),
+        json!({"start": 10, "end": 40, "service": "frontend"}),
+        json!({"start": 20, "end": 30, "service": "Backend"}),
+        json!({"service": "backend"}),
+    ];
+    test_sandbox.add_documents(docs).await.unwrap();
+
+    let aggregation_request = json!({
+        "services": {
+            "terms": {"field": "service_lowercase"},
+            "aggs": {"avg_duration": {"avg": {"field": "duration"}}}
+        },
+        "durations": {"histogram": {"field": "duration", "interval": 10}},
+        "max_start": {"max": {"field": "start"}},
+    });
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: serde_json::to_string(&QueryAst::MatchAll).unwrap(),
+        aggregation_request: Some(aggregation_request.to_string()),
+        runtime_mappings: vec![
+            RuntimeMapping {
+                name: "duration".to_string(),
+                field_type: "long".to_string(),
+                script: "end - start".to_string(),
+            },
+            RuntimeMapping {
+                name: "service_lowercase".to_string(),
+                field_type: "keyword".to_string(),
+                script: "lowercase(service)".to_string(),
+            },
+        ],
+        ..Default::default()
+    };
+    let search_resp = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(search_resp.num_hits, 4);
+
+    let aggregation_json: JsonValue =
+        serde_json::from_str(search_resp.aggregation.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        aggregation_json,
+        json!({
+            "services": {
+                "doc_count_error_upper_bound": 0,
+                "sum_other_doc_count": 0,
+                "buckets": [
+                    {"key": "backend", "doc_count": 2, "avg_duration": {"value": 10.0}},
+                    {"key": "frontend", "doc_count": 2, "avg_duration": {"value": 17.5}},
+                ]
+            },
+            "durations": {
+                "buckets": [
+                    {"key": 0.0, "doc_count": 1},
+                    {"key": 10.0, "doc_count": 1},
+                    {"key": 20.0, "doc_count": 0},
+                    {"key": 30.0, "doc_count": 1},
+                ]
+            },
+            "max_start": {"value": 20.0},
+        })
+    );
+    test_sandbox.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_aggregation_on_runtime_fields_unsupported_aggregation() {
+    let index_id = "aggregation_on_runtime_fields_unsupported".to_string();
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: start
+                type: i64
+                fast: true
+            "#;
+    let test_sandbox = TestSandbox::create(&index_id, doc_mapping_yaml, "{}", &[])
+        .await
+        .unwrap();
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: serde_json::to_string(&QueryAst::MatchAll).unwrap(),
+        aggregation_request: Some(
+            json!({"double_start_percentiles": {"percentiles": {"field": "double_start"}}})
+                .to_string(),
+        ),
+        runtime_mappings: vec![RuntimeMapping {
+            name: "double_start".to_string(),
+            field_type: "double".to_string(),
+            script: "start * 2".to_string(),
+        }],
+        ..Default::default()
+    };
+    let search_error = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(search_error.to_string().contains(
+        "`percentiles` aggregations are not supported in requests referencing runtime fields"
+    ));
+    test_sandbox.assert_quit().await;
+}
+
 #[tokio::test]
 async fn test_single_node_invalid_sorting_with_query() {
     let index_id = "single-node-invalid-sorting";
@@ -0,0 +1,209 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use quickwit_common::split_file;
+use quickwit_common::uri::Uri;
+use quickwit_proto::search::SplitIdAndFooterOffsets;
+use quickwit_storage::{
+    BulkDeleteError, OwnedBytes, PutPayload, SendableAsync, Storage, StorageResolver, StorageResult,
+};
+use tokio::io::AsyncRead;
+
+/// Resolves the storage the leaf requests should read the given splits from.
+///
+/// Splits moved to another storage by the index lifecycle policy carry their storage URI. If some
+/// of the splits are in that case, the returned storage routes the reads of their split files to
+/// the storage holding them, and all the other reads to the index storage.
+pub(crate) async fn resolve_index_storage(
+    storage_resolver: &StorageResolver,
+    index_uri: &Uri,
+    splits: &[SplitIdAndFooterOffsets],
+) -> crate::Result<Arc<dyn Storage>> {
+    let index_storage = storage_resolver.resolve(index_uri).await?;
+    let mut storages: HashMap<&str, Arc<dyn Storage>> = HashMap::new();
+    let mut split_storages: HashMap<PathBuf, Arc<dyn Storage>> = HashMap::new();
+
+    for split in splits {
+        let Some(storage_uri_str) = split.storage_uri.as_deref() else {
+            continue;
+        };
+        let split_storage = if let Some(storage) = storages.get(storage_uri_str) {
+            storage.clone()
+        } else {
+            let storage_uri = Uri::from_str(storage_uri_str)?;
+            let storage = storage_resolver.resolve(&storage_uri).await?;
+            storages.insert(storage_uri_str, storage.clone());
+            storage
+        };
+        split_storages.insert(PathBuf::from(split_file(&split.split_id)), split_storage);
+    }
+    if split_storages.is_empty() {
+        return Ok(index_storage);
+    }
+    let tiered_index_storage = TieredIndexStorage {
+        index_storage,
+        split_storages,
+    };
+    Ok(Arc::new(tiered_index_storage))
+}
+
+/// Storage proxy dispatching each split file to the storage holding it. Writes always go to the
+/// index storage.
+struct TieredIndexStorage {
+    index_storage: Arc<dyn Storage>,
+    split_storages: HashMap<PathBuf, Arc<dyn Storage>>,
+}
+
+impl TieredIndexStorage {
+    fn storage(&self, path: &Path) -> &dyn Storage {
+        self.split_storages
+            .get(path)
+            .unwrap_or(&self.index_storage)
+            .as_ref()
+    }
+}
+
+impl fmt::Debug for TieredIndexStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TieredIndexStorage")
+            .field("uri", self.index_storage.uri())
+            .field("num_tiered_splits", &self.split_storages.len())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Storage for TieredIndexStorage {
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        self.index_storage.check_connectivity().await
+    }
+
+    async fn put(&self, path: &Path, payload: Box<dyn PutPayload>) -> StorageResult<()> {
+        self.index_storage.put(path, payload).await
+    }
+
+    async fn copy_to(&self, path: &Path, output: &mut dyn SendableAsync) -> StorageResult<()> {
+        self.storage(path).copy_to(path, output).await
+    }
+
+    async fn get_slice(&self, path: &Path, range: Range<usize>) -> StorageResult<OwnedBytes> {
+        self.storage(path).get_slice(path, range).await
+    }
+
+    async fn get_slice_stream(
+        &self,
+        path: &Path,
+        range: Range<usize>,
+    ) -> StorageResult<Box<dyn AsyncRead + Send + Unpin>> {
+        self.storage(path).get_slice_stream(path, range).await
+    }
+
+    async fn get_all(&self, path: &Path) -> StorageResult<OwnedBytes> {
+        self.storage(path).get_all(path).await
+    }
+
+    async fn delete(&self, path: &Path) -> StorageResult<()> {
+        self.index_storage.delete(path).await
+    }
+
+    async fn bulk_delete<'a>(&self, paths: &[&'a Path]) -> Result<(), BulkDeleteError> {
+        self.index_storage.bulk_delete(paths).await
+    }
+
+    async fn exists(&self, path: &Path) -> StorageResult<bool> {
+        self.storage(path).exists(path).await
+    }
+
+    async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
+        self.storage(path).file_num_bytes(path).await
+    }
+
+    fn uri(&self) -> &Uri {
+        self.index_storage.uri()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split_for_test(split_id: &str, storage_uri_opt: Option<&str>) -> SplitIdAndFooterOffsets {
+        SplitIdAndFooterOffsets {
+            split_id: split_id.to_string(),
+            split_footer_start: 0,
+            split_footer_end: 0,
+            timestamp_start: None,
+            timestamp_end: None,
+            storage_uri: storage_uri_opt.map(|storage_uri| storage_uri.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_index_storage_routes_tiered_splits() {
+        let storage_resolver = StorageResolver::for_test();
+        let index_uri = Uri::for_test("ram:///indexes/test-index");
+        let cold_storage_uri = Uri::for_test("ram:///cold/test-index");
+
+        let index_storage = storage_resolver.resolve(&index_uri).await.unwrap();
+        index_storage
+            .put(Path::new("hot.split"), Box::new(b"hot".to_vec()))
+            .await
+            .unwrap();
+        let cold_storage = storage_resolver.resolve(&cold_storage_uri).await.unwrap();
+        cold_storage
+            .put(Path::new("cold.split"), Box::new(b"cold".to_vec()))
+            .await
+            .unwrap();
+
+        let splits = [split_for_test("hot", None)];
+        let storage = resolve_index_storage(&storage_resolver, &index_uri, &splits)
+            .await
+            .unwrap();
+        assert!(format!("{storage:?}").starts_with("PrefixStorage"));
+
+        let splits = [
+            split_for_test("hot", None),
+            split_for_test("cold", Some("ram:///cold/test-index")),
+        ];
+        let storage = resolve_index_storage(&storage_resolver, &index_uri, &splits)
+            .await
+            .unwrap();
+        assert_eq!(storage.uri(), &index_uri);
+
+        let hot_bytes = storage.get_all(Path::new("hot.split")).await.unwrap();
+        assert_eq!(hot_bytes.as_slice(), b"hot");
+
+        let cold_bytes = storage.get_all(Path::new("cold.split")).await.unwrap();
+        assert_eq!(cold_bytes.as_slice(), b"cold");
+
+        let cold_slice = storage
+            .get_slice(Path::new("cold.split"), 1..3)
+            .await
+            .unwrap();
+        assert_eq!(cold_slice.as_slice(), b"ol");
+    }
+}
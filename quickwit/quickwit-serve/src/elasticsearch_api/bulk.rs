@@ -136,6 +136,7 @@ async fn elastic_ingest_bulk(
     let bulk_response = ElasticBulkResponse {
         took_millis,
         errors,
+        items: Vec::new(),
     };
     Ok(bulk_response)
 }
@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::time::Instant;
 
 use hyper::StatusCode;
@@ -39,6 +40,83 @@ pub(crate) struct ElasticBulkResponse {
     #[serde(rename = "took")]
     pub took_millis: u64,
     pub errors: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<ElasticBulkItem>,
+}
+
+/// Outcome of a single action of a bulk request, keyed by the type of the action.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ElasticBulkItem {
+    Create(ElasticBulkItemResponse),
+    Index(ElasticBulkItemResponse),
+}
+
+impl ElasticBulkItem {
+    #[cfg(test)]
+    pub fn response(&self) -> &ElasticBulkItemResponse {
+        match self {
+            ElasticBulkItem::Create(response) => response,
+            ElasticBulkItem::Index(response) => response,
+        }
+    }
+
+    fn response_mut(&mut self) -> &mut ElasticBulkItemResponse {
+        match self {
+            ElasticBulkItem::Create(response) => response,
+            ElasticBulkItem::Index(response) => response,
+        }
+    }
+
+    fn set_error(&mut self, status: StatusCode, error_type: &str, reason: String) {
+        let response = self.response_mut();
+        response.status = status.as_u16();
+        response.error = Some(ElasticBulkItemError {
+            error_type: error_type.to_string(),
+            reason,
+        });
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ElasticBulkItemResponse {
+    #[serde(rename = "_index")]
+    pub index_id: IndexId,
+    pub status: u16,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ElasticBulkItemError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ElasticBulkItemError {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub reason: String,
+}
+
+/// Returns the status code and Elasticsearch error type reported for the items of a subrequest
+/// that failed.
+fn ingest_failure_to_es_error(reason: IngestFailureReason) -> (StatusCode, &'static str) {
+    match reason {
+        IngestFailureReason::IndexNotFound => (StatusCode::NOT_FOUND, "index_not_found_exception"),
+        IngestFailureReason::RateLimited
+        | IngestFailureReason::ResourceExhausted
+        | IngestFailureReason::QuotaExceeded => (
+            StatusCode::TOO_MANY_REQUESTS,
+            "es_rejected_execution_exception",
+        ),
+        IngestFailureReason::NoShardsAvailable => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "unavailable_shards_exception",
+        ),
+        IngestFailureReason::SourceNotFound
+        | IngestFailureReason::Internal
+        | IngestFailureReason::Unspecified => {
+            (StatusCode::INTERNAL_SERVER_ERROR, "internal_server_error")
+        }
+    }
 }
 
 pub(crate) async fn elastic_bulk_ingest_v2(
@@ -50,6 +128,9 @@ pub(crate) async fn elastic_bulk_ingest_v2(
     let now = Instant::now();
     let mut ingest_request_builder = IngestRequestV2Builder::default();
     let mut lines = lines(&body.content).enumerate();
+    let mut items: Vec<ElasticBulkItem> = Vec::new();
+    // For each index, the positions in `items` of the documents of its doc batch.
+    let mut per_index_id_item_positions: HashMap<IndexId, Vec<usize>> = HashMap::new();
 
     while let Some((line_no, line)) = lines.next() {
         let action = serde_json::from_slice::<BulkAction>(line).map_err(|error| {
@@ -68,6 +149,7 @@ pub(crate) async fn elastic_bulk_ingest_v2(
         // `my-index`, ES honors it and creates the doc for the requested index. That is,
         // `my-index` is a default value in case `_index`` is missing, but not a constraint on
         // each sub-action.
+        let is_create = matches!(action, BulkAction::Create(_));
         let index_id = action
             .into_index_id()
             .or_else(|| default_index_id.clone())
@@ -77,6 +159,21 @@ pub(crate) async fn elastic_bulk_ingest_v2(
                     format!("`_index` field of action on line #{line_no} is missing"),
                 )
             })?;
+        per_index_id_item_positions
+            .entry(index_id.clone())
+            .or_default()
+            .push(items.len());
+        let item_response = ElasticBulkItemResponse {
+            index_id: index_id.clone(),
+            status: StatusCode::CREATED.as_u16(),
+            error: None,
+        };
+        let item = if is_create {
+            ElasticBulkItem::Create(item_response)
+        } else {
+            ElasticBulkItem::Index(item_response)
+        };
+        items.push(item);
         ingest_request_builder.add_doc(index_id, source);
     }
     let commit_type: CommitTypeV2 = bulk_options.refresh.into();
@@ -89,7 +186,7 @@ pub(crate) async fn elastic_bulk_ingest_v2(
     if let Some(ingest_request) = ingest_request_opt {
         let ingest_response_v2 = ingest_router.ingest(ingest_request).await?;
         let took_millis = now.elapsed().as_millis() as u64;
-        let errors = !ingest_response_v2.failures.is_empty();
+        let mut errors = !ingest_response_v2.failures.is_empty();
 
         for failure in ingest_response_v2.failures {
             // This custom logic for Airmail is temporary.
@@ -98,10 +195,42 @@ pub(crate) async fn elastic_bulk_ingest_v2(
                 let elasticsearch_error = ElasticsearchError::new(StatusCode::NOT_FOUND, reason);
                 return Err(elasticsearch_error);
             }
+            let Some(item_positions) = per_index_id_item_positions.get(&failure.index_id) else {
+                continue;
+            };
+            let (status, error_type) = ingest_failure_to_es_error(failure.reason());
+            let reason = format!(
+                "failed to ingest document into index `{}`: {}",
+                failure.index_id,
+                failure.reason().as_str_name()
+            );
+            for &item_position in item_positions {
+                items[item_position].set_error(status, error_type, reason.clone());
+            }
+        }
+        for success in ingest_response_v2.successes {
+            let index_id = &success.index_uid().index_id;
+
+            let Some(item_positions) = per_index_id_item_positions.get(index_id) else {
+                continue;
+            };
+            for parse_failure in success.parse_failures {
+                let Some(&item_position) = item_positions.get(parse_failure.doc_ordinal as usize)
+                else {
+                    continue;
+                };
+                items[item_position].set_error(
+                    StatusCode::BAD_REQUEST,
+                    "mapper_parsing_exception",
+                    parse_failure.message,
+                );
+                errors = true;
+            }
         }
         let bulk_response = ElasticBulkResponse {
             took_millis,
             errors,
+            items,
         };
         Ok(bulk_response)
     } else {
@@ -112,13 +241,14 @@ pub(crate) async fn elastic_bulk_ingest_v2(
 #[cfg(test)]
 mod tests {
     use quickwit_proto::ingest::router::{
-        IngestFailure, IngestFailureReason, IngestResponseV2, IngestSuccess,
+        IngestFailure, IngestFailureReason, IngestResponseV2, IngestSuccess, ParseFailure,
+        ParseFailureReason,
     };
     use quickwit_proto::types::{IndexUid, Position, ShardId};
     use warp::{Filter, Rejection, Reply};
 
     use super::*;
-    use crate::elasticsearch_api::bulk_v2::ElasticBulkResponse;
+    use crate::elasticsearch_api::bulk_v2::{ElasticBulkItem, ElasticBulkResponse};
     use crate::elasticsearch_api::filter::elastic_bulk_filter;
     use crate::elasticsearch_api::make_elastic_api_response;
     use crate::elasticsearch_api::model::ElasticsearchError;
@@ -171,6 +301,9 @@ mod tests {
                             source_id: INGEST_V2_SOURCE_ID.to_string(),
                             shard_id: Some(ShardId::from(1)),
                             replication_position_inclusive: Some(Position::offset(1u64)),
+                            parse_failures: Vec::new(),
+                            published_split_ids: Vec::new(),
+                            published_position_inclusive: None,
                         },
                         IngestSuccess {
                             subrequest_id: 1,
@@ -178,6 +311,9 @@ mod tests {
                             source_id: INGEST_V2_SOURCE_ID.to_string(),
                             shard_id: Some(ShardId::from(1)),
                             replication_position_inclusive: Some(Position::offset(0u64)),
+                            parse_failures: Vec::new(),
+                            published_split_ids: Vec::new(),
+                            published_position_inclusive: None,
                         },
                     ],
                     failures: Vec::new(),
@@ -204,6 +340,105 @@ mod tests {
 
         let bulk_response: ElasticBulkResponse = serde_json::from_slice(response.body()).unwrap();
         assert!(!bulk_response.errors);
+        assert_eq!(bulk_response.items.len(), 3);
+
+        for item in &bulk_response.items {
+            assert!(matches!(item, ElasticBulkItem::Create(_)));
+            assert_eq!(item.response().status, 201);
+            assert!(item.response().error.is_none());
+        }
+        assert_eq!(bulk_response.items[0].response().index_id, "my-index-1");
+        assert_eq!(bulk_response.items[1].response().index_id, "my-index-2");
+        assert_eq!(bulk_response.items[2].response().index_id, "my-index-1");
+    }
+
+    #[tokio::test]
+    async fn test_bulk_api_reports_item_errors() {
+        let mut ingest_router_mock = IngestRouterServiceClient::mock();
+        ingest_router_mock
+            .expect_ingest()
+            .once()
+            .returning(|ingest_request| {
+                assert_eq!(ingest_request.subrequests.len(), 2);
+
+                let subrequest_id_1 = ingest_request
+                    .subrequests
+                    .iter()
+                    .find(|subrequest| subrequest.index_id == "my-index-1")
+                    .unwrap()
+                    .subrequest_id;
+                let subrequest_id_2 = ingest_request
+                    .subrequests
+                    .iter()
+                    .find(|subrequest| subrequest.index_id == "my-index-2")
+                    .unwrap()
+                    .subrequest_id;
+
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess {
+                        subrequest_id: subrequest_id_1,
+                        index_uid: Some(IndexUid::for_test("my-index-1", 0)),
+                        source_id: INGEST_V2_SOURCE_ID.to_string(),
+                        shard_id: Some(ShardId::from(1)),
+                        replication_position_inclusive: Some(Position::offset(0u64)),
+                        parse_failures: vec![ParseFailure {
+                            doc_ordinal: 1,
+                            reason: ParseFailureReason::InvalidSchema as i32,
+                            message: "the field `ts` could not be parsed".to_string(),
+                        }],
+                        published_split_ids: Vec::new(),
+                        published_position_inclusive: None,
+                    }],
+                    failures: vec![IngestFailure {
+                        subrequest_id: subrequest_id_2,
+                        index_id: "my-index-2".to_string(),
+                        source_id: INGEST_V2_SOURCE_ID.to_string(),
+                        reason: IngestFailureReason::RateLimited as i32,
+                    }],
+                })
+            });
+        let ingest_router = IngestRouterServiceClient::from(ingest_router_mock);
+        let handler = es_compat_bulk_handler_v2(ingest_router);
+
+        let payload = r#"
+            {"create": {"_index": "my-index-1"}}
+            {"ts": 1, "message": "my-message-1"}
+            {"index": {"_index": "my-index-2"}}
+            {"ts": 1, "message": "my-message-1"}
+            {"index": {"_index": "my-index-1"}}
+            {"ts": "foo", "message": "my-message-2"}
+        "#;
+        let response = warp::test::request()
+            .path("/_elastic/_bulk")
+            .method("POST")
+            .body(payload)
+            .reply(&handler)
+            .await;
+        assert_eq!(response.status(), 200);
+
+        let bulk_response: ElasticBulkResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(bulk_response.errors);
+        assert_eq!(bulk_response.items.len(), 3);
+
+        let item = &bulk_response.items[0];
+        assert!(matches!(item, ElasticBulkItem::Create(_)));
+        assert_eq!(item.response().status, 201);
+        assert!(item.response().error.is_none());
+
+        let item = &bulk_response.items[1];
+        assert!(matches!(item, ElasticBulkItem::Index(_)));
+        assert_eq!(item.response().index_id, "my-index-2");
+        assert_eq!(item.response().status, 429);
+        let error = item.response().error.as_ref().unwrap();
+        assert_eq!(error.error_type, "es_rejected_execution_exception");
+
+        let item = &bulk_response.items[2];
+        assert!(matches!(item, ElasticBulkItem::Index(_)));
+        assert_eq!(item.response().index_id, "my-index-1");
+        assert_eq!(item.response().status, 400);
+        let error = item.response().error.as_ref().unwrap();
+        assert_eq!(error.error_type, "mapper_parsing_exception");
+        assert_eq!(error.reason, "the field `ts` could not be parsed");
     }
 
     #[tokio::test]
@@ -247,6 +482,9 @@ mod tests {
                         source_id: INGEST_V2_SOURCE_ID.to_string(),
                         shard_id: Some(ShardId::from(1)),
                         replication_position_inclusive: Some(Position::offset(0u64)),
+                        parse_failures: Vec::new(),
+                        published_split_ids: Vec::new(),
+                        published_position_inclusive: None,
                     }],
                     failures: Vec::new(),
                 })
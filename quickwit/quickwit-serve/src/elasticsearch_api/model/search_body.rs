@@ -17,11 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
 use quickwit_proto::search::SortOrder;
 use quickwit_query::{ElasticQueryDsl, OneFieldMap};
+use quickwit_search::RuntimeFieldMapping;
 use serde::de::{MapAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 
@@ -86,6 +87,8 @@ pub struct SearchBody {
     pub stored_fields: Option<BTreeSet<String>>,
     #[serde(default)]
     pub search_after: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub runtime_mappings: BTreeMap<String, RuntimeFieldMapping>,
 }
 
 struct FieldSortVecVisitor;
@@ -266,7 +269,36 @@ mod tests {
         assert!(error_msg.contains("unknown field `term`"));
         assert!(error_msg.contains(
             "expected one of `from`, `size`, `query`, `sort`, `aggs`, `track_total_hits`, \
-             `stored_fields`, `search_after`"
+             `stored_fields`, `search_after`, `runtime_mappings`"
         ));
     }
+
+    #[test]
+    fn test_runtime_mappings() {
+        let json = r#"
+        {
+            "runtime_mappings": {
+                "duration_ms": {
+                    "type": "long",
+                    "script": {
+                        "source": "emit(doc['end'].value - doc['start'].value)"
+                    }
+                }
+            },
+            "sort": [{ "duration_ms": "desc" }]
+        }
+        "#;
+        let search_body: SearchBody = serde_json::from_str(json).unwrap();
+        let runtime_mapping = search_body
+            .runtime_mappings
+            .get("duration_ms")
+            .unwrap()
+            .clone()
+            .into_runtime_mapping("duration_ms".to_string());
+        assert_eq!(runtime_mapping.field_type, "long");
+        assert_eq!(
+            runtime_mapping.script,
+            "emit(doc['end'].value - doc['start'].value)"
+        );
+    }
 }
@@ -34,8 +34,8 @@ use quickwit_index_management::IndexService;
 use quickwit_metastore::*;
 use quickwit_proto::metastore::MetastoreServiceClient;
 use quickwit_proto::search::{
-    CountHits, ListFieldsResponse, PartialHit, ScrollRequest, SearchResponse, SortByValue,
-    SortDatetimeFormat,
+    CountHits, ListFieldsResponse, PartialHit, RuntimeMapping, ScrollRequest, SearchPriority,
+    SearchResponse, SortByValue, SortDatetimeFormat,
 };
 use quickwit_proto::types::IndexUid;
 use quickwit_query::query_ast::{BoolQuery, QueryAst, UserInputQuery};
@@ -261,6 +261,12 @@ fn build_request_for_es_api(
         });
     }
 
+    let runtime_mappings: Vec<RuntimeMapping> = search_body
+        .runtime_mappings
+        .into_iter()
+        .map(|(name, runtime_mapping)| runtime_mapping.into_runtime_mapping(name))
+        .collect();
+
     let aggregation_request: Option<String> = if search_body.aggs.is_empty() {
         None
     } else {
@@ -321,6 +327,10 @@ fn build_request_for_es_api(
             scroll_ttl_secs,
             search_after,
             count_hits,
+            priority: SearchPriority::Interactive.into(),
+            profile: false,
+            dry_run: false,
+            runtime_mappings,
         },
         has_doc_id_field,
     ))
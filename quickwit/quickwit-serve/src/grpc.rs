@@ -26,6 +26,7 @@ use quickwit_cluster::cluster_grpc_server;
 use quickwit_common::tower::BoxFutureInfaillible;
 use quickwit_config::service::QuickwitService;
 use quickwit_proto::indexing::IndexingServiceClient;
+use quickwit_proto::jaeger::storage::v1::dependencies_reader_plugin_server::DependenciesReaderPluginServer;
 use quickwit_proto::jaeger::storage::v1::span_reader_plugin_server::SpanReaderPluginServer;
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::logs_service_server::LogsServiceServer;
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceServiceServer;
@@ -159,13 +160,17 @@ pub(crate) async fn start_grpc_server(
         None
     };
 
-    // Mount gRPC jaeger service if present.
-    let jaeger_grpc_service = if let Some(jaeger_service) = services.jaeger_service_opt.clone() {
-        enabled_grpc_services.insert("jaeger");
-        Some(SpanReaderPluginServer::new(jaeger_service))
-    } else {
-        None
-    };
+    // Mount gRPC jaeger services if present.
+    let (jaeger_grpc_service, jaeger_dependencies_grpc_service) =
+        if let Some(jaeger_service) = services.jaeger_service_opt.clone() {
+            enabled_grpc_services.insert("jaeger");
+            (
+                Some(SpanReaderPluginServer::new(jaeger_service.clone())),
+                Some(DependenciesReaderPluginServer::new(jaeger_service)),
+            )
+        } else {
+            (None, None)
+        };
     let server_router = server
         .add_service(cluster_grpc_service)
         .add_optional_service(control_plane_grpc_service)
@@ -174,6 +179,7 @@ pub(crate) async fn start_grpc_server(
         .add_optional_service(ingest_router_grpc_service)
         .add_optional_service(ingester_grpc_service)
         .add_optional_service(jaeger_grpc_service)
+        .add_optional_service(jaeger_dependencies_grpc_service)
         .add_optional_service(metastore_grpc_service)
         .add_optional_service(otlp_log_grpc_service)
         .add_optional_service(otlp_trace_grpc_service)
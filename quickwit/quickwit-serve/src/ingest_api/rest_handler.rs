@@ -27,8 +27,8 @@ use quickwit_proto::ingest::router::{
     IngestFailureReason, IngestRequestV2, IngestResponseV2, IngestRouterService,
     IngestRouterServiceClient, IngestSubrequest,
 };
-use quickwit_proto::types::IndexId;
-use serde::Deserialize;
+use quickwit_proto::types::{IndexId, Position, ShardId, SplitId};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use warp::{Filter, Rejection};
 
@@ -56,6 +56,19 @@ struct InvalidUtf8;
 
 impl warp::reject::Reject for InvalidUtf8 {}
 
+/// Response of the ingest v2 endpoint. For `wait_for` and `force` commits, it also holds the IDs of
+/// the splits in which the documents were published along with the publish position of the shard.
+#[derive(Debug, Default, Serialize, PartialEq)]
+struct IngestV2Response {
+    num_docs_for_processing: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shard_id: Option<ShardId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    published_split_ids: Vec<SplitId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published_position_inclusive: Option<Position>,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 struct IngestOptions {
     #[serde(alias = "commit")]
@@ -127,7 +140,7 @@ async fn ingest_v2(
     body: Body,
     ingest_options: IngestOptions,
     mut ingest_router: IngestRouterServiceClient,
-) -> Result<IngestResponse, IngestServiceError> {
+) -> Result<IngestV2Response, IngestServiceError> {
     let mut doc_batch_builder = DocBatchV2Builder::default();
 
     for doc in lines(&body.content) {
@@ -136,7 +149,7 @@ async fn ingest_v2(
     let doc_batch_opt = doc_batch_builder.build();
 
     let Some(doc_batch) = doc_batch_opt else {
-        let response = IngestResponse::default();
+        let response = IngestV2Response::default();
         return Ok(response);
     };
     let num_docs = doc_batch.num_docs();
@@ -146,6 +159,7 @@ async fn ingest_v2(
         index_id,
         source_id: INGEST_V2_SOURCE_ID.to_string(),
         doc_batch: Some(doc_batch),
+        dedup_id: None,
     };
     let request = IngestRequestV2 {
         commit_type: ingest_options.commit_type as i32,
@@ -158,7 +172,7 @@ async fn ingest_v2(
 fn convert_ingest_response_v2(
     mut response: IngestResponseV2,
     num_docs: usize,
-) -> Result<IngestResponse, IngestServiceError> {
+) -> Result<IngestV2Response, IngestServiceError> {
     let num_responses = response.successes.len() + response.failures.len();
     if num_responses != 1 {
         return Err(IngestServiceError::Internal(format!(
@@ -166,9 +180,14 @@ fn convert_ingest_response_v2(
             num_responses
         )));
     }
-    if response.successes.pop().is_some() {
-        return Ok(IngestResponse {
-            num_docs_for_processing: num_docs as u64,
+    if let Some(ingest_success) = response.successes.pop() {
+        // Documents rejected by the router are not processed.
+        let num_docs_for_processing = num_docs - ingest_success.parse_failures.len();
+        return Ok(IngestV2Response {
+            num_docs_for_processing: num_docs_for_processing as u64,
+            shard_id: ingest_success.shard_id,
+            published_split_ids: ingest_success.published_split_ids,
+            published_position_inclusive: ingest_success.published_position_inclusive,
         });
     }
     let ingest_failure = response.failures.pop().unwrap();
@@ -187,6 +206,7 @@ fn convert_ingest_response_v2(
         IngestFailureReason::NoShardsAvailable => IngestServiceError::Unavailable,
         IngestFailureReason::RateLimited => IngestServiceError::RateLimited,
         IngestFailureReason::ResourceExhausted => IngestServiceError::RateLimited,
+        IngestFailureReason::QuotaExceeded => IngestServiceError::RateLimited,
     })
 }
 
@@ -282,9 +302,12 @@ pub(crate) mod tests {
         IngestApiService, IngestResponse, IngestServiceClient, SuggestTruncateRequest,
         QUEUES_DIR_NAME,
     };
-    use quickwit_proto::ingest::router::IngestRouterServiceClient;
+    use quickwit_proto::ingest::router::{
+        IngestResponseV2, IngestRouterServiceClient, IngestSuccess, ParseFailure,
+    };
+    use quickwit_proto::types::{IndexUid, Position, ShardId};
 
-    use super::ingest_api_handlers;
+    use super::{convert_ingest_response_v2, ingest_api_handlers};
     use crate::ingest_api::lines;
 
     #[test]
@@ -305,6 +328,55 @@ pub(crate) mod tests {
         }
     }
 
+    #[test]
+    fn test_convert_ingest_response_v2() {
+        let response = IngestResponseV2 {
+            successes: vec![IngestSuccess {
+                subrequest_id: 0,
+                index_uid: Some(IndexUid::for_test("test-index", 0)),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                replication_position_inclusive: Some(Position::offset(2u64)),
+                parse_failures: vec![ParseFailure::default()],
+                published_split_ids: Vec::new(),
+                published_position_inclusive: None,
+            }],
+            failures: Vec::new(),
+        };
+        let rest_response = convert_ingest_response_v2(response, 3).unwrap();
+        assert_eq!(
+            serde_json::to_value(rest_response).unwrap(),
+            serde_json::json!({
+                "num_docs_for_processing": 2,
+                "shard_id": "00000000000000000001",
+            })
+        );
+
+        let response = IngestResponseV2 {
+            successes: vec![IngestSuccess {
+                subrequest_id: 0,
+                index_uid: Some(IndexUid::for_test("test-index", 0)),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                replication_position_inclusive: Some(Position::offset(2u64)),
+                parse_failures: Vec::new(),
+                published_split_ids: vec!["test-split".to_string()],
+                published_position_inclusive: Some(Position::offset(2u64)),
+            }],
+            failures: Vec::new(),
+        };
+        let rest_response = convert_ingest_response_v2(response, 3).unwrap();
+        assert_eq!(
+            serde_json::to_value(rest_response).unwrap(),
+            serde_json::json!({
+                "num_docs_for_processing": 3,
+                "shard_id": "00000000000000000001",
+                "published_split_ids": ["test-split"],
+                "published_position_inclusive": "00000000000000000002",
+            })
+        );
+    }
+
     pub(crate) async fn setup_ingest_service(
         queues: &[&str],
         config: &IngestApiConfig,
@@ -44,6 +44,7 @@ mod rest_api_response;
 mod search_api;
 pub(crate) mod simple_list;
 mod template_api;
+mod tempo_api;
 mod ui_handler;
 
 use std::collections::{HashMap, HashSet};
@@ -86,9 +87,11 @@ use quickwit_indexing::actors::IndexingService;
 use quickwit_indexing::models::ShardPositionsService;
 use quickwit_indexing::start_indexing_service;
 use quickwit_ingest::{
-    get_idle_shard_timeout, setup_local_shards_update_listener, start_ingest_api_service,
+    get_idle_shard_timeout, setup_ingest_volume_update_listener,
+    setup_local_shards_update_listener, start_ingest_api_service, start_kafka_produce_server,
     wait_for_ingester_decommission, wait_for_ingester_status, GetMemoryCapacity, IngestRequest,
-    IngestRouter, IngestServiceClient, Ingester, IngesterPool, LocalShardsUpdate,
+    IngestRouter, IngestServiceClient, IngestVolumeUpdate, Ingester, IngesterPool,
+    LocalShardsUpdate,
 };
 use quickwit_jaeger::JaegerService;
 use quickwit_janitor::{start_janitor_service, JanitorService};
@@ -203,6 +206,7 @@ struct QuickwitServices {
     /// We must maintain a reference to the subscription handles to continue receiving
     /// notifications. Otherwise, the subscriptions are dropped.
     _local_shards_update_listener_handle_opt: Option<ListenerHandle>,
+    _ingest_volume_update_listener_handle_opt: Option<ListenerHandle>,
     _report_splits_subscription_handle_opt: Option<EventSubscriptionHandle>,
 }
 
@@ -401,6 +405,7 @@ pub async fn serve_quickwit(
                 .stack_delete_index_layer(broker_layer.clone())
                 .stack_add_source_layer(broker_layer.clone())
                 .stack_delete_source_layer(broker_layer.clone())
+                .stack_delete_shards_layer(broker_layer.clone())
                 .stack_toggle_source_layer(broker_layer)
                 .stack_layer(METASTORE_GRPC_SERVER_METRICS_LAYER.clone())
                 .build(metastore);
@@ -585,6 +590,14 @@ pub async fn serve_quickwit(
         None
     };
 
+    // The control plane listens for ingest volume updates to enforce the ingest quotas.
+    let ingest_volume_update_listener_handle_opt =
+        if node_config.is_service_enabled(QuickwitService::ControlPlane) {
+            Some(setup_ingest_volume_update_listener(cluster.clone(), event_broker.clone()).await)
+        } else {
+            None
+        };
+
     let report_splits_subscription_handle_opt =
         // DISCLAIMER: This is quirky here: We base our decision to forward the split report depending
         // on the current searcher configuration.
@@ -604,6 +617,8 @@ pub async fn serve_quickwit(
             storage_resolver.clone(),
             event_broker.clone(),
             std::env::var(DISABLE_DELETE_TASK_SERVICE_ENV_KEY).is_err(),
+            Some(search_service.clone()),
+            Some(ingest_service.clone()),
         )
         .await
         .context("failed to start janitor service")?;
@@ -642,6 +657,7 @@ pub async fn serve_quickwit(
 
     let grpc_listen_addr = node_config.grpc_listen_addr;
     let rest_listen_addr = node_config.rest_config.listen_addr;
+    let decommission_mode = node_config.ingest_api_config.decommission_mode;
     let quickwit_services: Arc<QuickwitServices> = Arc::new(QuickwitServices {
         node_config: Arc::new(node_config),
         cluster: cluster.clone(),
@@ -649,6 +665,7 @@ pub async fn serve_quickwit(
         metastore_client: metastore_through_control_plane.clone(),
         control_plane_service,
         _local_shards_update_listener_handle_opt: local_shards_update_listener_handle_opt,
+        _ingest_volume_update_listener_handle_opt: ingest_volume_update_listener_handle_opt,
         _report_splits_subscription_handle_opt: report_splits_subscription_handle_opt,
         index_manager,
         indexing_service_opt,
@@ -721,7 +738,9 @@ pub async fn serve_quickwit(
         // We must decommission the ingester first before terminating the indexing pipelines that
         // may consume from it. We also need to keep the gRPC server running while doing so.
         if let Some(ingester_service) = ingester_service_opt {
-            if let Err(error) = wait_for_ingester_decommission(ingester_service).await {
+            if let Err(error) =
+                wait_for_ingester_decommission(ingester_service, decommission_mode).await
+            {
                 error!("failed to decommission ingester gracefully: {:?}", error);
             }
         }
@@ -773,6 +792,8 @@ async fn setup_ingest_v2(
         control_plane.clone(),
         ingester_pool.clone(),
         replication_factor,
+        node_config.ingest_api_config.validate_docs,
+        node_config.ingest_api_config.dedup_window(),
     );
     ingest_router.subscribe(event_broker);
 
@@ -799,7 +820,7 @@ async fn setup_ingest_v2(
         let idle_shard_timeout = get_idle_shard_timeout();
         let ingester = Ingester::try_new(
             cluster.clone(),
-            control_plane,
+            control_plane.clone(),
             ingester_pool.clone(),
             &wal_dir_path,
             node_config.ingest_api_config.max_queue_disk_usage,
@@ -807,6 +828,8 @@ async fn setup_ingest_v2(
             rate_limiter_settings,
             replication_factor,
             idle_shard_timeout,
+            node_config.ingest_api_config.dedup_window(),
+            node_config.ingest_api_config.wal_compression_level,
         )
         .await?;
         ingester.subscribe(event_broker);
@@ -881,6 +904,28 @@ async fn setup_ingest_v2(
             .stack_layer(INGEST_GRPC_SERVER_METRICS_LAYER.clone())
             .build(ingester)
     });
+    if let (Some(ingester_service), Some(kafka_listen_port)) = (
+        &ingester_service_opt,
+        node_config.ingest_api_config.kafka_listen_port,
+    ) {
+        let listen_addr = SocketAddr::new(node_config.grpc_listen_addr.ip(), kafka_listen_port);
+        let advertise_addr =
+            SocketAddr::new(node_config.grpc_advertise_addr.ip(), kafka_listen_port);
+        start_kafka_produce_server(
+            listen_addr,
+            advertise_addr,
+            cluster.clone(),
+            control_plane,
+            ingester_service.clone(),
+            content_length_limit,
+            node_config
+                .ingest_api_config
+                .wal_compression_level
+                .is_some(),
+        )
+        .await
+        .context("failed to start Kafka produce server")?;
+    }
     Ok((ingest_router_service, ingester_service_opt))
 }
 
@@ -980,6 +1025,9 @@ async fn setup_control_plane(
     event_broker
         .subscribe_without_timeout::<LocalShardsUpdate>(subscriber.clone())
         .forever();
+    event_broker
+        .subscribe_without_timeout::<IngestVolumeUpdate>(subscriber.clone())
+        .forever();
     event_broker
         .subscribe_without_timeout::<ShardPositionsUpdate>(subscriber)
         .forever();
@@ -1036,6 +1084,7 @@ fn setup_indexer_pool(
                                 client,
                                 indexing_tasks,
                                 indexing_capacity,
+                                availability_zone: node.availability_zone().map(str::to_string),
                             },
                         );
                         Some(change)
@@ -1055,6 +1104,7 @@ fn setup_indexer_pool(
                                 client,
                                 indexing_tasks,
                                 indexing_capacity,
+                                availability_zone: node.availability_zone().map(str::to_string),
                             },
                         );
                         Some(change)
@@ -18,4 +18,4 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 mod rest_handler;
-pub(crate) use rest_handler::otlp_ingest_api_handlers;
+pub(crate) use rest_handler::{otlp_ingest_api_handlers, UnsupportedOtlpMediaType};
@@ -18,8 +18,11 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use bytes::Bytes;
+use hyper::header::CONTENT_TYPE;
 use quickwit_opentelemetry::otlp::{
-    OtlpGrpcLogsService, OtlpGrpcTracesService, OTEL_LOGS_INDEX_ID, OTEL_TRACES_INDEX_ID,
+    otlp_logs_response_to_json, otlp_traces_response_to_json, parse_otlp_logs_request_json,
+    parse_otlp_traces_request_json, OtlpGrpcLogsService, OtlpGrpcTracesService, OTEL_LOGS_INDEX_ID,
+    OTEL_TRACES_INDEX_ID,
 };
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::logs_service_server::LogsService;
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::{
@@ -31,11 +34,11 @@ use quickwit_proto::opentelemetry::proto::collector::trace::v1::{
 };
 use quickwit_proto::{tonic, ServiceError, ServiceErrorCode};
 use serde::{self, Serialize};
-use tracing::error;
+use warp::reject::Reject;
 use warp::{Filter, Rejection};
 
-use crate::rest_api_response::into_rest_api_response;
-use crate::{require, with_arg, BodyFormat};
+use crate::rest_api_response::{into_rest_api_response, RestApiResponse};
+use crate::{require, BodyFormat};
 
 #[derive(utoipa::OpenApi)]
 #[openapi(paths())]
@@ -52,22 +55,52 @@ pub(crate) fn otlp_ingest_api_handlers(
         .or(otlp_ingest_traces_handler(otlp_traces_service))
 }
 
+/// The encoding of an OTLP/HTTP payload, determined by the `content-type` header of the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OtlpEncoding {
+    Json,
+    Protobuf,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "request's content-type is not supported: supported media types are `application/json` and \
+     `application/x-protobuf`"
+)]
+pub(crate) struct UnsupportedOtlpMediaType;
+
+impl Reject for UnsupportedOtlpMediaType {}
+
+fn extract_otlp_encoding() -> impl Filter<Extract = (OtlpEncoding,), Error = Rejection> + Copy {
+    warp::header::<mime_guess::Mime>(CONTENT_TYPE.as_str()).and_then(
+        |mime: mime_guess::Mime| async move {
+            match mime.essence_str() {
+                "application/json" => Ok(OtlpEncoding::Json),
+                "application/x-protobuf" | "application/protobuf" => Ok(OtlpEncoding::Protobuf),
+                _ => Err(warp::reject::custom(UnsupportedOtlpMediaType)),
+            }
+        },
+    )
+}
+
 pub(crate) fn otlp_default_logs_handler(
     otlp_logs_service: Option<OtlpGrpcLogsService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     require(otlp_logs_service)
         .and(warp::path!("otlp" / "v1" / "logs"))
-        .and(warp::header::exact_ignore_case(
-            "content-type",
-            "application/x-protobuf",
-        ))
         .and(warp::post())
+        .and(extract_otlp_encoding())
         .and(warp::body::bytes())
-        .then(|otlp_logs_service, body| async move {
-            otlp_ingest_logs(otlp_logs_service, OTEL_LOGS_INDEX_ID.to_string(), body).await
+        .then(|otlp_logs_service, otlp_encoding, body| async move {
+            let export_result = otlp_ingest_logs(
+                otlp_logs_service,
+                OTEL_LOGS_INDEX_ID.to_string(),
+                otlp_encoding,
+                body,
+            )
+            .await;
+            make_otlp_logs_response(export_result, otlp_encoding)
         })
-        .and(with_arg(BodyFormat::default()))
-        .map(into_rest_api_response)
 }
 
 pub(crate) fn otlp_logs_handler(
@@ -75,15 +108,16 @@ pub(crate) fn otlp_logs_handler(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     require(otlp_log_service)
         .and(warp::path!(String / "otlp" / "v1" / "logs"))
-        .and(warp::header::exact_ignore_case(
-            "content-type",
-            "application/x-protobuf",
-        ))
         .and(warp::post())
+        .and(extract_otlp_encoding())
         .and(warp::body::bytes())
-        .then(otlp_ingest_logs)
-        .and(with_arg(BodyFormat::default()))
-        .map(into_rest_api_response)
+        .then(
+            |otlp_logs_service, index_id, otlp_encoding, body| async move {
+                let export_result =
+                    otlp_ingest_logs(otlp_logs_service, index_id, otlp_encoding, body).await;
+                make_otlp_logs_response(export_result, otlp_encoding)
+            },
+        )
 }
 
 pub(crate) fn otlp_default_traces_handler(
@@ -91,17 +125,19 @@ pub(crate) fn otlp_default_traces_handler(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     require(otlp_traces_service)
         .and(warp::path!("otlp" / "v1" / "traces"))
-        .and(warp::header::exact_ignore_case(
-            "content-type",
-            "application/x-protobuf",
-        ))
         .and(warp::post())
+        .and(extract_otlp_encoding())
         .and(warp::body::bytes())
-        .then(|otlp_traces_service, body| async move {
-            otlp_ingest_traces(otlp_traces_service, OTEL_TRACES_INDEX_ID.to_string(), body).await
+        .then(|otlp_traces_service, otlp_encoding, body| async move {
+            let export_result = otlp_ingest_traces(
+                otlp_traces_service,
+                OTEL_TRACES_INDEX_ID.to_string(),
+                otlp_encoding,
+                body,
+            )
+            .await;
+            make_otlp_traces_response(export_result, otlp_encoding)
         })
-        .and(with_arg(BodyFormat::default()))
-        .map(into_rest_api_response)
 }
 
 pub(crate) fn otlp_ingest_traces_handler(
@@ -109,15 +145,16 @@ pub(crate) fn otlp_ingest_traces_handler(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     require(otlp_traces_service)
         .and(warp::path!(String / "otlp" / "v1" / "traces"))
-        .and(warp::header::exact_ignore_case(
-            "content-type",
-            "application/x-protobuf",
-        ))
         .and(warp::post())
+        .and(extract_otlp_encoding())
         .and(warp::body::bytes())
-        .then(otlp_ingest_traces)
-        .and(with_arg(BodyFormat::default()))
-        .map(into_rest_api_response)
+        .then(
+            |otlp_traces_service, index_id, otlp_encoding, body| async move {
+                let export_result =
+                    otlp_ingest_traces(otlp_traces_service, index_id, otlp_encoding, body).await;
+                make_otlp_traces_response(export_result, otlp_encoding)
+            },
+        )
 }
 
 #[derive(Debug, Clone, thiserror::Error, Serialize)]
@@ -137,32 +174,80 @@ impl ServiceError for OtlpApiError {
     }
 }
 
+impl From<tonic::Status> for OtlpApiError {
+    fn from(status: tonic::Status) -> Self {
+        // The OTLP services reject requests in which every record is invalid with
+        // `InvalidArgument`, which must not be retried by clients.
+        if status.code() == tonic::Code::InvalidArgument {
+            OtlpApiError::InvalidPayload(status.message().to_string())
+        } else {
+            OtlpApiError::Ingest(status.to_string())
+        }
+    }
+}
+
+/// Responds to JSON requests in OTLP/JSON. Protobuf requests are answered in the JSON
+/// serialization of the response message.
+fn make_otlp_logs_response(
+    export_result: Result<ExportLogsServiceResponse, OtlpApiError>,
+    otlp_encoding: OtlpEncoding,
+) -> RestApiResponse {
+    match otlp_encoding {
+        OtlpEncoding::Json => into_rest_api_response(
+            export_result.map(|response| otlp_logs_response_to_json(&response)),
+            BodyFormat::default(),
+        ),
+        OtlpEncoding::Protobuf => into_rest_api_response(export_result, BodyFormat::default()),
+    }
+}
+
+fn make_otlp_traces_response(
+    export_result: Result<ExportTraceServiceResponse, OtlpApiError>,
+    otlp_encoding: OtlpEncoding,
+) -> RestApiResponse {
+    match otlp_encoding {
+        OtlpEncoding::Json => into_rest_api_response(
+            export_result.map(|response| otlp_traces_response_to_json(&response)),
+            BodyFormat::default(),
+        ),
+        OtlpEncoding::Protobuf => into_rest_api_response(export_result, BodyFormat::default()),
+    }
+}
+
 async fn otlp_ingest_logs(
     otlp_logs_service: OtlpGrpcLogsService,
     _index_id: String, // <- TODO: use index ID when gRPC service supports it.
+    otlp_encoding: OtlpEncoding,
     body: Bytes,
 ) -> Result<ExportLogsServiceResponse, OtlpApiError> {
     // TODO: use index ID.
-    let export_logs_request: ExportLogsServiceRequest = prost::Message::decode(&body[..])
-        .map_err(|err| OtlpApiError::InvalidPayload(err.to_string()))?;
+    let export_logs_request: ExportLogsServiceRequest = match otlp_encoding {
+        OtlpEncoding::Json => parse_otlp_logs_request_json(&body)
+            .map_err(|err| OtlpApiError::InvalidPayload(err.to_string()))?,
+        OtlpEncoding::Protobuf => prost::Message::decode(&body[..])
+            .map_err(|err| OtlpApiError::InvalidPayload(err.to_string()))?,
+    };
     let result = otlp_logs_service
         .export(tonic::Request::new(export_logs_request))
-        .await
-        .map_err(|err| OtlpApiError::Ingest(err.to_string()))?;
+        .await?;
     Ok(result.into_inner())
 }
 
 async fn otlp_ingest_traces(
     otlp_traces_service: OtlpGrpcTracesService,
     _index_id: String, // <- TODO: use index ID when gRPC service supports it.
+    otlp_encoding: OtlpEncoding,
     body: Bytes,
 ) -> Result<ExportTraceServiceResponse, OtlpApiError> {
-    let export_traces_request: ExportTraceServiceRequest = prost::Message::decode(&body[..])
-        .map_err(|err| OtlpApiError::InvalidPayload(err.to_string()))?;
+    let export_traces_request: ExportTraceServiceRequest = match otlp_encoding {
+        OtlpEncoding::Json => parse_otlp_traces_request_json(&body)
+            .map_err(|err| OtlpApiError::InvalidPayload(err.to_string()))?,
+        OtlpEncoding::Protobuf => prost::Message::decode(&body[..])
+            .map_err(|err| OtlpApiError::InvalidPayload(err.to_string()))?,
+    };
     let response = otlp_traces_service
         .export(tonic::Request::new(export_traces_request))
-        .await
-        .map_err(|err| OtlpApiError::Ingest(err.to_string()))?;
+        .await?;
     Ok(response.into_inner())
 }
 
@@ -181,6 +266,7 @@ mod tests {
     };
     use quickwit_proto::opentelemetry::proto::logs::v1::{LogRecord, ResourceLogs, ScopeLogs};
     use quickwit_proto::opentelemetry::proto::resource::v1::Resource;
+    use serde_json::Value as JsonValue;
     use warp::Filter;
 
     use super::otlp_ingest_api_handlers;
@@ -333,4 +419,116 @@ mod tests {
             assert_eq!(actual_response.partial_success.unwrap().rejected_spans, 0);
         }
     }
+
+    #[tokio::test]
+    async fn test_otlp_ingest_traces_handler_json_partial_success() {
+        let mut ingest_service_mock = IngestServiceClient::mock();
+        ingest_service_mock
+            .expect_ingest()
+            .withf(|request| {
+                request.doc_batches.len() == 1 && request.doc_batches[0].doc_lengths.len() == 1
+            })
+            .returning(|_| {
+                Ok(IngestResponse {
+                    num_docs_for_processing: 1,
+                })
+            });
+        let ingest_service_client = IngestServiceClient::from(ingest_service_mock);
+        let logs_service = OtlpGrpcLogsService::new(ingest_service_client.clone());
+        let traces_service =
+            OtlpGrpcTracesService::new(ingest_service_client, Some(CommitType::Force));
+        let otlp_api_handler =
+            otlp_ingest_api_handlers(Some(logs_service), Some(traces_service)).recover(recover_fn);
+
+        let body = r#"{
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [{"key": "service.name", "value": {"stringValue": "api"}}]
+                },
+                "scopeSpans": [{
+                    "spans": [
+                        {
+                            "traceId": "5b8efff798038103d269b633813fc60c",
+                            "spanId": "eee19b7ec3c1b174",
+                            "name": "GET /",
+                            "kind": 2,
+                            "startTimeUnixNano": "1544712660000000000",
+                            "endTimeUnixNano": "1544712661000000000"
+                        },
+                        {
+                            "traceId": "010203",
+                            "spanId": "eee19b7ec3c1b175",
+                            "name": "GET /",
+                            "startTimeUnixNano": "1544712660000000000",
+                            "endTimeUnixNano": "1544712661000000000"
+                        }
+                    ]
+                }]
+            }]
+        }"#;
+        let resp = warp::test::request()
+            .path("/otlp/v1/traces")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(body)
+            .reply(&otlp_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            actual_response,
+            serde_json::json!({
+                "partialSuccess": {
+                    "rejectedSpans": "1",
+                    "errorMessage": "failed to parse span: `trace ID must be 16 bytes long, got 3`",
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_otlp_ingest_logs_handler_json_all_rejected() {
+        let ingest_service_client = IngestServiceClient::from(IngestServiceClient::mock());
+        let logs_service = OtlpGrpcLogsService::new(ingest_service_client.clone());
+        let traces_service = OtlpGrpcTracesService::new(ingest_service_client, None);
+        let otlp_api_handler =
+            otlp_ingest_api_handlers(Some(logs_service), Some(traces_service)).recover(recover_fn);
+
+        let body = r#"{
+            "resourceLogs": [{
+                "scopeLogs": [{
+                    "logRecords": [{
+                        "timeUnixNano": "1704036033047000000",
+                        "traceId": "010203"
+                    }]
+                }]
+            }]
+        }"#;
+        let resp = warp::test::request()
+            .path("/otlp/v1/logs")
+            .method("POST")
+            .header("content-type", "application/json; charset=utf-8")
+            .body(body)
+            .reply(&otlp_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_otlp_ingest_handler_unsupported_content_type() {
+        let ingest_service_client = IngestServiceClient::from(IngestServiceClient::mock());
+        let logs_service = OtlpGrpcLogsService::new(ingest_service_client.clone());
+        let traces_service = OtlpGrpcTracesService::new(ingest_service_client, None);
+        let otlp_api_handler =
+            otlp_ingest_api_handlers(Some(logs_service), Some(traces_service)).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/otlp/v1/traces")
+            .method("POST")
+            .header("content-type", "text/plain")
+            .body("foo")
+            .reply(&otlp_api_handler)
+            .await;
+        assert_eq!(resp.status(), 415);
+    }
 }
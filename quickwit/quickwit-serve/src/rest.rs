@@ -44,10 +44,14 @@ use crate::jaeger_api::jaeger_api_handlers;
 use crate::log_level_handler::log_level_handler;
 use crate::metrics_api::metrics_handler;
 use crate::node_info_handler::node_info_handler;
-use crate::otlp_api::otlp_ingest_api_handlers;
+use crate::otlp_api::{otlp_ingest_api_handlers, UnsupportedOtlpMediaType};
 use crate::rest_api_response::{RestApiError, RestApiResponse};
-use crate::search_api::{search_get_handler, search_post_handler, search_stream_handler};
+use crate::search_api::{
+    pipe_query_get_handler, pipe_query_post_handler, search_get_handler, search_post_handler,
+    search_stream_handler,
+};
 use crate::template_api::index_template_api_handlers;
+use crate::tempo_api::tempo_api_handlers;
 use crate::ui_handler::ui_handler;
 use crate::{BodyFormat, BuildInfo, QuickwitServices, RuntimeInfo};
 
@@ -185,6 +189,12 @@ fn api_v1_routes(
             .or(search_stream_handler(
                 quickwit_services.search_service.clone(),
             ))
+            .or(pipe_query_get_handler(
+                quickwit_services.search_service.clone(),
+            ))
+            .or(pipe_query_post_handler(
+                quickwit_services.search_service.clone(),
+            ))
             .or(ingest_api_handlers(
                 quickwit_services.ingest_router_service.clone(),
                 quickwit_services.ingest_service.clone(),
@@ -204,6 +214,7 @@ fn api_v1_routes(
             .or(jaeger_api_handlers(
                 quickwit_services.jaeger_service_opt.clone(),
             ))
+            .or(tempo_api_handlers(quickwit_services.search_service.clone()))
             .or(elastic_api_handlers(
                 quickwit_services.node_config.clone(),
                 quickwit_services.search_service.clone(),
@@ -273,6 +284,11 @@ fn get_status_with_error(rejection: Rejection) -> RestApiError {
             status_code: StatusCode::BAD_REQUEST,
             message: error.to_string(),
         }
+    } else if let Some(error) = rejection.find::<UnsupportedOtlpMediaType>() {
+        RestApiError {
+            status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            message: error.to_string(),
+        }
     } else if let Some(error) = rejection.find::<warp::reject::UnsupportedMediaType>() {
         RestApiError {
             status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
@@ -616,6 +632,7 @@ mod tests {
         let quickwit_services = QuickwitServices {
             _report_splits_subscription_handle_opt: None,
             _local_shards_update_listener_handle_opt: None,
+            _ingest_volume_update_listener_handle_opt: None,
             cluster,
             control_plane_service,
             indexing_service_opt: None,
@@ -23,8 +23,9 @@ mod rest_handler;
 pub use self::grpc_adapter::GrpcSearchAdapter;
 pub(crate) use self::rest_handler::{extract_index_id_patterns, extract_index_id_patterns_default};
 pub use self::rest_handler::{
-    search_get_handler, search_post_handler, search_request_from_api_request,
-    search_stream_handler, SearchApi, SearchRequestQueryString, SortBy,
+    pipe_query_get_handler, pipe_query_post_handler, search_get_handler, search_post_handler,
+    search_request_from_api_request, search_stream_handler, PipeQueryRequest, PipeQueryResponse,
+    SearchApi, SearchRequestQueryString, SortBy,
 };
 
 #[cfg(test)]
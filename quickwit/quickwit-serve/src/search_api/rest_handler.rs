@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::sync::Arc;
 
@@ -25,10 +26,14 @@ use hyper::header::HeaderValue;
 use hyper::HeaderMap;
 use percent_encoding::percent_decode_str;
 use quickwit_config::validate_index_id_pattern;
-use quickwit_proto::search::{CountHits, OutputFormat, SortField, SortOrder};
+use quickwit_proto::search::{
+    CountHits, OutputFormat, SearchPriority, SearchProfile, SortField, SortOrder, SplitPlan,
+    SplitSearchProfile,
+};
 use quickwit_proto::ServiceError;
 use quickwit_query::query_ast::query_ast_from_user_text;
-use quickwit_search::{SearchError, SearchResponseRest, SearchService};
+use quickwit_query::{parse_pipe_query, PipeQuery, PipeSortOrder};
+use quickwit_search::{RuntimeFieldMapping, SearchError, SearchResponseRest, SearchService};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value as JsonValue;
 use tracing::info;
@@ -42,15 +47,27 @@ use crate::{with_arg, BodyFormat};
 
 #[derive(utoipa::OpenApi)]
 #[openapi(
-    paths(search_get_handler, search_post_handler, search_stream_handler,),
+    paths(
+        search_get_handler,
+        search_post_handler,
+        search_stream_handler,
+        pipe_query_get_handler,
+        pipe_query_post_handler,
+    ),
     components(schemas(
         BodyFormat,
         OutputFormat,
+        PipeQueryRequest,
+        PipeQueryResponse,
+        SearchPriority,
+        SearchProfile,
         SearchRequestQueryString,
         SearchResponseRest,
         SortBy,
         SortField,
         SortOrder,
+        SplitPlan,
+        SplitSearchProfile,
     ),)
 )]
 pub struct SearchApi;
@@ -231,6 +248,33 @@ pub struct SearchRequestQueryString {
     #[serde(with = "count_hits_from_bool")]
     #[serde(default = "count_hits_from_bool::default")]
     pub count_all: CountHits,
+    /// Priority class of the search: `interactive` (default) or `batch`. When search quotas are
+    /// configured on the searchers, the number of concurrent batch searches can be capped.
+    #[param(value_type = String)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_interactive_priority")]
+    pub priority: SearchPriority,
+    /// If set to true, the response includes a profile of the search, with per-split timings,
+    /// bytes downloaded and cache hits and misses.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub profile: bool,
+    /// If set to true, the search is not executed. The response only includes the list of splits
+    /// that would have been searched and an estimate of the number of bytes to download.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// Fields computed at query time from the fields of the documents, e.g.
+    /// `{"duration_ms": {"type": "long", "script": "end - start"}}`.
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub runtime_mappings: BTreeMap<String, RuntimeFieldMapping>,
+}
+
+fn is_interactive_priority(priority: &SearchPriority) -> bool {
+    *priority == SearchPriority::Interactive
 }
 
 mod count_hits_from_bool {
@@ -285,6 +329,14 @@ pub fn search_request_from_api_request(
         scroll_ttl_secs: None,
         search_after: None,
         count_hits: search_request.count_all.into(),
+        priority: search_request.priority.into(),
+        profile: search_request.profile,
+        dry_run: search_request.dry_run,
+        runtime_mappings: search_request
+            .runtime_mappings
+            .into_iter()
+            .map(|(name, runtime_mapping)| runtime_mapping.into_runtime_mapping(name))
+            .collect(),
     };
     Ok(search_request)
 }
@@ -376,6 +428,189 @@ pub fn search_post_handler(
         .then(search)
 }
 
+/// This struct represents the pipe query passed to the REST API.
+#[derive(Debug, Default, Eq, PartialEq, Deserialize, utoipa::IntoParams, utoipa::ToSchema)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+pub struct PipeQueryRequest {
+    /// Pipe query, e.g. `service:api | where status >= 500 | stats count() by host`.
+    pub query: String,
+    /// If set, restrict search to documents with a `timestamp >= start_timestamp`.
+    /// This timestamp is expressed in seconds.
+    pub start_timestamp: Option<i64>,
+    /// If set, restrict search to documents with a `timestamp < end_timestamp``.
+    /// This timestamp is expressed in seconds.
+    pub end_timestamp: Option<i64>,
+    /// The output format.
+    #[serde(default)]
+    pub format: BodyFormat,
+}
+
+/// PipeQueryResponse represents the response returned by the REST pipe query API.
+#[derive(Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct PipeQueryResponse {
+    /// Overall number of documents matching the query.
+    pub num_hits: u64,
+    /// Result rows: the documents, or one row per group if the query computes stats.
+    #[schema(value_type = Vec<Object>)]
+    pub rows: Vec<JsonValue>,
+    /// Elapsed time.
+    pub elapsed_time_micros: u64,
+    /// Search errors.
+    pub errors: Vec<String>,
+    /// Warnings about incomplete rows, for instance when `stats` dropped some groups.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+fn search_request_from_pipe_query(
+    index_id_patterns: Vec<String>,
+    pipe_query: &PipeQuery,
+    pipe_query_request: &PipeQueryRequest,
+) -> Result<quickwit_proto::search::SearchRequest, SearchError> {
+    // The `index=` clauses of the query take precedence over the indexes of the path.
+    let index_id_patterns = if pipe_query.index_id_patterns.is_empty() {
+        index_id_patterns
+    } else {
+        for index_id_pattern in &pipe_query.index_id_patterns {
+            validate_index_id_pattern(index_id_pattern, true)
+                .map_err(|error| SearchError::InvalidArgument(error.to_string()))?;
+        }
+        pipe_query.index_id_patterns.clone()
+    };
+    let sort_fields = pipe_query
+        .sort_fields
+        .iter()
+        .map(|pipe_sort_field| {
+            let sort_order = match pipe_sort_field.sort_order {
+                PipeSortOrder::Asc => SortOrder::Asc,
+                PipeSortOrder::Desc => SortOrder::Desc,
+            };
+            SortField {
+                field_name: pipe_sort_field.field_name.clone(),
+                sort_order: sort_order as i32,
+                sort_datetime_format: None,
+            }
+        })
+        .collect();
+    let search_request = quickwit_proto::search::SearchRequest {
+        index_id_patterns,
+        query_ast: serde_json::to_string(&pipe_query.query_ast)?,
+        start_timestamp: pipe_query_request.start_timestamp,
+        end_timestamp: pipe_query_request.end_timestamp,
+        max_hits: pipe_query.max_hits(),
+        aggregation_request: pipe_query
+            .aggregation_request()
+            .map(|aggregation_request| aggregation_request.to_string()),
+        sort_fields,
+        count_hits: CountHits::CountAll as i32,
+        ..Default::default()
+    };
+    Ok(search_request)
+}
+
+async fn pipe_query_endpoint(
+    index_id_patterns: Vec<String>,
+    pipe_query_request: PipeQueryRequest,
+    search_service: &dyn SearchService,
+) -> Result<PipeQueryResponse, SearchError> {
+    let pipe_query = parse_pipe_query(&pipe_query_request.query)
+        .map_err(|error| SearchError::InvalidQuery(error.to_string()))?;
+    let search_request =
+        search_request_from_pipe_query(index_id_patterns, &pipe_query, &pipe_query_request)?;
+    let search_response = search_service.root_search(search_request).await?;
+    let search_response_rest = SearchResponseRest::try_from(search_response)?;
+    let pipe_query_rows = pipe_query
+        .rows(
+            search_response_rest.num_hits,
+            search_response_rest.hits,
+            search_response_rest.aggregations.as_ref(),
+        )
+        .map_err(|error| SearchError::Internal(error.to_string()))?;
+    let pipe_query_response = PipeQueryResponse {
+        num_hits: search_response_rest.num_hits,
+        rows: pipe_query_rows.rows,
+        elapsed_time_micros: search_response_rest.elapsed_time_micros,
+        errors: search_response_rest.errors,
+        warnings: pipe_query_rows.warnings,
+    };
+    Ok(pipe_query_response)
+}
+
+fn pipe_query_get_filter(
+) -> impl Filter<Extract = (Vec<String>, PipeQueryRequest), Error = Rejection> + Clone {
+    warp::path!(String / "query")
+        .and_then(extract_index_id_patterns)
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+}
+
+fn pipe_query_post_filter(
+) -> impl Filter<Extract = (Vec<String>, PipeQueryRequest), Error = Rejection> + Clone {
+    warp::path!(String / "query")
+        .and_then(extract_index_id_patterns)
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::body::json())
+}
+
+async fn pipe_query(
+    index_id_patterns: Vec<String>,
+    pipe_query_request: PipeQueryRequest,
+    search_service: Arc<dyn SearchService>,
+) -> impl warp::Reply {
+    info!(request =? pipe_query_request, "pipe-query");
+    let body_format = pipe_query_request.format;
+    let result = pipe_query_endpoint(index_id_patterns, pipe_query_request, &*search_service).await;
+    into_rest_api_response(result, body_format)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/{index_id}/query",
+    responses(
+        (status = 200, description = "Successfully executed pipe query.", body = PipeQueryResponse)
+    ),
+    params(
+        PipeQueryRequest,
+        ("index_id" = String, Path, description = "The index ID to search, unless the query has `index=` clauses."),
+    )
+)]
+/// Pipe Query (GET Variant)
+///
+/// Parses the pipe query from the request query string.
+pub fn pipe_query_get_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    pipe_query_get_filter()
+        .and(with_arg(search_service))
+        .then(pipe_query)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/{index_id}/query",
+    request_body = PipeQueryRequest,
+    responses(
+        (status = 200, description = "Successfully executed pipe query.", body = PipeQueryResponse)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to search, unless the query has `index=` clauses."),
+    )
+)]
+/// Pipe Query (POST Variant)
+///
+/// Parses the pipe query from the request body.
+pub fn pipe_query_post_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    pipe_query_post_filter()
+        .and(with_arg(search_service))
+        .then(pipe_query)
+}
+
 #[utoipa::path(
     get,
     tag = "Search",
@@ -524,6 +759,7 @@ mod tests {
     use assert_json_diff::{assert_json_eq, assert_json_include};
     use bytes::Bytes;
     use mockall::predicate;
+    use quickwit_proto::search::RuntimeMapping;
     use quickwit_search::{MockSearchService, SearchError};
     use serde_json::{json, Value as JsonValue};
 
@@ -536,7 +772,9 @@ mod tests {
         let mock_search_service_in_arc = Arc::new(mock_search_service);
         search_get_handler(mock_search_service_in_arc.clone())
             .or(search_post_handler(mock_search_service_in_arc.clone()))
-            .or(search_stream_handler(mock_search_service_in_arc))
+            .or(search_stream_handler(mock_search_service_in_arc.clone()))
+            .or(pipe_query_get_handler(mock_search_service_in_arc.clone()))
+            .or(pipe_query_post_handler(mock_search_service_in_arc))
             .recover(recover_fn)
     }
 
@@ -572,6 +810,7 @@ mod tests {
             elapsed_time_micros: 0u64,
             errors: Vec::new(),
             aggregations: None,
+            profile: None,
         };
         let search_response_json: JsonValue = serde_json::to_value(search_response)?;
         let expected_search_response_json: JsonValue = json!({
@@ -737,6 +976,34 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_rest_search_api_route_priority() {
+        let rest_search_api_filter = search_get_filter();
+        let (_indexes, req) = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&priority=batch")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(req.priority, SearchPriority::Batch);
+
+        let rest_search_api_filter = search_get_filter();
+        let (_indexes, req) = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(req.priority, SearchPriority::Interactive);
+
+        let rest_search_api_filter = search_get_filter();
+        let rejection = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&priority=urgent")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap_err();
+        let parse_error = rejection.find::<serde_qs::Error>().unwrap();
+        assert!(parse_error.to_string().contains("unknown variant `urgent`"));
+    }
+
     #[tokio::test]
     async fn test_rest_search_api_route_simple_default_num_hits_default_offset() {
         let rest_search_api_filter = search_get_filter();
@@ -1026,6 +1293,209 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_search_api_route_profile_and_dry_run() {
+        let rest_search_api_filter = search_get_filter();
+        let (_indexes, req) = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&profile=true")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert!(req.profile);
+        assert!(!req.dry_run);
+
+        let rest_search_api_filter = search_get_filter();
+        let (index_id_patterns, req) = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&dry_run=true")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert!(!req.profile);
+        assert!(req.dry_run);
+
+        let search_request = search_request_from_api_request(index_id_patterns, req).unwrap();
+        assert!(search_request.dry_run);
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_route_post_with_runtime_mappings() {
+        let rest_search_api_filter = search_post_filter();
+        let (index_id_patterns, req) = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&true)
+            .body(
+                r#"{
+                    "query": "*",
+                    "sort_by": "-duration_ms",
+                    "runtime_mappings": {
+                        "duration_ms": {"type": "long", "script": "end - start"},
+                        "status": {
+                            "type": "long",
+                            "script": {"source": "regex_extract(message, 'status=(\\d+)')"}
+                        }
+                    }
+                }"#,
+            )
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(req.runtime_mappings.len(), 2);
+
+        let search_request = search_request_from_api_request(index_id_patterns, req).unwrap();
+        assert_eq!(
+            search_request.runtime_mappings,
+            vec![
+                RuntimeMapping {
+                    name: "duration_ms".to_string(),
+                    field_type: "long".to_string(),
+                    script: "end - start".to_string(),
+                },
+                RuntimeMapping {
+                    name: "status".to_string(),
+                    field_type: "long".to_string(),
+                    script: r"regex_extract(message, 'status=(\d+)')".to_string(),
+                },
+            ]
+        );
+        assert_eq!(search_request.sort_fields[0].field_name, "duration_ms");
+    }
+
+    #[tokio::test]
+    async fn test_rest_pipe_query_api_with_stats() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                let aggregation_request: JsonValue =
+                    serde_json::from_str(search_request.aggregation_request.as_ref().unwrap())
+                        .unwrap();
+                search_request.index_id_patterns == vec!["logs".to_string()]
+                    && search_request.max_hits == 0
+                    && search_request.start_timestamp == Some(1_700_000_000)
+                    && aggregation_request
+                        == json!({"group_0": {"terms": {"field": "host", "size": 1000}}})
+            })
+            .returning(|_| {
+                Ok(quickwit_proto::search::SearchResponse {
+                    num_hits: 10,
+                    elapsed_time_micros: 16,
+                    aggregation: Some(
+                        json!({
+                            "group_0": {
+                                "buckets": [
+                                    {"key": "web-1", "doc_count": 2},
+                                    {"key": "web-2", "doc_count": 8}
+                                ]
+                            }
+                        })
+                        .to_string(),
+                    ),
+                    ..Default::default()
+                })
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/query")
+            .json(&json!({
+                "query": "index=logs service:api | where status >= 500 | stats count() by host \
+                          | sort -count | head 1",
+                "start_timestamp": 1_700_000_000,
+            }))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_json_eq!(
+            resp_json,
+            json!({
+                "num_hits": 10,
+                "rows": [{"host": "web-2", "count": 8}],
+                "elapsed_time_micros": 16,
+                "errors": [],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rest_pipe_query_api_with_hits() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                search_request.index_id_patterns == vec!["quickwit-demo-index".to_string()]
+                    && search_request.max_hits == 5
+                    && search_request.aggregation_request.is_none()
+                    && search_request.sort_fields
+                        == vec![SortField {
+                            field_name: "timestamp".to_string(),
+                            sort_order: SortOrder::Desc as i32,
+                            sort_datetime_format: None,
+                        }]
+            })
+            .returning(|_| {
+                Ok(quickwit_proto::search::SearchResponse {
+                    num_hits: 1,
+                    hits: vec![quickwit_proto::search::Hit {
+                        json: r#"{"host": "web-1", "status": 503}"#.to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path(
+                "/quickwit-demo-index/query?query=*%20%7C%20sort%20-timestamp%20%7C%20head%205%20%\
+                 7C%20fields%20host",
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_json_include!(
+            actual: resp_json,
+            expected: json!({"num_hits": 1, "rows": [{"host": "web-1"}]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rest_pipe_query_api_with_invalid_query() {
+        let mock_search_service = MockSearchService::new();
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/query?query=*%20%7C%20dedup%20host")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert!(resp_json["message"]
+            .as_str()
+            .unwrap()
+            .contains("unknown command `dedup`"));
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_with_too_many_requests() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_root_search().returning(|_| {
+            Err(SearchError::TooManyRequests(
+                "too many searches are queued for index `quickwit-demo-index`".to_string(),
+            ))
+        });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        assert_eq!(
+            warp::test::request()
+                .path("/quickwit-demo-index/search?query=*&priority=batch")
+                .reply(&rest_search_api_handler)
+                .await
+                .status(),
+            429
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rest_search_api_with_wrong_fieldname() -> anyhow::Result<()> {
         let mut mock_search_service = MockSearchService::new();
@@ -0,0 +1,581 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+
+use hyper::StatusCode;
+use quickwit_opentelemetry::otlp::{
+    Event as QwEvent, Link as QwLink, Span as QwSpan, SpanId, SpanStatus as QwSpanStatus, TraceId,
+};
+use quickwit_proto::opentelemetry::proto::common::v1::any_value::Value as OtlpValue;
+use quickwit_proto::opentelemetry::proto::common::v1::{
+    AnyValue as OtlpAnyValue, ArrayValue as OtlpArrayValue, InstrumentationScope,
+    KeyValue as OtlpKeyValue, KeyValueList as OtlpKeyValueList,
+};
+use quickwit_proto::opentelemetry::proto::resource::v1::Resource as OtlpResource;
+use quickwit_proto::opentelemetry::proto::trace::v1::span::{Event as OtlpEvent, Link as OtlpLink};
+use quickwit_proto::opentelemetry::proto::trace::v1::{
+    ResourceSpans, ScopeSpans, Span as OtlpSpan, Status as OtlpStatus,
+};
+use quickwit_proto::ServiceError;
+use quickwit_search::SearchError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+
+pub(super) const DEFAULT_NUMBER_OF_TRACES: usize = 20;
+
+/// Service name reported for the traces whose root span has not been indexed, following Tempo.
+pub(super) const ROOT_SPAN_NOT_RECEIVED: &str = "<root span not yet received>";
+
+#[derive(Debug, Default, Clone, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoSearchQueryParams {
+    /// TraceQL query.
+    pub q: Option<String>,
+    /// Logfmt-encoded tags, such as `service.name=api http.method=GET`.
+    pub tags: Option<String>,
+    pub min_duration: Option<String>,
+    pub max_duration: Option<String>,
+    pub limit: Option<usize>,
+    /// Start of the time range in seconds since the Unix epoch.
+    pub start: Option<i64>,
+    /// End of the time range in seconds since the Unix epoch.
+    pub end: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, utoipa::IntoParams)]
+pub struct TempoTimeRangeQueryParams {
+    /// Start of the time range in seconds since the Unix epoch.
+    pub start: Option<i64>,
+    /// End of the time range in seconds since the Unix epoch.
+    pub end: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+pub struct TempoSearchResponse {
+    pub traces: Vec<TempoTraceSearchMetadata>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoTraceSearchMetadata {
+    #[serde(rename = "traceID")]
+    pub trace_id: String,
+    pub root_service_name: String,
+    pub root_trace_name: String,
+    /// Tempo serializes 64-bit integers as strings.
+    pub start_time_unix_nano: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoTagNamesResponse {
+    pub tag_names: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoTagValuesResponse {
+    pub tag_values: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TempoError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl TempoError {
+    pub fn bad_request(error: anyhow::Error) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for TempoError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<SearchError> for TempoError {
+    fn from(search_error: SearchError) -> Self {
+        Self {
+            status: search_error.error_code().http_status_code(),
+            message: search_error.to_string(),
+        }
+    }
+}
+
+/// The `tempopb.Trace` message returned by Tempo's trace by ID endpoint when Protobuf is
+/// requested, which is how Grafana fetches traces.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TempoTrace {
+    #[prost(message, repeated, tag = "1")]
+    pub batches: Vec<ResourceSpans>,
+}
+
+impl TempoTrace {
+    /// Groups the spans of a trace by service and instrumentation scope.
+    pub fn from_spans(spans: Vec<QwSpan>) -> Self {
+        let mut spans_per_service: BTreeMap<String, Vec<QwSpan>> = BTreeMap::new();
+
+        for span in spans {
+            spans_per_service
+                .entry(span.service_name.clone())
+                .or_default()
+                .push(span);
+        }
+        let batches = spans_per_service
+            .into_values()
+            .map(build_resource_spans)
+            .collect();
+        TempoTrace { batches }
+    }
+
+    /// Serializes the trace following the OTLP/JSON encoding.
+    pub fn to_json(&self) -> JsonValue {
+        let batches: Vec<JsonValue> = self
+            .batches
+            .iter()
+            .map(|resource_spans| {
+                let resource = resource_spans
+                    .resource
+                    .as_ref()
+                    .map(|resource| {
+                        json!({
+                            "attributes": key_values_to_json(&resource.attributes),
+                            "droppedAttributesCount": resource.dropped_attributes_count,
+                        })
+                    })
+                    .unwrap_or_default();
+                let scope_spans: Vec<JsonValue> = resource_spans
+                    .scope_spans
+                    .iter()
+                    .map(scope_spans_to_json)
+                    .collect();
+                json!({
+                    "resource": resource,
+                    "scopeSpans": scope_spans,
+                })
+            })
+            .collect();
+        json!({ "batches": batches })
+    }
+}
+
+fn build_resource_spans(mut spans: Vec<QwSpan>) -> ResourceSpans {
+    let first_span = &mut spans[0];
+    let mut resource_attributes = std::mem::take(&mut first_span.resource_attributes);
+    resource_attributes.insert(
+        "service.name".to_string(),
+        JsonValue::String(first_span.service_name.clone()),
+    );
+    let resource = OtlpResource {
+        attributes: to_otlp_key_values(resource_attributes),
+        dropped_attributes_count: first_span.resource_dropped_attributes_count,
+    };
+    let mut spans_per_scope: BTreeMap<(String, String), (InstrumentationScope, Vec<OtlpSpan>)> =
+        BTreeMap::new();
+
+    for mut span in spans {
+        let scope_name = span.scope_name.take().unwrap_or_default();
+        let scope_version = span.scope_version.take().unwrap_or_default();
+        let scope_attributes = std::mem::take(&mut span.scope_attributes);
+        let scope_dropped_attributes_count = span.scope_dropped_attributes_count;
+
+        spans_per_scope
+            .entry((scope_name.clone(), scope_version.clone()))
+            .or_insert_with(|| {
+                let scope = InstrumentationScope {
+                    name: scope_name,
+                    version: scope_version,
+                    attributes: to_otlp_key_values(scope_attributes),
+                    dropped_attributes_count: scope_dropped_attributes_count,
+                };
+                (scope, Vec::new())
+            })
+            .1
+            .push(qw_span_to_otlp_span(span));
+    }
+    let scope_spans = spans_per_scope
+        .into_values()
+        .map(|(scope, spans)| ScopeSpans {
+            scope: Some(scope),
+            spans,
+            schema_url: String::new(),
+        })
+        .collect();
+    ResourceSpans {
+        resource: Some(resource),
+        scope_spans,
+        schema_url: String::new(),
+    }
+}
+
+fn qw_span_to_otlp_span(span: QwSpan) -> OtlpSpan {
+    let status = if span.span_status.is_unset() {
+        None
+    } else {
+        Some(qw_span_status_to_otlp_status(span.span_status))
+    };
+    OtlpSpan {
+        trace_id: span.trace_id.to_vec(),
+        span_id: span.span_id.as_bytes().to_vec(),
+        trace_state: span.trace_state.unwrap_or_default(),
+        parent_span_id: span
+            .parent_span_id
+            .as_ref()
+            .map(|span_id| span_id.as_bytes().to_vec())
+            .unwrap_or_default(),
+        name: span.span_name,
+        kind: span.span_kind as i32,
+        start_time_unix_nano: span.span_start_timestamp_nanos,
+        end_time_unix_nano: span.span_end_timestamp_nanos,
+        attributes: to_otlp_key_values(span.span_attributes),
+        dropped_attributes_count: span.span_dropped_attributes_count,
+        events: span
+            .events
+            .into_iter()
+            .map(qw_event_to_otlp_event)
+            .collect(),
+        dropped_events_count: span.span_dropped_events_count,
+        links: span.links.into_iter().map(qw_link_to_otlp_link).collect(),
+        dropped_links_count: span.span_dropped_links_count,
+        status,
+    }
+}
+
+fn qw_span_status_to_otlp_status(span_status: QwSpanStatus) -> OtlpStatus {
+    OtlpStatus {
+        message: span_status.message.unwrap_or_default(),
+        code: span_status.code as i32,
+    }
+}
+
+fn qw_event_to_otlp_event(event: QwEvent) -> OtlpEvent {
+    OtlpEvent {
+        time_unix_nano: event.event_timestamp_nanos,
+        name: event.event_name,
+        attributes: to_otlp_key_values(event.event_attributes),
+        dropped_attributes_count: event.event_dropped_attributes_count,
+    }
+}
+
+fn qw_link_to_otlp_link(link: QwLink) -> OtlpLink {
+    OtlpLink {
+        trace_id: link.link_trace_id.to_vec(),
+        span_id: link.link_span_id.as_bytes().to_vec(),
+        trace_state: link.link_trace_state.unwrap_or_default(),
+        attributes: to_otlp_key_values(link.link_attributes),
+        dropped_attributes_count: link.link_dropped_attributes_count,
+    }
+}
+
+fn to_otlp_key_values(attributes: HashMap<String, JsonValue>) -> Vec<OtlpKeyValue> {
+    let mut key_values: Vec<OtlpKeyValue> = attributes
+        .into_iter()
+        .map(|(key, value)| OtlpKeyValue {
+            key,
+            value: Some(to_otlp_any_value(value)),
+        })
+        .collect();
+    key_values.sort_unstable_by(|left, right| left.key.cmp(&right.key));
+    key_values
+}
+
+fn to_otlp_any_value(value: JsonValue) -> OtlpAnyValue {
+    let value_opt = match value {
+        JsonValue::Null => None,
+        JsonValue::Bool(value) => Some(OtlpValue::BoolValue(value)),
+        JsonValue::Number(number) => {
+            if let Some(value) = number.as_i64() {
+                Some(OtlpValue::IntValue(value))
+            } else {
+                number.as_f64().map(OtlpValue::DoubleValue)
+            }
+        }
+        JsonValue::String(value) => Some(OtlpValue::StringValue(value)),
+        JsonValue::Array(values) => Some(OtlpValue::ArrayValue(OtlpArrayValue {
+            values: values.into_iter().map(to_otlp_any_value).collect(),
+        })),
+        JsonValue::Object(object) => Some(OtlpValue::KvlistValue(OtlpKeyValueList {
+            values: to_otlp_key_values(object.into_iter().collect()),
+        })),
+    };
+    OtlpAnyValue { value: value_opt }
+}
+
+fn scope_spans_to_json(scope_spans: &ScopeSpans) -> JsonValue {
+    let scope = scope_spans
+        .scope
+        .as_ref()
+        .map(|scope| {
+            json!({
+                "name": scope.name,
+                "version": scope.version,
+                "attributes": key_values_to_json(&scope.attributes),
+                "droppedAttributesCount": scope.dropped_attributes_count,
+            })
+        })
+        .unwrap_or_default();
+    let spans: Vec<JsonValue> = scope_spans.spans.iter().map(span_to_json).collect();
+    json!({
+        "scope": scope,
+        "spans": spans,
+    })
+}
+
+fn span_to_json(span: &OtlpSpan) -> JsonValue {
+    let events: Vec<JsonValue> = span
+        .events
+        .iter()
+        .map(|event| {
+            json!({
+                "timeUnixNano": event.time_unix_nano.to_string(),
+                "name": event.name,
+                "attributes": key_values_to_json(&event.attributes),
+                "droppedAttributesCount": event.dropped_attributes_count,
+            })
+        })
+        .collect();
+    let links: Vec<JsonValue> = span
+        .links
+        .iter()
+        .map(|link| {
+            json!({
+                "traceId": hex::encode(&link.trace_id),
+                "spanId": hex::encode(&link.span_id),
+                "traceState": link.trace_state,
+                "attributes": key_values_to_json(&link.attributes),
+                "droppedAttributesCount": link.dropped_attributes_count,
+            })
+        })
+        .collect();
+    let status = span
+        .status
+        .as_ref()
+        .map(|status| json!({ "code": status.code, "message": status.message }))
+        .unwrap_or_else(|| json!({}));
+    json!({
+        "traceId": hex::encode(&span.trace_id),
+        "spanId": hex::encode(&span.span_id),
+        "traceState": span.trace_state,
+        "parentSpanId": hex::encode(&span.parent_span_id),
+        "name": span.name,
+        "kind": span.kind,
+        "startTimeUnixNano": span.start_time_unix_nano.to_string(),
+        "endTimeUnixNano": span.end_time_unix_nano.to_string(),
+        "attributes": key_values_to_json(&span.attributes),
+        "droppedAttributesCount": span.dropped_attributes_count,
+        "events": events,
+        "droppedEventsCount": span.dropped_events_count,
+        "links": links,
+        "droppedLinksCount": span.dropped_links_count,
+        "status": status,
+    })
+}
+
+fn key_values_to_json(key_values: &[OtlpKeyValue]) -> JsonValue {
+    key_values
+        .iter()
+        .map(|key_value| {
+            let value = key_value
+                .value
+                .as_ref()
+                .map(any_value_to_json)
+                .unwrap_or_else(|| json!({}));
+            json!({ "key": key_value.key, "value": value })
+        })
+        .collect()
+}
+
+fn any_value_to_json(any_value: &OtlpAnyValue) -> JsonValue {
+    match &any_value.value {
+        None => json!({}),
+        Some(OtlpValue::StringValue(value)) => json!({ "stringValue": value }),
+        Some(OtlpValue::BoolValue(value)) => json!({ "boolValue": value }),
+        Some(OtlpValue::IntValue(value)) => json!({ "intValue": value.to_string() }),
+        Some(OtlpValue::DoubleValue(value)) => json!({ "doubleValue": value }),
+        Some(OtlpValue::ArrayValue(array)) => {
+            let values: Vec<JsonValue> = array.values.iter().map(any_value_to_json).collect();
+            json!({ "arrayValue": { "values": values } })
+        }
+        Some(OtlpValue::KvlistValue(kv_list)) => {
+            json!({ "kvlistValue": { "values": key_values_to_json(&kv_list.values) } })
+        }
+        Some(OtlpValue::BytesValue(value)) => json!({ "bytesValue": hex::encode(value) }),
+    }
+}
+
+/// The subset of the span fields required to summarize a trace in search results.
+#[derive(Debug, Deserialize)]
+pub(super) struct SearchSpan {
+    pub trace_id: TraceId,
+    #[serde(default)]
+    pub parent_span_id: Option<SpanId>,
+    pub service_name: String,
+    pub span_name: String,
+    pub span_start_timestamp_nanos: u64,
+    pub span_end_timestamp_nanos: u64,
+}
+
+/// Summarizes the spans of a trace, following Tempo's conventions.
+pub(super) fn build_trace_search_metadata(spans: &[SearchSpan]) -> TempoTraceSearchMetadata {
+    let trace_id = spans[0].trace_id.hex_display();
+    let start_time_unix_nano = spans
+        .iter()
+        .map(|span| span.span_start_timestamp_nanos)
+        .min()
+        .unwrap_or_default();
+    let end_time_unix_nano = spans
+        .iter()
+        .map(|span| span.span_end_timestamp_nanos)
+        .max()
+        .unwrap_or_default();
+    let (root_service_name, root_trace_name) = spans
+        .iter()
+        .find(|span| span.parent_span_id.is_none())
+        .map(|root_span| (root_span.service_name.clone(), root_span.span_name.clone()))
+        .unwrap_or_else(|| (ROOT_SPAN_NOT_RECEIVED.to_string(), String::new()));
+    TempoTraceSearchMetadata {
+        trace_id,
+        root_service_name,
+        root_trace_name,
+        start_time_unix_nano: start_time_unix_nano.to_string(),
+        duration_ms: end_time_unix_nano.saturating_sub(start_time_unix_nano) / 1_000_000,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+
+    fn qw_span_for_test(span_id: u8, parent_span_id_opt: Option<u8>, service_name: &str) -> QwSpan {
+        let span_json = json!({
+            "trace_id": "01010101010101010101010101010101",
+            "service_name": service_name,
+            "resource_attributes": {"host.name": "localhost"},
+            "scope_name": "tracer",
+            "span_id": format!("{span_id:016x}"),
+            "parent_span_id": parent_span_id_opt.map(|span_id| format!("{span_id:016x}")),
+            "span_kind": 2,
+            "span_name": format!("span-{span_id}"),
+            "span_start_timestamp_nanos": 1_000_000_000,
+            "span_end_timestamp_nanos": 2_000_000_000,
+            "span_attributes": {"http.status_code": 500, "http.method": "GET"},
+            "span_status": {"code": "error", "message": "boom"},
+        });
+        serde_json::from_value(span_json).unwrap()
+    }
+
+    #[test]
+    fn test_tempo_trace_from_spans() {
+        let spans = vec![
+            qw_span_for_test(1, None, "frontend"),
+            qw_span_for_test(2, Some(1), "backend"),
+            qw_span_for_test(3, Some(2), "backend"),
+        ];
+        let trace = TempoTrace::from_spans(spans);
+        assert_eq!(trace.batches.len(), 2);
+
+        let backend_batch = &trace.batches[0];
+        let resource_attributes = &backend_batch.resource.as_ref().unwrap().attributes;
+        assert_eq!(resource_attributes.len(), 2);
+        assert_eq!(resource_attributes[1].key, "service.name");
+        assert_eq!(
+            resource_attributes[1].value.as_ref().unwrap().value,
+            Some(OtlpValue::StringValue("backend".to_string()))
+        );
+        assert_eq!(backend_batch.scope_spans.len(), 1);
+        assert_eq!(
+            backend_batch.scope_spans[0].scope.as_ref().unwrap().name,
+            "tracer"
+        );
+
+        let span = &backend_batch.scope_spans[0].spans[0];
+        assert_eq!(span.trace_id, vec![1; 16]);
+        assert_eq!(span.span_id, 2u64.to_be_bytes());
+        assert_eq!(span.parent_span_id, 1u64.to_be_bytes());
+        assert_eq!(span.name, "span-2");
+        assert_eq!(span.kind, 2);
+        assert_eq!(span.attributes[1].key, "http.status_code");
+        assert_eq!(
+            span.attributes[1].value.as_ref().unwrap().value,
+            Some(OtlpValue::IntValue(500))
+        );
+        assert_eq!(span.status.as_ref().unwrap().code, 2);
+
+        let decoded_trace = TempoTrace::decode(trace.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded_trace, trace);
+
+        let trace_json = trace.to_json();
+        let span_json = &trace_json["batches"][0]["scopeSpans"][0]["spans"][0];
+        assert_eq!(span_json["traceId"], "01010101010101010101010101010101");
+        assert_eq!(span_json["parentSpanId"], "0000000000000001");
+        assert_eq!(span_json["startTimeUnixNano"], "1000000000");
+        assert_eq!(
+            span_json["attributes"][1],
+            json!({"key": "http.status_code", "value": {"intValue": "500"}})
+        );
+    }
+
+    #[test]
+    fn test_build_trace_search_metadata() {
+        let trace_id = TraceId::new([1; 16]);
+        let spans = vec![
+            SearchSpan {
+                trace_id,
+                parent_span_id: Some(SpanId::new([1; 8])),
+                service_name: "backend".to_string(),
+                span_name: "query".to_string(),
+                span_start_timestamp_nanos: 2_000_000,
+                span_end_timestamp_nanos: 5_000_000,
+            },
+            SearchSpan {
+                trace_id,
+                parent_span_id: None,
+                service_name: "frontend".to_string(),
+                span_name: "GET /".to_string(),
+                span_start_timestamp_nanos: 1_000_000,
+                span_end_timestamp_nanos: 4_000_000,
+            },
+        ];
+        let metadata = build_trace_search_metadata(&spans);
+        assert_eq!(metadata.trace_id, "01010101010101010101010101010101");
+        assert_eq!(metadata.root_service_name, "frontend");
+        assert_eq!(metadata.root_trace_name, "GET /");
+        assert_eq!(metadata.start_time_unix_nano, "1000000");
+        assert_eq!(metadata.duration_ms, 4);
+
+        let metadata = build_trace_search_metadata(&spans[..1]);
+        assert_eq!(metadata.root_service_name, ROOT_SPAN_NOT_RECEIVED);
+        assert_eq!(metadata.start_time_unix_nano, "2000000");
+        assert_eq!(metadata.duration_ms, 3);
+    }
+}
@@ -0,0 +1,788 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use anyhow::Context;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::StatusCode;
+use prost::Message;
+use quickwit_opentelemetry::otlp::{Span as QwSpan, TraceId};
+use quickwit_proto::search::{CountHits, ListTermsRequest, SearchRequest};
+use quickwit_query::query_ast::{BoolQuery, QueryAst, TermQuery};
+use quickwit_search::{FindTraceIdsCollector, SearchService};
+use tantivy::collector::Collector;
+use tantivy::schema::Type;
+use tantivy::Term;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+use super::model::{
+    build_trace_search_metadata, SearchSpan, TempoError, TempoSearchQueryParams,
+    TempoSearchResponse, TempoTagNamesResponse, TempoTagValuesResponse, TempoTimeRangeQueryParams,
+    TempoTrace, DEFAULT_NUMBER_OF_TRACES,
+};
+use super::traceql::{parse_max_duration, parse_min_duration, parse_tags, parse_traceql};
+use crate::rest_api_response::RestApiResponse;
+use crate::search_api::extract_index_id_patterns;
+use crate::{with_arg, BodyFormat};
+
+/// The maximum number of spans fetched to build a trace or the search results.
+const MAX_FETCH_SPANS: u64 = 10_000;
+
+/// The maximum number of terms fetched per list terms request when listing tag names and values.
+const MAX_LIST_TERMS_HITS: u64 = 1_000;
+
+/// The maximum number of list terms requests issued to list the tag names of a JSON field.
+const MAX_LIST_TERMS_REQUESTS: usize = 10;
+
+/// The JSON fields of the OpenTelemetry traces index holding the span and resource attributes.
+const ATTRIBUTES_FIELD_NAMES: [&str; 2] = ["span_attributes", "resource_attributes"];
+
+// Tantivy encodes JSON terms as `<path segments separated by 1u8><0u8><type code><value>`.
+const JSON_PATH_SEGMENT_SEP: u8 = 1;
+const JSON_END_OF_PATH: u8 = 0;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(
+    tempo_trace_handler,
+    tempo_search_handler,
+    tempo_search_tags_handler,
+    tempo_search_tag_values_handler
+))]
+pub(crate) struct TempoApi;
+
+/// Setup Tempo API handlers
+///
+/// This is where all Tempo handlers
+/// should be registered.
+/// Requests are executed on the OpenTelemetry traces indexes.
+pub(crate) fn tempo_api_handlers(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    tempo_trace_handler(search_service.clone())
+        .or(tempo_search_handler(search_service.clone()))
+        .or(tempo_search_tags_handler(search_service.clone()))
+        .or(tempo_search_tag_values_handler(search_service))
+}
+
+fn tempo_api_path_filter() -> impl Filter<Extract = (Vec<String>,), Error = Rejection> + Clone {
+    warp::path!(String / "tempo" / "api" / ..)
+        .and(warp::get())
+        .and_then(extract_index_id_patterns)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Tempo",
+    path = "/{otel-traces-index-id}/tempo/api/traces/{id}",
+    responses(
+        (status = 200, description = "Successfully fetched the spans of the trace, encoded as `tempopb.Trace` in Protobuf or OTLP/JSON depending on the `Accept` header."),
+        (status = 404, description = "The trace was not found."),
+    ),
+    params(
+        ("otel-traces-index-id" = String, Path, description = "The name of the index to get the trace from."),
+        ("id" = String, Path, description = "The hex-encoded ID of the trace."),
+        TempoTimeRangeQueryParams,
+    )
+)]
+pub fn tempo_trace_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    tempo_api_path_filter()
+        .and(warp::path!("traces" / String))
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(warp::header::optional::<String>("accept"))
+        .and(with_arg(search_service))
+        .then(tempo_trace_reply)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Tempo",
+    path = "/{otel-traces-index-id}/tempo/api/search",
+    responses(
+        (status = 200, description = "Successfully searched traces.", body = TempoSearchResponse)
+    ),
+    params(
+        ("otel-traces-index-id" = String, Path, description = "The name of the index to search traces in."),
+        TempoSearchQueryParams,
+    )
+)]
+pub fn tempo_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    tempo_api_path_filter()
+        .and(warp::path!("search"))
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(search_service))
+        .then(tempo_search)
+        .map(make_tempo_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Tempo",
+    path = "/{otel-traces-index-id}/tempo/api/search/tags",
+    responses(
+        (status = 200, description = "Successfully fetched tag names.", body = TempoTagNamesResponse)
+    ),
+    params(
+        ("otel-traces-index-id" = String, Path, description = "The name of the index to get tag names for."),
+        TempoTimeRangeQueryParams,
+    )
+)]
+pub fn tempo_search_tags_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    tempo_api_path_filter()
+        .and(warp::path!("search" / "tags"))
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(search_service))
+        .then(tempo_search_tags)
+        .map(make_tempo_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Tempo",
+    path = "/{otel-traces-index-id}/tempo/api/search/tag/{tag}/values",
+    responses(
+        (status = 200, description = "Successfully fetched tag values.", body = TempoTagValuesResponse)
+    ),
+    params(
+        ("otel-traces-index-id" = String, Path, description = "The name of the index to get tag values for."),
+        ("tag" = String, Path, description = "The name of the tag, such as `service.name` or `http.method`."),
+        TempoTimeRangeQueryParams,
+    )
+)]
+pub fn tempo_search_tag_values_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    tempo_api_path_filter()
+        .and(warp::path!("search" / "tag" / String / "values"))
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(search_service))
+        .then(tempo_search_tag_values)
+        .map(make_tempo_api_response)
+}
+
+fn parse_trace_id(trace_id_hex: &str) -> Result<TraceId, TempoError> {
+    // Tempo accepts trace IDs stripped of their leading zeros.
+    let padded_trace_id_hex = format!("{trace_id_hex:0>32}");
+    let trace_id_bytes = hex::decode(&padded_trace_id_hex)
+        .context("failed to decode hex-encoded trace ID")
+        .and_then(|trace_id_bytes| {
+            TraceId::try_from(trace_id_bytes).context("failed to parse trace ID")
+        })
+        .map_err(TempoError::bad_request)?;
+    Ok(trace_id_bytes)
+}
+
+async fn tempo_trace_reply(
+    index_id_patterns: Vec<String>,
+    trace_id_hex: String,
+    search_params: TempoTimeRangeQueryParams,
+    accept_opt: Option<String>,
+    search_service: Arc<dyn SearchService>,
+) -> Response {
+    let trace_result = tempo_trace(
+        index_id_patterns,
+        trace_id_hex,
+        search_params,
+        search_service,
+    )
+    .await;
+    make_tempo_trace_response(trace_result, accept_opt)
+}
+
+async fn tempo_trace(
+    index_id_patterns: Vec<String>,
+    trace_id_hex: String,
+    search_params: TempoTimeRangeQueryParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<TempoTrace, TempoError> {
+    let trace_id = parse_trace_id(&trace_id_hex)?;
+    let query_ast: QueryAst = TermQuery {
+        field: "trace_id".to_string(),
+        value: trace_id.hex_display(),
+    }
+    .into();
+    let search_request = SearchRequest {
+        index_id_patterns,
+        query_ast: serde_json::to_string(&query_ast).expect("query AST should be serializable"),
+        max_hits: MAX_FETCH_SPANS,
+        start_timestamp: search_params.start,
+        end_timestamp: search_params.end,
+        count_hits: CountHits::Underestimate.into(),
+        ..Default::default()
+    };
+    let search_response = search_service.root_search(search_request).await?;
+
+    if search_response.hits.is_empty() {
+        return Err(TempoError {
+            status: StatusCode::NOT_FOUND,
+            message: format!("trace `{trace_id_hex}` not found"),
+        });
+    }
+    let spans: Vec<QwSpan> = search_response
+        .hits
+        .into_iter()
+        .map(|hit| serde_json::from_str(&hit.json))
+        .collect::<Result<_, _>>()
+        .context("failed to deserialize spans")?;
+    Ok(TempoTrace::from_spans(spans))
+}
+
+async fn tempo_search(
+    index_id_patterns: Vec<String>,
+    search_params: TempoSearchQueryParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<TempoSearchResponse, TempoError> {
+    let mut bool_query = BoolQuery::default();
+
+    if let Some(traceql) = search_params.q.as_deref() {
+        if !traceql.trim().is_empty() {
+            let traceql_query = parse_traceql(traceql).map_err(TempoError::bad_request)?;
+            bool_query.must.push(traceql_query);
+        }
+    }
+    if let Some(tags) = search_params.tags.as_deref() {
+        let tags_query = parse_tags(tags).map_err(TempoError::bad_request)?;
+        bool_query.must.push(tags_query);
+    }
+    if let Some(min_duration) = search_params.min_duration.as_deref() {
+        let min_duration_query =
+            parse_min_duration(min_duration).map_err(TempoError::bad_request)?;
+        bool_query.must.push(min_duration_query);
+    }
+    if let Some(max_duration) = search_params.max_duration.as_deref() {
+        let max_duration_query =
+            parse_max_duration(max_duration).map_err(TempoError::bad_request)?;
+        bool_query.must.push(max_duration_query);
+    }
+    let query_ast: QueryAst = if bool_query.must.is_empty() {
+        QueryAst::MatchAll
+    } else {
+        bool_query.into()
+    };
+    let num_traces = search_params.limit.unwrap_or(DEFAULT_NUMBER_OF_TRACES);
+    let aggregation_request = serde_json::to_string(&FindTraceIdsCollector {
+        num_traces,
+        trace_id_field_name: "trace_id".to_string(),
+        span_timestamp_field_name: "span_start_timestamp_nanos".to_string(),
+    })
+    .expect("the collector should be serializable");
+    let search_request = SearchRequest {
+        index_id_patterns: index_id_patterns.clone(),
+        query_ast: serde_json::to_string(&query_ast).expect("query AST should be serializable"),
+        aggregation_request: Some(aggregation_request),
+        max_hits: 0,
+        start_timestamp: search_params.start,
+        end_timestamp: search_params.end,
+        count_hits: CountHits::Underestimate.into(),
+        ..Default::default()
+    };
+    let search_response = search_service.root_search(search_request).await?;
+
+    let Some(aggregation_json) = search_response.aggregation else {
+        return Ok(TempoSearchResponse::default());
+    };
+    let trace_ids: <FindTraceIdsCollector as Collector>::Fruit =
+        serde_json::from_str(&aggregation_json)
+            .context("failed to deserialize trace IDs aggregation")?;
+    if trace_ids.is_empty() {
+        return Ok(TempoSearchResponse::default());
+    }
+    // Fetch the spans of the matching traces to identify their root span and duration.
+    let mut spans_query = BoolQuery::default();
+
+    for trace_id in &trace_ids {
+        spans_query.should.push(
+            TermQuery {
+                field: "trace_id".to_string(),
+                value: trace_id.trace_id.hex_display(),
+            }
+            .into(),
+        );
+    }
+    let spans_query_ast: QueryAst = spans_query.into();
+    let spans_request = SearchRequest {
+        index_id_patterns,
+        query_ast: serde_json::to_string(&spans_query_ast)
+            .expect("query AST should be serializable"),
+        max_hits: MAX_FETCH_SPANS,
+        start_timestamp: search_params.start,
+        end_timestamp: search_params.end,
+        count_hits: CountHits::Underestimate.into(),
+        ..Default::default()
+    };
+    let spans_response = search_service.root_search(spans_request).await?;
+
+    let mut spans_per_trace: HashMap<TraceId, Vec<SearchSpan>> = HashMap::new();
+
+    for hit in spans_response.hits {
+        let span: SearchSpan =
+            serde_json::from_str(&hit.json).context("failed to deserialize span")?;
+        spans_per_trace.entry(span.trace_id).or_default().push(span);
+    }
+    // Preserve the order of the aggregation, most recent traces first.
+    let traces = trace_ids
+        .iter()
+        .filter_map(|trace_id| spans_per_trace.get(&trace_id.trace_id))
+        .map(|spans| build_trace_search_metadata(spans))
+        .collect();
+    Ok(TempoSearchResponse { traces })
+}
+
+async fn tempo_search_tags(
+    index_id_patterns: Vec<String>,
+    search_params: TempoTimeRangeQueryParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<TempoTagNamesResponse, TempoError> {
+    let mut tag_names = BTreeSet::from(["service.name".to_string()]);
+
+    for field_name in ATTRIBUTES_FIELD_NAMES {
+        let mut start_key: Option<Vec<u8>> = None;
+
+        for _ in 0..MAX_LIST_TERMS_REQUESTS {
+            let list_terms_request = ListTermsRequest {
+                index_id_patterns: index_id_patterns.clone(),
+                field: field_name.to_string(),
+                max_hits: Some(MAX_LIST_TERMS_HITS),
+                start_timestamp: search_params.start,
+                end_timestamp: search_params.end,
+                start_key: start_key.take(),
+                end_key: None,
+            };
+            let list_terms_response = search_service.root_list_terms(list_terms_request).await?;
+            let num_terms = list_terms_response.terms.len();
+            let mut last_json_path_opt: Option<&[u8]> = None;
+
+            for term_bytes in &list_terms_response.terms {
+                let Some((json_path, _)) = split_json_term(term_bytes) else {
+                    continue;
+                };
+                tag_names.insert(json_path_to_tag_name(json_path));
+                last_json_path_opt = Some(json_path);
+            }
+            if (num_terms as u64) < MAX_LIST_TERMS_HITS {
+                break;
+            }
+            let Some(last_json_path) = last_json_path_opt else {
+                break;
+            };
+            // Skip the remaining values of the last path.
+            let mut next_start_key = last_json_path.to_vec();
+            next_start_key.push(JSON_END_OF_PATH + 1);
+            start_key = Some(next_start_key);
+        }
+    }
+    Ok(TempoTagNamesResponse {
+        tag_names: tag_names.into_iter().collect(),
+    })
+}
+
+async fn tempo_search_tag_values(
+    index_id_patterns: Vec<String>,
+    tag: String,
+    search_params: TempoTimeRangeQueryParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<TempoTagValuesResponse, TempoError> {
+    let mut tag_values = BTreeSet::new();
+
+    let intrinsic_field_name_opt = match tag.as_str() {
+        "service.name" => Some("service_name"),
+        "name" => Some("span_name"),
+        _ => None,
+    };
+    if let Some(field_name) = intrinsic_field_name_opt {
+        let list_terms_request = ListTermsRequest {
+            index_id_patterns,
+            field: field_name.to_string(),
+            max_hits: Some(MAX_LIST_TERMS_HITS),
+            start_timestamp: search_params.start,
+            end_timestamp: search_params.end,
+            start_key: None,
+            end_key: None,
+        };
+        let list_terms_response = search_service.root_list_terms(list_terms_request).await?;
+
+        for term_bytes in list_terms_response.terms {
+            if let Some(tag_value) = Term::wrap(&term_bytes).value().as_str() {
+                tag_values.insert(tag_value.to_string());
+            }
+        }
+        return Ok(TempoTagValuesResponse {
+            tag_values: tag_values.into_iter().collect(),
+        });
+    }
+    let json_path = tag_name_to_json_path(&tag);
+
+    for field_name in ATTRIBUTES_FIELD_NAMES {
+        let mut start_key = json_path.clone();
+        start_key.push(JSON_END_OF_PATH);
+        let mut end_key = json_path.clone();
+        end_key.push(JSON_END_OF_PATH + 1);
+
+        let list_terms_request = ListTermsRequest {
+            index_id_patterns: index_id_patterns.clone(),
+            field: field_name.to_string(),
+            max_hits: Some(MAX_LIST_TERMS_HITS),
+            start_timestamp: search_params.start,
+            end_timestamp: search_params.end,
+            start_key: Some(start_key),
+            end_key: Some(end_key),
+        };
+        let list_terms_response = search_service.root_list_terms(list_terms_request).await?;
+
+        for term_bytes in &list_terms_response.terms {
+            if let Some(tag_value) =
+                split_json_term(term_bytes).and_then(|(_, value)| decode_json_term_value(value))
+            {
+                tag_values.insert(tag_value);
+            }
+        }
+    }
+    Ok(TempoTagValuesResponse {
+        tag_values: tag_values.into_iter().collect(),
+    })
+}
+
+/// Splits a serialized JSON term into its path and its encoded value.
+fn split_json_term(term_bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let value_bytes_len = Term::wrap(term_bytes).serialized_value_bytes().len();
+    let value_bytes = &term_bytes[term_bytes.len() - value_bytes_len..];
+    let end_of_path_pos = value_bytes
+        .iter()
+        .position(|byte| *byte == JSON_END_OF_PATH)?;
+    Some((
+        &value_bytes[..end_of_path_pos],
+        &value_bytes[end_of_path_pos + 1..],
+    ))
+}
+
+fn json_path_to_tag_name(json_path: &[u8]) -> String {
+    String::from_utf8_lossy(json_path).replace(JSON_PATH_SEGMENT_SEP as char, ".")
+}
+
+fn tag_name_to_json_path(tag_name: &str) -> Vec<u8> {
+    tag_name
+        .replace('.', &(JSON_PATH_SEGMENT_SEP as char).to_string())
+        .into_bytes()
+}
+
+fn decode_json_term_value(value: &[u8]) -> Option<String> {
+    let (type_code, value_bytes) = value.split_first()?;
+    let value_u64_opt = value_bytes.try_into().ok().map(u64::from_be_bytes);
+
+    match Type::from_code(*type_code)? {
+        Type::Str => std::str::from_utf8(value_bytes).ok().map(str::to_string),
+        Type::U64 => value_u64_opt.map(|value| value.to_string()),
+        Type::I64 => value_u64_opt.map(|value| tantivy::u64_to_i64(value).to_string()),
+        Type::F64 => value_u64_opt.map(|value| tantivy::u64_to_f64(value).to_string()),
+        Type::Bool => value_u64_opt.map(|value| (value != 0).to_string()),
+        _ => None,
+    }
+}
+
+fn make_tempo_api_response<T: serde::Serialize>(
+    tempo_result: Result<T, TempoError>,
+) -> RestApiResponse {
+    let status_code = match &tempo_result {
+        Ok(_) => StatusCode::OK,
+        Err(error) => error.status,
+    };
+    RestApiResponse::new(&tempo_result, status_code, BodyFormat::default())
+}
+
+/// Grafana requests traces encoded in Protobuf, other clients get OTLP/JSON.
+fn make_tempo_trace_response(
+    trace_result: Result<TempoTrace, TempoError>,
+    accept_opt: Option<String>,
+) -> Response {
+    let accepts_protobuf = accept_opt
+        .as_deref()
+        .is_some_and(|accept| accept.contains("application/protobuf"));
+    match trace_result {
+        Ok(trace) if accepts_protobuf => {
+            let mut response = Response::new(trace.encode_to_vec().into());
+            response.headers_mut().insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/protobuf"),
+            );
+            response
+        }
+        Ok(trace) => make_tempo_api_response(Ok(trace.to_json())).into_response(),
+        Err(error) => make_tempo_api_response::<()>(Err(error)).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use quickwit_opentelemetry::otlp::OTEL_TRACES_INDEX_ID;
+    use quickwit_proto::search::{Hit, ListTermsResponse, SearchResponse};
+    use quickwit_search::MockSearchService;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::recover_fn;
+
+    const SPAN_JSON: &str = r#"{
+        "trace_id": "0000000000000000000000000000abcd",
+        "service_name": "api",
+        "span_id": "0000000000000001",
+        "span_kind": 2,
+        "span_name": "GET /",
+        "span_start_timestamp_nanos": 1000000000,
+        "span_end_timestamp_nanos": 1250000000
+    }"#;
+
+    fn json_term_for_test(json_path: &str, value: &str) -> Vec<u8> {
+        // Serialized terms are laid out as `<field id: u32 BE><type code><value bytes>`.
+        let mut term_bytes = 0u32.to_be_bytes().to_vec();
+        term_bytes.push(Type::Json.to_code());
+        term_bytes.extend_from_slice(&tag_name_to_json_path(json_path));
+        term_bytes.push(JSON_END_OF_PATH);
+        term_bytes.push(Type::Str.to_code());
+        term_bytes.extend_from_slice(value.as_bytes());
+        term_bytes
+    }
+
+    #[test]
+    fn test_split_json_term() {
+        let term_bytes = json_term_for_test("http.method", "GET");
+        let (json_path, value) = split_json_term(&term_bytes).unwrap();
+        assert_eq!(json_path_to_tag_name(json_path), "http.method");
+        assert_eq!(decode_json_term_value(value).unwrap(), "GET");
+    }
+
+    #[test]
+    fn test_parse_trace_id() {
+        assert_eq!(
+            parse_trace_id("abcd").unwrap().hex_display(),
+            "0000000000000000000000000000abcd"
+        );
+        parse_trace_id("xyz").unwrap_err();
+        parse_trace_id(&"a".repeat(34)).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_tempo_trace() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|req| {
+                req.index_id_patterns == vec![OTEL_TRACES_INDEX_ID.to_string()]
+                    && req.query_ast.contains("0000000000000000000000000000abcd")
+                    && req.start_timestamp == Some(1)
+            })
+            .return_once(|_| {
+                Ok(SearchResponse {
+                    num_hits: 1,
+                    hits: vec![Hit {
+                        json: SPAN_JSON.to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })
+            });
+        let tempo_api_handler =
+            tempo_api_handlers(Arc::new(mock_search_service)).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/otel-traces-v0_7/tempo/api/traces/abcd?start=1")
+            .header("accept", "application/protobuf")
+            .reply(&tempo_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/protobuf"
+        );
+        let trace = TempoTrace::decode(resp.body().as_ref()).unwrap();
+        assert_eq!(trace.batches.len(), 1);
+        assert_eq!(trace.batches[0].scope_spans[0].spans[0].name, "GET /");
+    }
+
+    #[tokio::test]
+    async fn test_tempo_trace_not_found() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .return_once(|_| Ok(SearchResponse::default()));
+        let tempo_api_handler =
+            tempo_api_handlers(Arc::new(mock_search_service)).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/otel-traces-v0_7/tempo/api/traces/abcd")
+            .reply(&tempo_api_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_tempo_search() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|req| req.aggregation_request.is_some())
+            .return_once(|req| {
+                assert!(req.query_ast.contains("span_attributes.http.status_code"));
+                assert!(req.query_ast.contains("span_duration_millis"));
+                assert_eq!(req.start_timestamp, Some(1));
+                assert_eq!(req.end_timestamp, Some(2));
+                let aggregation = serde_json::json!([{
+                    "trace_id": "0000000000000000000000000000abcd",
+                    "span_timestamp": 1000000000,
+                }]);
+                Ok(SearchResponse {
+                    num_hits: 1,
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+        mock_search_service
+            .expect_root_search()
+            .withf(|req| req.aggregation_request.is_none())
+            .return_once(|_| {
+                Ok(SearchResponse {
+                    num_hits: 1,
+                    hits: vec![Hit {
+                        json: SPAN_JSON.to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })
+            });
+        let tempo_api_handler =
+            tempo_api_handlers(Arc::new(mock_search_service)).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path(
+                "/otel-traces-v0_7/tempo/api/search?q=%7B%20span.http.status_code%20%3E%3D%20500%\
+                 20%7D&minDuration=100ms&limit=10&start=1&end=2",
+            )
+            .reply(&tempo_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let search_response: TempoSearchResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(search_response.traces.len(), 1);
+        let trace = &search_response.traces[0];
+        assert_eq!(trace.trace_id, "0000000000000000000000000000abcd");
+        assert_eq!(trace.root_service_name, "api");
+        assert_eq!(trace.root_trace_name, "GET /");
+        assert_eq!(trace.duration_ms, 250);
+    }
+
+    #[tokio::test]
+    async fn test_tempo_search_invalid_traceql() {
+        let mock_search_service = MockSearchService::new();
+        let tempo_api_handler =
+            tempo_api_handlers(Arc::new(mock_search_service)).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/otel-traces-v0_7/tempo/api/search?q=%7B%20foo%20%7D")
+            .reply(&tempo_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let error_body: HashMap<String, String> = serde_json::from_slice(resp.body()).unwrap();
+        assert!(error_body.contains_key("message"));
+    }
+
+    #[tokio::test]
+    async fn test_tempo_search_tags() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_list_terms()
+            .returning(|req| {
+                let terms = if req.field == "span_attributes" {
+                    vec![
+                        json_term_for_test("http.method", "GET"),
+                        json_term_for_test("http.method", "POST"),
+                    ]
+                } else {
+                    vec![json_term_for_test("host.name", "localhost")]
+                };
+                Ok(ListTermsResponse {
+                    num_hits: terms.len() as u64,
+                    terms,
+                    elapsed_time_micros: 0,
+                    errors: Vec::new(),
+                })
+            });
+        let tempo_api_handler =
+            tempo_api_handlers(Arc::new(mock_search_service)).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/otel-traces-v0_7/tempo/api/search/tags")
+            .reply(&tempo_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let response_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            response_json,
+            serde_json::json!({"tagNames": ["host.name", "http.method", "service.name"]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tempo_search_tag_values() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_list_terms()
+            .times(2)
+            .returning(|req| {
+                let mut expected_start_key = tag_name_to_json_path("http.method");
+                expected_start_key.push(JSON_END_OF_PATH);
+                assert_eq!(req.start_key.as_ref(), Some(&expected_start_key));
+
+                let terms = if req.field == "span_attributes" {
+                    vec![
+                        json_term_for_test("http.method", "GET"),
+                        json_term_for_test("http.method", "POST"),
+                    ]
+                } else {
+                    Vec::new()
+                };
+                Ok(ListTermsResponse {
+                    num_hits: terms.len() as u64,
+                    terms,
+                    elapsed_time_micros: 0,
+                    errors: Vec::new(),
+                })
+            });
+        let tempo_api_handler =
+            tempo_api_handlers(Arc::new(mock_search_service)).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/otel-traces-v0_7/tempo/api/search/tag/http.method/values")
+            .reply(&tempo_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let response_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            response_json,
+            serde_json::json!({"tagValues": ["GET", "POST"]})
+        );
+    }
+}
@@ -0,0 +1,964 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::ops::Bound;
+
+use anyhow::{bail, Context};
+use quickwit_query::query_ast::{BoolQuery, FieldPresenceQuery, QueryAst, RangeQuery, TermQuery};
+use quickwit_query::JsonLiteral;
+
+/// Maximum nesting depth of parenthesized expressions, which bounds the recursion of the parser.
+const MAX_NESTING_DEPTH: usize = 32;
+
+/// Parses a [TraceQL](https://grafana.com/docs/tempo/latest/traceql/) query and compiles it into
+/// a query matching spans.
+///
+/// Only a subset of the language is supported: a single spanset filter made of comparisons
+/// between an attribute and a static value, combined with `&&`, `||`, and parentheses. For
+/// instance: `{ span.http.status_code >= 500 && resource.service.name = "api" }`.
+pub(super) fn parse_traceql(query: &str) -> anyhow::Result<QueryAst> {
+    let tokens = tokenize(query)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+    };
+    parser.expect(Token::LeftBrace)?;
+
+    let query_ast = if parser.peek_token() == Some(&Token::RightBrace) {
+        QueryAst::MatchAll
+    } else {
+        parser.parse_or()?
+    };
+    parser.expect(Token::RightBrace)?;
+
+    if let Some(token) = parser.next_token() {
+        bail!(
+            "unsupported TraceQL query: expected a single spanset filter, found `{token}` after \
+             the closing brace"
+        );
+    }
+    Ok(query_ast)
+}
+
+/// Compiles the `tags` parameter of the Tempo search API, a logfmt-encoded list of `key=value`
+/// pairs such as `service.name=api http.method="GET"`, into a query matching spans.
+pub(super) fn parse_tags(tags: &str) -> anyhow::Result<QueryAst> {
+    let mut bool_query = BoolQuery::default();
+    let mut chars = tags.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        if chars.peek().is_none() {
+            break;
+        }
+        let key: String =
+            std::iter::from_fn(|| chars.next_if(|c| *c != '=' && !c.is_whitespace())).collect();
+        if chars.next() != Some('=') {
+            bail!("failed to parse tags `{tags}`: expected `=` after key `{key}`");
+        }
+        let value = if chars.next_if_eq(&'"').is_some() {
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => value.extend(chars.next()),
+                    Some(c) => value.push(c),
+                    None => bail!("failed to parse tags `{tags}`: unterminated quoted value"),
+                }
+            }
+            value
+        } else {
+            std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())).collect()
+        };
+        let attribute = match key.as_str() {
+            "service.name" => Attribute::ServiceName,
+            "name" => Attribute::Name,
+            "status.code" => Attribute::Status,
+            _ => Attribute::Unscoped(key),
+        };
+        let static_value = match attribute {
+            Attribute::Status => Static::Keyword(value),
+            _ => Static::Str(value),
+        };
+        bool_query.must.push(compile_comparison(
+            attribute,
+            ComparisonOp::Eq,
+            static_value,
+        )?);
+    }
+    if bool_query.must.is_empty() {
+        return Ok(QueryAst::MatchAll);
+    }
+    Ok(bool_query.into())
+}
+
+/// Compiles the `minDuration` parameter of the Tempo search API, such as `100ms`, into a query
+/// matching spans.
+pub(super) fn parse_min_duration(min_duration: &str) -> anyhow::Result<QueryAst> {
+    let nanos = parse_duration(min_duration)?;
+    compile_comparison(
+        Attribute::Duration,
+        ComparisonOp::Gte,
+        Static::Duration(nanos),
+    )
+}
+
+/// Compiles the `maxDuration` parameter of the Tempo search API, such as `1.5s`, into a query
+/// matching spans.
+pub(super) fn parse_max_duration(max_duration: &str) -> anyhow::Result<QueryAst> {
+    let nanos = parse_duration(max_duration)?;
+    compile_comparison(
+        Attribute::Duration,
+        ComparisonOp::Lte,
+        Static::Duration(nanos),
+    )
+}
+
+fn parse_duration(duration: &str) -> anyhow::Result<u64> {
+    let unit_start = duration
+        .find(|c: char| c.is_alphabetic())
+        .unwrap_or(duration.len());
+    let (number_str, unit) = duration.split_at(unit_start);
+
+    match parse_number(number_str, unit)? {
+        Token::Duration(nanos) => Ok(nanos),
+        _ => bail!("invalid duration `{duration}`, expected a duration such as `100ms`"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ComparisonOp {
+    Eq,
+    NotEq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Regex,
+    NotRegex,
+}
+
+impl fmt::Display for ComparisonOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op_str = match self {
+            ComparisonOp::Eq => "=",
+            ComparisonOp::NotEq => "!=",
+            ComparisonOp::Gt => ">",
+            ComparisonOp::Gte => ">=",
+            ComparisonOp::Lt => "<",
+            ComparisonOp::Lte => "<=",
+            ComparisonOp::Regex => "=~",
+            ComparisonOp::NotRegex => "!~",
+        };
+        f.write_str(op_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LeftBrace,
+    RightBrace,
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+    Op(ComparisonOp),
+    Identifier(String),
+    Str(String),
+    Number(serde_json::Number),
+    // Duration in nanoseconds.
+    Duration(u64),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::LeftBrace => f.write_str("{"),
+            Token::RightBrace => f.write_str("}"),
+            Token::LeftParen => f.write_str("("),
+            Token::RightParen => f.write_str(")"),
+            Token::And => f.write_str("&&"),
+            Token::Or => f.write_str("||"),
+            Token::Op(op) => write!(f, "{op}"),
+            Token::Identifier(identifier) => f.write_str(identifier),
+            Token::Str(value) => write!(f, "{value:?}"),
+            Token::Number(number) => write!(f, "{number}"),
+            Token::Duration(nanos) => write!(f, "{nanos}ns"),
+        }
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | '_' | ':' | '-' | '/')
+}
+
+fn tokenize(query: &str) -> anyhow::Result<Vec<Token>> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        let next_c_opt = chars.get(pos + 1).copied();
+
+        let (token, num_chars) = match (c, next_c_opt) {
+            (c, _) if c.is_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            ('{', _) => (Token::LeftBrace, 1),
+            ('}', _) => (Token::RightBrace, 1),
+            ('(', _) => (Token::LeftParen, 1),
+            (')', _) => (Token::RightParen, 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('~')) => (Token::Op(ComparisonOp::Regex), 2),
+            ('!', Some('~')) => (Token::Op(ComparisonOp::NotRegex), 2),
+            ('!', Some('=')) => (Token::Op(ComparisonOp::NotEq), 2),
+            ('>', Some('=')) => (Token::Op(ComparisonOp::Gte), 2),
+            ('<', Some('=')) => (Token::Op(ComparisonOp::Lte), 2),
+            ('=', _) => (Token::Op(ComparisonOp::Eq), 1),
+            ('>', _) => (Token::Op(ComparisonOp::Gt), 1),
+            ('<', _) => (Token::Op(ComparisonOp::Lt), 1),
+            ('"', _) => {
+                let mut value = String::new();
+                let mut end = pos + 1;
+                loop {
+                    match chars.get(end) {
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escaped_c = chars
+                                .get(end + 1)
+                                .with_context(|| format!("unterminated string in `{query}`"))?;
+                            value.push(match escaped_c {
+                                'n' => '\n',
+                                't' => '\t',
+                                c => *c,
+                            });
+                            end += 2;
+                        }
+                        Some(c) => {
+                            value.push(*c);
+                            end += 1;
+                        }
+                        None => bail!("unterminated string in `{query}`"),
+                    }
+                }
+                (Token::Str(value), end + 1 - pos)
+            }
+            (c, _)
+                if c.is_ascii_digit()
+                    || (c == '-' && next_c_opt.is_some_and(|c| c.is_ascii_digit())) =>
+            {
+                let mut end = pos + 1;
+                while chars
+                    .get(end)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    end += 1;
+                }
+                let number_str: String = chars[pos..end].iter().collect();
+                let unit_start = end;
+                while chars.get(end).is_some_and(|c| c.is_alphabetic()) {
+                    end += 1;
+                }
+                let unit: String = chars[unit_start..end].iter().collect();
+                let token = parse_number(&number_str, &unit)?;
+                (token, end - pos)
+            }
+            (c, _) if is_identifier_char(c) => {
+                let mut end = pos + 1;
+                while chars.get(end).copied().is_some_and(is_identifier_char) {
+                    end += 1;
+                }
+                let identifier: String = chars[pos..end].iter().collect();
+                (Token::Identifier(identifier), end - pos)
+            }
+            _ => bail!("unexpected character `{c}` at position {pos} in `{query}`"),
+        };
+        tokens.push(token);
+        pos += num_chars;
+    }
+    Ok(tokens)
+}
+
+fn parse_number(number_str: &str, unit: &str) -> anyhow::Result<Token> {
+    let nanos_per_unit: f64 = match unit {
+        "" => {
+            let number = if let Ok(integer) = number_str.parse::<i64>() {
+                integer.into()
+            } else {
+                number_str
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .with_context(|| format!("invalid number `{number_str}`"))?
+            };
+            return Ok(Token::Number(number));
+        }
+        "ns" => 1.0,
+        "us" | "µs" => 1_000.0,
+        "ms" => 1_000_000.0,
+        "s" => 1_000_000_000.0,
+        "m" => 60_000_000_000.0,
+        "h" => 3_600_000_000_000.0,
+        _ => bail!("invalid duration unit `{unit}` in `{number_str}{unit}`"),
+    };
+    let value: f64 = number_str
+        .parse()
+        .with_context(|| format!("invalid duration `{number_str}{unit}`"))?;
+    if value < 0.0 {
+        bail!("invalid negative duration `{number_str}{unit}`");
+    }
+    Ok(Token::Duration((value * nanos_per_unit) as u64))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek_token(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token_opt = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token_opt
+    }
+
+    fn expect(&mut self, expected: Token) -> anyhow::Result<()> {
+        match self.next_token() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => bail!("expected `{expected}`, found `{token}`"),
+            None => bail!("expected `{expected}`, found end of query"),
+        }
+    }
+
+    fn parse_or(&mut self) -> anyhow::Result<QueryAst> {
+        let mut sub_queries = vec![self.parse_and()?];
+
+        while self.peek_token() == Some(&Token::Or) {
+            self.pos += 1;
+            sub_queries.push(self.parse_and()?);
+        }
+        if sub_queries.len() == 1 {
+            return Ok(sub_queries.pop().unwrap());
+        }
+        let bool_query = BoolQuery {
+            should: sub_queries,
+            ..Default::default()
+        };
+        Ok(bool_query.into())
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<QueryAst> {
+        let mut sub_queries = vec![self.parse_primary()?];
+
+        while self.peek_token() == Some(&Token::And) {
+            self.pos += 1;
+            sub_queries.push(self.parse_primary()?);
+        }
+        if sub_queries.len() == 1 {
+            return Ok(sub_queries.pop().unwrap());
+        }
+        let bool_query = BoolQuery {
+            must: sub_queries,
+            ..Default::default()
+        };
+        Ok(bool_query.into())
+    }
+
+    fn parse_primary(&mut self) -> anyhow::Result<QueryAst> {
+        match self.next_token() {
+            Some(Token::LeftParen) => {
+                if self.depth == MAX_NESTING_DEPTH {
+                    bail!(
+                        "unsupported TraceQL query: expressions cannot be nested more than \
+                         {MAX_NESTING_DEPTH} levels deep"
+                    );
+                }
+                self.depth += 1;
+                let query_ast = self.parse_or()?;
+                self.expect(Token::RightParen)?;
+                self.depth -= 1;
+                Ok(query_ast)
+            }
+            Some(Token::Identifier(name)) => {
+                let attribute = Attribute::parse(&name)?;
+                let op = match self.next_token() {
+                    Some(Token::Op(op)) => op,
+                    Some(token) => bail!("expected a comparison operator, found `{token}`"),
+                    None => bail!("expected a comparison operator, found end of query"),
+                };
+                let value = match self.next_token() {
+                    Some(Token::Str(value)) => Static::Str(value),
+                    Some(Token::Number(number)) => Static::Number(number),
+                    Some(Token::Duration(nanos)) => Static::Duration(nanos),
+                    Some(Token::Identifier(identifier)) => match identifier.as_str() {
+                        "true" => Static::Bool(true),
+                        "false" => Static::Bool(false),
+                        "nil" => Static::Nil,
+                        _ => Static::Keyword(identifier),
+                    },
+                    Some(token) => bail!("expected a value, found `{token}`"),
+                    None => bail!("expected a value, found end of query"),
+                };
+                compile_comparison(attribute, op, value)
+            }
+            Some(token) => bail!("expected an attribute or `(`, found `{token}`"),
+            None => bail!("expected an attribute or `(`, found end of query"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Attribute {
+    Name,
+    Status,
+    Kind,
+    Duration,
+    ServiceName,
+    Span(String),
+    Resource(String),
+    Unscoped(String),
+}
+
+impl Attribute {
+    fn parse(name: &str) -> anyhow::Result<Self> {
+        let attribute = match name {
+            "name" | "span:name" => Attribute::Name,
+            "status" | "span:status" => Attribute::Status,
+            "kind" | "span:kind" => Attribute::Kind,
+            "duration" | "span:duration" => Attribute::Duration,
+            "resource.service.name" => Attribute::ServiceName,
+            _ => {
+                if let Some(key) = name.strip_prefix("span.") {
+                    Attribute::Span(key.to_string())
+                } else if let Some(key) = name.strip_prefix("resource.") {
+                    Attribute::Resource(key.to_string())
+                } else if let Some(key) = name.strip_prefix('.') {
+                    Attribute::Unscoped(key.to_string())
+                } else {
+                    bail!("unsupported TraceQL attribute `{name}`");
+                }
+            }
+        };
+        Ok(attribute)
+    }
+
+    /// Returns the fields of the OpenTelemetry traces index holding the attribute.
+    fn field_names(&self) -> Vec<String> {
+        match self {
+            Attribute::Name => vec!["span_name".to_string()],
+            Attribute::Status => vec!["span_status.code".to_string()],
+            Attribute::Kind => vec!["span_kind".to_string()],
+            Attribute::Duration => vec!["span_duration_millis".to_string()],
+            Attribute::ServiceName => vec!["service_name".to_string()],
+            Attribute::Span(key) => vec![format!("span_attributes.{key}")],
+            Attribute::Resource(key) => vec![format!("resource_attributes.{key}")],
+            Attribute::Unscoped(key) if key == "service.name" => vec![
+                "service_name".to_string(),
+                "span_attributes.service.name".to_string(),
+            ],
+            Attribute::Unscoped(key) => vec![
+                format!("span_attributes.{key}"),
+                format!("resource_attributes.{key}"),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Static {
+    Str(String),
+    Number(serde_json::Number),
+    Bool(bool),
+    // Duration in nanoseconds.
+    Duration(u64),
+    Nil,
+    // Status and kind values, such as `error` or `server`.
+    Keyword(String),
+}
+
+impl Static {
+    fn into_term_value(self) -> anyhow::Result<String> {
+        match self {
+            Static::Str(value) => Ok(value),
+            Static::Number(number) => Ok(number.to_string()),
+            Static::Bool(value) => Ok(value.to_string()),
+            Static::Duration(_) => bail!("durations can only be compared to `duration`"),
+            Static::Nil => bail!("`nil` can only be compared with `=` or `!=`"),
+            Static::Keyword(keyword) => {
+                bail!("unexpected value `{keyword}`, string values must be double-quoted")
+            }
+        }
+    }
+
+    fn into_json_literal(self) -> anyhow::Result<JsonLiteral> {
+        match self {
+            Static::Str(value) => Ok(JsonLiteral::String(value)),
+            Static::Number(number) => Ok(JsonLiteral::Number(number)),
+            static_value => bail!(
+                "only numbers and strings can be compared with `<`, `<=`, `>`, and `>=`, found \
+                 `{static_value:?}`"
+            ),
+        }
+    }
+}
+
+fn term_query(field: String, value: String) -> QueryAst {
+    TermQuery { field, value }.into()
+}
+
+fn exists_query(field: String) -> QueryAst {
+    FieldPresenceQuery { field }.into()
+}
+
+fn negate(query_ast: QueryAst) -> QueryAst {
+    BoolQuery {
+        must_not: vec![query_ast],
+        ..Default::default()
+    }
+    .into()
+}
+
+fn range_query(field: String, op: ComparisonOp, value: JsonLiteral) -> QueryAst {
+    let (lower_bound, upper_bound) = match op {
+        ComparisonOp::Gt => (Bound::Excluded(value), Bound::Unbounded),
+        ComparisonOp::Gte => (Bound::Included(value), Bound::Unbounded),
+        ComparisonOp::Lt => (Bound::Unbounded, Bound::Excluded(value)),
+        ComparisonOp::Lte => (Bound::Unbounded, Bound::Included(value)),
+        _ => unreachable!("`{op}` is not a range operator"),
+    };
+    RangeQuery {
+        field,
+        lower_bound,
+        upper_bound,
+    }
+    .into()
+}
+
+/// Splits a regular expression made of literal alternatives, such as `api|web`, into its
+/// alternatives. Other regular expressions are not supported.
+fn split_regex_alternatives(regex: &str) -> anyhow::Result<Vec<String>> {
+    if regex.contains(|c: char| ".^$*+?()[]{}\\".contains(c)) {
+        bail!(
+            "unsupported regular expression `{regex}`: only alternatives of literal values such \
+             as `a|b` are supported"
+        );
+    }
+    Ok(regex.split('|').map(str::to_string).collect())
+}
+
+fn compile_comparison(
+    attribute: Attribute,
+    op: ComparisonOp,
+    value: Static,
+) -> anyhow::Result<QueryAst> {
+    match attribute {
+        Attribute::Status => {
+            let status_query = match &value {
+                Static::Keyword(status) if status == "error" || status == "ok" => {
+                    term_query("span_status.code".to_string(), status.clone())
+                }
+                // Unset statuses are not indexed.
+                Static::Keyword(status) if status == "unset" => {
+                    negate(exists_query("span_status.code".to_string()))
+                }
+                _ => bail!("invalid status `{value:?}`, expected `error`, `ok`, or `unset`"),
+            };
+            match op {
+                ComparisonOp::Eq => Ok(status_query),
+                ComparisonOp::NotEq => Ok(negate(status_query)),
+                _ => bail!("`status` can only be compared with `=` or `!=`"),
+            }
+        }
+        Attribute::Kind => {
+            let span_kind = match &value {
+                Static::Keyword(kind) => match kind.as_str() {
+                    "unspecified" => 0,
+                    "internal" => 1,
+                    "server" => 2,
+                    "client" => 3,
+                    "producer" => 4,
+                    "consumer" => 5,
+                    _ => bail!("invalid span kind `{kind}`"),
+                },
+                _ => bail!("invalid span kind `{value:?}`"),
+            };
+            let kind_query = term_query("span_kind".to_string(), span_kind.to_string());
+            match op {
+                ComparisonOp::Eq => Ok(kind_query),
+                ComparisonOp::NotEq => Ok(negate(kind_query)),
+                _ => bail!("`kind` can only be compared with `=` or `!=`"),
+            }
+        }
+        Attribute::Duration => {
+            let Static::Duration(nanos) = value else {
+                bail!("`duration` must be compared to a duration such as `100ms`");
+            };
+            // Span durations are stored in a fast field with a millisecond precision, which is not
+            // indexed: equality is expressed as a range.
+            let millis = nanos / 1_000_000;
+            let field = "span_duration_millis".to_string();
+            let eq_query = || -> QueryAst {
+                RangeQuery {
+                    field: field.clone(),
+                    lower_bound: Bound::Included(millis.into()),
+                    upper_bound: Bound::Included(millis.into()),
+                }
+                .into()
+            };
+            match op {
+                ComparisonOp::Eq => Ok(eq_query()),
+                ComparisonOp::NotEq => Ok(negate(eq_query())),
+                ComparisonOp::Gt | ComparisonOp::Gte | ComparisonOp::Lt | ComparisonOp::Lte => {
+                    Ok(range_query(field, op, millis.into()))
+                }
+                ComparisonOp::Regex | ComparisonOp::NotRegex => {
+                    bail!("`duration` cannot be compared with `{op}`")
+                }
+            }
+        }
+        _ => {
+            let mut field_queries = Vec::new();
+
+            for field in attribute.field_names() {
+                let field_query = match (op, value.clone()) {
+                    (ComparisonOp::Eq, Static::Nil) => negate(exists_query(field)),
+                    (ComparisonOp::NotEq, Static::Nil) => exists_query(field),
+                    (ComparisonOp::Eq, value) => term_query(field, value.into_term_value()?),
+                    (ComparisonOp::NotEq, value) => BoolQuery {
+                        must: vec![exists_query(field.clone())],
+                        must_not: vec![term_query(field, value.into_term_value()?)],
+                        ..Default::default()
+                    }
+                    .into(),
+                    (ComparisonOp::Regex | ComparisonOp::NotRegex, Static::Str(regex)) => {
+                        let alternative_queries: Vec<QueryAst> = split_regex_alternatives(&regex)?
+                            .into_iter()
+                            .map(|alternative| term_query(field.clone(), alternative))
+                            .collect();
+                        if op == ComparisonOp::Regex {
+                            BoolQuery {
+                                should: alternative_queries,
+                                ..Default::default()
+                            }
+                            .into()
+                        } else {
+                            BoolQuery {
+                                must: vec![exists_query(field)],
+                                must_not: alternative_queries,
+                                ..Default::default()
+                            }
+                            .into()
+                        }
+                    }
+                    (ComparisonOp::Regex | ComparisonOp::NotRegex, _) => {
+                        bail!("regular expressions must be double-quoted strings")
+                    }
+                    (op, value) => range_query(field, op, value.into_json_literal()?),
+                };
+                field_queries.push(field_query);
+            }
+            if field_queries.len() == 1 {
+                return Ok(field_queries.pop().unwrap());
+            }
+            // For negative comparisons on unscoped attributes, the attribute must not match on
+            // any of the fields.
+            let bool_query = if matches!(value, Static::Nil) && op == ComparisonOp::Eq {
+                BoolQuery {
+                    must: field_queries,
+                    ..Default::default()
+                }
+            } else {
+                BoolQuery {
+                    should: field_queries,
+                    ..Default::default()
+                }
+            };
+            Ok(bool_query.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize(r#"{ span.http.status_code >= 500 && name = "GET \"/\"" }"#).unwrap();
+        assert_eq!(
+            tokens,
+            [
+                Token::LeftBrace,
+                Token::Identifier("span.http.status_code".to_string()),
+                Token::Op(ComparisonOp::Gte),
+                Token::Number(500.into()),
+                Token::And,
+                Token::Identifier("name".to_string()),
+                Token::Op(ComparisonOp::Eq),
+                Token::Str("GET \"/\"".to_string()),
+                Token::RightBrace,
+            ]
+        );
+        let tokens = tokenize("{duration>1.5s||.ratio<-0.5}").unwrap();
+        assert_eq!(
+            tokens,
+            [
+                Token::LeftBrace,
+                Token::Identifier("duration".to_string()),
+                Token::Op(ComparisonOp::Gt),
+                Token::Duration(1_500_000_000),
+                Token::Or,
+                Token::Identifier(".ratio".to_string()),
+                Token::Op(ComparisonOp::Lt),
+                Token::Number(serde_json::Number::from_f64(-0.5).unwrap()),
+                Token::RightBrace,
+            ]
+        );
+        tokenize(r#"{ name = "unterminated }"#).unwrap_err();
+        tokenize("{ duration > 10years }").unwrap_err();
+        tokenize("{ name = 'single-quoted' }").unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_traceql() {
+        assert_eq!(parse_traceql("{}").unwrap(), QueryAst::MatchAll);
+        assert_eq!(
+            parse_traceql(r#"{ resource.service.name = "api" }"#).unwrap(),
+            term_query("service_name".to_string(), "api".to_string())
+        );
+        assert_eq!(
+            parse_traceql(r#"{ span.http.status_code >= 500 && resource.service.name = "api" }"#)
+                .unwrap(),
+            BoolQuery {
+                must: vec![
+                    RangeQuery {
+                        field: "span_attributes.http.status_code".to_string(),
+                        lower_bound: Bound::Included(JsonLiteral::Number(500.into())),
+                        upper_bound: Bound::Unbounded,
+                    }
+                    .into(),
+                    term_query("service_name".to_string(), "api".to_string()),
+                ],
+                ..Default::default()
+            }
+            .into()
+        );
+        assert_eq!(
+            parse_traceql(r#"{ (name = "a" || name = "b") && status = error }"#).unwrap(),
+            BoolQuery {
+                must: vec![
+                    BoolQuery {
+                        should: vec![
+                            term_query("span_name".to_string(), "a".to_string()),
+                            term_query("span_name".to_string(), "b".to_string()),
+                        ],
+                        ..Default::default()
+                    }
+                    .into(),
+                    term_query("span_status.code".to_string(), "error".to_string()),
+                ],
+                ..Default::default()
+            }
+            .into()
+        );
+        assert_eq!(
+            parse_traceql("{ kind = server && duration > 100ms }").unwrap(),
+            BoolQuery {
+                must: vec![
+                    term_query("span_kind".to_string(), "2".to_string()),
+                    RangeQuery {
+                        field: "span_duration_millis".to_string(),
+                        lower_bound: Bound::Excluded(JsonLiteral::Number(100.into())),
+                        upper_bound: Bound::Unbounded,
+                    }
+                    .into(),
+                ],
+                ..Default::default()
+            }
+            .into()
+        );
+        assert_eq!(
+            parse_traceql(r#"{ .region != "eu" }"#).unwrap(),
+            BoolQuery {
+                should: vec![
+                    BoolQuery {
+                        must: vec![exists_query("span_attributes.region".to_string())],
+                        must_not: vec![term_query(
+                            "span_attributes.region".to_string(),
+                            "eu".to_string()
+                        )],
+                        ..Default::default()
+                    }
+                    .into(),
+                    BoolQuery {
+                        must: vec![exists_query("resource_attributes.region".to_string())],
+                        must_not: vec![term_query(
+                            "resource_attributes.region".to_string(),
+                            "eu".to_string()
+                        )],
+                        ..Default::default()
+                    }
+                    .into(),
+                ],
+                ..Default::default()
+            }
+            .into()
+        );
+        assert_eq!(
+            parse_traceql(r#"{ resource.service.name =~ "api|web" }"#).unwrap(),
+            BoolQuery {
+                should: vec![
+                    term_query("service_name".to_string(), "api".to_string()),
+                    term_query("service_name".to_string(), "web".to_string()),
+                ],
+                ..Default::default()
+            }
+            .into()
+        );
+        assert_eq!(
+            parse_traceql("{ span.user_id = nil }").unwrap(),
+            negate(exists_query("span_attributes.user_id".to_string()))
+        );
+        assert_eq!(
+            parse_traceql("{ status != unset }").unwrap(),
+            negate(negate(exists_query("span_status.code".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_parse_traceql_duration_equality() {
+        let eq_query: QueryAst = RangeQuery {
+            field: "span_duration_millis".to_string(),
+            lower_bound: Bound::Included(JsonLiteral::Number(100.into())),
+            upper_bound: Bound::Included(JsonLiteral::Number(100.into())),
+        }
+        .into();
+        assert_eq!(
+            parse_traceql("{ duration = 100ms }").unwrap(),
+            eq_query.clone()
+        );
+        assert_eq!(
+            parse_traceql("{ duration != 100ms }").unwrap(),
+            negate(eq_query)
+        );
+    }
+
+    #[test]
+    fn test_parse_traceql_nesting_depth() {
+        let query = format!("{{ {}name = \"a\"{} }}", "(".repeat(32), ")".repeat(32));
+        assert_eq!(
+            parse_traceql(&query).unwrap(),
+            term_query("span_name".to_string(), "a".to_string())
+        );
+        let query = format!("{{ {}name = \"a\"{} }}", "(".repeat(33), ")".repeat(33));
+        parse_traceql(&query).unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_traceql_errors() {
+        for query in [
+            "",
+            "{",
+            r#"name = "a""#,
+            r#"{ name = "a" } && { name = "b" }"#,
+            r#"{ name = "a" } | count() > 1"#,
+            r#"{ name }"#,
+            r#"{ foo = "a" }"#,
+            "{ span.foo = bar }",
+            "{ status = warning }",
+            "{ kind > server }",
+            "{ duration > 100 }",
+            r#"{ span.foo =~ "a.*" }"#,
+            "{ span.foo > true }",
+        ] {
+            assert!(
+                parse_traceql(query).is_err(),
+                "query `{query}` should fail to parse"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_min_max_duration() {
+        assert_eq!(
+            parse_min_duration("1.5s").unwrap(),
+            RangeQuery {
+                field: "span_duration_millis".to_string(),
+                lower_bound: Bound::Included(JsonLiteral::Number(1_500.into())),
+                upper_bound: Bound::Unbounded,
+            }
+            .into()
+        );
+        assert_eq!(
+            parse_max_duration("100ms").unwrap(),
+            RangeQuery {
+                field: "span_duration_millis".to_string(),
+                lower_bound: Bound::Unbounded,
+                upper_bound: Bound::Included(JsonLiteral::Number(100.into())),
+            }
+            .into()
+        );
+        parse_min_duration("100").unwrap_err();
+        parse_min_duration("-1s").unwrap_err();
+        parse_max_duration("1d").unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags("").unwrap(), QueryAst::MatchAll);
+        assert_eq!(
+            parse_tags(r#"service.name=api http.method="GET /" status.code=error"#).unwrap(),
+            BoolQuery {
+                must: vec![
+                    term_query("service_name".to_string(), "api".to_string()),
+                    BoolQuery {
+                        should: vec![
+                            term_query(
+                                "span_attributes.http.method".to_string(),
+                                "GET /".to_string()
+                            ),
+                            term_query(
+                                "resource_attributes.http.method".to_string(),
+                                "GET /".to_string()
+                            ),
+                        ],
+                        ..Default::default()
+                    }
+                    .into(),
+                    term_query("span_status.code".to_string(), "error".to_string()),
+                ],
+                ..Default::default()
+            }
+            .into()
+        );
+        parse_tags("service.name").unwrap_err();
+        parse_tags(r#"service.name="api"#).unwrap_err();
+    }
+}
@@ -40,7 +40,7 @@ pub(crate) use debouncer::DebouncedStorage;
 
 pub use self::metrics::STORAGE_METRICS;
 pub use self::payload::PutPayload;
-pub use self::storage::Storage;
+pub use self::storage::{SendableAsync, Storage};
 
 mod bundle_storage;
 mod error;
@@ -56,6 +56,7 @@ mod split;
 mod split_cache;
 mod storage_factory;
 mod storage_resolver;
+mod storage_with_download_counter;
 mod versioned_component;
 
 use quickwit_common::uri::Uri;
@@ -80,13 +81,14 @@ pub use self::opendal_storage::new_emulated_google_cloud_storage;
 #[cfg(feature = "gcs")]
 pub use self::opendal_storage::GoogleCloudStorageFactory;
 pub use self::ram_storage::{RamStorage, RamStorageBuilder};
-pub use self::split::{SplitPayload, SplitPayloadBuilder};
+pub use self::split::{FilePayload, SplitPayload, SplitPayloadBuilder};
 #[cfg(any(test, feature = "testsuite"))]
 pub use self::storage::MockStorage;
 #[cfg(any(test, feature = "testsuite"))]
 pub use self::storage_factory::MockStorageFactory;
 pub use self::storage_factory::{StorageFactory, UnsupportedStorage};
 pub use self::storage_resolver::StorageResolver;
+pub use self::storage_with_download_counter::wrap_storage_with_download_counter;
 #[cfg(feature = "integration-testsuite")]
 pub use self::test_suite::{
     storage_test_multi_part_upload, storage_test_single_part_upload, storage_test_suite,
@@ -75,12 +75,21 @@ impl PutPayload for SplitPayload {
     }
 }
 
+/// Payload streaming the content of a local file.
 #[derive(Clone)]
-struct FilePayload {
+pub struct FilePayload {
     len: u64,
     path: PathBuf,
 }
 
+impl FilePayload {
+    /// Creates a payload for the local file located at `path`.
+    pub async fn from_path(path: PathBuf) -> io::Result<Self> {
+        let len = tokio::fs::metadata(&path).await?.len();
+        Ok(Self { len, path })
+    }
+}
+
 #[async_trait]
 impl PutPayload for FilePayload {
     fn len(&self) -> u64 {